@@ -0,0 +1,75 @@
+//! The bonus example: how likely is each cause given that a bonus was paid?
+//!
+//! Run with `cargo run --example bonus`.
+use bayesian_network::*;
+use std::collections::HashMap;
+
+fn main() {
+    let mut network = BayesianNetwork::new(vec![true, false]);
+
+    network.add_node("業績", NodeType::Root(cpt! { true: 0.01, false: 0.99 }));
+
+    network.add_node("競馬", NodeType::Root(cpt! { true: 0.1, false: 0.9 }));
+
+    network.add_node("ごきげん", NodeType::Inner);
+
+    network.add_node("ボーナス", NodeType::Leaf);
+
+    network.add_node("ごちそう", NodeType::Leaf);
+
+    network.add_dependency(
+        vec!["業績", "競馬"],
+        "ごきげん",
+        cpt! {
+            [true, true] => { true: 0.99, false: 0.01 },
+            [false, true] => { true: 0.6, false: 0.4 },
+            [true, false] => { true: 0.9, false: 0.1 },
+            [false, false] => { true: 0.01, false: 0.99 },
+        },
+    );
+
+    network.add_dependency(
+        vec!["ごきげん"],
+        "ボーナス",
+        cpt! {
+            [true] => { true: 0.3, false: 0.7 },
+            [false] => { true: 0.01, false: 0.99 },
+        },
+    );
+
+    network.add_dependency(
+        vec!["ごきげん"],
+        "ごちそう",
+        cpt! {
+            [true] => { true: 0.9, false: 0.1 },
+            [false] => { true: 0.01, false: 0.99 },
+        },
+    );
+
+    let mut evidence = HashMap::new();
+    evidence.insert("ボーナス", true);
+
+    let inferred_probabilities = network.infer(&evidence);
+    println!(
+        "業績が上がった確率: {}",
+        network.get_inferred_probability(&inferred_probabilities, "業績", true)
+    );
+    println!(
+        "競馬で勝った確率: {}",
+        network.get_inferred_probability(&inferred_probabilities, "競馬", true)
+    );
+    println!(
+        "ごきげんな確率: {}",
+        network.get_inferred_probability(&inferred_probabilities, "ごきげん", true)
+    );
+    println!(
+        "ボーナスが出る確率: {}",
+        network.get_inferred_probability(&inferred_probabilities, "ボーナス", true)
+    );
+    println!(
+        "ごちそうが出る確率: {}",
+        network.get_inferred_probability(&inferred_probabilities, "ごちそう", true)
+    );
+
+    println!("{:?}", network);
+}
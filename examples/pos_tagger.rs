@@ -0,0 +1,92 @@
+//! Part-of-speech tagging of "time flies like an arrow" with an HMM learned from a tagged corpus.
+//!
+//! Run with `cargo run --example pos_tagger [corpus] [sentence...]`.
+use bayesian_network::*;
+
+const UNKNOWN: &str = "<unk>";
+
+/// The (tag, word) pairs of each sentence of a corpus of word/tag tokens.
+fn read_corpus(corpus: &str) -> Vec<Vec<(String, String)>> {
+    corpus
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.split_whitespace()
+                .map(|token| {
+                    let (word, tag) = token
+                        .rsplit_once('/')
+                        .unwrap_or_else(|| panic!("Token `{}` is not of the form word/tag", token));
+                    (tag.to_string(), word.to_string())
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Learn an HMM from the tagged sentences, with add-one smoothing. Words outside the corpus
+/// are read as `UNKNOWN`, which every tag emits with its pseudo count.
+fn learn(sentences: &[Vec<(String, String)>]) -> Hmm<String> {
+    let mut tags = Vec::new();
+    let mut words = vec![UNKNOWN.to_string()];
+    for (tag, word) in sentences.iter().flatten() {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+        if !words.contains(word) {
+            words.push(word.clone());
+        }
+    }
+    let uniform = |n: usize| vec![1.0 / n as f64; n];
+    let mut hmm = Hmm::new(
+        tags.clone(),
+        words.clone(),
+        uniform(tags.len()),
+        vec![uniform(tags.len()); tags.len()],
+        vec![uniform(words.len()); tags.len()],
+    );
+    hmm.fit(sentences, 1.0);
+    hmm
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/pos_corpus.txt").to_string()
+    });
+    let corpus = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read corpus `{}`: {}", path, e));
+    let hmm = learn(&read_corpus(&corpus));
+
+    let sentence = args.collect::<Vec<String>>();
+    let sentence = if sentence.is_empty() {
+        "time flies like an arrow"
+            .split(' ')
+            .map(String::from)
+            .collect()
+    } else {
+        sentence
+    };
+    let observed = sentence
+        .iter()
+        .map(|word| {
+            if hmm.observations().contains(word) {
+                word.clone()
+            } else {
+                UNKNOWN.to_string()
+            }
+        })
+        .collect::<Vec<String>>();
+
+    // The most probable tag sequence, with the posterior probability of each tag.
+    let tags = hmm.viterbi(&observed);
+    let posteriors = hmm.forward_backward(&observed);
+    for ((word, tag), posterior) in sentence.iter().zip(&tags).zip(&posteriors) {
+        let prob = posterior
+            .iter()
+            .find(|(state, _)| state == tag)
+            .map(|(_, prob)| *prob)
+            .unwrap_or(0.0);
+        println!("{:<8} {} ({:.3})", word, tag, prob);
+    }
+}
@@ -0,0 +1,222 @@
+use std::{fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId},
+    causal::subsets,
+};
+
+/// A backdoor path between a treatment and an outcome, as found by `check_backdoor_set`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackdoorPath {
+    /// The nodes along the path, from the treatment to the outcome.
+    pub nodes: Vec<Name>,
+    /// Whether the path is open when nothing is adjusted for.
+    pub open_unadjusted: bool,
+    /// Whether the path is open when adjusting for the checked set.
+    pub open_adjusted: bool,
+}
+
+impl BackdoorPath {
+    /// Whether adjusting for the checked set blocks the path.
+    pub fn is_blocked_by_set(&self) -> bool {
+        self.open_unadjusted && !self.open_adjusted
+    }
+
+    /// Whether adjusting for the checked set opens the path, through a collider.
+    pub fn is_opened_by_set(&self) -> bool {
+        !self.open_unadjusted && self.open_adjusted
+    }
+}
+
+/// Why a set is or is not a valid backdoor adjustment set, as returned by
+/// `check_backdoor_set`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdjustmentCheck {
+    pub valid: bool,
+    /// The nodes of the set that descend from the treatment.
+    pub descendants: Vec<Name>,
+    /// The nodes of the set that are hidden.
+    pub hidden: Vec<Name>,
+    /// Every backdoor path, with whether the set leaves it open.
+    pub paths: Vec<BackdoorPath>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get every minimal backdoor adjustment set for the effect of `treatment` on `outcome`:
+    /// the valid sets none of whose proper subsets is valid, by size and then node order.
+    /// Minimal sets only hold ancestors of the treatment or outcome, through whose subsets
+    /// the search goes, so its cost grows exponentially with their number.
+    pub fn backdoor_sets(&self, treatment: &str, outcome: &str) -> Vec<Vec<Name>> {
+        let treatment = self.causal_node(treatment);
+        let outcome = self.causal_node(outcome);
+        let descendants = self.descendants(&[treatment]);
+        let candidates = self
+            .ancestors(&[treatment, outcome])
+            .into_iter()
+            .filter(|id| {
+                *id != treatment
+                    && *id != outcome
+                    && !descendants.contains(id)
+                    && !self.is_node_hidden(self.get_node_name(*id))
+            })
+            .collect::<Vec<NodeId>>();
+        let mut minimal: Vec<Vec<NodeId>> = Vec::new();
+        for size in 0..=candidates.len() {
+            for subset in subsets(&candidates, size) {
+                if minimal
+                    .iter()
+                    .any(|smaller| smaller.iter().all(|id| subset.contains(id)))
+                {
+                    continue;
+                }
+                if self.is_backdoor_set(treatment, outcome, &subset) {
+                    minimal.push(subset);
+                }
+            }
+        }
+        minimal
+            .iter()
+            .map(|subset| self.node_names_of(subset))
+            .collect()
+    }
+
+    /// Check whether `set` is a valid backdoor adjustment set for the effect of `treatment`
+    /// on `outcome`, listing the backdoor paths it blocks, leaves open or opens.
+    pub fn check_backdoor_set(
+        &self,
+        treatment: &str,
+        outcome: &str,
+        set: &[&str],
+    ) -> AdjustmentCheck {
+        let treatment = self.causal_node(treatment);
+        let outcome = self.causal_node(outcome);
+        let set = set
+            .iter()
+            .map(|name| self.causal_node(name))
+            .collect::<Vec<NodeId>>();
+        let descendants = self.descendants(&[treatment]);
+        let in_set_descendants = set
+            .iter()
+            .copied()
+            .filter(|id| descendants.contains(id))
+            .collect::<Vec<NodeId>>();
+        let hidden = set
+            .iter()
+            .copied()
+            .filter(|id| self.is_node_hidden(self.get_node_name(*id)))
+            .collect::<Vec<NodeId>>();
+        let paths = self
+            .backdoor_paths(treatment, outcome)
+            .into_iter()
+            .map(|path| BackdoorPath {
+                open_unadjusted: self.is_path_open(&path, &[]),
+                open_adjusted: self.is_path_open(&path, &set),
+                nodes: self.node_names_of(&path),
+            })
+            .collect::<Vec<BackdoorPath>>();
+        AdjustmentCheck {
+            valid: in_set_descendants.is_empty()
+                && hidden.is_empty()
+                && !paths.iter().any(|path| path.open_adjusted),
+            descendants: self.node_names_of(&in_set_descendants),
+            hidden: self.node_names_of(&hidden),
+            paths,
+        }
+    }
+
+    /// The simple paths between `treatment` and `outcome`, ignoring edge direction, that
+    /// start with an edge into the treatment.
+    fn backdoor_paths(&self, treatment: NodeId, outcome: NodeId) -> Vec<Vec<NodeId>> {
+        self.simple_paths(treatment, outcome)
+            .into_iter()
+            .filter(|path| path.len() > 1 && self.nodes[treatment].parents.contains(&path[1]))
+            .collect()
+    }
+
+    /// Whether a path is open given `given`: each collider on it is in `given` or has a
+    /// descendant there, and no other node on it is in `given`.
+    pub(crate) fn is_path_open(&self, path: &[NodeId], given: &[NodeId]) -> bool {
+        path.windows(3).all(|window| {
+            let (before, node, after) = (window[0], window[1], window[2]);
+            let parents = &self.nodes[node].parents;
+            if parents.contains(&before) && parents.contains(&after) {
+                self.descendants(&[node])
+                    .iter()
+                    .any(|id| given.contains(id))
+            } else {
+                !given.contains(&node)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::graph;
+
+    /// Pearl's example: `z3` is a confounder, but also a collider between `z1` and `z2`.
+    fn pearl() -> BayesianNetwork<bool> {
+        graph(
+            &["z1", "z2", "z3", "x", "w", "y"],
+            &[
+                ("z1", "z3"),
+                ("z2", "z3"),
+                ("z1", "x"),
+                ("z3", "x"),
+                ("z3", "y"),
+                ("z2", "y"),
+                ("x", "w"),
+                ("w", "y"),
+            ],
+        )
+    }
+
+    #[test]
+    fn backdoor_sets_must_close_the_path_the_confounder_opens() {
+        assert_eq!(
+            pearl().backdoor_sets("x", "y"),
+            [vec!["z1", "z3"], vec!["z2", "z3"]]
+        );
+        let m_bias = graph(
+            &["a", "b", "m", "x", "y"],
+            &[("a", "x"), ("a", "m"), ("b", "m"), ("b", "y"), ("x", "y")],
+        );
+        assert_eq!(m_bias.backdoor_sets("x", "y"), [Vec::<String>::new()]);
+    }
+
+    #[test]
+    fn check_backdoor_set_explains_each_path() {
+        let network = pearl();
+        let check = network.check_backdoor_set("x", "y", &["z3"]);
+        assert!(!check.valid);
+        assert_eq!(check.paths.len(), 4);
+        let opened = check
+            .paths
+            .iter()
+            .filter(|path| path.is_opened_by_set())
+            .map(|path| path.nodes.clone())
+            .collect::<Vec<Vec<String>>>();
+        assert_eq!(opened, [vec!["x", "z1", "z3", "z2", "y"]]);
+        assert_eq!(
+            check
+                .paths
+                .iter()
+                .filter(|path| path.is_blocked_by_set())
+                .count(),
+            3
+        );
+
+        let check = network.check_backdoor_set("x", "y", &["z1", "z3", "w"]);
+        assert!(!check.valid);
+        assert_eq!(check.descendants, ["w"]);
+        assert!(check.paths.iter().all(|path| !path.open_adjusted));
+
+        let mut hidden = pearl();
+        hidden.set_node_hidden("z1", true);
+        let check = hidden.check_backdoor_set("x", "y", &["z1", "z3"]);
+        assert!(!check.valid);
+        assert_eq!(check.hidden, ["z1"]);
+        assert!(hidden.check_backdoor_set("x", "y", &["z2", "z3"]).valid);
+    }
+}
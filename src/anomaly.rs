@@ -0,0 +1,140 @@
+use crate::bayesian_network::{BayesianNetwork, NodeId, Probability};
+use crate::dataset::Dataset;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+/// The anomaly score of one record of a dataset.
+#[derive(Clone, Debug)]
+pub struct AnomalyScore {
+    /// The index of the record in the dataset.
+    pub index: usize,
+    /// The natural log-likelihood of the record under the network.
+    pub log_likelihood: f64,
+    /// Whether the log-likelihood fell below the threshold.
+    pub anomalous: bool,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the natural log-likelihood of a (possibly partial) record.
+    /// Nodes missing from the record are marginalized out by variable elimination, which is
+    /// exact on any network.
+    pub fn log_likelihood(&self, record: &HashMap<&str, T>) -> f64 {
+        let mut assignment: HashMap<NodeId, T> = HashMap::new();
+        for (name, value) in record {
+            if !self.node_map.contains_key(*name) {
+                panic!("Record node `{}` not found", name);
+            }
+            if !self.value_space.contains(value) {
+                panic!(
+                    "Record value {:?} of `{}` not found in value space",
+                    value, name
+                );
+            }
+            assignment.insert(self.get_node_index(name), value.clone());
+        }
+
+        if assignment.len() == self.nodes.len() {
+            return self
+                .nodes
+                .iter()
+                .map(|node| {
+                    self.local_probability(node, &assignment[&node.id], &assignment)
+                        .ln()
+                })
+                .sum();
+        }
+
+        let prob = self.assignment_probability(&assignment);
+        if prob <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        prob.ln()
+    }
+
+    /// Get the surprise (negative natural log-likelihood) of a record.
+    pub fn surprise(&self, record: &HashMap<&str, T>) -> f64 {
+        -self.log_likelihood(record)
+    }
+
+    /// Get the probability of a record under the network.
+    pub fn likelihood(&self, record: &HashMap<&str, T>) -> Probability {
+        self.log_likelihood(record).exp()
+    }
+
+    /// Score every record of `dataset`, flagging those whose log-likelihood is below `threshold`.
+    pub fn score_anomalies(&self, dataset: &Dataset<T>, threshold: f64) -> Vec<AnomalyScore> {
+        (0..dataset.len())
+            .map(|index| {
+                let log_likelihood = self.log_likelihood(&dataset.record(index));
+                AnomalyScore {
+                    index,
+                    log_likelihood,
+                    anomalous: log_likelihood < threshold,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, evidence_probability, joint, sprinkler, TOLERANCE};
+
+    #[test]
+    fn log_likelihood_matches_enumeration() {
+        let network = bonus();
+        for (assignment, prob) in joint(&network).into_iter().take(8) {
+            let record = assignment
+                .iter()
+                .map(|(id, value)| (network.get_node_name(*id), *value))
+                .collect::<HashMap<&str, bool>>();
+            assert_close(network.likelihood(&record), prob, TOLERANCE);
+        }
+        let partial = HashMap::from([("bonus", true), ("performance", false)]);
+        assert_close(
+            network.log_likelihood(&partial),
+            evidence_probability(&network, &partial).ln(),
+            TOLERANCE,
+        );
+        assert_close(
+            network.surprise(&partial),
+            -network.log_likelihood(&partial),
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn log_likelihood_is_exact_off_polytrees() {
+        let network = sprinkler();
+        for record in [
+            HashMap::from([("wet_grass", true)]),
+            HashMap::from([("sprinkler", true), ("wet_grass", true)]),
+            HashMap::from([("cloudy", false), ("wet_grass", false)]),
+            HashMap::from([("sprinkler", true), ("rain", true)]),
+        ] {
+            assert_close(
+                network.log_likelihood(&record),
+                evidence_probability(&network, &record).ln(),
+                TOLERANCE,
+            );
+        }
+    }
+
+    #[test]
+    fn score_anomalies_flags_unlikely_records() {
+        let network = bonus();
+        let mut dataset = Dataset::new(vec!["performance", "bonus"]);
+        dataset.add_row(vec![false, false]);
+        dataset.add_row(vec![true, true]);
+        let scores = network.score_anomalies(&dataset, 0.01f64.ln());
+        assert_eq!(scores.len(), 2);
+        assert!(!scores[0].anomalous);
+        assert!(scores[1].anomalous);
+        assert_eq!(scores[1].index, 1);
+        assert_close(
+            scores[1].log_likelihood,
+            network.log_likelihood(&dataset.record(1)),
+            TOLERANCE,
+        );
+    }
+}
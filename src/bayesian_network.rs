@@ -1,8 +1,24 @@
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    hash::Hash,
+};
 
-type Name = String;
-type Probability = f64;
-type NodeId = usize;
+use crate::{
+    cpt::CompactCpt,
+    error::Error,
+    learning::configurations,
+    normalization::Normalization,
+    trace::{InferenceTrace, MessageKind},
+};
+
+pub(crate) type Name = String;
+pub(crate) type Probability = f64;
+pub(crate) type NodeId = usize;
+
+/// The format name of errors about the structure of a network built in code.
+const NETWORK: &str = "Bayesian";
 
 /// The type of a node in a Bayesian network.
 #[derive(Clone)]
@@ -28,12 +44,21 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> Debug for NodeType<T> {
     }
 }
 
-struct Node<T> {
-    id: NodeId,
-    parents: Vec<NodeId>,
-    children: Vec<NodeId>,
-    probability: HashMap<Vec<T>, HashMap<T, Probability>>,
-    node_type: NodeType<T>,
+#[derive(Clone)]
+pub(crate) struct Node<T> {
+    pub(crate) id: NodeId,
+    pub(crate) parents: Vec<NodeId>,
+    pub(crate) children: Vec<NodeId>,
+    pub(crate) probability: HashMap<Vec<T>, HashMap<T, Probability>>,
+    pub(crate) node_type: NodeType<T>,
+    pub(crate) states: Option<Vec<T>>,
+    pub(crate) metadata: BTreeMap<String, String>,
+    pub(crate) shared_cpt: Option<Name>,
+    /// The Dirichlet parameters of each CPT row, as estimated by the last `fit`.
+    pub(crate) dirichlet: Option<HashMap<Vec<T>, HashMap<T, Probability>>>,
+    /// The CPT in compact form, evaluated row by row on demand, in which case `probability`
+    /// is empty.
+    pub(crate) compact_cpt: Option<CompactCpt<T>>,
 }
 
 impl<T> Node<T> {
@@ -44,15 +69,22 @@ impl<T> Node<T> {
             children: Vec::new(),
             probability: HashMap::new(),
             node_type,
+            states: None,
+            metadata: BTreeMap::new(),
+            shared_cpt: None,
+            dirichlet: None,
+            compact_cpt: None,
         }
     }
 }
 
 /// A Bayesian network.
 pub struct BayesianNetwork<T: Clone + PartialEq + Eq + Hash + Debug> {
-    nodes: Vec<Node<T>>,
-    node_map: HashMap<Name, NodeId>,
-    value_space: Vec<T>,
+    pub(crate) nodes: Vec<Node<T>>,
+    pub(crate) node_map: HashMap<Name, NodeId>,
+    pub(crate) value_space: Vec<T>,
+    pub(crate) shared_cpts: HashMap<Name, HashMap<Vec<T>, HashMap<T, Probability>>>,
+    pub(crate) normalization: Normalization,
 }
 
 impl<T: Clone + PartialEq + Eq + Hash + Debug> Debug for BayesianNetwork<T> {
@@ -86,7 +118,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> Debug for BayesianNetwork<T> {
                             .map(|id| id_to_name[id])
                             .collect::<Vec<&Name>>()
                     ));
-                    s.push_str(&format!("  probability: {:?}\n\n", node.probability));
+                    s.push_str(&format!("  probability: {:?}\n\n", self.get_cpt(node.id)));
                 }
                 NodeType::Inner => {
                     s.push_str(&format!(
@@ -96,7 +128,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> Debug for BayesianNetwork<T> {
                             .map(|id| id_to_name[id])
                             .collect::<Vec<&Name>>()
                     ));
-                    s.push_str(&format!("  probability: {:?}\n", node.probability));
+                    s.push_str(&format!("  probability: {:?}\n", self.get_cpt(node.id)));
                     s.push_str(&format!(
                         "  children: {:?}\n\n",
                         node.children
@@ -118,10 +150,14 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
             nodes: Vec::new(),
             node_map: HashMap::new(),
             value_space,
+            shared_cpts: HashMap::new(),
+            normalization: Normalization::default(),
         }
     }
 
-    /// Add a node to the network.
+    /// Add a node to the network. The prior of a root node is normalized according to the
+    /// network's normalization policy, and panics if the policy is strict and it is
+    /// unnormalized.
     pub fn add_node(&mut self, name: &str, node_type: NodeType<T>) {
         let mut node_type = node_type;
         if let NodeType::Root(prob) = &mut node_type {
@@ -130,7 +166,6 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                     prob.insert(value.clone(), 0.0);
                 }
             }
-            let mut sum = 0.0;
             for value in prob.keys() {
                 if !self.value_space.contains(value) {
                     panic!(
@@ -138,13 +173,9 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                         name
                     );
                 }
-                sum += prob[value];
             }
-            if (sum - 1.0).abs() > 0.0000001 {
-                eprintln!(
-                    "Warning: Root node `{}` probability map may not sum to 1.0",
-                    name
-                );
+            if let Err(error) = self.normalize_row(name, &[], prob) {
+                panic!("{}", error);
             }
         }
         let id = self.nodes.len();
@@ -152,24 +183,121 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
         self.node_map.insert(name.to_string(), id);
     }
 
-    /// Add a dependency to the network.
+    /// Add a dependency to the network, normalizing each row according to the network's
+    /// normalization policy. Panics with the error `try_add_dependency` would return.
     pub fn add_dependency(
         &mut self,
         parent_names: Vec<&str>,
         child_name: &str,
         prob: HashMap<Vec<T>, HashMap<T, Probability>>,
     ) {
+        if let Err(error) = self.try_add_dependency(parent_names, child_name, prob) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Add a dependency to the network like `add_dependency`, or return an error without
+    /// changing the network: `Error::Invalid` if a node is missing, a leaf would get a child,
+    /// a root would get a parent, or a key does not fit the parents; `Error::CycleDetected`
+    /// if it would close a directed cycle; and `Error::Unnormalized` if the policy is strict
+    /// and a row is unnormalized.
+    pub fn try_add_dependency(
+        &mut self,
+        parent_names: Vec<&str>,
+        child_name: &str,
+        prob: HashMap<Vec<T>, HashMap<T, Probability>>,
+    ) -> Result<(), Error> {
+        self.try_add_dependency_with(parent_names, child_name, prob, None)
+    }
+
+    /// Add a dependency like `try_add_dependency`, with the CPT given by the table `prob` or,
+    /// if there is one, by `compact_cpt`, in which case `prob` is empty.
+    pub(crate) fn try_add_dependency_with(
+        &mut self,
+        parent_names: Vec<&str>,
+        child_name: &str,
+        prob: HashMap<Vec<T>, HashMap<T, Probability>>,
+        compact_cpt: Option<CompactCpt<T>>,
+    ) -> Result<(), Error> {
+        let invalid = |message: String| Error::Invalid {
+            format: NETWORK,
+            message,
+        };
+        let child_id = match self.node_map.get(child_name) {
+            Some(id) => *id,
+            None => {
+                return Err(invalid(format!(
+                    "dependency child node `{}` not found",
+                    child_name
+                )))
+            }
+        };
+        let mut parent_ids = Vec::new();
+        for parent_name in &parent_names {
+            let parent_id = match self.node_map.get(*parent_name) {
+                Some(id) => *id,
+                None => {
+                    return Err(invalid(format!(
+                        "dependency parent node `{}` not found",
+                        parent_name
+                    )))
+                }
+            };
+            if let NodeType::Leaf = self.nodes[parent_id].node_type {
+                return Err(invalid(format!(
+                    "cannot add dependency from leaf node `{}`",
+                    parent_name
+                )));
+            }
+            if let NodeType::Root(_) = self.nodes[child_id].node_type {
+                return Err(invalid(format!(
+                    "cannot add dependency to root node `{}`",
+                    child_name
+                )));
+            }
+            if let Some(path) = self.directed_path(child_id, parent_id) {
+                let mut cycle = vec![parent_name.to_string()];
+                cycle.extend(path.iter().map(|id| self.get_node_name(*id).to_string()));
+                return Err(Error::CycleDetected(cycle));
+            }
+            parent_ids.push(parent_id);
+        }
+        if let Some(CompactCpt::Function(function)) = &compact_cpt {
+            let domain = self.nodes[child_id]
+                .states
+                .as_ref()
+                .unwrap_or(&self.value_space);
+            let parent_domains = parent_ids
+                .iter()
+                .map(|id| self.get_node_domain(*id))
+                .collect::<Vec<Vec<T>>>();
+            for key in configurations(&parent_domains) {
+                let value = function.evaluate(&key);
+                if !domain.contains(&value) {
+                    return Err(invalid(format!(
+                        "function of `{}` maps {:?} to {:?}, which is not one of its values",
+                        child_name, key, value
+                    )));
+                }
+            }
+        }
         let mut prob = prob;
         for (key, map) in &mut prob {
             if key.len() != parent_names.len() {
-                panic!("Dependency probability map key length does not match parent length ({} and {}, {:?} -> {})", key.len(), parent_names.len(), parent_names, child_name);
+                return Err(invalid(format!(
+                    "dependency probability map key length does not match parent length ({} and {}, {:?} -> {})",
+                    key.len(),
+                    parent_names.len(),
+                    parent_names,
+                    child_name
+                )));
             }
             for value in key {
                 if !self.value_space.contains(value) {
-                    panic!(
-                        "Dependency probability map key contains value not in value space ({:?}, {:?} -> {})",
+                    return Err(invalid(format!(
+                        "dependency probability map key contains value not in value space ({:?}, {:?} -> {})",
                         value, parent_names, child_name
-                    );
+                    )));
                 }
             }
             for value in &self.value_space {
@@ -177,42 +305,298 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                     map.insert(value.clone(), 0.0);
                 }
             }
-            let mut sum = 0.0;
-            for value in map.keys() {
-                sum += map[value];
-            }
-            if (sum - 1.0).abs() > 0.0000001 {
-                eprintln!(
-                    "Warning: Dependency probability map may not sum to 1.0 ({:?} -> {})",
-                    parent_names, child_name
-                );
-            }
+            self.normalize_row(child_name, key, map)?;
         }
-        if !self.node_map.contains_key(child_name) {
-            panic!("Dependency child node `{}` not found", child_name);
+        for parent_id in parent_ids {
+            self.nodes[parent_id].children.push(child_id);
+            self.nodes[child_id].parents.push(parent_id);
         }
-        let child_id = self.node_map[child_name];
-        for parent_name in parent_names {
-            if !self.node_map.contains_key(parent_name) {
-                panic!("Dependency parent node `{}` not found", parent_name);
+        self.nodes[child_id].probability = prob;
+        self.nodes[child_id].compact_cpt = compact_cpt;
+        self.nodes[child_id].shared_cpt = None;
+        self.nodes[child_id].dirichlet = None;
+        Ok(())
+    }
+
+    /// The nodes along a directed path from `from` to `to`, both included, if there is one.
+    fn directed_path(&self, from: NodeId, to: NodeId) -> Option<Vec<NodeId>> {
+        let mut previous: Vec<Option<NodeId>> = vec![None; self.nodes.len()];
+        let mut visited = vec![false; self.nodes.len()];
+        visited[from] = true;
+        let mut stack = vec![from];
+        while let Some(id) = stack.pop() {
+            if id == to {
+                let mut path = vec![to];
+                while let Some(before) = previous[path[path.len() - 1]] {
+                    path.push(before);
+                }
+                path.reverse();
+                return Some(path);
             }
-            let parent_id = self.node_map[parent_name];
-            if let NodeType::Leaf = self.nodes[parent_id].node_type {
-                panic!("Cannot add dependency from leaf node `{}`", parent_name);
+            for child in &self.nodes[id].children {
+                if !visited[*child] {
+                    visited[*child] = true;
+                    previous[*child] = Some(id);
+                    stack.push(*child);
+                }
             }
-            if let NodeType::Root(_) = self.nodes[child_id].node_type {
-                panic!("Cannot add dependency to root node `{}`", child_name);
+        }
+        None
+    }
+
+    /// Get the names of all nodes, in the order they were added.
+    pub fn node_names(&self) -> Vec<&str> {
+        self.nodes
+            .iter()
+            .map(|node| self.get_node_name(node.id))
+            .collect()
+    }
+
+    /// Declare the values a node can take, in order.
+    /// File formats use them as the node's states; they default to the whole value space.
+    pub fn set_node_states(&mut self, name: &str, states: Vec<T>) {
+        if !self.node_map.contains_key(name) {
+            panic!("Node `{}` not found", name);
+        }
+        for value in &states {
+            if !self.value_space.contains(value) {
+                panic!("State {:?} of `{}` not found in value space", value, name);
             }
-            self.nodes[parent_id].children.push(child_id);
-            self.nodes[child_id].parents.push(parent_id);
         }
-        self.nodes[child_id].probability = prob;
+        let id = self.node_map[name];
+        self.nodes[id].states = Some(states);
+    }
+
+    /// Get the values a node can take, in order.
+    pub fn get_node_states(&self, name: &str) -> Vec<T> {
+        if !self.node_map.contains_key(name) {
+            panic!("Node `{}` not found", name);
+        }
+        self.nodes[self.node_map[name]]
+            .states
+            .clone()
+            .unwrap_or_else(|| self.value_space.clone())
+    }
+
+    /// Attach a metadata entry (e.g. a display position) to a node.
+    pub fn set_node_metadata(&mut self, name: &str, key: &str, value: &str) {
+        if !self.node_map.contains_key(name) {
+            panic!("Node `{}` not found", name);
+        }
+        let id = self.node_map[name];
+        self.nodes[id]
+            .metadata
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// Get a metadata entry of a node.
+    pub fn get_node_metadata(&self, name: &str, key: &str) -> Option<&str> {
+        if !self.node_map.contains_key(name) {
+            panic!("Node `{}` not found", name);
+        }
+        self.nodes[self.node_map[name]]
+            .metadata
+            .get(key)
+            .map(|value| value.as_str())
+    }
+
+    /// Convert every value of the network with `f`, keeping its structure and probabilities.
+    /// `f` must map distinct values to distinct values. Compact CPTs become tables.
+    pub fn map_values<U, F>(&self, f: F) -> BayesianNetwork<U>
+    where
+        U: Clone + PartialEq + Eq + Hash + Debug,
+        F: Fn(&T) -> U,
+    {
+        let map = |prob: &HashMap<T, Probability>| {
+            prob.iter()
+                .map(|(value, prob)| (f(value), *prob))
+                .collect::<HashMap<U, Probability>>()
+        };
+        BayesianNetwork {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| Node {
+                    id: node.id,
+                    parents: node.parents.clone(),
+                    children: node.children.clone(),
+                    probability: self
+                        .get_cpt(node.id)
+                        .iter()
+                        .map(|(key, prob)| (key.iter().map(&f).collect(), map(prob)))
+                        .collect(),
+                    node_type: match &node.node_type {
+                        NodeType::Root(prob) => NodeType::Root(map(prob)),
+                        NodeType::Leaf => NodeType::Leaf,
+                        NodeType::Inner => NodeType::Inner,
+                    },
+                    states: node
+                        .states
+                        .as_ref()
+                        .map(|states| states.iter().map(&f).collect()),
+                    metadata: node.metadata.clone(),
+                    shared_cpt: node.shared_cpt.clone(),
+                    dirichlet: node.dirichlet.as_ref().map(|dirichlet| {
+                        dirichlet
+                            .iter()
+                            .map(|(key, prob)| (key.iter().map(&f).collect(), map(prob)))
+                            .collect()
+                    }),
+                    compact_cpt: None,
+                })
+                .collect(),
+            node_map: self.node_map.clone(),
+            value_space: self.value_space.iter().map(&f).collect(),
+            shared_cpts: self
+                .shared_cpts
+                .iter()
+                .map(|(name, cpt)| {
+                    (
+                        name.clone(),
+                        cpt.iter()
+                            .map(|(key, prob)| (key.iter().map(&f).collect(), map(prob)))
+                            .collect(),
+                    )
+                })
+                .collect(),
+            normalization: self.normalization,
+        }
     }
 
-    fn get_node_index(&self, name: &str) -> NodeId {
+    pub(crate) fn get_node_index(&self, name: &str) -> NodeId {
         self.node_map[name]
     }
 
+    /// The declared states of a node, or else the values it can take with nonzero probability
+    /// (the whole value space if it can take none).
+    pub(crate) fn get_node_domain(&self, id: NodeId) -> Vec<T> {
+        let node = &self.nodes[id];
+        if let Some(states) = &node.states {
+            return states.clone();
+        }
+        let keys = match &node.node_type {
+            NodeType::Root(_) => Vec::new(),
+            _ => self.cpt_keys(node),
+        };
+        let domain = self
+            .value_space
+            .iter()
+            .filter(|value| match &node.node_type {
+                NodeType::Root(prob_map) => prob_map[*value] > 0.0,
+                _ => keys
+                    .iter()
+                    .any(|key| self.cpt_probability(node, key, value) > 0.0),
+            })
+            .cloned()
+            .collect::<Vec<T>>();
+        if domain.is_empty() {
+            self.value_space.clone()
+        } else {
+            domain
+        }
+    }
+
+    pub(crate) fn get_node_name(&self, id: NodeId) -> &str {
+        self.node_map
+            .iter()
+            .find(|(_, node_id)| **node_id == id)
+            .map(|(name, _)| name.as_str())
+            .unwrap()
+    }
+
+    /// Probability of `node` taking `value` given the values of its parents in `assignment`.
+    pub(crate) fn local_probability(
+        &self,
+        node: &Node<T>,
+        value: &T,
+        assignment: &HashMap<NodeId, T>,
+    ) -> Probability {
+        if let NodeType::Root(prob_map) = &node.node_type {
+            return prob_map[value];
+        }
+        let parent_values = node
+            .parents
+            .iter()
+            .map(|parent| assignment[parent].clone())
+            .collect::<Vec<T>>();
+        self.cpt_probability(node, &parent_values, value)
+    }
+
+    /// The probability of `value` in the CPT row of a non-root node for `parent_values`,
+    /// evaluating a compact CPT on demand.
+    pub(crate) fn cpt_probability(
+        &self,
+        node: &Node<T>,
+        parent_values: &[T],
+        value: &T,
+    ) -> Probability {
+        match &node.compact_cpt {
+            Some(cpt) => cpt.probability(parent_values, value),
+            None => node
+                .probability
+                .get(parent_values)
+                .and_then(|prob| prob.get(value))
+                .copied()
+                .unwrap_or(0.0),
+        }
+    }
+
+    /// The parent values of each CPT row of a non-root node: the keys of its table, or every
+    /// configuration of the parent domains for a compact CPT.
+    pub(crate) fn cpt_keys(&self, node: &Node<T>) -> Vec<Vec<T>> {
+        match &node.compact_cpt {
+            Some(_) => configurations(
+                &node
+                    .parents
+                    .iter()
+                    .map(|parent| self.get_node_domain(*parent))
+                    .collect::<Vec<Vec<T>>>(),
+            ),
+            None => node.probability.keys().cloned().collect(),
+        }
+    }
+
+    /// The CPT of a non-root node as a table, with a compact CPT expanded over the parent
+    /// domains, for the formats and analyses that need every row.
+    pub(crate) fn get_cpt(&self, id: NodeId) -> Cow<'_, HashMap<Vec<T>, HashMap<T, Probability>>> {
+        let node = &self.nodes[id];
+        match &node.compact_cpt {
+            Some(_) => Cow::Owned(
+                self.cpt_keys(node)
+                    .into_iter()
+                    .map(|key| {
+                        let row = self
+                            .value_space
+                            .iter()
+                            .map(|value| (value.clone(), self.cpt_probability(node, &key, value)))
+                            .collect();
+                        (key, row)
+                    })
+                    .collect(),
+            ),
+            None => Cow::Borrowed(&node.probability),
+        }
+    }
+
+    /// Replace the compact CPT of a node, if it has one, by its table, before changing rows.
+    pub(crate) fn expand_cpt(&mut self, id: NodeId) {
+        if self.nodes[id].compact_cpt.is_some() {
+            self.nodes[id].probability = self.get_cpt(id).into_owned();
+            self.nodes[id].compact_cpt = None;
+        }
+    }
+
+    /// A copy of the network, keeping compact CPTs compact.
+    pub(crate) fn copy(&self) -> BayesianNetwork<T> {
+        BayesianNetwork {
+            nodes: self.nodes.clone(),
+            node_map: self.node_map.clone(),
+            value_space: self.value_space.clone(),
+            shared_cpts: self.shared_cpts.clone(),
+            normalization: self.normalization,
+        }
+    }
+
     fn pass_pi(
         &self,
         node: &Node<T>,
@@ -222,6 +606,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
         pi_map: &mut HashMap<(NodeId, NodeId), HashMap<T, Probability>>,
     ) {
         let mut map = HashMap::new();
+        let keys = self.cpt_keys(node);
         for value in &self.value_space {
             let mut lambda = 1.0;
             for other_child in &node.children {
@@ -242,7 +627,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                     .filter(|(_, parent)| evidence.contains_key(parent))
                     .map(|(i, _)| i)
                     .collect::<Vec<usize>>();
-                for (parent_values, prob) in &node.probability {
+                for parent_values in &keys {
                     let mut skip = false;
                     for e in &evident_parents {
                         if parent_values[*e] != evidence[&parents[*e]] {
@@ -257,7 +642,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                     for (i, parent) in node.parents.iter().enumerate() {
                         parent_mul *= pi_map[&(*parent, node.id)][&parent_values[i]];
                     }
-                    sum += prob[value] * parent_mul;
+                    sum += self.cpt_probability(node, parent_values, value) * parent_mul;
                 }
             }
             map.insert(value.clone(), lambda * sum);
@@ -275,6 +660,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
         lambda_map: &mut HashMap<(NodeId, NodeId), HashMap<T, Probability>>,
     ) {
         let mut map = HashMap::new();
+        let keys = self.cpt_keys(node);
         for value in &self.value_space {
             let mut sum = 0.0;
             let parents = &node.parents;
@@ -284,7 +670,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                 .filter(|(_, parent)| evidence.contains_key(parent))
                 .map(|(i, _)| i)
                 .collect::<Vec<usize>>();
-            for (parent_values, prob) in &node.probability {
+            for parent_values in &keys {
                 if parent_values[parent_index] != *value {
                     continue;
                 }
@@ -314,7 +700,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                     for child in &node.children {
                         lambda *= lambda_map[&(*child, node.id)][node_value];
                     }
-                    node_sum += lambda * prob[node_value];
+                    node_sum += lambda * self.cpt_probability(node, parent_values, node_value);
                 }
                 sum += parent_mul * node_sum;
             }
@@ -325,10 +711,23 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
 
     /// Infer the probability of each node given the evidence.
     pub fn infer(&self, evidence: &HashMap<&str, T>) -> Vec<HashMap<T, Probability>> {
+        self.propagate(evidence, None)
+    }
+
+    /// Infer the probability of each node given the evidence like `infer`, recording every
+    /// message passed into `trace` if it is given.
+    pub(crate) fn propagate(
+        &self,
+        evidence: &HashMap<&str, T>,
+        mut trace: Option<&mut InferenceTrace<T>>,
+    ) -> Vec<HashMap<T, Probability>> {
         let mut pi_map = HashMap::new();
         let mut lambda_map = HashMap::new();
         let mut _evidence: HashMap<NodeId, T> = HashMap::new();
         for (name, value) in evidence {
+            if self.is_node_hidden(name) {
+                panic!("Hidden node `{}` cannot be observed", name);
+            }
             _evidence.insert(self.node_map[&name.to_string()], value.clone());
         }
         let evidence = &_evidence;
@@ -358,6 +757,9 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                                 }
                                 update = true;
                                 self.pass_pi(node, child, evidence, &lambda_map, &mut pi_map);
+                                if let Some(trace) = trace.as_deref_mut() {
+                                    trace.record(self, MessageKind::Pi, node.id, *child, &pi_map);
+                                }
                             }
                         }
                     } else {
@@ -367,6 +769,9 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                             }
                             update = true;
                             self.pass_pi(node, child, evidence, &lambda_map, &mut pi_map);
+                            if let Some(trace) = trace.as_deref_mut() {
+                                trace.record(self, MessageKind::Pi, node.id, *child, &pi_map);
+                            }
                         }
                     }
                 }
@@ -400,6 +805,15 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                                     &pi_map,
                                     &mut lambda_map,
                                 );
+                                if let Some(trace) = trace.as_deref_mut() {
+                                    trace.record(
+                                        self,
+                                        MessageKind::Lambda,
+                                        node.id,
+                                        *parent,
+                                        &lambda_map,
+                                    );
+                                }
                             }
                         }
                     } else {
@@ -416,6 +830,15 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                                 &pi_map,
                                 &mut lambda_map,
                             );
+                            if let Some(trace) = trace.as_deref_mut() {
+                                trace.record(
+                                    self,
+                                    MessageKind::Lambda,
+                                    node.id,
+                                    *parent,
+                                    &lambda_map,
+                                );
+                            }
                         }
                     }
                 }
@@ -425,11 +848,15 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
             } else {
                 update = false;
             }
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.iterations += 1;
+            }
         }
         let mut inferred_probabilities = Vec::new();
         for node in &self.nodes {
             let mut map = HashMap::new();
             let mut probs = Vec::new();
+            let keys = self.cpt_keys(node);
             for value in &self.value_space {
                 let mut lambda = 1.0;
                 for child in &node.children {
@@ -452,7 +879,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                         .map(|(i, _)| i)
                         .collect::<Vec<usize>>();
                     let mut pi = 0.0;
-                    for (parent_values, prob) in &node.probability {
+                    for parent_values in &keys {
                         let mut skip = false;
                         for e in &evident_parents {
                             if parent_values[*e] != evidence[&parents[*e]] {
@@ -467,7 +894,7 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
                         for (i, parent) in node.parents.iter().enumerate() {
                             parent_mul *= pi_map[&(*parent, node.id)][&parent_values[i]];
                         }
-                        pi += prob[value] * parent_mul;
+                        pi += self.cpt_probability(node, parent_values, value) * parent_mul;
                     }
                     pi
                 };
@@ -499,3 +926,76 @@ impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
         inferred_probabilities[self.get_node_index(name)][&value]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_probability, row, TOLERANCE};
+
+    #[test]
+    fn infer_matches_enumeration() {
+        let network = bonus();
+        for evidence in [
+            HashMap::new(),
+            HashMap::from([("bonus", true)]),
+            HashMap::from([("bonus", true), ("feast", false)]),
+        ] {
+            let probabilities = network.infer(&evidence);
+            for name in network.node_names() {
+                assert_close(
+                    network.get_inferred_probability(&probabilities, name, true),
+                    brute_force_probability(&network, name, &true, &evidence),
+                    TOLERANCE,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn try_add_dependency_detects_cycles() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.5)));
+        for name in ["b", "c"] {
+            network.add_node(name, NodeType::Inner);
+        }
+        network.add_dependency(vec!["a"], "b", HashMap::from([(vec![true], row(0.5))]));
+        network.add_dependency(vec!["b"], "c", HashMap::from([(vec![true], row(0.5))]));
+        match network.try_add_dependency(vec!["c"], "b", HashMap::new()) {
+            Err(Error::CycleDetected(path)) => assert_eq!(path, vec!["c", "b", "c"]),
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+        assert!(network.nodes[network.get_node_index("b")].parents.len() == 1);
+    }
+
+    #[test]
+    fn try_add_dependency_rejects_invalid_dependencies() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.5)));
+        network.add_node("b", NodeType::Leaf);
+        let cases = [
+            (
+                vec!["a"],
+                "missing",
+                HashMap::from([(vec![true], row(0.5))]),
+            ),
+            (
+                vec!["missing"],
+                "b",
+                HashMap::from([(vec![true], row(0.5))]),
+            ),
+            (
+                vec!["a"],
+                "b",
+                HashMap::from([(vec![true, true], row(0.5))]),
+            ),
+            (vec!["b"], "a", HashMap::from([(vec![true], row(0.5))])),
+        ];
+        for (parents, child, prob) in cases {
+            assert!(matches!(
+                network.try_add_dependency(parents, child, prob),
+                Err(Error::Invalid { .. })
+            ));
+        }
+        assert!(network.nodes.iter().all(|node| node.parents.is_empty()));
+    }
+}
@@ -0,0 +1,114 @@
+use std::{collections::HashMap, str::FromStr};
+
+/// Command-line options of a subcommand: `--name value`, `--name=value` or a bare `--switch`.
+pub struct Args {
+    options: HashMap<String, Vec<String>>,
+    switches: Vec<String>,
+}
+
+impl Args {
+    pub fn parse(args: &[String]) -> Result<Args, String> {
+        let mut options: HashMap<String, Vec<String>> = HashMap::new();
+        let mut switches = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            let arg = args[i]
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument `{}`", args[i]))?;
+            if let Some((name, value)) = arg.split_once('=') {
+                options
+                    .entry(name.to_string())
+                    .or_default()
+                    .push(value.to_string());
+            } else if i + 1 < args.len() && !args[i + 1].starts_with("--") {
+                options
+                    .entry(arg.to_string())
+                    .or_default()
+                    .push(args[i + 1].clone());
+                i += 1;
+            } else {
+                switches.push(arg.to_string());
+            }
+            i += 1;
+        }
+        Ok(Args { options, switches })
+    }
+
+    /// The last value given for an option.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .get(name)
+            .and_then(|values| values.last())
+            .map(|value| value.as_str())
+    }
+
+    pub fn required(&self, name: &str) -> Result<&str, String> {
+        self.value(name)
+            .ok_or_else(|| format!("missing required option `--{}`", name))
+    }
+
+    /// The last value given for an option, parsed.
+    pub fn parsed<F: FromStr>(&self, name: &str) -> Result<Option<F>, String> {
+        self.value(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid value `{}` for `--{}`", value, name))
+            })
+            .transpose()
+    }
+
+    /// Every value given for a repeatable option.
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.options
+            .get(name)
+            .map(|values| values.iter().map(|value| value.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|switch| switch == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::args;
+
+    #[test]
+    fn parse_reads_options_repeated_options_and_switches() {
+        let args = args(&[
+            "--model",
+            "net.json",
+            "--evidence",
+            "a=x",
+            "--evidence=b=y",
+            "--n",
+            "5",
+            "--states",
+        ]);
+        assert_eq!(args.value("model"), Some("net.json"));
+        assert_eq!(args.values("evidence"), ["a=x", "b=y"]);
+        assert_eq!(args.parsed::<usize>("n"), Ok(Some(5)));
+        assert_eq!(args.parsed::<usize>("seed"), Ok(None));
+        assert!(args.switch("states"));
+        assert!(!args.switch("model"));
+        assert_eq!(
+            args.required("out").err().unwrap(),
+            "missing required option `--out`"
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bare_words_and_bad_values() {
+        let words = ["query".to_string()];
+        assert_eq!(
+            super::Args::parse(&words).err().unwrap(),
+            "unexpected argument `query`"
+        );
+        assert_eq!(
+            args(&["--n", "many"]).parsed::<usize>("n"),
+            Err("invalid value `many` for `--n`".to_string())
+        );
+    }
+}
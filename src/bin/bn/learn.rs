@@ -0,0 +1,63 @@
+use crate::args::Args;
+use crate::model;
+use bayesian_network::{chow_liu, hill_climb, BayesianNetwork, Dataset};
+
+pub const USAGE: &str = "\
+bn learn --data PATH --out PATH [--structure hillclimb|chowliu] [--ess N]
+         [--max-parents N] [--model PATH]
+
+Learn a network from a CSV dataset whose header names the nodes, and save it in the
+format given by the extension of --out. With --model, the structure of an existing
+network is kept and only its probabilities are learned. Probabilities are estimated
+with a BDeu prior of equivalent sample size --ess (1 by default).";
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let path = args.required("data")?;
+    let dataset = Dataset::read_csv(path).map_err(|e| format!("{}: {}", path, e))?;
+    if dataset.is_empty() {
+        return Err(format!("{}: no records", path));
+    }
+    let ess = args.parsed("ess")?.unwrap_or(1.0);
+    let max_parents = args.parsed("max-parents")?.unwrap_or(3);
+
+    let mut network = match args.value("model") {
+        Some(model_path) => {
+            let network = model::load(model_path)?;
+            for name in network.node_names() {
+                if dataset.column_index(name).is_none() {
+                    return Err(format!("{}: no column for node `{}`", path, name));
+                }
+            }
+            network
+        }
+        None => {
+            let edges = match args.value("structure").unwrap_or("hillclimb") {
+                "hillclimb" => hill_climb(&dataset, ess, max_parents),
+                "chowliu" => chow_liu(&dataset),
+                structure => return Err(format!("unknown structure learner `{}`", structure)),
+            };
+            let mut value_space = Vec::new();
+            for row in dataset.rows() {
+                for value in row {
+                    if !value_space.contains(value) {
+                        value_space.push(value.clone());
+                    }
+                }
+            }
+            let names = dataset.columns().iter().map(|name| name.as_str()).collect();
+            let mut network = BayesianNetwork::from_edges(value_space, names, &edges);
+            for (column, name) in dataset.columns().iter().enumerate() {
+                let mut states = Vec::new();
+                for row in dataset.rows() {
+                    if !states.contains(&row[column]) {
+                        states.push(row[column].clone());
+                    }
+                }
+                network.set_node_states(name, states);
+            }
+            network
+        }
+    };
+    network.fit_bdeu(&dataset, ess);
+    model::save(&network, args.required("out")?)
+}
@@ -0,0 +1,75 @@
+//! `bn`: command-line access to Bayesian networks stored in files.
+mod args;
+mod learn;
+mod model;
+mod query;
+mod repl;
+mod sample;
+mod scenarios;
+#[cfg(feature = "server")]
+mod serve;
+#[cfg(test)]
+mod testing;
+mod validate;
+mod viz;
+
+use args::Args;
+
+type Command = fn(&Args) -> Result<(), String>;
+
+const USAGE: &str = "\
+usage: bn <command> [options]
+
+commands:
+  learn      learn a network from a CSV dataset
+  query      print posterior probabilities given evidence
+  repl       explore the network interactively
+  sample     generate synthetic records as CSV
+  scenarios  compare posteriors across named evidence scenarios
+  serve      serve queries over HTTP (with the `server` feature)
+  validate   check the network for structural and numerical problems
+  viz        render the network as DOT, Mermaid or SVG
+
+Run `bn <command> --help` for the options of a command.";
+
+fn main() {
+    let argv = std::env::args().skip(1).collect::<Vec<String>>();
+    let (command, rest) = match argv.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let (run, usage): (Command, &str) = match command {
+        "learn" => (learn::run, learn::USAGE),
+        "query" => (query::run, query::USAGE),
+        "repl" => (repl::run, repl::USAGE),
+        "sample" => (sample::run, sample::USAGE),
+        "scenarios" => (scenarios::run, scenarios::USAGE),
+        #[cfg(feature = "server")]
+        "serve" => (serve::run, serve::USAGE),
+        "validate" => (validate::run, validate::USAGE),
+        "viz" => (viz::run, viz::USAGE),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => {
+            eprintln!("error: unknown command `{}`\n\n{}", command, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let result = Args::parse(rest).and_then(|args| {
+        if args.switch("help") {
+            println!("usage: {}", usage);
+            Ok(())
+        } else {
+            run(&args)
+        }
+    });
+    if let Err(message) = result {
+        eprintln!("error: {}", message);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,193 @@
+use bayesian_network::BayesianNetwork;
+use serde::{de, Deserialize, Deserializer};
+use std::{collections::HashMap, fmt, path::Path};
+
+/// A value of a serialized network, read as a string whatever scalar type it was saved with.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+struct Label(String);
+
+impl<'de> Deserialize<'de> for Label {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Label, D::Error> {
+        struct Visitor;
+        impl de::Visitor<'_> for Visitor {
+            type Value = Label;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string, number or boolean")
+            }
+            fn visit_bool<E>(self, v: bool) -> Result<Label, E> {
+                Ok(Label(v.to_string()))
+            }
+            fn visit_i64<E>(self, v: i64) -> Result<Label, E> {
+                Ok(Label(v.to_string()))
+            }
+            fn visit_u64<E>(self, v: u64) -> Result<Label, E> {
+                Ok(Label(v.to_string()))
+            }
+            fn visit_f64<E>(self, v: f64) -> Result<Label, E> {
+                Ok(Label(v.to_string()))
+            }
+            fn visit_str<E>(self, v: &str) -> Result<Label, E> {
+                Ok(Label(v.to_string()))
+            }
+        }
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Load a network, choosing the format from the file extension. Values are read as strings.
+pub fn load(path: &str) -> Result<BayesianNetwork<String>, String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("");
+    let result = match extension {
+        "json" | "yaml" | "yml" => {
+            let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            let network: Result<BayesianNetwork<Label>, String> = if extension == "json" {
+                serde_json::from_str(&source).map_err(|e| e.to_string())
+            } else {
+                serde_yaml::from_str(&source).map_err(|e| e.to_string())
+            };
+            return network
+                .map(|network| network.map_values(|label| label.0.clone()))
+                .map_err(|e| format!("{}: {}", path, e));
+        }
+        "bif" => BayesianNetwork::read_bif(path),
+        "xml" | "xmlbif" => BayesianNetwork::read_xmlbif(path),
+        "net" => BayesianNetwork::read_hugin(path),
+        "xdsl" => BayesianNetwork::read_xdsl(path),
+        "uai" => BayesianNetwork::read_uai(path),
+        "bin" => BayesianNetwork::load_binary(path),
+        _ => return Err(format!("{}: unknown model format `.{}`", path, extension)),
+    };
+    result.map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Save a network, choosing the format from the file extension.
+pub fn save(network: &BayesianNetwork<String>, path: &str) -> Result<(), String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("");
+    let result = match extension {
+        "json" | "yaml" | "yml" => {
+            let source = if extension == "json" {
+                serde_json::to_string_pretty(network).map_err(|e| e.to_string())
+            } else {
+                serde_yaml::to_string(network).map_err(|e| e.to_string())
+            };
+            return source
+                .and_then(|source| std::fs::write(path, source).map_err(|e| e.to_string()))
+                .map_err(|e| format!("{}: {}", path, e));
+        }
+        "bif" => network.write_bif(path),
+        "xml" | "xmlbif" => network.write_xmlbif(path),
+        "net" => network.write_hugin(path),
+        "uai" => network.write_uai(path),
+        "bin" => network.save_binary(path),
+        _ => return Err(format!("{}: unknown model format `.{}`", path, extension)),
+    };
+    result.map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Collect evidence from `--evidence NAME=VALUE` options and an optional `--evidence-file`
+/// (whose first case is used), checking it against the network.
+pub fn evidence(
+    network: &BayesianNetwork<String>,
+    assignments: &[&str],
+    file: Option<&str>,
+) -> Result<HashMap<String, String>, String> {
+    let mut evidence = HashMap::new();
+    if let Some(file) = file {
+        let cases = network
+            .read_evidence(file)
+            .map_err(|e| format!("{}: {}", file, e))?;
+        if let Some(case) = cases.into_iter().next() {
+            evidence.extend(case);
+        }
+    }
+    for assignment in assignments {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("evidence `{}` is not of the form NAME=VALUE", assignment))?;
+        evidence.insert(name.to_string(), value.to_string());
+    }
+    check_evidence(network, &evidence)?;
+    Ok(evidence)
+}
+
+/// Check that evidence only names nodes of the network and states of those nodes.
+pub fn check_evidence(
+    network: &BayesianNetwork<String>,
+    evidence: &HashMap<String, String>,
+) -> Result<(), String> {
+    let names = network.node_names();
+    for (name, value) in evidence {
+        if !names.contains(&name.as_str()) {
+            return Err(format!("evidence node `{}` not found", name));
+        }
+        if !network.get_node_states(name).contains(value) {
+            return Err(format!("`{}` is not a state of `{}`", value, name));
+        }
+    }
+    Ok(())
+}
+
+/// Borrow owned evidence in the form `infer` takes.
+pub fn borrow(evidence: &HashMap<String, String>) -> HashMap<&str, String> {
+    evidence
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{output_path, MODEL};
+
+    #[test]
+    fn load_reads_values_as_strings_and_save_round_trips() {
+        let network = load(MODEL).unwrap();
+        assert_eq!(network.get_node_states("ボーナス"), ["true", "false"]);
+        let path = output_path("model.bif");
+        save(&network, &path).unwrap();
+        let read = load(&path).unwrap();
+        assert_eq!(read.node_names(), network.node_names());
+        let evidence = HashMap::from([("ボーナス", "true".to_string())]);
+        let expected = network.posterior(&evidence);
+        let actual = read.posterior(&evidence);
+        for name in network.node_names() {
+            for state in ["true", "false"] {
+                let state = state.to_string();
+                let difference = actual.probability(name, &state).unwrap()
+                    - expected.probability(name, &state).unwrap();
+                assert!(difference.abs() < 1e-9);
+            }
+        }
+        assert!(load("net.txt")
+            .unwrap_err()
+            .contains("unknown model format"));
+    }
+
+    #[test]
+    fn evidence_checks_nodes_states_and_form() {
+        let network = load(MODEL).unwrap();
+        assert_eq!(
+            evidence(&network, &["ボーナス=true"], None).unwrap(),
+            HashMap::from([("ボーナス".to_string(), "true".to_string())])
+        );
+        assert_eq!(
+            evidence(&network, &["bonus=true"], None).unwrap_err(),
+            "evidence node `bonus` not found"
+        );
+        assert_eq!(
+            evidence(&network, &["ボーナス=maybe"], None).unwrap_err(),
+            "`maybe` is not a state of `ボーナス`"
+        );
+        assert_eq!(
+            evidence(&network, &["ボーナス"], None).unwrap_err(),
+            "evidence `ボーナス` is not of the form NAME=VALUE"
+        );
+    }
+}
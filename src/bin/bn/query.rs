@@ -0,0 +1,208 @@
+use crate::args::Args;
+use crate::model;
+use bayesian_network::{BayesianNetwork, Posterior};
+use std::collections::HashMap;
+
+pub const USAGE: &str = "\
+bn query --model PATH [--evidence NAME=VALUE]... [--evidence-file PATH]
+         [--target NAME]... [--format table|json|csv] [--out PATH]
+bn query --model PATH --query QUERY
+
+Print the posterior distribution of the target nodes (all nodes by default), or
+evaluate a query such as `P(A=a | B=b)`, `P(A | B=b)` or `MAP(A, C | B=b)`.";
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let network = model::load(args.required("model")?)?;
+    if let Some(query) = args.value("query") {
+        let result = network.eval(query).map_err(|e| e.to_string())?;
+        print!("{}", result);
+        return Ok(());
+    }
+    let evidence = model::evidence(
+        &network,
+        &args.values("evidence"),
+        args.value("evidence-file"),
+    )?;
+    let names = network.node_names();
+    let targets = match args.values("target") {
+        targets if targets.is_empty() => names.clone(),
+        targets => targets,
+    };
+    for target in &targets {
+        if !names.contains(target) {
+            return Err(format!("target node `{}` not found", target));
+        }
+    }
+
+    let posterior = posterior(&network, &evidence, &targets)?;
+
+    let output = match args.value("format").unwrap_or("table") {
+        "table" => table(&posterior),
+        "json" => posterior.to_json(),
+        "csv" => posterior.to_csv(),
+        format => return Err(format!("unknown output format `{}`", format)),
+    };
+    match args.value("out") {
+        Some(path) => std::fs::write(path, output).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            print!("{}", output);
+            Ok(())
+        }
+    }
+}
+
+/// The posterior distribution of each target over its states, by exact elimination so that
+/// models with loops are answered too.
+pub fn posterior(
+    network: &BayesianNetwork<String>,
+    evidence: &HashMap<String, String>,
+    targets: &[&str],
+) -> Result<Posterior<String>, String> {
+    let evidence = model::borrow(evidence);
+    if network.likelihood(&evidence) <= 0.0 {
+        return Err("the evidence has zero probability".to_string());
+    }
+    Ok(network.exact_posterior(targets, &evidence))
+}
+
+/// Lay out `node state probability` rows with aligned columns.
+pub fn table(posterior: &Posterior<String>) -> String {
+    let width = |s: &str| s.chars().count();
+    let nodes = posterior.nodes();
+    let node_width = nodes
+        .iter()
+        .map(|target| width(target))
+        .chain([width("node")])
+        .max()
+        .unwrap();
+    let state_width = nodes
+        .iter()
+        .flat_map(|target| posterior.distribution(target).unwrap())
+        .map(|(state, _)| width(state))
+        .chain([width("state")])
+        .max()
+        .unwrap();
+    let pad = |s: &str, to: usize| format!("{}{}", s, " ".repeat(to - width(s)));
+    let mut s = format!(
+        "{}  {}  probability\n",
+        pad("node", node_width),
+        pad("state", state_width)
+    );
+    for target in &nodes {
+        for (state, prob) in posterior.distribution(target).unwrap() {
+            s.push_str(&format!(
+                "{}  {}  {:.6}\n",
+                pad(target, node_width),
+                pad(state, state_width),
+                prob
+            ));
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{args, output_path, MODEL};
+
+    #[test]
+    fn table_aligns_nodes_states_and_probabilities() {
+        let network = model::load(MODEL).unwrap();
+        let posterior = posterior(&network, &HashMap::new(), &["ボーナス"]).unwrap();
+        // Widths count characters. P(bonus) = 0.3 P(mood) + 0.01 (1 - P(mood)), with P(mood) = 0.0774.
+        assert_eq!(
+            table(&posterior),
+            "node  state  probability\n\
+             ボーナス  true   0.032446\n\
+             ボーナス  false  0.967554\n"
+        );
+    }
+
+    #[test]
+    fn run_writes_the_posterior_of_the_targets() {
+        let path = output_path("query.json");
+        run(&args(&[
+            "--model",
+            MODEL,
+            "--evidence",
+            "ボーナス=true",
+            "--target",
+            "業績",
+            "--format",
+            "json",
+            "--out",
+            &path,
+        ]))
+        .unwrap();
+        let network = model::load(MODEL).unwrap();
+        let evidence = HashMap::from([("ボーナス".to_string(), "true".to_string())]);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            posterior(&network, &evidence, &["業績"]).unwrap().to_json()
+        );
+    }
+
+    /// The sprinkler network, whose loop through `cloudy` and `wet_grass` makes it no polytree.
+    const SPRINKLER: &str = "
+        variable cloudy { type discrete [ 2 ] { t, f }; }
+        variable sprinkler { type discrete [ 2 ] { t, f }; }
+        variable rain { type discrete [ 2 ] { t, f }; }
+        variable wet { type discrete [ 2 ] { t, f }; }
+        probability ( cloudy ) { table 0.5, 0.5; }
+        probability ( sprinkler | cloudy ) { (t) 0.1, 0.9; (f) 0.5, 0.5; }
+        probability ( rain | cloudy ) { (t) 0.8, 0.2; (f) 0.2, 0.8; }
+        probability ( wet | sprinkler, rain ) { (t, t) 0.99, 0.01; (t, f) 0.9, 0.1; (f, t) 0.9, 0.1; (f, f) 0.0, 1.0; }
+    ";
+
+    #[test]
+    fn posteriors_of_models_with_loops_are_exact() {
+        let path = output_path("sprinkler.bif");
+        std::fs::write(&path, SPRINKLER).unwrap();
+        let network = model::load(&path).unwrap();
+        let evidence = HashMap::from([("wet".to_string(), "t".to_string())]);
+        let rain_posterior = posterior(&network, &evidence, &["rain"]).unwrap();
+        // P(rain, wet) = .5 (.8 (.1 .99 + .9 .9) + .2 (.5 .99 + .5 .9)) and P(wet) adds
+        // .5 (.2 .1 .9 + .8 .5 .9).
+        let rain = 0.5 * (0.8 * (0.1 * 0.99 + 0.9 * 0.9) + 0.2 * (0.5 * 0.99 + 0.5 * 0.9));
+        let wet = rain + 0.5 * (0.2 * 0.1 * 0.9 + 0.8 * 0.5 * 0.9);
+        let probability = rain_posterior
+            .probability("rain", &"t".to_string())
+            .unwrap();
+        assert!((probability - rain / wet).abs() < 1e-9);
+
+        let out = output_path("sprinkler.csv");
+        run(&args(&[
+            "--model",
+            &path,
+            "--evidence",
+            "wet=t",
+            "--format",
+            "csv",
+            "--out",
+            &out,
+        ]))
+        .unwrap();
+        let impossible = HashMap::from([
+            ("wet".to_string(), "t".to_string()),
+            ("sprinkler".to_string(), "f".to_string()),
+            ("rain".to_string(), "f".to_string()),
+        ]);
+        assert_eq!(
+            posterior(&network, &impossible, &["cloudy"]).unwrap_err(),
+            "the evidence has zero probability"
+        );
+    }
+
+    #[test]
+    fn run_rejects_unknown_targets_and_formats() {
+        assert_eq!(
+            run(&args(&["--model", MODEL, "--target", "bonus"])).unwrap_err(),
+            "target node `bonus` not found"
+        );
+        assert_eq!(
+            run(&args(&["--model", MODEL, "--format", "xml"])).unwrap_err(),
+            "unknown output format `xml`"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::args::Args;
+use crate::{model, query};
+use bayesian_network::BayesianNetwork;
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+pub const USAGE: &str = "\
+bn repl --model PATH [--evidence NAME=VALUE]... [--evidence-file PATH]
+
+Explore the network interactively. Type `help` at the prompt for the commands.";
+
+const HELP: &str = "\
+set NAME=VALUE...    add or change evidence
+retract [NAME...]    remove evidence (all of it without names)
+evidence             show the current evidence
+query [NAME...]      print posteriors (of all nodes without names)
+watch NAME...        print these posteriors whenever the evidence changes
+unwatch [NAME...]    stop watching (all nodes without names)
+P(...) / MAP(...)    evaluate a query, e.g. P(A=a | B=b) or MAP(A | B=b)
+cpt NAME             print the probability table of a node
+nodes                list the nodes and their states
+help                 show this help
+quit                 leave";
+
+/// The state of an interactive session.
+struct Session {
+    network: BayesianNetwork<String>,
+    evidence: HashMap<String, String>,
+    watched: Vec<String>,
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let network = model::load(args.required("model")?)?;
+    let evidence = model::evidence(
+        &network,
+        &args.values("evidence"),
+        args.value("evidence-file"),
+    )?;
+    let mut session = Session {
+        network,
+        evidence,
+        watched: Vec::new(),
+    };
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("bn> ");
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|e| e.to_string())?,
+            None => {
+                println!();
+                return Ok(());
+            }
+        };
+        match session.respond(&line) {
+            Some(output) => print!("{}", output),
+            None => return Ok(()),
+        }
+    }
+}
+
+impl Session {
+    /// Answer one line typed at the prompt with the text to print, or `None` to leave.
+    fn respond(&mut self, line: &str) -> Option<String> {
+        if line.trim_start().starts_with("P(") || line.trim_start().starts_with("MAP(") {
+            return Some(match self.network.eval(line) {
+                Ok(result) => result.to_string(),
+                Err(e) => format!("error: {}\n", e),
+            });
+        }
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        let Some((command, words)) = words.split_first() else {
+            return Some(String::new());
+        };
+        if matches!(*command, "quit" | "exit") {
+            return None;
+        }
+        Some(match self.execute(command, words) {
+            Ok(output) => output,
+            Err(message) => format!("error: {}\n", message),
+        })
+    }
+
+    /// Run a command, returning what it prints.
+    fn execute(&mut self, command: &str, words: &[&str]) -> Result<String, String> {
+        let mut output = String::new();
+        match command {
+            "set" => {
+                if words.is_empty() {
+                    return Err("usage: set NAME=VALUE...".to_string());
+                }
+                let evidence = model::evidence(&self.network, words, None)?;
+                self.evidence.extend(evidence);
+                output = self.watched_table()?;
+            }
+            "retract" => {
+                let names = self.check_names(words)?;
+                if names.is_empty() {
+                    self.evidence.clear();
+                } else {
+                    for name in names {
+                        self.evidence.remove(name);
+                    }
+                }
+                output = self.watched_table()?;
+            }
+            "evidence" => {
+                let mut evidence = self.evidence.iter().collect::<Vec<(&String, &String)>>();
+                evidence.sort();
+                for (name, value) in evidence {
+                    output.push_str(&format!("{} = {}\n", name, value));
+                }
+            }
+            "query" => {
+                let names = match self.check_names(words)? {
+                    names if names.is_empty() => self.network.node_names(),
+                    names => names,
+                };
+                let posterior = query::posterior(&self.network, &self.evidence, &names)?;
+                output = query::table(&posterior);
+            }
+            "watch" => {
+                if words.is_empty() {
+                    return Err("usage: watch NAME...".to_string());
+                }
+                for name in self.check_names(words)? {
+                    if !self.watched.iter().any(|watched| watched == name) {
+                        self.watched.push(name.to_string());
+                    }
+                }
+                output = self.watched_table()?;
+            }
+            "unwatch" => {
+                let names = self.check_names(words)?;
+                self.watched
+                    .retain(|watched| !names.is_empty() && !names.contains(&watched.as_str()));
+            }
+            "cpt" => match self.check_names(words)?.as_slice() {
+                [name] => output = self.network.cpt_table(name),
+                _ => return Err("usage: cpt NAME".to_string()),
+            },
+            "nodes" => {
+                for name in self.network.node_names() {
+                    output.push_str(&format!(
+                        "{}: {}\n",
+                        name,
+                        self.network.get_node_states(name).join(", ")
+                    ));
+                }
+            }
+            "help" => output = format!("{}\n", HELP),
+            _ => return Err(format!("unknown command `{}` (try `help`)", command)),
+        }
+        Ok(output)
+    }
+
+    /// Check that every name is a node of the network.
+    fn check_names<'a>(&self, names: &[&'a str]) -> Result<Vec<&'a str>, String> {
+        let nodes = self.network.node_names();
+        for name in names {
+            if !nodes.contains(name) {
+                return Err(format!("node `{}` not found", name));
+            }
+        }
+        Ok(names.to_vec())
+    }
+
+    /// The posteriors of the watched nodes, or nothing if none are watched.
+    fn watched_table(&self) -> Result<String, String> {
+        if self.watched.is_empty() {
+            return Ok(String::new());
+        }
+        let names = self
+            .watched
+            .iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<&str>>();
+        let posterior = query::posterior(&self.network, &self.evidence, &names)?;
+        Ok(query::table(&posterior))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MODEL;
+
+    fn session() -> Session {
+        Session {
+            network: model::load(MODEL).unwrap(),
+            evidence: HashMap::new(),
+            watched: Vec::new(),
+        }
+    }
+
+    /// The table `query` prints for `names` given `evidence`.
+    fn expected_table(session: &Session, evidence: &[(&str, &str)], names: &[&str]) -> String {
+        let evidence = evidence
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        query::table(&query::posterior(&session.network, &evidence, names).unwrap())
+    }
+
+    #[test]
+    fn watched_posteriors_follow_the_evidence() {
+        let mut session = session();
+        assert_eq!(
+            session.respond("watch 業績").unwrap(),
+            expected_table(&session, &[], &["業績"])
+        );
+        assert_eq!(
+            session.respond("set ボーナス=true ごちそう=false").unwrap(),
+            expected_table(
+                &session,
+                &[("ボーナス", "true"), ("ごちそう", "false")],
+                &["業績"]
+            )
+        );
+        assert_eq!(
+            session.respond("evidence").unwrap(),
+            "ごちそう = false\nボーナス = true\n"
+        );
+        assert_eq!(
+            session.respond("retract ごちそう").unwrap(),
+            expected_table(&session, &[("ボーナス", "true")], &["業績"])
+        );
+        assert_eq!(
+            session.respond("query 競馬").unwrap(),
+            expected_table(&session, &[("ボーナス", "true")], &["競馬"])
+        );
+        assert_eq!(session.respond("unwatch").unwrap(), "");
+        assert_eq!(session.respond("retract").unwrap(), "");
+        assert!(session.evidence.is_empty());
+    }
+
+    #[test]
+    fn queries_tables_and_errors_are_printed() {
+        let mut session = session();
+        let network = model::load(MODEL).unwrap();
+        assert_eq!(
+            session.respond("P(業績=true | ボーナス=true)").unwrap(),
+            network
+                .eval("P(業績=true | ボーナス=true)")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            session.respond("cpt ボーナス").unwrap(),
+            network.cpt_table("ボーナス")
+        );
+        assert!(session
+            .respond("nodes")
+            .unwrap()
+            .starts_with("業績: true, false\n"));
+        assert_eq!(
+            session.respond("set ボーナス=maybe").unwrap(),
+            "error: `maybe` is not a state of `ボーナス`\n"
+        );
+        assert_eq!(
+            session.respond("cpt bonus").unwrap(),
+            "error: node `bonus` not found\n"
+        );
+        assert_eq!(
+            session.respond("fly").unwrap(),
+            "error: unknown command `fly` (try `help`)\n"
+        );
+        assert_eq!(session.respond("   ").unwrap(), "");
+        assert!(session.respond("quit").is_none());
+    }
+}
@@ -0,0 +1,35 @@
+use crate::args::Args;
+use crate::model;
+use bayesian_network::seeded_rng;
+
+pub const USAGE: &str = "\
+bn sample --model PATH [--n N] [--evidence NAME=VALUE]... [--evidence-file PATH]
+          [--seed N] [--out PATH]
+
+Generate N synthetic records (1000 by default) from the network, conditioned on the
+evidence if any, and write them as CSV to --out or standard output.";
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let network = model::load(args.required("model")?)?;
+    let evidence = model::evidence(
+        &network,
+        &args.values("evidence"),
+        args.value("evidence-file"),
+    )?;
+    let n = args.parsed("n")?.unwrap_or(1000);
+    let evidence = model::borrow(&evidence);
+
+    let dataset = match args.parsed("seed")? {
+        Some(seed) => network.simulate_with_rng(n, &evidence, &mut seeded_rng(seed)),
+        None => network.simulate(n, &evidence),
+    };
+    match args.value("out") {
+        Some(path) => dataset
+            .write_csv(path)
+            .map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            print!("{}", dataset.to_csv());
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::args::Args;
+use crate::model;
+
+pub const USAGE: &str = "\
+bn scenarios --model PATH --scenarios PATH [--target NAME]... [--format table|csv]
+             [--out PATH]
+
+Infer the target posteriors (of all nodes by default) under each named scenario of a
+JSON or YAML file, a list of {\"name\": ..., \"evidence\": {...}} entries, and print
+them side by side.";
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let network = model::load(args.required("model")?)?;
+    let path = args.required("scenarios")?;
+    let scenarios = network
+        .read_scenarios(path)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let names = network.node_names();
+    let targets = match args.values("target") {
+        targets if targets.is_empty() => names.clone(),
+        targets => targets,
+    };
+    for target in &targets {
+        if !names.contains(target) {
+            return Err(format!("target node `{}` not found", target));
+        }
+    }
+
+    let report = network.run_scenarios(&scenarios, &targets);
+    let output = match args.value("format").unwrap_or("table") {
+        "table" => report.to_table(),
+        "csv" => report.to_csv(),
+        format => return Err(format!("unknown output format `{}`", format)),
+    };
+    match args.value("out") {
+        Some(path) => std::fs::write(path, output).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            print!("{}", output);
+            Ok(())
+        }
+    }
+}
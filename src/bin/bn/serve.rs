@@ -0,0 +1,453 @@
+use crate::args::Args;
+use crate::{model, query};
+use bayesian_network::{seeded_rng, BayesianNetwork, QueryResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+pub const USAGE: &str = "\
+bn serve --model PATH [--host ADDR] [--port N]
+
+Serve the network over HTTP (on 127.0.0.1:8080 by default). Every endpoint takes a
+JSON body and answers with JSON:
+
+  POST /query   {\"evidence\": {...}, \"targets\": [...]} or {\"query\": \"P(A | B=b)\"}
+  POST /map     {\"evidence\": {...}, \"targets\": [...]}
+  POST /sample  {\"evidence\": {...}, \"n\": 100, \"seed\": 0}   (n at most 100000)
+
+A MAP query may span at most 65536 joint assignments of its targets, and at most
+64 connections are served at once.";
+
+/// The largest request line and headers accepted, together.
+const MAX_HEADER: usize = 16 << 10;
+
+/// The largest request body accepted.
+const MAX_BODY: usize = 1 << 20;
+
+/// How long a connection may wait on one read or write before it is dropped.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most connections served at once; others are turned away.
+const MAX_CONNECTIONS: usize = 64;
+
+/// The most joint assignments of its targets a MAP query may enumerate.
+const MAX_MAP_CONFIGURATIONS: usize = 1 << 16;
+
+/// The most records `/sample` draws for one request.
+const MAX_SAMPLES: usize = 100_000;
+
+/// The body of a request; each endpoint uses the fields it needs.
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    evidence: HashMap<String, Value>,
+    #[serde(default)]
+    targets: Vec<String>,
+    query: Option<String>,
+    n: Option<usize>,
+    seed: Option<u64>,
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let network = Arc::new(model::load(args.required("model")?)?);
+    let address = format!(
+        "{}:{}",
+        args.value("host").unwrap_or("127.0.0.1"),
+        args.parsed::<u16>("port")?.unwrap_or(8080)
+    );
+    let listener = TcpListener::bind(&address).map_err(|e| format!("{}: {}", address, e))?;
+    eprintln!("listening on http://{}", address);
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let result = stream
+            .set_read_timeout(Some(TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(TIMEOUT)));
+        if let Err(e) = result {
+            eprintln!("warning: {}", e);
+            continue;
+        }
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            let busy = error("too many connections");
+            if let Err(e) = reply(&mut stream, "503 Service Unavailable", &busy) {
+                eprintln!("warning: {}", e);
+            }
+            continue;
+        }
+        let connection = Connection(Arc::clone(&connections));
+        let network = Arc::clone(&network);
+        std::thread::spawn(move || {
+            if let Err(e) = handle(&network, stream) {
+                eprintln!("warning: {}", e);
+            }
+            drop(connection);
+        });
+    }
+    Ok(())
+}
+
+/// A connection being served, counted in the shared total until it is dropped.
+struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answer one HTTP request.
+fn handle(network: &BayesianNetwork<String>, mut stream: TcpStream) -> std::io::Result<()> {
+    let (status, body) = match read_request(stream.try_clone()?)? {
+        Ok((method, path, body)) => respond(network, &method, &path, &body),
+        Err(rejection) => rejection,
+    };
+    reply(&mut stream, status, &body)
+}
+
+/// The method, path and body of a request.
+type Parts = (String, String, Vec<u8>);
+
+/// A status and the body of the response to send.
+type Response = (&'static str, String);
+
+/// Read the method, path and body of a request, or the response refusing it if its headers
+/// or body are too large.
+fn read_request<R: Read>(stream: R) -> std::io::Result<Result<Parts, Response>> {
+    let mut reader = BufReader::new(stream);
+    // The request line and headers share one budget, so that no line can grow unbounded.
+    let mut budget = MAX_HEADER as u64;
+    let mut read_line = |reader: &mut BufReader<R>, line: &mut String| {
+        let read = reader.by_ref().take(budget).read_line(line)?;
+        budget -= read as u64;
+        Ok::<bool, std::io::Error>(read == 0 || line.ends_with('\n'))
+    };
+    let too_large = || {
+        Ok(Err((
+            "431 Request Header Fields Too Large",
+            error("request headers too large"),
+        )))
+    };
+    let mut request_line = String::new();
+    if !read_line(&mut reader, &mut request_line)? {
+        return too_large();
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = parts.next().unwrap_or("").to_string();
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if !read_line(&mut reader, &mut header)? {
+            return too_large();
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    if content_length > MAX_BODY {
+        return Ok(Err((
+            "413 Payload Too Large",
+            error("request body too large"),
+        )));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Ok((method, path, body)))
+}
+
+fn reply(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn error(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
+
+fn respond(network: &BayesianNetwork<String>, method: &str, path: &str, body: &[u8]) -> Response {
+    let endpoint: fn(&BayesianNetwork<String>, Request) -> Result<Value, String> = match path {
+        "/query" => query,
+        "/map" => map,
+        "/sample" => sample,
+        _ => return ("404 Not Found", error("not found")),
+    };
+    if method != "POST" {
+        return ("405 Method Not Allowed", error("use POST"));
+    }
+    let body = if body.is_empty() { b"{}" } else { body };
+    let result = serde_json::from_slice(body)
+        .map_err(|e| e.to_string())
+        .and_then(|request| endpoint(network, request));
+    match result {
+        Ok(value) => ("200 OK", value.to_string()),
+        Err(message) => ("400 Bad Request", error(&message)),
+    }
+}
+
+/// Read the evidence of a request, accepting any scalar value by its string form, and check
+/// that it is possible.
+fn evidence(
+    network: &BayesianNetwork<String>,
+    request: &Request,
+) -> Result<HashMap<String, String>, String> {
+    let evidence = request
+        .evidence
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Bool(_) | Value::Number(_) => value.to_string(),
+                _ => return Err(format!("evidence value of `{}` is not a scalar", name)),
+            };
+            Ok((name.clone(), value))
+        })
+        .collect::<Result<HashMap<String, String>, String>>()?;
+    model::check_evidence(network, &evidence)?;
+    if network.likelihood(&model::borrow(&evidence)) <= 0.0 {
+        return Err("the evidence has zero probability".to_string());
+    }
+    Ok(evidence)
+}
+
+/// The requested targets, or every node not in the evidence if none are given.
+fn targets<'a>(
+    network: &'a BayesianNetwork<String>,
+    request: &'a Request,
+    evidence: &HashMap<String, String>,
+) -> Result<Vec<&'a str>, String> {
+    let names = network.node_names();
+    if request.targets.is_empty() {
+        return Ok(names
+            .into_iter()
+            .filter(|name| !evidence.contains_key(*name))
+            .collect());
+    }
+    request
+        .targets
+        .iter()
+        .map(|target| {
+            if names.contains(&target.as_str()) {
+                Ok(target.as_str())
+            } else {
+                Err(format!("target node `{}` not found", target))
+            }
+        })
+        .collect()
+}
+
+fn assignment_json(assignment: &[(String, String)], probability: f64) -> Value {
+    let assignment = assignment
+        .iter()
+        .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+        .collect::<serde_json::Map<String, Value>>();
+    json!({ "assignment": assignment, "probability": probability })
+}
+
+/// Refuse a MAP query over more than `MAX_MAP_CONFIGURATIONS` joint assignments.
+fn check_map_size(configurations: usize) -> Result<(), String> {
+    if configurations > MAX_MAP_CONFIGURATIONS {
+        return Err(format!(
+            "MAP targets span {} joint assignments, more than {}",
+            configurations, MAX_MAP_CONFIGURATIONS
+        ));
+    }
+    Ok(())
+}
+
+fn query(network: &BayesianNetwork<String>, request: Request) -> Result<Value, String> {
+    if let Some(query) = &request.query {
+        if let Some(configurations) = network
+            .map_configurations(query)
+            .map_err(|e| e.to_string())?
+        {
+            check_map_size(configurations)?;
+        }
+        return match network.eval(query).map_err(|e| e.to_string())? {
+            QueryResult::Probability(probability) => Ok(json!({ "probability": probability })),
+            QueryResult::Distribution(posterior) => {
+                serde_json::from_str(&posterior.to_json()).map_err(|e| e.to_string())
+            }
+            QueryResult::Map {
+                assignment,
+                probability,
+            } => Ok(assignment_json(&assignment, probability)),
+        };
+    }
+    let evidence = evidence(network, &request)?;
+    let names = network.node_names();
+    let targets = if request.targets.is_empty() {
+        names
+    } else {
+        targets(network, &request, &evidence)?
+    };
+    let posterior = query::posterior(network, &evidence, &targets)?;
+    serde_json::from_str(&posterior.to_json()).map_err(|e| e.to_string())
+}
+
+fn map(network: &BayesianNetwork<String>, request: Request) -> Result<Value, String> {
+    let evidence = evidence(network, &request)?;
+    let targets = targets(network, &request, &evidence)?;
+    check_map_size(targets.iter().fold(1usize, |count, target| {
+        count.saturating_mul(network.get_node_states(target).len())
+    }))?;
+    let (assignment, probability) = network.most_probable(&targets, &model::borrow(&evidence));
+    Ok(assignment_json(&assignment, probability))
+}
+
+fn sample(network: &BayesianNetwork<String>, request: Request) -> Result<Value, String> {
+    let n = request.n.unwrap_or(100);
+    if n > MAX_SAMPLES {
+        return Err(format!("cannot draw more than {} samples", MAX_SAMPLES));
+    }
+    let evidence = evidence(network, &request)?;
+    let evidence = model::borrow(&evidence);
+    let dataset = match request.seed {
+        Some(seed) => network.simulate_with_rng(n, &evidence, &mut seeded_rng(seed)),
+        None => network.simulate(n, &evidence),
+    };
+    Ok(json!({ "columns": dataset.columns(), "rows": dataset.rows() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MODEL;
+    use bayesian_network::NodeType;
+
+    /// POST `body` to `path`, returning the status and the parsed response.
+    fn post(path: &str, body: &str) -> (&'static str, Value) {
+        let network = model::load(MODEL).unwrap();
+        let (status, body) = respond(&network, "POST", path, body.as_bytes());
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    #[test]
+    fn query_answers_with_the_posterior() {
+        let network = model::load(MODEL).unwrap();
+        let (status, body) = post(
+            "/query",
+            r#"{"evidence": {"ボーナス": true}, "targets": ["業績"]}"#,
+        );
+        assert_eq!(status, "200 OK");
+        let evidence = HashMap::from([("ボーナス".to_string(), "true".to_string())]);
+        let posterior = query::posterior(&network, &evidence, &["業績"]).unwrap();
+        let expected = posterior.to_json();
+        assert_eq!(body, serde_json::from_str::<Value>(&expected).unwrap());
+
+        let (status, body) = post("/query", r#"{"query": "P(業績=true | ボーナス=true)"}"#);
+        assert_eq!(status, "200 OK");
+        let probability = body["probability"].as_f64().unwrap();
+        let expected = posterior.probability("業績", &"true".to_string()).unwrap();
+        assert!((probability - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn map_and_sample_answer_with_assignments_and_records() {
+        let (status, body) = post("/map", r#"{"evidence": {"ボーナス": true}}"#);
+        assert_eq!(status, "200 OK");
+        assert!(body["assignment"].get("ごきげん").is_some());
+        assert!(body["assignment"].get("ボーナス").is_none());
+
+        let request = r#"{"evidence": {"ボーナス": "true"}, "n": 5, "seed": 3}"#;
+        let (status, body) = post("/sample", request);
+        assert_eq!(status, "200 OK");
+        let rows = body["rows"].as_array().unwrap();
+        assert_eq!(rows.len(), 5);
+        let bonus = body["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .position(|column| column == "ボーナス")
+            .unwrap();
+        assert!(rows.iter().all(|row| row[bonus] == "true"));
+        assert_eq!(post("/sample", request).1, body);
+    }
+
+    #[test]
+    fn bad_requests_are_rejected() {
+        let (status, body) = post("/sample", &format!(r#"{{"n": {}}}"#, MAX_SAMPLES + 1));
+        assert_eq!(status, "400 Bad Request");
+        assert_eq!(body["error"], "cannot draw more than 100000 samples");
+        let (status, body) = post("/query", r#"{"evidence": {"ボーナス": "maybe"}}"#);
+        assert_eq!(status, "400 Bad Request");
+        assert_eq!(body["error"], "`maybe` is not a state of `ボーナス`");
+        let (status, _) = post("/query", "not json");
+        assert_eq!(status, "400 Bad Request");
+        let (status, _) = post("/posterior", "{}");
+        assert_eq!(status, "404 Not Found");
+        let network = model::load(MODEL).unwrap();
+        assert_eq!(
+            respond(&network, "GET", "/query", b"").0,
+            "405 Method Not Allowed"
+        );
+    }
+
+    #[test]
+    fn large_map_queries_are_rejected() {
+        let states = vec!["true".to_string(), "false".to_string()];
+        let mut network = BayesianNetwork::new(states.clone());
+        let prior = states.into_iter().map(|value| (value, 0.5)).collect();
+        let names = (0..17).map(|i| format!("n{}", i)).collect::<Vec<String>>();
+        for name in &names {
+            network.add_node(name, NodeType::Root(HashMap::clone(&prior)));
+        }
+        let message = "MAP targets span 131072 joint assignments, more than 65536";
+        let (status, body) = respond(&network, "POST", "/map", b"{}");
+        assert_eq!(status, "400 Bad Request");
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap()["error"],
+            message
+        );
+        let request = json!({ "query": format!("MAP({})", names.join(", ")) }).to_string();
+        let (status, body) = respond(&network, "POST", "/query", request.as_bytes());
+        assert_eq!(status, "400 Bad Request");
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap()["error"],
+            message
+        );
+    }
+
+    #[test]
+    fn oversized_requests_are_refused() {
+        let request = b"POST /query HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}";
+        let (method, path, body) = read_request(&request[..]).unwrap().unwrap();
+        assert_eq!((method.as_str(), path.as_str()), ("POST", "/query"));
+        assert_eq!(body, b"{}");
+
+        let header = format!("X-Padding: {}\r\n", "a".repeat(MAX_HEADER));
+        let request = format!("POST /query HTTP/1.1\r\n{}\r\n", header);
+        let (status, _) = read_request(request.as_bytes()).unwrap().unwrap_err();
+        assert_eq!(status, "431 Request Header Fields Too Large");
+        let request = format!(
+            "POST /query HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        let (status, _) = read_request(request.as_bytes()).unwrap().unwrap_err();
+        assert_eq!(status, "413 Payload Too Large");
+    }
+}
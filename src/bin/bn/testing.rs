@@ -0,0 +1,22 @@
+//! Fixtures shared by the tests of the subcommands.
+use crate::args::Args;
+
+/// The bonus example network: performance (業績) and horse racing (競馬) cause a good mood
+/// (ごきげん), which causes a bonus (ボーナス) and a feast (ごちそう).
+pub const MODEL: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/bonus.json");
+
+/// Parse the options of a subcommand.
+pub fn args(words: &[&str]) -> Args {
+    let words = words
+        .iter()
+        .map(|word| word.to_string())
+        .collect::<Vec<String>>();
+    Args::parse(&words).unwrap()
+}
+
+/// A fresh path in the temporary directory for a file written by the test `name`.
+pub fn output_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("bn-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path.to_string_lossy().into_owned()
+}
@@ -0,0 +1,61 @@
+use crate::args::Args;
+use crate::model;
+use bayesian_network::Severity;
+
+pub const USAGE: &str = "\
+bn validate --model PATH [--format json|text]
+
+Check the network for directed cycles, incomplete or unnormalized CPTs and unreachable
+nodes, and print the diagnostics (as JSON by default). Exits with status 1 if any
+check fails with an error.";
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let network = model::load(args.required("model")?)?;
+    let diagnostics = network.validate();
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+
+    match args.value("format").unwrap_or("json") {
+        "json" => {
+            let json = serde_json::json!({
+                "valid": errors == 0,
+                "errors": errors,
+                "warnings": diagnostics.len() - errors,
+                "diagnostics": diagnostics
+                    .iter()
+                    .map(|diagnostic| serde_json::json!({
+                        "severity": diagnostic.severity.to_string(),
+                        "check": diagnostic.check,
+                        "node": diagnostic.node,
+                        "message": diagnostic.message,
+                    }))
+                    .collect::<Vec<serde_json::Value>>(),
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?
+            );
+        }
+        "text" => {
+            for diagnostic in &diagnostics {
+                match &diagnostic.node {
+                    Some(node) => println!(
+                        "{}[{}] {}: {}",
+                        diagnostic.severity, diagnostic.check, node, diagnostic.message
+                    ),
+                    None => println!(
+                        "{}[{}] {}",
+                        diagnostic.severity, diagnostic.check, diagnostic.message
+                    ),
+                }
+            }
+        }
+        format => return Err(format!("unknown output format `{}`", format)),
+    }
+    if errors > 0 {
+        return Err(format!("{} validation error(s)", errors));
+    }
+    Ok(())
+}
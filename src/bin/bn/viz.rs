@@ -0,0 +1,104 @@
+use crate::args::Args;
+use crate::model;
+use bayesian_network::DiagramOptions;
+
+pub const USAGE: &str = "\
+bn viz --model PATH [--format dot|mermaid|svg] [--states] [--posteriors]
+       [--evidence NAME=VALUE]... [--evidence-file PATH] [--out PATH]
+
+Render the network (as DOT by default) to --out or standard output. --states lists the
+states of each node, --posteriors annotates them with their probabilities given the
+evidence, and evidence nodes are highlighted.";
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let network = model::load(args.required("model")?)?;
+    let evidence = model::evidence(
+        &network,
+        &args.values("evidence"),
+        args.value("evidence-file"),
+    )?;
+    let evidence = model::borrow(&evidence);
+    let inferred_probabilities = if args.switch("posteriors") {
+        Some(network.infer(&evidence))
+    } else {
+        None
+    };
+    let options = DiagramOptions {
+        states: args.switch("states"),
+        probabilities: inferred_probabilities.as_deref(),
+        evidence: Some(&evidence),
+    };
+
+    let diagram = match args.value("format").unwrap_or("dot") {
+        "dot" => network.to_dot_with(&options),
+        "mermaid" => network.to_mermaid_with(&options),
+        "svg" => network.to_svg_with(&options),
+        format => return Err(format!("unknown diagram format `{}`", format)),
+    };
+    match args.value("out") {
+        Some(path) => std::fs::write(path, diagram).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            print!("{}", diagram);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{args, output_path, MODEL};
+    use std::collections::HashMap;
+
+    /// Run `bn viz` with the given options and read back the diagram.
+    fn render(name: &str, options: &[&str]) -> String {
+        let path = output_path(name);
+        let mut words = vec!["--model", MODEL, "--out", &path];
+        words.extend(options);
+        run(&args(&words)).unwrap();
+        std::fs::read_to_string(&path).unwrap()
+    }
+
+    #[test]
+    fn run_annotates_nodes_with_their_posteriors() {
+        let network = model::load(MODEL).unwrap();
+        let prior = network.infer(&HashMap::new());
+        let mermaid = render("viz.mmd", &["--format", "mermaid", "--posteriors"]);
+        assert_eq!(
+            mermaid,
+            network.to_mermaid_with(&DiagramOptions {
+                states: false,
+                probabilities: Some(&prior),
+                evidence: Some(&HashMap::new()),
+            })
+        );
+        // P(bonus) = 0.3 P(mood) + 0.01 (1 - P(mood)), with P(mood) = 0.0774.
+        assert!(mermaid.contains("true: 0.0324"));
+    }
+
+    #[test]
+    fn run_highlights_the_evidence() {
+        let network = model::load(MODEL).unwrap();
+        let evidence = HashMap::from([("ボーナス", "true".to_string())]);
+        let posterior = network.infer(&evidence);
+        let dot = render("viz.dot", &["--evidence", "ボーナス=true", "--posteriors"]);
+        assert_eq!(
+            dot,
+            network.to_dot_with(&DiagramOptions {
+                states: false,
+                probabilities: Some(&posterior),
+                evidence: Some(&evidence),
+            })
+        );
+        assert!(dot.contains("ボーナス = true"));
+        assert!(render("viz.svg", &["--format", "svg", "--states"]).starts_with("<svg"));
+    }
+
+    #[test]
+    fn run_rejects_unknown_formats() {
+        assert_eq!(
+            run(&args(&["--model", MODEL, "--format", "png"])).unwrap_err(),
+            "unknown diagram format `png`"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use crate::bayesian_network::BayesianNetwork;
+use crate::error::Error;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt::Debug, hash::Hash, path::Path};
+
+const FORMAT: &str = "binary";
+const MAGIC: &[u8; 4] = b"BNET";
+/// Bumped whenever the encoded layout changes; older versions are rejected on load.
+const VERSION: u16 = 2;
+
+impl<T> BayesianNetwork<T>
+where
+    T: Clone + PartialEq + Eq + Hash + Debug + Serialize + DeserializeOwned,
+{
+    /// Encode the network compactly: a `BNET` magic number and a format version, followed by
+    /// the bincode encoding of the network.
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self).expect("Failed to encode network");
+        bytes
+    }
+
+    /// Decode a network encoded by `to_binary`.
+    pub fn from_binary(bytes: &[u8]) -> Result<BayesianNetwork<T>, Error> {
+        let invalid = |message: String| Error::Invalid {
+            format: FORMAT,
+            message,
+        };
+        if bytes.len() < 6 || &bytes[..4] != MAGIC {
+            return Err(invalid("missing BNET header".to_string()));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(invalid(format!(
+                "unsupported version {} (expected {})",
+                version, VERSION
+            )));
+        }
+        bincode::deserialize(&bytes[6..]).map_err(|error| invalid(error.to_string()))
+    }
+
+    /// Save the network to a file in the compact binary encoding.
+    pub fn save_binary<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_binary())?)
+    }
+
+    /// Load a network saved by `save_binary`.
+    pub fn load_binary<P: AsRef<Path>>(path: P) -> Result<BayesianNetwork<T>, Error> {
+        BayesianNetwork::from_binary(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_same_joint, bonus, TOLERANCE};
+
+    #[test]
+    fn binary_round_trip_keeps_the_network() {
+        let network = bonus();
+        let bytes = network.to_binary();
+        assert_eq!(&bytes[..4], MAGIC);
+        let read = BayesianNetwork::<bool>::from_binary(&bytes).unwrap();
+        assert_same_joint(
+            &network,
+            &read.map_values(|value| value.to_string()),
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn headers_and_versions_are_checked() {
+        let mut bytes = bonus().to_binary();
+        assert!(matches!(
+            BayesianNetwork::<bool>::from_binary(&bytes[..3]),
+            Err(Error::Invalid { format: FORMAT, .. })
+        ));
+        bytes[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        match BayesianNetwork::<bool>::from_binary(&bytes) {
+            Err(Error::Invalid { message, .. }) => assert!(message.contains("unsupported version")),
+            result => panic!("expected an invalid version, got {:?}", result.err()),
+        }
+        let mut truncated = bonus().to_binary();
+        truncated.truncate(truncated.len() / 2);
+        assert!(BayesianNetwork::<bool>::from_binary(&truncated).is_err());
+    }
+}
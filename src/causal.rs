@@ -0,0 +1,625 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability},
+    error::Error,
+    learning::configurations,
+};
+
+/// The node metadata key marking a node as hidden.
+const HIDDEN_KEY: &str = "hidden";
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Mark a node as hidden, or as observed again. Hidden nodes stand for unobserved
+    /// variables: they cannot be given as evidence, are never used to adjust for confounding,
+    /// and need no dataset column for `fit_em`.
+    pub fn set_node_hidden(&mut self, name: &str, hidden: bool) {
+        if hidden {
+            self.set_node_metadata(name, HIDDEN_KEY, "true");
+        } else if let Some(id) = self.node_map.get(name) {
+            self.nodes[*id].metadata.remove(HIDDEN_KEY);
+        } else {
+            panic!("Node `{}` not found", name);
+        }
+    }
+
+    /// Whether a node is hidden.
+    pub fn is_node_hidden(&self, name: &str) -> bool {
+        self.get_node_metadata(name, HIDDEN_KEY) == Some("true")
+    }
+
+    /// Get the network mutilated by the interventions `do(X = x)`: each intervened node loses
+    /// its incoming edges and becomes a root fixed to its value.
+    pub fn intervene(&self, interventions: &HashMap<&str, T>) -> BayesianNetwork<T> {
+        let mut network = self.copy();
+        for (name, value) in interventions {
+            let id = match network.node_map.get(*name) {
+                Some(id) => *id,
+                None => panic!("Node `{}` not found", name),
+            };
+            if !network.value_space.contains(value) {
+                panic!("Intervention value {:?} not in value space", value);
+            }
+            for parent in std::mem::take(&mut network.nodes[id].parents) {
+                network.nodes[parent].children.retain(|child| *child != id);
+            }
+            let node = &mut network.nodes[id];
+            node.probability.clear();
+            node.compact_cpt = None;
+            node.shared_cpt = None;
+            node.dirichlet = None;
+            node.node_type = NodeType::Root(
+                network
+                    .value_space
+                    .iter()
+                    .map(|other| (other.clone(), if other == value { 1.0 } else { 0.0 }))
+                    .collect(),
+            );
+        }
+        network
+    }
+
+    /// Infer the probability of each node under the interventions `do(X = x)` given the
+    /// evidence, P(Y | do(X = x), e), by inference on the mutilated network. Unlike
+    /// conditioning on `X = x`, intervening tells nothing about the causes of `X`.
+    pub fn infer_do(
+        &self,
+        interventions: &HashMap<&str, T>,
+        evidence: &HashMap<&str, T>,
+    ) -> Vec<HashMap<T, Probability>> {
+        self.intervene(interventions).infer(evidence)
+    }
+}
+
+/// The criterion by which a causal effect was identified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Criterion {
+    Backdoor,
+    FrontDoor,
+}
+
+/// A causal effect P(outcome | do(treatment)) identified from the network, as returned by
+/// `causal_effect`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CausalEffect<T> {
+    /// The interventional distribution of the outcome over its states.
+    pub distribution: Vec<(T, Probability)>,
+    pub criterion: Criterion,
+    /// The nodes adjusted for (the mediators for the front-door criterion), in node order.
+    pub adjustment_set: Vec<Name>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Find a smallest set of nodes satisfying the backdoor criterion for the effect of
+    /// `treatment` on `outcome`: no node of the set is hidden or descends from the treatment,
+    /// and the set blocks every path between them that starts with an edge into the
+    /// treatment. The search goes through the subsets of their ancestors by size, so its cost
+    /// grows exponentially with their number.
+    pub fn backdoor_set(&self, treatment: &str, outcome: &str) -> Option<Vec<Name>> {
+        let treatment = self.causal_node(treatment);
+        let outcome = self.causal_node(outcome);
+        let descendants = self.descendants(&[treatment]);
+        let candidates = self
+            .ancestors(&[treatment, outcome])
+            .into_iter()
+            .filter(|id| {
+                *id != treatment
+                    && *id != outcome
+                    && !descendants.contains(id)
+                    && !self.is_node_hidden(self.get_node_name(*id))
+            })
+            .collect::<Vec<NodeId>>();
+        for size in 0..=candidates.len() {
+            for subset in subsets(&candidates, size) {
+                if self.is_backdoor_set(treatment, outcome, &subset) {
+                    return Some(self.node_names_of(&subset));
+                }
+            }
+        }
+        None
+    }
+
+    /// Compute P(outcome | do(treatment = value)) by adjusting for a backdoor set found by
+    /// `backdoor_set`, as the sum over its values z of P(outcome | value, z) P(z). Returns
+    /// `None` if no backdoor set exists, and `Error::Query` if positivity does not hold: the
+    /// treatment value has probability 0 given some adjustment values z with P(z) > 0, so
+    /// P(outcome | value, z) is undefined.
+    pub fn backdoor_effect(
+        &self,
+        treatment: &str,
+        value: &T,
+        outcome: &str,
+    ) -> Result<Option<CausalEffect<T>>, Error> {
+        let adjustment_set = match self.backdoor_set(treatment, outcome) {
+            Some(adjustment_set) => adjustment_set,
+            None => return Ok(None),
+        };
+        let set = adjustment_set
+            .iter()
+            .map(|name| self.node_map[name])
+            .collect::<Vec<NodeId>>();
+        let treatment = self.causal_node(treatment);
+        let outcome = self.causal_node(outcome);
+        let adjustment_domains = set
+            .iter()
+            .map(|id| self.get_node_domain(*id))
+            .collect::<Vec<Vec<T>>>();
+        let mut distribution = Vec::new();
+        for state in self.get_node_domain(outcome) {
+            let mut prob = 0.0;
+            for z in configurations(&adjustment_domains) {
+                let mut assignment = set
+                    .iter()
+                    .copied()
+                    .zip(z.iter().cloned())
+                    .collect::<HashMap<NodeId, T>>();
+                let marginal = self.assignment_probability(&assignment);
+                if marginal <= 0.0 {
+                    continue;
+                }
+                assignment.insert(treatment, value.clone());
+                let treated = self.assignment_probability(&assignment);
+                if treated <= 0.0 {
+                    return Err(Error::Query(format!(
+                        "positivity does not hold: treatment value {:?} has zero probability with adjustment values {:?}",
+                        value, z
+                    )));
+                }
+                assignment.insert(outcome, state.clone());
+                prob += self.assignment_probability(&assignment) / treated * marginal;
+            }
+            distribution.push((state, prob));
+        }
+        Ok(Some(CausalEffect {
+            distribution,
+            criterion: Criterion::Backdoor,
+            adjustment_set,
+        }))
+    }
+
+    /// Find a smallest set of mediators satisfying the front-door criterion for the effect of
+    /// `treatment` on `outcome`: none is hidden, they intercept every directed path from the
+    /// treatment to the outcome, every backdoor path from the treatment to them is blocked,
+    /// and every backdoor path from them to the outcome is blocked by the treatment.
+    pub fn frontdoor_set(&self, treatment: &str, outcome: &str) -> Option<Vec<Name>> {
+        let treatment = self.causal_node(treatment);
+        let outcome = self.causal_node(outcome);
+        let ancestors = self.ancestors(&[outcome]);
+        let candidates = self
+            .descendants(&[treatment])
+            .into_iter()
+            .filter(|id| {
+                *id != treatment
+                    && *id != outcome
+                    && ancestors.contains(id)
+                    && !self.is_node_hidden(self.get_node_name(*id))
+            })
+            .collect::<Vec<NodeId>>();
+        for size in 1..=candidates.len() {
+            for subset in subsets(&candidates, size) {
+                if self.is_frontdoor_set(treatment, outcome, &subset) {
+                    return Some(self.node_names_of(&subset));
+                }
+            }
+        }
+        None
+    }
+
+    /// Compute P(outcome | do(treatment = value)) through mediators found by `frontdoor_set`,
+    /// as the sum over their values m of P(m | value) times the sum over treatment values t
+    /// of P(outcome | t, m) P(t). Returns `None` if no such mediators exist, and
+    /// `Error::Query` if positivity does not hold: the treatment value has probability 0, or
+    /// some treatment value t with P(t) > 0 never occurs with mediator values m that the
+    /// treatment value can produce, so P(outcome | t, m) is undefined.
+    pub fn frontdoor_effect(
+        &self,
+        treatment: &str,
+        value: &T,
+        outcome: &str,
+    ) -> Result<Option<CausalEffect<T>>, Error> {
+        let adjustment_set = match self.frontdoor_set(treatment, outcome) {
+            Some(adjustment_set) => adjustment_set,
+            None => return Ok(None),
+        };
+        let set = adjustment_set
+            .iter()
+            .map(|name| self.node_map[name])
+            .collect::<Vec<NodeId>>();
+        let treatment = self.causal_node(treatment);
+        let outcome = self.causal_node(outcome);
+        let treatment_probability =
+            |t: &T| self.assignment_probability(&[(treatment, t.clone())].into_iter().collect());
+        let treated = treatment_probability(value);
+        if treated <= 0.0 {
+            return Err(Error::Query(format!(
+                "treatment value {:?} has zero probability",
+                value
+            )));
+        }
+        let mediator_domains = set
+            .iter()
+            .map(|id| self.get_node_domain(*id))
+            .collect::<Vec<Vec<T>>>();
+        let mut distribution = Vec::new();
+        for state in self.get_node_domain(outcome) {
+            let mut prob = 0.0;
+            for m in configurations(&mediator_domains) {
+                let mut assignment = set
+                    .iter()
+                    .copied()
+                    .zip(m.iter().cloned())
+                    .collect::<HashMap<NodeId, T>>();
+                assignment.insert(treatment, value.clone());
+                let mediated = self.assignment_probability(&assignment) / treated;
+                if mediated <= 0.0 {
+                    continue;
+                }
+                let mut inner = 0.0;
+                for t in self.get_node_domain(treatment) {
+                    let marginal = treatment_probability(&t);
+                    if marginal <= 0.0 {
+                        continue;
+                    }
+                    assignment.insert(treatment, t.clone());
+                    assignment.remove(&outcome);
+                    let joint = self.assignment_probability(&assignment);
+                    if joint <= 0.0 {
+                        return Err(Error::Query(format!(
+                            "positivity does not hold: treatment value {:?} has zero probability with mediator values {:?}",
+                            t, m
+                        )));
+                    }
+                    assignment.insert(outcome, state.clone());
+                    inner += self.assignment_probability(&assignment) / joint * marginal;
+                }
+                prob += mediated * inner;
+            }
+            distribution.push((state, prob));
+        }
+        Ok(Some(CausalEffect {
+            distribution,
+            criterion: Criterion::FrontDoor,
+            adjustment_set,
+        }))
+    }
+
+    /// Compute P(outcome | do(treatment = value)) by backdoor adjustment, or else by the
+    /// front-door formula. Returns `None` if the effect is identified by neither, and the
+    /// error of `backdoor_effect` or `frontdoor_effect` if positivity does not hold for the
+    /// formula used.
+    pub fn causal_effect(
+        &self,
+        treatment: &str,
+        value: &T,
+        outcome: &str,
+    ) -> Result<Option<CausalEffect<T>>, Error> {
+        match self.backdoor_effect(treatment, value, outcome)? {
+            Some(effect) => Ok(Some(effect)),
+            None => self.frontdoor_effect(treatment, value, outcome),
+        }
+    }
+
+    /// Whether the mediators `set` satisfy the front-door criterion for the effect of
+    /// `treatment` on `outcome`.
+    pub(crate) fn is_frontdoor_set(
+        &self,
+        treatment: NodeId,
+        outcome: NodeId,
+        set: &[NodeId],
+    ) -> bool {
+        // Every directed path from the treatment to the outcome goes through the set.
+        let mut reached = vec![false; self.nodes.len()];
+        let mut stack = vec![treatment];
+        while let Some(id) = stack.pop() {
+            if !reached[id] && !set.contains(&id) {
+                reached[id] = true;
+                stack.extend(&self.nodes[id].children);
+            }
+        }
+        if reached[outcome] {
+            return false;
+        }
+        let connected = self.d_connected(&[treatment], &[], &[treatment]);
+        if set.iter().any(|id| connected.contains(id)) {
+            return false;
+        }
+        !self.d_connected(set, &[treatment], set).contains(&outcome)
+    }
+
+    /// Whether `set` satisfies the backdoor criterion for the effect of `treatment` on
+    /// `outcome`.
+    pub(crate) fn is_backdoor_set(
+        &self,
+        treatment: NodeId,
+        outcome: NodeId,
+        set: &[NodeId],
+    ) -> bool {
+        let descendants = self.descendants(&[treatment]);
+        !set.iter().any(|id| descendants.contains(id))
+            && !self
+                .d_connected(&[treatment], set, &[treatment])
+                .contains(&outcome)
+    }
+
+    /// The probability of the nodes taking the given values.
+    pub(crate) fn assignment_probability(&self, assignment: &HashMap<NodeId, T>) -> Probability {
+        let domains = self
+            .nodes
+            .iter()
+            .map(|node| match assignment.get(&node.id) {
+                Some(value) => vec![value.clone()],
+                None => self.get_node_domain(node.id),
+            })
+            .collect::<Vec<Vec<T>>>();
+        self.eliminate(&domains, false).0
+    }
+
+    /// The given nodes and their ancestors, in node order.
+    pub(crate) fn ancestors(&self, ids: &[NodeId]) -> Vec<NodeId> {
+        self.closure(ids, |id| &self.nodes[id].parents)
+    }
+
+    /// The given nodes and their descendants, in node order.
+    pub(crate) fn descendants(&self, ids: &[NodeId]) -> Vec<NodeId> {
+        self.closure(ids, |id| &self.nodes[id].children)
+    }
+
+    fn closure<'a, F: Fn(NodeId) -> &'a Vec<NodeId>>(
+        &'a self,
+        ids: &[NodeId],
+        next: F,
+    ) -> Vec<NodeId> {
+        let mut reached = vec![false; self.nodes.len()];
+        let mut stack = ids.to_vec();
+        while let Some(id) = stack.pop() {
+            if !reached[id] {
+                reached[id] = true;
+                stack.extend(next(id));
+            }
+        }
+        (0..self.nodes.len()).filter(|id| reached[*id]).collect()
+    }
+
+    /// The nodes d-connected to `sources` given `given`, ignoring the edges out of the nodes
+    /// in `cut`, by the Bayes ball algorithm. Nodes in `given` are never d-connected.
+    pub(crate) fn d_connected(
+        &self,
+        sources: &[NodeId],
+        given: &[NodeId],
+        cut: &[NodeId],
+    ) -> Vec<NodeId> {
+        let parents = |id: NodeId| {
+            self.nodes[id]
+                .parents
+                .iter()
+                .copied()
+                .filter(|parent| !cut.contains(parent))
+                .collect::<Vec<NodeId>>()
+        };
+        let children = |id: NodeId| {
+            if cut.contains(&id) {
+                Vec::new()
+            } else {
+                self.nodes[id].children.clone()
+            }
+        };
+        // The given nodes and their ancestors, where colliders let the ball through.
+        let mut opens_collider = vec![false; self.nodes.len()];
+        let mut stack = given.to_vec();
+        while let Some(id) = stack.pop() {
+            if !opens_collider[id] {
+                opens_collider[id] = true;
+                stack.extend(parents(id));
+            }
+        }
+
+        // Visits are (node, whether the ball arrived from a child and travels up).
+        let mut visited = vec![[false; 2]; self.nodes.len()];
+        let mut reached = vec![false; self.nodes.len()];
+        let mut stack = sources
+            .iter()
+            .map(|id| (*id, true))
+            .collect::<Vec<(NodeId, bool)>>();
+        while let Some((id, up)) = stack.pop() {
+            if visited[id][up as usize] {
+                continue;
+            }
+            visited[id][up as usize] = true;
+            let observed = given.contains(&id);
+            if !observed {
+                reached[id] = true;
+            }
+            if up && !observed {
+                stack.extend(parents(id).into_iter().map(|parent| (parent, true)));
+                stack.extend(children(id).into_iter().map(|child| (child, false)));
+            } else if !up {
+                if !observed {
+                    stack.extend(children(id).into_iter().map(|child| (child, false)));
+                }
+                if opens_collider[id] {
+                    stack.extend(parents(id).into_iter().map(|parent| (parent, true)));
+                }
+            }
+        }
+        (0..self.nodes.len())
+            .filter(|id| reached[*id] && !sources.contains(id))
+            .collect()
+    }
+
+    /// The node of a causal query.
+    pub(crate) fn causal_node(&self, name: &str) -> NodeId {
+        match self.node_map.get(name) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", name),
+        }
+    }
+
+    pub(crate) fn node_names_of(&self, ids: &[NodeId]) -> Vec<Name> {
+        ids.iter()
+            .map(|id| self.get_node_name(*id).to_string())
+            .collect()
+    }
+}
+
+/// The subsets of `items` of the given size, keeping their order.
+pub(crate) fn subsets(items: &[NodeId], size: usize) -> Vec<Vec<NodeId>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    if items.len() < size {
+        return Vec::new();
+    }
+    let mut with_first = subsets(&items[1..], size - 1);
+    for subset in &mut with_first {
+        subset.insert(0, items[0]);
+    }
+    with_first.extend(subsets(&items[1..], size));
+    with_first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, brute_force_probability, confounded, row, TOLERANCE};
+
+    /// A hidden `u` confounds `x` and `y`, and `x` affects `y` only through `m`.
+    fn frontdoor(m_given_x: [Probability; 2]) -> BayesianNetwork<bool> {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("u", NodeType::Root(row(0.3)));
+        network.add_node("x", NodeType::Inner);
+        network.add_node("m", NodeType::Inner);
+        network.add_node("y", NodeType::Leaf);
+        network.add_dependency(
+            vec!["u"],
+            "x",
+            HashMap::from([(vec![true], row(0.8)), (vec![false], row(0.2))]),
+        );
+        network.add_dependency(
+            vec!["x"],
+            "m",
+            HashMap::from([
+                (vec![true], row(m_given_x[0])),
+                (vec![false], row(m_given_x[1])),
+            ]),
+        );
+        network.add_dependency(
+            vec!["u", "m"],
+            "y",
+            HashMap::from([
+                (vec![true, true], row(0.9)),
+                (vec![true, false], row(0.7)),
+                (vec![false, true], row(0.4)),
+                (vec![false, false], row(0.1)),
+            ]),
+        );
+        network.set_node_hidden("u", true);
+        network
+    }
+
+    #[test]
+    fn infer_do_cuts_the_treatment_from_its_causes() {
+        let network = confounded();
+        let interventions = HashMap::from([("x", true)]);
+        let probabilities = network.infer_do(&interventions, &HashMap::new());
+        // P(y | do(x)) = sum over z of P(z) P(y | z, x), unlike P(y | x).
+        let expected = 0.4 * 0.9 + 0.6 * 0.5;
+        assert_close(
+            network.get_inferred_probability(&probabilities, "y", true),
+            expected,
+            TOLERANCE,
+        );
+        assert_close(
+            network.get_inferred_probability(&probabilities, "z", true),
+            0.4,
+            TOLERANCE,
+        );
+        let conditioned = brute_force_probability(&network, "y", &true, &interventions);
+        assert!((conditioned - expected).abs() > 0.01);
+    }
+
+    #[test]
+    fn backdoor_effect_matches_intervention() {
+        let network = confounded();
+        assert_eq!(network.backdoor_set("x", "y"), Some(vec!["z".to_string()]));
+        for value in [true, false] {
+            let effect = network.backdoor_effect("x", &value, "y").unwrap().unwrap();
+            assert_eq!(effect.criterion, Criterion::Backdoor);
+            let probabilities = network.infer_do(&HashMap::from([("x", value)]), &HashMap::new());
+            for (state, prob) in effect.distribution {
+                assert_close(
+                    prob,
+                    network.get_inferred_probability(&probabilities, "y", state),
+                    TOLERANCE,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn backdoor_effect_needs_an_observed_adjustment_set() {
+        let network = frontdoor([0.9, 0.2]);
+        assert_eq!(network.backdoor_set("x", "y"), None);
+        assert!(network.backdoor_effect("x", &true, "y").unwrap().is_none());
+    }
+
+    #[test]
+    fn backdoor_effect_reports_positivity_violations() {
+        // Like `confounded`, but `x` is never true when `z` is false.
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("z", NodeType::Root(row(0.4)));
+        network.add_node("x", NodeType::Inner);
+        network.add_node("y", NodeType::Leaf);
+        network.add_dependency(
+            vec!["z"],
+            "x",
+            HashMap::from([(vec![true], row(0.8)), (vec![false], row(0.0))]),
+        );
+        network.add_dependency(
+            vec!["z", "x"],
+            "y",
+            HashMap::from([
+                (vec![true, true], row(0.9)),
+                (vec![true, false], row(0.6)),
+                (vec![false, true], row(0.5)),
+                (vec![false, false], row(0.1)),
+            ]),
+        );
+        assert!(matches!(
+            network.backdoor_effect("x", &true, "y"),
+            Err(Error::Query(_))
+        ));
+        assert!(matches!(
+            network.causal_effect("x", &true, "y"),
+            Err(Error::Query(_))
+        ));
+        assert!(network.backdoor_effect("x", &false, "y").is_ok());
+    }
+
+    #[test]
+    fn frontdoor_effect_matches_intervention() {
+        let network = frontdoor([0.75, 0.15]);
+        assert_eq!(network.backdoor_set("x", "y"), None);
+        for value in [true, false] {
+            let effect = network.frontdoor_effect("x", &value, "y").unwrap().unwrap();
+            assert_eq!(effect.criterion, Criterion::FrontDoor);
+            assert_eq!(effect.adjustment_set, vec!["m"]);
+            let intervened = network.infer_do(&HashMap::from([("x", value)]), &HashMap::new());
+            for (state, prob) in effect.distribution {
+                assert_close(
+                    prob,
+                    network.get_inferred_probability(&intervened, "y", state),
+                    TOLERANCE,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn frontdoor_effect_reports_positivity_violations() {
+        let network = frontdoor([1.0, 0.0]);
+        assert!(matches!(
+            network.frontdoor_effect("x", &true, "y"),
+            Err(Error::Query(_))
+        ));
+    }
+}
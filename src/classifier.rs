@@ -0,0 +1,234 @@
+use crate::bayesian_network::{BayesianNetwork, NodeType, Probability};
+use crate::dataset::Dataset;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+/// The prediction of a classifier for one record.
+#[derive(Clone, Debug)]
+pub struct Prediction<T> {
+    /// The most probable class.
+    pub class: T,
+    /// The posterior probability of every class.
+    pub posterior: HashMap<T, Probability>,
+}
+
+/// Evaluation metrics of predictions against the true classes.
+#[derive(Clone, Debug)]
+pub struct Evaluation<T> {
+    /// The fraction of records whose predicted class is the true class.
+    pub accuracy: f64,
+    /// The number of records for each `(true class, predicted class)` pair.
+    pub confusion_matrix: HashMap<(T, T), usize>,
+    /// The mean negative natural log of the posterior probability of the true class.
+    pub log_loss: f64,
+}
+
+/// The predictions for every record of a dataset.
+#[derive(Clone, Debug)]
+pub struct BatchPrediction<T> {
+    /// The prediction for each record, in dataset order.
+    pub predictions: Vec<Prediction<T>>,
+    /// The evaluation metrics, present when the dataset contains the class column.
+    pub evaluation: Option<Evaluation<T>>,
+}
+
+/// A classifier predicting the value of a class node from feature evidence.
+pub struct Classifier<T: Clone + PartialEq + Eq + Hash + Debug> {
+    network: BayesianNetwork<T>,
+    target: String,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> Classifier<T> {
+    /// Create a classifier predicting the node `target` of `network`.
+    pub fn new(network: BayesianNetwork<T>, target: &str) -> Classifier<T> {
+        if !network.node_map.contains_key(target) {
+            panic!("Class node `{}` not found", target);
+        }
+        Classifier {
+            network,
+            target: target.to_string(),
+        }
+    }
+
+    /// Build a naive Bayes classifier from a dataset: the `target` column becomes a root node
+    /// with every other column as a leaf child, and all CPTs are estimated with `pseudo_count`
+    /// smoothing. The value space is every value occurring in the dataset.
+    pub fn naive_bayes(
+        dataset: &Dataset<T>,
+        target: &str,
+        pseudo_count: Probability,
+    ) -> Classifier<T> {
+        if dataset.column_index(target).is_none() {
+            panic!("Class column `{}` not found", target);
+        }
+        let mut value_space = Vec::new();
+        for row in dataset.rows() {
+            for value in row {
+                if !value_space.contains(value) {
+                    value_space.push(value.clone());
+                }
+            }
+        }
+        let uniform = 1.0 / value_space.len() as Probability;
+        let prior = value_space
+            .iter()
+            .map(|value| (value.clone(), uniform))
+            .collect();
+        let mut network = BayesianNetwork::new(value_space);
+        network.add_node(target, NodeType::Root(prior));
+        for feature in dataset.columns() {
+            if feature != target {
+                network.add_node(feature, NodeType::Leaf);
+                network.add_dependency(vec![target], feature, HashMap::new());
+            }
+        }
+        network.fit(dataset, pseudo_count);
+        Classifier::new(network, target)
+    }
+
+    /// Get the underlying network.
+    pub fn network(&self) -> &BayesianNetwork<T> {
+        &self.network
+    }
+
+    /// Get the name of the class node.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Predict the class given the observed features.
+    pub fn predict(&self, features: &HashMap<&str, T>) -> Prediction<T> {
+        self.network.predict(&self.target, features)
+    }
+
+    /// Predict the class of every record of a dataset.
+    pub fn predict_batch(&self, dataset: &Dataset<T>) -> BatchPrediction<T> {
+        self.network.predict_batch(dataset, &self.target)
+    }
+}
+
+/// Smallest probability used when computing the log loss, so a confident mistake is finite.
+const LOG_LOSS_EPSILON: Probability = 1e-15;
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Predict the value of `target` given the observed features.
+    pub fn predict(&self, target: &str, features: &HashMap<&str, T>) -> Prediction<T> {
+        if !self.node_map.contains_key(target) {
+            panic!("Class node `{}` not found", target);
+        }
+        if features.contains_key(target) {
+            panic!("Features contain the class node `{}`", target);
+        }
+        let inferred_probabilities = self.infer(features);
+        let posterior = inferred_probabilities[self.get_node_index(target)].clone();
+        let class = self
+            .value_space
+            .iter()
+            .max_by(|a, b| posterior[*a].total_cmp(&posterior[*b]))
+            .unwrap()
+            .clone();
+        Prediction { class, posterior }
+    }
+
+    /// Predict `target` for every record of `dataset`, using every other column as evidence.
+    /// When the dataset contains the `target` column, the predictions are also evaluated.
+    pub fn predict_batch(&self, dataset: &Dataset<T>, target: &str) -> BatchPrediction<T> {
+        for column in dataset.columns() {
+            if !self.node_map.contains_key(column) {
+                panic!("Dataset column `{}` not found in network", column);
+            }
+        }
+        let target_column = dataset.column_index(target);
+        let predictions = (0..dataset.len())
+            .map(|index| {
+                let mut features = dataset.record(index);
+                features.remove(target);
+                self.predict(target, &features)
+            })
+            .collect::<Vec<Prediction<T>>>();
+        let evaluation = target_column.map(|column| {
+            let mut correct = 0;
+            let mut confusion_matrix = HashMap::new();
+            let mut log_loss = 0.0;
+            for (row, prediction) in dataset.rows().iter().zip(&predictions) {
+                let actual = &row[column];
+                if *actual == prediction.class {
+                    correct += 1;
+                }
+                *confusion_matrix
+                    .entry((actual.clone(), prediction.class.clone()))
+                    .or_insert(0) += 1;
+                log_loss -= prediction.posterior[actual].max(LOG_LOSS_EPSILON).ln();
+            }
+            Evaluation {
+                accuracy: correct as f64 / dataset.len() as f64,
+                confusion_matrix,
+                log_loss: log_loss / dataset.len() as f64,
+            }
+        });
+        BatchPrediction {
+            predictions,
+            evaluation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, TOLERANCE};
+
+    fn messages() -> Dataset<&'static str> {
+        let mut dataset = Dataset::new(vec!["class", "offer", "link"]);
+        for row in [
+            ["spam", "yes", "yes"],
+            ["spam", "yes", "no"],
+            ["ham", "no", "no"],
+            ["ham", "no", "yes"],
+            ["ham", "no", "no"],
+        ] {
+            dataset.add_row(row.to_vec());
+        }
+        dataset
+    }
+
+    #[test]
+    fn naive_bayes_predicts_the_smoothed_posterior() {
+        let classifier = Classifier::naive_bayes(&messages(), "class", 1.0);
+        assert_eq!(classifier.target(), "class");
+        let prediction = classifier.predict(&HashMap::from([("offer", "yes"), ("link", "yes")]));
+        // P(class) = (3/7, 4/7), P(offer | class) = (3/4, 1/5), P(link | class) = (2/4, 2/5).
+        let spam = 3.0 / 7.0 * 0.75 * 0.5;
+        let ham = 4.0 / 7.0 * 0.2 * 0.4;
+        assert_eq!(prediction.class, "spam");
+        assert_close(prediction.posterior["spam"], spam / (spam + ham), TOLERANCE);
+        assert_close(prediction.posterior["ham"], ham / (spam + ham), TOLERANCE);
+        assert_close(prediction.posterior["yes"], 0.0, TOLERANCE);
+    }
+
+    #[test]
+    fn predict_batch_evaluates_against_the_class_column() {
+        let dataset = messages();
+        let classifier = Classifier::naive_bayes(&dataset, "class", 1.0);
+        let batch = classifier.predict_batch(&dataset);
+        let evaluation = batch.evaluation.unwrap();
+        assert_eq!(evaluation.accuracy, 1.0);
+        assert_eq!(
+            evaluation.confusion_matrix,
+            HashMap::from([(("spam", "spam"), 2), (("ham", "ham"), 3)])
+        );
+        let log_loss = dataset
+            .rows()
+            .iter()
+            .zip(&batch.predictions)
+            .map(|(row, prediction)| -prediction.posterior[row[0]].ln())
+            .sum::<f64>()
+            / dataset.len() as f64;
+        assert_close(evaluation.log_loss, log_loss, TOLERANCE);
+
+        let mut unlabeled = Dataset::new(vec!["offer"]);
+        unlabeled.add_row(vec!["no"]);
+        let batch = classifier.predict_batch(&unlabeled);
+        assert!(batch.evaluation.is_none());
+        assert_eq!(batch.predictions[0].class, "ham");
+    }
+}
@@ -0,0 +1,253 @@
+use std::{fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name, NodeId};
+
+/// A greedy rule for choosing the next node to eliminate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EliminationHeuristic {
+    /// The node whose elimination adds the fewest edges between its neighbours.
+    MinFill,
+    /// The node with the fewest neighbours.
+    MinDegree,
+}
+
+/// How costly exact inference on a network is, as returned by `complexity_report`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComplexityReport {
+    /// The heuristic whose order gives the smallest total table size.
+    pub heuristic: EliminationHeuristic,
+    pub elimination_order: Vec<Name>,
+    /// The size of the largest clique formed by the order, minus one: an upper bound on the
+    /// treewidth of the moral graph.
+    pub induced_width: usize,
+    /// The number of entries of the largest table formed by the order.
+    pub largest_table: f64,
+    /// The number of entries of all the tables formed by the order, which exact inference
+    /// takes time and memory in proportion to.
+    pub total_table_size: f64,
+    /// Whether the network is a polytree, so that `infer` is exact.
+    pub polytree: bool,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get an order in which to eliminate every node, chosen greedily by `heuristic` on the
+    /// moral graph, with ties broken by node order.
+    pub fn elimination_order(&self, heuristic: EliminationHeuristic) -> Vec<Name> {
+        self.node_names_of(&self.greedy_order(heuristic))
+    }
+
+    /// Get the induced width of an elimination order of every node: the size of the largest
+    /// clique it forms in the moral graph, minus one.
+    pub fn induced_width(&self, order: &[&str]) -> usize {
+        let order = self.order_ids(order);
+        self.elimination_cliques(&order)
+            .iter()
+            .map(|clique| clique.len() - 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Estimate the cost of exact inference from the best of the min-fill and min-degree
+    /// elimination orders, to tell whether it is feasible before running it.
+    pub fn complexity_report(&self) -> ComplexityReport {
+        let sizes = (0..self.nodes.len())
+            .map(|id| self.get_node_domain(id).len() as f64)
+            .collect::<Vec<f64>>();
+        let table_size = |clique: &Vec<NodeId>| clique.iter().map(|id| sizes[*id]).product();
+        let (heuristic, order, cliques, total_table_size) = [
+            EliminationHeuristic::MinFill,
+            EliminationHeuristic::MinDegree,
+        ]
+        .into_iter()
+        .map(|heuristic| {
+            let order = self.greedy_order(heuristic);
+            let cliques = self.elimination_cliques(&order);
+            let total = cliques.iter().map(table_size).sum::<f64>();
+            (heuristic, order, cliques, total)
+        })
+        .min_by(|a, b| a.3.total_cmp(&b.3))
+        .unwrap();
+        let components = self.component_indices();
+        let count = components.iter().max().map_or(0, |max| max + 1);
+        let edges = self
+            .nodes
+            .iter()
+            .map(|node| node.parents.len())
+            .sum::<usize>();
+        ComplexityReport {
+            heuristic,
+            elimination_order: self.node_names_of(&order),
+            induced_width: cliques
+                .iter()
+                .map(|clique| clique.len() - 1)
+                .max()
+                .unwrap_or(0),
+            largest_table: cliques.iter().map(table_size).fold(0.0, f64::max),
+            total_table_size,
+            polytree: edges + count == self.nodes.len(),
+        }
+    }
+
+    /// The moral graph as an adjacency matrix: each node linked to its parents, its children
+    /// and the other parents of its children.
+    pub(crate) fn moral_adjacency(&self) -> Vec<Vec<bool>> {
+        let n = self.nodes.len();
+        let mut adjacent = vec![vec![false; n]; n];
+        for node in &self.nodes {
+            for (i, parent) in node.parents.iter().enumerate() {
+                adjacent[*parent][node.id] = true;
+                adjacent[node.id][*parent] = true;
+                for other in &node.parents[i + 1..] {
+                    adjacent[*parent][*other] = true;
+                    adjacent[*other][*parent] = true;
+                }
+            }
+        }
+        adjacent
+    }
+
+    /// The cliques formed by eliminating the nodes of the moral graph in `order`: each node
+    /// with its neighbours not yet eliminated, in node order.
+    pub(crate) fn elimination_cliques(&self, order: &[NodeId]) -> Vec<Vec<NodeId>> {
+        self.fill_in(order).0
+    }
+
+    /// The cliques formed by eliminating the nodes of the moral graph in `order`, with the
+    /// adjacency matrix of the moral graph plus the edges added along the way.
+    pub(crate) fn fill_in(&self, order: &[NodeId]) -> (Vec<Vec<NodeId>>, Vec<Vec<bool>>) {
+        let mut adjacent = self.moral_adjacency();
+        let mut eliminated = vec![false; self.nodes.len()];
+        let mut cliques = Vec::new();
+        for id in order {
+            let mut clique = neighbours(&adjacent, &eliminated, *id);
+            connect(&mut adjacent, &clique);
+            eliminated[*id] = true;
+            clique.push(*id);
+            clique.sort();
+            cliques.push(clique);
+        }
+        (cliques, adjacent)
+    }
+
+    /// A greedy elimination order of every node by `heuristic`.
+    pub(crate) fn greedy_order(&self, heuristic: EliminationHeuristic) -> Vec<NodeId> {
+        let mut adjacent = self.moral_adjacency();
+        let mut eliminated = vec![false; self.nodes.len()];
+        let mut order = Vec::new();
+        for _ in 0..self.nodes.len() {
+            let cost = |id: NodeId| {
+                let neighbours = neighbours(&adjacent, &eliminated, id);
+                let fill = neighbours
+                    .iter()
+                    .enumerate()
+                    .map(|(i, a)| {
+                        neighbours[i + 1..]
+                            .iter()
+                            .filter(|b| !adjacent[*a][**b])
+                            .count()
+                    })
+                    .sum::<usize>();
+                match heuristic {
+                    EliminationHeuristic::MinFill => (fill, neighbours.len()),
+                    EliminationHeuristic::MinDegree => (neighbours.len(), fill),
+                }
+            };
+            let id = (0..self.nodes.len())
+                .filter(|id| !eliminated[*id])
+                .min_by_key(|id| cost(*id))
+                .unwrap();
+            let neighbours = neighbours(&adjacent, &eliminated, id);
+            connect(&mut adjacent, &neighbours);
+            eliminated[id] = true;
+            order.push(id);
+        }
+        order
+    }
+
+    /// The ids of an order naming every node exactly once.
+    pub(crate) fn order_ids(&self, order: &[&str]) -> Vec<NodeId> {
+        let ids = order
+            .iter()
+            .map(|name| self.causal_node(name))
+            .collect::<Vec<NodeId>>();
+        let mut seen = vec![false; self.nodes.len()];
+        for id in &ids {
+            if seen[*id] {
+                panic!(
+                    "Node `{}` appears twice in the elimination order",
+                    self.get_node_name(*id)
+                );
+            }
+            seen[*id] = true;
+        }
+        if let Some(missing) = (0..self.nodes.len()).find(|id| !seen[*id]) {
+            panic!(
+                "Node `{}` is missing from the elimination order",
+                self.get_node_name(missing)
+            );
+        }
+        ids
+    }
+}
+
+/// The neighbours of `id` not yet eliminated, in node order.
+fn neighbours(adjacent: &[Vec<bool>], eliminated: &[bool], id: NodeId) -> Vec<NodeId> {
+    (0..adjacent.len())
+        .filter(|other| adjacent[id][*other] && !eliminated[*other])
+        .collect()
+}
+
+/// Link every pair of `nodes`.
+fn connect(adjacent: &mut [Vec<bool>], nodes: &[NodeId]) {
+    for a in nodes {
+        for b in nodes {
+            if a != b {
+                adjacent[*a][*b] = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{graph, sprinkler};
+
+    fn star() -> BayesianNetwork<bool> {
+        graph(
+            &["h", "l1", "l2", "l3", "l4"],
+            &[("h", "l1"), ("h", "l2"), ("h", "l3"), ("h", "l4")],
+        )
+    }
+
+    #[test]
+    fn induced_width_depends_on_the_order() {
+        let network = star();
+        assert_eq!(network.induced_width(&["h", "l1", "l2", "l3", "l4"]), 4);
+        assert_eq!(network.induced_width(&["l1", "l2", "l3", "l4", "h"]), 1);
+        // Moralizing the sprinkler network links the sprinkler and the rain, so the cloud,
+        // the sprinkler and the rain form a clique.
+        let network = sprinkler();
+        assert_eq!(
+            network.induced_width(&["wet_grass", "cloudy", "sprinkler", "rain"]),
+            2
+        );
+    }
+
+    #[test]
+    fn complexity_report_eliminates_the_leaves_of_a_star_first() {
+        let report = star().complexity_report();
+        assert_eq!(report.heuristic, EliminationHeuristic::MinFill);
+        assert_eq!(report.elimination_order, ["l1", "l2", "l3", "h", "l4"]);
+        assert_eq!(report.induced_width, 1);
+        assert_eq!(report.largest_table, 4.0);
+        // Four cliques of the hub and a leaf, then the last leaf on its own.
+        assert_eq!(report.total_table_size, 18.0);
+        assert!(report.polytree);
+
+        let report = sprinkler().complexity_report();
+        assert_eq!(report.induced_width, 2);
+        assert_eq!(report.largest_table, 8.0);
+        assert!(!report.polytree);
+    }
+}
@@ -0,0 +1,209 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability};
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the connected components of the network, ignoring edge direction: the sets of
+    /// nodes linked by paths, each in node order and ordered by their first node. Nodes in
+    /// different components are independent whatever the evidence.
+    pub fn connected_components(&self) -> Vec<Vec<Name>> {
+        let components = self.component_indices();
+        let count = components.iter().max().map_or(0, |max| max + 1);
+        (0..count)
+            .map(|component| {
+                let ids = (0..self.nodes.len())
+                    .filter(|id| components[*id] == component)
+                    .collect::<Vec<NodeId>>();
+                self.node_names_of(&ids)
+            })
+            .collect()
+    }
+
+    /// Split the network into one network per connected component, in the order of
+    /// `connected_components`. Each keeps the CPTs, states, metadata and shared CPT ties of
+    /// its nodes.
+    pub fn split_components(&self) -> Vec<BayesianNetwork<T>> {
+        let components = self.component_indices();
+        let count = components.iter().max().map_or(0, |max| max + 1);
+        (0..count)
+            .map(|component| {
+                let ids = (0..self.nodes.len())
+                    .filter(|id| components[*id] == component)
+                    .collect::<Vec<NodeId>>();
+                self.subnetwork(&ids, &HashMap::new())
+            })
+            .collect()
+    }
+
+    /// A network of the given nodes, whose parents must all be among them except for the
+    /// nodes in `fixed`, which become roots certain to take their value. It keeps the CPTs,
+    /// states, metadata and shared CPT ties of the other nodes.
+    pub(crate) fn subnetwork(
+        &self,
+        ids: &[NodeId],
+        fixed: &HashMap<NodeId, T>,
+    ) -> BayesianNetwork<T> {
+        let mut network = BayesianNetwork::new(self.value_space.clone());
+        network.normalization = self.normalization;
+        for id in ids {
+            let node = &self.nodes[*id];
+            let name = self.get_node_name(*id);
+            let node_type = match fixed.get(id) {
+                Some(value) => NodeType::Root(
+                    self.get_node_domain(*id)
+                        .into_iter()
+                        .map(|state| {
+                            let prob = if state == *value { 1.0 } else { 0.0 };
+                            (state, prob)
+                        })
+                        .collect(),
+                ),
+                None => node.node_type.clone(),
+            };
+            network.add_node(name, node_type);
+            if let Some(states) = &node.states {
+                network.set_node_states(name, states.clone());
+            }
+            for (key, value) in &node.metadata {
+                network.set_node_metadata(name, key, value);
+            }
+        }
+        for id in ids.iter().filter(|id| !fixed.contains_key(id)) {
+            let node = &self.nodes[*id];
+            let name = self.get_node_name(*id);
+            if !node.parents.is_empty() {
+                network.add_dependency(
+                    node.parents
+                        .iter()
+                        .map(|parent| self.get_node_name(*parent))
+                        .collect(),
+                    name,
+                    node.probability.clone(),
+                );
+            }
+            if let Some(cpt) = &node.shared_cpt {
+                if !network.shared_cpts.contains_key(cpt) {
+                    network
+                        .shared_cpts
+                        .insert(cpt.clone(), self.shared_cpts[cpt].clone());
+                }
+                network.tie_node(name, cpt);
+            }
+            let copy = network.node_map[name];
+            network.nodes[copy].dirichlet = node.dirichlet.clone();
+            network.nodes[copy].compact_cpt = node.compact_cpt.clone();
+        }
+        network
+    }
+
+    /// The index of the connected component of each node, numbered in order of their first
+    /// node.
+    pub(crate) fn component_indices(&self) -> Vec<usize> {
+        let mut components = vec![usize::MAX; self.nodes.len()];
+        let mut count = 0;
+        for start in 0..self.nodes.len() {
+            if components[start] != usize::MAX {
+                continue;
+            }
+            let mut stack = vec![start];
+            components[start] = count;
+            while let Some(id) = stack.pop() {
+                let node = &self.nodes[id];
+                for next in node.parents.iter().chain(&node.children) {
+                    if components[*next] == usize::MAX {
+                        components[*next] = count;
+                        stack.push(*next);
+                    }
+                }
+            }
+            count += 1;
+        }
+        components
+    }
+
+    /// The likelihood weight of a draw within each component: the probability of the
+    /// evidence on its nodes given the rest of the draw.
+    pub(crate) fn component_weights(
+        &self,
+        assignment: &HashMap<NodeId, T>,
+        evidence: &HashMap<NodeId, T>,
+        components: &[usize],
+    ) -> Vec<Probability> {
+        let count = components.iter().max().map_or(0, |max| max + 1);
+        let mut weights = vec![1.0; count];
+        for (id, value) in evidence {
+            weights[components[*id]] *= self.local_probability(&self.nodes[*id], value, assignment);
+        }
+        weights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sampling::{seeded_rng, PosteriorSampler},
+        testing::{assert_close, brute_force_probability, row, sprinkler, TOLERANCE},
+    };
+
+    /// The sprinkler network next to an unrelated coin and its reported flip.
+    fn two_components() -> BayesianNetwork<bool> {
+        let mut network = sprinkler();
+        network.add_node("coin", NodeType::Root(row(0.3)));
+        network.add_node("flip", NodeType::Leaf);
+        network.add_dependency(
+            vec!["coin"],
+            "flip",
+            HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.2))]),
+        );
+        network
+    }
+
+    #[test]
+    fn split_components_keeps_the_marginals() {
+        let network = two_components();
+        assert_eq!(
+            network.connected_components(),
+            [
+                vec!["cloudy", "sprinkler", "rain", "wet_grass"],
+                vec!["coin", "flip"]
+            ]
+        );
+        let parts = network.split_components();
+        assert_eq!(parts.len(), 2);
+        let evidence = HashMap::from([("wet_grass", true)]);
+        assert_close(
+            brute_force_probability(&parts[0], "rain", &true, &evidence),
+            brute_force_probability(&network, "rain", &true, &evidence),
+            TOLERANCE,
+        );
+        let evidence = HashMap::from([("flip", true)]);
+        assert_close(
+            brute_force_probability(&parts[1], "coin", &true, &evidence),
+            brute_force_probability(&network, "coin", &true, &evidence),
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn posterior_samples_weigh_each_component_by_its_own_evidence() {
+        let network = two_components();
+        let evidence = HashMap::from([("wet_grass", true), ("flip", false)]);
+        let dataset = network.sample_posterior_with_rng(
+            &evidence,
+            20_000,
+            &PosteriorSampler::LikelihoodWeighting { particles: 20_000 },
+            &mut seeded_rng(2),
+        );
+        for name in ["rain", "coin"] {
+            let column = dataset.column_index(name).unwrap();
+            let frequency = dataset.rows().iter().filter(|row| row[column]).count() as f64
+                / dataset.len() as f64;
+            assert_close(
+                frequency,
+                brute_force_probability(&network, name, &true, &evidence),
+                0.02,
+            );
+        }
+    }
+}
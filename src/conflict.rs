@@ -0,0 +1,169 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId},
+    causal::subsets,
+};
+
+/// The conflict among some of the findings, as listed by `conflict_report`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FindingConflict<T> {
+    /// The findings, in node order.
+    pub findings: Vec<(Name, T)>,
+    /// Their conflict measure, as computed by `conflict`.
+    pub conflict: f64,
+}
+
+/// The conflict of a set of findings, with the subsets of findings responsible for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConflictReport<T> {
+    /// The conflict measure of all the findings.
+    pub conflict: f64,
+    /// The subsets of at least two findings, the most conflicting first.
+    pub subsets: Vec<FindingConflict<T>>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get Jensen's conflict measure of `evidence`: the natural log of the product of the
+    /// probabilities of the findings over the probability of them all together. A positive
+    /// value means the findings are less likely together than apart, so some may be wrong.
+    /// It is `f64::INFINITY` when they cannot occur together, including when a single
+    /// finding is impossible on its own.
+    pub fn conflict(&self, evidence: &HashMap<&str, T>) -> f64 {
+        let separately = evidence
+            .iter()
+            .map(|(name, value)| self.log_likelihood(&HashMap::from([(*name, value.clone())])))
+            .sum::<f64>();
+        if separately == f64::NEG_INFINITY {
+            return f64::INFINITY;
+        }
+        separately - self.log_likelihood(evidence)
+    }
+
+    /// Get the conflict of `evidence` and of each subset of at most `max_size` of its
+    /// findings, to locate the ones that clash. The number of subsets grows exponentially
+    /// with `max_size`.
+    pub fn conflict_report(
+        &self,
+        evidence: &HashMap<&str, T>,
+        max_size: usize,
+    ) -> ConflictReport<T> {
+        let mut ids = evidence
+            .keys()
+            .map(|name| self.causal_node(name))
+            .collect::<Vec<NodeId>>();
+        ids.sort();
+        let mut report = ConflictReport {
+            conflict: self.conflict(evidence),
+            subsets: Vec::new(),
+        };
+        for size in 2..=max_size.min(ids.len()) {
+            for subset in subsets(&ids, size) {
+                let findings = subset
+                    .iter()
+                    .map(|id| {
+                        let name = self.get_node_name(*id);
+                        (name.to_string(), evidence[name].clone())
+                    })
+                    .collect::<Vec<(Name, T)>>();
+                let conflict = self.conflict(
+                    &findings
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.clone()))
+                        .collect(),
+                );
+                report.subsets.push(FindingConflict { findings, conflict });
+            }
+        }
+        report
+            .subsets
+            .sort_by(|a, b| b.conflict.total_cmp(&a.conflict));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bayesian_network::NodeType,
+        testing::{assert_close, evidence_probability, row, sprinkler, TOLERANCE},
+    };
+
+    /// Jensen's conflict measure, by enumeration.
+    fn brute_force_conflict(
+        network: &BayesianNetwork<bool>,
+        evidence: &HashMap<&str, bool>,
+    ) -> f64 {
+        let separately = evidence
+            .iter()
+            .map(|(name, value)| evidence_probability(network, &HashMap::from([(*name, *value)])))
+            .product::<f64>();
+        (separately / evidence_probability(network, evidence)).ln()
+    }
+
+    #[test]
+    fn conflict_matches_enumeration() {
+        let network = sprinkler();
+        let evidence = HashMap::from([("sprinkler", true), ("rain", true), ("wet_grass", false)]);
+        let conflict = network.conflict(&evidence);
+        assert_close(
+            conflict,
+            brute_force_conflict(&network, &evidence),
+            TOLERANCE,
+        );
+        // Grass that stays dry under both the sprinkler and the rain is suspicious.
+        assert!(conflict > 0.0);
+        // Wet grass without either is impossible.
+        let impossible =
+            HashMap::from([("sprinkler", false), ("rain", false), ("wet_grass", true)]);
+        assert_eq!(network.conflict(&impossible), f64::INFINITY);
+    }
+
+    #[test]
+    fn an_impossible_finding_is_an_infinite_conflict() {
+        // Always cloudy, so a clear sky is impossible even on its own.
+        let mut network = sprinkler();
+        let cloudy = network.get_node_index("cloudy");
+        network.nodes[cloudy].node_type = NodeType::Root(row(1.0));
+        let evidence = HashMap::from([("cloudy", false), ("rain", true)]);
+        assert_eq!(network.conflict(&evidence), f64::INFINITY);
+        // The report types are named from the crate root.
+        let report: crate::ConflictReport<bool> = network.conflict_report(&evidence, 2);
+        assert_eq!(report.conflict, f64::INFINITY);
+        assert_eq!(report.subsets[0].conflict, f64::INFINITY);
+    }
+
+    #[test]
+    fn conflict_report_ranks_the_subsets() {
+        let network = sprinkler();
+        let evidence = HashMap::from([("sprinkler", true), ("rain", true), ("wet_grass", false)]);
+        let report = network.conflict_report(&evidence, 2);
+        assert_close(report.conflict, network.conflict(&evidence), TOLERANCE);
+        assert_eq!(report.subsets.len(), 3);
+        for subset in &report.subsets {
+            let findings = subset
+                .findings
+                .iter()
+                .map(|(name, value)| (name.as_str(), *value))
+                .collect::<HashMap<&str, bool>>();
+            assert_eq!(findings.len(), 2);
+            assert_close(
+                subset.conflict,
+                brute_force_conflict(&network, &findings),
+                TOLERANCE,
+            );
+        }
+        assert!(report
+            .subsets
+            .windows(2)
+            .all(|pair| pair[0].conflict >= pair[1].conflict));
+        // The sprinkler and the rain clash less with each other than with the dry grass.
+        let pair = &report.subsets[2];
+        assert_eq!(
+            pair.findings,
+            [("sprinkler".to_string(), true), ("rain".to_string(), true)]
+        );
+        assert_eq!(network.conflict_report(&evidence, 3).subsets.len(), 4);
+    }
+}
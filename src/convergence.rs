@@ -0,0 +1,300 @@
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    dataset::Dataset,
+};
+
+/// R-hat below which a state is considered to have mixed.
+const R_HAT_THRESHOLD: f64 = 1.01;
+
+/// Convergence statistics of the indicator of one node taking one state, across the chains
+/// of a `GibbsRun`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Convergence<T> {
+    pub node: Name,
+    pub state: T,
+    /// The estimated posterior probability, over all chains.
+    pub mean: Probability,
+    /// The estimated posterior probability within each chain.
+    pub chain_means: Vec<Probability>,
+    /// The number of independent draws the chains are worth for estimating `mean`.
+    pub effective_sample_size: f64,
+    /// The split Gelman-Rubin statistic: close to 1.0 when the chains agree, larger when
+    /// they have not mixed.
+    pub r_hat: f64,
+}
+
+/// The samples of several Gibbs chains, as returned by `sample_gibbs`, with convergence
+/// statistics for every state of every non-evidence node.
+#[derive(Clone, Debug)]
+pub struct GibbsRun<T> {
+    /// The kept samples of each chain, one column per node in node order.
+    pub chains: Vec<Dataset<T>>,
+    pub convergence: Vec<Convergence<T>>,
+}
+
+impl<T: Clone> GibbsRun<T> {
+    /// Get the samples of all chains together.
+    pub fn samples(&self) -> Dataset<T> {
+        let columns = self.chains[0]
+            .columns()
+            .iter()
+            .map(|name| name.as_str())
+            .collect();
+        let mut samples = Dataset::new(columns);
+        for chain in &self.chains {
+            for row in chain.rows() {
+                samples.add_row(row.clone());
+            }
+        }
+        samples
+    }
+
+    /// Get the largest R-hat over all states.
+    pub fn max_r_hat(&self) -> f64 {
+        self.convergence
+            .iter()
+            .map(|convergence| convergence.r_hat)
+            .fold(1.0, f64::max)
+    }
+
+    /// Get the smallest effective sample size over all states.
+    pub fn min_effective_sample_size(&self) -> f64 {
+        self.convergence
+            .iter()
+            .map(|convergence| convergence.effective_sample_size)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Whether every R-hat is below 1.01, the usual sign that the chains have mixed.
+    pub fn has_converged(&self) -> bool {
+        self.max_r_hat() < R_HAT_THRESHOLD
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Run `chains` independent Gibbs chains given `evidence`, each started from its own
+    /// random state and keeping `n` samples after discarding `burn_in` sweeps and then
+    /// keeping every `thin`-th sweep, and report how well they have mixed.
+    pub fn sample_gibbs(
+        &self,
+        evidence: &HashMap<&str, T>,
+        n: usize,
+        chains: usize,
+        burn_in: usize,
+        thin: usize,
+    ) -> GibbsRun<T> {
+        self.sample_gibbs_with_rng(evidence, n, chains, burn_in, thin, &mut rand::thread_rng())
+    }
+
+    /// Run Gibbs chains like `sample_gibbs`, using `rng`.
+    pub fn sample_gibbs_with_rng<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<&str, T>,
+        n: usize,
+        chains: usize,
+        burn_in: usize,
+        thin: usize,
+        rng: &mut R,
+    ) -> GibbsRun<T> {
+        if chains == 0 || n < 4 {
+            panic!("Convergence diagnostics need at least one chain of four samples");
+        }
+        let evidence = self.evidence_ids(evidence);
+        let samples = (0..chains)
+            .map(|_| self.gibbs_chain(&evidence, n, burn_in, thin, rng))
+            .collect::<Vec<Vec<HashMap<NodeId, T>>>>();
+
+        let mut convergence = Vec::new();
+        for node in self
+            .nodes
+            .iter()
+            .filter(|node| !evidence.contains_key(&node.id))
+        {
+            for state in self.get_node_domain(node.id) {
+                let traces = samples
+                    .iter()
+                    .map(|chain| {
+                        chain
+                            .iter()
+                            .map(|assignment| (assignment[&node.id] == state) as u8 as f64)
+                            .collect()
+                    })
+                    .collect::<Vec<Vec<f64>>>();
+                let chain_means = traces.iter().map(|trace| mean(trace)).collect::<Vec<f64>>();
+                let (effective_sample_size, r_hat) = mixing(&split(&traces));
+                convergence.push(Convergence {
+                    node: self.get_node_name(node.id).to_string(),
+                    state,
+                    mean: mean(&chain_means),
+                    chain_means,
+                    effective_sample_size,
+                    r_hat,
+                });
+            }
+        }
+
+        let chains = samples
+            .iter()
+            .map(|chain| {
+                let mut dataset = Dataset::new(self.node_names());
+                for assignment in chain {
+                    dataset.add_row(self.assignment_row(assignment));
+                }
+                dataset
+            })
+            .collect();
+        GibbsRun {
+            chains,
+            convergence,
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Split each trace into its first and second halves, so that a chain still drifting
+/// disagrees with itself.
+pub(crate) fn split(traces: &[Vec<f64>]) -> Vec<&[f64]> {
+    traces
+        .iter()
+        .flat_map(|trace| {
+            let half = trace.len() / 2;
+            [&trace[..half], &trace[trace.len() - half..]]
+        })
+        .collect()
+}
+
+/// The effective sample size and R-hat of equally long traces: R-hat compares the variance
+/// between and within the traces, and the effective sample size sums their autocorrelations
+/// over pairs of lags while the pairs stay positive (Geyer's initial positive sequence).
+pub(crate) fn mixing(traces: &[&[f64]]) -> (f64, f64) {
+    let m = traces.len() as f64;
+    let length = traces[0].len();
+    let l = length as f64;
+    let means = traces.iter().map(|trace| mean(trace)).collect::<Vec<f64>>();
+    let grand_mean = mean(&means);
+    let within = traces
+        .iter()
+        .zip(&means)
+        .map(|(trace, mean)| trace.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (l - 1.0))
+        .sum::<f64>()
+        / m;
+    let between = if m > 1.0 {
+        l * means
+            .iter()
+            .map(|mean| (mean - grand_mean).powi(2))
+            .sum::<f64>()
+            / (m - 1.0)
+    } else {
+        0.0
+    };
+    let variance = (l - 1.0) / l * within + between / l;
+    let total = m * l;
+    if within <= 0.0 {
+        // Every trace is constant: mixed if they are all equal, stuck otherwise.
+        return if between <= 0.0 {
+            (total, 1.0)
+        } else {
+            (m, f64::INFINITY)
+        };
+    }
+    let r_hat = (variance / within).sqrt();
+
+    let autocorrelation = |lag: usize| {
+        let autocovariance = traces
+            .iter()
+            .zip(&means)
+            .map(|(trace, mean)| {
+                (0..length - lag)
+                    .map(|i| (trace[i] - mean) * (trace[i + lag] - mean))
+                    .sum::<f64>()
+                    / l
+            })
+            .sum::<f64>()
+            / m;
+        1.0 - (within - autocovariance) / variance
+    };
+    let mut tau = -1.0;
+    let mut lag = 0;
+    while lag + 1 < length {
+        let pair = autocorrelation(lag) + autocorrelation(lag + 1);
+        if pair <= 0.0 {
+            break;
+        }
+        tau += 2.0 * pair;
+        lag += 2;
+    }
+    // As in Stan, antithetic chains are credited with at most `total * log10(total)` draws.
+    (total / tau.max(1.0 / total.log10()), r_hat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, brute_force_probability, confounded, TOLERANCE};
+
+    #[test]
+    fn split_halves_each_trace() {
+        let traces = vec![
+            vec![1.0, 2.0, 3.0, 4.0, 5.0],
+            vec![6.0, 7.0, 8.0, 9.0, 10.0],
+        ];
+        assert_eq!(
+            split(&traces),
+            vec![
+                &[1.0, 2.0][..],
+                &[4.0, 5.0][..],
+                &[6.0, 7.0][..],
+                &[9.0, 10.0][..],
+            ]
+        );
+    }
+
+    #[test]
+    fn mixing_of_hand_computed_traces() {
+        // Within-trace variance 1/3 and no variance between the traces.
+        let (ess, r_hat) = mixing(&[&[0.0, 1.0, 0.0, 1.0], &[1.0, 0.0, 1.0, 0.0]]);
+        assert_close(r_hat, 0.75_f64.sqrt(), TOLERANCE);
+        // Alternating traces are antithetic, so the effective sample size is capped.
+        assert_close(ess, 8.0 * 8.0_f64.log10(), TOLERANCE);
+
+        assert_eq!(mixing(&[&[1.0, 1.0], &[1.0, 1.0]]), (4.0, 1.0));
+        assert_eq!(mixing(&[&[1.0, 1.0], &[0.0, 0.0]]), (2.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn chains_agree_with_enumeration_and_converge() {
+        let network = confounded();
+        let evidence = HashMap::from([("y", true)]);
+        let run = network.sample_gibbs_with_rng(&evidence, 5_000, 4, 100, 1, &mut seeded_rng(1));
+        assert_eq!(run.chains.len(), 4);
+        assert_eq!(run.samples().len(), 20_000);
+        // Two states for each of the two free nodes.
+        assert_eq!(run.convergence.len(), 4);
+        let z = run
+            .convergence
+            .iter()
+            .find(|convergence| convergence.node == "z" && convergence.state)
+            .unwrap();
+        assert_eq!(z.chain_means.len(), 4);
+        assert_close(
+            z.mean,
+            brute_force_probability(&network, "z", &true, &evidence),
+            0.02,
+        );
+        assert!(run.has_converged(), "R-hat {}", run.max_r_hat());
+        assert!(run.min_effective_sample_size() > 1_000.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Convergence diagnostics need at least one chain of four samples")]
+    fn short_chains_panic() {
+        confounded().sample_gibbs_with_rng(&HashMap::new(), 3, 2, 0, 1, &mut seeded_rng(2));
+    }
+}
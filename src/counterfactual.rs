@@ -0,0 +1,210 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability},
+    learning::configurations,
+};
+
+/// The name of the counterfactual copy of a node in a twin network.
+pub fn counterfactual_name(name: &str) -> Name {
+    format!("{}*", name)
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Build the twin network of the interventions `do(X = x)`: every node has a factual copy
+    /// under its own name and a counterfactual copy named by `counterfactual_name`, in which
+    /// the interventions are made. The copies share their exogenous noise, modelled as an
+    /// independent draw from each CPT row: a counterfactual node whose parents take the same
+    /// values as in the factual world takes the same value as its factual copy, and otherwise
+    /// follows its CPT row.
+    pub fn twin_network(&self, interventions: &HashMap<&str, T>) -> BayesianNetwork<T> {
+        for (name, value) in interventions {
+            if !self.node_map.contains_key(*name) {
+                panic!("Node `{}` not found", name);
+            }
+            if !self.value_space.contains(value) {
+                panic!("Intervention value {:?} not in value space", value);
+            }
+        }
+        let order = self.topological_order();
+        if order.len() < self.nodes.len() {
+            panic!("Cannot build the twin network of a cyclic network");
+        }
+        let mut twin = BayesianNetwork::new(self.value_space.clone());
+        twin.normalization = self.normalization;
+        let copy_type = |id: NodeId| match &self.nodes[id].node_type {
+            NodeType::Root(prior) => NodeType::Root(prior.clone()),
+            _ => NodeType::Inner,
+        };
+        for id in &order {
+            let name = self.get_node_name(*id);
+            twin.add_node(name, copy_type(*id));
+            if !self.nodes[*id].parents.is_empty() {
+                let parents = self.nodes[*id]
+                    .parents
+                    .iter()
+                    .map(|parent| self.get_node_name(*parent))
+                    .collect::<Vec<&str>>();
+                twin.add_dependency(parents, name, self.nodes[*id].probability.clone());
+                let copy = twin.node_map[name];
+                twin.nodes[copy].compact_cpt = self.nodes[*id].compact_cpt.clone();
+            }
+            if let Some(states) = &self.nodes[*id].states {
+                twin.set_node_states(name, states.clone());
+            }
+        }
+        for id in &order {
+            let name = self.get_node_name(*id);
+            let copy = counterfactual_name(name);
+            if let Some(value) = interventions.get(name) {
+                twin.add_node(
+                    &copy,
+                    NodeType::Root(
+                        self.value_space
+                            .iter()
+                            .map(|other| (other.clone(), if other == value { 1.0 } else { 0.0 }))
+                            .collect(),
+                    ),
+                );
+            } else {
+                twin.add_node(&copy, NodeType::Inner);
+                let parents = &self.nodes[*id].parents;
+                let domain = self.get_node_domain(*id);
+                let parent_domains = parents
+                    .iter()
+                    .map(|parent| self.get_node_domain(*parent))
+                    .collect::<Vec<Vec<T>>>();
+                let configurations = configurations(&parent_domains);
+                // Keyed by the counterfactual parent values, the factual value and the
+                // factual parent values.
+                let mut probability = HashMap::new();
+                for counterfactual in &configurations {
+                    for value in &domain {
+                        for factual in &configurations {
+                            let row = if counterfactual == factual {
+                                self.value_space
+                                    .iter()
+                                    .map(|other| {
+                                        (other.clone(), if other == value { 1.0 } else { 0.0 })
+                                    })
+                                    .collect::<HashMap<T, Probability>>()
+                            } else {
+                                self.row(*id, counterfactual)
+                            };
+                            let mut key = counterfactual.clone();
+                            key.push(value.clone());
+                            key.extend(factual.iter().cloned());
+                            probability.insert(key, row);
+                        }
+                    }
+                }
+                let parent_names = parents
+                    .iter()
+                    .map(|parent| counterfactual_name(self.get_node_name(*parent)))
+                    .chain(std::iter::once(name.to_string()))
+                    .chain(
+                        parents
+                            .iter()
+                            .map(|parent| self.get_node_name(*parent).to_string()),
+                    )
+                    .collect::<Vec<Name>>();
+                twin.add_dependency(
+                    parent_names.iter().map(|name| name.as_str()).collect(),
+                    &copy,
+                    probability,
+                );
+            }
+            if let Some(states) = &self.nodes[*id].states {
+                twin.set_node_states(&copy, states.clone());
+            }
+        }
+        twin
+    }
+
+    /// Get the distribution of `query` in the counterfactual world where the interventions
+    /// `do(X = x)` had been made, given `evidence` observed in the factual world, by
+    /// inference on the twin network.
+    pub fn counterfactual(
+        &self,
+        evidence: &HashMap<&str, T>,
+        interventions: &HashMap<&str, T>,
+        query: &str,
+    ) -> Vec<(T, Probability)> {
+        let id = match self.node_map.get(query) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", query),
+        };
+        let twin = self.twin_network(interventions);
+        let mut assignment = twin
+            .evidence_ids(evidence)
+            .into_iter()
+            .collect::<HashMap<NodeId, T>>();
+        let total = twin.assignment_probability(&assignment);
+        if total <= 0.0 {
+            panic!("Evidence has zero probability");
+        }
+        let target = twin.node_map[&counterfactual_name(query)];
+        self.get_node_domain(id)
+            .into_iter()
+            .map(|value| {
+                assignment.insert(target, value.clone());
+                let prob = twin.assignment_probability(&assignment) / total;
+                (value, prob)
+            })
+            .collect()
+    }
+
+    /// The CPT row of a node for the given parent values, with every value of the value
+    /// space.
+    fn row(&self, id: NodeId, parent_values: &[T]) -> HashMap<T, Probability> {
+        let node = &self.nodes[id];
+        self.value_space
+            .iter()
+            .map(|value| {
+                let prob = match &node.node_type {
+                    NodeType::Root(prior) => prior.get(value).copied().unwrap_or(0.0),
+                    _ => self.cpt_probability(node, parent_values, value),
+                };
+                (value.clone(), prob)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, confounded, TOLERANCE};
+
+    #[test]
+    fn twin_network_copies_every_node_and_fixes_the_interventions() {
+        let twin = confounded().twin_network(&HashMap::from([("x", true)]));
+        assert_eq!(twin.nodes.len(), 6);
+        let x = twin.get_node_index("x*");
+        assert!(twin.nodes[x].parents.is_empty());
+        assert!(matches!(&twin.nodes[x].node_type, NodeType::Root(prior) if prior[&true] == 1.0));
+        let y = twin.get_node_index("y*");
+        assert_eq!(
+            twin.node_names_of(&twin.nodes[y].parents),
+            ["z*", "x*", "y", "z", "x"]
+        );
+    }
+
+    #[test]
+    fn counterfactual_shares_the_noise_of_the_factual_world() {
+        let network = confounded();
+        let evidence = HashMap::from([("x", false), ("y", false)]);
+        // Without interventions, the counterfactual world is the factual one.
+        let same = network.counterfactual(&evidence, &HashMap::new(), "y");
+        assert_eq!(same, [(true, 0.0), (false, 1.0)]);
+        // Had x been true, y follows its row for x = true under the posterior of z, which
+        // is .4 * .2 * .4 = .032 against .6 * .7 * .9 = .378 in the factual world.
+        let result = network.counterfactual(&evidence, &HashMap::from([("x", true)]), "y");
+        let expected = (0.032 * 0.9 + 0.378 * 0.5) / 0.41;
+        assert_close(result[0].1, expected, TOLERANCE);
+        assert_close(result[1].1, 1.0 - expected, TOLERANCE);
+        // The confounder keeps its factual value.
+        let z = network.counterfactual(&evidence, &HashMap::from([("x", true)]), "z");
+        assert_close(z[0].1, 0.032 / 0.41, TOLERANCE);
+    }
+}
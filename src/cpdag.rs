@@ -0,0 +1,249 @@
+use rand::Rng;
+use std::hash::Hash;
+
+use crate::{
+    bayesian_network::Name,
+    dataset::Dataset,
+    learning::{chow_liu, hill_climb, Edge},
+};
+
+/// A completed partially directed acyclic graph: the Markov equivalence class of a DAG, with
+/// the edges whose direction every member shares directed and the others undirected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cpdag {
+    nodes: Vec<Name>,
+    directed: Vec<Edge>,
+    undirected: Vec<Edge>,
+}
+
+impl Cpdag {
+    /// Get the equivalence class of the DAG with the given edges: its skeleton, with its
+    /// v-structures and the edges they compel directed. Its nodes are those named by an
+    /// edge, so nodes without edges are left out.
+    pub fn from_edges(edges: &[Edge]) -> Cpdag {
+        let mut nodes = edges
+            .iter()
+            .flat_map(|(parent, child)| [parent.clone(), child.clone()])
+            .collect::<Vec<Name>>();
+        nodes.sort();
+        nodes.dedup();
+        let n = nodes.len();
+        let index = |name: &Name| nodes.binary_search(name).unwrap();
+        let mut parents = vec![vec![false; n]; n];
+        for (parent, child) in edges {
+            parents[index(child)][index(parent)] = true;
+        }
+        let adjacent = |a: usize, b: usize| parents[a][b] || parents[b][a];
+
+        // `directed[a][b]` is an edge a -> b, and `undirected[a][b]` an edge a - b.
+        let mut directed = vec![vec![false; n]; n];
+        let mut undirected = vec![vec![false; n]; n];
+        for child in 0..n {
+            for parent in 0..n {
+                if !parents[child][parent] {
+                    continue;
+                }
+                let collider = (0..n).any(|other| {
+                    other != parent && parents[child][other] && !adjacent(parent, other)
+                });
+                if collider {
+                    directed[parent][child] = true;
+                } else {
+                    undirected[parent][child] = true;
+                    undirected[child][parent] = true;
+                }
+            }
+        }
+
+        // Meek's rules, until no undirected edge can be oriented.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for a in 0..n {
+                for b in 0..n {
+                    if !undirected[a][b] {
+                        continue;
+                    }
+                    // R1: c -> a - b with c and b not adjacent.
+                    let r1 = (0..n).any(|c| directed[c][a] && !adjacent(c, b) && c != b);
+                    // R2: a -> c -> b.
+                    let r2 = (0..n).any(|c| directed[a][c] && directed[c][b]);
+                    // R3: a - c -> b and a - d -> b with c and d not adjacent.
+                    let r3 = (0..n).any(|c| {
+                        undirected[a][c]
+                            && directed[c][b]
+                            && (0..n).any(|d| {
+                                d != c && undirected[a][d] && directed[d][b] && !adjacent(c, d)
+                            })
+                    });
+                    if r1 || r2 || r3 {
+                        undirected[a][b] = false;
+                        undirected[b][a] = false;
+                        directed[a][b] = true;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        let mut cpdag = Cpdag {
+            nodes: nodes.clone(),
+            directed: Vec::new(),
+            undirected: Vec::new(),
+        };
+        for a in 0..n {
+            for b in 0..n {
+                if directed[a][b] {
+                    cpdag.directed.push((nodes[a].clone(), nodes[b].clone()));
+                } else if undirected[a][b] && a < b {
+                    cpdag.undirected.push((nodes[a].clone(), nodes[b].clone()));
+                }
+            }
+        }
+        cpdag
+    }
+
+    /// Get the names of the nodes, in order.
+    pub fn nodes(&self) -> &[Name] {
+        &self.nodes
+    }
+
+    /// Get the edges directed in every DAG of the class.
+    pub fn directed(&self) -> &[Edge] {
+        &self.directed
+    }
+
+    /// Get the edges whose direction differs between DAGs of the class, each as a pair of
+    /// names in order.
+    pub fn undirected(&self) -> &[Edge] {
+        &self.undirected
+    }
+
+    /// Get the edges of every DAG of the class. The search goes through every orientation of
+    /// the undirected edges, so its cost grows exponentially with their number.
+    pub fn dags(&self) -> Vec<Vec<Edge>> {
+        let mut dags = Vec::new();
+        for mask in 0..(1usize << self.undirected.len()) {
+            let mut edges = self.directed.clone();
+            for (i, (a, b)) in self.undirected.iter().enumerate() {
+                if mask & (1 << i) == 0 {
+                    edges.push((a.clone(), b.clone()));
+                } else {
+                    edges.push((b.clone(), a.clone()));
+                }
+            }
+            if is_acyclic(&self.nodes, &edges) && Cpdag::from_edges(&edges) == *self {
+                edges.sort();
+                dags.push(edges);
+            }
+        }
+        dags
+    }
+
+    /// Draw the edges of a DAG of the class uniformly at random.
+    pub fn sample_dag(&self) -> Vec<Edge> {
+        self.sample_dag_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Draw a DAG of the class like `sample_dag`, with the given random number generator.
+    pub fn sample_dag_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<Edge> {
+        let mut dags = self.dags();
+        let index = rng.gen_range(0..dags.len());
+        dags.swap_remove(index)
+    }
+}
+
+/// Whether the edges between the nodes form no directed cycle.
+fn is_acyclic(nodes: &[Name], edges: &[Edge]) -> bool {
+    let index = |name: &Name| nodes.iter().position(|node| node == name).unwrap();
+    let mut indegree = vec![0; nodes.len()];
+    for (_, child) in edges {
+        indegree[index(child)] += 1;
+    }
+    let mut ready = (0..nodes.len())
+        .filter(|id| indegree[*id] == 0)
+        .collect::<Vec<usize>>();
+    let mut visited = 0;
+    while let Some(id) = ready.pop() {
+        visited += 1;
+        for (parent, child) in edges {
+            if index(parent) == id {
+                let child = index(child);
+                indegree[child] -= 1;
+                if indegree[child] == 0 {
+                    ready.push(child);
+                }
+            }
+        }
+    }
+    visited == nodes.len()
+}
+
+/// Learn the equivalence class of the Chow-Liu tree of `dataset`.
+pub fn chow_liu_cpdag<T: Clone + PartialEq + Eq + Hash>(dataset: &Dataset<T>) -> Cpdag {
+    Cpdag::from_edges(&chow_liu(dataset))
+}
+
+/// Learn the equivalence class of the structure found by `hill_climb`, since the BDeu score
+/// cannot tell apart the DAGs of a class.
+pub fn hill_climb_cpdag<T: Clone + PartialEq + Eq + Hash>(
+    dataset: &Dataset<T>,
+    ess: f64,
+    max_parents: usize,
+) -> Cpdag {
+    Cpdag::from_edges(&hill_climb(dataset, ess, max_parents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded_rng;
+
+    fn edges(pairs: &[(&str, &str)]) -> Vec<Edge> {
+        pairs
+            .iter()
+            .map(|(parent, child)| (parent.to_string(), child.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn a_chain_is_undirected_with_three_dags() {
+        let cpdag = Cpdag::from_edges(&edges(&[("a", "b"), ("b", "c")]));
+        assert_eq!(cpdag.nodes(), ["a", "b", "c"]);
+        assert!(cpdag.directed().is_empty());
+        assert_eq!(cpdag.undirected(), edges(&[("a", "b"), ("b", "c")]));
+        let dags = cpdag.dags();
+        assert_eq!(
+            dags,
+            [
+                edges(&[("a", "b"), ("b", "c")]),
+                edges(&[("b", "a"), ("b", "c")]),
+                edges(&[("b", "a"), ("c", "b")]),
+            ]
+        );
+        // Reversing the chain stays in the class.
+        assert_eq!(Cpdag::from_edges(&edges(&[("c", "b"), ("b", "a")])), cpdag);
+        assert!(dags.contains(&cpdag.sample_dag_with_rng(&mut seeded_rng(2))));
+    }
+
+    #[test]
+    fn a_v_structure_and_the_edges_it_compels_stay_directed() {
+        let collider = Cpdag::from_edges(&edges(&[("a", "c"), ("b", "c")]));
+        assert_eq!(collider.directed(), edges(&[("a", "c"), ("b", "c")]));
+        assert!(collider.undirected().is_empty());
+        assert_eq!(collider.dags(), [edges(&[("a", "c"), ("b", "c")])]);
+
+        // Meek's first rule directs c -> d away from the collider.
+        let compelled = Cpdag::from_edges(&edges(&[("a", "c"), ("b", "c"), ("c", "d")]));
+        assert_eq!(
+            compelled.directed(),
+            edges(&[("a", "c"), ("b", "c"), ("c", "d")])
+        );
+        assert_eq!(compelled.dags().len(), 1);
+
+        // A shielded collider is no v-structure.
+        let triangle = Cpdag::from_edges(&edges(&[("a", "b"), ("a", "c"), ("b", "c")]));
+        assert!(triangle.directed().is_empty());
+        assert_eq!(triangle.dags().len(), 6);
+    }
+}
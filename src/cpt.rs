@@ -0,0 +1,828 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash, sync::Arc};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Probability},
+    cpt_tree::CptTree,
+    learning::configurations,
+    normalization::Normalization,
+    softmax::SoftmaxCpd,
+};
+
+/// A compact description of a CPT. `add_dependency_spec` keeps functions and trees in their
+/// compact form and expands the other kinds into the full table over the parent values.
+#[derive(Clone, Debug)]
+pub enum CptSpec<T> {
+    /// The full table, as given to `add_dependency`.
+    Table(HashMap<Vec<T>, HashMap<T, Probability>>),
+    /// The same distribution of the child whatever the parent values.
+    Constant(HashMap<T, Probability>),
+    /// A noisy-OR over binary causes: the child is `on` unless the leak and every parent
+    /// that is `on` independently fail to cause it, parent `i` causing it with probability
+    /// `weights[i]`. Parent values other than `on` count as absent.
+    NoisyOr {
+        on: T,
+        off: T,
+        leak: Probability,
+        weights: Vec<Probability>,
+    },
+    /// A noisy-MAX over graded states, lowest first: each parent independently pushes the
+    /// child up to some level, `causes[i][v]` being the distribution of the level parent `i`
+    /// causes when it takes value `v`, and `leak` that of the level reached with no cause.
+    /// The child takes the highest level caused. Parent values without a distribution cause
+    /// the lowest level.
+    NoisyMax {
+        states: Vec<T>,
+        leak: Vec<Probability>,
+        causes: Vec<HashMap<T, Vec<Probability>>>,
+    },
+    /// A deterministic node, whose value is a function of its parent values.
+    Function(CptFunction<T>),
+    /// A decision tree over the parent values.
+    Tree(CptTree<T>),
+    /// A softmax of weights for each parent value.
+    Softmax(SoftmaxCpd<T>),
+    /// A CPT that depends only on how many parents take `value`: `rows[k]` is the
+    /// distribution of the child when `k` of them do, the last row covering any larger count.
+    Count {
+        value: T,
+        rows: Vec<HashMap<T, Probability>>,
+    },
+    /// A CPT that depends only on whether at least `at_least` parents take `value`, or all
+    /// of them if `at_least` is `None`, with the distribution `above` if so and `below` if not.
+    Threshold {
+        value: T,
+        at_least: Option<usize>,
+        above: HashMap<T, Probability>,
+        below: HashMap<T, Probability>,
+    },
+}
+
+/// How `add_dependency_with_default` fills the rows missing from a partial CPT.
+#[derive(Clone, Debug)]
+pub enum DefaultRow<T> {
+    /// Use the given distribution.
+    Distribution(HashMap<T, Probability>),
+    /// Use the uniform distribution over the declared states of the child, or over the
+    /// value space if it has none.
+    Uniform,
+}
+
+/// The function of a deterministic node, mapping parent values in order to the child value.
+#[derive(Clone)]
+pub struct CptFunction<T>(Arc<Function<T>>);
+
+type Function<T> = dyn Fn(&[T]) -> T + Send + Sync;
+
+impl<T> CptFunction<T> {
+    /// The child value for the parent values, in parent order.
+    pub(crate) fn evaluate(&self, parent_values: &[T]) -> T {
+        (self.0)(parent_values)
+    }
+}
+
+impl<T> Debug for CptFunction<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CptFunction")
+    }
+}
+
+/// A CPT stored in compact form on its node and evaluated row by row when needed, instead of
+/// as a table.
+#[derive(Clone, Debug)]
+pub(crate) enum CompactCpt<T> {
+    /// A deterministic node: each row gives probability 1.0 to the value of the function.
+    Function(CptFunction<T>),
+    /// A decision tree, each row being the leaf the parent values reach.
+    Tree(CptTree<T>),
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> CompactCpt<T> {
+    /// The probability of `value` given the parent values, in parent order.
+    pub(crate) fn probability(&self, parent_values: &[T], value: &T) -> Probability {
+        match self {
+            CompactCpt::Function(CptFunction(function)) => {
+                if function(parent_values) == *value {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            CompactCpt::Tree(tree) => tree
+                .distribution(parent_values)
+                .get(value)
+                .copied()
+                .unwrap_or(0.0),
+        }
+    }
+}
+
+impl CptSpec<bool> {
+    /// A noisy-OR over boolean causes, with `leak` the probability that the child is true
+    /// with no cause present and `per_parent_probs[i]` the probability that parent `i` alone
+    /// makes it true.
+    pub fn noisy_or(leak: Probability, per_parent_probs: Vec<Probability>) -> CptSpec<bool> {
+        CptSpec::noisy_or_with(true, false, leak, per_parent_probs)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> CptSpec<T> {
+    /// The uniform distribution over `states` in every row.
+    pub fn uniform(states: Vec<T>) -> CptSpec<T> {
+        if states.is_empty() {
+            panic!("Uniform CPT has no states");
+        }
+        let prob = 1.0 / states.len() as Probability;
+        CptSpec::Constant(states.into_iter().map(|value| (value, prob)).collect())
+    }
+
+    /// A child that always takes `value`.
+    pub fn delta(value: T) -> CptSpec<T> {
+        let mut prob = HashMap::new();
+        prob.insert(value, 1.0);
+        CptSpec::Constant(prob)
+    }
+
+    /// A child that copies the value of its single parent.
+    pub fn identity() -> CptSpec<T> {
+        CptSpec::function(|parent_values: &[T]| {
+            if parent_values.len() != 1 {
+                panic!(
+                    "Identity CPT needs exactly one parent, not {}",
+                    parent_values.len()
+                );
+            }
+            parent_values[0].clone()
+        })
+    }
+
+    /// The distribution of a spec that does not depend on any parent, for use as the prior
+    /// of a root node, as in `NodeType::Root(CptSpec::uniform(states).prior())`.
+    pub fn prior(&self) -> HashMap<T, Probability> {
+        self.table(&[]).remove(&Vec::new()).unwrap()
+    }
+
+    /// A noisy-OR whose present and absent values are `on` and `off`, which must differ.
+    pub fn noisy_or_with(
+        on: T,
+        off: T,
+        leak: Probability,
+        per_parent_probs: Vec<Probability>,
+    ) -> CptSpec<T> {
+        if on == off {
+            panic!("Noisy-OR present and absent values are both {:?}", on);
+        }
+        for prob in per_parent_probs.iter().chain([&leak]) {
+            if !(0.0..=1.0).contains(prob) {
+                panic!("Noisy-OR probability {} is not between 0 and 1", prob);
+            }
+        }
+        CptSpec::NoisyOr {
+            on,
+            off,
+            leak,
+            weights: per_parent_probs,
+        }
+    }
+
+    /// A noisy-MAX over the child levels `states`, lowest first, with a distribution over the
+    /// levels for the leak and for each value of each parent that causes a rise. Each
+    /// distribution must have one probability per level and sum to 1.
+    pub fn noisy_max(
+        states: Vec<T>,
+        leak: Vec<Probability>,
+        causes: Vec<HashMap<T, Vec<Probability>>>,
+    ) -> CptSpec<T> {
+        for distribution in causes
+            .iter()
+            .flat_map(|cause| cause.values())
+            .chain([&leak])
+        {
+            if distribution.len() != states.len() {
+                panic!(
+                    "Noisy-MAX distribution has {} probabilities for {} states",
+                    distribution.len(),
+                    states.len()
+                );
+            }
+            for prob in distribution {
+                if !(0.0..=1.0).contains(prob) {
+                    panic!("Noisy-MAX probability {} is not between 0 and 1", prob);
+                }
+            }
+            let sum = distribution.iter().sum::<Probability>();
+            if (sum - 1.0).abs() > Normalization::default().tolerance {
+                panic!("Noisy-MAX distribution {:?} sums to {}", distribution, sum);
+            }
+        }
+        CptSpec::NoisyMax {
+            states,
+            leak,
+            causes,
+        }
+    }
+
+    /// A deterministic node computed from its parent values by `function`, such as a logical
+    /// AND or a sum of coded states. The node keeps the function, and inference and sampling
+    /// evaluate it for each parent configuration they visit instead of storing a table.
+    /// Every value it returns must be one of the child's declared states, or else in the
+    /// value space, and `fit` leaves the node as it is.
+    pub fn function<F: Fn(&[T]) -> T + Send + Sync + 'static>(function: F) -> CptSpec<T> {
+        CptSpec::Function(CptFunction(Arc::new(function)))
+    }
+
+    /// A CPT chosen by the number of parents taking `value`, `rows[k]` being the distribution
+    /// of the child when `k` of them do.
+    pub fn count(value: T, rows: Vec<HashMap<T, Probability>>) -> CptSpec<T> {
+        if rows.is_empty() {
+            panic!("Count CPT has no rows");
+        }
+        CptSpec::Count { value, rows }
+    }
+
+    /// A CPT with distribution `above` when at least `m` parents take `value`, and `below`
+    /// otherwise.
+    pub fn at_least(
+        value: T,
+        m: usize,
+        above: HashMap<T, Probability>,
+        below: HashMap<T, Probability>,
+    ) -> CptSpec<T> {
+        CptSpec::Threshold {
+            value,
+            at_least: Some(m),
+            above,
+            below,
+        }
+    }
+
+    /// A CPT with distribution `above` when any parent takes `value`, and `below` otherwise.
+    pub fn any(
+        value: T,
+        above: HashMap<T, Probability>,
+        below: HashMap<T, Probability>,
+    ) -> CptSpec<T> {
+        CptSpec::at_least(value, 1, above, below)
+    }
+
+    /// A CPT with distribution `above` when every parent takes `value`, and `below`
+    /// otherwise.
+    pub fn all(
+        value: T,
+        above: HashMap<T, Probability>,
+        below: HashMap<T, Probability>,
+    ) -> CptSpec<T> {
+        CptSpec::Threshold {
+            value,
+            at_least: None,
+            above,
+            below,
+        }
+    }
+
+    /// Expand into the full table, with one row for every combination of parent values.
+    pub fn table(&self, parent_domains: &[Vec<T>]) -> HashMap<Vec<T>, HashMap<T, Probability>> {
+        match self {
+            CptSpec::Table(table) => table.clone(),
+            CptSpec::Constant(prob) => configurations(parent_domains)
+                .into_iter()
+                .map(|key| (key, prob.clone()))
+                .collect(),
+            CptSpec::NoisyOr {
+                on,
+                off,
+                leak,
+                weights,
+            } => {
+                if weights.len() != parent_domains.len() {
+                    panic!(
+                        "Noisy-OR has {} parent probabilities for {} parents",
+                        weights.len(),
+                        parent_domains.len()
+                    );
+                }
+                configurations(parent_domains)
+                    .into_iter()
+                    .map(|key| {
+                        let inhibited = key
+                            .iter()
+                            .zip(weights)
+                            .filter(|(value, _)| *value == on)
+                            .map(|(_, weight)| 1.0 - weight)
+                            .product::<Probability>()
+                            * (1.0 - leak);
+                        let mut row = HashMap::new();
+                        row.insert(on.clone(), 1.0 - inhibited);
+                        row.insert(off.clone(), inhibited);
+                        (key, row)
+                    })
+                    .collect()
+            }
+            CptSpec::NoisyMax {
+                states,
+                leak,
+                causes,
+            } => {
+                if causes.len() != parent_domains.len() {
+                    panic!(
+                        "Noisy-MAX has {} causes for {} parents",
+                        causes.len(),
+                        parent_domains.len()
+                    );
+                }
+                // The probability that the child is at most each level is the product of
+                // those of the leak and of every parent.
+                let cumulative = |distribution: &[Probability]| {
+                    distribution
+                        .iter()
+                        .scan(0.0, |total, prob| {
+                            *total += prob;
+                            Some(*total)
+                        })
+                        .collect::<Vec<Probability>>()
+                };
+                configurations(parent_domains)
+                    .into_iter()
+                    .map(|key| {
+                        let mut at_most = cumulative(leak);
+                        for (value, cause) in key.iter().zip(causes) {
+                            if let Some(distribution) = cause.get(value) {
+                                for (total, prob) in
+                                    at_most.iter_mut().zip(cumulative(distribution))
+                                {
+                                    *total *= prob;
+                                }
+                            }
+                        }
+                        let row = states
+                            .iter()
+                            .enumerate()
+                            .map(|(level, state)| {
+                                let below = if level == 0 { 0.0 } else { at_most[level - 1] };
+                                (state.clone(), (at_most[level] - below).max(0.0))
+                            })
+                            .collect();
+                        (key, row)
+                    })
+                    .collect()
+            }
+            CptSpec::Function(CptFunction(function)) => configurations(parent_domains)
+                .into_iter()
+                .map(|key| {
+                    let mut row = HashMap::new();
+                    row.insert(function(&key), 1.0);
+                    (key, row)
+                })
+                .collect(),
+            CptSpec::Tree(tree) => configurations(parent_domains)
+                .into_iter()
+                .map(|key| {
+                    let row = tree.distribution(&key).clone();
+                    (key, row)
+                })
+                .collect(),
+            CptSpec::Softmax(cpd) => configurations(parent_domains)
+                .into_iter()
+                .map(|key| {
+                    let row = cpd.distribution(&key);
+                    (key, row)
+                })
+                .collect(),
+            CptSpec::Count { value, rows } => configurations(parent_domains)
+                .into_iter()
+                .map(|key| {
+                    let count = key.iter().filter(|v| *v == value).count();
+                    let row = rows[count.min(rows.len() - 1)].clone();
+                    (key, row)
+                })
+                .collect(),
+            CptSpec::Threshold {
+                value,
+                at_least,
+                above,
+                below,
+            } => {
+                let at_least = at_least.unwrap_or(parent_domains.len());
+                configurations(parent_domains)
+                    .into_iter()
+                    .map(|key| {
+                        let count = key.iter().filter(|v| *v == value).count();
+                        let row = if count >= at_least { above } else { below };
+                        (key, row.clone())
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Add a dependency from a CPT that may leave out some parent configurations, filling
+    /// each missing row, over the declared states of each parent or else the values it can
+    /// take, with `default`.
+    pub fn add_dependency_with_default(
+        &mut self,
+        parent_names: Vec<&str>,
+        child_name: &str,
+        prob: HashMap<Vec<T>, HashMap<T, Probability>>,
+        default: DefaultRow<T>,
+    ) {
+        let child = match self.node_map.get(child_name) {
+            Some(id) => *id,
+            None => panic!("Dependency child node `{}` not found", child_name),
+        };
+        let default = match default {
+            DefaultRow::Distribution(prob) => prob,
+            DefaultRow::Uniform => {
+                let states = match &self.nodes[child].states {
+                    Some(states) => states.clone(),
+                    None => self.value_space.clone(),
+                };
+                let prob = 1.0 / states.len() as Probability;
+                states.into_iter().map(|value| (value, prob)).collect()
+            }
+        };
+        let parent_domains = self.parent_domains(&parent_names);
+        let mut prob = prob;
+        for key in configurations(&parent_domains) {
+            prob.entry(key).or_insert_with(|| default.clone());
+        }
+        self.add_dependency(parent_names, child_name, prob);
+    }
+
+    /// Add a dependency whose CPT is given by `spec`. A function or a tree is kept as it is
+    /// and evaluated for each parent configuration when needed; the other kinds are expanded
+    /// over the declared states of each parent, or else the values it can take.
+    pub fn add_dependency_spec(
+        &mut self,
+        parent_names: Vec<&str>,
+        child_name: &str,
+        spec: &CptSpec<T>,
+    ) {
+        let compact_cpt = match spec {
+            CptSpec::Function(function) => Some(CompactCpt::Function(function.clone())),
+            CptSpec::Tree(tree) => {
+                let mut tree = tree.clone();
+                for leaf in tree.leaves_mut() {
+                    self.check_row_values(leaf.keys(), child_name);
+                    for value in &self.value_space {
+                        leaf.entry(value.clone()).or_insert(0.0);
+                    }
+                    if let Err(error) = self.normalize_row(child_name, &[], leaf) {
+                        panic!("{}", error);
+                    }
+                }
+                Some(CompactCpt::Tree(tree))
+            }
+            _ => None,
+        };
+        if let Some(compact_cpt) = compact_cpt {
+            let result = self.try_add_dependency_with(
+                parent_names,
+                child_name,
+                HashMap::new(),
+                Some(compact_cpt),
+            );
+            if let Err(error) = result {
+                panic!("{}", error);
+            }
+            return;
+        }
+        let parent_domains = self.parent_domains(&parent_names);
+        let table = spec.table(&parent_domains);
+        self.check_row_values(table.values().flat_map(|row| row.keys()), child_name);
+        self.add_dependency(parent_names, child_name, table);
+    }
+
+    fn check_row_values<'a, I: Iterator<Item = &'a T>>(&self, values: I, child_name: &str)
+    where
+        T: 'a,
+    {
+        for value in values {
+            if !self.value_space.contains(value) {
+                panic!(
+                    "Dependency probability map contains value not in value space ({:?} -> {})",
+                    value, child_name
+                );
+            }
+        }
+    }
+
+    /// The declared states of each named parent, or else the values it can take.
+    fn parent_domains(&self, parent_names: &[&str]) -> Vec<Vec<T>> {
+        parent_names
+            .iter()
+            .map(|name| match self.node_map.get(*name) {
+                Some(id) => self.get_node_domain(*id),
+                None => panic!("Dependency parent node `{}` not found", name),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bayesian_network::NodeType,
+        error::Error,
+        sampling::seeded_rng,
+        testing::{assert_close, brute_force_probability, joint, row, TOLERANCE},
+    };
+
+    /// `c` is the exclusive or of `a` and `b`, and `d` a noisy reading of `c`.
+    fn exclusive_or() -> BayesianNetwork<bool> {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.3)));
+        network.add_node("b", NodeType::Root(row(0.6)));
+        network.add_node("c", NodeType::Inner);
+        network.add_node("d", NodeType::Leaf);
+        network.add_dependency_spec(
+            vec!["a", "b"],
+            "c",
+            &CptSpec::function(|parent_values: &[bool]| parent_values[0] != parent_values[1]),
+        );
+        network.add_dependency(
+            vec!["c"],
+            "d",
+            HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.2))]),
+        );
+        network
+    }
+
+    #[test]
+    fn function_cpt_is_kept_as_a_function() {
+        let network = exclusive_or();
+        let c = &network.nodes[network.get_node_index("c")];
+        assert!(c.probability.is_empty());
+        assert!(matches!(c.compact_cpt, Some(CompactCpt::Function(_))));
+        let table = network.get_cpt(c.id);
+        assert_eq!(table.len(), 4);
+        for (key, prob) in table.iter() {
+            assert_eq!(prob[&(key[0] != key[1])], 1.0);
+            assert_eq!(prob[&(key[0] == key[1])], 0.0);
+        }
+    }
+
+    #[test]
+    fn function_cpt_inference_matches_enumeration() {
+        let network = exclusive_or();
+        for evidence in [
+            HashMap::new(),
+            HashMap::from([("d", true)]),
+            HashMap::from([("c", true), ("a", false)]),
+        ] {
+            let probabilities = network.infer(&evidence);
+            for name in network.node_names() {
+                assert_close(
+                    network.get_inferred_probability(&probabilities, name, true),
+                    brute_force_probability(&network, name, &true, &evidence),
+                    TOLERANCE,
+                );
+            }
+        }
+
+        let evidence = HashMap::from([("d", false)]);
+        let (best, prob) = network.most_probable_explanation(&evidence);
+        let (assignment, best_prob) = joint(&network)
+            .into_iter()
+            .filter(|(assignment, _)| !assignment[&network.get_node_index("d")])
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        for (name, value) in best {
+            assert_eq!(assignment[&network.get_node_index(&name)], value);
+        }
+        assert_close(
+            prob,
+            best_prob / (1.0 - brute_force_probability(&network, "d", &true, &HashMap::new())),
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn function_cpt_samples_are_deterministic() {
+        let network = exclusive_or();
+        let samples = network.sample_with_rng(200, &mut seeded_rng(7));
+        let column = |name: &str| samples.column_index(name).unwrap();
+        let (a, b, c) = (column("a"), column("b"), column("c"));
+        for sample in samples.rows() {
+            assert_eq!(sample[c], sample[a] != sample[b]);
+        }
+    }
+
+    #[test]
+    fn fit_leaves_function_nodes_alone() {
+        let mut network = exclusive_or();
+        let samples = network.sample_with_rng(200, &mut seeded_rng(3));
+        network.fit(&samples, 1.0);
+        let c = &network.nodes[network.get_node_index("c")];
+        assert!(matches!(c.compact_cpt, Some(CompactCpt::Function(_))));
+        assert!(c.probability.is_empty());
+        assert!(c.dirichlet.is_none());
+        assert!(network.nodes[network.get_node_index("d")]
+            .dirichlet
+            .is_some());
+    }
+
+    #[test]
+    fn function_outputs_must_be_values_of_the_child() {
+        let mut network = BayesianNetwork::new(vec![0, 1, 2]);
+        let prior = HashMap::from([(0, 0.5), (1, 0.5), (2, 0.0)]);
+        network.add_node("a", NodeType::Root(prior.clone()));
+        network.add_node("b", NodeType::Root(prior));
+        network.add_node("sum", NodeType::Leaf);
+        network.set_node_states("sum", vec![0, 1]);
+        let sum = CptFunction(Arc::new(|parent_values: &[i32]| parent_values.iter().sum()));
+        let result = network.try_add_dependency_with(
+            vec!["a", "b"],
+            "sum",
+            HashMap::new(),
+            Some(CompactCpt::Function(sum)),
+        );
+        match result {
+            Err(Error::Invalid { message, .. }) => {
+                assert!(message.contains("maps [1, 1] to 2"), "{}", message)
+            }
+            other => panic!("expected an invalid function, got {:?}", other),
+        }
+        assert!(network.nodes[network.get_node_index("sum")]
+            .parents
+            .is_empty());
+    }
+
+    #[test]
+    fn noisy_or_table_multiplies_the_inhibitions() {
+        let table =
+            CptSpec::noisy_or(0.1, vec![0.8, 0.6]).table(&[vec![true, false], vec![true, false]]);
+        for (key, expected) in [
+            (vec![true, true], 1.0 - 0.2 * 0.4 * 0.9),
+            (vec![true, false], 1.0 - 0.2 * 0.9),
+            (vec![false, true], 1.0 - 0.4 * 0.9),
+            (vec![false, false], 0.1),
+        ] {
+            assert_close(table[&key][&true], expected, TOLERANCE);
+            assert_close(table[&key][&false], 1.0 - expected, TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn noisy_or_with_counts_other_values_as_absent() {
+        let table = CptSpec::noisy_or_with(2, 0, 0.0, vec![0.5]).table(&[vec![0, 1, 2]]);
+        assert_eq!(table[&vec![0]], HashMap::from([(2, 0.0), (0, 1.0)]));
+        assert_eq!(table[&vec![1]], HashMap::from([(2, 0.0), (0, 1.0)]));
+        assert_eq!(table[&vec![2]], HashMap::from([(2, 0.5), (0, 0.5)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Noisy-OR present and absent values are both true")]
+    fn noisy_or_with_rejects_equal_values() {
+        CptSpec::noisy_or_with(true, true, 0.1, vec![0.5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Noisy-OR probability 1.5 is not between 0 and 1")]
+    fn noisy_or_rejects_probabilities_out_of_range() {
+        CptSpec::noisy_or(0.1, vec![1.5]);
+    }
+
+    #[test]
+    fn noisy_max_takes_the_highest_level_caused() {
+        let spec = CptSpec::noisy_max(
+            vec![0, 1, 2],
+            vec![0.9, 0.1, 0.0],
+            vec![
+                HashMap::from([(1, vec![0.2, 0.5, 0.3])]),
+                HashMap::from([(1, vec![0.5, 0.5, 0.0])]),
+            ],
+        );
+        let table = spec.table(&[vec![0, 1], vec![0, 1]]);
+        // The child is at most level l with the product of the chances that the leak and
+        // each active parent stay at or below it.
+        for (key, expected) in [
+            (vec![1, 1], [0.9 * 0.2 * 0.5, 0.7 - 0.09, 0.3]),
+            (vec![1, 0], [0.9 * 0.2, 0.7 - 0.18, 0.3]),
+            (vec![0, 1], [0.9 * 0.5, 0.55, 0.0]),
+            (vec![0, 0], [0.9, 0.1, 0.0]),
+        ] {
+            for (level, prob) in expected.into_iter().enumerate() {
+                assert_close(table[&key][&level], prob, TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Noisy-MAX distribution has 2 probabilities for 3 states")]
+    fn noisy_max_rejects_distributions_of_the_wrong_length() {
+        CptSpec::noisy_max(vec![0, 1, 2], vec![0.5, 0.5], Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Noisy-MAX probability -0.2 is not between 0 and 1")]
+    fn noisy_max_rejects_probabilities_out_of_range() {
+        let cause = HashMap::from([(1, vec![-0.2, 0.7, 0.5])]);
+        CptSpec::noisy_max(vec![0, 1, 2], vec![1.0, 0.0, 0.0], vec![cause]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Noisy-MAX distribution [0.5, 0.25, 0.125] sums to 0.875")]
+    fn noisy_max_rejects_unnormalized_distributions() {
+        CptSpec::noisy_max(vec![0, 1, 2], vec![0.5, 0.25, 0.125], Vec::new());
+    }
+
+    #[test]
+    fn count_and_threshold_tables_depend_on_how_many_parents_are_true() {
+        let domains = vec![vec![true, false]; 3];
+        let trues = |key: &Vec<bool>| key.iter().filter(|value| **value).count();
+        let count = CptSpec::count(true, vec![row(0.1), row(0.5), row(0.9)]).table(&domains);
+        assert_eq!(count.len(), 8);
+        for (key, prob) in &count {
+            // The last row covers three parents as well as two.
+            let expected = [0.1, 0.5, 0.9, 0.9][trues(key)];
+            assert_eq!(prob[&true], expected);
+        }
+        let at_least = CptSpec::at_least(true, 2, row(0.8), row(0.3)).table(&domains);
+        let any = CptSpec::any(true, row(0.8), row(0.3)).table(&domains);
+        let all = CptSpec::all(true, row(0.8), row(0.3)).table(&domains);
+        for key in count.keys() {
+            let above = |holds: bool| if holds { 0.8 } else { 0.3 };
+            assert_eq!(at_least[key][&true], above(trues(key) >= 2));
+            assert_eq!(any[key][&true], above(trues(key) >= 1));
+            assert_eq!(all[key][&true], above(trues(key) == 3));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Count CPT has no rows")]
+    fn count_needs_a_row() {
+        CptSpec::<bool>::count(true, Vec::new());
+    }
+
+    #[test]
+    fn add_dependency_with_default_fills_the_missing_rows() {
+        let mut network = BayesianNetwork::new(vec![0, 1, 2]);
+        network.add_node("a", NodeType::Root(HashMap::from([(0, 0.5), (1, 0.5)])));
+        network.add_node("b", NodeType::Leaf);
+        network.add_node("c", NodeType::Leaf);
+        network.set_node_states("a", vec![0, 1]);
+        network.set_node_states("c", vec![1, 2]);
+        let given = HashMap::from([(vec![0], HashMap::from([(2, 1.0)]))]);
+        network.add_dependency_with_default(
+            vec!["a"],
+            "b",
+            given.clone(),
+            DefaultRow::Distribution(HashMap::from([(0, 0.2), (1, 0.8)])),
+        );
+        network.add_dependency_with_default(vec!["a"], "c", given, DefaultRow::Uniform);
+        let b = &network.nodes[network.get_node_index("b")];
+        assert_eq!(b.probability.len(), 2);
+        assert_eq!(b.probability[&vec![0]][&2], 1.0);
+        assert_eq!(b.probability[&vec![1]][&1], 0.8);
+        // The uniform row is over the declared states of the child.
+        let c = &network.nodes[network.get_node_index("c")];
+        assert_eq!(c.probability[&vec![1]][&0], 0.0);
+        assert_eq!(c.probability[&vec![1]][&1], 0.5);
+        assert_eq!(c.probability[&vec![1]][&2], 0.5);
+        assert_close(
+            brute_force_probability(&network, "b", &1, &HashMap::new()),
+            0.4,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn uniform_delta_and_identity_generate_their_rows() {
+        let domains = [vec![true, false]];
+        let uniform = CptSpec::uniform(vec![0, 1, 2, 3]);
+        assert_eq!(
+            uniform.prior(),
+            HashMap::from([(0, 0.25), (1, 0.25), (2, 0.25), (3, 0.25)])
+        );
+        for row in CptSpec::delta(false).table(&domains).values() {
+            assert_eq!(row, &HashMap::from([(false, 1.0)]));
+        }
+        let identity = CptSpec::identity().table(&domains);
+        assert_eq!(identity[&vec![true]], HashMap::from([(true, 1.0)]));
+        assert_eq!(identity[&vec![false]], HashMap::from([(false, 1.0)]));
+
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.3)));
+        network.add_node("b", NodeType::Leaf);
+        network.add_dependency_spec(vec!["a"], "b", &CptSpec::identity());
+        assert_close(
+            brute_force_probability(&network, "b", &true, &HashMap::new()),
+            0.3,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Uniform CPT has no states")]
+    fn uniform_needs_a_state() {
+        CptSpec::<bool>::uniform(Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Identity CPT needs exactly one parent, not 2")]
+    fn identity_needs_exactly_one_parent() {
+        CptSpec::identity().table(&[vec![true, false], vec![true, false]]);
+    }
+}
@@ -0,0 +1,310 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name, NodeType, Probability};
+
+/// The entry point of the CPT builder, as in
+/// `Cpt::for_child("C").given(["A", "B"]).row([true, true]).probs([0.99, 0.01])`.
+pub struct Cpt;
+
+impl Cpt {
+    /// Start building the CPT of `child`.
+    pub fn for_child<T: Clone + PartialEq + Eq + Hash + Debug>(child: &str) -> CptBuilder<T> {
+        CptBuilder {
+            child: child.to_string(),
+            parents: Vec::new(),
+            states: None,
+            row: None,
+            rows: Vec::new(),
+        }
+    }
+}
+
+/// A CPT under construction, checked as each part is given. Probabilities are listed in the
+/// order of the child states, which are those given to `states`, or else the declared states
+/// of the child or the value space of the network it is added to.
+#[derive(Clone, Debug)]
+pub struct CptBuilder<T> {
+    child: Name,
+    parents: Vec<Name>,
+    states: Option<Vec<T>>,
+    row: Option<Vec<T>>,
+    rows: Vec<(Vec<T>, Vec<Probability>)>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> CptBuilder<T> {
+    /// Set the parents, in the order their values are given to `row`.
+    pub fn given<'a, I: IntoIterator<Item = &'a str>>(mut self, parents: I) -> CptBuilder<T> {
+        if !self.rows.is_empty() || self.row.is_some() {
+            panic!("Parents of `{}` must be given before its rows", self.child);
+        }
+        self.parents = parents.into_iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Set the states of the child, in the order their probabilities are given to `probs`.
+    pub fn states<I: IntoIterator<Item = T>>(mut self, states: I) -> CptBuilder<T> {
+        let states = states.into_iter().collect::<Vec<T>>();
+        for (i, state) in states.iter().enumerate() {
+            if states[..i].contains(state) {
+                panic!("State {:?} of `{}` appears twice", state, self.child);
+            }
+        }
+        for (_, probs) in &self.rows {
+            self.check_length(probs.len(), states.len());
+        }
+        self.states = Some(states);
+        self
+    }
+
+    /// Start a row with the given parent values, in parent order.
+    pub fn row<I: IntoIterator<Item = T>>(mut self, parent_values: I) -> CptBuilder<T> {
+        if let Some(row) = &self.row {
+            panic!("Row {:?} of `{}` has no probabilities", row, self.child);
+        }
+        let row = parent_values.into_iter().collect::<Vec<T>>();
+        if row.len() != self.parents.len() {
+            panic!(
+                "Row {:?} of `{}` has {} values for {} parents",
+                row,
+                self.child,
+                row.len(),
+                self.parents.len()
+            );
+        }
+        if self.rows.iter().any(|(key, _)| *key == row) {
+            panic!("Row {:?} of `{}` appears twice", row, self.child);
+        }
+        self.row = Some(row);
+        self
+    }
+
+    /// Give the probabilities of the child states in the current row. A CPT without parents
+    /// has a single row, which need not be started with `row`.
+    pub fn probs<I: IntoIterator<Item = Probability>>(mut self, probs: I) -> CptBuilder<T> {
+        let row = match self.row.take() {
+            Some(row) => row,
+            None if self.parents.is_empty() && self.rows.is_empty() => Vec::new(),
+            None => panic!("Probabilities of `{}` given without a row", self.child),
+        };
+        let probs = probs.into_iter().collect::<Vec<Probability>>();
+        if let Some(states) = &self.states {
+            self.check_length(probs.len(), states.len());
+        }
+        if probs.iter().any(|prob| !(0.0..=1.0).contains(prob)) {
+            panic!(
+                "Row {:?} of `{}` has a probability outside 0 and 1",
+                row, self.child
+            );
+        }
+        let sum = probs.iter().sum::<Probability>();
+        if (sum - 1.0).abs() > 0.0000001 {
+            panic!(
+                "Row {:?} of `{}` sums to {} instead of 1.0",
+                row, self.child, sum
+            );
+        }
+        self.rows.push((row, probs));
+        self
+    }
+
+    /// Get the CPT, as taken by `add_dependency`. The child states must have been given.
+    pub fn build(&self) -> HashMap<Vec<T>, HashMap<T, Probability>> {
+        match &self.states {
+            Some(states) => self.table(states),
+            None => panic!("States of `{}` must be given to build its CPT", self.child),
+        }
+    }
+
+    /// Check the CPT against `network` and add it as the dependency of the child on its
+    /// parents, or as its prior if it is a root node.
+    pub fn add_to(&self, network: &mut BayesianNetwork<T>) {
+        if let Some(row) = &self.row {
+            panic!("Row {:?} of `{}` has no probabilities", row, self.child);
+        }
+        let child = match network.node_map.get(&self.child) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", self.child),
+        };
+        let states = match (&self.states, &network.nodes[child].states) {
+            (Some(states), _) | (None, Some(states)) => states.clone(),
+            (None, None) => network.value_space.clone(),
+        };
+        for (_, probs) in &self.rows {
+            self.check_length(probs.len(), states.len());
+        }
+        for (parent, name) in self.parents.iter().enumerate() {
+            let domain = match network.node_map.get(name) {
+                Some(id) => network.get_node_domain(*id),
+                None => panic!("Node `{}` not found", name),
+            };
+            for (row, _) in &self.rows {
+                if !domain.contains(&row[parent]) {
+                    panic!(
+                        "Row {:?} of `{}` has value {:?} outside the domain of `{}`",
+                        row, self.child, row[parent], name
+                    );
+                }
+            }
+        }
+        for state in &states {
+            if !network.value_space.contains(state) {
+                panic!("State {:?} of `{}` not in value space", state, self.child);
+            }
+        }
+        let mut table = self.table(&states);
+        if let NodeType::Root(prior) = &mut network.nodes[child].node_type {
+            if !self.parents.is_empty() {
+                panic!("Cannot add dependency to root node `{}`", self.child);
+            }
+            *prior = table.remove(&Vec::new()).unwrap_or_default();
+            for value in &network.value_space {
+                prior.entry(value.clone()).or_insert(0.0);
+            }
+        } else {
+            network.add_dependency(
+                self.parents.iter().map(|name| name.as_str()).collect(),
+                &self.child,
+                table,
+            );
+        }
+    }
+
+    fn table(&self, states: &[T]) -> HashMap<Vec<T>, HashMap<T, Probability>> {
+        self.rows
+            .iter()
+            .map(|(row, probs)| {
+                self.check_length(probs.len(), states.len());
+                (
+                    row.clone(),
+                    states.iter().cloned().zip(probs.iter().copied()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn check_length(&self, probs: usize, states: usize) {
+        if probs != states {
+            panic!(
+                "Row of `{}` has {} probabilities for {} states",
+                self.child, probs, states
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, joint, row, TOLERANCE};
+
+    #[test]
+    fn builder_reproduces_the_bonus_network() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("performance", NodeType::Root(row(0.5)));
+        network.add_node("horse_race", NodeType::Root(row(0.5)));
+        network.add_node("mood", NodeType::Inner);
+        network.add_node("bonus", NodeType::Leaf);
+        network.add_node("feast", NodeType::Leaf);
+        Cpt::for_child("performance")
+            .probs([0.01, 0.99])
+            .add_to(&mut network);
+        Cpt::for_child("horse_race")
+            .states([false, true])
+            .probs([0.9, 0.1])
+            .add_to(&mut network);
+        Cpt::for_child("mood")
+            .given(["performance", "horse_race"])
+            .row([true, true])
+            .probs([0.99, 1.0 - 0.99])
+            .row([false, true])
+            .probs([0.6, 1.0 - 0.6])
+            .row([true, false])
+            .probs([0.9, 1.0 - 0.9])
+            .row([false, false])
+            .probs([0.01, 1.0 - 0.01])
+            .add_to(&mut network);
+        for (child, given) in [("bonus", [0.3, 0.01]), ("feast", [0.9, 0.01])] {
+            Cpt::for_child(child)
+                .given(["mood"])
+                .row([true])
+                .probs([given[0], 1.0 - given[0]])
+                .row([false])
+                .probs([given[1], 1.0 - given[1]])
+                .add_to(&mut network);
+        }
+        for ((assignment, prob), (expected, expected_prob)) in
+            joint(&network).into_iter().zip(joint(&bonus()))
+        {
+            assert_eq!(assignment, expected);
+            assert_close(prob, expected_prob, TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn build_gives_the_table() {
+        let table = Cpt::for_child("b")
+            .given(["a"])
+            .states(["low", "high"])
+            .row(["low"])
+            .probs([0.75, 0.25])
+            .row(["high"])
+            .probs([0.5, 0.5])
+            .build();
+        assert_eq!(
+            table,
+            HashMap::from([
+                (vec!["low"], HashMap::from([("low", 0.75), ("high", 0.25)])),
+                (vec!["high"], HashMap::from([("low", 0.5), ("high", 0.5)])),
+            ])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Row [true] of `b` sums to 0.9 instead of 1.0")]
+    fn builder_rejects_unnormalized_rows() {
+        Cpt::for_child("b")
+            .given(["a"])
+            .row([true])
+            .probs([0.5, 0.4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Row [true, false] of `b` has 2 values for 1 parents")]
+    fn builder_rejects_rows_of_the_wrong_arity() {
+        Cpt::for_child::<bool>("b").given(["a"]).row([true, false]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Row [true] of `b` appears twice")]
+    fn builder_rejects_repeated_rows() {
+        Cpt::for_child("b")
+            .given(["a"])
+            .row([true])
+            .probs([0.5, 0.5])
+            .row([true]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Row of `b` has 2 probabilities for 3 states")]
+    fn builder_checks_rows_against_the_states() {
+        Cpt::for_child("b")
+            .given(["a"])
+            .row([1])
+            .probs([0.5, 0.5])
+            .states([1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Row [2] of `b` has value 2 outside the domain of `a`")]
+    fn add_to_checks_parent_values_against_the_network() {
+        let mut network = BayesianNetwork::new(vec![0, 1, 2]);
+        network.add_node("a", NodeType::Root(HashMap::from([(0, 0.5), (1, 0.5)])));
+        network.set_node_states("a", vec![0, 1]);
+        network.add_node("b", NodeType::Leaf);
+        Cpt::for_child("b")
+            .given(["a"])
+            .row([2])
+            .probs([0.2, 0.3, 0.5])
+            .add_to(&mut network);
+    }
+}
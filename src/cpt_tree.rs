@@ -0,0 +1,363 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, NodeType, Probability},
+    cpt::CompactCpt,
+    dataset::Dataset,
+};
+
+/// A CPT as a decision tree over the parent values, for a child that depends on only some of
+/// its parents in each context. Parents are referred to by their position in the parent list.
+#[derive(Clone, Debug)]
+pub enum CptTree<T> {
+    /// The distribution of the child in every context reaching this leaf.
+    Leaf(HashMap<T, Probability>),
+    /// Branch on the value of a parent, falling back to `otherwise` for values without a
+    /// branch of their own.
+    Split {
+        parent: usize,
+        branches: Vec<(T, CptTree<T>)>,
+        otherwise: Option<Box<CptTree<T>>>,
+    },
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> CptTree<T> {
+    /// A leaf with the given distribution of the child.
+    pub fn leaf(prob: HashMap<T, Probability>) -> CptTree<T> {
+        CptTree::Leaf(prob)
+    }
+
+    /// A split on the parent at position `parent`, with one subtree per value.
+    pub fn split(parent: usize, branches: Vec<(T, CptTree<T>)>) -> CptTree<T> {
+        CptTree::Split {
+            parent,
+            branches,
+            otherwise: None,
+        }
+    }
+
+    /// A split on the parent at position `parent`, with one subtree per listed value and
+    /// `otherwise` for the rest.
+    pub fn split_or(
+        parent: usize,
+        branches: Vec<(T, CptTree<T>)>,
+        otherwise: CptTree<T>,
+    ) -> CptTree<T> {
+        CptTree::Split {
+            parent,
+            branches,
+            otherwise: Some(Box::new(otherwise)),
+        }
+    }
+
+    /// Get the distribution of the child given the parent values, in parent order.
+    pub fn distribution(&self, parent_values: &[T]) -> &HashMap<T, Probability> {
+        match self {
+            CptTree::Leaf(prob) => prob,
+            CptTree::Split {
+                parent,
+                branches,
+                otherwise,
+            } => {
+                let value = Self::parent_value(*parent, parent_values);
+                match branches.iter().find(|(branch, _)| branch == value) {
+                    Some((_, tree)) => tree.distribution(parent_values),
+                    None => match otherwise {
+                        Some(tree) => tree.distribution(parent_values),
+                        None => panic!("CPT tree has no branch for value {:?}", value),
+                    },
+                }
+            }
+        }
+    }
+
+    /// Get the number of leaves, which is the number of distributions the tree stores.
+    pub fn leaf_count(&self) -> usize {
+        match self {
+            CptTree::Leaf(_) => 1,
+            CptTree::Split {
+                branches,
+                otherwise,
+                ..
+            } => {
+                branches
+                    .iter()
+                    .map(|(_, tree)| tree.leaf_count())
+                    .sum::<usize>()
+                    + otherwise.as_ref().map_or(0, |tree| tree.leaf_count())
+            }
+        }
+    }
+
+    fn distribution_mut(&mut self, parent_values: &[T]) -> &mut HashMap<T, Probability> {
+        match self {
+            CptTree::Leaf(prob) => prob,
+            CptTree::Split {
+                parent,
+                branches,
+                otherwise,
+            } => {
+                let value = Self::parent_value(*parent, parent_values);
+                match branches.iter().position(|(branch, _)| branch == value) {
+                    Some(index) => branches[index].1.distribution_mut(parent_values),
+                    None => match otherwise {
+                        Some(tree) => tree.distribution_mut(parent_values),
+                        None => panic!("CPT tree has no branch for value {:?}", value),
+                    },
+                }
+            }
+        }
+    }
+
+    fn parent_value(parent: usize, parent_values: &[T]) -> &T {
+        match parent_values.get(parent) {
+            Some(value) => value,
+            None => panic!(
+                "CPT tree splits on parent {} of only {}",
+                parent,
+                parent_values.len()
+            ),
+        }
+    }
+
+    pub(crate) fn leaves_mut(&mut self) -> Vec<&mut HashMap<T, Probability>> {
+        match self {
+            CptTree::Leaf(prob) => vec![prob],
+            CptTree::Split {
+                branches,
+                otherwise,
+                ..
+            } => branches
+                .iter_mut()
+                .map(|(_, tree)| tree)
+                .chain(otherwise.iter_mut().map(|tree| tree.as_mut()))
+                .flat_map(|tree| tree.leaves_mut())
+                .collect(),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Estimate the leaves of `tree` as the CPT of `child` from a dataset of complete records,
+    /// pooling the counts of every parent configuration that reaches the same leaf, and set
+    /// the CPT of `child` to the result. The child's domain is its declared states, or else
+    /// the values observed in its column, and `pseudo_count` is added to every cell of a leaf.
+    /// The node keeps the tree, which inference walks to look up each row, and is no longer
+    /// tied to a shared CPT.
+    pub fn fit_cpt_tree(
+        &mut self,
+        child: &str,
+        mut tree: CptTree<T>,
+        dataset: &Dataset<T>,
+        pseudo_count: Probability,
+    ) {
+        let id = match self.node_map.get(child) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", child),
+        };
+        if let NodeType::Root(_) = self.nodes[id].node_type {
+            panic!("Cannot fit a CPT tree to root node `{}`", child);
+        }
+        let column = |name: &str| {
+            dataset
+                .column_index(name)
+                .unwrap_or_else(|| panic!("Dataset column `{}` not found", name))
+        };
+        let child_column = column(child);
+        let parent_columns = self.nodes[id]
+            .parents
+            .iter()
+            .map(|parent| column(self.get_node_name(*parent)))
+            .collect::<Vec<usize>>();
+        let domain = match &self.nodes[id].states {
+            Some(states) => states.clone(),
+            None => self
+                .value_space
+                .iter()
+                .filter(|value| {
+                    dataset
+                        .rows()
+                        .iter()
+                        .any(|row| row[child_column] == **value)
+                })
+                .cloned()
+                .collect(),
+        };
+        self.estimate_leaves(
+            &mut tree,
+            dataset,
+            child_column,
+            &parent_columns,
+            &domain,
+            pseudo_count,
+        );
+
+        let node = &mut self.nodes[id];
+        node.probability.clear();
+        node.compact_cpt = Some(CompactCpt::Tree(tree));
+        node.shared_cpt = None;
+        node.dirichlet = None;
+        self.normalize_learned();
+    }
+
+    /// Set every leaf of `tree` to the distribution of the child column over `domain`, counted
+    /// from the rows reaching it by their parent columns, with `pseudo_count` added to each cell.
+    pub(crate) fn estimate_leaves(
+        &self,
+        tree: &mut CptTree<T>,
+        dataset: &Dataset<T>,
+        child_column: usize,
+        parent_columns: &[usize],
+        domain: &[T],
+        pseudo_count: Probability,
+    ) {
+        for leaf in tree.leaves_mut() {
+            *leaf = self
+                .value_space
+                .iter()
+                .map(|value| {
+                    let count = if domain.contains(value) {
+                        pseudo_count
+                    } else {
+                        0.0
+                    };
+                    (value.clone(), count)
+                })
+                .collect();
+        }
+        for row in dataset.rows() {
+            let key = parent_columns
+                .iter()
+                .map(|column| row[*column].clone())
+                .collect::<Vec<T>>();
+            if let Some(count) = tree.distribution_mut(&key).get_mut(&row[child_column]) {
+                *count += 1.0;
+            }
+        }
+        for leaf in tree.leaves_mut() {
+            let total = leaf.values().sum::<Probability>();
+            for (value, prob) in leaf.iter_mut() {
+                *prob = if total > 0.0 {
+                    *prob / total
+                } else if domain.contains(value) {
+                    1.0 / domain.len() as Probability
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, brute_force_probability, row, TOLERANCE};
+
+    /// `c` depends on `b` only when `a` is false, and `d` on `c`.
+    fn context_specific() -> BayesianNetwork<bool> {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.4)));
+        network.add_node("b", NodeType::Root(row(0.5)));
+        network.add_node("c", NodeType::Inner);
+        network.add_node("d", NodeType::Leaf);
+        let cpt = HashMap::from([
+            (vec![true, true], row(0.5)),
+            (vec![true, false], row(0.5)),
+            (vec![false, true], row(0.5)),
+            (vec![false, false], row(0.5)),
+        ]);
+        network.add_shared_cpt("c.cpt", cpt);
+        network.add_shared_dependency(vec!["a", "b"], "c", "c.cpt");
+        network.add_dependency(
+            vec!["c"],
+            "d",
+            HashMap::from([(vec![true], row(0.8)), (vec![false], row(0.3))]),
+        );
+        network
+    }
+
+    fn tree() -> CptTree<bool> {
+        CptTree::split(
+            0,
+            vec![
+                (true, CptTree::leaf(HashMap::new())),
+                (
+                    false,
+                    CptTree::split(
+                        1,
+                        vec![
+                            (true, CptTree::leaf(HashMap::new())),
+                            (false, CptTree::leaf(HashMap::new())),
+                        ],
+                    ),
+                ),
+            ],
+        )
+    }
+
+    fn dataset() -> Dataset<bool> {
+        let mut dataset = Dataset::new(vec!["a", "b", "c", "d"]);
+        for row in [
+            [true, true, true, true],
+            [true, false, true, false],
+            [true, false, false, false],
+            [false, true, false, true],
+            [false, true, false, false],
+            [false, false, true, true],
+        ] {
+            dataset.add_row(row.to_vec());
+        }
+        dataset
+    }
+
+    /// Check the estimate of `c` from `dataset` with a pseudo count of 1, kept as a tree.
+    fn assert_fitted_tree(network: &BayesianNetwork<bool>) {
+        let c = &network.nodes[network.get_node_index("c")];
+        assert!(matches!(c.compact_cpt, Some(CompactCpt::Tree(_))));
+        assert!(c.probability.is_empty());
+        assert!(c.shared_cpt.is_none());
+        assert!(c.dirichlet.is_none());
+        // The leaves pool their configurations: 2 of 3 for `a`, 0 of 2 and 1 of 1 below.
+        for (key, expected) in [
+            (vec![true, true], 3.0 / 5.0),
+            (vec![true, false], 3.0 / 5.0),
+            (vec![false, true], 1.0 / 4.0),
+            (vec![false, false], 2.0 / 3.0),
+        ] {
+            assert_close(network.cpt_probability(c, &key, &true), expected, TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn fit_cpt_tree_keeps_the_tree_on_the_node() {
+        let mut network = context_specific();
+        let dataset = dataset();
+        network.fit(&dataset, 1.0);
+        network.fit_cpt_tree("c", tree(), &dataset, 1.0);
+
+        assert_fitted_tree(&network);
+        for evidence in [HashMap::new(), HashMap::from([("d", true)])] {
+            let probabilities = network.infer(&evidence);
+            for name in network.node_names() {
+                assert_close(
+                    network.get_inferred_probability(&probabilities, name, true),
+                    brute_force_probability(&network, name, &true, &evidence),
+                    TOLERANCE,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fit_refits_the_leaves_of_a_tree() {
+        let mut network = context_specific();
+        let dataset = dataset();
+        let mut first = Dataset::new(vec!["a", "b", "c", "d"]);
+        first.add_row(vec![false, false, false, false]);
+        network.fit_cpt_tree("c", tree(), &first, 1.0);
+        network.fit(&dataset, 1.0);
+
+        assert_fitted_tree(&network);
+    }
+}
@@ -0,0 +1,369 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, NodeId, NodeType, Probability};
+
+/// The most vertex combinations of the interval rows that `bounds` enumerates; beyond it,
+/// each row is optimized in turn.
+const MAX_COMBINATIONS: usize = 4096;
+
+/// An interval CPT row: the node, its parent values, and the extreme points of the
+/// distributions within the intervals, over the child domain in order.
+type IntervalRow<T> = (NodeId, Vec<T>, Vec<Vec<Probability>>);
+
+/// A credal network: a Bayesian network some of whose CPT rows are only known to lie within
+/// probability intervals. The remaining rows are taken from the network.
+pub struct CredalNetwork<T: Clone + PartialEq + Eq + Hash + Debug> {
+    network: BayesianNetwork<T>,
+    rows: Vec<IntervalRow<T>>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> CredalNetwork<T> {
+    /// Create a credal network with the structure and point probabilities of `network`.
+    pub fn new(network: BayesianNetwork<T>) -> CredalNetwork<T> {
+        CredalNetwork {
+            network,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Get the underlying network.
+    pub fn network(&self) -> &BayesianNetwork<T> {
+        &self.network
+    }
+
+    /// Let the CPT row of a node for the given parent values, in parent order, be any
+    /// distribution whose probabilities lie within the given `(lower, upper)` intervals.
+    /// Values without an interval have probability 0. A root node has a single row, with no
+    /// parent values.
+    pub fn set_interval_row(
+        &mut self,
+        name: &str,
+        parent_values: Vec<T>,
+        intervals: HashMap<T, (Probability, Probability)>,
+    ) {
+        let id = match self.network.node_map.get(name) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", name),
+        };
+        if parent_values.len() != self.network.nodes[id].parents.len() {
+            panic!(
+                "Row {:?} of `{}` has {} values for {} parents",
+                parent_values,
+                name,
+                parent_values.len(),
+                self.network.nodes[id].parents.len()
+            );
+        }
+        let domain = self.domain(id);
+        for (value, (lower, upper)) in &intervals {
+            if !domain.contains(value) {
+                panic!("Value {:?} of `{}` not in its domain", value, name);
+            }
+            if !(0.0 <= *lower && lower <= upper && *upper <= 1.0) {
+                panic!(
+                    "Interval [{}, {}] of `{}` is not within 0 and 1",
+                    lower, upper, name
+                );
+            }
+        }
+        let bounds = domain
+            .iter()
+            .map(|value| intervals.get(value).copied().unwrap_or((0.0, 0.0)))
+            .collect::<Vec<(Probability, Probability)>>();
+        let vertices = vertices(&bounds);
+        if vertices.is_empty() {
+            panic!(
+                "Intervals of row {:?} of `{}` admit no distribution",
+                parent_values, name
+            );
+        }
+        self.rows
+            .retain(|(node, key, _)| !(*node == id && *key == parent_values));
+        self.rows.push((id, parent_values, vertices));
+    }
+
+    /// Get the lower and upper bounds of the probability of a node taking `value` given
+    /// `evidence`, over every choice of the interval rows. The bounds are exact when the
+    /// interval rows have few combinations of extreme points; otherwise they are found by
+    /// optimizing one row at a time, and may be narrower than the exact ones.
+    pub fn bounds(
+        &self,
+        name: &str,
+        value: &T,
+        evidence: &HashMap<&str, T>,
+    ) -> (Probability, Probability) {
+        let target = match self.network.node_map.get(name) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", name),
+        };
+        let mut domains = self
+            .network
+            .nodes
+            .iter()
+            .map(|node| self.domain(node.id))
+            .collect::<Vec<Vec<T>>>();
+        for (id, observed) in self.network.evidence_ids(evidence) {
+            domains[id] = vec![observed];
+        }
+        let mut joint = domains.clone();
+        joint[target].retain(|state| state == value);
+
+        let mut network = self.network.copy();
+        let mut evaluate = |choice: &[usize]| {
+            for ((id, key, vertices), vertex) in self.rows.iter().zip(choice) {
+                let row = self
+                    .domain(*id)
+                    .into_iter()
+                    .zip(vertices[*vertex].iter().copied())
+                    .collect::<HashMap<T, Probability>>();
+                set_row(&mut network, *id, key, row);
+            }
+            let (evidence, _) = network.eliminate(&domains, false);
+            if evidence <= 0.0 {
+                return None;
+            }
+            let (joint, _) = network.eliminate(&joint, false);
+            Some(joint / evidence)
+        };
+
+        let sizes = self
+            .rows
+            .iter()
+            .map(|(_, _, vertices)| vertices.len())
+            .collect::<Vec<usize>>();
+        let combinations = sizes
+            .iter()
+            .fold(1usize, |total, size| total.saturating_mul(*size));
+        let (mut lower, mut upper) = (f64::INFINITY, f64::NEG_INFINITY);
+        if combinations <= MAX_COMBINATIONS {
+            let mut choice = vec![0; sizes.len()];
+            loop {
+                if let Some(prob) = evaluate(&choice) {
+                    lower = lower.min(prob);
+                    upper = upper.max(prob);
+                }
+                // Advance the choice like a mixed-radix counter.
+                let mut row = 0;
+                while row < sizes.len() {
+                    choice[row] += 1;
+                    if choice[row] < sizes[row] {
+                        break;
+                    }
+                    choice[row] = 0;
+                    row += 1;
+                }
+                if row == sizes.len() {
+                    break;
+                }
+            }
+        } else {
+            for minimize in [true, false] {
+                let mut choice = vec![0; sizes.len()];
+                let mut best = evaluate(&choice);
+                let mut improved = true;
+                while improved {
+                    improved = false;
+                    for row in 0..sizes.len() {
+                        for vertex in 0..sizes[row] {
+                            let current = choice[row];
+                            choice[row] = vertex;
+                            let prob = evaluate(&choice);
+                            let better = match (prob, best) {
+                                (Some(prob), Some(best)) => {
+                                    if minimize {
+                                        prob < best - 1e-12
+                                    } else {
+                                        prob > best + 1e-12
+                                    }
+                                }
+                                (Some(_), None) => true,
+                                (None, _) => false,
+                            };
+                            if better {
+                                best = prob;
+                                improved = true;
+                            } else {
+                                choice[row] = current;
+                            }
+                        }
+                    }
+                }
+                if let Some(best) = best {
+                    lower = lower.min(best);
+                    upper = upper.max(best);
+                }
+            }
+        }
+        if lower > upper {
+            panic!("Evidence has zero probability");
+        }
+        (lower, upper)
+    }
+
+    /// Get the bounds of every state of a node given `evidence`, as `bounds`.
+    pub fn bounds_all(
+        &self,
+        name: &str,
+        evidence: &HashMap<&str, T>,
+    ) -> Vec<(T, Probability, Probability)> {
+        let id = match self.network.node_map.get(name) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", name),
+        };
+        self.domain(id)
+            .into_iter()
+            .map(|value| {
+                let (lower, upper) = self.bounds(name, &value, evidence);
+                (value, lower, upper)
+            })
+            .collect()
+    }
+
+    /// The declared states of a node, or else the whole value space, since interval rows may
+    /// give probability to values the point probabilities do not.
+    fn domain(&self, id: NodeId) -> Vec<T> {
+        match &self.network.nodes[id].states {
+            Some(states) => states.clone(),
+            None => self.network.value_space.clone(),
+        }
+    }
+}
+
+/// Replace the CPT row of a node for the given parent values.
+pub(crate) fn set_row<T: Clone + PartialEq + Eq + Hash + Debug>(
+    network: &mut BayesianNetwork<T>,
+    id: NodeId,
+    key: &[T],
+    mut row: HashMap<T, Probability>,
+) {
+    for value in &network.value_space {
+        row.entry(value.clone()).or_insert(0.0);
+    }
+    network.expand_cpt(id);
+    let node = &mut network.nodes[id];
+    match &mut node.node_type {
+        NodeType::Root(prior) => *prior = row,
+        _ => {
+            node.probability.insert(key.to_vec(), row);
+        }
+    }
+}
+
+/// The extreme points of the distributions whose probabilities lie within `bounds`. At an
+/// extreme point, every probability but at most one is at one of its bounds.
+fn vertices(bounds: &[(Probability, Probability)]) -> Vec<Vec<Probability>> {
+    let mut vertices: Vec<Vec<Probability>> = Vec::new();
+    let n = bounds.len();
+    for free in 0..n {
+        for mask in 0..(1usize << (n - 1)) {
+            let mut vertex = vec![0.0; n];
+            let mut bit = 0;
+            for (i, (lower, upper)) in bounds.iter().enumerate() {
+                if i == free {
+                    continue;
+                }
+                vertex[i] = if mask & (1 << bit) != 0 {
+                    *upper
+                } else {
+                    *lower
+                };
+                bit += 1;
+            }
+            let rest = 1.0 - vertex.iter().sum::<Probability>();
+            let (lower, upper) = bounds[free];
+            if rest < lower - 1e-9 || rest > upper + 1e-9 {
+                continue;
+            }
+            vertex[free] = rest.clamp(lower, upper);
+            if !vertices
+                .iter()
+                .any(|other| other.iter().zip(&vertex).all(|(a, b)| (a - b).abs() < 1e-9))
+            {
+                vertices.push(vertex);
+            }
+        }
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_probability, row, TOLERANCE};
+
+    /// The bonus network with an imprecise mood given both causes and bonus given a good mood.
+    fn imprecise_bonus() -> CredalNetwork<bool> {
+        let mut credal = CredalNetwork::new(bonus());
+        credal.set_interval_row(
+            "mood",
+            vec![true, true],
+            HashMap::from([(true, (0.9, 1.0)), (false, (0.0, 0.1))]),
+        );
+        credal.set_interval_row(
+            "bonus",
+            vec![true],
+            HashMap::from([(true, (0.2, 0.4)), (false, (0.6, 0.8))]),
+        );
+        credal
+    }
+
+    fn queries() -> Vec<(&'static str, HashMap<&'static str, bool>)> {
+        vec![
+            ("bonus", HashMap::new()),
+            ("mood", HashMap::from([("bonus", true)])),
+            (
+                "performance",
+                HashMap::from([("bonus", true), ("feast", true)]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn bounds_contain_the_precise_posterior() {
+        let credal = imprecise_bonus();
+        let precise = bonus();
+        for (name, evidence) in queries() {
+            let (lower, upper) = credal.bounds(name, &true, &evidence);
+            let prob = brute_force_probability(&precise, name, &true, &evidence);
+            assert!(
+                lower - TOLERANCE <= prob && prob <= upper + TOLERANCE,
+                "P({} | {:?}) = {} outside [{}, {}]",
+                name,
+                evidence,
+                prob,
+                lower,
+                upper
+            );
+            assert!(lower < upper);
+        }
+    }
+
+    #[test]
+    fn bounds_match_enumeration_of_the_extreme_points() {
+        let credal = imprecise_bonus();
+        let mood = bonus().get_node_index("mood");
+        let bonus_id = bonus().get_node_index("bonus");
+        for (name, evidence) in queries() {
+            let mut posteriors = Vec::new();
+            for mood_row in [row(0.9), row(1.0)] {
+                for bonus_row in [row(0.2), row(0.4)] {
+                    let mut network = bonus();
+                    set_row(&mut network, mood, &[true, true], mood_row.clone());
+                    set_row(&mut network, bonus_id, &[true], bonus_row);
+                    posteriors.push(brute_force_probability(&network, name, &true, &evidence));
+                }
+            }
+            let (lower, upper) = credal.bounds(name, &true, &evidence);
+            assert_close(
+                lower,
+                posteriors.iter().copied().fold(1.0, f64::min),
+                TOLERANCE,
+            );
+            assert_close(
+                upper,
+                posteriors.iter().copied().fold(0.0, f64::max),
+                TOLERANCE,
+            );
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+use crate::{bayesian_network::Name, error::Error};
+
+const FORMAT: &str = "CSV";
+
+/// A dataset of complete records over named variables.
+#[derive(Clone, Debug)]
+pub struct Dataset<T> {
+    columns: Vec<Name>,
+    rows: Vec<Vec<T>>,
+}
+
+impl<T: Clone> Dataset<T> {
+    /// Create an empty dataset with the given column names.
+    pub fn new(columns: Vec<&str>) -> Dataset<T> {
+        Dataset {
+            columns: columns.into_iter().map(|name| name.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a record. Values must be given in column order.
+    pub fn add_row(&mut self, row: Vec<T>) {
+        if row.len() != self.columns.len() {
+            panic!(
+                "Row length does not match column length ({} and {})",
+                row.len(),
+                self.columns.len()
+            );
+        }
+        self.rows.push(row);
+    }
+
+    /// Get the column names.
+    pub fn columns(&self) -> &[Name] {
+        &self.columns
+    }
+
+    /// Get the records.
+    pub fn rows(&self) -> &[Vec<T>] {
+        &self.rows
+    }
+
+    pub(crate) fn rows_mut(&mut self) -> &mut [Vec<T>] {
+        &mut self.rows
+    }
+
+    /// Get the index of a column.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column == name)
+    }
+
+    /// Get the record at `index` keyed by column name.
+    pub fn record(&self, index: usize) -> HashMap<&str, T> {
+        self.columns
+            .iter()
+            .map(|name| name.as_str())
+            .zip(self.rows[index].iter().cloned())
+            .collect()
+    }
+
+    /// Get the number of records.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Check whether the dataset has no records.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+impl Dataset<String> {
+    /// Parse a dataset from CSV. The first line holds the column names, and fields may be
+    /// double-quoted. Blank lines are ignored and empty fields are rejected.
+    pub fn from_csv(source: &str) -> Result<Dataset<String>, Error> {
+        let mut lines = source
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end_matches('\r')))
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (line, header) = lines.next().ok_or(Error::Invalid {
+            format: FORMAT,
+            message: "missing header".to_string(),
+        })?;
+        let columns = parse_fields(header, line)?;
+        let mut dataset = Dataset::new(columns.iter().map(|name| name.as_str()).collect());
+        for (line, text) in lines {
+            let row = parse_fields(text, line)?;
+            if row.len() != columns.len() {
+                return Err(Error::Syntax {
+                    format: FORMAT,
+                    line,
+                    message: format!("expected {} fields, found {}", columns.len(), row.len()),
+                });
+            }
+            dataset.add_row(row);
+        }
+        Ok(dataset)
+    }
+
+    /// Read a dataset from a CSV file.
+    pub fn read_csv<P: AsRef<Path>>(path: P) -> Result<Dataset<String>, Error> {
+        Dataset::from_csv(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl<T: Clone + Display> Dataset<T> {
+    /// Write the dataset as CSV with a header line, quoting fields where needed.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        let line = |fields: Vec<String>| {
+            fields
+                .iter()
+                .map(|field| quote_field(field))
+                .collect::<Vec<String>>()
+                .join(",")
+                + "\n"
+        };
+        csv.push_str(&line(self.columns.clone()));
+        for row in &self.rows {
+            csv.push_str(&line(row.iter().map(|value| value.to_string()).collect()));
+        }
+        csv
+    }
+
+    /// Write the dataset to a CSV file.
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_csv())?)
+    }
+}
+
+/// Quote a field if it would not read back as itself.
+pub(crate) fn quote_field(field: &str) -> String {
+    if field.is_empty() || field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Split a CSV line into fields, unquoting quoted ones.
+fn parse_fields(text: &str, line: usize) -> Result<Vec<String>, Error> {
+    let error = |message: &str| Error::Syntax {
+        format: FORMAT,
+        line,
+        message: message.to_string(),
+    };
+    let mut fields = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err(error("unterminated quoted field")),
+                }
+            }
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if chars.peek().is_some_and(|c| *c != ',') {
+                return Err(error("unexpected character after quoted field"));
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                field.push(c);
+            }
+            field = field.trim_end().to_string();
+            if field.is_empty() {
+                return Err(error("empty field"));
+            }
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_keys_values_by_column() {
+        let mut dataset = Dataset::new(vec!["a", "b"]);
+        dataset.add_row(vec![1, 2]);
+        dataset.add_row(vec![3, 4]);
+        assert_eq!(dataset.len(), 2);
+        assert_eq!(dataset.column_index("b"), Some(1));
+        assert_eq!(dataset.column_index("c"), None);
+        assert_eq!(dataset.record(1), HashMap::from([("a", 3), ("b", 4)]));
+    }
+
+    #[test]
+    fn from_csv_unquotes_fields_and_skips_blank_lines() {
+        let dataset =
+            Dataset::from_csv("name, \"note\"\r\n\nalice, \"says \"\"hi\"\", twice\"\nbob ,x\n")
+                .unwrap();
+        assert_eq!(dataset.columns(), ["name", "note"]);
+        assert_eq!(
+            dataset.rows(),
+            [
+                vec!["alice".to_string(), "says \"hi\", twice".to_string()],
+                vec!["bob".to_string(), "x".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn from_csv_reports_the_line_of_a_bad_row() {
+        for (source, line) in [
+            ("a,b\n1,2\n\n3\n", 4),
+            ("a,b\n1,\n", 2),
+            ("a,b\n\"1,2\n", 2),
+        ] {
+            match Dataset::from_csv(source) {
+                Err(Error::Syntax { line: actual, .. }) => assert_eq!(actual, line, "{}", source),
+                result => panic!("expected a syntax error, got {:?}", result.err()),
+            }
+        }
+        assert!(matches!(
+            Dataset::from_csv("\n"),
+            Err(Error::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn to_csv_quotes_fields_that_would_not_read_back() {
+        let mut dataset = Dataset::new(vec!["name", "note"]);
+        dataset.add_row(vec!["a,b".to_string(), " padded".to_string()]);
+        dataset.add_row(vec!["say \"hi\"".to_string(), "plain".to_string()]);
+        let csv = dataset.to_csv();
+        assert_eq!(
+            csv,
+            "name,note\n\"a,b\",\" padded\"\n\"say \"\"hi\"\"\",plain\n"
+        );
+        let read = Dataset::from_csv(&csv).unwrap();
+        assert_eq!(read.columns(), dataset.columns());
+        assert_eq!(read.rows(), dataset.rows());
+    }
+
+    #[test]
+    #[should_panic(expected = "Row length does not match column length")]
+    fn add_row_checks_its_length() {
+        let mut dataset = Dataset::new(vec!["a", "b"]);
+        dataset.add_row(vec![1]);
+    }
+}
@@ -0,0 +1,232 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name, NodeId, Probability};
+
+/// A table over some nodes, indexed by their domain positions with the last node varying
+/// fastest.
+struct Factor {
+    nodes: Vec<NodeId>,
+    values: Vec<Probability>,
+}
+
+impl Factor {
+    fn value(&self, sizes: &[usize], positions: &[usize]) -> Probability {
+        let mut index = 0;
+        for node in &self.nodes {
+            index = index * sizes[*node] + positions[*node];
+        }
+        self.values[index]
+    }
+}
+
+/// Visit every joint position of `nodes`, writing each one into `positions`.
+fn for_each_position<F: FnMut(&[usize])>(
+    nodes: &[NodeId],
+    sizes: &[usize],
+    positions: &mut [usize],
+    mut visit: F,
+) {
+    for node in nodes {
+        positions[*node] = 0;
+    }
+    loop {
+        visit(positions);
+        let mut carry = true;
+        for node in nodes.iter().rev() {
+            positions[*node] += 1;
+            if positions[*node] < sizes[*node] {
+                carry = false;
+                break;
+            }
+            positions[*node] = 0;
+        }
+        if carry {
+            return;
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Find the jointly most probable assignment of every node not in `evidence` by
+    /// max-product variable elimination, and return it in node order with its probability
+    /// given the evidence. Unlike taking the most probable state of each node separately,
+    /// the result is always a consistent joint assignment. The cost is small on polytrees
+    /// and grows with the width of the network.
+    pub fn most_probable_explanation(
+        &self,
+        evidence: &HashMap<&str, T>,
+    ) -> (Vec<(Name, T)>, Probability) {
+        let evidence = self.evidence_ids(evidence);
+        let domains = self
+            .nodes
+            .iter()
+            .map(|node| match evidence.get(&node.id) {
+                Some(value) => vec![value.clone()],
+                None => self.get_node_domain(node.id),
+            })
+            .collect::<Vec<Vec<T>>>();
+        let (joint, positions) = self.eliminate(&domains, true);
+        if joint <= 0.0 {
+            panic!("Evidence has zero probability");
+        }
+        let (evidence_probability, _) = self.eliminate(&domains, false);
+        let assignment = self
+            .nodes
+            .iter()
+            .filter(|node| !evidence.contains_key(&node.id))
+            .map(|node| {
+                (
+                    self.get_node_name(node.id).to_string(),
+                    domains[node.id][positions[node.id]].clone(),
+                )
+            })
+            .collect();
+        (assignment, joint / evidence_probability)
+    }
+
+    /// Eliminate every node from the product of the CPTs restricted to `domains`, maximizing
+    /// if `max` and summing otherwise. Returns the resulting value and, when maximizing, the
+    /// domain position of each node in a maximizing assignment.
+    pub(crate) fn eliminate(&self, domains: &[Vec<T>], max: bool) -> (Probability, Vec<usize>) {
+        let sizes = domains
+            .iter()
+            .map(|domain| domain.len())
+            .collect::<Vec<usize>>();
+        let mut positions = vec![0; self.nodes.len()];
+        let mut factors = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut nodes = node.parents.clone();
+                nodes.push(node.id);
+                let mut values = Vec::new();
+                for_each_position(&nodes, &sizes, &mut positions, |positions| {
+                    let assignment = nodes
+                        .iter()
+                        .map(|id| (*id, domains[*id][positions[*id]].clone()))
+                        .collect::<HashMap<NodeId, T>>();
+                    values.push(self.local_probability(node, &assignment[&node.id], &assignment));
+                });
+                Factor { nodes, values }
+            })
+            .collect::<Vec<Factor>>();
+
+        // The product of the factors mentioning each eliminated node, to read off its best
+        // value once the nodes eliminated after it are decided.
+        let mut eliminated: Vec<(NodeId, Factor)> = Vec::new();
+        let mut remaining = (0..self.nodes.len()).collect::<Vec<NodeId>>();
+        while !remaining.is_empty() {
+            // Greedily eliminate the node whose combined factor is smallest.
+            let scope = |id: NodeId| {
+                let mut scope = Vec::new();
+                for factor in factors.iter().filter(|factor| factor.nodes.contains(&id)) {
+                    for node in &factor.nodes {
+                        if !scope.contains(node) {
+                            scope.push(*node);
+                        }
+                    }
+                }
+                scope
+            };
+            let (index, _) = remaining
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, id)| {
+                    scope(**id)
+                        .iter()
+                        .map(|node| sizes[*node])
+                        .product::<usize>()
+                })
+                .unwrap();
+            let id = remaining.swap_remove(index);
+            let mut nodes = scope(id);
+            nodes.sort();
+            let (used, kept): (Vec<Factor>, Vec<Factor>) = factors
+                .into_iter()
+                .partition(|factor| factor.nodes.contains(&id));
+            factors = kept;
+
+            let mut product = Vec::new();
+            for_each_position(&nodes, &sizes, &mut positions, |positions| {
+                product.push(
+                    used.iter()
+                        .map(|factor| factor.value(&sizes, positions))
+                        .product::<Probability>(),
+                );
+            });
+            let product = Factor {
+                nodes: nodes.clone(),
+                values: product,
+            };
+            let rest = nodes
+                .iter()
+                .copied()
+                .filter(|node| *node != id)
+                .collect::<Vec<NodeId>>();
+            let mut values = Vec::new();
+            for_each_position(&rest, &sizes, &mut positions, |positions| {
+                let mut positions = positions.to_vec();
+                let mut result: Probability = 0.0;
+                for value in 0..sizes[id] {
+                    positions[id] = value;
+                    let p = product.value(&sizes, &positions);
+                    result = if max { result.max(p) } else { result + p };
+                }
+                values.push(result);
+            });
+            factors.push(Factor {
+                nodes: rest,
+                values,
+            });
+            if max {
+                eliminated.push((id, product));
+            }
+        }
+        let total = factors
+            .iter()
+            .map(|factor| factor.values[0])
+            .product::<Probability>();
+
+        for (id, product) in eliminated.iter().rev() {
+            let mut best = (0, Probability::NEG_INFINITY);
+            for value in 0..sizes[*id] {
+                positions[*id] = value;
+                let p = product.value(&sizes, &positions);
+                if p > best.1 {
+                    best = (value, p);
+                }
+            }
+            positions[*id] = best.0;
+        }
+        (total, positions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_mpe, evidence_probability, TOLERANCE};
+
+    #[test]
+    fn most_probable_explanation_matches_exhaustive_argmax() {
+        let network = bonus();
+        for evidence in [
+            HashMap::new(),
+            HashMap::from([("bonus", true)]),
+            HashMap::from([("bonus", true), ("feast", false)]),
+            HashMap::from([("performance", true), ("feast", true)]),
+        ] {
+            let (assignment, prob) = network.most_probable_explanation(&evidence);
+            let (expected, joint) = brute_force_mpe(&network, &evidence);
+            assert_eq!(assignment.len(), network.nodes.len() - evidence.len());
+            for (name, value) in assignment {
+                assert_eq!(value, expected[&name], "{} given {:?}", name, evidence);
+            }
+            assert_close(
+                prob,
+                joint / evidence_probability(&network, &evidence),
+                TOLERANCE,
+            );
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use crate::{
+    bayesian_network::{BayesianNetwork, Probability},
+    formats::{escape_xml, tables},
+};
+use std::{collections::HashMap, fmt::Debug, fmt::Display, hash::Hash};
+
+/// Options for rendering a network as a diagram.
+pub struct DiagramOptions<'a, T> {
+    /// List the states of each node.
+    pub states: bool,
+    /// Annotate each node with the probability of each of its states, as returned by `infer`.
+    pub probabilities: Option<&'a [HashMap<T, Probability>]>,
+    /// Highlight these evidence nodes and show their observed values.
+    pub evidence: Option<&'a HashMap<&'a str, T>>,
+}
+
+impl<T> Default for DiagramOptions<'_, T> {
+    fn default() -> Self {
+        DiagramOptions {
+            states: false,
+            probabilities: None,
+            evidence: None,
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Display> BayesianNetwork<T> {
+    /// The lines of a node's label under `options`, starting with its name.
+    fn diagram_label(&self, id: usize, options: &DiagramOptions<T>) -> Vec<String> {
+        let name = self.get_node_name(id);
+        let mut lines = vec![name.to_string()];
+        if let Some(value) = options.evidence.and_then(|evidence| evidence.get(name)) {
+            lines[0] = format!("{} = {}", name, value);
+        }
+        if options.states || options.probabilities.is_some() {
+            for value in self.get_node_domain(id) {
+                match options.probabilities {
+                    Some(probabilities) => {
+                        lines.push(format!("{}: {:.4}", value, probabilities[id][&value]))
+                    }
+                    None => lines.push(value.to_string()),
+                }
+            }
+        }
+        lines
+    }
+
+    fn is_evidence(&self, id: usize, options: &DiagramOptions<T>) -> bool {
+        options
+            .evidence
+            .is_some_and(|evidence| evidence.contains_key(self.get_node_name(id)))
+    }
+
+    /// Render the network as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DiagramOptions::default())
+    }
+
+    /// Render the network as a Graphviz DOT graph with state, probability and evidence
+    /// annotations. Evidence nodes are filled and hidden nodes dashed.
+    pub fn to_dot_with(&self, options: &DiagramOptions<T>) -> String {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut s = String::new();
+        s.push_str("digraph BayesianNetwork {\n");
+        s.push_str("  node [shape=box, style=rounded];\n");
+        for node in &self.nodes {
+            let label = self
+                .diagram_label(node.id, options)
+                .iter()
+                .map(|line| escape(line))
+                .collect::<Vec<String>>()
+                .join("\\n");
+            let style = if self.is_evidence(node.id, options) {
+                ", style=\"rounded,filled\", fillcolor=lightgrey, penwidth=2"
+            } else if self.is_node_hidden(self.get_node_name(node.id)) {
+                ", style=\"rounded,dashed\""
+            } else {
+                ""
+            };
+            s.push_str(&format!(
+                "  \"{}\" [label=\"{}\"{}];\n",
+                escape(self.get_node_name(node.id)),
+                label,
+                style
+            ));
+        }
+        for node in &self.nodes {
+            for child in &node.children {
+                s.push_str(&format!(
+                    "  \"{}\" -> \"{}\";\n",
+                    escape(self.get_node_name(node.id)),
+                    escape(self.get_node_name(*child))
+                ));
+            }
+        }
+        s.push_str("}\n");
+        s
+    }
+
+    /// Render the network as a Mermaid `graph TD` diagram.
+    pub fn to_mermaid(&self) -> String {
+        self.to_mermaid_with(&DiagramOptions::default())
+    }
+
+    /// Render the network as a Mermaid `graph TD` diagram with state, probability and evidence
+    /// annotations. Evidence nodes get the `evidence` class.
+    pub fn to_mermaid_with(&self, options: &DiagramOptions<T>) -> String {
+        // Mermaid ids must be plain identifiers, so nodes are numbered and named in labels.
+        let escape = |s: &str| s.replace('"', "#quot;");
+        let mut s = String::new();
+        s.push_str("graph TD\n");
+        for node in &self.nodes {
+            let label = self
+                .diagram_label(node.id, options)
+                .iter()
+                .map(|line| escape(line))
+                .collect::<Vec<String>>()
+                .join("<br/>");
+            s.push_str(&format!("    n{}[\"{}\"]\n", node.id, label));
+        }
+        for node in &self.nodes {
+            for child in &node.children {
+                s.push_str(&format!("    n{} --> n{}\n", node.id, child));
+            }
+        }
+        let evidence = self
+            .nodes
+            .iter()
+            .filter(|node| self.is_evidence(node.id, options))
+            .map(|node| format!("n{}", node.id))
+            .collect::<Vec<String>>();
+        if !evidence.is_empty() {
+            s.push_str("    classDef evidence fill:#ddd,stroke-width:2px\n");
+            s.push_str(&format!("    class {} evidence\n", evidence.join(",")));
+        }
+        s
+    }
+
+    /// Render the network as a standalone SVG image.
+    pub fn to_svg(&self) -> String {
+        self.to_svg_with(&DiagramOptions::default())
+    }
+
+    /// Render the network as a standalone SVG image with state, probability and evidence
+    /// annotations. Nodes are laid out in layers by their depth below the roots, and
+    /// evidence nodes are filled.
+    pub fn to_svg_with(&self, options: &DiagramOptions<T>) -> String {
+        const CHAR_WIDTH: f64 = 8.0;
+        const LINE_HEIGHT: f64 = 16.0;
+        const PADDING: f64 = 10.0;
+        const GAP: f64 = 30.0;
+        const LAYER_GAP: f64 = 50.0;
+
+        // Depth of each node: the longest path from a root. Nodes on a cycle stay at 0.
+        let mut depth = vec![0; self.nodes.len()];
+        for id in self.topological_order() {
+            for child in &self.nodes[id].children {
+                depth[*child] = depth[*child].max(depth[id] + 1);
+            }
+        }
+        let mut layers: Vec<Vec<usize>> = Vec::new();
+        for (id, depth) in depth.iter().enumerate() {
+            if layers.len() <= *depth {
+                layers.resize(depth + 1, Vec::new());
+            }
+            layers[*depth].push(id);
+        }
+
+        let labels = (0..self.nodes.len())
+            .map(|id| self.diagram_label(id, options))
+            .collect::<Vec<Vec<String>>>();
+        // Wide (e.g. CJK) characters take about two columns.
+        let text_width = |line: &String| {
+            line.chars()
+                .map(|c| if c.is_ascii() { 1.0 } else { 2.0 })
+                .sum::<f64>()
+                * CHAR_WIDTH
+        };
+        let size = labels
+            .iter()
+            .map(|lines| {
+                let width = lines.iter().map(text_width).fold(0.0, f64::max) + 2.0 * PADDING;
+                let height = lines.len() as f64 * LINE_HEIGHT + 2.0 * PADDING;
+                (width, height)
+            })
+            .collect::<Vec<(f64, f64)>>();
+
+        // Order each layer by the mean position of the parents, then place it centred.
+        let mut position = vec![(0.0, 0.0); self.nodes.len()];
+        let layer_width = |layer: &[usize]| {
+            layer.iter().map(|id| size[*id].0).sum::<f64>()
+                + GAP * layer.len().saturating_sub(1) as f64
+        };
+        let width = layers
+            .iter()
+            .map(|layer| layer_width(layer))
+            .fold(0.0, f64::max)
+            + 2.0 * GAP;
+        let mut y = GAP;
+        for layer in &mut layers {
+            let centre = |id: &usize| {
+                let parents = &self.nodes[*id].parents;
+                if parents.is_empty() {
+                    *id as f64
+                } else {
+                    parents
+                        .iter()
+                        .map(|parent| position[*parent].0)
+                        .sum::<f64>()
+                        / parents.len() as f64
+                }
+            };
+            if y > GAP {
+                layer.sort_by(|a, b| centre(a).total_cmp(&centre(b)));
+            }
+            let mut x = (width - layer_width(layer)) / 2.0;
+            let height = layer.iter().map(|id| size[*id].1).fold(0.0, f64::max);
+            for id in layer.iter() {
+                position[*id] = (x + size[*id].0 / 2.0, y);
+                x += size[*id].0 + GAP;
+            }
+            y += height + LAYER_GAP;
+        }
+        let height = y - LAYER_GAP + GAP;
+
+        let mut s = String::new();
+        s.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+             font-family=\"sans-serif\" font-size=\"13\">\n",
+            width, height
+        ));
+        s.push_str(
+            "  <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+             markerWidth=\"8\" markerHeight=\"8\" orient=\"auto\">\
+             <path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>\n",
+        );
+        for node in &self.nodes {
+            let (x, y) = position[node.id];
+            for child in &node.children {
+                let (child_x, child_y) = position[*child];
+                s.push_str(&format!(
+                    "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" \
+                     stroke=\"black\" marker-end=\"url(#arrow)\"/>\n",
+                    x,
+                    y + size[node.id].1,
+                    child_x,
+                    child_y
+                ));
+            }
+        }
+        for node in &self.nodes {
+            let (x, y) = position[node.id];
+            let (width, height) = size[node.id];
+            let (fill, stroke_width) = if self.is_evidence(node.id, options) {
+                ("lightgrey", 2)
+            } else {
+                ("white", 1)
+            };
+            s.push_str(&format!(
+                "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"6\" \
+                 fill=\"{}\" stroke=\"black\" stroke-width=\"{}\"/>\n",
+                x - width / 2.0,
+                y,
+                width,
+                height,
+                fill,
+                stroke_width
+            ));
+            for (i, line) in labels[node.id].iter().enumerate() {
+                s.push_str(&format!(
+                    "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\"{}>{}</text>\n",
+                    x,
+                    y + PADDING + (i as f64 + 0.8) * LINE_HEIGHT,
+                    if i == 0 { " font-weight=\"bold\"" } else { "" },
+                    escape_xml(line)
+                ));
+            }
+        }
+        s.push_str("</svg>\n");
+        s
+    }
+
+    /// Lay out the CPT of a node as aligned plain text: one row per parent configuration,
+    /// with the parent values followed by the probability of each state.
+    pub fn cpt_table(&self, name: &str) -> String {
+        if !self.node_map.contains_key(name) {
+            panic!("Node `{}` not found", name);
+        }
+        let table = tables(self).swap_remove(self.get_node_index(name));
+        let mut lines = vec![table
+            .parents
+            .iter()
+            .chain(&table.states)
+            .cloned()
+            .collect::<Vec<String>>()];
+        for (key, probs) in &table.rows {
+            lines.push(
+                key.iter()
+                    .cloned()
+                    .chain(probs.iter().map(|prob| format!("{:.4}", prob)))
+                    .collect(),
+            );
+        }
+        let width = |s: &str| s.chars().count();
+        let widths = (0..lines[0].len())
+            .map(|column| lines.iter().map(|line| width(&line[column])).max().unwrap())
+            .collect::<Vec<usize>>();
+        let mut s = String::new();
+        for line in &lines {
+            let cells = line
+                .iter()
+                .zip(&widths)
+                .map(|(cell, to)| format!("{}{}", cell, " ".repeat(to - width(cell))))
+                .collect::<Vec<String>>();
+            let (given, probs) = cells.split_at(table.parents.len());
+            let row = if given.is_empty() {
+                probs.join("  ")
+            } else {
+                format!("{} | {}", given.join("  "), probs.join("  "))
+            };
+            s.push_str(row.trim_end());
+            s.push('\n');
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bonus;
+
+    #[test]
+    fn dot_draws_each_arc_and_highlights_evidence() {
+        let network = bonus();
+        let evidence = HashMap::from([("bonus", true)]);
+        let dot = network.to_dot_with(&DiagramOptions {
+            evidence: Some(&evidence),
+            ..DiagramOptions::default()
+        });
+        assert_eq!(dot.matches(" -> ").count(), 4);
+        assert!(dot.contains("\"performance\" -> \"mood\";"));
+        assert!(dot.contains("\"bonus\" [label=\"bonus = true\", style=\"rounded,filled\""));
+        assert!(dot.contains("\"feast\" [label=\"feast\"];"));
+    }
+
+    #[test]
+    fn mermaid_labels_carry_the_marginals() {
+        let network = bonus();
+        let probabilities = network.infer(&HashMap::new());
+        let mermaid = network.to_mermaid_with(&DiagramOptions {
+            probabilities: Some(&probabilities),
+            ..DiagramOptions::default()
+        });
+        let performance = network.get_node_index("performance");
+        assert!(mermaid.contains(&format!(
+            "    n{}[\"performance<br/>true: 0.0100<br/>false: 0.9900\"]\n",
+            performance
+        )));
+        assert!(!mermaid.contains("classDef"));
+    }
+
+    #[test]
+    fn svg_is_well_formed_and_layered_by_depth() {
+        let network = bonus();
+        let svg = network.to_svg_with(&DiagramOptions {
+            states: true,
+            ..DiagramOptions::default()
+        });
+        let document = roxmltree::Document::parse(&svg).unwrap();
+        let rects = document
+            .descendants()
+            .filter(|node| node.has_tag_name("rect"))
+            .map(|node| node.attribute("y").unwrap().parse::<f64>().unwrap())
+            .collect::<Vec<f64>>();
+        let lines = document
+            .descendants()
+            .filter(|node| node.has_tag_name("line"))
+            .count();
+        assert_eq!(lines, 4);
+        // Roots, then mood, then its children.
+        let [performance, horse_race, mood, bonus, feast] = rects[..] else {
+            panic!("expected five nodes");
+        };
+        assert_eq!(performance, horse_race);
+        assert!(mood > performance);
+        assert_eq!(bonus, feast);
+        assert!(bonus > mood);
+    }
+
+    #[test]
+    fn cpt_table_aligns_parents_and_states() {
+        assert_eq!(
+            bonus().cpt_table("bonus"),
+            "mood  | true    false\ntrue  | 0.3000  0.7000\nfalse | 0.0100  0.9900\n"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, Probability},
+    credal::set_row,
+    hybrid::standard_normal,
+};
+
+/// The spread of a posterior probability over the Dirichlet posteriors of the CPT rows, as
+/// returned by `credible_interval`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CredibleInterval {
+    pub mean: Probability,
+    pub std_dev: Probability,
+    pub lower: Probability,
+    pub upper: Probability,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the Dirichlet parameters of the CPT row of a node for the given parent values, as
+    /// estimated by the last `fit`. A root node has a single row, with no parent values.
+    pub fn get_dirichlet(
+        &self,
+        name: &str,
+        parent_values: &[T],
+    ) -> Option<&HashMap<T, Probability>> {
+        match self.node_map.get(name) {
+            Some(id) => self.nodes[*id].dirichlet.as_ref()?.get(parent_values),
+            None => panic!("Node `{}` not found", name),
+        }
+    }
+
+    /// Get the mean, standard deviation and central `level` credible interval of the
+    /// probability of a node taking `value` given `evidence`, by drawing `samples` sets of
+    /// CPT rows from their Dirichlet posteriors. Rows without a posterior keep their
+    /// probabilities, so the interval is a point for a network that was never fitted.
+    pub fn credible_interval(
+        &self,
+        name: &str,
+        value: &T,
+        evidence: &HashMap<&str, T>,
+        level: Probability,
+        samples: usize,
+    ) -> CredibleInterval {
+        self.credible_interval_with_rng(
+            name,
+            value,
+            evidence,
+            level,
+            samples,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Get a credible interval like `credible_interval`, sampling with the given random
+    /// number generator.
+    pub fn credible_interval_with_rng<R: Rng + ?Sized>(
+        &self,
+        name: &str,
+        value: &T,
+        evidence: &HashMap<&str, T>,
+        level: Probability,
+        samples: usize,
+        rng: &mut R,
+    ) -> CredibleInterval {
+        let target = match self.node_map.get(name) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", name),
+        };
+        if !(0.0..=1.0).contains(&level) {
+            panic!("Credible level {} is outside 0 and 1", level);
+        }
+        let mut domains = self
+            .nodes
+            .iter()
+            .map(|node| self.get_node_domain(node.id))
+            .collect::<Vec<Vec<T>>>();
+        for (id, observed) in self.evidence_ids(evidence) {
+            domains[id] = vec![observed];
+        }
+        let mut joint = domains.clone();
+        joint[target].retain(|state| state == value);
+
+        let mut network = self.copy();
+        let mut probabilities = Vec::with_capacity(samples);
+        for _ in 0..samples.max(1) {
+            // Nodes tied to a shared CPT share its draw.
+            let mut shared: HashMap<(&Name, &Vec<T>), HashMap<T, Probability>> = HashMap::new();
+            for node in &self.nodes {
+                let dirichlet = match &node.dirichlet {
+                    Some(dirichlet) => dirichlet,
+                    None => continue,
+                };
+                for (key, alphas) in dirichlet {
+                    let row = match node
+                        .shared_cpt
+                        .as_ref()
+                        .and_then(|cpt| shared.get(&(cpt, key)))
+                    {
+                        Some(row) => row.clone(),
+                        None => match sample_dirichlet(alphas, rng) {
+                            Some(row) => row,
+                            None => continue,
+                        },
+                    };
+                    if let Some(cpt) = &node.shared_cpt {
+                        shared.insert((cpt, key), row.clone());
+                    }
+                    set_row(&mut network, node.id, key, row);
+                }
+            }
+            let (evidence, _) = network.eliminate(&domains, false);
+            if evidence <= 0.0 {
+                continue;
+            }
+            let (joint, _) = network.eliminate(&joint, false);
+            probabilities.push(joint / evidence);
+        }
+        if probabilities.is_empty() {
+            panic!("Evidence has zero probability");
+        }
+
+        probabilities.sort_by(|a, b| a.total_cmp(b));
+        let n = probabilities.len() as Probability;
+        let mean = probabilities.iter().sum::<Probability>() / n;
+        let variance = probabilities
+            .iter()
+            .map(|prob| (prob - mean).powi(2))
+            .sum::<Probability>()
+            / n;
+        let quantile = |q: Probability| {
+            let index = (q * (probabilities.len() - 1) as Probability).round() as usize;
+            probabilities[index]
+        };
+        CredibleInterval {
+            mean,
+            std_dev: variance.sqrt(),
+            lower: quantile((1.0 - level) / 2.0),
+            upper: quantile((1.0 + level) / 2.0),
+        }
+    }
+}
+
+/// Draw a distribution from the Dirichlet distribution with the given parameters, if any is
+/// positive.
+fn sample_dirichlet<T: Clone + Eq + Hash, R: Rng + ?Sized>(
+    alphas: &HashMap<T, Probability>,
+    rng: &mut R,
+) -> Option<HashMap<T, Probability>> {
+    let draws = alphas
+        .iter()
+        .map(|(value, alpha)| {
+            let draw = if *alpha > 0.0 {
+                sample_gamma(*alpha, rng)
+            } else {
+                0.0
+            };
+            (value.clone(), draw)
+        })
+        .collect::<HashMap<T, Probability>>();
+    let total = draws.values().sum::<Probability>();
+    if total <= 0.0 {
+        return None;
+    }
+    Some(
+        draws
+            .into_iter()
+            .map(|(value, draw)| (value, draw / total))
+            .collect(),
+    )
+}
+
+/// Draw from the gamma distribution with the given shape and unit scale, by the method of
+/// Marsaglia and Tsang.
+fn sample_gamma<R: Rng + ?Sized>(shape: f64, rng: &mut R) -> f64 {
+    if shape < 1.0 {
+        let u = 1.0 - rng.gen::<f64>();
+        return sample_gamma(shape + 1.0, rng) * u.powf(1.0 / shape);
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = standard_normal(rng);
+        let v = (1.0 + c * x).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u = 1.0 - rng.gen::<f64>();
+        if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::Dataset,
+        sampling::seeded_rng,
+        testing::{assert_close, brute_force_probability, graph, sprinkler, TOLERANCE},
+    };
+
+    /// A coin fitted to `heads` heads and `tails` tails with one pseudo count per side.
+    fn coin(heads: usize, tails: usize) -> BayesianNetwork<bool> {
+        let mut network = graph(&["coin"], &[]);
+        let mut dataset = Dataset::new(vec!["coin"]);
+        for _ in 0..heads {
+            dataset.add_row(vec![true]);
+        }
+        for _ in 0..tails {
+            dataset.add_row(vec![false]);
+        }
+        network.fit(&dataset, 1.0);
+        network
+    }
+
+    #[test]
+    fn interval_follows_the_beta_posterior() {
+        // Beta(8, 4): mean 2/3, standard deviation sqrt(32 / (144 * 13)), and central 95%
+        // interval from about .390 to .891.
+        let network = coin(7, 3);
+        assert_eq!(network.get_dirichlet("coin", &[]).unwrap()[&true], 8.0);
+        let interval = network.credible_interval_with_rng(
+            "coin",
+            &true,
+            &HashMap::new(),
+            0.95,
+            20000,
+            &mut seeded_rng(3),
+        );
+        assert_close(interval.mean, 8.0 / 12.0, 0.01);
+        assert_close(interval.std_dev, (32.0f64 / (144.0 * 13.0)).sqrt(), 0.01);
+        assert_close(interval.lower, 0.390, 0.02);
+        assert_close(interval.upper, 0.891, 0.02);
+    }
+
+    #[test]
+    fn more_data_narrows_the_interval() {
+        let interval = |network: BayesianNetwork<bool>| {
+            network.credible_interval_with_rng(
+                "coin",
+                &true,
+                &HashMap::new(),
+                0.9,
+                5000,
+                &mut seeded_rng(4),
+            )
+        };
+        let thin = interval(coin(7, 3));
+        let thick = interval(coin(700, 300));
+        assert!(thick.upper - thick.lower < (thin.upper - thin.lower) / 5.0);
+        assert_close(thick.mean, 701.0 / 1002.0, 0.005);
+    }
+
+    #[test]
+    fn unfitted_network_gives_a_point() {
+        let network = sprinkler();
+        let evidence = HashMap::from([("wet_grass", true)]);
+        let interval = network.credible_interval_with_rng(
+            "rain",
+            &true,
+            &evidence,
+            0.95,
+            10,
+            &mut seeded_rng(5),
+        );
+        let expected = brute_force_probability(&network, "rain", &true, &evidence);
+        assert_close(interval.mean, expected, TOLERANCE);
+        assert_close(interval.std_dev, 0.0, TOLERANCE);
+        assert_close(interval.lower, expected, TOLERANCE);
+        assert_close(interval.upper, expected, TOLERANCE);
+    }
+
+    #[test]
+    #[should_panic(expected = "Credible level 1.5 is outside 0 and 1")]
+    fn level_must_be_a_probability() {
+        coin(1, 1).credible_interval("coin", &true, &HashMap::new(), 1.5, 10);
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name},
+    dataset::Dataset,
+    error::Error,
+};
+
+/// The node metadata key under which `set_node_bins` records bin boundaries.
+const BINS_KEY: &str = "bins";
+
+/// How `Bins::fit` chooses the bin boundaries of a continuous variable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Binning {
+    /// `bins` bins of equal width between the smallest and largest value.
+    EqualWidth { bins: usize },
+    /// `bins` bins holding about the same number of values each.
+    EqualFrequency { bins: usize },
+    /// The recursive minimum description length splits of Fayyad and Irani, chosen to
+    /// predict the discrete column `target`.
+    Mdl { target: Name },
+}
+
+/// The boundaries of the bins of a continuous variable, in increasing order. Bin `i` holds
+/// the values from boundary `i - 1` (inclusive) up to boundary `i` (exclusive), the first
+/// and last bins being unbounded below and above.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bins {
+    boundaries: Vec<f64>,
+}
+
+impl Bins {
+    /// Create bins with the given boundaries.
+    pub fn new(boundaries: Vec<f64>) -> Bins {
+        if boundaries.iter().any(|boundary| boundary.is_nan()) {
+            panic!("Bin boundary is NaN");
+        }
+        let mut boundaries = boundaries;
+        boundaries.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        boundaries.dedup();
+        Bins { boundaries }
+    }
+
+    /// Choose bins for `values`. Supervised binning also needs the `targets` value of each
+    /// record.
+    pub fn fit(values: &[f64], binning: &Binning, targets: Option<&[String]>) -> Bins {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        match binning {
+            Binning::EqualWidth { bins } => {
+                let (min, max) = match (sorted.first(), sorted.last()) {
+                    (Some(min), Some(max)) => (*min, *max),
+                    _ => return Bins::new(Vec::new()),
+                };
+                let width = (max - min) / (*bins).max(1) as f64;
+                Bins::new((1..*bins).map(|i| min + width * i as f64).collect())
+            }
+            Binning::EqualFrequency { bins } => Bins::new(
+                (1..*bins)
+                    .filter_map(|i| sorted.get(i * sorted.len() / bins).copied())
+                    .filter(|boundary| Some(boundary) != sorted.first())
+                    .collect(),
+            ),
+            Binning::Mdl { target } => {
+                let targets = match targets {
+                    Some(targets) if targets.len() == values.len() => targets,
+                    _ => panic!("MDL binning needs a value of `{}` for every record", target),
+                };
+                let mut pairs = values
+                    .iter()
+                    .copied()
+                    .zip(targets.iter().map(|target| target.as_str()))
+                    .collect::<Vec<(f64, &str)>>();
+                pairs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                let mut boundaries = Vec::new();
+                mdl_split(&pairs, &mut boundaries);
+                Bins::new(boundaries)
+            }
+        }
+    }
+
+    /// Get the boundaries, in increasing order.
+    pub fn boundaries(&self) -> &[f64] {
+        &self.boundaries
+    }
+
+    /// Get the index of the bin holding `x`.
+    pub fn index(&self, x: f64) -> usize {
+        self.boundaries
+            .iter()
+            .take_while(|boundary| x >= **boundary)
+            .count()
+    }
+
+    /// Get the labels of the bins, such as `[1.5,3)`, in order.
+    pub fn labels(&self) -> Vec<String> {
+        (0..=self.boundaries.len())
+            .map(|index| self.label_of(index))
+            .collect()
+    }
+
+    /// Get the label of the bin holding `x`.
+    pub fn label(&self, x: f64) -> String {
+        self.label_of(self.index(x))
+    }
+
+    fn label_of(&self, index: usize) -> String {
+        let lower = match index {
+            0 => "-inf".to_string(),
+            _ => self.boundaries[index - 1].to_string(),
+        };
+        let upper = match self.boundaries.get(index) {
+            Some(boundary) => boundary.to_string(),
+            None => "inf".to_string(),
+        };
+        format!("[{},{})", lower, upper)
+    }
+}
+
+/// The entropy in bits of the class labels of `pairs`, with the number of distinct labels.
+fn class_entropy(pairs: &[(f64, &str)]) -> (f64, usize) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (_, class) in pairs {
+        *counts.entry(class).or_insert(0) += 1;
+    }
+    let n = pairs.len() as f64;
+    let entropy = counts
+        .values()
+        .map(|count| {
+            let p = *count as f64 / n;
+            -p * p.log2()
+        })
+        .sum();
+    (entropy, counts.len())
+}
+
+/// Add the accepted MDL cut points of `pairs`, sorted by value, to `boundaries`.
+fn mdl_split(pairs: &[(f64, &str)], boundaries: &mut Vec<f64>) {
+    let n = pairs.len();
+    if n < 2 {
+        return;
+    }
+    let (entropy, classes) = class_entropy(pairs);
+    let mut best: Option<(usize, f64)> = None;
+    for split in 1..n {
+        if pairs[split - 1].0 == pairs[split].0 {
+            continue;
+        }
+        let (left, _) = class_entropy(&pairs[..split]);
+        let (right, _) = class_entropy(&pairs[split..]);
+        let weighted = (split as f64 * left + (n - split) as f64 * right) / n as f64;
+        if best.is_none_or(|(_, best)| weighted < best) {
+            best = Some((split, weighted));
+        }
+    }
+    let (split, weighted) = match best {
+        Some(best) => best,
+        None => return,
+    };
+    let (left, left_classes) = class_entropy(&pairs[..split]);
+    let (right, right_classes) = class_entropy(&pairs[split..]);
+    let gain = entropy - weighted;
+    let delta = (3f64.powi(classes as i32) - 2.0).log2()
+        - (classes as f64 * entropy - left_classes as f64 * left - right_classes as f64 * right);
+    if gain <= (((n - 1) as f64).log2() + delta) / n as f64 {
+        return;
+    }
+    boundaries.push((pairs[split - 1].0 + pairs[split].0) / 2.0);
+    mdl_split(&pairs[..split], boundaries);
+    mdl_split(&pairs[split..], boundaries);
+}
+
+impl Dataset<String> {
+    /// Replace the numbers in a continuous column by the labels of the bins chosen for them
+    /// by `binning`, and return the bins.
+    pub fn discretize(&mut self, column: &str, binning: &Binning) -> Result<Bins, Error> {
+        let invalid = |message: String| Error::Invalid {
+            format: "CSV",
+            message,
+        };
+        let index = self
+            .column_index(column)
+            .ok_or_else(|| invalid(format!("column `{}` not found", column)))?;
+        let values = self
+            .rows()
+            .iter()
+            .map(|row| match row[index].trim().parse::<f64>() {
+                Ok(value) if !value.is_nan() => Ok(value),
+                _ => Err(invalid(format!(
+                    "value `{}` of column `{}` is not a number",
+                    row[index], column
+                ))),
+            })
+            .collect::<Result<Vec<f64>, Error>>()?;
+        let targets = match binning {
+            Binning::Mdl { target } => {
+                let target = self
+                    .column_index(target)
+                    .ok_or_else(|| invalid(format!("column `{}` not found", target)))?;
+                Some(
+                    self.rows()
+                        .iter()
+                        .map(|row| row[target].clone())
+                        .collect::<Vec<String>>(),
+                )
+            }
+            _ => None,
+        };
+        let bins = Bins::fit(&values, binning, targets.as_deref());
+        for (row, value) in self.rows_mut().iter_mut().zip(values) {
+            row[index] = bins.label(value);
+        }
+        Ok(bins)
+    }
+}
+
+impl BayesianNetwork<String> {
+    /// Record the bins of a discretized node in its metadata, so continuous values can be
+    /// mapped to its states with `bin_value`.
+    pub fn set_node_bins(&mut self, name: &str, bins: &Bins) {
+        let boundaries = bins
+            .boundaries()
+            .iter()
+            .map(|boundary| boundary.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        self.set_node_metadata(name, BINS_KEY, &boundaries);
+    }
+
+    /// Get the bins recorded for a node by `set_node_bins`.
+    pub fn get_node_bins(&self, name: &str) -> Option<Bins> {
+        let boundaries = self.get_node_metadata(name, BINS_KEY)?;
+        if boundaries.is_empty() {
+            return Some(Bins::new(Vec::new()));
+        }
+        boundaries
+            .split(',')
+            .map(|boundary| boundary.parse::<f64>().ok())
+            .collect::<Option<Vec<f64>>>()
+            .map(Bins::new)
+    }
+
+    /// Map a continuous value to the state of a discretized node whose bin holds it.
+    pub fn bin_value(&self, name: &str, x: f64) -> String {
+        match self.get_node_bins(name) {
+            Some(bins) => bins.label(x),
+            None => panic!("Node `{}` has no bins", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bayesian_network::NodeType;
+
+    fn numbers(range: std::ops::RangeInclusive<i32>) -> Vec<f64> {
+        range.map(f64::from).collect()
+    }
+
+    #[test]
+    fn equal_width_splits_the_range_evenly() {
+        let bins = Bins::fit(&numbers(0..=10), &Binning::EqualWidth { bins: 5 }, None);
+        assert_eq!(bins.boundaries(), [2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(bins.index(-5.0), 0);
+        // Each bin holds its lower boundary.
+        assert_eq!(bins.index(2.0), 1);
+        assert_eq!(bins.index(100.0), 4);
+        assert_eq!(
+            bins.labels(),
+            ["[-inf,2)", "[2,4)", "[4,6)", "[6,8)", "[8,inf)"]
+        );
+        assert_eq!(bins.label(7.5), "[6,8)");
+    }
+
+    #[test]
+    fn equal_frequency_balances_the_counts() {
+        let values = numbers(1..=8);
+        let bins = Bins::fit(&values, &Binning::EqualFrequency { bins: 4 }, None);
+        assert_eq!(bins.boundaries(), [3.0, 5.0, 7.0]);
+        let mut counts = [0; 4];
+        for value in &values {
+            counts[bins.index(*value)] += 1;
+        }
+        assert_eq!(counts, [2, 2, 2, 2]);
+        // A boundary at the smallest value would leave the first bin empty.
+        let skewed = Bins::fit(
+            &[1.0, 1.0, 1.0, 1.0, 2.0, 3.0],
+            &Binning::EqualFrequency { bins: 2 },
+            None,
+        );
+        assert!(skewed.boundaries().is_empty());
+    }
+
+    #[test]
+    fn mdl_cuts_only_where_the_class_changes() {
+        let values = numbers(1..=20);
+        let targets = values
+            .iter()
+            .map(|value| if *value <= 10.0 { "a" } else { "b" }.to_string())
+            .collect::<Vec<String>>();
+        let binning = Binning::Mdl {
+            target: "class".to_string(),
+        };
+        let bins = Bins::fit(&values, &binning, Some(&targets));
+        assert_eq!(bins.boundaries(), [10.5]);
+        // A single class gives nothing to predict.
+        let same = vec!["a".to_string(); values.len()];
+        assert!(Bins::fit(&values, &binning, Some(&same))
+            .boundaries()
+            .is_empty());
+    }
+
+    #[test]
+    fn discretize_replaces_the_column_by_bin_labels() {
+        let mut dataset = Dataset::new(vec!["x", "y"]);
+        for (x, y) in [("0", "a"), ("2.5", "a"), ("7.5", "b"), ("10", "b")] {
+            dataset.add_row(vec![x.to_string(), y.to_string()]);
+        }
+        let bins = dataset
+            .discretize("x", &Binning::EqualWidth { bins: 2 })
+            .unwrap();
+        assert_eq!(bins.boundaries(), [5.0]);
+        let column = dataset
+            .rows()
+            .iter()
+            .map(|row| row[0].as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(column, ["[-inf,5)", "[-inf,5)", "[5,inf)", "[5,inf)"]);
+        assert!(matches!(
+            dataset.discretize("x", &Binning::EqualWidth { bins: 2 }),
+            Err(Error::Invalid { .. })
+        ));
+        assert!(dataset
+            .discretize("z", &Binning::EqualWidth { bins: 2 })
+            .is_err());
+    }
+
+    #[test]
+    fn node_bins_round_trip_through_metadata() {
+        let mut network = BayesianNetwork::new(vec!["[-inf,5)".to_string(), "[5,inf)".to_string()]);
+        network.add_node(
+            "x",
+            NodeType::Root(HashMap::from([
+                ("[-inf,5)".to_string(), 0.5),
+                ("[5,inf)".to_string(), 0.5),
+            ])),
+        );
+        assert_eq!(network.get_node_bins("x"), None);
+        network.set_node_bins("x", &Bins::new(vec![5.0]));
+        assert_eq!(network.get_node_bins("x"), Some(Bins::new(vec![5.0])));
+        assert_eq!(network.bin_value("x", 6.0), "[5,inf)");
+    }
+}
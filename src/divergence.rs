@@ -0,0 +1,188 @@
+use rand::Rng;
+use std::{fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, Probability},
+    posterior::Posterior,
+};
+
+impl<T: Clone + PartialEq> Posterior<T> {
+    /// Get the KL divergence of each node's distribution in `other` from its distribution
+    /// here, in nats, in the order of `nodes`. It is infinite for a node that `other` gives
+    /// probability 0 to a state this posterior does not.
+    pub fn kl_divergence(&self, other: &Posterior<T>) -> Vec<(Name, f64)> {
+        self.paired(other)
+            .into_iter()
+            .map(|(name, p, q)| (name, kl(&p, &q)))
+            .collect()
+    }
+
+    /// Get the Jensen-Shannon divergence between each node's distributions here and in
+    /// `other`, in nats, in the order of `nodes`: symmetric and at most ln 2.
+    pub fn js_divergence(&self, other: &Posterior<T>) -> Vec<(Name, f64)> {
+        self.paired(other)
+            .into_iter()
+            .map(|(name, p, q)| {
+                let m = p
+                    .iter()
+                    .zip(&q)
+                    .map(|(p, q)| (p + q) / 2.0)
+                    .collect::<Vec<Probability>>();
+                (name, (kl(&p, &m) + kl(&q, &m)) / 2.0)
+            })
+            .collect()
+    }
+
+    /// The probabilities of each node's states here and in `other`, over the states here.
+    fn paired(&self, other: &Posterior<T>) -> Vec<(Name, Vec<Probability>, Vec<Probability>)> {
+        self.marginals
+            .iter()
+            .map(|(name, states)| {
+                if other.distribution(name).is_none() {
+                    panic!("Node `{}` not found", name);
+                }
+                let p = states.iter().map(|(_, prob)| *prob).collect();
+                let q = states
+                    .iter()
+                    .map(|(value, _)| other.probability(name, value).unwrap_or(0.0))
+                    .collect();
+                (name.clone(), p, q)
+            })
+            .collect()
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Estimate the KL divergence of the joint distribution of `other` from that of this
+    /// network, in nats, from `n` records drawn from this network. Both networks must have the
+    /// same nodes.
+    pub fn kl_divergence(&self, other: &BayesianNetwork<T>, n: usize) -> f64 {
+        self.kl_divergence_with_rng(other, n, &mut rand::thread_rng())
+    }
+
+    /// Estimate the KL divergence like `kl_divergence`, using `rng`.
+    pub fn kl_divergence_with_rng<R: Rng + ?Sized>(
+        &self,
+        other: &BayesianNetwork<T>,
+        n: usize,
+        rng: &mut R,
+    ) -> f64 {
+        self.check_same_nodes(other);
+        let records = self.sample_with_rng(n, rng);
+        (0..records.len())
+            .map(|index| {
+                let record = records.record(index);
+                self.log_likelihood(&record) - other.log_likelihood(&record)
+            })
+            .sum::<f64>()
+            / n as f64
+    }
+
+    /// Estimate the Jensen-Shannon divergence between the joint distributions of this network
+    /// and `other`, in nats, from `n` records drawn from each. Both networks must have the same
+    /// nodes.
+    pub fn js_divergence(&self, other: &BayesianNetwork<T>, n: usize) -> f64 {
+        self.js_divergence_with_rng(other, n, &mut rand::thread_rng())
+    }
+
+    /// Estimate the Jensen-Shannon divergence like `js_divergence`, using `rng`.
+    pub fn js_divergence_with_rng<R: Rng + ?Sized>(
+        &self,
+        other: &BayesianNetwork<T>,
+        n: usize,
+        rng: &mut R,
+    ) -> f64 {
+        self.check_same_nodes(other);
+        // Each half is the KL divergence of the mixture from one network, estimated from
+        // that network's records.
+        let half = |from: &BayesianNetwork<T>, to: &BayesianNetwork<T>, rng: &mut R| {
+            let records = from.sample_with_rng(n, rng);
+            (0..records.len())
+                .map(|index| {
+                    let record = records.record(index);
+                    let p = from.likelihood(&record);
+                    let q = to.likelihood(&record);
+                    (2.0 * p / (p + q)).ln()
+                })
+                .sum::<f64>()
+                / n as f64
+        };
+        (half(self, other, rng) + half(other, self, rng)) / 2.0
+    }
+
+    fn check_same_nodes(&self, other: &BayesianNetwork<T>) {
+        for name in self.node_map.keys().chain(other.node_map.keys()) {
+            if !self.node_map.contains_key(name) || !other.node_map.contains_key(name) {
+                panic!("Node `{}` is not in both networks", name);
+            }
+        }
+    }
+}
+
+/// The KL divergence of `q` from `p`, in nats.
+fn kl(p: &[Probability], q: &[Probability]) -> f64 {
+    p.iter()
+        .zip(q)
+        .filter(|(p, _)| **p > 0.0)
+        .map(|(p, q)| p * (p / q).ln())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        sampling::seeded_rng,
+        testing::{assert_close, bonus, confounded, joint, row, TOLERANCE},
+    };
+
+    #[test]
+    fn posterior_divergences_compare_each_node() {
+        let network = bonus();
+        let prior = network.posterior(&HashMap::new());
+        let posterior = network.posterior(&HashMap::from([("performance", true)]));
+        let kl = posterior.kl_divergence(&prior);
+        assert_eq!(kl[0].0, "performance");
+        assert_close(kl[0].1, (1.0 / 0.01_f64).ln(), TOLERANCE);
+        assert_eq!(kl[1], ("horse_race".to_string(), 0.0));
+        // Against the mixture, the posterior is [1, 0] and the prior is [.01, .99].
+        let js = posterior.js_divergence(&prior);
+        let mixture = (1.0 / 0.505_f64).ln()
+            + 0.01 * (0.01 / 0.505_f64).ln()
+            + 0.99 * (0.99 / 0.495_f64).ln();
+        assert_close(js[0].1, mixture / 2.0, TOLERANCE);
+        assert!(js.iter().all(|(_, js)| *js <= 2.0_f64.ln()));
+    }
+
+    #[test]
+    fn network_divergences_estimate_the_joint_divergences() {
+        let p = confounded();
+        let mut q = confounded();
+        let x = q.get_node_index("x");
+        q.nodes[x].probability.insert(vec![false], row(0.6));
+        let (p_joint, q_joint) = (joint(&p), joint(&q));
+        let mut kl = 0.0;
+        let mut js = 0.0;
+        for ((_, p), (_, q)) in p_joint.iter().zip(&q_joint) {
+            let m = (p + q) / 2.0;
+            kl += p * (p / q).ln();
+            js += (p * (p / m).ln() + q * (q / m).ln()) / 2.0;
+        }
+        assert_close(
+            p.kl_divergence_with_rng(&q, 20_000, &mut seeded_rng(6)),
+            kl,
+            0.01,
+        );
+        assert_close(
+            p.js_divergence_with_rng(&q, 20_000, &mut seeded_rng(6)),
+            js,
+            0.01,
+        );
+        assert_close(
+            p.kl_divergence_with_rng(&p, 100, &mut seeded_rng(6)),
+            0.0,
+            TOLERANCE,
+        );
+    }
+}
@@ -0,0 +1,398 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, Node, NodeId, NodeType, Probability},
+    dataset::Dataset,
+    particle_filter::ParticleFilter,
+};
+
+/// A dynamic Bayesian network in two-slice form: `prior` describes the first time slice, and
+/// `transition` describes any later slice together with the nodes of the previous slice it
+/// depends on. The interface pairs each such previous-slice node with the node of the slice
+/// it stands for.
+pub struct DynamicBayesianNetwork<T: Clone + PartialEq + Eq + Hash + Debug> {
+    pub(crate) prior: BayesianNetwork<T>,
+    pub(crate) transition: BayesianNetwork<T>,
+    pub(crate) interface: Vec<(Name, Name)>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> DynamicBayesianNetwork<T> {
+    /// Create a dynamic network. `interface` pairs each node of `transition` that stands for
+    /// the previous slice with the node whose value it takes, such as `("Rain_prev", "Rain")`;
+    /// the second node of each pair must be in both networks.
+    pub fn new(
+        prior: BayesianNetwork<T>,
+        transition: BayesianNetwork<T>,
+        interface: &[(&str, &str)],
+    ) -> DynamicBayesianNetwork<T> {
+        for (previous, current) in interface {
+            if !transition.node_map.contains_key(*previous) {
+                panic!("Node `{}` not found in the transition slice", previous);
+            }
+            if !transition.node_map.contains_key(*current) {
+                panic!("Node `{}` not found in the transition slice", current);
+            }
+            if !prior.node_map.contains_key(*current) {
+                panic!("Node `{}` not found in the prior slice", current);
+            }
+        }
+        DynamicBayesianNetwork {
+            prior,
+            transition,
+            interface: interface
+                .iter()
+                .map(|(previous, current)| (previous.to_string(), current.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Get the network of the first time slice.
+    pub fn prior(&self) -> &BayesianNetwork<T> {
+        &self.prior
+    }
+
+    /// Get the network of a later slice with its previous-slice nodes.
+    pub fn transition(&self) -> &BayesianNetwork<T> {
+        &self.transition
+    }
+
+    /// Get the (previous-slice node, current-slice node) pairs.
+    pub fn interface(&self) -> Vec<(&str, &str)> {
+        self.interface
+            .iter()
+            .map(|(previous, current)| (previous.as_str(), current.as_str()))
+            .collect()
+    }
+
+    /// The nodes of a slice: every node of the prior slice for slice 0, and every node of the
+    /// transition slice except the previous-slice ones afterwards.
+    pub fn slice_nodes(&self, slice: usize) -> Vec<&str> {
+        if slice == 0 {
+            return self.prior.node_names();
+        }
+        self.transition
+            .node_names()
+            .into_iter()
+            .filter(|name| !self.interface.iter().any(|(previous, _)| previous == name))
+            .collect()
+    }
+
+    /// Unroll the first `t` time slices into a flat network, naming the copy of node `X` in
+    /// slice `i` `X[i]`. The copies of `X` in every slice after the first are tied to the
+    /// shared CPT `X.transition`, so `fit` on the unrolled network pools their counts. A
+    /// compact CPT, such as a function, is copied as it is instead.
+    pub fn unroll(&self, t: usize) -> BayesianNetwork<T> {
+        if t == 0 {
+            panic!("Cannot unroll zero time slices");
+        }
+        let mut value_space = self.prior.value_space.clone();
+        for value in &self.transition.value_space {
+            if !value_space.contains(value) {
+                value_space.push(value.clone());
+            }
+        }
+        let mut unrolled = BayesianNetwork::new(value_space);
+        // The id of each node of the previous slice, by name in its source network.
+        let mut previous_slice: HashMap<&str, NodeId> = HashMap::new();
+        for slice in 0..t {
+            let source = if slice == 0 {
+                &self.prior
+            } else {
+                &self.transition
+            };
+            let mut current_slice = HashMap::new();
+            for name in self.slice_nodes(slice) {
+                current_slice.insert(name, unrolled.nodes.len() + current_slice.len());
+            }
+            for name in self.slice_nodes(slice) {
+                let node = &source.nodes[source.node_map[name]];
+                let id = current_slice[name];
+                let parents = node
+                    .parents
+                    .iter()
+                    .map(|parent| {
+                        let parent = source.get_node_name(*parent);
+                        match self
+                            .interface
+                            .iter()
+                            .find(|(previous, _)| previous == parent)
+                        {
+                            Some((_, current)) if slice > 0 => previous_slice[current.as_str()],
+                            _ => current_slice[parent],
+                        }
+                    })
+                    .collect::<Vec<NodeId>>();
+                unrolled.nodes.push(Node {
+                    id,
+                    parents,
+                    children: Vec::new(),
+                    probability: node.probability.clone(),
+                    node_type: node.node_type.clone(),
+                    states: node.states.clone(),
+                    metadata: node.metadata.clone(),
+                    shared_cpt: None,
+                    dirichlet: node.dirichlet.clone(),
+                    compact_cpt: node.compact_cpt.clone(),
+                });
+                unrolled.node_map.insert(format!("{}[{}]", name, slice), id);
+            }
+            previous_slice = current_slice;
+        }
+
+        for id in 0..unrolled.nodes.len() {
+            for parent in unrolled.nodes[id].parents.clone() {
+                unrolled.nodes[parent].children.push(id);
+            }
+        }
+        // Every later slice shares the CPTs of the transition slice, except compact CPTs,
+        // which each copy keeps.
+        if t > 1 {
+            for name in self.slice_nodes(1) {
+                let node = &self.transition.nodes[self.transition.node_map[name]];
+                if node.compact_cpt.is_some() {
+                    continue;
+                }
+                let cpt_name = format!("{}.transition", name);
+                let cpt = match &node.node_type {
+                    NodeType::Root(prior) => HashMap::from([(Vec::new(), prior.clone())]),
+                    _ => node.probability.clone(),
+                };
+                unrolled.shared_cpts.insert(cpt_name.clone(), cpt);
+                for slice in 1..t {
+                    let id = unrolled.node_map[&format!("{}[{}]", name, slice)];
+                    unrolled.nodes[id].shared_cpt = Some(cpt_name.clone());
+                }
+            }
+        }
+        // A node that feeds the next slice can no longer be a leaf, and one that closes the
+        // last slice no longer needs to be inner.
+        for node in &mut unrolled.nodes {
+            if !matches!(node.node_type, NodeType::Root(_)) {
+                node.node_type = if node.children.is_empty() {
+                    NodeType::Leaf
+                } else {
+                    NodeType::Inner
+                };
+            }
+        }
+        unrolled
+    }
+
+    /// Estimate the CPTs of both slices from fully observed sequences, one row per time
+    /// slice and one column per slice node: the prior slice from the first row of each
+    /// sequence and the transition slice from every pair of consecutive rows. `pseudo_count`
+    /// is added as by `BayesianNetwork::fit`.
+    pub fn fit(&mut self, sequences: &[Dataset<T>], pseudo_count: Probability) {
+        let prior_nodes = self.prior.node_names();
+        let mut first = Dataset::new(prior_nodes.clone());
+        let transition_nodes = self.transition.node_names();
+        let mut pairs = Dataset::new(transition_nodes.clone());
+        for sequence in sequences {
+            let column = |name: &str| {
+                sequence
+                    .column_index(name)
+                    .unwrap_or_else(|| panic!("Dataset column `{}` not found", name))
+            };
+            let rows = sequence.rows();
+            if let Some(row) = rows.first() {
+                first.add_row(
+                    prior_nodes
+                        .iter()
+                        .map(|name| row[column(name)].clone())
+                        .collect(),
+                );
+            }
+            for window in rows.windows(2) {
+                pairs.add_row(
+                    transition_nodes
+                        .iter()
+                        .map(|name| {
+                            match self.interface.iter().find(|(previous, _)| previous == name) {
+                                Some((_, current)) => window[0][column(current)].clone(),
+                                None => window[1][column(name)].clone(),
+                            }
+                        })
+                        .collect(),
+                );
+            }
+        }
+        self.prior.fit(&first, pseudo_count);
+        if !pairs.is_empty() {
+            self.transition.fit(&pairs, pseudo_count);
+        }
+    }
+
+    /// Create a particle filter with `n` particles over the slices of the network.
+    pub fn particle_filter(&self, n: usize) -> ParticleFilter<'_, T> {
+        ParticleFilter::new(&self.prior, &self.transition, &self.interface(), n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, evidence_probability, umbrella, TOLERANCE};
+
+    #[test]
+    fn unroll_copies_each_slice_and_links_the_interface() {
+        let network = umbrella();
+        assert_eq!(network.slice_nodes(0), vec!["Rain", "Umbrella"]);
+        assert_eq!(network.slice_nodes(1), vec!["Rain", "Umbrella"]);
+        let unrolled = network.unroll(3);
+        assert_eq!(
+            unrolled.node_names(),
+            vec![
+                "Rain[0]",
+                "Umbrella[0]",
+                "Rain[1]",
+                "Umbrella[1]",
+                "Rain[2]",
+                "Umbrella[2]"
+            ]
+        );
+        let rain = |slice: usize| unrolled.get_node_index(&format!("Rain[{}]", slice));
+        assert_eq!(unrolled.nodes[rain(2)].parents, vec![rain(1)]);
+        assert!(matches!(unrolled.nodes[rain(1)].node_type, NodeType::Inner));
+        assert!(matches!(
+            unrolled.nodes[rain(0)].node_type,
+            NodeType::Root(_)
+        ));
+        // Later slices share the transition CPTs.
+        assert_eq!(
+            unrolled.nodes[rain(2)].shared_cpt.as_deref(),
+            Some("Rain.transition")
+        );
+        assert_eq!(unrolled.nodes[rain(0)].shared_cpt, None);
+        assert!(unrolled.validate().is_empty());
+    }
+
+    #[test]
+    fn unrolled_joint_is_the_product_over_slices() {
+        let unrolled = umbrella().unroll(2);
+        let evidence = HashMap::from([
+            ("Rain[0]", true),
+            ("Umbrella[0]", true),
+            ("Rain[1]", true),
+            ("Umbrella[1]", false),
+        ]);
+        assert_close(
+            evidence_probability(&unrolled, &evidence),
+            0.5 * 0.9 * 0.7 * 0.1,
+            TOLERANCE,
+        );
+        // Rain persists: P(Rain[1]) = 0.5 * 0.7 + 0.5 * 0.3.
+        let probabilities = unrolled.infer(&HashMap::new());
+        assert_close(
+            unrolled.get_inferred_probability(&probabilities, "Rain[1]", true),
+            0.5,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Node `Rain_before` not found in the transition slice")]
+    fn interface_nodes_must_exist() {
+        let network = umbrella();
+        DynamicBayesianNetwork::new(
+            network.prior().copy(),
+            network.transition().copy(),
+            &[("Rain_before", "Rain")],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot unroll zero time slices")]
+    fn unroll_needs_a_slice() {
+        umbrella().unroll(0);
+    }
+
+    /// Three sequences of (rain, umbrella) days.
+    fn sequences() -> Vec<Dataset<bool>> {
+        [
+            [(true, true), (true, false), (false, false)],
+            [(false, false), (false, true), (true, true)],
+            [(true, true), (true, true), (true, true)],
+        ]
+        .iter()
+        .map(|days| {
+            let mut sequence = Dataset::new(vec!["Rain", "Umbrella"]);
+            for (rain, umbrella) in days {
+                sequence.add_row(vec![*rain, *umbrella]);
+            }
+            sequence
+        })
+        .collect()
+    }
+
+    #[test]
+    fn fit_counts_first_days_and_consecutive_pairs() {
+        let mut network = umbrella();
+        network.fit(&sequences(), 0.0);
+        let probability = |slice: &BayesianNetwork<bool>, name: &str, key: Vec<bool>| {
+            let node = &slice.nodes[slice.get_node_index(name)];
+            match &node.node_type {
+                NodeType::Root(prior) => prior[&true],
+                _ => node.probability[&key][&true],
+            }
+        };
+        // The first days: rain twice out of three, always with an umbrella.
+        let prior = network.prior();
+        assert_close(probability(prior, "Rain", vec![]), 2.0 / 3.0, TOLERANCE);
+        assert_close(probability(prior, "Umbrella", vec![true]), 1.0, TOLERANCE);
+        assert_close(probability(prior, "Umbrella", vec![false]), 0.0, TOLERANCE);
+        // The six later days, each with the day before.
+        let transition = network.transition();
+        assert_close(probability(transition, "Rain", vec![true]), 0.75, TOLERANCE);
+        assert_close(probability(transition, "Rain", vec![false]), 0.5, TOLERANCE);
+        assert_close(
+            probability(transition, "Umbrella", vec![true]),
+            0.75,
+            TOLERANCE,
+        );
+        assert_close(
+            probability(transition, "Umbrella", vec![false]),
+            0.5,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn fit_matches_the_pooled_fit_of_the_unrolled_network() {
+        let mut network = umbrella();
+        network.fit(&sequences(), 1.0);
+        let fitted = network.unroll(3);
+
+        let mut unrolled = umbrella().unroll(3);
+        let columns = unrolled.node_names();
+        let mut dataset = Dataset::new(columns.clone());
+        for sequence in sequences() {
+            dataset.add_row(
+                columns
+                    .iter()
+                    .map(|name| {
+                        let (node, slice) = name.trim_end_matches(']').split_once('[').unwrap();
+                        let row = &sequence.rows()[slice.parse::<usize>().unwrap()];
+                        row[sequence.column_index(node).unwrap()]
+                    })
+                    .collect(),
+            );
+        }
+        unrolled.fit(&dataset, 1.0);
+        match (&unrolled.nodes[0].node_type, &fitted.nodes[0].node_type) {
+            (NodeType::Root(prior), NodeType::Root(expected)) => {
+                for (value, prob) in prior {
+                    assert_close(*prob, expected[value], TOLERANCE);
+                }
+            }
+            _ => panic!("`Rain[0]` is not a root"),
+        }
+        for node in &unrolled.nodes {
+            let expected = &fitted.nodes[node.id].probability;
+            for (key, row) in &node.probability {
+                for (value, prob) in row {
+                    assert_close(*prob, expected[key][value], TOLERANCE);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    learning::configurations,
+};
+
+/// How strongly a parent influences a child, as returned by `edge_strengths`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeStrength {
+    pub parent: Name,
+    pub child: Name,
+    /// The mutual information between the parent and the child given the other parents of
+    /// the child, in nats, under the joint distribution of the network.
+    pub mutual_information: f64,
+    /// The largest KL divergence, in nats, between two CPT rows of the child that differ only
+    /// in the value of the parent. It is infinite if one row rules out a value the other
+    /// allows.
+    pub max_divergence: f64,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the strength of every edge, from the strongest to the weakest by mutual
+    /// information. Edges whose strength is close to zero can be removed with little change to
+    /// the joint distribution.
+    pub fn edge_strengths(&self) -> Vec<EdgeStrength> {
+        let mut strengths = Vec::new();
+        for node in &self.nodes {
+            for position in 0..node.parents.len() {
+                strengths.push(self.strength_of(node.id, position));
+            }
+        }
+        strengths.sort_by(|a, b| b.mutual_information.total_cmp(&a.mutual_information));
+        strengths
+    }
+
+    /// Get the strength of the edge from `parent` to `child`.
+    pub fn edge_strength(&self, parent: &str, child: &str) -> EdgeStrength {
+        let position = self.edge_position(parent, child);
+        self.strength_of(self.node_map[child], position)
+    }
+
+    /// The strength of the edge into `child` from its parent at `position`.
+    fn strength_of(&self, child: NodeId, position: usize) -> EdgeStrength {
+        let node = &self.nodes[child];
+        let domain = self.get_node_domain(child);
+        let parent_domains = node
+            .parents
+            .iter()
+            .map(|parent| self.get_node_domain(*parent))
+            .collect::<Vec<Vec<T>>>();
+
+        // Rows grouped by the values of the other parents, with their probabilities.
+        let mut groups: HashMap<Vec<T>, Vec<(Probability, Vec<Probability>)>> = HashMap::new();
+        for configuration in configurations(&parent_domains) {
+            let assignment = node
+                .parents
+                .iter()
+                .copied()
+                .zip(configuration.iter().cloned())
+                .collect::<HashMap<NodeId, T>>();
+            let weight = self.assignment_probability(&assignment);
+            let row = domain
+                .iter()
+                .map(|value| self.local_probability(node, value, &assignment))
+                .collect::<Vec<Probability>>();
+            let mut others = configuration;
+            others.remove(position);
+            groups.entry(others).or_default().push((weight, row));
+        }
+
+        let mut mutual_information = 0.0;
+        let mut max_divergence: f64 = 0.0;
+        for rows in groups.values() {
+            let total = rows.iter().map(|(weight, _)| weight).sum::<Probability>();
+            if total > 0.0 {
+                let mixture = (0..domain.len())
+                    .map(|i| {
+                        rows.iter()
+                            .map(|(weight, row)| weight * row[i])
+                            .sum::<f64>()
+                            / total
+                    })
+                    .collect::<Vec<Probability>>();
+                for (weight, row) in rows {
+                    mutual_information += weight * divergence(row, &mixture);
+                }
+            }
+            for (_, row) in rows {
+                for (_, other) in rows {
+                    max_divergence = max_divergence.max(divergence(row, other));
+                }
+            }
+        }
+        EdgeStrength {
+            parent: self.get_node_name(node.parents[position]).to_string(),
+            child: self.get_node_name(child).to_string(),
+            mutual_information: mutual_information.max(0.0),
+            max_divergence,
+        }
+    }
+}
+
+/// The KL divergence of `q` from `p`, in nats.
+fn divergence(p: &[Probability], q: &[Probability]) -> f64 {
+    p.iter()
+        .zip(q)
+        .filter(|(p, _)| **p > 0.0)
+        .map(|(p, q)| p * (p / q).ln())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, confounded, joint, TOLERANCE};
+
+    /// The mutual information between `a` and `b` given `given`, by enumeration.
+    fn conditional_mutual_information(
+        network: &BayesianNetwork<bool>,
+        a: &str,
+        b: &str,
+        given: &[&str],
+    ) -> f64 {
+        let marginal = |names: &[&str]| {
+            let mut marginal: HashMap<Vec<bool>, Probability> = HashMap::new();
+            for (assignment, prob) in joint(network) {
+                let key = names
+                    .iter()
+                    .map(|name| assignment[&network.get_node_index(name)])
+                    .collect();
+                *marginal.entry(key).or_default() += prob;
+            }
+            marginal
+        };
+        let with = |extra: &[&str]| {
+            let mut names = given.to_vec();
+            names.extend(extra);
+            marginal(&names)
+        };
+        let (abz, az, bz, z) = (with(&[a, b]), with(&[a]), with(&[b]), with(&[]));
+        abz.iter()
+            .filter(|(_, prob)| **prob > 0.0)
+            .map(|(key, prob)| {
+                let n = given.len();
+                let mut with_b = key[..n].to_vec();
+                with_b.push(key[n + 1]);
+                prob * (prob * z[&key[..n]] / (az[&key[..=n]] * bz[&with_b])).ln()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn mutual_information_matches_enumeration() {
+        let network = confounded();
+        let zx = network.edge_strength("z", "x");
+        assert_close(
+            zx.mutual_information,
+            conditional_mutual_information(&network, "z", "x", &[]),
+            TOLERANCE,
+        );
+        let xy = network.edge_strength("x", "y");
+        assert_close(
+            xy.mutual_information,
+            conditional_mutual_information(&network, "x", "y", &["z"]),
+            TOLERANCE,
+        );
+        let zy = network.edge_strength("z", "y");
+        assert_close(
+            zy.mutual_information,
+            conditional_mutual_information(&network, "z", "y", &["x"]),
+            TOLERANCE,
+        );
+        let strengths = network.edge_strengths();
+        assert_eq!(strengths.len(), 3);
+        assert!(strengths
+            .windows(2)
+            .all(|pair| pair[0].mutual_information >= pair[1].mutual_information));
+    }
+
+    #[test]
+    fn max_divergence_compares_rows_that_differ_in_the_parent() {
+        let kl = |p: f64, q: f64| p * (p / q).ln() + (1.0 - p) * ((1.0 - p) / (1.0 - q)).ln();
+        let network = confounded();
+        // The rows of y for x = true and x = false are .9 and .6 when z is true, and .5 and
+        // .1 when z is false.
+        let expected = [kl(0.9, 0.6), kl(0.6, 0.9), kl(0.5, 0.1), kl(0.1, 0.5)]
+            .into_iter()
+            .fold(0.0, f64::max);
+        assert_close(
+            network.edge_strength("x", "y").max_divergence,
+            expected,
+            TOLERANCE,
+        );
+        assert_close(
+            network.edge_strength("z", "x").max_divergence,
+            kl(0.8, 0.3).max(kl(0.3, 0.8)),
+            TOLERANCE,
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use crate::bayesian_network::Name;
+
+/// An error raised while reading or writing a network, or evaluating a query on it.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+    /// The input is not well-formed.
+    Syntax {
+        format: &'static str,
+        line: usize,
+        message: String,
+    },
+    /// The input is well-formed but does not describe a valid network.
+    Invalid {
+        format: &'static str,
+        message: String,
+    },
+    /// The query is well-formed but does not fit the network.
+    Query(String),
+    /// A dependency would close a directed cycle, given as the nodes along it from the new
+    /// parent back to itself.
+    CycleDetected(Vec<Name>),
+    /// A row of probabilities does not sum to 1 under a strict normalization policy. `name`
+    /// is the node or shared CPT, and `row` its parent values, empty for a prior.
+    Unnormalized { name: Name, row: String, sum: f64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+            Error::Syntax {
+                format,
+                line,
+                message,
+            } => write!(f, "{} syntax error at line {}: {}", format, line, message),
+            Error::Invalid { format, message } => {
+                write!(f, "Invalid {} network: {}", format, message)
+            }
+            Error::Query(message) => write!(f, "Invalid query: {}", message),
+            Error::CycleDetected(path) => write!(f, "Directed cycle: {}", path.join(" -> ")),
+            Error::Unnormalized { name, row, sum } if row.is_empty() => {
+                write!(f, "Probabilities of `{}` sum to {}", name, sum)
+            }
+            Error::Unnormalized { name, row, sum } => {
+                write!(
+                    f,
+                    "Probabilities of `{}` given {} sum to {}",
+                    name, row, sum
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_describes_each_error() {
+        let syntax = Error::Syntax {
+            format: "BIF",
+            line: 3,
+            message: "expected `;`".to_string(),
+        };
+        assert_eq!(
+            syntax.to_string(),
+            "BIF syntax error at line 3: expected `;`"
+        );
+        let cycle = Error::CycleDetected(vec!["a".to_string(), "b".to_string(), "a".to_string()]);
+        assert_eq!(cycle.to_string(), "Directed cycle: a -> b -> a");
+        let prior = Error::Unnormalized {
+            name: "a".to_string(),
+            row: String::new(),
+            sum: 1.5,
+        };
+        assert_eq!(prior.to_string(), "Probabilities of `a` sum to 1.5");
+        let row = Error::Unnormalized {
+            name: "b".to_string(),
+            row: "[true]".to_string(),
+            sum: 0.5,
+        };
+        assert_eq!(
+            row.to_string(),
+            "Probabilities of `b` given [true] sum to 0.5"
+        );
+    }
+
+    #[test]
+    fn io_errors_keep_their_source() {
+        let error = Error::from(std::io::Error::new(std::io::ErrorKind::NotFound, "missing"));
+        assert!(matches!(error, Error::Io(_)));
+        assert_eq!(
+            std::error::Error::source(&error).unwrap().to_string(),
+            "missing"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    convergence::{mixing, split},
+    posterior::Posterior,
+    sampling::PosteriorSampler,
+};
+
+/// The normal quantile for a two-sided 95% interval.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+/// A posterior estimated by sampling, as returned by `estimate_posterior`, with the Monte
+/// Carlo standard error of every probability.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PosteriorEstimate<T> {
+    posterior: Posterior<T>,
+    standard_errors: Vec<(Name, Vec<(T, f64)>)>,
+}
+
+impl<T: Clone + PartialEq> PosteriorEstimate<T> {
+    /// Get the estimated posterior.
+    pub fn posterior(&self) -> &Posterior<T> {
+        &self.posterior
+    }
+
+    /// Get the standard error of the estimated probability of a node taking `value`.
+    pub fn standard_error(&self, name: &str, value: &T) -> Option<f64> {
+        self.standard_errors
+            .iter()
+            .find(|(node, _)| node == name)?
+            .1
+            .iter()
+            .find(|(state, _)| state == value)
+            .map(|(_, error)| *error)
+    }
+
+    /// Get an approximate 95% confidence interval for the probability of a node taking
+    /// `value`, clipped to [0, 1].
+    pub fn interval(&self, name: &str, value: &T) -> Option<(Probability, Probability)> {
+        let prob = self.posterior.probability(name, value)?;
+        let error = self.standard_error(name, value)?;
+        Some((
+            (prob - Z_95 * error).max(0.0),
+            (prob + Z_95 * error).min(1.0),
+        ))
+    }
+
+    /// Get the largest standard error over all states, to decide whether to draw more samples.
+    pub fn max_standard_error(&self) -> f64 {
+        self.standard_errors
+            .iter()
+            .flat_map(|(_, states)| states.iter().map(|(_, error)| *error))
+            .fold(0.0, f64::max)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Estimate the posterior of every node given `evidence` from `n` samples drawn with
+    /// `sampler`, together with the standard error of each probability. Likelihood weighting
+    /// uses the spread of the weights and Gibbs sampling the autocorrelation of its chain.
+    pub fn estimate_posterior(
+        &self,
+        evidence: &HashMap<&str, T>,
+        n: usize,
+        sampler: &PosteriorSampler,
+    ) -> PosteriorEstimate<T> {
+        self.estimate_posterior_with_rng(evidence, n, sampler, &mut rand::thread_rng())
+    }
+
+    /// Estimate the posterior like `estimate_posterior`, using `rng`.
+    pub fn estimate_posterior_with_rng<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<&str, T>,
+        n: usize,
+        sampler: &PosteriorSampler,
+        rng: &mut R,
+    ) -> PosteriorEstimate<T> {
+        let evidence = self.evidence_ids(evidence);
+        // Each draw is weighted per connected component, so the evidence in one component
+        // does not affect the estimates in another.
+        let components = self.component_indices();
+        let samples = match sampler {
+            PosteriorSampler::LikelihoodWeighting { particles } => {
+                let order = self.topological_order();
+                (0..(*particles).max(n))
+                    .map(|_| {
+                        let (assignment, _) = self.weighted_sample(&order, &evidence, rng);
+                        let weights = self.component_weights(&assignment, &evidence, &components);
+                        (assignment, weights)
+                    })
+                    .collect::<Vec<(HashMap<NodeId, T>, Vec<Probability>)>>()
+            }
+            PosteriorSampler::Gibbs { burn_in, thin } => {
+                let count = components.iter().max().map_or(0, |max| max + 1);
+                self.gibbs_chain(&evidence, n.max(4), *burn_in, *thin, rng)
+                    .into_iter()
+                    .map(|assignment| (assignment, vec![1.0; count]))
+                    .collect()
+            }
+        };
+        let totals = (0..components.iter().max().map_or(0, |max| max + 1))
+            .map(|component| {
+                samples
+                    .iter()
+                    .map(|(_, weights)| weights[component])
+                    .sum::<Probability>()
+            })
+            .collect::<Vec<Probability>>();
+        if totals.iter().any(|total| *total <= 0.0) {
+            panic!("Evidence has zero probability");
+        }
+
+        let mut marginals = Vec::new();
+        let mut standard_errors = Vec::new();
+        for node in &self.nodes {
+            let component = components[node.id];
+            let total = totals[component];
+            let mut states = Vec::new();
+            let mut errors = Vec::new();
+            for value in self.get_node_domain(node.id) {
+                let indicators = samples
+                    .iter()
+                    .map(|(assignment, _)| (assignment[&node.id] == value) as u8 as f64)
+                    .collect::<Vec<f64>>();
+                let prob = samples
+                    .iter()
+                    .zip(&indicators)
+                    .map(|((_, weights), indicator)| weights[component] * indicator)
+                    .sum::<Probability>()
+                    / total;
+                let error = match sampler {
+                    // The delta-method error of a self-normalized importance sampling estimate.
+                    PosteriorSampler::LikelihoodWeighting { .. } => {
+                        samples
+                            .iter()
+                            .zip(&indicators)
+                            .map(|((_, weights), indicator)| {
+                                (weights[component] * (indicator - prob)).powi(2)
+                            })
+                            .sum::<f64>()
+                            .sqrt()
+                            / total
+                    }
+                    PosteriorSampler::Gibbs { .. } => {
+                        let (effective_sample_size, _) = mixing(&split(&[indicators]));
+                        (prob * (1.0 - prob) / effective_sample_size).sqrt()
+                    }
+                };
+                states.push((value.clone(), prob));
+                errors.push((value, error));
+            }
+            let name = self.get_node_name(node.id).to_string();
+            marginals.push((name.clone(), states));
+            standard_errors.push((name, errors));
+        }
+        PosteriorEstimate {
+            posterior: Posterior { marginals },
+            standard_errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, brute_force_probability, confounded, TOLERANCE};
+
+    #[test]
+    fn estimates_are_within_their_errors_of_enumeration() {
+        let network = confounded();
+        let evidence = HashMap::from([("y", true)]);
+        for sampler in [
+            PosteriorSampler::LikelihoodWeighting { particles: 10_000 },
+            PosteriorSampler::Gibbs {
+                burn_in: 100,
+                thin: 1,
+            },
+        ] {
+            let estimate = network.estimate_posterior_with_rng(
+                &evidence,
+                10_000,
+                &sampler,
+                &mut seeded_rng(1),
+            );
+            for name in ["z", "x"] {
+                let exact = brute_force_probability(&network, name, &true, &evidence);
+                let prob = estimate.posterior().probability(name, &true).unwrap();
+                let error = estimate.standard_error(name, &true).unwrap();
+                assert!(error > 0.0 && error < 0.02);
+                assert!((prob - exact).abs() < 4.0 * error, "{:?} {}", sampler, name);
+                let (low, high) = estimate.interval(name, &true).unwrap();
+                assert_close(high - low, 2.0 * Z_95 * error, TOLERANCE);
+            }
+            // The evidence itself is certain.
+            assert_eq!(estimate.interval("y", &true), Some((1.0, 1.0)));
+            assert_eq!(estimate.standard_error("y", &true), Some(0.0));
+        }
+    }
+
+    #[test]
+    fn unweighted_errors_are_binomial() {
+        let network = confounded();
+        let estimate = network.estimate_posterior_with_rng(
+            &HashMap::new(),
+            4_000,
+            &PosteriorSampler::LikelihoodWeighting { particles: 0 },
+            &mut seeded_rng(2),
+        );
+        let prob = estimate.posterior().probability("z", &true).unwrap();
+        assert_close(
+            estimate.standard_error("z", &true).unwrap(),
+            (prob * (1.0 - prob) / 4_000.0).sqrt(),
+            TOLERANCE,
+        );
+        assert!(estimate.max_standard_error() >= estimate.standard_error("z", &true).unwrap());
+        assert_eq!(estimate.standard_error("w", &true), None);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::bayesian_network::BayesianNetwork;
+use crate::error::Error;
+use crate::scenario::Scenario;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{collections::HashMap, fmt::Debug, hash::Hash, path::Path};
+
+/// An evidence file holds either one case or a list of cases.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EvidenceFile<T: Eq + Hash> {
+    Case(HashMap<String, T>),
+    Cases(Vec<HashMap<String, T>>),
+}
+
+impl<T: Eq + Hash> EvidenceFile<T> {
+    fn into_cases(self) -> Vec<HashMap<String, T>> {
+        match self {
+            EvidenceFile::Case(case) => vec![case],
+            EvidenceFile::Cases(cases) => cases,
+        }
+    }
+}
+
+/// A scenario as written in a scenario file.
+#[derive(Deserialize)]
+struct ScenarioRepr<T: Eq + Hash> {
+    name: String,
+    #[serde(default = "HashMap::new")]
+    evidence: HashMap<String, T>,
+}
+
+impl<T> BayesianNetwork<T>
+where
+    T: Clone + PartialEq + Eq + Hash + Debug + DeserializeOwned,
+{
+    /// Check that every evidence case only names nodes of the network and values of its
+    /// value space.
+    fn check_evidence(
+        &self,
+        format: &'static str,
+        cases: Vec<HashMap<String, T>>,
+    ) -> Result<Vec<HashMap<String, T>>, Error> {
+        for case in &cases {
+            for (name, value) in case {
+                let message = if !self.node_map.contains_key(name) {
+                    format!("evidence node `{}` not found", name)
+                } else if !self.value_space.contains(value) {
+                    format!(
+                        "evidence value {:?} of `{}` not found in value space",
+                        value, name
+                    )
+                } else {
+                    continue;
+                };
+                return Err(Error::Invalid { format, message });
+            }
+        }
+        Ok(cases)
+    }
+
+    /// Parse evidence from JSON: one case (`{"ボーナス": true}`) or a list of cases.
+    pub fn evidence_from_json(&self, source: &str) -> Result<Vec<HashMap<String, T>>, Error> {
+        let file: EvidenceFile<T> =
+            serde_json::from_str(source).map_err(|error| Error::Syntax {
+                format: "JSON",
+                line: error.line(),
+                message: error.to_string(),
+            })?;
+        self.check_evidence("JSON", file.into_cases())
+    }
+
+    /// Parse evidence from YAML: one case (a mapping) or a list of cases.
+    pub fn evidence_from_yaml(&self, source: &str) -> Result<Vec<HashMap<String, T>>, Error> {
+        let file: EvidenceFile<T> =
+            serde_yaml::from_str(source).map_err(|error| Error::Syntax {
+                format: "YAML",
+                line: error
+                    .location()
+                    .map(|location| location.line())
+                    .unwrap_or(0),
+                message: error.to_string(),
+            })?;
+        self.check_evidence("YAML", file.into_cases())
+    }
+
+    /// Read evidence from a `.json`, `.yaml` or `.yml` file.
+    pub fn read_evidence<P: AsRef<Path>>(&self, path: P) -> Result<Vec<HashMap<String, T>>, Error> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => self.evidence_from_yaml(&source),
+            _ => self.evidence_from_json(&source),
+        }
+    }
+
+    /// Parse scenarios from JSON: a list of `{"name": ..., "evidence": {...}}` objects.
+    pub fn scenarios_from_json(&self, source: &str) -> Result<Vec<Scenario<T>>, Error> {
+        let scenarios: Vec<ScenarioRepr<T>> =
+            serde_json::from_str(source).map_err(|error| Error::Syntax {
+                format: "JSON",
+                line: error.line(),
+                message: error.to_string(),
+            })?;
+        self.check_scenarios("JSON", scenarios)
+    }
+
+    /// Parse scenarios from YAML: a list of mappings with `name` and `evidence` keys.
+    pub fn scenarios_from_yaml(&self, source: &str) -> Result<Vec<Scenario<T>>, Error> {
+        let scenarios: Vec<ScenarioRepr<T>> =
+            serde_yaml::from_str(source).map_err(|error| Error::Syntax {
+                format: "YAML",
+                line: error
+                    .location()
+                    .map(|location| location.line())
+                    .unwrap_or(0),
+                message: error.to_string(),
+            })?;
+        self.check_scenarios("YAML", scenarios)
+    }
+
+    /// Read scenarios from a `.json`, `.yaml` or `.yml` file.
+    pub fn read_scenarios<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Scenario<T>>, Error> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => self.scenarios_from_yaml(&source),
+            _ => self.scenarios_from_json(&source),
+        }
+    }
+
+    fn check_scenarios(
+        &self,
+        format: &'static str,
+        scenarios: Vec<ScenarioRepr<T>>,
+    ) -> Result<Vec<Scenario<T>>, Error> {
+        let names = scenarios
+            .iter()
+            .map(|scenario| scenario.name.clone())
+            .collect::<Vec<String>>();
+        let cases = self.check_evidence(
+            format,
+            scenarios
+                .into_iter()
+                .map(|scenario| scenario.evidence)
+                .collect(),
+        )?;
+        Ok(names
+            .iter()
+            .zip(cases)
+            .map(|(name, evidence)| Scenario::new(name, evidence))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bonus;
+
+    #[test]
+    fn json_and_yaml_hold_one_case_or_many() {
+        let network = bonus();
+        let case = HashMap::from([("bonus".to_string(), true)]);
+        assert_eq!(
+            network.evidence_from_json(r#"{"bonus": true}"#).unwrap(),
+            vec![case.clone()]
+        );
+        assert_eq!(
+            network
+                .evidence_from_json(r#"[{"bonus": true}, {"feast": false, "mood": true}]"#)
+                .unwrap(),
+            vec![
+                case.clone(),
+                HashMap::from([("feast".to_string(), false), ("mood".to_string(), true)]),
+            ]
+        );
+        assert_eq!(
+            network.evidence_from_yaml("bonus: true\n").unwrap(),
+            vec![case.clone()]
+        );
+        assert_eq!(
+            network.evidence_from_yaml("- bonus: true\n- {}\n").unwrap(),
+            vec![case, HashMap::new()]
+        );
+    }
+
+    #[test]
+    fn unknown_nodes_and_malformed_files_are_errors() {
+        let network = bonus();
+        match network.evidence_from_json(r#"{"salary": true}"#) {
+            Err(Error::Invalid { format, message }) => {
+                assert_eq!(format, "JSON");
+                assert!(message.contains("`salary`"));
+            }
+            result => panic!("expected an invalid node, got {:?}", result),
+        }
+        assert!(matches!(
+            network.evidence_from_json("{\n\"bonus\": maybe}"),
+            Err(Error::Syntax {
+                format: "JSON",
+                line: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            network.evidence_from_yaml("bonus: [true\n"),
+            Err(Error::Syntax { format: "YAML", .. })
+        ));
+    }
+
+    #[test]
+    fn scenario_files_name_their_evidence() {
+        let network = bonus();
+        let expected = vec![
+            Scenario::new("baseline", HashMap::new()),
+            Scenario::new("payday", HashMap::from([("bonus".to_string(), true)])),
+        ];
+        assert_eq!(
+            network
+                .scenarios_from_json(
+                    r#"[{"name": "baseline"}, {"name": "payday", "evidence": {"bonus": true}}]"#
+                )
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            network
+                .scenarios_from_yaml(
+                    "- name: baseline\n- name: payday\n  evidence:\n    bonus: true\n"
+                )
+                .unwrap(),
+            expected
+        );
+        assert!(matches!(
+            network.scenarios_from_json(r#"[{"name": "x", "evidence": {"salary": true}}]"#),
+            Err(Error::Invalid { .. })
+        ));
+    }
+}
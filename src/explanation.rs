@@ -0,0 +1,202 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display},
+    hash::Hash,
+};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, Probability},
+    posterior::Posterior,
+};
+
+/// A link of an explanation chain, with how far the finding moved the posterior of the node
+/// it leads to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExplanationLink {
+    pub from: Name,
+    pub to: Name,
+    /// The total variation distance between the posteriors of `to` with and without the
+    /// finding.
+    pub shift: Probability,
+}
+
+/// A path along which a finding influences the target, from the finding to the target.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExplanationChain<T> {
+    pub finding: Name,
+    pub value: T,
+    /// How much the finding changes the probability of the target value: its probability
+    /// given all the evidence minus its probability with this finding retracted.
+    pub effect: Probability,
+    pub links: Vec<ExplanationLink>,
+}
+
+/// Why a target value has its posterior probability, as returned by `explain`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation<T> {
+    pub target: Name,
+    pub value: T,
+    /// The probability of the target value given all the evidence.
+    pub probability: Probability,
+    /// The probability of the target value given no evidence.
+    pub prior: Probability,
+    /// The active paths from each finding to the target, the largest effects first.
+    pub chains: Vec<ExplanationChain<T>>,
+}
+
+impl<T: Display> Display for Explanation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "P({} = {}) is {:.3} given the evidence, against {:.3} without it.",
+            self.target, self.value, self.probability, self.prior
+        )?;
+        if self.chains.is_empty() {
+            return writeln!(f, "No finding is connected to {}.", self.target);
+        }
+        for chain in &self.chains {
+            let direction = if chain.effect >= 0.0 {
+                "raises"
+            } else {
+                "lowers"
+            };
+            let links = chain
+                .links
+                .iter()
+                .map(|link| format!("{} ({:.3})", link.to, link.shift))
+                .collect::<Vec<String>>();
+            writeln!(
+                f,
+                "{} = {} {} it by {:.3} through {} -> {}",
+                chain.finding,
+                chain.value,
+                direction,
+                chain.effect.abs(),
+                chain.finding,
+                links.join(" -> ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Explain the posterior probability of `target` taking `value` given `evidence`: for
+    /// each finding, the active paths through which it reaches the target given the other
+    /// findings, with how far it moves each node along the way. The search goes through
+    /// every simple path, so its cost grows exponentially with the size of the network.
+    pub fn explain(&self, target: &str, value: &T, evidence: &HashMap<&str, T>) -> Explanation<T> {
+        let full = self.posterior(evidence);
+        let probability = match full.probability(target, value) {
+            Some(probability) => probability,
+            None => panic!("Value {:?} is not a state of `{}`", value, target),
+        };
+        let prior = self
+            .posterior(&HashMap::new())
+            .probability(target, value)
+            .unwrap();
+        let mut findings = evidence.iter().collect::<Vec<(&&str, &T)>>();
+        findings.sort_by_key(|(name, _)| self.get_node_index(name));
+        let mut chains = Vec::new();
+        for (name, finding_value) in findings {
+            let mut rest = evidence.clone();
+            rest.remove(name);
+            let retracted = self.posterior(&rest);
+            let effect = probability - retracted.probability(target, value).unwrap();
+            let given = rest.keys().copied().collect::<Vec<&str>>();
+            for path in self.active_paths(name, target, &given) {
+                let links = path
+                    .windows(2)
+                    .map(|pair| ExplanationLink {
+                        from: pair[0].clone(),
+                        to: pair[1].clone(),
+                        shift: shift(&full, &retracted, &pair[1]),
+                    })
+                    .collect();
+                chains.push(ExplanationChain {
+                    finding: name.to_string(),
+                    value: finding_value.clone(),
+                    effect,
+                    links,
+                });
+            }
+        }
+        chains.sort_by(|a, b| b.effect.abs().total_cmp(&a.effect.abs()));
+        Explanation {
+            target: target.to_string(),
+            value: value.clone(),
+            probability,
+            prior,
+            chains,
+        }
+    }
+}
+
+/// The total variation distance between the distributions of a node in two posteriors.
+fn shift<T: Clone + PartialEq>(a: &Posterior<T>, b: &Posterior<T>, name: &str) -> Probability {
+    a.distribution(name)
+        .unwrap()
+        .iter()
+        .map(|(value, p)| (p - b.probability(name, value).unwrap()).abs())
+        .sum::<Probability>()
+        / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_probability, TOLERANCE};
+
+    #[test]
+    fn explain_follows_each_finding_to_the_target() {
+        let network = bonus();
+        let evidence = HashMap::from([("horse_race", true), ("bonus", true)]);
+        let explanation = network.explain("performance", &true, &evidence);
+        let probability = brute_force_probability(&network, "performance", &true, &evidence);
+        assert_close(explanation.probability, probability, TOLERANCE);
+        assert_close(explanation.prior, 0.01, TOLERANCE);
+        assert_eq!(explanation.chains.len(), 2);
+        for chain in &explanation.chains {
+            let mut rest = evidence.clone();
+            rest.remove(chain.finding.as_str());
+            assert_close(
+                chain.effect,
+                probability - brute_force_probability(&network, "performance", &true, &rest),
+                TOLERANCE,
+            );
+            let mood = brute_force_probability(&network, "mood", &true, &evidence)
+                - brute_force_probability(&network, "mood", &true, &rest);
+            assert_eq!(chain.links[0].to, "mood");
+            assert_close(chain.links[0].shift, mood.abs(), TOLERANCE);
+            assert_eq!(chain.links[1].to, "performance");
+        }
+        // The bonus raises the chance of a good performance, but the horse race explains the
+        // good mood away.
+        let effect = |finding: &str| {
+            explanation
+                .chains
+                .iter()
+                .find(|chain| chain.finding == finding)
+                .unwrap()
+                .effect
+        };
+        assert!(effect("bonus") > 0.0);
+        assert!(effect("horse_race") < 0.0);
+        assert!(explanation.chains[0].effect.abs() >= explanation.chains[1].effect.abs());
+        assert!(explanation
+            .to_string()
+            .contains("horse_race = true lowers it by"));
+    }
+
+    #[test]
+    fn explain_has_no_chain_from_separated_findings() {
+        let network = bonus();
+        let explanation =
+            network.explain("performance", &true, &HashMap::from([("horse_race", true)]));
+        assert!(explanation.chains.is_empty());
+        assert_close(explanation.probability, explanation.prior, TOLERANCE);
+        assert!(explanation
+            .to_string()
+            .ends_with("No finding is connected to performance.\n"));
+    }
+}
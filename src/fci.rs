@@ -0,0 +1,554 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    bayesian_network::Name,
+    causal::subsets,
+    dataset::Dataset,
+    learning::{joint_counts, ln_gamma},
+};
+
+/// The mark at one end of an edge of a partial ancestral graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mark {
+    /// The node at this end is an ancestor of the node at the other end.
+    Tail,
+    /// The node at this end is not an ancestor of the node at the other end.
+    Arrow,
+    /// The mark differs between the graphs of the class.
+    Circle,
+}
+
+/// An edge of a partial ancestral graph, with the mark at each end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PagEdge {
+    pub from: Name,
+    pub to: Name,
+    pub from_mark: Mark,
+    pub to_mark: Mark,
+}
+
+/// `marks[a][b]` is the mark at the `b` end of the edge between `a` and `b`, if they are
+/// adjacent.
+type Marks = Vec<Vec<Option<Mark>>>;
+
+/// The separating sets found for non-adjacent pairs, keyed with the smaller column first.
+type Sepsets = HashMap<(usize, usize), Vec<usize>>;
+
+/// A partial ancestral graph: the class of causal DAGs, possibly with hidden common causes,
+/// that agree with the independences in the data, as learned by `fci`. An arrow mark at a
+/// node rules out it being an ancestor of the other end; a tail mark makes it one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pag {
+    nodes: Vec<Name>,
+    marks: Marks,
+}
+
+impl Pag {
+    /// Get the names of the nodes, in order.
+    pub fn nodes(&self) -> &[Name] {
+        &self.nodes
+    }
+
+    /// Get the mark at the `to` end of the edge between `from` and `to`, or `None` if they
+    /// are not adjacent.
+    pub fn mark(&self, from: &str, to: &str) -> Option<Mark> {
+        let index = |name: &str| match self.nodes.iter().position(|node| node == name) {
+            Some(index) => index,
+            None => panic!("Node `{}` not found", name),
+        };
+        self.marks[index(from)][index(to)]
+    }
+
+    /// Get the edges, each from the node that comes first.
+    pub fn edges(&self) -> Vec<PagEdge> {
+        let mut edges = Vec::new();
+        for a in 0..self.nodes.len() {
+            for b in a + 1..self.nodes.len() {
+                if let (Some(from_mark), Some(to_mark)) = (self.marks[b][a], self.marks[a][b]) {
+                    edges.push(PagEdge {
+                        from: self.nodes[a].clone(),
+                        to: self.nodes[b].clone(),
+                        from_mark,
+                        to_mark,
+                    });
+                }
+            }
+        }
+        edges
+    }
+}
+
+/// Get the p-value of the G-test of independence of columns `x` and `y` given the columns
+/// `given`, with the degrees of freedom of the values seen in each stratum.
+pub fn independence_p_value<T: Clone + PartialEq + Eq + Hash>(
+    dataset: &Dataset<T>,
+    x: &str,
+    y: &str,
+    given: &[&str],
+) -> f64 {
+    let column = |name: &str| {
+        dataset
+            .column_index(name)
+            .unwrap_or_else(|| panic!("Dataset column `{}` not found", name))
+    };
+    let given = given
+        .iter()
+        .map(|name| column(name))
+        .collect::<Vec<usize>>();
+    g_test(dataset, column(x), column(y), &given)
+}
+
+/// The p-value of the G-test of independence of columns `x` and `y` given `given`.
+fn g_test<T: Clone + Eq + Hash>(dataset: &Dataset<T>, x: usize, y: usize, given: &[usize]) -> f64 {
+    let with = |column: usize| {
+        let mut columns = given.to_vec();
+        columns.push(column);
+        columns
+    };
+    let strata = joint_counts(dataset, given);
+    let x_counts = joint_counts(dataset, &with(x));
+    let y_counts = joint_counts(dataset, &with(y));
+    let mut columns = with(x);
+    columns.push(y);
+    let mut statistic = 0.0;
+    for (key, count) in joint_counts(dataset, &columns) {
+        let stratum = &key[..given.len()];
+        let x_key = &key[..given.len() + 1];
+        let mut y_key = stratum.to_vec();
+        y_key.push(key[given.len() + 1].clone());
+        statistic +=
+            2.0 * count * (count * strata[stratum] / (x_counts[x_key] * y_counts[&y_key])).ln();
+    }
+    let mut levels: HashMap<&[T], (f64, f64)> = HashMap::new();
+    for key in x_counts.keys() {
+        levels.entry(&key[..given.len()]).or_insert((0.0, 0.0)).0 += 1.0;
+    }
+    for key in y_counts.keys() {
+        levels.entry(&key[..given.len()]).or_insert((0.0, 0.0)).1 += 1.0;
+    }
+    let freedom = levels
+        .values()
+        .map(|(x_levels, y_levels)| (x_levels - 1.0) * (y_levels - 1.0))
+        .sum::<f64>();
+    if freedom <= 0.0 {
+        return 1.0;
+    }
+    1.0 - lower_gamma(freedom / 2.0, statistic.max(0.0) / 2.0)
+}
+
+/// The regularized lower incomplete gamma function P(a, x).
+fn lower_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let scale = (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        // Series expansion.
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..1000 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        (sum * scale).min(1.0)
+    } else {
+        // Continued fraction for the upper function, by Lentz's method.
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut fraction = d;
+        for n in 1..1000 {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            fraction *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        (1.0 - scale * fraction).max(0.0)
+    }
+}
+
+/// Learn a partial ancestral graph with the FCI algorithm, which stays sound when hidden
+/// common causes may exist. Independence is decided by G-tests at significance level
+/// `alpha`, with conditioning sets of at most `max_size` columns: the adjacency search goes
+/// through subsets of neighbours and then of possible d-separating sets, so its cost grows
+/// exponentially with `max_size`. Marks are oriented from the unshielded colliders by
+/// the rules R1 to R4 of FCI.
+pub fn fci<T: Clone + PartialEq + Eq + Hash>(
+    dataset: &Dataset<T>,
+    alpha: f64,
+    max_size: usize,
+) -> Pag {
+    let n = dataset.columns().len();
+    let mut adjacent = vec![vec![true; n]; n];
+    for (id, row) in adjacent.iter_mut().enumerate() {
+        row[id] = false;
+    }
+    let mut sepsets = Sepsets::new();
+    let separate = |adjacent: &mut Vec<Vec<bool>>,
+                    sepsets: &mut Sepsets,
+                    x: usize,
+                    y: usize,
+                    given: &[usize]| {
+        if g_test(dataset, x, y, given) > alpha {
+            adjacent[x][y] = false;
+            adjacent[y][x] = false;
+            sepsets.insert((x.min(y), x.max(y)), given.to_vec());
+            true
+        } else {
+            false
+        }
+    };
+
+    // Remove the edges between columns made independent by subsets of their neighbours.
+    for size in 0..=max_size {
+        let mut testable = false;
+        for x in 0..n {
+            for y in 0..n {
+                if !adjacent[x][y] {
+                    continue;
+                }
+                let neighbours = (0..n)
+                    .filter(|z| *z != y && adjacent[x][*z])
+                    .collect::<Vec<usize>>();
+                testable |= neighbours.len() >= size;
+                for given in subsets(&neighbours, size) {
+                    if separate(&mut adjacent, &mut sepsets, x, y, &given) {
+                        break;
+                    }
+                }
+            }
+        }
+        if !testable {
+            break;
+        }
+    }
+
+    // Remove the edges between columns made independent by subsets of their possible
+    // d-separating sets, found from the colliders of the skeleton.
+    let marks = collider_marks(&adjacent, &sepsets);
+    let candidates = (0..n)
+        .map(|x| possible_d_sep(&marks, &adjacent, x))
+        .collect::<Vec<Vec<usize>>>();
+    for x in 0..n {
+        for y in 0..n {
+            if !adjacent[x][y] {
+                continue;
+            }
+            let candidates = candidates[x]
+                .iter()
+                .copied()
+                .filter(|z| *z != y)
+                .collect::<Vec<usize>>();
+            'sizes: for size in 0..=max_size.min(candidates.len()) {
+                for given in subsets(&candidates, size) {
+                    if separate(&mut adjacent, &mut sepsets, x, y, &given) {
+                        break 'sizes;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut marks = collider_marks(&adjacent, &sepsets);
+    orient(&mut marks, &sepsets);
+    Pag {
+        nodes: dataset.columns().to_vec(),
+        marks,
+    }
+}
+
+/// Circle marks on every edge, with arrows into the unshielded colliders: the middle nodes
+/// of non-adjacent pairs that are not in their separating set.
+fn collider_marks(adjacent: &[Vec<bool>], sepsets: &Sepsets) -> Marks {
+    let n = adjacent.len();
+    let mut marks = (0..n)
+        .map(|a| {
+            (0..n)
+                .map(|b| adjacent[a][b].then_some(Mark::Circle))
+                .collect()
+        })
+        .collect::<Marks>();
+    for b in 0..n {
+        for a in 0..n {
+            for c in a + 1..n {
+                if adjacent[a][b]
+                    && adjacent[c][b]
+                    && !adjacent[a][c]
+                    && !sepsets[&(a, c)].contains(&b)
+                {
+                    marks[a][b] = Some(Mark::Arrow);
+                    marks[c][b] = Some(Mark::Arrow);
+                }
+            }
+        }
+    }
+    marks
+}
+
+/// The nodes reachable from `x` by paths on which every inner node is a collider or the
+/// middle of a triangle.
+fn possible_d_sep(marks: &Marks, adjacent: &[Vec<bool>], x: usize) -> Vec<usize> {
+    let n = adjacent.len();
+    let mut reached = vec![false; n];
+    let mut visited = vec![vec![false; n]; n];
+    let mut stack = Vec::new();
+    for v in 0..n {
+        if adjacent[x][v] {
+            reached[v] = true;
+            visited[x][v] = true;
+            stack.push((x, v));
+        }
+    }
+    while let Some((a, b)) = stack.pop() {
+        for c in 0..n {
+            if c == a || c == x || !adjacent[b][c] || visited[b][c] {
+                continue;
+            }
+            let collider = marks[a][b] == Some(Mark::Arrow) && marks[c][b] == Some(Mark::Arrow);
+            if collider || adjacent[a][c] {
+                reached[c] = true;
+                visited[b][c] = true;
+                stack.push((b, c));
+            }
+        }
+    }
+    (0..n).filter(|v| reached[*v]).collect()
+}
+
+/// Apply the orientation rules R1 to R4 until no mark changes.
+fn orient(marks: &mut Marks, sepsets: &Sepsets) {
+    let n = marks.len();
+    let is = |mark: Option<Mark>, expected: Mark| mark == Some(expected);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for a in 0..n {
+            for b in 0..n {
+                for c in 0..n {
+                    if a == b || b == c || a == c {
+                        continue;
+                    }
+                    // R1: a *-> b o-* c with a and c not adjacent gives b -> c.
+                    if is(marks[a][b], Mark::Arrow)
+                        && is(marks[c][b], Mark::Circle)
+                        && marks[a][c].is_none()
+                    {
+                        marks[b][c] = Some(Mark::Arrow);
+                        marks[c][b] = Some(Mark::Tail);
+                        changed = true;
+                    }
+                    // R2: a -> b *-> c or a *-> b -> c, with a *-o c, gives a *-> c.
+                    if is(marks[a][c], Mark::Circle)
+                        && is(marks[a][b], Mark::Arrow)
+                        && is(marks[b][c], Mark::Arrow)
+                        && (is(marks[b][a], Mark::Tail) || is(marks[c][b], Mark::Tail))
+                    {
+                        marks[a][c] = Some(Mark::Arrow);
+                        changed = true;
+                    }
+                    // R3: a *-> b <-* c and a *-o d o-* c with a and c not adjacent and
+                    // d *-o b gives d *-> b.
+                    if is(marks[a][b], Mark::Arrow)
+                        && is(marks[c][b], Mark::Arrow)
+                        && marks[a][c].is_none()
+                    {
+                        let middles = (0..n)
+                            .filter(|d| {
+                                is(marks[a][*d], Mark::Circle)
+                                    && is(marks[c][*d], Mark::Circle)
+                                    && is(marks[*d][b], Mark::Circle)
+                            })
+                            .collect::<Vec<usize>>();
+                        for d in middles {
+                            marks[d][b] = Some(Mark::Arrow);
+                            changed = true;
+                        }
+                    }
+                    // R4: a discriminating path d ... a b c for b, with b o-* c.
+                    if is(marks[c][b], Mark::Circle)
+                        && is(marks[b][a], Mark::Arrow)
+                        && is(marks[a][c], Mark::Arrow)
+                        && is(marks[c][a], Mark::Tail)
+                    {
+                        if let Some(d) = discriminating_start(marks, a, b, c) {
+                            if sepsets[&(d.min(c), d.max(c))].contains(&b) {
+                                marks[b][c] = Some(Mark::Arrow);
+                                marks[c][b] = Some(Mark::Tail);
+                            } else {
+                                marks[a][b] = Some(Mark::Arrow);
+                                marks[b][c] = Some(Mark::Arrow);
+                                marks[c][b] = Some(Mark::Arrow);
+                            }
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The first node `d` of a discriminating path d ... a b c for `b`: `d` is not adjacent to
+/// `c`, and every node between `d` and `b` is a collider on the path and a parent of `c`.
+fn discriminating_start(marks: &Marks, a: usize, b: usize, c: usize) -> Option<usize> {
+    let n = marks.len();
+    let mut visited = vec![false; n];
+    visited[a] = true;
+    visited[b] = true;
+    visited[c] = true;
+    let mut queue = std::collections::VecDeque::from([a]);
+    while let Some(current) = queue.pop_front() {
+        for d in 0..n {
+            if visited[d] || marks[d][current] != Some(Mark::Arrow) {
+                continue;
+            }
+            if marks[d][c].is_none() {
+                return Some(d);
+            }
+            let parent_of_c = marks[d][c] == Some(Mark::Arrow) && marks[c][d] == Some(Mark::Tail);
+            if parent_of_c && marks[current][d] == Some(Mark::Arrow) {
+                visited[d] = true;
+                queue.push_back(d);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bayesian_network::{BayesianNetwork, NodeType};
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, row};
+
+    /// The records of `dataset` restricted to the columns `names`, in that order.
+    fn project(dataset: &Dataset<bool>, names: &[&str]) -> Dataset<bool> {
+        let columns = names
+            .iter()
+            .map(|name| dataset.column_index(name).unwrap())
+            .collect::<Vec<usize>>();
+        let mut projected = Dataset::new(names.to_vec());
+        for record in dataset.rows() {
+            projected.add_row(columns.iter().map(|column| record[*column]).collect());
+        }
+        projected
+    }
+
+    /// A boolean CPT over two parents, listing P(true) for (true, true), (true, false),
+    /// (false, true) and (false, false).
+    fn two_parents(probs: [f64; 4]) -> HashMap<Vec<bool>, HashMap<bool, f64>> {
+        HashMap::from([
+            (vec![true, true], row(probs[0])),
+            (vec![true, false], row(probs[1])),
+            (vec![false, true], row(probs[2])),
+            (vec![false, false], row(probs[3])),
+        ])
+    }
+
+    #[test]
+    fn lower_gamma_gives_chi_square_tail_probabilities() {
+        // Critical values of the chi-square distribution: (degrees of freedom, value, tail).
+        for (freedom, value, tail) in [
+            (1.0, 3.841_459, 0.05),
+            (2.0, 5.991_465, 0.05),
+            (4.0, 9.487_729, 0.05),
+            (10.0, 3.940_299, 0.95),
+            (10.0, 23.209_251, 0.01),
+        ] {
+            assert_close(1.0 - lower_gamma(freedom / 2.0, value / 2.0), tail, 1e-6);
+        }
+        assert_eq!(lower_gamma(1.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn independence_p_value_matches_the_g_test() {
+        // Each stratum of `z` holds the table [[12, 8], [8, 12]], with G = 1.610841.
+        let mut dataset = Dataset::new(vec!["x", "y", "z"]);
+        for z in [true, false] {
+            for (x, y, count) in [
+                (true, true, 12),
+                (true, false, 8),
+                (false, true, 8),
+                (false, false, 12),
+            ] {
+                for _ in 0..count {
+                    dataset.add_row(vec![x, y, z]);
+                }
+            }
+        }
+        // One degree of freedom for the pooled table [[24, 16], [16, 24]], with G doubled.
+        assert_close(
+            independence_p_value(&dataset, "x", "y", &[]),
+            0.072_668_917,
+            1e-6,
+        );
+        // Two degrees of freedom: exp(-G) with G summed over the strata.
+        assert_close(
+            independence_p_value(&dataset, "x", "y", &["z"]),
+            (-1.610_841_084_055_f64).exp(),
+            1e-9,
+        );
+        assert_close(independence_p_value(&dataset, "x", "z", &[]), 1.0, 1e-9);
+    }
+
+    #[test]
+    fn fci_puts_arrowheads_into_a_collider() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.5)));
+        network.add_node("b", NodeType::Root(row(0.4)));
+        network.add_node("c", NodeType::Leaf);
+        network.add_dependency(vec!["a", "b"], "c", two_parents([0.95, 0.6, 0.5, 0.05]));
+        let dataset = network.sample_with_rng(5_000, &mut seeded_rng(3));
+        let pag = fci(&dataset, 0.01, 2);
+        assert_eq!(pag.mark("a", "b"), None);
+        assert_eq!(pag.mark("a", "c"), Some(Mark::Arrow));
+        assert_eq!(pag.mark("b", "c"), Some(Mark::Arrow));
+        assert_eq!(pag.mark("c", "a"), Some(Mark::Circle));
+        assert_eq!(pag.mark("c", "b"), Some(Mark::Circle));
+    }
+
+    #[test]
+    fn fci_finds_a_hidden_common_cause() {
+        // a -> b <- u -> c <- d, with u unobserved.
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.5)));
+        network.add_node("u", NodeType::Root(row(0.5)));
+        network.add_node("d", NodeType::Root(row(0.5)));
+        network.add_node("b", NodeType::Leaf);
+        network.add_node("c", NodeType::Leaf);
+        network.add_dependency(vec!["a", "u"], "b", two_parents([0.95, 0.5, 0.5, 0.05]));
+        network.add_dependency(vec!["u", "d"], "c", two_parents([0.95, 0.5, 0.5, 0.05]));
+        let sample = network.sample_with_rng(5_000, &mut seeded_rng(4));
+        let pag = fci(&project(&sample, &["a", "b", "c", "d"]), 0.01, 2);
+        assert_eq!(pag.mark("b", "c"), Some(Mark::Arrow));
+        assert_eq!(pag.mark("c", "b"), Some(Mark::Arrow));
+        assert_eq!(pag.mark("a", "b"), Some(Mark::Arrow));
+        assert_eq!(pag.mark("d", "c"), Some(Mark::Arrow));
+        assert_eq!(pag.mark("a", "c"), None);
+        assert_eq!(pag.mark("b", "d"), None);
+        let bidirected = pag
+            .edges()
+            .into_iter()
+            .filter(|edge| edge.from_mark == Mark::Arrow && edge.to_mark == Mark::Arrow)
+            .map(|edge| (edge.from, edge.to))
+            .collect::<Vec<(Name, Name)>>();
+        assert_eq!(bidirected, vec![("b".to_string(), "c".to_string())]);
+    }
+}
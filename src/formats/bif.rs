@@ -0,0 +1,392 @@
+use super::lexer::{Lexer, Token};
+use super::{build, quoted, tables, Table};
+use crate::bayesian_network::{BayesianNetwork, Probability};
+use crate::error::Error;
+use crate::learning::configurations;
+use crate::normalization::Normalization;
+use std::{collections::HashMap, fmt::Debug, fmt::Display, hash::Hash, path::Path};
+
+const FORMAT: &str = "BIF";
+
+/// The body of a `probability` block before the states of the parents are known.
+enum Entries {
+    /// A flat table listing the child state slowest and the last parent fastest.
+    Table(Vec<Probability>),
+    Rows {
+        rows: Vec<(Vec<String>, Vec<Probability>)>,
+        default: Option<Vec<Probability>>,
+    },
+}
+
+fn numbers(lexer: &mut Lexer) -> Result<Vec<Probability>, Error> {
+    let mut numbers = Vec::new();
+    while !lexer.is_punct(';') {
+        if lexer.is_punct(',') {
+            lexer.next()?;
+            continue;
+        }
+        numbers.push(lexer.number()?);
+    }
+    lexer.expect_punct(';')?;
+    Ok(numbers)
+}
+
+fn variable(lexer: &mut Lexer) -> Result<(String, Vec<String>), Error> {
+    let name = lexer.name()?;
+    let mut states = None;
+    lexer.expect_punct('{')?;
+    while !lexer.is_punct('}') {
+        match lexer.next()? {
+            Token::Word(word) if word == "type" => {
+                lexer.expect_word("discrete")?;
+                lexer.expect_punct('[')?;
+                let count = lexer.number()? as usize;
+                lexer.expect_punct(']')?;
+                lexer.expect_punct('{')?;
+                let mut values = Vec::new();
+                while !lexer.is_punct('}') {
+                    if lexer.is_punct(',') {
+                        lexer.next()?;
+                        continue;
+                    }
+                    values.push(lexer.name()?);
+                }
+                lexer.expect_punct('}')?;
+                lexer.expect_punct(';')?;
+                if values.len() != count {
+                    return lexer.error(format!(
+                        "variable `{}` declares {} states but lists {}",
+                        name,
+                        count,
+                        values.len()
+                    ));
+                }
+                states = Some(values);
+            }
+            Token::Word(word) if word == "property" => lexer.skip_past(';')?,
+            token => return lexer.error(format!("unexpected {:?} in variable `{}`", token, name)),
+        }
+    }
+    lexer.expect_punct('}')?;
+    match states {
+        Some(states) => Ok((name, states)),
+        None => lexer.error(format!("variable `{}` has no type declaration", name)),
+    }
+}
+
+fn probability(lexer: &mut Lexer) -> Result<(String, Vec<String>, Entries), Error> {
+    lexer.expect_punct('(')?;
+    let child = lexer.name()?;
+    // Older BIF versions list the parents after the child without a `|`.
+    let mut parents = Vec::new();
+    while !lexer.is_punct(')') {
+        if lexer.is_punct(',') || lexer.is_punct('|') {
+            lexer.next()?;
+            continue;
+        }
+        parents.push(lexer.name()?);
+    }
+    lexer.expect_punct(')')?;
+    lexer.expect_punct('{')?;
+    let mut table = None;
+    let mut rows = Vec::new();
+    let mut default = None;
+    while !lexer.is_punct('}') {
+        if lexer.is_punct('(') {
+            lexer.next()?;
+            let mut key = Vec::new();
+            while !lexer.is_punct(')') {
+                if lexer.is_punct(',') {
+                    lexer.next()?;
+                    continue;
+                }
+                key.push(lexer.name()?);
+            }
+            lexer.expect_punct(')')?;
+            rows.push((key, numbers(lexer)?));
+            continue;
+        }
+        match lexer.next()? {
+            Token::Word(word) if word == "table" => table = Some(numbers(lexer)?),
+            Token::Word(word) if word == "default" => default = Some(numbers(lexer)?),
+            Token::Word(word) if word == "property" => lexer.skip_past(';')?,
+            token => {
+                return lexer.error(format!(
+                    "unexpected {:?} in probability of `{}`",
+                    token, child
+                ))
+            }
+        }
+    }
+    lexer.expect_punct('}')?;
+    let entries = match table {
+        Some(table) => Entries::Table(table),
+        None => Entries::Rows { rows, default },
+    };
+    Ok((child, parents, entries))
+}
+
+fn parse(source: &str) -> Result<Vec<Table>, Error> {
+    let mut lexer = Lexer::new(FORMAT, source, &["//"])?;
+    let mut variables = Vec::new();
+    let mut probabilities = HashMap::new();
+    while let Some(token) = lexer.peek() {
+        match token.clone() {
+            Token::Word(word) if word == "network" => {
+                lexer.next()?;
+                lexer.name()?;
+                lexer.skip_block()?;
+            }
+            Token::Word(word) if word == "variable" => {
+                lexer.next()?;
+                variables.push(variable(&mut lexer)?);
+            }
+            Token::Word(word) if word == "probability" => {
+                lexer.next()?;
+                let line = lexer.line();
+                let (child, parents, entries) = probability(&mut lexer)?;
+                if probabilities.contains_key(&child) {
+                    return Err(Error::Syntax {
+                        format: FORMAT,
+                        line,
+                        message: format!("probability of `{}` is defined twice", child),
+                    });
+                }
+                probabilities.insert(child, (parents, entries));
+            }
+            token => return lexer.error(format!("unexpected {:?}", token)),
+        }
+    }
+
+    let states = variables
+        .iter()
+        .map(|(name, states)| (name.clone(), states.clone()))
+        .collect::<HashMap<String, Vec<String>>>();
+    let invalid = |message: String| Error::Invalid {
+        format: FORMAT,
+        message,
+    };
+    let mut tables = Vec::new();
+    for (name, child_states) in variables {
+        let (parents, entries) = probabilities
+            .remove(&name)
+            .ok_or_else(|| invalid(format!("variable `{}` has no probability block", name)))?;
+        let mut parent_domains = Vec::new();
+        for parent in &parents {
+            parent_domains.push(states.get(parent).cloned().ok_or_else(|| {
+                invalid(format!("parent `{}` of `{}` is not defined", parent, name))
+            })?);
+        }
+        let keys = configurations(&parent_domains);
+        let rows = match entries {
+            Entries::Table(table) => {
+                if table.len() != keys.len() * child_states.len() {
+                    return Err(invalid(format!(
+                        "table of `{}` has {} entries, expected {}",
+                        name,
+                        table.len(),
+                        keys.len() * child_states.len()
+                    )));
+                }
+                keys.iter()
+                    .enumerate()
+                    .map(|(k, key)| {
+                        let probs = (0..child_states.len())
+                            .map(|s| table[s * keys.len() + k])
+                            .collect();
+                        (key.clone(), probs)
+                    })
+                    .collect()
+            }
+            Entries::Rows { mut rows, default } => {
+                if let Some(default) = default {
+                    for key in keys {
+                        if !rows.iter().any(|(row_key, _)| *row_key == key) {
+                            rows.push((key, default.clone()));
+                        }
+                    }
+                }
+                rows
+            }
+        };
+        tables.push(Table {
+            name,
+            states: child_states,
+            parents,
+            rows,
+            properties: Vec::new(),
+        });
+    }
+    if let Some(name) = probabilities.keys().next() {
+        return Err(invalid(format!(
+            "probability block for undefined variable `{}`",
+            name
+        )));
+    }
+    Ok(tables)
+}
+
+impl BayesianNetwork<String> {
+    /// Parse a network in the Bayesian Interchange Format (.bif).
+    /// The value space is the union of the states of every variable.
+    pub fn from_bif(source: &str) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_bif_with(source, Normalization::default())
+    }
+
+    /// Parse a network like `from_bif`, handling unnormalized rows with `normalization`.
+    pub fn from_bif_with(
+        source: &str,
+        normalization: Normalization,
+    ) -> Result<BayesianNetwork<String>, Error> {
+        build(FORMAT, parse(source)?, normalization)
+    }
+
+    /// Read a network from a .bif file.
+    pub fn read_bif<P: AsRef<Path>>(path: P) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_bif(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Display> BayesianNetwork<T> {
+    /// Write the network in the Bayesian Interchange Format (.bif).
+    /// Each variable's states are the values it can take with nonzero probability.
+    pub fn to_bif(&self) -> String {
+        let tables = tables(self);
+        let mut s = String::new();
+        s.push_str("network unknown {\n}\n");
+        let names = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| quoted(name))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        for table in &tables {
+            s.push_str(&format!(
+                "variable {} {{\n  type discrete [ {} ] {{ {} }};\n}}\n",
+                quoted(&table.name),
+                table.states.len(),
+                names(&table.states)
+            ));
+        }
+        for table in &tables {
+            let probs = |probs: &[Probability]| {
+                probs
+                    .iter()
+                    .map(|prob| prob.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            if table.parents.is_empty() {
+                s.push_str(&format!(
+                    "probability ( {} ) {{\n  table {};\n}}\n",
+                    quoted(&table.name),
+                    probs(&table.rows[0].1)
+                ));
+            } else {
+                s.push_str(&format!(
+                    "probability ( {} | {} ) {{\n",
+                    quoted(&table.name),
+                    names(&table.parents)
+                ));
+                for (key, row) in &table.rows {
+                    s.push_str(&format!("  ({}) {};\n", names(key), probs(row)));
+                }
+                s.push_str("}\n");
+            }
+        }
+        s
+    }
+
+    /// Write the network to a .bif file.
+    pub fn write_bif<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_bif())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bayesian_network::NodeType;
+    use crate::normalization::NormalizationPolicy;
+    use crate::testing::{assert_close, assert_same_joint, bonus, odd_names, TOLERANCE};
+
+    #[test]
+    fn to_bif_reads_back_as_the_same_network() {
+        let network = bonus();
+        let read = BayesianNetwork::from_bif(&network.to_bif()).unwrap();
+        assert_same_joint(&network, &read, TOLERANCE);
+    }
+
+    #[test]
+    fn names_with_spaces_and_punctuation_are_quoted() {
+        let network = odd_names();
+        let bif = network.to_bif();
+        assert!(bif.contains("variable \"dog out\" {"));
+        assert!(bif.contains("probability ( \"bowel-problem?\" | \"dog out\" ) {"));
+        let read = BayesianNetwork::from_bif(&bif).unwrap();
+        assert_eq!(read.node_names(), network.node_names());
+        assert_same_joint(&network, &read, TOLERANCE);
+    }
+
+    #[test]
+    fn tables_list_the_child_state_slowest_and_defaults_fill_missing_rows() {
+        let source = "
+            network sprinkler { property author \"x\"; }
+            variable Rain { type discrete [ 2 ] { yes, no }; }
+            variable Cloudy { type discrete [ 2 ] { yes, no }; }
+            variable Wet { type discrete [ 2 ] { yes, no }; }
+            // Old-style parents, without a bar.
+            probability ( Cloudy ) { table 0.4, 0.6; }
+            probability ( Rain Cloudy ) { table 0.8, 0.1, 0.2, 0.9; }
+            probability ( Wet | Rain ) { (yes) 0.9, 0.1; default 0.3, 0.7; }
+        ";
+        let network = BayesianNetwork::from_bif(source).unwrap();
+        // The probability that `name` is yes given its parent's `state`.
+        let yes = |name: &str, state: &str| {
+            let node = &network.nodes[network.get_node_index(name)];
+            network.cpt_probability(node, &[state.to_string()], &"yes".to_string())
+        };
+        assert_close(yes("Rain", "yes"), 0.8, TOLERANCE);
+        assert_close(yes("Rain", "no"), 0.1, TOLERANCE);
+        assert_close(yes("Wet", "yes"), 0.9, TOLERANCE);
+        assert_close(yes("Wet", "no"), 0.3, TOLERANCE);
+    }
+
+    #[test]
+    fn malformed_input_is_reported_with_its_line() {
+        let source =
+            "variable a { type discrete [ 2 ] { yes, no }; }\nprobability ( a ) { table 0.5 0.5 }";
+        match BayesianNetwork::from_bif(source) {
+            Err(Error::Syntax { format, line, .. }) => {
+                assert_eq!(format, FORMAT);
+                assert_eq!(line, 2);
+            }
+            result => panic!("expected a syntax error, got {:?}", result.err()),
+        }
+
+        let undefined = "variable a { type discrete [ 2 ] { yes, no }; }\nprobability ( a | b ) { table 0.5, 0.5; }";
+        assert!(matches!(
+            BayesianNetwork::from_bif(undefined),
+            Err(Error::Invalid { format: FORMAT, .. })
+        ));
+    }
+
+    #[test]
+    fn strict_normalization_rejects_unnormalized_tables() {
+        let source = "variable a { type discrete [ 2 ] { yes, no }; }\nprobability ( a ) { table 0.5, 0.6; }";
+        let strict = Normalization {
+            policy: NormalizationPolicy::Strict,
+            ..Normalization::default()
+        };
+        assert!(matches!(
+            BayesianNetwork::from_bif_with(source, strict),
+            Err(Error::Unnormalized { .. })
+        ));
+        let network = BayesianNetwork::from_bif(source).unwrap();
+        match &network.nodes[0].node_type {
+            NodeType::Root(prior) => assert_close(prior["yes"], 0.5 / 1.1, TOLERANCE),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use super::lexer::{Lexer, Token};
+use super::{build, quoted, tables, Table};
+use crate::bayesian_network::{BayesianNetwork, Probability};
+use crate::error::Error;
+use crate::learning::configurations;
+use crate::normalization::Normalization;
+use std::{collections::HashMap, fmt::Debug, fmt::Display, hash::Hash, path::Path};
+
+const FORMAT: &str = "Hugin NET";
+
+/// Read an attribute value up to its terminating `;`: a string, a word, or a parenthesized list
+/// (returned with its parentheses, items separated by spaces).
+fn attribute_value(lexer: &mut Lexer) -> Result<String, Error> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    loop {
+        match lexer.next()? {
+            Token::Punct(';') if depth == 0 => break,
+            Token::Punct('(') => {
+                depth += 1;
+                parts.push("(".to_string());
+            }
+            Token::Punct(')') => {
+                depth -= 1;
+                parts.push(")".to_string());
+            }
+            Token::Str(s) if depth == 0 && parts.is_empty() => parts.push(s),
+            Token::Str(s) => parts.push(format!("\"{}\"", s)),
+            Token::Word(word) => parts.push(word),
+            Token::Punct(c) => parts.push(c.to_string()),
+        }
+    }
+    Ok(parts.join(" ").replace("( ", "(").replace(" )", ")"))
+}
+
+fn node(lexer: &mut Lexer) -> Result<Table, Error> {
+    let name = lexer.name()?;
+    let mut states = Vec::new();
+    let mut properties = Vec::new();
+    lexer.expect_punct('{')?;
+    while !lexer.is_punct('}') {
+        let key = lexer.name()?;
+        lexer.expect_punct('=')?;
+        if key == "states" {
+            lexer.expect_punct('(')?;
+            while !lexer.is_punct(')') {
+                states.push(lexer.name()?);
+            }
+            lexer.expect_punct(')')?;
+            lexer.expect_punct(';')?;
+        } else {
+            properties.push((key, attribute_value(lexer)?));
+        }
+    }
+    lexer.expect_punct('}')?;
+    if states.is_empty() {
+        return lexer.error(format!("node `{}` has no states", name));
+    }
+    Ok(Table {
+        name,
+        states,
+        parents: Vec::new(),
+        rows: Vec::new(),
+        properties,
+    })
+}
+
+fn potential(lexer: &mut Lexer) -> Result<(String, Vec<String>, Vec<Probability>), Error> {
+    lexer.expect_punct('(')?;
+    let child = lexer.name()?;
+    let mut parents = Vec::new();
+    if lexer.is_punct('|') {
+        lexer.next()?;
+        while !lexer.is_punct(')') {
+            parents.push(lexer.name()?);
+        }
+    }
+    lexer.expect_punct(')')?;
+    lexer.expect_punct('{')?;
+    let mut data = None;
+    while !lexer.is_punct('}') {
+        let key = lexer.name()?;
+        lexer.expect_punct('=')?;
+        if key == "data" {
+            // Parentheses only group the numbers; the child state varies fastest.
+            let mut numbers = Vec::new();
+            while !lexer.is_punct(';') {
+                if lexer.is_punct('(') || lexer.is_punct(')') {
+                    lexer.next()?;
+                    continue;
+                }
+                numbers.push(lexer.number()?);
+            }
+            lexer.expect_punct(';')?;
+            data = Some(numbers);
+        } else {
+            lexer.skip_past(';')?;
+        }
+    }
+    lexer.expect_punct('}')?;
+    match data {
+        Some(data) => Ok((child, parents, data)),
+        None => lexer.error(format!("potential of `{}` has no data", child)),
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Table>, Error> {
+    let mut lexer = Lexer::new(FORMAT, source, &["%"])?;
+    let mut tables: Vec<Table> = Vec::new();
+    let mut potentials = Vec::new();
+    while let Some(token) = lexer.peek() {
+        match token.clone() {
+            Token::Word(word) if word == "net" => {
+                lexer.next()?;
+                lexer.skip_block()?;
+            }
+            Token::Word(word) if word == "discrete" => {
+                lexer.next()?;
+            }
+            Token::Word(word) if word == "node" => {
+                lexer.next()?;
+                tables.push(node(&mut lexer)?);
+            }
+            Token::Word(word) if word == "potential" => {
+                lexer.next()?;
+                potentials.push(potential(&mut lexer)?);
+            }
+            Token::Word(word)
+                if ["continuous", "decision", "utility", "class"].contains(&word.as_str()) =>
+            {
+                return lexer.error(format!("unsupported `{}` declaration", word));
+            }
+            token => return lexer.error(format!("unexpected {:?}", token)),
+        }
+    }
+
+    let states = tables
+        .iter()
+        .map(|table| (table.name.clone(), table.states.clone()))
+        .collect::<HashMap<String, Vec<String>>>();
+    let invalid = |message: String| Error::Invalid {
+        format: FORMAT,
+        message,
+    };
+    for (child, parents, data) in potentials {
+        let mut parent_domains = Vec::new();
+        for parent in &parents {
+            parent_domains.push(states.get(parent).cloned().ok_or_else(|| {
+                invalid(format!("parent `{}` of `{}` is not defined", parent, child))
+            })?);
+        }
+        let table = tables
+            .iter_mut()
+            .find(|table| table.name == child)
+            .ok_or_else(|| invalid(format!("potential for undefined node `{}`", child)))?;
+        if !table.rows.is_empty() {
+            return Err(invalid(format!("node `{}` has two potentials", child)));
+        }
+        let keys = configurations(&parent_domains);
+        if data.len() != keys.len() * table.states.len() {
+            return Err(invalid(format!(
+                "potential of `{}` has {} entries, expected {}",
+                child,
+                data.len(),
+                keys.len() * table.states.len()
+            )));
+        }
+        table.parents = parents;
+        table.rows = keys
+            .into_iter()
+            .zip(data.chunks(table.states.len()))
+            .map(|(key, probs)| (key, probs.to_vec()))
+            .collect();
+    }
+    Ok(tables)
+}
+
+impl BayesianNetwork<String> {
+    /// Parse a network in the Hugin NET format.
+    /// Node attributes other than `states` (such as `label` and `position`) become node metadata.
+    pub fn from_hugin(source: &str) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_hugin_with(source, Normalization::default())
+    }
+
+    /// Parse a network like `from_hugin`, handling unnormalized rows with `normalization`.
+    pub fn from_hugin_with(
+        source: &str,
+        normalization: Normalization,
+    ) -> Result<BayesianNetwork<String>, Error> {
+        build(FORMAT, parse(source)?, normalization)
+    }
+
+    /// Read a network from a Hugin .net file.
+    pub fn read_hugin<P: AsRef<Path>>(path: P) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_hugin(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Display> BayesianNetwork<T> {
+    /// Write the network in the Hugin NET format.
+    pub fn to_hugin(&self) -> String {
+        let tables = tables(self);
+        let mut s = String::new();
+        s.push_str("net\n{\n}\n");
+        for table in &tables {
+            s.push_str(&format!("\nnode {}\n{{\n", quoted(&table.name)));
+            let states = table
+                .states
+                .iter()
+                .map(|state| format!("\"{}\"", state))
+                .collect::<Vec<String>>();
+            s.push_str(&format!("    states = ({});\n", states.join(" ")));
+            for (key, value) in &table.properties {
+                if value.starts_with('(') {
+                    s.push_str(&format!("    {} = {};\n", key, value.replace(',', "")));
+                } else {
+                    s.push_str(&format!("    {} = \"{}\";\n", key, value));
+                }
+            }
+            s.push_str("}\n");
+        }
+        for table in &tables {
+            if table.parents.is_empty() {
+                s.push_str(&format!("\npotential ( {} )\n{{\n", quoted(&table.name)));
+            } else {
+                s.push_str(&format!(
+                    "\npotential ( {} | {} )\n{{\n",
+                    quoted(&table.name),
+                    table
+                        .parents
+                        .iter()
+                        .map(|parent| quoted(parent))
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                ));
+            }
+            let rows = table
+                .rows
+                .iter()
+                .map(|(_, probs)| {
+                    let probs = probs
+                        .iter()
+                        .map(|prob| prob.to_string())
+                        .collect::<Vec<String>>();
+                    format!("({})", probs.join(" "))
+                })
+                .collect::<Vec<String>>();
+            s.push_str(&format!(
+                "    data = ({});\n}}\n",
+                rows.join("\n            ")
+            ));
+        }
+        s
+    }
+
+    /// Write the network to a Hugin .net file.
+    pub fn write_hugin<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_hugin())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, assert_same_joint, bonus, odd_names, TOLERANCE};
+
+    #[test]
+    fn to_hugin_reads_back_with_attributes() {
+        let mut network = bonus();
+        network.set_node_metadata("mood", "label", "Good mood");
+        network.set_node_metadata("mood", "position", "(10 20)");
+        let read = BayesianNetwork::from_hugin(&network.to_hugin()).unwrap();
+        assert_same_joint(&network, &read, TOLERANCE);
+        assert_eq!(read.get_node_metadata("mood", "label"), Some("Good mood"));
+        assert_eq!(read.get_node_metadata("mood", "position"), Some("(10 20)"));
+    }
+
+    #[test]
+    fn names_with_spaces_and_punctuation_are_quoted() {
+        let network = odd_names();
+        let net = network.to_hugin();
+        assert!(net.contains("node \"dog out\"\n"));
+        assert!(net.contains("potential ( \"bowel-problem?\" | \"dog out\" )"));
+        let read = BayesianNetwork::from_hugin(&net).unwrap();
+        assert_eq!(read.node_names(), network.node_names());
+        assert_same_joint(&network, &read, TOLERANCE);
+    }
+
+    #[test]
+    fn data_lists_the_child_state_fastest() {
+        let source = "
+            % A comment.
+            net { node_size = (80 40); }
+            node a { states = (\"yes\" \"no\"); }
+            discrete node b { states = (\"yes\" \"no\"); label = \"B\"; }
+            potential ( a ) { data = ( 0.3 0.7 ); }
+            potential ( b | a ) { data = (( 0.9 0.1 ) ( 0.2 0.8 )); experience = (1 1); }
+        ";
+        let network = BayesianNetwork::from_hugin(source).unwrap();
+        let b = &network.nodes[network.get_node_index("b")];
+        let yes = "yes".to_string();
+        assert_close(
+            network.cpt_probability(b, &["no".to_string()], &yes),
+            0.2,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn unsupported_and_incomplete_networks_are_rejected() {
+        assert!(matches!(
+            BayesianNetwork::from_hugin("node a { states = (\"x\"); }\ncontinuous node b { }"),
+            Err(Error::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(
+            BayesianNetwork::from_hugin("node a { states = (\"x\"); }\npotential ( a ) { }"),
+            Err(Error::Syntax { .. })
+        ));
+        assert!(matches!(
+            BayesianNetwork::from_hugin("node a { states = (\"x\"); }"),
+            Err(Error::Invalid { .. })
+        ));
+    }
+}
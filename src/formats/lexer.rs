@@ -0,0 +1,236 @@
+use crate::error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token {
+    /// An identifier or number.
+    Word(String),
+    /// A double-quoted string, without the quotes.
+    Str(String),
+    Punct(char),
+}
+
+const PUNCTUATION: &str = "{}()[]|,;=";
+
+/// A tokenizer for the C-like text formats (BIF, Hugin NET) and textual queries.
+pub(crate) struct Lexer {
+    format: &'static str,
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Lexer {
+    /// Tokenize `source`, skipping `/* */` block comments and lines starting with any of
+    /// `line_comments`.
+    pub(crate) fn new(
+        format: &'static str,
+        source: &str,
+        line_comments: &[&str],
+    ) -> Result<Lexer, Error> {
+        let mut tokens = Vec::new();
+        let mut line = 1;
+        let mut rest = source;
+        while let Some(c) = rest.chars().next() {
+            if c == '\n' {
+                line += 1;
+                rest = &rest[1..];
+            } else if c.is_whitespace() {
+                rest = &rest[c.len_utf8()..];
+            } else if line_comments
+                .iter()
+                .any(|comment| rest.starts_with(comment))
+            {
+                rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let end = comment.find("*/").ok_or(Error::Syntax {
+                    format,
+                    line,
+                    message: "unterminated comment".to_string(),
+                })?;
+                line += comment[..end].matches('\n').count();
+                rest = &comment[end + 2..];
+            } else if c == '"' {
+                let end = rest[1..].find('"').ok_or(Error::Syntax {
+                    format,
+                    line,
+                    message: "unterminated string".to_string(),
+                })?;
+                tokens.push((Token::Str(rest[1..end + 1].to_string()), line));
+                line += rest[1..end + 1].matches('\n').count();
+                rest = &rest[end + 2..];
+            } else if PUNCTUATION.contains(c) {
+                tokens.push((Token::Punct(c), line));
+                rest = &rest[1..];
+            } else {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '"' || PUNCTUATION.contains(c))
+                    .unwrap_or(rest.len());
+                tokens.push((Token::Word(rest[..end].to_string()), line));
+                rest = &rest[end..];
+            }
+        }
+        Ok(Lexer {
+            format,
+            tokens,
+            position: 0,
+        })
+    }
+
+    pub(crate) fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map(|(_, line)| *line)
+            .unwrap_or(1)
+    }
+
+    pub(crate) fn error<V>(&self, message: String) -> Result<V, Error> {
+        Err(Error::Syntax {
+            format: self.format,
+            line: self.line(),
+            message,
+        })
+    }
+
+    pub(crate) fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Token, Error> {
+        match self.tokens.get(self.position) {
+            Some((token, _)) => {
+                self.position += 1;
+                Ok(token.clone())
+            }
+            None => self.error("unexpected end of input".to_string()),
+        }
+    }
+
+    pub(crate) fn is_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    pub(crate) fn expect_punct(&mut self, c: char) -> Result<(), Error> {
+        match self.next()? {
+            Token::Punct(p) if p == c => Ok(()),
+            token => {
+                self.position -= 1;
+                self.error(format!("expected `{}`, found {:?}", c, token))
+            }
+        }
+    }
+
+    pub(crate) fn expect_word(&mut self, word: &str) -> Result<(), Error> {
+        match self.next()? {
+            Token::Word(w) if w == word => Ok(()),
+            token => {
+                self.position -= 1;
+                self.error(format!("expected `{}`, found {:?}", word, token))
+            }
+        }
+    }
+
+    /// Read an identifier, which may also be given as a quoted string.
+    pub(crate) fn name(&mut self) -> Result<String, Error> {
+        match self.next()? {
+            Token::Word(word) | Token::Str(word) => Ok(word),
+            token => {
+                self.position -= 1;
+                self.error(format!("expected a name, found {:?}", token))
+            }
+        }
+    }
+
+    pub(crate) fn number(&mut self) -> Result<f64, Error> {
+        match self.next()? {
+            Token::Word(word) => match word.parse() {
+                Ok(number) => Ok(number),
+                Err(_) => {
+                    self.position -= 1;
+                    self.error(format!("expected a number, found `{}`", word))
+                }
+            },
+            token => {
+                self.position -= 1;
+                self.error(format!("expected a number, found {:?}", token))
+            }
+        }
+    }
+
+    /// Skip tokens up to and including the next `c`.
+    pub(crate) fn skip_past(&mut self, c: char) -> Result<(), Error> {
+        while self.next()? != Token::Punct(c) {}
+        Ok(())
+    }
+
+    /// Skip a balanced `{ ... }` block, starting at its opening brace.
+    pub(crate) fn skip_block(&mut self) -> Result<(), Error> {
+        self.expect_punct('{')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Punct('{') => depth += 1,
+                Token::Punct('}') => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_skip_comments_and_keep_their_lines() {
+        let source = "// header\nvariable \"a b\" {\n/* two\nlines */ type [2];\n}";
+        let mut lexer = Lexer::new("BIF", source, &["//"]).unwrap();
+        let mut tokens = Vec::new();
+        while lexer.peek().is_some() {
+            let line = lexer.line();
+            tokens.push((lexer.next().unwrap(), line));
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::Word("variable".to_string()), 2),
+                (Token::Str("a b".to_string()), 2),
+                (Token::Punct('{'), 2),
+                (Token::Word("type".to_string()), 4),
+                (Token::Punct('['), 4),
+                (Token::Word("2".to_string()), 4),
+                (Token::Punct(']'), 4),
+                (Token::Punct(';'), 4),
+                (Token::Punct('}'), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_report_the_line_of_the_offending_token() {
+        let mut lexer = Lexer::new("BIF", "table\n0.5,\nhalf;", &[]).unwrap();
+        lexer.expect_word("table").unwrap();
+        assert_eq!(lexer.number().unwrap(), 0.5);
+        lexer.expect_punct(',').unwrap();
+        match lexer.number() {
+            Err(Error::Syntax { line, .. }) => assert_eq!(line, 3),
+            result => panic!("expected a syntax error, got {:?}", result),
+        }
+        // A failed expectation does not consume the token.
+        assert_eq!(lexer.name().unwrap(), "half");
+    }
+
+    #[test]
+    fn unterminated_comments_and_strings_are_syntax_errors() {
+        for source in ["a /* b", "a\n\"b"] {
+            match Lexer::new("NET", source, &[]) {
+                Err(Error::Syntax { format, line, .. }) => {
+                    assert_eq!(format, "NET");
+                    assert_eq!(line, if source.contains('\n') { 2 } else { 1 });
+                }
+                Err(error) => panic!("expected a syntax error, got {}", error),
+                Ok(_) => panic!("expected a syntax error"),
+            }
+        }
+    }
+}
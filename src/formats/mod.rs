@@ -0,0 +1,308 @@
+mod bif;
+mod hugin;
+pub(crate) mod lexer;
+mod uai;
+mod xdsl;
+mod xmlbif;
+
+use crate::bayesian_network::{BayesianNetwork, NodeType, Probability};
+use crate::error::Error;
+use crate::learning::configurations;
+use crate::normalization::Normalization;
+use std::{collections::HashMap, fmt::Debug, fmt::Display, hash::Hash};
+
+/// A variable with its states and conditional probability table, as exchanged by file formats.
+pub(crate) struct Table {
+    pub(crate) name: String,
+    pub(crate) states: Vec<String>,
+    pub(crate) parents: Vec<String>,
+    /// Rows keyed by parent states, each giving the probabilities of `states` in order.
+    pub(crate) rows: Vec<(Vec<String>, Vec<Probability>)>,
+    /// Node metadata as key-value pairs.
+    pub(crate) properties: Vec<(String, String)>,
+}
+
+/// Build a network from the tables of every variable, adding nodes in the given order, with
+/// `normalization` applied to every row.
+pub(crate) fn build(
+    format: &'static str,
+    tables: Vec<Table>,
+    normalization: Normalization,
+) -> Result<BayesianNetwork<String>, Error> {
+    let invalid = |message: String| Error::Invalid { format, message };
+    let mut value_space = Vec::new();
+    let mut states = HashMap::new();
+    for table in &tables {
+        if states.insert(table.name.as_str(), &table.states).is_some() {
+            return Err(invalid(format!(
+                "variable `{}` is defined twice",
+                table.name
+            )));
+        }
+        for state in &table.states {
+            if !value_space.contains(state) {
+                value_space.push(state.clone());
+            }
+        }
+    }
+    let mut has_children = HashMap::new();
+    for table in &tables {
+        for parent in &table.parents {
+            let parent_states = states.get(parent.as_str()).ok_or_else(|| {
+                invalid(format!(
+                    "parent `{}` of `{}` is not defined",
+                    parent, table.name
+                ))
+            })?;
+            if parent_states.is_empty() {
+                return Err(invalid(format!("variable `{}` has no states", parent)));
+            }
+            has_children.insert(parent.as_str(), true);
+        }
+        for (key, probs) in &table.rows {
+            if key.len() != table.parents.len() || probs.len() != table.states.len() {
+                return Err(invalid(format!(
+                    "malformed probability row for `{}`",
+                    table.name
+                )));
+            }
+            for (parent, value) in table.parents.iter().zip(key) {
+                if !states[parent.as_str()].contains(value) {
+                    return Err(invalid(format!(
+                        "`{}` is not a state of `{}` (in the table of `{}`)",
+                        value, parent, table.name
+                    )));
+                }
+            }
+        }
+    }
+
+    let mut network = BayesianNetwork::new(value_space);
+    network.normalization = normalization;
+    for table in &tables {
+        let node_type = if table.parents.is_empty() {
+            let probs = table
+                .rows
+                .first()
+                .map(|(_, probs)| probs.clone())
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "variable `{}` has no probability table",
+                        table.name
+                    ))
+                })?;
+            let mut prior = table.states.iter().cloned().zip(probs).collect();
+            network.normalize_row(&table.name, &[], &mut prior)?;
+            NodeType::Root(prior)
+        } else if has_children.contains_key(table.name.as_str()) {
+            NodeType::Inner
+        } else {
+            NodeType::Leaf
+        };
+        network.add_node(&table.name, node_type);
+        network.set_node_states(&table.name, table.states.clone());
+        for (key, value) in &table.properties {
+            network.set_node_metadata(&table.name, key, value);
+        }
+    }
+    for table in &tables {
+        if table.parents.is_empty() {
+            continue;
+        }
+        let prob = table
+            .rows
+            .iter()
+            .map(|(key, probs)| {
+                (
+                    key.clone(),
+                    table
+                        .states
+                        .iter()
+                        .cloned()
+                        .zip(probs.iter().copied())
+                        .collect(),
+                )
+            })
+            .collect::<HashMap<Vec<String>, HashMap<String, Probability>>>();
+        network.try_add_dependency(
+            table.parents.iter().map(|parent| parent.as_str()).collect(),
+            &table.name,
+            prob,
+        )?;
+    }
+    Ok(network)
+}
+
+/// A node or state name as written by the C-like text formats: as it is if it is a plain
+/// identifier, or else double-quoted so that spaces and punctuation read back.
+pub(crate) fn quoted(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name)
+    }
+}
+
+/// Describe every node of a network as a table, in node order. The states of a node are its
+/// declared states, or else the values of the value space it can take with nonzero probability
+/// (all of them if it can take none).
+pub(crate) fn tables<T: Clone + PartialEq + Eq + Hash + Debug + Display>(
+    network: &BayesianNetwork<T>,
+) -> Vec<Table> {
+    let domains = network
+        .nodes
+        .iter()
+        .map(|node| network.get_node_domain(node.id))
+        .collect::<Vec<Vec<T>>>();
+
+    network
+        .nodes
+        .iter()
+        .map(|node| {
+            let rows = match &node.node_type {
+                NodeType::Root(prob_map) => vec![(
+                    Vec::new(),
+                    domains[node.id]
+                        .iter()
+                        .map(|value| prob_map[value])
+                        .collect(),
+                )],
+                _ => {
+                    let parent_domains = node
+                        .parents
+                        .iter()
+                        .map(|parent| domains[*parent].clone())
+                        .collect::<Vec<Vec<T>>>();
+                    configurations(&parent_domains)
+                        .into_iter()
+                        .map(|key| {
+                            let probs = domains[node.id]
+                                .iter()
+                                .map(|value| network.cpt_probability(node, &key, value))
+                                .collect();
+                            (key.iter().map(|value| value.to_string()).collect(), probs)
+                        })
+                        .collect()
+                }
+            };
+            Table {
+                name: network.get_node_name(node.id).to_string(),
+                states: domains[node.id]
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect(),
+                parents: node
+                    .parents
+                    .iter()
+                    .map(|parent| network.get_node_name(*parent).to_string())
+                    .collect(),
+                rows,
+                properties: node
+                    .metadata
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalization::NormalizationPolicy;
+    use crate::testing::{assert_same_joint, bonus, TOLERANCE};
+
+    fn table(name: &str, parents: &[&str], rows: Vec<(Vec<&str>, Vec<Probability>)>) -> Table {
+        Table {
+            name: name.to_string(),
+            states: vec!["yes".to_string(), "no".to_string()],
+            parents: parents.iter().map(|parent| parent.to_string()).collect(),
+            rows: rows
+                .into_iter()
+                .map(|(key, probs)| (key.into_iter().map(String::from).collect(), probs))
+                .collect(),
+            properties: Vec::new(),
+        }
+    }
+
+    fn strict() -> Normalization {
+        Normalization {
+            policy: NormalizationPolicy::Strict,
+            ..Normalization::default()
+        }
+    }
+
+    #[test]
+    fn tables_build_back_into_the_same_network() {
+        let network = bonus();
+        let built = build("test", tables(&network), Normalization::default()).unwrap();
+        assert_same_joint(&network, &built, TOLERANCE);
+    }
+
+    #[test]
+    fn build_rejects_cycles_and_unknown_states() {
+        let cyclic = vec![
+            table(
+                "a",
+                &["b"],
+                vec![(vec!["yes"], vec![0.5, 0.5]), (vec!["no"], vec![0.5, 0.5])],
+            ),
+            table(
+                "b",
+                &["a"],
+                vec![(vec!["yes"], vec![0.5, 0.5]), (vec!["no"], vec![0.5, 0.5])],
+            ),
+        ];
+        assert!(matches!(
+            build("test", cyclic, Normalization::default()),
+            Err(Error::CycleDetected(_))
+        ));
+
+        let unknown = vec![
+            table("a", &[], vec![(vec![], vec![0.5, 0.5])]),
+            table("b", &["a"], vec![(vec!["maybe"], vec![0.5, 0.5])]),
+        ];
+        assert!(matches!(
+            build("test", unknown, Normalization::default()),
+            Err(Error::Invalid { format: "test", .. })
+        ));
+    }
+
+    #[test]
+    fn build_applies_the_normalization_policy() {
+        let tables = || {
+            vec![
+                table("a", &[], vec![(vec![], vec![0.5, 0.5])]),
+                table(
+                    "b",
+                    &["a"],
+                    vec![(vec!["yes"], vec![3.0, 1.0]), (vec!["no"], vec![0.5, 0.5])],
+                ),
+            ]
+        };
+        match build("test", tables(), strict()) {
+            Err(Error::Unnormalized { name, sum, .. }) => {
+                assert_eq!(name, "b");
+                assert_eq!(sum, 4.0);
+            }
+            result => panic!("expected an unnormalized row, got {:?}", result.err()),
+        }
+        let network = build("test", tables(), Normalization::default()).unwrap();
+        let b = &network.nodes[network.get_node_index("b")];
+        let key = vec!["yes".to_string()];
+        assert_eq!(network.cpt_probability(b, &key, &"yes".to_string()), 0.75);
+    }
+
+    #[test]
+    fn escape_xml_escapes_markup() {
+        assert_eq!(escape_xml("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+}
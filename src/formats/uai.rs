@@ -0,0 +1,402 @@
+use super::{build, tables, Table};
+use crate::bayesian_network::{BayesianNetwork, Probability};
+use crate::error::Error;
+use crate::learning::configurations;
+use crate::normalization::Normalization;
+use std::{collections::HashMap, fmt::Debug, fmt::Display, hash::Hash, path::Path};
+
+const FORMAT: &str = "UAI";
+
+/// Whitespace-separated tokens with their line numbers.
+struct Tokens<'a> {
+    tokens: Vec<(&'a str, usize)>,
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(source: &'a str) -> Tokens<'a> {
+        Tokens {
+            tokens: source
+                .lines()
+                .enumerate()
+                .flat_map(|(i, line)| line.split_whitespace().map(move |token| (token, i + 1)))
+                .collect(),
+            position: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn error<V>(&self, message: String) -> Result<V, Error> {
+        Err(Error::Syntax {
+            format: FORMAT,
+            line: self
+                .tokens
+                .get(self.position)
+                .or(self.tokens.last())
+                .map(|(_, line)| *line)
+                .unwrap_or(1),
+            message,
+        })
+    }
+
+    fn next(&mut self) -> Result<&'a str, Error> {
+        match self.tokens.get(self.position) {
+            Some((token, _)) => {
+                self.position += 1;
+                Ok(token)
+            }
+            None => self.error("unexpected end of input".to_string()),
+        }
+    }
+
+    fn integer(&mut self) -> Result<usize, Error> {
+        let token = self.next()?;
+        match token.parse() {
+            Ok(integer) => Ok(integer),
+            Err(_) => {
+                self.position -= 1;
+                self.error(format!(
+                    "expected a non-negative integer, found `{}`",
+                    token
+                ))
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Probability, Error> {
+        let token = self.next()?;
+        match token.parse() {
+            Ok(number) => Ok(number),
+            Err(_) => {
+                self.position -= 1;
+                self.error(format!("expected a number, found `{}`", token))
+            }
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Table>, Error> {
+    let mut tokens = Tokens::new(source);
+    match tokens.next()? {
+        "BAYES" => {}
+        preamble => {
+            tokens.position -= 1;
+            return match preamble {
+                "MARKOV" => tokens.error("MARKOV networks are not supported".to_string()),
+                _ => tokens.error(format!("unknown network type `{}`", preamble)),
+            };
+        }
+    }
+    let variable_count = tokens.integer()?;
+    let mut cardinalities = Vec::new();
+    for _ in 0..variable_count {
+        cardinalities.push(tokens.integer()?);
+    }
+    let function_count = tokens.integer()?;
+    let mut scopes = Vec::new();
+    for _ in 0..function_count {
+        let size = tokens.integer()?;
+        if size == 0 {
+            return tokens.error("function with an empty scope".to_string());
+        }
+        let mut scope = Vec::new();
+        for _ in 0..size {
+            let variable = tokens.integer()?;
+            if variable >= variable_count {
+                return tokens.error(format!("variable {} is out of range", variable));
+            }
+            scope.push(variable);
+        }
+        scopes.push(scope);
+    }
+
+    let states = cardinalities
+        .iter()
+        .map(|cardinality| (0..*cardinality).map(|state| state.to_string()).collect())
+        .collect::<Vec<Vec<String>>>();
+    let mut tables = (0..variable_count)
+        .map(|variable| Table {
+            name: variable.to_string(),
+            states: states[variable].clone(),
+            parents: Vec::new(),
+            rows: Vec::new(),
+            properties: Vec::new(),
+        })
+        .collect::<Vec<Table>>();
+    // Each function is the CPT of the last variable of its scope, which varies fastest.
+    for scope in scopes {
+        let (child, parents) = scope.split_last().unwrap();
+        let keys = configurations(
+            &parents
+                .iter()
+                .map(|parent| states[*parent].clone())
+                .collect::<Vec<Vec<String>>>(),
+        );
+        let entry_count = tokens.integer()?;
+        if entry_count != keys.len() * cardinalities[*child] {
+            return tokens.error(format!(
+                "function of variable {} has {} entries, expected {}",
+                child,
+                entry_count,
+                keys.len() * cardinalities[*child]
+            ));
+        }
+        let mut values = Vec::new();
+        for _ in 0..entry_count {
+            values.push(tokens.number()?);
+        }
+        let table = &mut tables[*child];
+        if !table.rows.is_empty() {
+            return tokens.error(format!("variable {} has two functions", child));
+        }
+        table.parents = parents.iter().map(|parent| parent.to_string()).collect();
+        table.rows = keys
+            .into_iter()
+            .zip(values.chunks(cardinalities[*child]))
+            .map(|(key, probs)| (key, probs.to_vec()))
+            .collect();
+    }
+    if !tokens.is_empty() {
+        return tokens.error("unexpected trailing input".to_string());
+    }
+    Ok(tables)
+}
+
+impl BayesianNetwork<String> {
+    /// Parse a network in the UAI inference-competition format (BAYES networks only).
+    /// Variables are named by their index (`"0"`, `"1"`, ...) and states likewise.
+    pub fn from_uai(source: &str) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_uai_with(source, Normalization::default())
+    }
+
+    /// Parse a network like `from_uai`, handling unnormalized rows with `normalization`.
+    pub fn from_uai_with(
+        source: &str,
+        normalization: Normalization,
+    ) -> Result<BayesianNetwork<String>, Error> {
+        build(FORMAT, parse(source)?, normalization)
+    }
+
+    /// Read a network from a .uai file.
+    pub fn read_uai<P: AsRef<Path>>(path: P) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_uai(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Display> BayesianNetwork<T> {
+    /// Write the network in the UAI format. Variables are numbered in node order and
+    /// states in the order of each node's states.
+    pub fn to_uai(&self) -> String {
+        let tables = tables(self);
+        let index = tables
+            .iter()
+            .enumerate()
+            .map(|(i, table)| (table.name.as_str(), i))
+            .collect::<HashMap<&str, usize>>();
+        let mut s = String::new();
+        s.push_str("BAYES\n");
+        s.push_str(&format!("{}\n", tables.len()));
+        let cardinalities = tables
+            .iter()
+            .map(|table| table.states.len().to_string())
+            .collect::<Vec<String>>();
+        s.push_str(&format!("{}\n", cardinalities.join(" ")));
+        s.push_str(&format!("{}\n", tables.len()));
+        for (i, table) in tables.iter().enumerate() {
+            let mut scope = table
+                .parents
+                .iter()
+                .map(|parent| index[parent.as_str()].to_string())
+                .collect::<Vec<String>>();
+            scope.push(i.to_string());
+            s.push_str(&format!("{} {}\n", scope.len(), scope.join(" ")));
+        }
+        for table in &tables {
+            s.push_str(&format!("\n{}\n", table.rows.len() * table.states.len()));
+            for (_, probs) in &table.rows {
+                let probs = probs
+                    .iter()
+                    .map(|prob| prob.to_string())
+                    .collect::<Vec<String>>();
+                s.push_str(&format!(" {}\n", probs.join(" ")));
+            }
+        }
+        s
+    }
+
+    /// Write the network to a .uai file.
+    pub fn write_uai<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_uai())?)
+    }
+
+    /// Parse a UAI evidence (.evid) file into evidence cases keyed by node name, using the same
+    /// variable and state numbering as `to_uai`. Both the single-case format
+    /// (`count var value ...`) and the older multi-case format (a case count first) are accepted.
+    pub fn parse_uai_evidence(&self, source: &str) -> Result<Vec<HashMap<String, T>>, Error> {
+        let tables = tables(self);
+        let mut tokens = Tokens::new(source);
+        let mut integers = Vec::new();
+        while !tokens.is_empty() {
+            integers.push(tokens.integer()?);
+        }
+        let cases = match integers.first() {
+            None => return Ok(Vec::new()),
+            Some(count) if integers.len() == 2 * count + 1 => vec![&integers[..]],
+            Some(case_count) => {
+                let mut cases = Vec::new();
+                let mut rest = &integers[1..];
+                for _ in 0..*case_count {
+                    let length = rest.first().map(|count| 2 * count + 1).unwrap_or(1);
+                    if rest.len() < length {
+                        return tokens.error("evidence case is truncated".to_string());
+                    }
+                    cases.push(&rest[..length]);
+                    rest = &rest[length..];
+                }
+                if !rest.is_empty() {
+                    return tokens.error("unexpected trailing input".to_string());
+                }
+                cases
+            }
+        };
+        let invalid = |message: String| Error::Invalid {
+            format: FORMAT,
+            message,
+        };
+        cases
+            .into_iter()
+            .map(|case| {
+                case[1..]
+                    .chunks(2)
+                    .map(|pair| {
+                        let table = tables.get(pair[0]).ok_or_else(|| {
+                            invalid(format!("evidence variable {} is out of range", pair[0]))
+                        })?;
+                        let id = self.get_node_index(&table.name);
+                        let states = self.nodes[id].states.clone().unwrap_or_else(|| {
+                            self.value_space
+                                .iter()
+                                .filter(|value| table.states.contains(&value.to_string()))
+                                .cloned()
+                                .collect()
+                        });
+                        let value = states.get(pair[1]).cloned().ok_or_else(|| {
+                            invalid(format!(
+                                "evidence state {} of variable {} is out of range",
+                                pair[1], pair[0]
+                            ))
+                        })?;
+                        Ok((table.name.clone(), value))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Read a UAI evidence (.evid) file. See `parse_uai_evidence`.
+    pub fn read_uai_evidence<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<Vec<HashMap<String, T>>, Error> {
+        self.parse_uai_evidence(&std::fs::read_to_string(path)?)
+    }
+
+    /// Write an evidence case in the single-case UAI evidence format.
+    pub fn to_uai_evidence(&self, evidence: &HashMap<&str, T>) -> String {
+        let tables = tables(self);
+        let mut pairs = evidence
+            .iter()
+            .map(|(name, value)| {
+                let variable = tables
+                    .iter()
+                    .position(|table| table.name == *name)
+                    .unwrap_or_else(|| panic!("Evidence node `{}` not found", name));
+                let state = tables[variable]
+                    .states
+                    .iter()
+                    .position(|state| *state == value.to_string())
+                    .unwrap_or_else(|| {
+                        panic!("Evidence value {:?} is not a state of `{}`", value, name)
+                    });
+                (variable, state)
+            })
+            .collect::<Vec<(usize, usize)>>();
+        pairs.sort();
+        let mut s = pairs.len().to_string();
+        for (variable, state) in pairs {
+            s.push_str(&format!(" {} {}", variable, state));
+        }
+        s.push('\n');
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, TOLERANCE};
+
+    #[test]
+    fn to_uai_reads_back_with_numbered_variables_and_states() {
+        let network = bonus();
+        let read = BayesianNetwork::from_uai(&network.to_uai()).unwrap();
+        let evidence = HashMap::from([("bonus", true)]);
+        let read_evidence = read
+            .parse_uai_evidence(&network.to_uai_evidence(&evidence))
+            .unwrap();
+        assert_eq!(
+            read_evidence,
+            vec![HashMap::from([("3".to_string(), "0".to_string())])]
+        );
+        let read_evidence = read_evidence[0]
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+        let expected = network.infer(&evidence);
+        let actual = read.infer(&read_evidence);
+        for node in &network.nodes {
+            for (state, value) in network.get_node_domain(node.id).iter().enumerate() {
+                assert_close(
+                    actual[read.get_node_index(&node.id.to_string())][&state.to_string()],
+                    expected[node.id][value],
+                    TOLERANCE,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn evidence_files_may_hold_several_cases() {
+        let network = bonus();
+        let cases = network.parse_uai_evidence("2\n1 3 0\n2 0 1 4 0").unwrap();
+        assert_eq!(
+            cases,
+            vec![
+                HashMap::from([("bonus".to_string(), true)]),
+                HashMap::from([
+                    ("performance".to_string(), false),
+                    ("feast".to_string(), true)
+                ]),
+            ]
+        );
+        assert!(matches!(
+            network.parse_uai_evidence("1 7 0"),
+            Err(Error::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_networks_are_rejected_with_their_line() {
+        assert!(matches!(
+            BayesianNetwork::from_uai("MARKOV\n1\n2\n1\n1 0\n2 0.5 0.5"),
+            Err(Error::Syntax { line: 1, .. })
+        ));
+        assert!(matches!(
+            BayesianNetwork::from_uai("BAYES\n1\n2\n1\n1 0\n\n3 0.5 0.5 0.5"),
+            Err(Error::Syntax { line: 7, .. })
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+use super::{build, Table};
+use crate::bayesian_network::{BayesianNetwork, Probability};
+use crate::error::Error;
+use crate::learning::configurations;
+use crate::normalization::Normalization;
+use roxmltree::{Document, Node};
+use std::{collections::HashMap, path::Path};
+
+const FORMAT: &str = "XDSL";
+
+fn syntax_error(node: Node, message: String) -> Error {
+    Error::Syntax {
+        format: FORMAT,
+        line: node.document().text_pos_at(node.range().start).row as usize,
+        message,
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.is_element() && child.has_tag_name(tag))
+}
+
+fn id(node: Node) -> Result<String, Error> {
+    node.attribute("id")
+        .map(|id| id.to_string())
+        .ok_or_else(|| syntax_error(node, format!("<{}> has no id", node.tag_name().name())))
+}
+
+fn parse(source: &str) -> Result<Vec<Table>, Error> {
+    let document = Document::parse(source).map_err(|error| Error::Syntax {
+        format: FORMAT,
+        line: error.pos().row as usize,
+        message: error.to_string(),
+    })?;
+    let root = document.root_element();
+    let nodes =
+        child(root, "nodes").ok_or_else(|| syntax_error(root, "missing <nodes>".to_string()))?;
+    let invalid = |message: String| Error::Invalid {
+        format: FORMAT,
+        message,
+    };
+
+    let mut tables: Vec<Table> = Vec::new();
+    let mut states: HashMap<String, Vec<String>> = HashMap::new();
+    for node in nodes.children().filter(|node| node.is_element()) {
+        let class = node.tag_name().name();
+        if class != "cpt" && class != "deterministic" {
+            return Err(invalid(format!(
+                "node `{}` has unsupported class <{}>; only <cpt> and <deterministic> nodes can be read",
+                node.attribute("id").unwrap_or("?"),
+                class
+            )));
+        }
+        let name = id(node)?;
+        let node_states = node
+            .children()
+            .filter(|child| child.is_element() && child.has_tag_name("state"))
+            .map(id)
+            .collect::<Result<Vec<String>, Error>>()?;
+        let parents = child(node, "parents")
+            .and_then(|parents| parents.text())
+            .unwrap_or("")
+            .split_whitespace()
+            .map(|parent| parent.to_string())
+            .collect::<Vec<String>>();
+        let mut parent_domains = Vec::new();
+        for parent in &parents {
+            // XDSL lists nodes after their parents.
+            parent_domains.push(states.get(parent).cloned().ok_or_else(|| {
+                invalid(format!(
+                    "parent `{}` of `{}` is not defined before it",
+                    parent, name
+                ))
+            })?);
+        }
+        let keys = configurations(&parent_domains);
+
+        let values = if class == "cpt" {
+            let text = child(node, "probabilities")
+                .and_then(|probabilities| probabilities.text())
+                .ok_or_else(|| {
+                    syntax_error(node, format!("node `{}` has no <probabilities>", name))
+                })?;
+            text.split_whitespace()
+                .map(|value| {
+                    value
+                        .parse::<Probability>()
+                        .map_err(|_| syntax_error(node, format!("`{}` is not a number", value)))
+                })
+                .collect::<Result<Vec<Probability>, Error>>()?
+        } else {
+            let text = child(node, "resultingstates")
+                .and_then(|resulting| resulting.text())
+                .ok_or_else(|| {
+                    syntax_error(node, format!("node `{}` has no <resultingstates>", name))
+                })?;
+            let mut values = Vec::new();
+            for result in text.split_whitespace() {
+                if !node_states.iter().any(|state| state == result) {
+                    return Err(invalid(format!(
+                        "`{}` is not a state of `{}`",
+                        result, name
+                    )));
+                }
+                values.extend(
+                    node_states
+                        .iter()
+                        .map(|state| if state == result { 1.0 } else { 0.0 }),
+                );
+            }
+            values
+        };
+        // The child state varies fastest, then the last parent.
+        if values.len() != keys.len() * node_states.len() {
+            return Err(invalid(format!(
+                "table of `{}` has {} entries, expected {}",
+                name,
+                values.len(),
+                keys.len() * node_states.len()
+            )));
+        }
+        let rows = keys
+            .into_iter()
+            .zip(values.chunks(node_states.len()))
+            .map(|(key, probs)| (key, probs.to_vec()))
+            .collect();
+        states.insert(name.clone(), node_states.clone());
+        tables.push(Table {
+            name,
+            states: node_states,
+            parents,
+            rows,
+            properties: Vec::new(),
+        });
+    }
+
+    // GeNIe keeps display names and positions in its extension block.
+    let genie = child(root, "extensions").and_then(|extensions| child(extensions, "genie"));
+    for node in genie
+        .iter()
+        .flat_map(|genie| genie.descendants())
+        .filter(|node| node.is_element() && node.has_tag_name("node"))
+    {
+        let name = id(node)?;
+        if let Some(table) = tables.iter_mut().find(|table| table.name == name) {
+            for (tag, key) in [("name", "label"), ("position", "position")] {
+                if let Some(text) = child(node, tag).and_then(|child| child.text()) {
+                    table
+                        .properties
+                        .push((key.to_string(), text.trim().to_string()));
+                }
+            }
+        }
+    }
+    Ok(tables)
+}
+
+impl BayesianNetwork<String> {
+    /// Parse a network in the GeNIe/SMILE XDSL format.
+    /// Only `cpt` and `deterministic` nodes are supported. GeNIe display names and positions
+    /// become `label` and `position` node metadata.
+    pub fn from_xdsl(source: &str) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_xdsl_with(source, Normalization::default())
+    }
+
+    /// Parse a network like `from_xdsl`, handling unnormalized rows with `normalization`.
+    pub fn from_xdsl_with(
+        source: &str,
+        normalization: Normalization,
+    ) -> Result<BayesianNetwork<String>, Error> {
+        build(FORMAT, parse(source)?, normalization)
+    }
+
+    /// Read a network from a GeNIe .xdsl file.
+    pub fn read_xdsl<P: AsRef<Path>>(path: P) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_xdsl(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, brute_force_probability, TOLERANCE};
+
+    const SPRINKLER: &str = r#"<?xml version="1.0"?>
+<smile version="1.0" id="sprinkler">
+  <nodes>
+    <cpt id="Rain">
+      <state id="yes" />
+      <state id="no" />
+      <probabilities>0.2 0.8</probabilities>
+    </cpt>
+    <deterministic id="Wet">
+      <state id="yes" />
+      <state id="no" />
+      <parents>Rain</parents>
+      <resultingstates>yes no</resultingstates>
+    </deterministic>
+    <cpt id="Slip">
+      <state id="yes" />
+      <state id="no" />
+      <parents>Wet</parents>
+      <probabilities>0.3 0.7 0.01 0.99</probabilities>
+    </cpt>
+  </nodes>
+  <extensions>
+    <genie>
+      <node id="Rain"><name>It rains</name><position>10 20 90 60</position></node>
+    </genie>
+  </extensions>
+</smile>"#;
+
+    #[test]
+    fn cpt_and_deterministic_nodes_are_read() {
+        let network = BayesianNetwork::from_xdsl(SPRINKLER).unwrap();
+        let yes = "yes".to_string();
+        assert_close(
+            network.infer(&HashMap::new())[network.get_node_index("Slip")][&yes],
+            0.2 * 0.3 + 0.8 * 0.01,
+            TOLERANCE,
+        );
+        let evidence = HashMap::from([("Slip", yes.clone())]);
+        assert_close(
+            network.infer(&evidence)[network.get_node_index("Rain")][&yes],
+            brute_force_probability(&network, "Rain", &yes, &evidence),
+            TOLERANCE,
+        );
+        assert_eq!(network.get_node_metadata("Rain", "label"), Some("It rains"));
+        assert_eq!(
+            network.get_node_metadata("Rain", "position"),
+            Some("10 20 90 60")
+        );
+    }
+
+    #[test]
+    fn unsupported_nodes_and_forward_parents_are_invalid() {
+        let decision = SPRINKLER.replace("<cpt id=\"Slip\">", "<decision id=\"Slip\">");
+        let decision = decision.replacen("</cpt>\n  </nodes>", "</decision>\n  </nodes>", 1);
+        assert!(matches!(
+            BayesianNetwork::from_xdsl(&decision),
+            Err(Error::Invalid { .. })
+        ));
+        let forward = SPRINKLER.replace("<parents>Rain</parents>", "<parents>Slip</parents>");
+        assert!(matches!(
+            BayesianNetwork::from_xdsl(&forward),
+            Err(Error::Invalid { .. })
+        ));
+        let unterminated = SPRINKLER.replace("</nodes>", "");
+        assert!(matches!(
+            BayesianNetwork::from_xdsl(&unterminated),
+            Err(Error::Syntax { .. })
+        ));
+    }
+}
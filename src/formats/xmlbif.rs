@@ -0,0 +1,240 @@
+use super::{build, escape_xml, tables, Table};
+use crate::bayesian_network::{BayesianNetwork, Probability};
+use crate::error::Error;
+use crate::learning::configurations;
+use crate::normalization::Normalization;
+use roxmltree::{Document, Node};
+use std::{collections::HashMap, fmt::Debug, fmt::Display, hash::Hash, path::Path};
+
+const FORMAT: &str = "XMLBIF";
+
+fn syntax_error(node: Node, message: String) -> Error {
+    Error::Syntax {
+        format: FORMAT,
+        line: node.document().text_pos_at(node.range().start).row as usize,
+        message,
+    }
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| {
+        child.is_element() && child.tag_name().name().eq_ignore_ascii_case(tag)
+    })
+}
+
+fn text(node: Node, tag: &'static str) -> Result<String, Error> {
+    children(node, tag)
+        .next()
+        .map(|child| child.text().unwrap_or("").trim().to_string())
+        .ok_or_else(|| syntax_error(node, format!("missing <{}>", tag)))
+}
+
+fn parse(source: &str) -> Result<Vec<Table>, Error> {
+    let document = Document::parse(source).map_err(|error| Error::Syntax {
+        format: FORMAT,
+        line: error.pos().row as usize,
+        message: error.to_string(),
+    })?;
+    let root = document.root_element();
+    let network = if root.tag_name().name().eq_ignore_ascii_case("NETWORK") {
+        root
+    } else {
+        children(root, "NETWORK")
+            .next()
+            .ok_or_else(|| syntax_error(root, "missing <NETWORK>".to_string()))?
+    };
+
+    let mut tables = Vec::new();
+    for variable in children(network, "VARIABLE") {
+        let name = text(variable, "NAME")?;
+        let states = children(variable, "OUTCOME")
+            .map(|outcome| outcome.text().unwrap_or("").trim().to_string())
+            .collect::<Vec<String>>();
+        let properties = children(variable, "PROPERTY")
+            .filter_map(|property| {
+                let text = property.text().unwrap_or("");
+                text.split_once('=')
+                    .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            })
+            .collect();
+        tables.push(Table {
+            name,
+            states,
+            parents: Vec::new(),
+            rows: Vec::new(),
+            properties,
+        });
+    }
+
+    let states = tables
+        .iter()
+        .map(|table| (table.name.clone(), table.states.clone()))
+        .collect::<HashMap<String, Vec<String>>>();
+    let invalid = |message: String| Error::Invalid {
+        format: FORMAT,
+        message,
+    };
+    for definition in children(network, "DEFINITION") {
+        let name = text(definition, "FOR")?;
+        let parents = children(definition, "GIVEN")
+            .map(|given| given.text().unwrap_or("").trim().to_string())
+            .collect::<Vec<String>>();
+        let values = text(definition, "TABLE")?
+            .split_whitespace()
+            .map(|value| {
+                value
+                    .parse::<Probability>()
+                    .map_err(|_| syntax_error(definition, format!("`{}` is not a number", value)))
+            })
+            .collect::<Result<Vec<Probability>, Error>>()?;
+        let mut parent_domains = Vec::new();
+        for parent in &parents {
+            parent_domains.push(states.get(parent).cloned().ok_or_else(|| {
+                invalid(format!("parent `{}` of `{}` is not defined", parent, name))
+            })?);
+        }
+        let table = tables
+            .iter_mut()
+            .find(|table| table.name == name)
+            .ok_or_else(|| invalid(format!("definition for undefined variable `{}`", name)))?;
+        if !table.rows.is_empty() {
+            return Err(invalid(format!("variable `{}` is defined twice", name)));
+        }
+        // The child state varies fastest, then the last parent.
+        let keys = configurations(&parent_domains);
+        if values.len() != keys.len() * table.states.len() {
+            return Err(invalid(format!(
+                "table of `{}` has {} entries, expected {}",
+                name,
+                values.len(),
+                keys.len() * table.states.len()
+            )));
+        }
+        table.parents = parents;
+        table.rows = keys
+            .into_iter()
+            .zip(values.chunks(table.states.len()))
+            .map(|(key, probs)| (key, probs.to_vec()))
+            .collect();
+    }
+    Ok(tables)
+}
+
+impl BayesianNetwork<String> {
+    /// Parse a network in the XML Bayesian Interchange Format (XMLBIF).
+    /// Variable properties of the form `key = value` (such as positions) become node metadata.
+    pub fn from_xmlbif(source: &str) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_xmlbif_with(source, Normalization::default())
+    }
+
+    /// Parse a network like `from_xmlbif`, handling unnormalized rows with `normalization`.
+    pub fn from_xmlbif_with(
+        source: &str,
+        normalization: Normalization,
+    ) -> Result<BayesianNetwork<String>, Error> {
+        build(FORMAT, parse(source)?, normalization)
+    }
+
+    /// Read a network from an XMLBIF file.
+    pub fn read_xmlbif<P: AsRef<Path>>(path: P) -> Result<BayesianNetwork<String>, Error> {
+        BayesianNetwork::from_xmlbif(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Display> BayesianNetwork<T> {
+    /// Write the network in the XML Bayesian Interchange Format (XMLBIF 0.3).
+    pub fn to_xmlbif(&self) -> String {
+        let tables = tables(self);
+        let mut s = String::new();
+        s.push_str("<?xml version=\"1.0\"?>\n");
+        s.push_str("<BIF VERSION=\"0.3\">\n<NETWORK>\n<NAME>unknown</NAME>\n");
+        for table in &tables {
+            s.push_str("<VARIABLE TYPE=\"nature\">\n");
+            s.push_str(&format!("  <NAME>{}</NAME>\n", escape_xml(&table.name)));
+            for state in &table.states {
+                s.push_str(&format!("  <OUTCOME>{}</OUTCOME>\n", escape_xml(state)));
+            }
+            for (key, value) in &table.properties {
+                s.push_str(&format!(
+                    "  <PROPERTY>{} = {}</PROPERTY>\n",
+                    escape_xml(key),
+                    escape_xml(value)
+                ));
+            }
+            s.push_str("</VARIABLE>\n");
+        }
+        for table in &tables {
+            s.push_str("<DEFINITION>\n");
+            s.push_str(&format!("  <FOR>{}</FOR>\n", escape_xml(&table.name)));
+            for parent in &table.parents {
+                s.push_str(&format!("  <GIVEN>{}</GIVEN>\n", escape_xml(parent)));
+            }
+            let values = table
+                .rows
+                .iter()
+                .flat_map(|(_, probs)| probs.iter().map(|prob| prob.to_string()))
+                .collect::<Vec<String>>();
+            s.push_str(&format!("  <TABLE>{}</TABLE>\n", values.join(" ")));
+            s.push_str("</DEFINITION>\n");
+        }
+        s.push_str("</NETWORK>\n</BIF>\n");
+        s
+    }
+
+    /// Write the network to an XMLBIF file.
+    pub fn write_xmlbif<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_xmlbif())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, assert_same_joint, bonus, TOLERANCE};
+
+    #[test]
+    fn to_xmlbif_reads_back_with_metadata() {
+        let mut network = bonus();
+        network.set_node_metadata("mood", "position", "(10, 20)");
+        let read = BayesianNetwork::from_xmlbif(&network.to_xmlbif()).unwrap();
+        assert_same_joint(&network, &read, TOLERANCE);
+        assert_eq!(read.get_node_metadata("mood", "position"), Some("(10, 20)"));
+    }
+
+    #[test]
+    fn tables_list_the_child_state_fastest() {
+        let source = "<BIF><NETWORK>
+            <VARIABLE><NAME>a</NAME><OUTCOME>yes</OUTCOME><OUTCOME>no</OUTCOME></VARIABLE>
+            <VARIABLE><NAME>b</NAME><OUTCOME>yes</OUTCOME><OUTCOME>no</OUTCOME></VARIABLE>
+            <DEFINITION><FOR>a</FOR><TABLE>0.3 0.7</TABLE></DEFINITION>
+            <DEFINITION><FOR>b</FOR><GIVEN>a</GIVEN><TABLE>0.9 0.1 0.2 0.8</TABLE></DEFINITION>
+        </NETWORK></BIF>";
+        let network = BayesianNetwork::from_xmlbif(source).unwrap();
+        let b = &network.nodes[network.get_node_index("b")];
+        let yes = "yes".to_string();
+        assert_close(
+            network.cpt_probability(b, &["no".to_string()], &yes),
+            0.2,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        assert!(matches!(
+            BayesianNetwork::from_xmlbif("<BIF><NETWORK>\n</BIF>"),
+            Err(Error::Syntax { line: 2, .. })
+        ));
+        let short = "<NETWORK>
+            <VARIABLE><NAME>a</NAME><OUTCOME>yes</OUTCOME><OUTCOME>no</OUTCOME></VARIABLE>
+            <DEFINITION><FOR>a</FOR><TABLE>1</TABLE></DEFINITION>
+        </NETWORK>";
+        assert!(matches!(
+            BayesianNetwork::from_xmlbif(short),
+            Err(Error::Invalid { format: FORMAT, .. })
+        ));
+    }
+}
@@ -0,0 +1,282 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeType, Probability},
+    plate::TemplateNode,
+};
+
+/// A reusable sub-network with a declared interface: input nodes, which stand for nodes the
+/// fragment depends on and are bound when it is instantiated, and output nodes, which other
+/// parts of a model may depend on. Instance `a` of fragment node `X` is named `a.X`.
+#[derive(Clone)]
+pub struct Fragment<T> {
+    inputs: Vec<Name>,
+    outputs: Vec<Name>,
+    nodes: Vec<TemplateNode<T>>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> Default for Fragment<T> {
+    fn default() -> Self {
+        Fragment::new()
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> Fragment<T> {
+    /// Create an empty fragment.
+    pub fn new() -> Fragment<T> {
+        Fragment {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Declare an input: a name fragment nodes may use as a parent, bound to a node outside
+    /// the fragment when it is instantiated.
+    pub fn add_input(&mut self, name: &str) {
+        self.check_new(name);
+        self.inputs.push(name.to_string());
+    }
+
+    /// Add a node to the fragment.
+    pub fn add_node(&mut self, name: &str, node_type: NodeType<T>) {
+        self.check_new(name);
+        self.nodes.push(TemplateNode::new(name, node_type));
+    }
+
+    /// Add a dependency to a fragment node. Each parent is a node or an input of the fragment.
+    pub fn add_dependency(
+        &mut self,
+        parent_names: Vec<&str>,
+        child_name: &str,
+        prob: HashMap<Vec<T>, HashMap<T, Probability>>,
+    ) {
+        for parent in &parent_names {
+            if !self.inputs.iter().any(|input| input == parent)
+                && !self.nodes.iter().any(|node| node.name == *parent)
+            {
+                panic!("Fragment node or input `{}` not found", parent);
+            }
+        }
+        let node = self.node_mut(child_name);
+        node.parents = parent_names.iter().map(|name| name.to_string()).collect();
+        node.probability = prob;
+    }
+
+    /// Declare the values a fragment node can take, in order.
+    pub fn set_node_states(&mut self, name: &str, states: Vec<T>) {
+        self.node_mut(name).states = Some(states);
+    }
+
+    /// Declare a fragment node as an output.
+    pub fn add_output(&mut self, name: &str) {
+        self.node_mut(name);
+        if !self.outputs.iter().any(|output| output == name) {
+            self.outputs.push(name.to_string());
+        }
+    }
+
+    /// Get the inputs, in order.
+    pub fn inputs(&self) -> Vec<&str> {
+        self.inputs.iter().map(|name| name.as_str()).collect()
+    }
+
+    /// Get the outputs, in order.
+    pub fn outputs(&self) -> Vec<&str> {
+        self.outputs.iter().map(|name| name.as_str()).collect()
+    }
+
+    /// Nest an instance of another fragment, binding each of its inputs to a node or input
+    /// of this fragment. Its nodes become nodes of this fragment named `instance.X`.
+    pub fn add_fragment(
+        &mut self,
+        fragment: &Fragment<T>,
+        instance: &str,
+        bindings: &[(&str, &str)],
+    ) {
+        let bindings = fragment.bindings(instance, bindings);
+        for target in bindings.values() {
+            if !self.inputs.iter().any(|input| input == target)
+                && !self.nodes.iter().any(|node| &node.name == target)
+            {
+                panic!("Fragment node or input `{}` not found", target);
+            }
+        }
+        let name = |name: &str| fragment.instance_name(instance, &bindings, name);
+        for node in &fragment.nodes {
+            self.check_new(&name(&node.name));
+            self.nodes.push(TemplateNode {
+                name: name(&node.name),
+                node_type: node.node_type.clone(),
+                parents: node.parents.iter().map(|parent| name(parent)).collect(),
+                probability: node.probability.clone(),
+                states: node.states.clone(),
+            });
+        }
+    }
+
+    fn check_new(&self, name: &str) {
+        if self.inputs.iter().any(|input| input == name)
+            || self.nodes.iter().any(|node| node.name == name)
+        {
+            panic!("Fragment node or input `{}` already exists", name);
+        }
+    }
+
+    fn node_mut(&mut self, name: &str) -> &mut TemplateNode<T> {
+        match self.nodes.iter_mut().find(|node| node.name == name) {
+            Some(node) => node,
+            None => panic!("Fragment node `{}` not found", name),
+        }
+    }
+
+    /// Check that `bindings` binds every input exactly, and key them by input.
+    fn bindings(&self, instance: &str, bindings: &[(&str, &str)]) -> HashMap<Name, Name> {
+        let bindings = bindings
+            .iter()
+            .map(|(input, target)| {
+                if !self.inputs.iter().any(|name| name == input) {
+                    panic!("Fragment input `{}` not found", input);
+                }
+                (input.to_string(), target.to_string())
+            })
+            .collect::<HashMap<Name, Name>>();
+        for input in &self.inputs {
+            if !bindings.contains_key(input) {
+                panic!(
+                    "Input `{}` of fragment instance `{}` is not bound",
+                    input, instance
+                );
+            }
+        }
+        bindings
+    }
+
+    /// The name of a node or input of the fragment in instance `instance`.
+    fn instance_name(&self, instance: &str, bindings: &HashMap<Name, Name>, name: &str) -> Name {
+        match bindings.get(name) {
+            Some(target) => target.clone(),
+            None => format!("{}.{}", instance, name),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Add an instance of `fragment`, binding each of its inputs to a node of the network.
+    /// Its nodes are named `instance.X`, so its outputs can be bound to the inputs of other
+    /// fragments or used as parents of other nodes.
+    pub fn add_fragment(
+        &mut self,
+        fragment: &Fragment<T>,
+        instance: &str,
+        bindings: &[(&str, &str)],
+    ) {
+        let bindings = fragment.bindings(instance, bindings);
+        self.add_template_nodes(&fragment.nodes, |name| {
+            fragment.instance_name(instance, &bindings, name)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, joint, row, TOLERANCE};
+
+    fn table(yes: Probability, no: Probability) -> HashMap<Vec<bool>, HashMap<bool, Probability>> {
+        HashMap::from([(vec![true], row(yes)), (vec![false], row(no))])
+    }
+
+    /// A relay passing on its input with some noise.
+    fn relay() -> Fragment<bool> {
+        let mut relay = Fragment::new();
+        relay.add_input("in");
+        relay.add_node("out", NodeType::Inner);
+        relay.add_dependency(vec!["in"], "out", table(0.95, 0.1));
+        relay.add_output("out");
+        relay
+    }
+
+    /// A sensor reading its signal.
+    fn sensor() -> Fragment<bool> {
+        let mut sensor = Fragment::new();
+        sensor.add_input("signal");
+        sensor.add_node("reading", NodeType::Leaf);
+        sensor.add_dependency(vec!["signal"], "reading", table(0.9, 0.2));
+        sensor.add_output("reading");
+        sensor
+    }
+
+    /// A fire behind a relay and a sensor, built by hand.
+    fn expected() -> BayesianNetwork<bool> {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("fire", NodeType::Root(row(0.2)));
+        network.add_node("out", NodeType::Inner);
+        network.add_node("reading", NodeType::Leaf);
+        network.add_dependency(vec!["fire"], "out", table(0.95, 0.1));
+        network.add_dependency(vec!["out"], "reading", table(0.9, 0.2));
+        network
+    }
+
+    fn assert_joint_matches(expected: &BayesianNetwork<bool>, actual: &BayesianNetwork<bool>) {
+        let actual = joint(actual);
+        for (assignment, prob) in joint(expected) {
+            let (_, actual) = actual
+                .iter()
+                .find(|(other, _)| *other == assignment)
+                .unwrap();
+            assert_close(*actual, prob, TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn bound_fragments_match_the_network_built_by_hand() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("fire", NodeType::Root(row(0.2)));
+        network.add_fragment(&relay(), "relay", &[("in", "fire")]);
+        network.add_fragment(&sensor(), "sensor", &[("signal", "relay.out")]);
+        assert_eq!(
+            network.node_names(),
+            vec!["fire", "relay.out", "sensor.reading"]
+        );
+        assert_eq!(
+            network.get_node_parents("sensor.reading"),
+            vec!["relay.out"]
+        );
+        assert_joint_matches(&expected(), &network);
+    }
+
+    #[test]
+    fn nested_fragments_are_named_by_their_instances() {
+        let mut detector = Fragment::new();
+        detector.add_input("cause");
+        detector.add_fragment(&relay(), "relay", &[("in", "cause")]);
+        detector.add_fragment(&sensor(), "sensor", &[("signal", "relay.out")]);
+        detector.add_output("sensor.reading");
+        assert_eq!(detector.inputs(), vec!["cause"]);
+        assert_eq!(detector.outputs(), vec!["sensor.reading"]);
+
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("fire", NodeType::Root(row(0.2)));
+        network.add_fragment(&detector, "kitchen", &[("cause", "fire")]);
+        assert_eq!(
+            network.node_names(),
+            vec!["fire", "kitchen.relay.out", "kitchen.sensor.reading"]
+        );
+        assert_joint_matches(&expected(), &network);
+    }
+
+    #[test]
+    #[should_panic(expected = "Input `signal` of fragment instance `sensor` is not bound")]
+    fn every_input_must_be_bound() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_fragment(&sensor(), "sensor", &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fragment node or input `smoke` not found")]
+    fn parents_must_be_nodes_or_inputs() {
+        let mut fragment = sensor();
+        fragment.add_dependency(vec!["smoke"], "reading", table(0.9, 0.2));
+    }
+}
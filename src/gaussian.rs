@@ -0,0 +1,637 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    learning::configurations,
+    mixture::MixtureNode,
+    posterior::Posterior,
+};
+
+/// A normal distribution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gaussian {
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl Gaussian {
+    /// Create a normal distribution with the given mean and variance.
+    pub fn new(mean: f64, variance: f64) -> Gaussian {
+        if variance.is_nan() || variance < 0.0 {
+            panic!("Gaussian variance {} is negative", variance);
+        }
+        Gaussian { mean, variance }
+    }
+
+    /// Get the density at `x`.
+    pub fn density(&self, x: f64) -> f64 {
+        (-(x - self.mean).powi(2) / (2.0 * self.variance)).exp()
+            / (2.0 * std::f64::consts::PI * self.variance).sqrt()
+    }
+}
+
+/// A linear-Gaussian CPD: the child is normal with mean
+/// `intercept + sum_i coefficients[i] * parent_i` over its continuous parents, and variance
+/// `variance`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearGaussian {
+    pub intercept: f64,
+    pub coefficients: Vec<f64>,
+    pub variance: f64,
+}
+
+impl LinearGaussian {
+    /// Create a linear-Gaussian CPD.
+    pub fn new(intercept: f64, coefficients: Vec<f64>, variance: f64) -> LinearGaussian {
+        if variance.is_nan() || variance < 0.0 {
+            panic!("Gaussian variance {} is negative", variance);
+        }
+        LinearGaussian {
+            intercept,
+            coefficients,
+            variance,
+        }
+    }
+}
+
+/// A continuous node of a `ClgNetwork`, with one linear-Gaussian CPD for each configuration of
+/// its discrete parents.
+#[derive(Clone, Debug)]
+pub(crate) struct ContinuousNode<T> {
+    pub(crate) name: Name,
+    pub(crate) discrete_parents: Vec<NodeId>,
+    pub(crate) continuous_parents: Vec<usize>,
+    pub(crate) cpds: HashMap<Vec<T>, LinearGaussian>,
+}
+
+/// A conditional linear Gaussian network: a discrete Bayesian network together with Gaussian
+/// nodes, each depending linearly on its continuous parents with parameters switched by its
+/// discrete parents, and Gaussian mixture leaves. Discrete nodes cannot have continuous
+/// parents.
+pub struct ClgNetwork<T: Clone + PartialEq + Eq + Hash + Debug> {
+    pub(crate) discrete: BayesianNetwork<T>,
+    pub(crate) continuous: Vec<ContinuousNode<T>>,
+    pub(crate) mixtures: Vec<MixtureNode<T>>,
+}
+
+/// The posterior of a hybrid network: a distribution over the states of each discrete node,
+/// and the mean and variance of each continuous node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HybridPosterior<T> {
+    pub(crate) discrete: Posterior<T>,
+    pub(crate) continuous: Vec<(Name, Gaussian)>,
+}
+
+impl<T: Clone + PartialEq> HybridPosterior<T> {
+    /// Get the posterior of the discrete nodes.
+    pub fn discrete(&self) -> &Posterior<T> {
+        &self.discrete
+    }
+
+    /// Get the probability of a discrete node taking `value`.
+    pub fn probability(&self, name: &str, value: &T) -> Option<Probability> {
+        self.discrete.probability(name, value)
+    }
+
+    /// Get the mean and variance of a continuous node. Its posterior is in general a mixture
+    /// of Gaussians, of which this is the moment-matched Gaussian.
+    pub fn gaussian(&self, name: &str) -> Option<Gaussian> {
+        self.continuous
+            .iter()
+            .find(|(node, _)| node == name)
+            .map(|(_, gaussian)| *gaussian)
+    }
+
+    /// Get the posterior mean of a continuous node.
+    pub fn mean(&self, name: &str) -> Option<f64> {
+        self.gaussian(name).map(|gaussian| gaussian.mean)
+    }
+
+    /// Get the posterior variance of a continuous node.
+    pub fn variance(&self, name: &str) -> Option<f64> {
+        self.gaussian(name).map(|gaussian| gaussian.variance)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> ClgNetwork<T> {
+    /// Create a network from its discrete part, with no continuous nodes yet.
+    pub fn new(discrete: BayesianNetwork<T>) -> ClgNetwork<T> {
+        ClgNetwork {
+            discrete,
+            continuous: Vec::new(),
+            mixtures: Vec::new(),
+        }
+    }
+
+    /// Get the discrete part of the network.
+    pub fn discrete(&self) -> &BayesianNetwork<T> {
+        &self.discrete
+    }
+
+    /// Get the names of the continuous nodes: the linear-Gaussian nodes, then the mixture
+    /// nodes, each in the order they were added.
+    pub fn continuous_node_names(&self) -> Vec<&str> {
+        self.continuous
+            .iter()
+            .map(|node| node.name.as_str())
+            .chain(self.mixtures.iter().map(|node| node.name.as_str()))
+            .collect()
+    }
+
+    /// Add a continuous root node with the given distribution.
+    pub fn add_gaussian(&mut self, name: &str, gaussian: Gaussian) {
+        let mut cpds = HashMap::new();
+        cpds.insert(
+            Vec::new(),
+            LinearGaussian::new(gaussian.mean, Vec::new(), gaussian.variance),
+        );
+        self.add_linear_gaussian(name, Vec::new(), Vec::new(), cpds);
+    }
+
+    /// Add a continuous node with a linear-Gaussian CPD for each configuration of its
+    /// discrete parents, keyed by their values in order. Its continuous parents must already
+    /// be in the network, and each CPD has one coefficient per continuous parent.
+    pub fn add_linear_gaussian(
+        &mut self,
+        name: &str,
+        discrete_parents: Vec<&str>,
+        continuous_parents: Vec<&str>,
+        cpds: HashMap<Vec<T>, LinearGaussian>,
+    ) {
+        if self.discrete.node_map.contains_key(name)
+            || self.continuous_index(name).is_some()
+            || self.mixture_index(name).is_some()
+        {
+            panic!("Node `{}` already exists", name);
+        }
+        let discrete_parents = discrete_parents
+            .iter()
+            .map(|parent| match self.discrete.node_map.get(*parent) {
+                Some(id) => *id,
+                None => panic!("Discrete parent node `{}` not found", parent),
+            })
+            .collect::<Vec<NodeId>>();
+        let continuous_parents = continuous_parents
+            .iter()
+            .map(|parent| match self.continuous_index(parent) {
+                Some(index) => index,
+                None => panic!("Continuous parent node `{}` not found", parent),
+            })
+            .collect::<Vec<usize>>();
+        for (key, cpd) in &cpds {
+            if key.len() != discrete_parents.len() {
+                panic!(
+                    "Linear-Gaussian CPD key {:?} of `{}` does not match its {} discrete parents",
+                    key,
+                    name,
+                    discrete_parents.len()
+                );
+            }
+            if cpd.coefficients.len() != continuous_parents.len() {
+                panic!(
+                    "Linear-Gaussian CPD of `{}` has {} coefficients for {} continuous parents",
+                    name,
+                    cpd.coefficients.len(),
+                    continuous_parents.len()
+                );
+            }
+        }
+        self.continuous.push(ContinuousNode {
+            name: name.to_string(),
+            discrete_parents,
+            continuous_parents,
+            cpds,
+        });
+    }
+
+    /// Infer the posterior of every node exactly, given discrete `evidence` and
+    /// `continuous_evidence` on continuous nodes. The cost grows with the number of joint
+    /// configurations of the discrete parents of continuous nodes.
+    pub fn infer(
+        &self,
+        evidence: &HashMap<&str, T>,
+        continuous_evidence: &HashMap<&str, f64>,
+    ) -> HybridPosterior<T> {
+        let discrete_evidence = self.discrete.evidence_ids(evidence);
+        let observed = self.continuous_evidence(continuous_evidence);
+        let observed_mixtures = self.mixture_evidence(continuous_evidence);
+        let domains = self
+            .discrete
+            .nodes
+            .iter()
+            .map(|node| match discrete_evidence.get(&node.id) {
+                Some(value) => vec![value.clone()],
+                None => self.discrete.get_node_domain(node.id),
+            })
+            .collect::<Vec<Vec<T>>>();
+
+        let switches = self.switches();
+        let switch_domains = switches
+            .iter()
+            .map(|id| domains[*id].clone())
+            .collect::<Vec<Vec<T>>>();
+
+        let mut components = Vec::new();
+        for configuration in configurations(&switch_domains) {
+            let mut restricted = domains.clone();
+            for (id, value) in switches.iter().zip(&configuration) {
+                restricted[*id] = vec![value.clone()];
+            }
+            let (prior, _) = self.discrete.eliminate(&restricted, false);
+            if prior <= 0.0 {
+                continue;
+            }
+            let assignment = switches
+                .iter()
+                .copied()
+                .zip(configuration)
+                .collect::<HashMap<NodeId, T>>();
+            let (mut moments, mut log_density) = self.conditional_moments(&assignment, &observed);
+            for index in 0..self.mixtures.len() {
+                let mixture = self.mixture(index, &assignment);
+                moments.push(match observed_mixtures.get(&index) {
+                    Some(value) => {
+                        log_density += mixture.density(*value).ln();
+                        Gaussian {
+                            mean: *value,
+                            variance: 0.0,
+                        }
+                    }
+                    None => mixture.moments(),
+                });
+            }
+            components.push((restricted, prior.ln() + log_density, prior, moments));
+        }
+        let max = components
+            .iter()
+            .map(|(_, log_weight, _, _)| *log_weight)
+            .fold(f64::NEG_INFINITY, f64::max);
+        if max == f64::NEG_INFINITY {
+            panic!("Evidence has zero probability");
+        }
+        let weights = components
+            .iter()
+            .map(|(_, log_weight, _, _)| (log_weight - max).exp())
+            .collect::<Vec<f64>>();
+        let total = weights.iter().sum::<f64>();
+
+        let marginals = self
+            .discrete
+            .nodes
+            .iter()
+            .map(|node| {
+                let states = self
+                    .discrete
+                    .get_node_domain(node.id)
+                    .into_iter()
+                    .map(|value| {
+                        let prob = if !domains[node.id].contains(&value) {
+                            0.0
+                        } else {
+                            components
+                                .iter()
+                                .zip(&weights)
+                                .map(|((restricted, _, prior, _), weight)| {
+                                    if !restricted[node.id].contains(&value) {
+                                        return 0.0;
+                                    }
+                                    let mut restricted = restricted.clone();
+                                    restricted[node.id] = vec![value.clone()];
+                                    let (joint, _) = self.discrete.eliminate(&restricted, false);
+                                    weight * joint / prior
+                                })
+                                .sum::<f64>()
+                                / total
+                        };
+                        (value, prob)
+                    })
+                    .collect();
+                (self.discrete.get_node_name(node.id).to_string(), states)
+            })
+            .collect();
+
+        let continuous = self
+            .continuous_node_names()
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let mean = components
+                    .iter()
+                    .zip(&weights)
+                    .map(|((_, _, _, moments), weight)| weight * moments[index].mean)
+                    .sum::<f64>()
+                    / total;
+                let second = components
+                    .iter()
+                    .zip(&weights)
+                    .map(|((_, _, _, moments), weight)| {
+                        weight * (moments[index].variance + moments[index].mean.powi(2))
+                    })
+                    .sum::<f64>()
+                    / total;
+                let variance = (second - mean * mean).max(0.0);
+                (name.to_string(), Gaussian { mean, variance })
+            })
+            .collect();
+
+        HybridPosterior {
+            discrete: Posterior { marginals },
+            continuous,
+        }
+    }
+
+    /// The discrete parents of continuous nodes, whose configuration decides the joint
+    /// Gaussian of the linear-Gaussian nodes and the mixture of each mixture node.
+    pub(crate) fn switches(&self) -> Vec<NodeId> {
+        let mut switches = Vec::new();
+        let parents = self
+            .continuous
+            .iter()
+            .map(|node| &node.discrete_parents)
+            .chain(self.mixtures.iter().map(|node| &node.discrete_parents));
+        for discrete_parents in parents {
+            for parent in discrete_parents {
+                if !switches.contains(parent) {
+                    switches.push(*parent);
+                }
+            }
+        }
+        switches
+    }
+
+    pub(crate) fn continuous_index(&self, name: &str) -> Option<usize> {
+        self.continuous.iter().position(|node| node.name == name)
+    }
+
+    /// Check continuous evidence and key it by linear-Gaussian node index, leaving out the
+    /// evidence on mixture nodes.
+    pub(crate) fn continuous_evidence(
+        &self,
+        continuous_evidence: &HashMap<&str, f64>,
+    ) -> HashMap<usize, f64> {
+        continuous_evidence
+            .iter()
+            .filter_map(|(name, value)| match self.continuous_index(name) {
+                Some(index) => Some((index, *value)),
+                None if self.mixture_index(name).is_some() => None,
+                None => panic!("Continuous node `{}` not found", name),
+            })
+            .collect()
+    }
+
+    /// The CPD of a continuous node given values of at least its discrete parents.
+    pub(crate) fn cpd(&self, index: usize, assignment: &HashMap<NodeId, T>) -> &LinearGaussian {
+        let node = &self.continuous[index];
+        let key = node
+            .discrete_parents
+            .iter()
+            .map(|parent| assignment[parent].clone())
+            .collect::<Vec<T>>();
+        match node.cpds.get(&key) {
+            Some(cpd) => cpd,
+            None => panic!(
+                "Continuous node `{}` has no CPD for discrete parents {:?}",
+                node.name, key
+            ),
+        }
+    }
+
+    /// The mean and variance of each continuous node given the discrete parents of the
+    /// continuous nodes and the observed continuous nodes, with the log density of the
+    /// observations.
+    fn conditional_moments(
+        &self,
+        assignment: &HashMap<NodeId, T>,
+        observed: &HashMap<usize, f64>,
+    ) -> (Vec<Gaussian>, f64) {
+        // The joint Gaussian of the continuous nodes, each of which comes after its parents.
+        let n = self.continuous.len();
+        let mut mean = vec![0.0; n];
+        let mut covariance = vec![vec![0.0; n]; n];
+        for (i, node) in self.continuous.iter().enumerate() {
+            let cpd = self.cpd(i, assignment);
+            mean[i] = cpd.intercept;
+            for (parent, coefficient) in node.continuous_parents.iter().zip(&cpd.coefficients) {
+                mean[i] += coefficient * mean[*parent];
+            }
+            let row = (0..i)
+                .map(|k| {
+                    node.continuous_parents
+                        .iter()
+                        .zip(&cpd.coefficients)
+                        .map(|(parent, coefficient)| coefficient * covariance[*parent][k])
+                        .sum::<f64>()
+                })
+                .collect::<Vec<f64>>();
+            for (k, value) in row.into_iter().enumerate() {
+                covariance[i][k] = value;
+                covariance[k][i] = value;
+            }
+            covariance[i][i] = cpd.variance
+                + node
+                    .continuous_parents
+                    .iter()
+                    .zip(&cpd.coefficients)
+                    .map(|(parent, coefficient)| coefficient * covariance[*parent][i])
+                    .sum::<f64>();
+        }
+
+        let evidence = (0..n)
+            .filter(|i| observed.contains_key(i))
+            .collect::<Vec<usize>>();
+        if evidence.is_empty() {
+            let moments = (0..n)
+                .map(|i| Gaussian {
+                    mean: mean[i],
+                    variance: covariance[i][i],
+                })
+                .collect();
+            return (moments, 0.0);
+        }
+        let evidence_covariance = evidence
+            .iter()
+            .map(|i| evidence.iter().map(|k| covariance[*i][*k]).collect())
+            .collect::<Vec<Vec<f64>>>();
+        let factor = match cholesky(&evidence_covariance) {
+            Some(factor) => factor,
+            None => panic!("Continuous evidence has a singular covariance"),
+        };
+        let residual = evidence
+            .iter()
+            .map(|i| observed[i] - mean[*i])
+            .collect::<Vec<f64>>();
+        let whitened = forward_substitute(&factor, &residual);
+        let log_density = -0.5 * whitened.iter().map(|x| x * x).sum::<f64>()
+            - factor
+                .iter()
+                .enumerate()
+                .map(|(i, row)| row[i].ln())
+                .sum::<f64>()
+            - 0.5 * evidence.len() as f64 * (2.0 * std::f64::consts::PI).ln();
+
+        let moments = (0..n)
+            .map(|i| {
+                if let Some(value) = observed.get(&i) {
+                    return Gaussian {
+                        mean: *value,
+                        variance: 0.0,
+                    };
+                }
+                let cross = evidence
+                    .iter()
+                    .map(|k| covariance[i][*k])
+                    .collect::<Vec<f64>>();
+                let projected = forward_substitute(&factor, &cross);
+                Gaussian {
+                    mean: mean[i]
+                        + projected
+                            .iter()
+                            .zip(&whitened)
+                            .map(|(a, b)| a * b)
+                            .sum::<f64>(),
+                    variance: (covariance[i][i] - projected.iter().map(|x| x * x).sum::<f64>())
+                        .max(0.0),
+                }
+            })
+            .collect();
+        (moments, log_density)
+    }
+}
+
+/// The lower triangular `L` with `L L^T = matrix`, if the matrix is positive definite.
+fn cholesky(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut factor = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum = (0..j).map(|k| factor[i][k] * factor[j][k]).sum::<f64>();
+            if i == j {
+                let diagonal = matrix[i][i] - sum;
+                if diagonal <= 0.0 {
+                    return None;
+                }
+                factor[i][i] = diagonal.sqrt();
+            } else {
+                factor[i][j] = (matrix[i][j] - sum) / factor[j][j];
+            }
+        }
+    }
+    Some(factor)
+}
+
+/// Solve `L x = b` for lower triangular `L`.
+fn forward_substitute(factor: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+    let mut x = vec![0.0; b.len()];
+    for i in 0..b.len() {
+        let sum = (0..i).map(|k| factor[i][k] * x[k]).sum::<f64>();
+        x[i] = (b[i] - sum) / factor[i][i];
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bayesian_network::NodeType;
+    use crate::testing::{assert_close, row, TOLERANCE};
+
+    #[test]
+    fn infer_conditions_a_linear_gaussian_chain() {
+        // X ~ N(0, 1) and Y = 2 X + N(0, 1).
+        let mut network = ClgNetwork::new(BayesianNetwork::new(vec![true, false]));
+        network.add_gaussian("x", Gaussian::new(0.0, 1.0));
+        network.add_linear_gaussian(
+            "y",
+            Vec::new(),
+            vec!["x"],
+            HashMap::from([(Vec::new(), LinearGaussian::new(0.0, vec![2.0], 1.0))]),
+        );
+        let prior = network.infer(&HashMap::new(), &HashMap::new());
+        assert_close(prior.mean("y").unwrap(), 0.0, TOLERANCE);
+        assert_close(prior.variance("y").unwrap(), 5.0, TOLERANCE);
+
+        let posterior = network.infer(&HashMap::new(), &HashMap::from([("y", 2.0)]));
+        assert_close(posterior.mean("x").unwrap(), 0.8, TOLERANCE);
+        assert_close(posterior.variance("x").unwrap(), 0.2, TOLERANCE);
+        assert_eq!(posterior.gaussian("y"), Some(Gaussian::new(2.0, 0.0)));
+    }
+
+    #[test]
+    fn infer_weighs_and_moment_matches_the_switch_configurations() {
+        // S switches X between N(2, 1) and N(-1, 4), and Y = X / 2 + N(0, 1).
+        let mut discrete = BayesianNetwork::new(vec![true, false]);
+        discrete.add_node("s", NodeType::Root(row(0.3)));
+        let mut network = ClgNetwork::new(discrete);
+        let components = [(true, 0.3, 2.0, 1.0), (false, 0.7, -1.0, 4.0)];
+        network.add_linear_gaussian(
+            "x",
+            vec!["s"],
+            Vec::new(),
+            components
+                .iter()
+                .map(|(s, _, mean, variance)| {
+                    (vec![*s], LinearGaussian::new(*mean, Vec::new(), *variance))
+                })
+                .collect(),
+        );
+        network.add_linear_gaussian(
+            "y",
+            Vec::new(),
+            vec!["x"],
+            HashMap::from([(Vec::new(), LinearGaussian::new(0.0, vec![0.5], 1.0))]),
+        );
+
+        let prior = network.infer(&HashMap::new(), &HashMap::new());
+        assert_close(prior.mean("x").unwrap(), 0.3 * 2.0 - 0.7, TOLERANCE);
+        assert_close(
+            prior.variance("x").unwrap(),
+            0.3 * (1.0 + 4.0) + 0.7 * (4.0 + 1.0) - 0.01,
+            TOLERANCE,
+        );
+
+        // Each configuration conditions X on Y = 1 and is weighted by the density of Y.
+        let y = 1.0;
+        let conditioned = components
+            .iter()
+            .map(|(_, prior, mean, variance)| {
+                let y_variance = 0.25 * variance + 1.0;
+                let gain = 0.5 * variance / y_variance;
+                let weight = prior * Gaussian::new(0.5 * mean, y_variance).density(y);
+                (
+                    weight,
+                    mean + gain * (y - 0.5 * mean),
+                    variance - gain * 0.5 * variance,
+                )
+            })
+            .collect::<Vec<(f64, f64, f64)>>();
+        let total = conditioned.iter().map(|(weight, _, _)| weight).sum::<f64>();
+        let mean = conditioned
+            .iter()
+            .map(|(weight, mean, _)| weight * mean)
+            .sum::<f64>()
+            / total;
+        let second = conditioned
+            .iter()
+            .map(|(weight, mean, variance)| weight * (variance + mean * mean))
+            .sum::<f64>()
+            / total;
+
+        let posterior = network.infer(&HashMap::new(), &HashMap::from([("y", y)]));
+        assert_close(
+            posterior.probability("s", &true).unwrap(),
+            conditioned[0].0 / total,
+            TOLERANCE,
+        );
+        assert_close(posterior.mean("x").unwrap(), mean, TOLERANCE);
+        assert_close(
+            posterior.variance("x").unwrap(),
+            second - mean * mean,
+            TOLERANCE,
+        );
+
+        let given_s = network.infer(&HashMap::from([("s", false)]), &HashMap::from([("y", y)]));
+        assert_close(given_s.probability("s", &false).unwrap(), 1.0, TOLERANCE);
+        assert_close(given_s.mean("x").unwrap(), conditioned[1].1, TOLERANCE);
+        assert_close(given_s.variance("x").unwrap(), conditioned[1].2, TOLERANCE);
+    }
+}
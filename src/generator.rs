@@ -0,0 +1,227 @@
+use rand::Rng;
+use std::collections::HashMap;
+
+use crate::{
+    bayesian_network::{BayesianNetwork, NodeType, Probability},
+    learning::configurations,
+};
+
+/// Settings for `BayesianNetwork::random`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RandomNetworkOptions {
+    /// The number of nodes, named `X0`, `X1`, ...
+    pub nodes: usize,
+    /// The largest number of parents of a node.
+    pub max_parents: usize,
+    /// The smallest number of states of a node.
+    pub min_states: usize,
+    /// The largest number of states of a node.
+    pub max_states: usize,
+    /// The chance that each entry of a CPT row is zero; every row keeps at least one nonzero
+    /// entry.
+    pub sparsity: Probability,
+    /// Keep the network a polytree, so that `infer` can be used on it.
+    pub polytree: bool,
+}
+
+impl Default for RandomNetworkOptions {
+    fn default() -> Self {
+        RandomNetworkOptions {
+            nodes: 10,
+            max_parents: 2,
+            min_states: 2,
+            max_states: 2,
+            sparsity: 0.0,
+            polytree: true,
+        }
+    }
+}
+
+impl BayesianNetwork<usize> {
+    /// Generate a random network for fuzzing and benchmarking. Each node takes the states
+    /// `0..k` for a random `k`, draws its parents among the nodes before it, and gets CPT
+    /// rows drawn uniformly from the probability simplex.
+    pub fn random(options: &RandomNetworkOptions) -> BayesianNetwork<usize> {
+        BayesianNetwork::random_with_rng(options, &mut rand::thread_rng())
+    }
+
+    /// Generate a random network like `random`, using `rng`.
+    pub fn random_with_rng<R: Rng + ?Sized>(
+        options: &RandomNetworkOptions,
+        rng: &mut R,
+    ) -> BayesianNetwork<usize> {
+        if options.min_states == 0 || options.min_states > options.max_states {
+            panic!("Invalid state counts for a random network");
+        }
+        let n = options.nodes;
+        let states = (0..n)
+            .map(|_| rng.gen_range(options.min_states..=options.max_states))
+            .collect::<Vec<usize>>();
+
+        // In a polytree, parents must come from different components so that no undirected
+        // cycle forms.
+        let mut component = (0..n).collect::<Vec<usize>>();
+        let mut parents = vec![Vec::new(); n];
+        for child in 1..n {
+            let count = rng.gen_range(0..=options.max_parents.min(child));
+            let mut candidates = (0..child).collect::<Vec<usize>>();
+            while parents[child].len() < count && !candidates.is_empty() {
+                let parent = candidates.swap_remove(rng.gen_range(0..candidates.len()));
+                if options.polytree {
+                    if component[parent] == component[child] {
+                        continue;
+                    }
+                    let (from, to) = (component[parent], component[child]);
+                    for c in component.iter_mut().filter(|c| **c == from) {
+                        *c = to;
+                    }
+                }
+                parents[child].push(parent);
+            }
+            parents[child].sort();
+        }
+
+        let names = (0..n).map(|id| format!("X{}", id)).collect::<Vec<String>>();
+        let mut network = BayesianNetwork::new((0..options.max_states).collect());
+        for id in 0..n {
+            let node_type = if parents[id].is_empty() {
+                let prior = random_row(states[id], options.sparsity, rng);
+                NodeType::Root((0..states[id]).zip(prior).collect())
+            } else if parents.iter().any(|parents| parents.contains(&id)) {
+                NodeType::Inner
+            } else {
+                NodeType::Leaf
+            };
+            network.add_node(&names[id], node_type);
+            network.set_node_states(&names[id], (0..states[id]).collect());
+        }
+        for id in 0..n {
+            if parents[id].is_empty() {
+                continue;
+            }
+            let domains = parents[id]
+                .iter()
+                .map(|parent| (0..states[*parent]).collect())
+                .collect::<Vec<Vec<usize>>>();
+            let cpt = configurations(&domains)
+                .into_iter()
+                .map(|configuration| {
+                    let row = random_row(states[id], options.sparsity, rng);
+                    (configuration, (0..states[id]).zip(row).collect())
+                })
+                .collect::<HashMap<Vec<usize>, HashMap<usize, Probability>>>();
+            let parent_names = parents[id]
+                .iter()
+                .map(|parent| names[*parent].as_str())
+                .collect();
+            network.add_dependency(parent_names, &names[id], cpt);
+        }
+        network
+    }
+}
+
+/// A distribution over `k` states drawn uniformly from the simplex, with each entry zeroed
+/// with chance `sparsity` as long as one stays nonzero.
+fn random_row<R: Rng + ?Sized>(k: usize, sparsity: Probability, rng: &mut R) -> Vec<Probability> {
+    let keep = rng.gen_range(0..k);
+    let mut row = (0..k)
+        .map(|i| {
+            if i != keep && rng.gen::<Probability>() < sparsity {
+                0.0
+            } else {
+                -(1.0 - rng.gen::<Probability>()).ln()
+            }
+        })
+        .collect::<Vec<Probability>>();
+    let total = row.iter().sum::<Probability>();
+    for prob in &mut row {
+        *prob /= total;
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, brute_force_probability};
+
+    #[test]
+    fn random_networks_respect_their_options() {
+        let options = RandomNetworkOptions {
+            nodes: 8,
+            max_parents: 3,
+            min_states: 2,
+            max_states: 4,
+            sparsity: 0.5,
+            polytree: false,
+        };
+        let mut rng = seeded_rng(1);
+        for _ in 0..20 {
+            let network = BayesianNetwork::random_with_rng(&options, &mut rng);
+            assert_eq!(network.nodes.len(), 8);
+            assert!(network
+                .validate()
+                .iter()
+                .all(|diagnostic| diagnostic.check == "unreachable"));
+            for node in &network.nodes {
+                assert!(node.parents.len() <= 3);
+                assert!(node.parents.iter().all(|parent| *parent < node.id));
+                let states = network.get_node_states(network.get_node_name(node.id));
+                assert!((2..=4).contains(&states.len()));
+            }
+        }
+    }
+
+    #[test]
+    fn random_polytrees_infer_like_enumeration() {
+        let options = RandomNetworkOptions {
+            nodes: 6,
+            max_states: 3,
+            ..RandomNetworkOptions::default()
+        };
+        let mut rng = seeded_rng(2);
+        for _ in 0..10 {
+            let network = BayesianNetwork::random_with_rng(&options, &mut rng);
+            // A polytree has fewer edges than nodes.
+            let edges = network
+                .nodes
+                .iter()
+                .map(|node| node.parents.len())
+                .sum::<usize>();
+            assert!(edges < network.nodes.len());
+            let evidence = HashMap::from([("X5", 0)]);
+            let probabilities = network.infer(&evidence);
+            for node in &network.nodes {
+                let name = network.get_node_name(node.id);
+                for value in network.get_node_domain(node.id) {
+                    assert_close(
+                        probabilities[node.id][&value],
+                        brute_force_probability(&network, name, &value, &evidence),
+                        1e-9,
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_rows_keep_one_state() {
+        let mut rng = seeded_rng(3);
+        for _ in 0..100 {
+            let row = random_row(3, 1.0, &mut rng);
+            assert_eq!(row.iter().filter(|prob| **prob > 0.0).count(), 1);
+            assert_close(row.iter().sum(), 1.0, 1e-12);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid state counts for a random network")]
+    fn state_counts_must_be_ordered() {
+        BayesianNetwork::random(&RandomNetworkOptions {
+            min_states: 3,
+            max_states: 2,
+            ..RandomNetworkOptions::default()
+        });
+    }
+}
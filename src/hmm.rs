@@ -0,0 +1,599 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, NodeType, Probability};
+
+/// Scaled forward messages, backward messages and scaling factors.
+type Messages = (
+    Vec<Vec<Probability>>,
+    Vec<Vec<Probability>>,
+    Vec<Probability>,
+);
+
+/// A hidden Markov model: a chain of hidden states, each emitting one observation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hmm<T> {
+    states: Vec<T>,
+    observations: Vec<T>,
+    initial: Vec<Probability>,
+    transition: Vec<Vec<Probability>>,
+    emission: Vec<Vec<Probability>>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> Hmm<T> {
+    /// Create a model over `states` and `observations`. `initial[i]` is the probability of
+    /// starting in state `i`, `transition[i][j]` that of moving from state `i` to state `j`,
+    /// and `emission[i][k]` that of state `i` emitting observation `k`.
+    pub fn new(
+        states: Vec<T>,
+        observations: Vec<T>,
+        initial: Vec<Probability>,
+        transition: Vec<Vec<Probability>>,
+        emission: Vec<Vec<Probability>>,
+    ) -> Hmm<T> {
+        let n = states.len();
+        if initial.len() != n
+            || transition.len() != n
+            || transition.iter().any(|row| row.len() != n)
+            || emission.len() != n
+            || emission.iter().any(|row| row.len() != observations.len())
+        {
+            panic!(
+                "HMM tables do not match {} states and {} observations",
+                n,
+                observations.len()
+            );
+        }
+        Hmm {
+            states,
+            observations,
+            initial,
+            transition,
+            emission,
+        }
+    }
+
+    /// Get the hidden states.
+    pub fn states(&self) -> &[T] {
+        &self.states
+    }
+
+    /// Get the observations the states can emit.
+    pub fn observations(&self) -> &[T] {
+        &self.observations
+    }
+
+    /// Build the network of a sequence of `length` steps, with hidden nodes `State[i]` and
+    /// observed nodes `Observation[i]`.
+    pub fn network(&self, length: usize) -> BayesianNetwork<T> {
+        let mut value_space = self.states.clone();
+        for observation in &self.observations {
+            if !value_space.contains(observation) {
+                value_space.push(observation.clone());
+            }
+        }
+        let mut network = BayesianNetwork::new(value_space);
+        let row = |values: &[T], probs: &[Probability]| {
+            values
+                .iter()
+                .cloned()
+                .zip(probs.iter().copied())
+                .collect::<HashMap<T, Probability>>()
+        };
+        let table = |values: &[T], rows: &[Vec<Probability>]| {
+            self.states
+                .iter()
+                .zip(rows)
+                .map(|(state, probs)| (vec![state.clone()], row(values, probs)))
+                .collect::<HashMap<Vec<T>, HashMap<T, Probability>>>()
+        };
+        network.add_chain(
+            "State",
+            "Observation",
+            length,
+            row(&self.states, &self.initial),
+            table(&self.states, &self.transition),
+            table(&self.observations, &self.emission),
+        );
+        for i in 0..length {
+            network.set_node_states(&format!("State[{}]", i), self.states.clone());
+            network.set_node_states(&format!("Observation[{}]", i), self.observations.clone());
+        }
+        network
+    }
+
+    /// The index of every observation of a sequence.
+    fn indices(&self, sequence: &[T]) -> Vec<usize> {
+        sequence
+            .iter()
+            .map(
+                |value| match self.observations.iter().position(|o| o == value) {
+                    Some(index) => index,
+                    None => panic!("Observation {:?} not found", value),
+                },
+            )
+            .collect()
+    }
+
+    /// The scaled forward and backward messages of an observed sequence, with the scaling
+    /// factors `c[t] = P(o[t] | o[..t])`. `alpha[t][i] * beta[t][i]` is the posterior of
+    /// state `i` at step `t`.
+    fn passes(&self, observed: &[usize]) -> Messages {
+        let n = self.states.len();
+        let mut alpha: Vec<Vec<Probability>> = Vec::with_capacity(observed.len());
+        let mut scales = Vec::with_capacity(observed.len());
+        for (t, &o) in observed.iter().enumerate() {
+            let mut next = (0..n)
+                .map(|j| {
+                    let prior = match t {
+                        0 => self.initial[j],
+                        _ => (0..n)
+                            .map(|i| alpha[t - 1][i] * self.transition[i][j])
+                            .sum::<Probability>(),
+                    };
+                    prior * self.emission[j][o]
+                })
+                .collect::<Vec<Probability>>();
+            let scale = next.iter().sum::<Probability>();
+            if scale <= 0.0 {
+                panic!("Evidence has zero probability");
+            }
+            for value in &mut next {
+                *value /= scale;
+            }
+            alpha.push(next);
+            scales.push(scale);
+        }
+
+        let mut beta = vec![vec![1.0; n]; observed.len()];
+        for t in (0..observed.len().saturating_sub(1)).rev() {
+            beta[t] = (0..n)
+                .map(|i| {
+                    (0..n)
+                        .map(|j| {
+                            self.transition[i][j]
+                                * self.emission[j][observed[t + 1]]
+                                * beta[t + 1][j]
+                        })
+                        .sum::<Probability>()
+                        / scales[t + 1]
+                })
+                .collect();
+        }
+        (alpha, beta, scales)
+    }
+
+    /// Get the posterior distribution of the hidden state at each step given the whole
+    /// observed sequence, by the scaled forward-backward algorithm.
+    pub fn forward_backward(&self, sequence: &[T]) -> Vec<Vec<(T, Probability)>> {
+        let (alpha, beta, _) = self.passes(&self.indices(sequence));
+        alpha
+            .iter()
+            .zip(&beta)
+            .map(|(alpha, beta)| {
+                self.states
+                    .iter()
+                    .cloned()
+                    .zip(alpha.iter().zip(beta).map(|(a, b)| a * b))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Get the natural log-probability of an observed sequence.
+    pub fn log_likelihood(&self, sequence: &[T]) -> f64 {
+        let (_, _, scales) = self.passes(&self.indices(sequence));
+        scales.iter().map(|scale| scale.ln()).sum()
+    }
+
+    /// Estimate the tables from sequences of (state, observation) pairs by counting, adding
+    /// `pseudo_count` to every cell. Rows that are never observed become uniform.
+    pub fn fit(&mut self, sequences: &[Vec<(T, T)>], pseudo_count: Probability) {
+        let n = self.states.len();
+        let state = |value: &T| match self.states.iter().position(|s| s == value) {
+            Some(index) => index,
+            None => panic!("State {:?} not found", value),
+        };
+        let mut initial = vec![pseudo_count; n];
+        let mut transition = vec![vec![pseudo_count; n]; n];
+        let mut emission = vec![vec![pseudo_count; self.observations.len()]; n];
+        for sequence in sequences {
+            let (states, observations): (Vec<T>, Vec<T>) = sequence.iter().cloned().unzip();
+            let states = states.iter().map(state).collect::<Vec<usize>>();
+            let observations = self.indices(&observations);
+            if let Some(first) = states.first() {
+                initial[*first] += 1.0;
+            }
+            for pair in states.windows(2) {
+                transition[pair[0]][pair[1]] += 1.0;
+            }
+            for (i, o) in states.iter().zip(observations) {
+                emission[*i][o] += 1.0;
+            }
+        }
+        self.initial = normalized_row(initial);
+        self.transition = transition.into_iter().map(normalized_row).collect();
+        self.emission = emission.into_iter().map(normalized_row).collect();
+    }
+
+    /// Estimate the tables from observed sequences alone by Baum-Welch expectation
+    /// maximization, starting from the current tables, until the log-likelihood improves by
+    /// less than `tolerance` or after `max_iterations` iterations. Returns the final
+    /// log-likelihood of the sequences.
+    pub fn baum_welch(
+        &mut self,
+        sequences: &[Vec<T>],
+        max_iterations: usize,
+        tolerance: f64,
+    ) -> f64 {
+        let n = self.states.len();
+        let sequences = sequences
+            .iter()
+            .filter(|sequence| !sequence.is_empty())
+            .map(|sequence| self.indices(sequence))
+            .collect::<Vec<Vec<usize>>>();
+        let mut previous = f64::NEG_INFINITY;
+        for _ in 0..max_iterations {
+            let mut initial = vec![0.0; n];
+            let mut transition = vec![vec![0.0; n]; n];
+            let mut emission = vec![vec![0.0; self.observations.len()]; n];
+            let mut log_likelihood = 0.0;
+            for observed in &sequences {
+                let (alpha, beta, scales) = self.passes(observed);
+                log_likelihood += scales.iter().map(|scale| scale.ln()).sum::<f64>();
+                for (t, &o) in observed.iter().enumerate() {
+                    for i in 0..n {
+                        let gamma = alpha[t][i] * beta[t][i];
+                        if t == 0 {
+                            initial[i] += gamma;
+                        }
+                        emission[i][o] += gamma;
+                        if t + 1 < observed.len() {
+                            for j in 0..n {
+                                transition[i][j] += alpha[t][i]
+                                    * self.transition[i][j]
+                                    * self.emission[j][observed[t + 1]]
+                                    * beta[t + 1][j]
+                                    / scales[t + 1];
+                            }
+                        }
+                    }
+                }
+            }
+            // Keep the old row of a state that is never visited.
+            let update = |old: &[Probability], new: Vec<Probability>| {
+                if new.iter().sum::<Probability>() > 0.0 {
+                    normalized_row(new)
+                } else {
+                    old.to_vec()
+                }
+            };
+            self.initial = update(&self.initial, initial);
+            for i in 0..n {
+                self.transition[i] = update(&self.transition[i], transition[i].clone());
+                self.emission[i] = update(&self.emission[i], emission[i].clone());
+            }
+            let converged = log_likelihood - previous < tolerance;
+            previous = log_likelihood;
+            if converged {
+                break;
+            }
+        }
+        sequences
+            .iter()
+            .map(|observed| {
+                let (_, _, scales) = self.passes(observed);
+                scales.iter().map(|scale| scale.ln()).sum::<f64>()
+            })
+            .sum()
+    }
+
+    /// Get the most likely sequence of hidden states given the observed sequence, by the
+    /// Viterbi algorithm in log space.
+    pub fn viterbi(&self, sequence: &[T]) -> Vec<T> {
+        let observed = self.indices(sequence);
+        let n = self.states.len();
+        if observed.is_empty() {
+            return Vec::new();
+        }
+        let mut scores = (0..n)
+            .map(|i| (self.initial[i] * self.emission[i][observed[0]]).ln())
+            .collect::<Vec<f64>>();
+        let mut backpointers: Vec<Vec<usize>> = Vec::with_capacity(observed.len() - 1);
+        for &o in &observed[1..] {
+            let mut next = Vec::with_capacity(n);
+            let mut pointers = Vec::with_capacity(n);
+            for j in 0..n {
+                let (best, score) = (0..n)
+                    .map(|i| (i, scores[i] + self.transition[i][j].ln()))
+                    .fold((0, f64::NEG_INFINITY), |best, candidate| {
+                        if candidate.1 > best.1 {
+                            candidate
+                        } else {
+                            best
+                        }
+                    });
+                next.push(score + self.emission[j][o].ln());
+                pointers.push(best);
+            }
+            scores = next;
+            backpointers.push(pointers);
+        }
+        if scores.iter().all(|score| *score == f64::NEG_INFINITY) {
+            panic!("Evidence has zero probability");
+        }
+        let mut state = (0..n)
+            .max_by(|a, b| scores[*a].total_cmp(&scores[*b]))
+            .unwrap();
+        let mut path = vec![state];
+        for pointers in backpointers.iter().rev() {
+            state = pointers[state];
+            path.push(state);
+        }
+        path.into_iter()
+            .rev()
+            .map(|state| self.states[state].clone())
+            .collect()
+    }
+}
+
+/// Scale a row of counts to sum to one, or make it uniform if it is all zero.
+fn normalized_row(row: Vec<Probability>) -> Vec<Probability> {
+    let total = row.iter().sum::<Probability>();
+    let len = row.len() as Probability;
+    row.into_iter()
+        .map(|count| {
+            if total > 0.0 {
+                count / total
+            } else {
+                1.0 / len
+            }
+        })
+        .collect()
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Add a chain of `length` (hidden, observed) node pairs named `hidden[0]`,
+    /// `observed[0]`, `hidden[1]`, ... The first hidden node is a root with prior `initial`,
+    /// each later one depends on the one before through `transition`, and each observed node
+    /// depends on its hidden node through `emission`. The transition and emission CPTs are
+    /// registered once as the shared CPTs `hidden.transition` and `observed.emission`, and
+    /// every position is tied to them.
+    pub fn add_chain(
+        &mut self,
+        hidden: &str,
+        observed: &str,
+        length: usize,
+        initial: HashMap<T, Probability>,
+        transition: HashMap<Vec<T>, HashMap<T, Probability>>,
+        emission: HashMap<Vec<T>, HashMap<T, Probability>>,
+    ) {
+        let transition_name = format!("{}.transition", hidden);
+        let emission_name = format!("{}.emission", observed);
+        self.add_shared_cpt(&transition_name, transition);
+        self.add_shared_cpt(&emission_name, emission);
+        for i in 0..length {
+            let hidden_name = format!("{}[{}]", hidden, i);
+            let observed_name = format!("{}[{}]", observed, i);
+            if i == 0 {
+                self.add_node(&hidden_name, NodeType::Root(initial.clone()));
+            } else {
+                self.add_node(&hidden_name, NodeType::Inner);
+                self.add_shared_dependency(
+                    vec![format!("{}[{}]", hidden, i - 1).as_str()],
+                    &hidden_name,
+                    &transition_name,
+                );
+            }
+            self.add_node(&observed_name, NodeType::Leaf);
+            self.add_shared_dependency(vec![hidden_name.as_str()], &observed_name, &emission_name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        assert_close, brute_force_mpe, brute_force_probability, evidence_probability, row,
+        TOLERANCE,
+    };
+
+    /// The ice cream HMM: hot or cold days, on which 1, 2 or 3 ice creams are eaten.
+    fn ice_cream() -> Hmm<&'static str> {
+        Hmm::new(
+            vec!["hot", "cold"],
+            vec!["1", "2", "3"],
+            vec![0.8, 0.2],
+            vec![vec![0.6, 0.4], vec![0.5, 0.5]],
+            vec![vec![0.2, 0.4, 0.4], vec![0.5, 0.4, 0.1]],
+        )
+    }
+
+    const SEQUENCE: [&str; 4] = ["3", "1", "3", "2"];
+
+    fn observation_names() -> Vec<String> {
+        (0..SEQUENCE.len())
+            .map(|i| format!("Observation[{}]", i))
+            .collect()
+    }
+
+    #[test]
+    fn viterbi_matches_exhaustive_argmax() {
+        let hmm = ice_cream();
+        let network = hmm.network(SEQUENCE.len());
+        let names = observation_names();
+        let evidence = names
+            .iter()
+            .map(String::as_str)
+            .zip(SEQUENCE)
+            .collect::<HashMap<&str, &str>>();
+        let (expected, _) = brute_force_mpe(&network, &evidence);
+        let states = hmm.viterbi(&SEQUENCE);
+        for (i, state) in states.iter().enumerate() {
+            assert_eq!(*state, expected[&format!("State[{}]", i)]);
+        }
+    }
+
+    #[test]
+    fn forward_backward_matches_enumeration() {
+        let hmm = ice_cream();
+        let network = hmm.network(SEQUENCE.len());
+        let names = observation_names();
+        let evidence = names
+            .iter()
+            .map(String::as_str)
+            .zip(SEQUENCE)
+            .collect::<HashMap<&str, &str>>();
+        for (i, posterior) in hmm.forward_backward(&SEQUENCE).iter().enumerate() {
+            for (state, prob) in posterior {
+                let name = format!("State[{}]", i);
+                assert_close(
+                    *prob,
+                    brute_force_probability(&network, &name, state, &evidence),
+                    TOLERANCE,
+                );
+            }
+        }
+        assert_close(
+            hmm.log_likelihood(&SEQUENCE),
+            evidence_probability(&network, &evidence).ln(),
+            TOLERANCE,
+        );
+    }
+
+    fn chain(network: &mut BayesianNetwork<bool>, hidden: &str, observed: &str) {
+        network.add_chain(
+            hidden,
+            observed,
+            3,
+            row(0.5),
+            HashMap::from([(vec![true], row(0.7)), (vec![false], row(0.2))]),
+            HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.1))]),
+        );
+    }
+
+    #[test]
+    fn add_chain_ties_every_position() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        chain(&mut network, "Part", "Word");
+        chain(&mut network, "Tag", "Token");
+        assert_eq!(
+            network.shared_cpt_names(),
+            vec![
+                "Part.transition",
+                "Tag.transition",
+                "Token.emission",
+                "Word.emission"
+            ]
+        );
+        assert_eq!(network.get_node_shared_cpt("Part[0]"), None);
+        for i in 1..3 {
+            assert_eq!(
+                network.get_node_shared_cpt(&format!("Part[{}]", i)),
+                Some("Part.transition")
+            );
+        }
+        for i in 0..3 {
+            assert_eq!(
+                network.get_node_shared_cpt(&format!("Word[{}]", i)),
+                Some("Word.emission")
+            );
+        }
+
+        let transition = HashMap::from([(vec![true], row(0.4)), (vec![false], row(0.6))]);
+        network.set_shared_cpt("Part.transition", transition.clone());
+        for i in 1..3 {
+            let id = network.get_node_index(&format!("Part[{}]", i));
+            assert_eq!(network.nodes[id].probability, transition);
+        }
+        let id = network.get_node_index("Tag[1]");
+        assert_eq!(network.nodes[id].probability[&vec![true]], row(0.7));
+    }
+
+    #[test]
+    fn fit_counts_states_and_emissions() {
+        let mut hmm = ice_cream();
+        hmm.fit(
+            &[
+                vec![("hot", "3"), ("hot", "2"), ("cold", "1")],
+                vec![("cold", "1"), ("cold", "1")],
+            ],
+            0.0,
+        );
+        assert_eq!(hmm.initial, vec![0.5, 0.5]);
+        assert_eq!(hmm.transition, vec![vec![0.5, 0.5], vec![0.0, 1.0]]);
+        assert_eq!(hmm.emission, vec![vec![0.0, 0.5, 0.5], vec![1.0, 0.0, 0.0]]);
+        // A pseudo count fills the rows that are never observed.
+        hmm.fit(&[vec![("hot", "3")]], 1.0);
+        assert_eq!(hmm.transition, vec![vec![0.5, 0.5], vec![0.5, 0.5]]);
+        assert_eq!(hmm.emission[0], vec![0.25, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn baum_welch_step_matches_expected_counts_by_enumeration() {
+        let hmm = ice_cream();
+        let network = hmm.network(SEQUENCE.len());
+        let names = observation_names();
+        let evidence = names
+            .iter()
+            .map(String::as_str)
+            .zip(SEQUENCE)
+            .collect::<HashMap<&str, &str>>();
+        let state = |i: usize| format!("State[{}]", i);
+        let gamma =
+            |i: usize, s: &'static str| brute_force_probability(&network, &state(i), &s, &evidence);
+        let observed = evidence_probability(&network, &evidence);
+        let xi = |i: usize, from: &'static str, to: &'static str| {
+            let (current, next) = (state(i), state(i + 1));
+            let mut evidence = evidence
+                .iter()
+                .map(|(name, value)| (*name, *value))
+                .collect::<HashMap<&str, &str>>();
+            evidence.insert(&current, from);
+            evidence.insert(&next, to);
+            evidence_probability(&network, &evidence) / observed
+        };
+
+        let mut learned = hmm.clone();
+        let log_likelihood = learned.baum_welch(&[SEQUENCE.to_vec()], 1, 0.0);
+        let states = hmm.states().to_vec();
+        for (i, from) in states.iter().enumerate() {
+            assert_close(learned.initial[i], gamma(0, from), TOLERANCE);
+            let visits = (0..SEQUENCE.len() - 1)
+                .map(|t| gamma(t, from))
+                .sum::<Probability>();
+            for (j, to) in states.iter().enumerate() {
+                let moves = (0..SEQUENCE.len() - 1)
+                    .map(|t| xi(t, from, to))
+                    .sum::<Probability>();
+                assert_close(learned.transition[i][j], moves / visits, TOLERANCE);
+            }
+            let visits = (0..SEQUENCE.len())
+                .map(|t| gamma(t, from))
+                .sum::<Probability>();
+            for (k, observation) in hmm.observations().iter().enumerate() {
+                let emitted = (0..SEQUENCE.len())
+                    .filter(|t| SEQUENCE[*t] == *observation)
+                    .map(|t| gamma(t, from))
+                    .sum::<Probability>();
+                assert_close(learned.emission[i][k], emitted / visits, TOLERANCE);
+            }
+        }
+        assert_close(log_likelihood, learned.log_likelihood(&SEQUENCE), TOLERANCE);
+    }
+
+    #[test]
+    fn baum_welch_never_lowers_the_likelihood() {
+        let sequences = vec![SEQUENCE.to_vec(), vec!["1", "1", "2", "3", "3"]];
+        let mut hmm = ice_cream();
+        let mut previous = sequences
+            .iter()
+            .map(|sequence| hmm.log_likelihood(sequence))
+            .sum::<f64>();
+        for _ in 0..10 {
+            let log_likelihood = hmm.baum_welch(&sequences, 1, 0.0);
+            assert!(log_likelihood >= previous - TOLERANCE);
+            previous = log_likelihood;
+        }
+    }
+}
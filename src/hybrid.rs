@@ -0,0 +1,295 @@
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::Probability,
+    gaussian::{ClgNetwork, Gaussian, HybridPosterior},
+    posterior::Posterior,
+};
+
+/// How `ClgNetwork::posterior` computes the posterior of a hybrid network.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HybridEngine {
+    /// Exact inference, as `ClgNetwork::infer`, whose cost grows with the number of joint
+    /// configurations of the discrete parents of continuous nodes.
+    Exact,
+    /// Likelihood weighting with `particles` samples: discrete and continuous nodes are
+    /// sampled forward with the evidence clamped, and each sample is weighted by the
+    /// probability or density of the evidence.
+    LikelihoodWeighting { particles: usize },
+    /// Exact inference if there are at most `max_configurations` configurations of the
+    /// discrete parents of continuous nodes, and likelihood weighting with `particles`
+    /// samples otherwise.
+    Auto {
+        max_configurations: usize,
+        particles: usize,
+    },
+}
+
+impl Default for HybridEngine {
+    fn default() -> Self {
+        HybridEngine::Auto {
+            max_configurations: 1024,
+            particles: 10_000,
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> ClgNetwork<T> {
+    /// Infer the posterior of every node given discrete `evidence` and `continuous_evidence`
+    /// with the given engine. The result is a `HybridPosterior` rather than a `Posterior` on
+    /// purpose: a `Posterior` holds distributions over states, which continuous nodes lack,
+    /// so the discrete marginals come as a `Posterior` next to the continuous moments.
+    pub fn posterior(
+        &self,
+        evidence: &HashMap<&str, T>,
+        continuous_evidence: &HashMap<&str, f64>,
+        engine: &HybridEngine,
+    ) -> HybridPosterior<T> {
+        self.posterior_with_rng(
+            evidence,
+            continuous_evidence,
+            engine,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Infer the posterior like `posterior`, sampling with the given random number generator.
+    pub fn posterior_with_rng<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<&str, T>,
+        continuous_evidence: &HashMap<&str, f64>,
+        engine: &HybridEngine,
+        rng: &mut R,
+    ) -> HybridPosterior<T> {
+        let particles = match engine {
+            HybridEngine::Exact => return self.infer(evidence, continuous_evidence),
+            HybridEngine::LikelihoodWeighting { particles } => *particles,
+            HybridEngine::Auto {
+                max_configurations,
+                particles,
+            } => {
+                if self.switch_configurations(evidence) <= *max_configurations {
+                    return self.infer(evidence, continuous_evidence);
+                }
+                *particles
+            }
+        };
+        self.likelihood_weighting(evidence, continuous_evidence, particles, rng)
+    }
+
+    /// The number of joint configurations of the discrete parents of continuous nodes
+    /// consistent with `evidence`, which exact inference enumerates.
+    fn switch_configurations(&self, evidence: &HashMap<&str, T>) -> usize {
+        let evidence = self.discrete.evidence_ids(evidence);
+        self.switches()
+            .iter()
+            .map(|id| {
+                if evidence.contains_key(id) {
+                    1
+                } else {
+                    self.discrete.get_node_domain(*id).len()
+                }
+            })
+            .fold(1usize, |total, size| total.saturating_mul(size))
+    }
+
+    fn likelihood_weighting<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<&str, T>,
+        continuous_evidence: &HashMap<&str, f64>,
+        particles: usize,
+        rng: &mut R,
+    ) -> HybridPosterior<T> {
+        let discrete_evidence = self.discrete.evidence_ids(evidence);
+        let observed = self.continuous_evidence(continuous_evidence);
+        let observed_mixtures = self.mixture_evidence(continuous_evidence);
+        let order = self.discrete.topological_order();
+        let domains = self
+            .discrete
+            .nodes
+            .iter()
+            .map(|node| self.discrete.get_node_domain(node.id))
+            .collect::<Vec<Vec<T>>>();
+        let mut counts = domains
+            .iter()
+            .map(|domain| vec![0.0; domain.len()])
+            .collect::<Vec<Vec<Probability>>>();
+        let mut moments = vec![(0.0, 0.0); self.continuous.len() + self.mixtures.len()];
+        let mut total = 0.0;
+        for _ in 0..particles {
+            let (assignment, mut weight) = self.discrete.weighted_sample_given(
+                &order,
+                &HashMap::new(),
+                &discrete_evidence,
+                rng,
+            );
+            if weight <= 0.0 {
+                continue;
+            }
+            let mut values = vec![0.0; self.continuous.len()];
+            for (index, node) in self.continuous.iter().enumerate() {
+                let cpd = self.cpd(index, &assignment);
+                let mean = cpd.intercept
+                    + node
+                        .continuous_parents
+                        .iter()
+                        .zip(&cpd.coefficients)
+                        .map(|(parent, coefficient)| coefficient * values[*parent])
+                        .sum::<f64>();
+                values[index] = match observed.get(&index) {
+                    Some(value) => {
+                        weight *= Gaussian::new(mean, cpd.variance).density(*value);
+                        *value
+                    }
+                    None => mean + cpd.variance.sqrt() * standard_normal(rng),
+                };
+            }
+            for index in 0..self.mixtures.len() {
+                let mixture = self.mixture(index, &assignment);
+                values.push(match observed_mixtures.get(&index) {
+                    Some(value) => {
+                        weight *= mixture.density(*value);
+                        *value
+                    }
+                    None => mixture.sample(rng),
+                });
+            }
+            if weight <= 0.0 || !weight.is_finite() {
+                continue;
+            }
+            total += weight;
+            for (id, value) in &assignment {
+                if let Some(position) = domains[*id].iter().position(|v| v == value) {
+                    counts[*id][position] += weight;
+                }
+            }
+            for (moment, value) in moments.iter_mut().zip(values) {
+                moment.0 += weight * value;
+                moment.1 += weight * value * value;
+            }
+        }
+        if total <= 0.0 {
+            panic!("Evidence has zero probability");
+        }
+
+        let marginals = self
+            .discrete
+            .nodes
+            .iter()
+            .map(|node| {
+                let states = domains[node.id]
+                    .iter()
+                    .cloned()
+                    .zip(counts[node.id].iter().map(|count| count / total))
+                    .collect();
+                (self.discrete.get_node_name(node.id).to_string(), states)
+            })
+            .collect();
+        let continuous = self
+            .continuous_node_names()
+            .into_iter()
+            .zip(moments)
+            .map(|(name, (first, second))| {
+                let mean = first / total;
+                let variance = (second / total - mean * mean).max(0.0);
+                (name.to_string(), Gaussian { mean, variance })
+            })
+            .collect();
+        HybridPosterior {
+            discrete: Posterior { marginals },
+            continuous,
+        }
+    }
+}
+
+/// Draw from the standard normal distribution by the Box-Muller transform.
+pub(crate) fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    let u = 1.0 - rng.gen::<f64>();
+    let v = rng.gen::<f64>();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bayesian_network::{BayesianNetwork, NodeType};
+    use crate::gaussian::LinearGaussian;
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, row};
+
+    /// S switches X between N(2, 1) and N(-1, 4), and Y = X / 2 + N(0, 1).
+    fn switched() -> ClgNetwork<bool> {
+        let mut discrete = BayesianNetwork::new(vec![true, false]);
+        discrete.add_node("s", NodeType::Root(row(0.3)));
+        let mut network = ClgNetwork::new(discrete);
+        network.add_linear_gaussian(
+            "x",
+            vec!["s"],
+            Vec::new(),
+            HashMap::from([
+                (vec![true], LinearGaussian::new(2.0, Vec::new(), 1.0)),
+                (vec![false], LinearGaussian::new(-1.0, Vec::new(), 4.0)),
+            ]),
+        );
+        network.add_linear_gaussian(
+            "y",
+            Vec::new(),
+            vec!["x"],
+            HashMap::from([(Vec::new(), LinearGaussian::new(0.0, vec![0.5], 1.0))]),
+        );
+        network
+    }
+
+    #[test]
+    fn likelihood_weighting_agrees_with_exact_inference() {
+        let network = switched();
+        let continuous_evidence = HashMap::from([("y", 1.0)]);
+        let exact = network.posterior(&HashMap::new(), &continuous_evidence, &HybridEngine::Exact);
+        assert_eq!(exact, network.infer(&HashMap::new(), &continuous_evidence));
+        let sampled = network.posterior_with_rng(
+            &HashMap::new(),
+            &continuous_evidence,
+            &HybridEngine::LikelihoodWeighting { particles: 50_000 },
+            &mut seeded_rng(5),
+        );
+        assert_close(
+            sampled.probability("s", &true).unwrap(),
+            exact.probability("s", &true).unwrap(),
+            0.01,
+        );
+        assert_close(sampled.mean("x").unwrap(), exact.mean("x").unwrap(), 0.03);
+        assert_close(
+            sampled.variance("x").unwrap(),
+            exact.variance("x").unwrap(),
+            0.05,
+        );
+        assert_close(sampled.mean("y").unwrap(), 1.0, 1e-9);
+    }
+
+    #[test]
+    fn auto_is_exact_up_to_its_configuration_limit() {
+        let network = switched();
+        let evidence = HashMap::new();
+        let continuous_evidence = HashMap::from([("y", 1.0)]);
+        let auto = |max_configurations| {
+            network.posterior_with_rng(
+                &evidence,
+                &continuous_evidence,
+                &HybridEngine::Auto {
+                    max_configurations,
+                    particles: 1_000,
+                },
+                &mut seeded_rng(6),
+            )
+        };
+        assert_eq!(auto(2), network.infer(&evidence, &continuous_evidence));
+        let sampled = network.posterior_with_rng(
+            &evidence,
+            &continuous_evidence,
+            &HybridEngine::LikelihoodWeighting { particles: 1_000 },
+            &mut seeded_rng(6),
+        );
+        assert_eq!(auto(1), sampled);
+    }
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name, Probability};
+
+/// How much one finding drives the posterior of a target, as returned by `evidence_impact`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvidenceImpact<T> {
+    pub node: Name,
+    pub value: T,
+    /// The posterior of the target with this finding retracted and the others kept.
+    pub retracted: Vec<(T, Probability)>,
+    /// The total variation distance between the posterior of the target given all the
+    /// evidence and `retracted`: the largest change in the probability of any event.
+    pub shift: Probability,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Retract each finding of `evidence` in turn and report how the posterior of `target`
+    /// changes, the findings driving it most first.
+    pub fn evidence_impact(
+        &self,
+        target: &str,
+        evidence: &HashMap<&str, T>,
+    ) -> Vec<EvidenceImpact<T>> {
+        let full = self.posterior(evidence).select(&[target]);
+        let full = full.distribution(target).unwrap();
+        let mut findings = evidence.iter().collect::<Vec<(&&str, &T)>>();
+        findings.sort_by_key(|(name, _)| self.get_node_index(name));
+        let mut impacts = findings
+            .into_iter()
+            .map(|(name, value)| {
+                let mut rest = evidence.clone();
+                rest.remove(name);
+                let retracted = self.posterior(&rest).select(&[target]);
+                let retracted = retracted.distribution(target).unwrap().to_vec();
+                let shift = full
+                    .iter()
+                    .zip(&retracted)
+                    .map(|((_, p), (_, q))| (p - q).abs())
+                    .sum::<Probability>()
+                    / 2.0;
+                EvidenceImpact {
+                    node: name.to_string(),
+                    value: value.clone(),
+                    retracted,
+                    shift,
+                }
+            })
+            .collect::<Vec<EvidenceImpact<T>>>();
+        impacts.sort_by(|a, b| b.shift.total_cmp(&a.shift));
+        impacts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_probability, TOLERANCE};
+
+    #[test]
+    fn evidence_impact_retracts_each_finding() {
+        let network = bonus();
+        let evidence = HashMap::from([("horse_race", false), ("bonus", true), ("feast", false)]);
+        let impacts = network.evidence_impact("mood", &evidence);
+        assert_eq!(impacts.len(), 3);
+        let full = brute_force_probability(&network, "mood", &true, &evidence);
+        for impact in &impacts {
+            let mut rest = evidence.clone();
+            rest.remove(impact.node.as_str());
+            let retracted = brute_force_probability(&network, "mood", &true, &rest);
+            assert_eq!(impact.value, evidence[impact.node.as_str()]);
+            assert!(impact.retracted[0].0);
+            assert_close(impact.retracted[0].1, retracted, TOLERANCE);
+            // With two states, the total variation is the change in either probability.
+            assert_close(impact.shift, (full - retracted).abs(), TOLERANCE);
+        }
+        assert!(impacts
+            .windows(2)
+            .all(|pair| pair[0].shift >= pair[1].shift));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    bayesian_network::{BayesianNetwork, Probability},
+    sampling::PosteriorSampler,
+};
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+/// A record completed by imputation.
+#[derive(Clone, Debug)]
+pub struct Imputation<'a, T> {
+    /// The observed values together with the imputed ones, keyed by node name.
+    pub record: HashMap<&'a str, T>,
+    /// The posterior probability of each imputed value, keyed by node name.
+    pub confidence: HashMap<&'a str, Probability>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Fill in the nodes missing from `record` with their posterior mode given the observed values.
+    pub fn impute(&self, record: &HashMap<&str, T>) -> Imputation<'_, T> {
+        self.impute_by(record, |_, marginal| {
+            self.value_space
+                .iter()
+                .max_by(|a, b| marginal[*a].total_cmp(&marginal[*b]))
+                .unwrap()
+                .clone()
+        })
+    }
+
+    /// Fill in the nodes missing from `record` with one joint draw from their posterior given
+    /// the observed values, by likelihood weighting as in `sample_posterior`. Repeated calls
+    /// give the completed records of multiple imputation.
+    pub fn impute_sample(&self, record: &HashMap<&str, T>) -> Imputation<'_, T> {
+        self.impute_sample_with_rng(
+            record,
+            &PosteriorSampler::default(),
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Fill in the nodes missing from `record` like `impute_sample`, drawing with `sampler`
+    /// and `rng`.
+    pub fn impute_sample_with_rng<R: Rng + ?Sized>(
+        &self,
+        record: &HashMap<&str, T>,
+        sampler: &PosteriorSampler,
+        rng: &mut R,
+    ) -> Imputation<'_, T> {
+        self.check_record(record);
+        let draw = self.sample_posterior_with_rng(record, 1, sampler, rng);
+        let row = &draw.rows()[0];
+        self.impute_by(record, |name, _| {
+            row[draw.column_index(name).unwrap()].clone()
+        })
+    }
+
+    fn check_record(&self, record: &HashMap<&str, T>) {
+        for name in record.keys() {
+            if !self.node_map.contains_key(*name) {
+                panic!("Record node `{}` not found", name);
+            }
+        }
+    }
+
+    /// Complete `record` with the value chosen for each missing node given its name and its
+    /// posterior marginal.
+    fn impute_by<F: FnMut(&str, &HashMap<T, Probability>) -> T>(
+        &self,
+        record: &HashMap<&str, T>,
+        mut choose: F,
+    ) -> Imputation<'_, T> {
+        self.check_record(record);
+        let inferred_probabilities = self.infer(record);
+        let mut imputation = Imputation {
+            record: HashMap::new(),
+            confidence: HashMap::new(),
+        };
+        for (name, id) in &self.node_map {
+            if let Some(value) = record.get(name.as_str()) {
+                imputation.record.insert(name.as_str(), value.clone());
+            } else {
+                let marginal = &inferred_probabilities[*id];
+                let value = choose(name, marginal);
+                imputation
+                    .confidence
+                    .insert(name.as_str(), marginal[&value]);
+                imputation.record.insert(name.as_str(), value);
+            }
+        }
+        imputation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sampling::seeded_rng,
+        testing::{assert_close, bonus, brute_force_probability, evidence_probability, TOLERANCE},
+    };
+
+    #[test]
+    fn impute_fills_missing_nodes_with_their_posterior_mode() {
+        let network = bonus();
+        let record = HashMap::from([("bonus", true), ("feast", true)]);
+        let imputation = network.impute(&record);
+        assert_eq!(imputation.record.len(), network.nodes.len());
+        assert_eq!(
+            imputation.confidence.len(),
+            network.nodes.len() - record.len()
+        );
+        for name in network.node_names() {
+            let value = imputation.record[name];
+            match record.get(name) {
+                Some(observed) => assert_eq!(value, *observed),
+                None => {
+                    let prob = brute_force_probability(&network, name, &value, &record);
+                    assert!(prob >= 0.5);
+                    assert_close(imputation.confidence[name], prob, TOLERANCE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn impute_sample_reports_the_marginal_of_each_draw() {
+        let network = bonus();
+        let record = HashMap::from([("mood", true)]);
+        let imputation = network.impute_sample_with_rng(
+            &record,
+            &PosteriorSampler::default(),
+            &mut seeded_rng(3),
+        );
+        assert!(imputation.record["mood"]);
+        for (name, confidence) in &imputation.confidence {
+            let value = imputation.record[name];
+            assert_close(
+                *confidence,
+                brute_force_probability(&network, name, &value, &record),
+                TOLERANCE,
+            );
+        }
+    }
+
+    #[test]
+    fn impute_sample_draws_the_missing_nodes_jointly() {
+        // Given a feast, the mood and the bonus are still dependent: drawing each from its
+        // marginal would give P(mood, bonus) = .235 instead of .265.
+        let network = bonus();
+        let record = HashMap::from([("feast", true)]);
+        let sampler = PosteriorSampler::Gibbs {
+            burn_in: 20,
+            thin: 1,
+        };
+        let mut rng = seeded_rng(8);
+        let n = 3000;
+        let mut counts = HashMap::new();
+        for _ in 0..n {
+            let imputation = network.impute_sample_with_rng(&record, &sampler, &mut rng);
+            assert!(imputation.record["feast"]);
+            *counts
+                .entry((imputation.record["mood"], imputation.record["bonus"]))
+                .or_insert(0) += 1;
+        }
+        let observed = evidence_probability(&network, &record);
+        for mood in [true, false] {
+            for bonus in [true, false] {
+                let evidence = HashMap::from([("feast", true), ("mood", mood), ("bonus", bonus)]);
+                let expected = evidence_probability(&network, &evidence) / observed;
+                let frequency =
+                    *counts.get(&(mood, bonus)).unwrap_or(&0) as Probability / n as Probability;
+                assert_close(frequency, expected, 0.02);
+            }
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use std::{fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, NodeId, Probability},
+    learning::configurations,
+    validation::{Diagnostic, Severity},
+};
+
+/// The node metadata key prefix under which `set_influence` records the sign of an edge,
+/// followed by the parent name.
+const INFLUENCE_KEY: &str = "influence:";
+
+const INFLUENCE_TOLERANCE: Probability = 1e-9;
+
+/// The most rounds of `project_influences`, each projecting every constrained edge once.
+const MAX_PROJECTION_ROUNDS: usize = 100;
+
+/// The qualitative influence of a parent on a child, with both domains ordered as the node
+/// states (or the value space). A positive influence means a later parent value makes later
+/// child values more likely: the probability of the child being at most any given value
+/// never grows with the parent, whatever the other parents. A negative influence means the
+/// opposite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Influence {
+    Positive,
+    Negative,
+}
+
+/// A constrained edge: the child, the position of the parent among its parents, and the sign.
+type ConstrainedEdge = (NodeId, usize, Influence);
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Declare the sign of the influence of `parent` on `child`, which must be an edge of the
+    /// network. It is recorded in the child metadata.
+    pub fn set_influence(&mut self, parent: &str, child: &str, influence: Influence) {
+        self.edge_position(parent, child);
+        let sign = match influence {
+            Influence::Positive => "+",
+            Influence::Negative => "-",
+        };
+        self.set_node_metadata(child, &format!("{}{}", INFLUENCE_KEY, parent), sign);
+    }
+
+    /// Get the declared sign of the influence of `parent` on `child`.
+    pub fn get_influence(&self, parent: &str, child: &str) -> Option<Influence> {
+        match self.get_node_metadata(child, &format!("{}{}", INFLUENCE_KEY, parent)) {
+            Some("+") => Some(Influence::Positive),
+            Some("-") => Some(Influence::Negative),
+            _ => None,
+        }
+    }
+
+    /// Check that the CPTs respect the declared influences. An empty list means they do.
+    pub fn check_influences(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for (child, position, influence) in self.constrained_edges() {
+            let node = &self.nodes[child];
+            let parent = node.parents[position];
+            let child_domain = self.get_node_domain(child);
+            let cpt = self.get_cpt(child);
+            for (lower, higher) in self.influence_pairs(child, position) {
+                let (lower_row, higher_row) = match (cpt.get(&lower), cpt.get(&higher)) {
+                    (Some(lower), Some(higher)) => (lower, higher),
+                    _ => continue,
+                };
+                let (mut lower_cdf, mut higher_cdf) = (0.0, 0.0);
+                for value in &child_domain[..child_domain.len().saturating_sub(1)] {
+                    lower_cdf += lower_row.get(value).copied().unwrap_or(0.0);
+                    higher_cdf += higher_row.get(value).copied().unwrap_or(0.0);
+                    let violated = match influence {
+                        Influence::Positive => higher_cdf > lower_cdf + INFLUENCE_TOLERANCE,
+                        Influence::Negative => higher_cdf < lower_cdf - INFLUENCE_TOLERANCE,
+                    };
+                    if violated {
+                        diagnostics.push(Diagnostic {
+                            severity: Severity::Error,
+                            check: "influence",
+                            node: Some(self.get_node_name(child).to_string()),
+                            message: format!(
+                                "{:?} influence of `{}` violated: P(at most {:?}) is {} given {:?} and {} given {:?}",
+                                influence,
+                                self.get_node_name(parent),
+                                value,
+                                lower_cdf,
+                                lower,
+                                higher_cdf,
+                                higher
+                            ),
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// Adjust the CPTs, for instance learned ones, to respect the declared influences: the
+    /// cumulative probabilities of each child value along each constrained parent are
+    /// replaced by their closest monotone sequence, edge after edge until every influence
+    /// holds.
+    pub fn project_influences(&mut self) {
+        let edges = self.constrained_edges();
+        for _ in 0..MAX_PROJECTION_ROUNDS {
+            if self.check_influences().is_empty() {
+                return;
+            }
+            for (child, position, influence) in &edges {
+                self.project_influence(*child, *position, *influence);
+            }
+        }
+    }
+
+    fn project_influence(&mut self, child: NodeId, position: usize, influence: Influence) {
+        self.expand_cpt(child);
+        let child_domain = self.get_node_domain(child);
+        let parent_domain = self.get_node_domain(self.nodes[child].parents[position]);
+        for key in self.other_configurations(child, position) {
+            let keys = parent_domain
+                .iter()
+                .map(|value| {
+                    let mut key = key.clone();
+                    key.insert(position, value.clone());
+                    key
+                })
+                .filter(|key| self.nodes[child].probability.contains_key(key))
+                .collect::<Vec<Vec<T>>>();
+            if keys.len() < 2 {
+                continue;
+            }
+            // The cumulative probabilities of each row, the last being 1.
+            let mut cdfs = keys
+                .iter()
+                .map(|key| {
+                    let row = &self.nodes[child].probability[key];
+                    let mut total = 0.0;
+                    child_domain
+                        .iter()
+                        .map(|value| {
+                            total += row.get(value).copied().unwrap_or(0.0);
+                            total
+                        })
+                        .collect::<Vec<Probability>>()
+                })
+                .collect::<Vec<Vec<Probability>>>();
+            for threshold in 0..child_domain.len().saturating_sub(1) {
+                let column = cdfs
+                    .iter()
+                    .map(|cdf| match influence {
+                        Influence::Positive => -cdf[threshold],
+                        Influence::Negative => cdf[threshold],
+                    })
+                    .collect::<Vec<Probability>>();
+                for (cdf, value) in cdfs.iter_mut().zip(isotonic(&column)) {
+                    cdf[threshold] = match influence {
+                        Influence::Positive => -value,
+                        Influence::Negative => value,
+                    };
+                }
+            }
+            for (key, cdf) in keys.iter().zip(cdfs) {
+                let row = self.nodes[child].probability.get_mut(key).unwrap();
+                let mut previous = 0.0;
+                for (value, cumulative) in child_domain.iter().zip(cdf) {
+                    row.insert(value.clone(), (cumulative - previous).max(0.0));
+                    previous = cumulative;
+                }
+            }
+        }
+    }
+
+    /// The edges with a declared influence.
+    fn constrained_edges(&self) -> Vec<ConstrainedEdge> {
+        let mut edges = Vec::new();
+        for node in &self.nodes {
+            let child = self.get_node_name(node.id);
+            for (position, parent) in node.parents.iter().enumerate() {
+                if let Some(influence) = self.get_influence(self.get_node_name(*parent), child) {
+                    edges.push((node.id, position, influence));
+                }
+            }
+        }
+        edges
+    }
+
+    /// The configurations of the parents of `child` other than the one at `position`.
+    fn other_configurations(&self, child: NodeId, position: usize) -> Vec<Vec<T>> {
+        let domains = self.nodes[child]
+            .parents
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != position)
+            .map(|(_, parent)| self.get_node_domain(*parent))
+            .collect::<Vec<Vec<T>>>();
+        configurations(&domains)
+    }
+
+    /// The pairs of parent configurations of `child` differing only in the parent at
+    /// `position`, taking consecutive values of its domain.
+    fn influence_pairs(&self, child: NodeId, position: usize) -> Vec<(Vec<T>, Vec<T>)> {
+        let domain = self.get_node_domain(self.nodes[child].parents[position]);
+        let mut pairs = Vec::new();
+        for key in self.other_configurations(child, position) {
+            for values in domain.windows(2) {
+                let mut lower = key.clone();
+                lower.insert(position, values[0].clone());
+                let mut higher = key.clone();
+                higher.insert(position, values[1].clone());
+                pairs.push((lower, higher));
+            }
+        }
+        pairs
+    }
+
+    /// The position of `parent` among the parents of `child`.
+    pub(crate) fn edge_position(&self, parent: &str, child: &str) -> usize {
+        let parent_id = match self.node_map.get(parent) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", parent),
+        };
+        let child_id = match self.node_map.get(child) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", child),
+        };
+        match self.nodes[child_id]
+            .parents
+            .iter()
+            .position(|id| *id == parent_id)
+        {
+            Some(position) => position,
+            None => panic!("Node `{}` is not a parent of `{}`", parent, child),
+        }
+    }
+}
+
+/// The non-decreasing sequence closest to `values` in least squares, by pooling adjacent
+/// violators.
+fn isotonic(values: &[Probability]) -> Vec<Probability> {
+    // Blocks of (mean, size).
+    let mut blocks: Vec<(Probability, usize)> = Vec::new();
+    for value in values {
+        blocks.push((*value, 1));
+        while blocks.len() > 1 && blocks[blocks.len() - 2].0 > blocks[blocks.len() - 1].0 {
+            let (mean, size) = blocks.pop().unwrap();
+            let last = blocks.last_mut().unwrap();
+            last.0 = (last.0 * last.1 as Probability + mean * size as Probability)
+                / (last.1 + size) as Probability;
+            last.1 += size;
+        }
+    }
+    blocks
+        .into_iter()
+        .flat_map(|(mean, size)| std::iter::repeat_n(mean, size))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        bayesian_network::NodeType,
+        testing::{assert_close, sprinkler, TOLERANCE},
+    };
+
+    #[test]
+    fn influences_are_recorded_on_the_child() {
+        let mut network = sprinkler();
+        network.set_influence("rain", "wet_grass", Influence::Positive);
+        network.set_influence("cloudy", "sprinkler", Influence::Negative);
+        assert_eq!(
+            network.get_influence("rain", "wet_grass"),
+            Some(Influence::Positive)
+        );
+        assert_eq!(
+            network.get_influence("cloudy", "sprinkler"),
+            Some(Influence::Negative)
+        );
+        assert_eq!(network.get_influence("sprinkler", "wet_grass"), None);
+    }
+
+    #[test]
+    fn check_reports_only_the_violated_influences() {
+        let mut network = sprinkler();
+        // Rain makes wet grass more likely whatever the sprinkler, and clouds make the
+        // sprinkler less likely.
+        network.set_influence("rain", "wet_grass", Influence::Positive);
+        network.set_influence("sprinkler", "wet_grass", Influence::Positive);
+        network.set_influence("cloudy", "sprinkler", Influence::Negative);
+        assert!(network.check_influences().is_empty());
+
+        network.set_influence("cloudy", "sprinkler", Influence::Positive);
+        let diagnostics = network.check_influences();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].check, "influence");
+        assert_eq!(diagnostics[0].node.as_deref(), Some("sprinkler"));
+        assert!(diagnostics[0]
+            .message
+            .starts_with("Positive influence of `cloudy` violated"));
+    }
+
+    #[test]
+    fn projection_pools_the_violating_rows() {
+        let mut network = sprinkler();
+        network.set_influence("cloudy", "sprinkler", Influence::Positive);
+        network.set_influence("rain", "wet_grass", Influence::Positive);
+        let wet_grass = network.nodes[network.get_node_index("wet_grass")]
+            .probability
+            .clone();
+        network.project_influences();
+        assert!(network.check_influences().is_empty());
+        // The rows given clouds and no clouds, .1 and .5, meet at their mean.
+        let sprinkler = &network.nodes[network.get_node_index("sprinkler")].probability;
+        for cloudy in [true, false] {
+            assert_close(sprinkler[&vec![cloudy]][&true], 0.3, TOLERANCE);
+            assert_close(sprinkler[&vec![cloudy]][&false], 0.7, TOLERANCE);
+        }
+        // Rows already respecting their influence are left alone.
+        assert_eq!(
+            network.nodes[network.get_node_index("wet_grass")].probability,
+            wet_grass
+        );
+    }
+
+    #[test]
+    fn projection_follows_the_state_order() {
+        // A three-valued level raising the chance of an alarm only from 0 to 1: the rows
+        // given 1 and 2 are pooled and the row given 0 is kept.
+        let mut network = BayesianNetwork::new(vec![0u8, 1, 2]);
+        network.add_node(
+            "level",
+            NodeType::Root(HashMap::from([(0, 0.3), (1, 0.3), (2, 0.4)])),
+        );
+        network.add_node("alarm", NodeType::Leaf);
+        network.set_node_states("alarm", vec![1, 0]);
+        network.add_dependency(
+            vec!["level"],
+            "alarm",
+            HashMap::from([
+                (vec![0], HashMap::from([(1, 0.1), (0, 0.9)])),
+                (vec![1], HashMap::from([(1, 0.5), (0, 0.5)])),
+                (vec![2], HashMap::from([(1, 0.3), (0, 0.7)])),
+            ]),
+        );
+        network.set_influence("level", "alarm", Influence::Negative);
+        assert_eq!(network.check_influences().len(), 1);
+        network.project_influences();
+        assert!(network.check_influences().is_empty());
+        let alarm = &network.nodes[network.get_node_index("alarm")].probability;
+        for (level, expected) in [(0, 0.1), (1, 0.4), (2, 0.4)] {
+            assert_close(alarm[&vec![level]][&1], expected, TOLERANCE);
+            assert_close(alarm[&vec![level]][&0], 1.0 - expected, TOLERANCE);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Node `wet_grass` is not a parent of `cloudy`")]
+    fn influences_need_an_edge() {
+        sprinkler().set_influence("wet_grass", "cloudy", Influence::Positive);
+    }
+}
@@ -0,0 +1,412 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability},
+    learning::configurations,
+};
+
+/// The kind of a node of an influence diagram.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    /// A random variable, with a prior or a CPT like a node of a Bayesian network.
+    Chance,
+    /// A variable set by the decision maker to one of its actions.
+    Decision,
+    /// A utility table over its parents, added up into the total utility.
+    Utility,
+}
+
+pub(crate) struct DecisionNode<T> {
+    pub(crate) name: Name,
+    pub(crate) actions: Vec<T>,
+    /// The nodes observed before the decision is made.
+    pub(crate) information: Vec<Name>,
+}
+
+pub(crate) struct UtilityNode<T> {
+    pub(crate) name: Name,
+    pub(crate) parents: Vec<Name>,
+    pub(crate) utility: HashMap<Vec<T>, f64>,
+}
+
+/// An influence diagram: a Bayesian network of chance nodes extended with decision nodes,
+/// which the decision maker sets, and utility nodes, which score the outcome.
+pub struct InfluenceDiagram<T: Clone + PartialEq + Eq + Hash + Debug> {
+    /// The chance nodes, with each decision as a root node uniform over its actions.
+    pub(crate) network: BayesianNetwork<T>,
+    /// The decisions, in the order they are made.
+    pub(crate) decisions: Vec<DecisionNode<T>>,
+    pub(crate) utilities: Vec<UtilityNode<T>>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> InfluenceDiagram<T> {
+    /// Create an empty influence diagram.
+    pub fn new(value_space: Vec<T>) -> InfluenceDiagram<T> {
+        InfluenceDiagram {
+            network: BayesianNetwork::new(value_space),
+            decisions: Vec::new(),
+            utilities: Vec::new(),
+        }
+    }
+
+    /// Add a chance node to the diagram.
+    pub fn add_chance_node(&mut self, name: &str, node_type: NodeType<T>) {
+        self.check_new_name(name);
+        self.network.add_node(name, node_type);
+    }
+
+    /// Add the CPT of a chance node given its parents, which may be chance or decision nodes.
+    pub fn add_dependency(
+        &mut self,
+        parent_names: Vec<&str>,
+        child_name: &str,
+        prob: HashMap<Vec<T>, HashMap<T, Probability>>,
+    ) {
+        if self.get_node_kind(child_name) != NodeKind::Chance {
+            panic!("Dependency child `{}` is not a chance node", child_name);
+        }
+        for parent_name in &parent_names {
+            if self.get_node_kind(parent_name) == NodeKind::Utility {
+                panic!("Dependency parent `{}` is a utility node", parent_name);
+            }
+        }
+        self.network.add_dependency(parent_names, child_name, prob);
+    }
+
+    /// Add a decision node that takes one of `actions`, made after observing the chance and
+    /// decision nodes of `information`. Decisions are made in the order they are added.
+    pub fn add_decision_node(&mut self, name: &str, actions: Vec<T>, information: Vec<&str>) {
+        self.check_new_name(name);
+        if actions.is_empty() {
+            panic!("Decision node `{}` has no actions", name);
+        }
+        for action in &actions {
+            if !self.network.value_space.contains(action) {
+                panic!(
+                    "Decision node `{}` action {:?} not found in value space",
+                    name, action
+                );
+            }
+        }
+        for observed in &information {
+            if self.get_node_kind(observed) == NodeKind::Utility {
+                panic!(
+                    "Decision node `{}` cannot observe utility node `{}`",
+                    name, observed
+                );
+            }
+        }
+        let uniform = 1.0 / actions.len() as Probability;
+        self.network.add_node(
+            name,
+            NodeType::Root(
+                actions
+                    .iter()
+                    .map(|action| (action.clone(), uniform))
+                    .collect(),
+            ),
+        );
+        self.network.set_node_states(name, actions.clone());
+        self.decisions.push(DecisionNode {
+            name: name.to_string(),
+            actions,
+            information: information.iter().map(|name| name.to_string()).collect(),
+        });
+    }
+
+    /// Add a utility node scoring each configuration of its chance and decision `parents`.
+    /// Configurations missing from `utility` are worth 0.
+    pub fn add_utility_node(
+        &mut self,
+        name: &str,
+        parents: Vec<&str>,
+        utility: HashMap<Vec<T>, f64>,
+    ) {
+        self.check_new_name(name);
+        for parent in &parents {
+            if self.get_node_kind(parent) == NodeKind::Utility {
+                panic!(
+                    "Utility node `{}` cannot depend on utility node `{}`",
+                    name, parent
+                );
+            }
+        }
+        for key in utility.keys() {
+            if key.len() != parents.len() {
+                panic!(
+                    "Utility key length does not match parent length ({} and {}, {:?} -> {})",
+                    key.len(),
+                    parents.len(),
+                    parents,
+                    name
+                );
+            }
+        }
+        self.utilities.push(UtilityNode {
+            name: name.to_string(),
+            parents: parents.iter().map(|parent| parent.to_string()).collect(),
+            utility,
+        });
+    }
+
+    /// Get the kind of a node.
+    pub fn get_node_kind(&self, name: &str) -> NodeKind {
+        if self.decisions.iter().any(|decision| decision.name == name) {
+            NodeKind::Decision
+        } else if self.utilities.iter().any(|utility| utility.name == name) {
+            NodeKind::Utility
+        } else if self.network.node_map.contains_key(name) {
+            NodeKind::Chance
+        } else {
+            panic!("Node `{}` not found", name)
+        }
+    }
+
+    /// Get the network of the chance nodes, in which each decision is a root node uniform
+    /// over its actions.
+    pub fn network(&self) -> &BayesianNetwork<T> {
+        &self.network
+    }
+
+    /// Get the decision nodes, in the order they are made.
+    pub fn decision_nodes(&self) -> Vec<&str> {
+        self.decisions
+            .iter()
+            .map(|decision| decision.name.as_str())
+            .collect()
+    }
+
+    /// Get the utility nodes, in the order they were added.
+    pub fn utility_nodes(&self) -> Vec<&str> {
+        self.utilities
+            .iter()
+            .map(|utility| utility.name.as_str())
+            .collect()
+    }
+
+    /// Get the actions of a decision node.
+    pub fn get_actions(&self, decision: &str) -> &[T] {
+        &self.decision(decision).actions
+    }
+
+    /// Get the nodes observed before a decision is made.
+    pub fn get_information(&self, decision: &str) -> Vec<&str> {
+        self.decision(decision)
+            .information
+            .iter()
+            .map(|name| name.as_str())
+            .collect()
+    }
+
+    /// Get the nodes known when a decision is made, under the no-forgetting assumption: the
+    /// nodes it observes, every earlier decision and every node an earlier decision observed.
+    pub fn get_no_forgetting_information(&self, decision: &str) -> Vec<&str> {
+        let index = match self.decisions.iter().position(|node| node.name == decision) {
+            Some(index) => index,
+            None => panic!("Decision node `{}` not found", decision),
+        };
+        self.no_forgetting(index)
+            .into_iter()
+            .map(|id| self.network.get_node_name(id))
+            .collect()
+    }
+
+    /// Get the expected total utility of every combination of actions, decided in advance
+    /// without observing anything but `evidence`, the best first. Combinations under which
+    /// the evidence cannot occur are left out.
+    pub fn expected_utilities(&self, evidence: &HashMap<&str, T>) -> Vec<(HashMap<Name, T>, f64)> {
+        let mut fixed = self.evidence_ids(evidence);
+        let actions = self
+            .decisions
+            .iter()
+            .map(|decision| decision.actions.clone())
+            .collect::<Vec<Vec<T>>>();
+        let mut utilities = configurations(&actions)
+            .into_iter()
+            .filter_map(|combination| {
+                for (decision, action) in self.decisions.iter().zip(&combination) {
+                    fixed.insert(self.network.get_node_index(&decision.name), action.clone());
+                }
+                let choice = self
+                    .decisions
+                    .iter()
+                    .zip(combination)
+                    .map(|(decision, action)| (decision.name.clone(), action))
+                    .collect::<HashMap<Name, T>>();
+                Some((choice, self.utility_given(&self.network, &fixed)?))
+            })
+            .collect::<Vec<(HashMap<Name, T>, f64)>>();
+        utilities.sort_by(|a, b| b.1.total_cmp(&a.1));
+        utilities
+    }
+
+    /// The expected total utility under `network` given that the nodes of `fixed` take
+    /// their values, if they can.
+    pub(crate) fn utility_given(
+        &self,
+        network: &BayesianNetwork<T>,
+        fixed: &HashMap<NodeId, T>,
+    ) -> Option<f64> {
+        let total = network.assignment_probability(fixed);
+        if total <= 0.0 {
+            return None;
+        }
+        let mut expected = 0.0;
+        for utility in &self.utilities {
+            let ids = utility
+                .parents
+                .iter()
+                .map(|parent| network.get_node_index(parent))
+                .collect::<Vec<NodeId>>();
+            let domains = ids
+                .iter()
+                .map(|id| match fixed.get(id) {
+                    Some(value) => vec![value.clone()],
+                    None => network.get_node_domain(*id),
+                })
+                .collect::<Vec<Vec<T>>>();
+            for key in configurations(&domains) {
+                let value = match utility.utility.get(&key) {
+                    Some(value) => *value,
+                    None => continue,
+                };
+                let mut assignment = fixed.clone();
+                assignment.extend(ids.iter().copied().zip(key));
+                expected += value * network.assignment_probability(&assignment) / total;
+            }
+        }
+        Some(expected)
+    }
+
+    /// The nodes known when the decision at `index` is made under no-forgetting, in node
+    /// order.
+    pub(crate) fn no_forgetting(&self, index: usize) -> Vec<NodeId> {
+        let mut ids = Vec::new();
+        for (earlier, decision) in self.decisions[..=index].iter().enumerate() {
+            if earlier < index {
+                ids.push(self.network.get_node_index(&decision.name));
+            }
+            ids.extend(
+                decision
+                    .information
+                    .iter()
+                    .map(|name| self.network.get_node_index(name)),
+            );
+        }
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Check that the decisions can be made in order: no decision influences an earlier
+    /// decision or a node known before it is made.
+    pub(crate) fn check_decision_order(&self) {
+        for (index, decision) in self.decisions.iter().enumerate() {
+            let descendants = self
+                .network
+                .descendants(&[self.network.get_node_index(&decision.name)]);
+            for id in self.no_forgetting(index) {
+                if descendants.contains(&id) {
+                    panic!(
+                        "Decision node `{}` influences `{}`, which is known before it is made",
+                        decision.name,
+                        self.network.get_node_name(id)
+                    );
+                }
+            }
+        }
+    }
+
+    /// The node index of each node of `evidence`, which must be chance nodes.
+    pub(crate) fn evidence_ids(&self, evidence: &HashMap<&str, T>) -> HashMap<NodeId, T> {
+        evidence
+            .iter()
+            .map(|(name, value)| {
+                if self.get_node_kind(name) != NodeKind::Chance {
+                    panic!("Evidence node `{}` is not a chance node", name);
+                }
+                (self.network.get_node_index(name), value.clone())
+            })
+            .collect()
+    }
+
+    pub(crate) fn decision(&self, name: &str) -> &DecisionNode<T> {
+        match self.decisions.iter().find(|decision| decision.name == name) {
+            Some(decision) => decision,
+            None => panic!("Decision node `{}` not found", name),
+        }
+    }
+
+    fn check_new_name(&self, name: &str) {
+        if self.network.node_map.contains_key(name)
+            || self.utilities.iter().any(|utility| utility.name == name)
+        {
+            panic!("Node `{}` already exists", name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, oil_wildcatter, TOLERANCE};
+
+    /// The expected utilities as (test action, drill action, utility), in order.
+    fn flatten(utilities: Vec<(HashMap<Name, &str>, f64)>) -> Vec<(&str, &str, f64)> {
+        utilities
+            .into_iter()
+            .map(|(choice, utility)| (choice["test"], choice["drill"], utility))
+            .collect()
+    }
+
+    #[test]
+    fn nodes_have_their_kinds_and_information() {
+        let diagram = oil_wildcatter();
+        assert_eq!(diagram.get_node_kind("oil"), NodeKind::Chance);
+        assert_eq!(diagram.get_node_kind("drill"), NodeKind::Decision);
+        assert_eq!(diagram.get_node_kind("payoff"), NodeKind::Utility);
+        assert_eq!(diagram.decision_nodes(), ["test", "drill"]);
+        assert_eq!(diagram.utility_nodes(), ["cost", "payoff"]);
+        assert_eq!(diagram.get_information("drill"), ["seismic"]);
+        assert_eq!(
+            diagram.get_no_forgetting_information("drill"),
+            ["test", "seismic"]
+        );
+        assert!(diagram.get_no_forgetting_information("test").is_empty());
+    }
+
+    #[test]
+    fn expected_utilities_rank_every_combination_of_actions() {
+        let diagram = oil_wildcatter();
+        let expected = [
+            ("no_test", "drill", 0.5 * -70.0 + 0.3 * 50.0 + 0.2 * 200.0),
+            ("test", "drill", 10.0),
+            ("no_test", "no_drill", 0.0),
+            ("test", "no_drill", -10.0),
+        ];
+        let utilities = flatten(diagram.expected_utilities(&HashMap::new()));
+        assert_eq!(utilities.len(), expected.len());
+        for (actual, expected) in utilities.iter().zip(expected) {
+            assert_eq!((actual.0, actual.1), (expected.0, expected.1));
+            assert_close(actual.2, expected.2, TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn expected_utilities_condition_on_the_evidence() {
+        let diagram = oil_wildcatter();
+        // A closed result needs a test, and makes the oil dry, wet or soaking with
+        // probabilities 0.05, 0.09 and 0.1 over 0.24.
+        let utilities =
+            flatten(diagram.expected_utilities(&HashMap::from([("seismic", "closed")])));
+        assert_eq!(utilities.len(), 2);
+        assert_eq!((utilities[0].0, utilities[0].1), ("test", "drill"));
+        assert_close(
+            utilities[0].2,
+            (0.05 * -70.0 + 0.09 * 50.0 + 0.1 * 200.0) / 0.24 - 10.0,
+            TOLERANCE,
+        );
+        assert_eq!((utilities[1].0, utilities[1].1), ("test", "no_drill"));
+        assert_close(utilities[1].2, -10.0, TOLERANCE);
+    }
+}
@@ -0,0 +1,136 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Probability};
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the entropy of the posterior of a node given `evidence`, in nats.
+    pub fn entropy(&self, name: &str, evidence: &HashMap<&str, T>) -> f64 {
+        entropy(&self.node_distribution(name, evidence))
+    }
+
+    /// Get the expected entropy of the posterior of `name` once `given` is observed as well
+    /// as `evidence`, in nats. It is the entropy given the evidence alone if `given` is
+    /// already observed.
+    pub fn conditional_entropy(&self, name: &str, given: &str, evidence: &HashMap<&str, T>) -> f64 {
+        if evidence.contains_key(given) {
+            return self.entropy(name, evidence);
+        }
+        self.outcomes(given, evidence)
+            .into_iter()
+            .map(|(prob, evidence)| prob * self.entropy(name, &evidence))
+            .sum()
+    }
+
+    /// Get the mutual information between two nodes under their posterior given `evidence`,
+    /// in nats: how much observing one is expected to reduce the entropy of the other.
+    pub fn mutual_information(&self, a: &str, b: &str, evidence: &HashMap<&str, T>) -> f64 {
+        (self.entropy(b, evidence) - self.conditional_entropy(b, a, evidence)).max(0.0)
+    }
+
+    /// The posterior distribution of a node given `evidence`.
+    pub(crate) fn node_distribution(
+        &self,
+        name: &str,
+        evidence: &HashMap<&str, T>,
+    ) -> Vec<(T, Probability)> {
+        self.posterior(evidence)
+            .select(&[name])
+            .distribution(name)
+            .unwrap()
+            .to_vec()
+    }
+
+    /// Each value `name` can take given `evidence`, as its probability and the evidence
+    /// extended with it.
+    pub(crate) fn outcomes<'a>(
+        &self,
+        name: &'a str,
+        evidence: &HashMap<&'a str, T>,
+    ) -> Vec<(Probability, HashMap<&'a str, T>)> {
+        self.node_distribution(name, evidence)
+            .into_iter()
+            .filter(|(_, prob)| *prob > 0.0)
+            .map(|(value, prob)| {
+                let mut evidence = evidence.clone();
+                evidence.insert(name, value);
+                (prob, evidence)
+            })
+            .collect()
+    }
+}
+
+/// The entropy of a distribution, in nats.
+pub(crate) fn entropy<T>(distribution: &[(T, Probability)]) -> f64 {
+    -distribution
+        .iter()
+        .filter(|(_, prob)| *prob > 0.0)
+        .map(|(_, prob)| prob * prob.ln())
+        .sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        assert_close, bonus, brute_force_probability, evidence_probability, TOLERANCE,
+    };
+
+    fn binary_entropy(p: f64) -> f64 {
+        entropy(&[(true, p), (false, 1.0 - p)])
+    }
+
+    #[test]
+    fn entropy_of_a_posterior_matches_enumeration() {
+        let network = bonus();
+        assert_close(
+            network.entropy("performance", &HashMap::new()),
+            -(0.01 * 0.01_f64.ln() + 0.99 * 0.99_f64.ln()),
+            TOLERANCE,
+        );
+        let evidence = HashMap::from([("bonus", true)]);
+        assert_close(
+            network.entropy("mood", &evidence),
+            binary_entropy(brute_force_probability(&network, "mood", &true, &evidence)),
+            TOLERANCE,
+        );
+        assert_eq!(entropy(&[(true, 1.0), (false, 0.0)]), 0.0);
+    }
+
+    #[test]
+    fn conditional_entropy_averages_over_the_observation() {
+        let network = bonus();
+        let evidence = HashMap::new();
+        let mut expected = 0.0;
+        for mood in [true, false] {
+            let observed = HashMap::from([("mood", mood)]);
+            expected += evidence_probability(&network, &observed)
+                * binary_entropy(brute_force_probability(&network, "bonus", &true, &observed));
+        }
+        assert_close(
+            network.conditional_entropy("bonus", "mood", &evidence),
+            expected,
+            TOLERANCE,
+        );
+        let observed = HashMap::from([("mood", true)]);
+        assert_eq!(
+            network.conditional_entropy("bonus", "mood", &observed),
+            network.entropy("bonus", &observed)
+        );
+    }
+
+    #[test]
+    fn mutual_information_is_symmetric() {
+        let network = bonus();
+        let evidence = HashMap::from([("feast", true)]);
+        let forward = network.mutual_information("performance", "horse_race", &evidence);
+        let backward = network.mutual_information("horse_race", "performance", &evidence);
+        assert_close(forward, backward, TOLERANCE);
+        // Observing a common effect makes the two causes dependent.
+        assert!(forward > 0.0);
+        assert_close(
+            network.mutual_information("performance", "horse_race", &HashMap::new()),
+            0.0,
+            TOLERANCE,
+        );
+    }
+}
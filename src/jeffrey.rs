@@ -0,0 +1,139 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Probability},
+    posterior::Posterior,
+};
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Infer the posterior of every node given `evidence` and an observation of `name` that
+    /// leaves it distributed as `target`, by Jeffrey's rule: the posteriors given each state
+    /// of the node are mixed with the target probabilities. The node posterior is then the
+    /// target, and states missing from it have probability 0.
+    pub fn jeffrey_posterior(
+        &self,
+        evidence: &HashMap<&str, T>,
+        name: &str,
+        target: &HashMap<T, Probability>,
+    ) -> Posterior<T> {
+        if !self.node_map.contains_key(name) {
+            panic!("Node `{}` not found", name);
+        }
+        if evidence.contains_key(name) {
+            panic!("Node `{}` is already observed", name);
+        }
+        if target.values().any(|prob| !(0.0..=1.0).contains(prob)) {
+            panic!(
+                "Target distribution of `{}` has a probability outside 0 and 1",
+                name
+            );
+        }
+        let sum = target.values().sum::<Probability>();
+        if (sum - 1.0).abs() > 0.0000001 {
+            panic!(
+                "Target distribution of `{}` sums to {} instead of 1.0",
+                name, sum
+            );
+        }
+
+        let prior = self.posterior(evidence);
+        let mut mixed: Option<Posterior<T>> = None;
+        for (value, weight) in target {
+            if *weight <= 0.0 {
+                continue;
+            }
+            if prior.probability(name, value).unwrap_or(0.0) <= 0.0 {
+                panic!("Evidence has zero probability");
+            }
+            let mut evidence = evidence.clone();
+            evidence.insert(name, value.clone());
+            let posterior = self.posterior(&evidence);
+            match &mut mixed {
+                None => {
+                    let mut posterior = posterior;
+                    for (_, states) in &mut posterior.marginals {
+                        for (_, prob) in states {
+                            *prob *= weight;
+                        }
+                    }
+                    mixed = Some(posterior);
+                }
+                Some(mixed) => {
+                    for ((_, total), (_, states)) in
+                        mixed.marginals.iter_mut().zip(posterior.marginals)
+                    {
+                        for ((_, total), (_, prob)) in total.iter_mut().zip(states) {
+                            *total += weight * prob;
+                        }
+                    }
+                }
+            }
+        }
+        match mixed {
+            Some(mixed) => mixed,
+            None => panic!("Target distribution of `{}` is empty", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_probability, TOLERANCE};
+
+    #[test]
+    fn node_posterior_is_the_target() {
+        let network = bonus();
+        let evidence = HashMap::from([("horse_race", false)]);
+        let target = HashMap::from([(true, 0.7), (false, 0.3)]);
+        let posterior = network.jeffrey_posterior(&evidence, "mood", &target);
+        assert_close(
+            posterior.probability("mood", &true).unwrap(),
+            0.7,
+            TOLERANCE,
+        );
+        assert_close(
+            posterior.probability("mood", &false).unwrap(),
+            0.3,
+            TOLERANCE,
+        );
+        // A certain target is ordinary conditioning.
+        let certain = network.jeffrey_posterior(&evidence, "mood", &HashMap::from([(true, 1.0)]));
+        assert_close(certain.probability("mood", &false).unwrap(), 0.0, TOLERANCE);
+    }
+
+    #[test]
+    fn other_nodes_mix_the_conditional_posteriors() {
+        let network = bonus();
+        let evidence = HashMap::from([("horse_race", false)]);
+        let target = HashMap::from([(true, 0.7), (false, 0.3)]);
+        let posterior = network.jeffrey_posterior(&evidence, "mood", &target);
+        for name in ["performance", "bonus", "feast"] {
+            for value in [true, false] {
+                let expected = [(true, 0.7), (false, 0.3)]
+                    .iter()
+                    .map(|(mood, weight)| {
+                        let mut evidence = evidence.clone();
+                        evidence.insert("mood", *mood);
+                        weight * brute_force_probability(&network, name, &value, &evidence)
+                    })
+                    .sum::<Probability>();
+                assert_close(
+                    posterior.probability(name, &value).unwrap(),
+                    expected,
+                    TOLERANCE,
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Target distribution of `mood` sums to 0.5 instead of 1.0")]
+    fn target_must_be_normalized() {
+        bonus().jeffrey_posterior(
+            &HashMap::new(),
+            "mood",
+            &HashMap::from([(true, 0.25), (false, 0.25)]),
+        );
+    }
+}
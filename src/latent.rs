@@ -0,0 +1,647 @@
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability},
+    dataset::Dataset,
+    error::Error,
+    learning::{configurations, Edge},
+};
+
+const FORMAT: &str = "ADMG";
+
+/// `fit_em` stops once an iteration improves the log-likelihood by less than this.
+const EM_TOLERANCE: f64 = 1e-9;
+
+/// A CPT, keyed by parent values.
+type Rows<T> = HashMap<Vec<T>, HashMap<T, Probability>>;
+
+/// The name of the hidden node standing for a latent common cause of `a` and `b`, as added by
+/// `add_latent_confounder`.
+pub fn confounder_name(a: &str, b: &str) -> Name {
+    format!("U_{}_{}", a, b)
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Add a hidden root node taking `states` uniformly, as a new last parent of each of
+    /// `children`. The CPT of each child keeps its rows for every value of the new parent
+    /// (its prior, if it was a root), so the joint distribution of the other nodes is
+    /// unchanged until the CPTs are learned with `fit_em`.
+    pub fn add_latent_node(&mut self, name: &str, children: &[&str], states: Vec<T>) {
+        let child_ids = children
+            .iter()
+            .map(|child| match self.node_map.get(*child) {
+                Some(id) => *id,
+                None => panic!("Node `{}` not found", child),
+            })
+            .collect::<Vec<NodeId>>();
+        let prior = self
+            .value_space
+            .iter()
+            .map(|value| {
+                let prob = if states.contains(value) {
+                    1.0 / states.len() as Probability
+                } else {
+                    0.0
+                };
+                (value.clone(), prob)
+            })
+            .collect::<HashMap<T, Probability>>();
+        self.add_node(name, NodeType::Root(prior));
+        self.set_node_states(name, states.clone());
+        self.set_node_hidden(name, true);
+        let latent = self.node_map[name];
+        for child in child_ids {
+            let rows = match &self.nodes[child].node_type {
+                NodeType::Root(prior) => HashMap::from([(Vec::new(), prior.clone())]),
+                _ => self.get_cpt(child).into_owned(),
+            };
+            let node = &mut self.nodes[child];
+            if let NodeType::Root(_) = node.node_type {
+                node.node_type = if node.children.is_empty() {
+                    NodeType::Leaf
+                } else {
+                    NodeType::Inner
+                };
+            }
+            node.probability = rows
+                .into_iter()
+                .flat_map(|(key, row)| {
+                    states.iter().map(move |state| {
+                        let mut key = key.clone();
+                        key.push(state.clone());
+                        (key, row.clone())
+                    })
+                })
+                .collect();
+            node.parents.push(latent);
+            node.compact_cpt = None;
+            node.shared_cpt = None;
+            node.dirichlet = None;
+            self.nodes[latent].children.push(child);
+        }
+    }
+
+    /// Add a latent common cause of `a` and `b`, the bidirected edge `a <-> b`, as a hidden
+    /// node named by `confounder_name` and added by `add_latent_node`. Returns its name.
+    pub fn add_latent_confounder(&mut self, a: &str, b: &str, states: Vec<T>) -> Name {
+        let name = confounder_name(a, b);
+        self.add_latent_node(&name, &[a, b], states);
+        name
+    }
+
+    /// Get the bidirected edges of the network: the pairs of observed nodes, in node order,
+    /// that are the only children of a hidden root.
+    pub fn bidirected_edges(&self) -> Vec<Edge> {
+        self.latent_confounders()
+            .into_iter()
+            .map(|id| {
+                let children = &self.nodes[id].children;
+                let (a, b) = (children[0].min(children[1]), children[0].max(children[1]));
+                (
+                    self.get_node_name(a).to_string(),
+                    self.get_node_name(b).to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// The hidden roots with exactly two children, both observed, written as bidirected edges.
+    fn latent_confounders(&self) -> Vec<NodeId> {
+        self.nodes
+            .iter()
+            .filter(|node| {
+                matches!(node.node_type, NodeType::Root(_))
+                    && self.is_node_hidden(self.get_node_name(node.id))
+                    && node.children.len() == 2
+                    && node
+                        .children
+                        .iter()
+                        .all(|child| !self.is_node_hidden(self.get_node_name(*child)))
+            })
+            .map(|node| node.id)
+            .collect()
+    }
+
+    /// Estimate every CPT like `fit`, by expectation maximization so that hidden nodes may
+    /// have no column in `dataset`: their values are summed out of each record under the
+    /// current CPTs. The CPTs of families with a hidden node start from random rows, and the
+    /// algorithm runs at most `iterations` rounds, stopping early once the log-likelihood
+    /// stops improving. Returns the log-likelihood of the dataset at the last round. The cost
+    /// of each round grows exponentially with the number of hidden nodes without a column.
+    pub fn fit_em(
+        &mut self,
+        dataset: &Dataset<T>,
+        pseudo_count: Probability,
+        iterations: usize,
+    ) -> f64 {
+        self.fit_em_with_rng(dataset, pseudo_count, iterations, &mut rand::thread_rng())
+    }
+
+    /// Estimate every CPT like `fit_em`, drawing the initial rows with `rng`.
+    pub fn fit_em_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        dataset: &Dataset<T>,
+        pseudo_count: Probability,
+        iterations: usize,
+        rng: &mut R,
+    ) -> f64 {
+        let columns = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let name = self.get_node_name(node.id);
+                match dataset.column_index(name) {
+                    Some(column) => Some(column),
+                    None if self.is_node_hidden(name) => None,
+                    None => panic!("Dataset column `{}` not found", name),
+                }
+            })
+            .collect::<Vec<Option<usize>>>();
+        for row in dataset.rows() {
+            for value in row {
+                if !self.value_space.contains(value) {
+                    panic!("Dataset value {:?} not found in value space", value);
+                }
+            }
+        }
+        let domains = self
+            .nodes
+            .iter()
+            .map(|node| match (&node.states, columns[node.id]) {
+                (Some(states), _) => states.clone(),
+                (None, Some(column)) => self
+                    .value_space
+                    .iter()
+                    .filter(|value| dataset.rows().iter().any(|row| row[column] == **value))
+                    .cloned()
+                    .collect(),
+                (None, None) => self.get_node_domain(node.id),
+            })
+            .collect::<Vec<Vec<T>>>();
+        let hidden = (0..self.nodes.len())
+            .filter(|id| columns[*id].is_none())
+            .collect::<Vec<NodeId>>();
+        let hidden_configurations = configurations(
+            &hidden
+                .iter()
+                .map(|id| domains[*id].clone())
+                .collect::<Vec<Vec<T>>>(),
+        );
+        let mut records: HashMap<&[T], f64> = HashMap::new();
+        for row in dataset.rows() {
+            *records.entry(row).or_insert(0.0) += 1.0;
+        }
+
+        // Nodes tied to the same shared CPT pool their expected counts.
+        let group_of = |id: NodeId| match &self.nodes[id].shared_cpt {
+            Some(name) => name.clone(),
+            None => self.get_node_name(id).to_string(),
+        };
+        let groups = (0..self.nodes.len()).map(group_of).collect::<Vec<String>>();
+        let keys = self
+            .nodes
+            .iter()
+            .map(|node| {
+                configurations(
+                    &node
+                        .parents
+                        .iter()
+                        .map(|parent| domains[*parent].clone())
+                        .collect::<Vec<Vec<T>>>(),
+                )
+            })
+            .collect::<Vec<Vec<Vec<T>>>>();
+        let estimate = |counts: &HashMap<String, Rows<T>>| {
+            let mut estimates: HashMap<String, Rows<T>> = HashMap::new();
+            for id in 0..self.nodes.len() {
+                if estimates.contains_key(&groups[id]) {
+                    continue;
+                }
+                let group_counts = counts.get(&groups[id]);
+                let mut rows = HashMap::new();
+                for key in &keys[id] {
+                    let row_counts = group_counts.and_then(|counts| counts.get(key));
+                    let count = |value: &T| {
+                        row_counts
+                            .and_then(|counts| counts.get(value))
+                            .copied()
+                            .unwrap_or(0.0)
+                            + pseudo_count
+                    };
+                    let total = domains[id].iter().map(count).sum::<Probability>();
+                    let row = self
+                        .value_space
+                        .iter()
+                        .map(|value| {
+                            let prob = if !domains[id].contains(value) {
+                                0.0
+                            } else if total > 0.0 {
+                                count(value) / total
+                            } else {
+                                1.0 / domains[id].len() as Probability
+                            };
+                            (value.clone(), prob)
+                        })
+                        .collect::<HashMap<T, Probability>>();
+                    rows.insert(key.clone(), row);
+                }
+                estimates.insert(groups[id].clone(), rows);
+            }
+            estimates
+        };
+
+        // Families without hidden nodes start from their counts, the others from random rows.
+        let mut counts: HashMap<String, Rows<T>> = HashMap::new();
+        for (row, count) in &records {
+            for node in &self.nodes {
+                let family = node.parents.iter().chain(std::iter::once(&node.id));
+                if family.clone().all(|id| columns[*id].is_some()) {
+                    let key = node
+                        .parents
+                        .iter()
+                        .map(|parent| row[columns[*parent].unwrap()].clone())
+                        .collect::<Vec<T>>();
+                    *counts
+                        .entry(groups[node.id].clone())
+                        .or_default()
+                        .entry(key)
+                        .or_default()
+                        .entry(row[columns[node.id].unwrap()].clone())
+                        .or_insert(0.0) += count;
+                }
+            }
+        }
+        for node in &self.nodes {
+            let family = node.parents.iter().chain(std::iter::once(&node.id));
+            if family.clone().any(|id| columns[*id].is_none()) {
+                let rows = counts.entry(groups[node.id].clone()).or_default();
+                for key in &keys[node.id] {
+                    let row = rows.entry(key.clone()).or_default();
+                    for value in &domains[node.id] {
+                        row.insert(value.clone(), rng.gen_range(0.5..1.5));
+                    }
+                }
+            }
+        }
+        let mut estimates = estimate(&counts);
+
+        let mut log_likelihood = f64::NEG_INFINITY;
+        for _ in 0..iterations {
+            let mut counts: HashMap<String, Rows<T>> = HashMap::new();
+            let mut current = 0.0;
+            for (row, count) in &records {
+                let mut assignment = (0..self.nodes.len())
+                    .filter_map(|id| columns[id].map(|column| (id, row[column].clone())))
+                    .collect::<HashMap<NodeId, T>>();
+                let mut weights = Vec::with_capacity(hidden_configurations.len());
+                for configuration in &hidden_configurations {
+                    for (id, value) in hidden.iter().zip(configuration) {
+                        assignment.insert(*id, value.clone());
+                    }
+                    let weight = self
+                        .nodes
+                        .iter()
+                        .map(|node| {
+                            let key = node
+                                .parents
+                                .iter()
+                                .map(|parent| assignment[parent].clone())
+                                .collect::<Vec<T>>();
+                            estimates[&groups[node.id]]
+                                .get(&key)
+                                .and_then(|row| row.get(&assignment[&node.id]))
+                                .copied()
+                                .unwrap_or(0.0)
+                        })
+                        .product::<Probability>();
+                    weights.push(weight);
+                }
+                let total = weights.iter().sum::<Probability>();
+                current += count * total.ln();
+                if total <= 0.0 {
+                    continue;
+                }
+                for (configuration, weight) in hidden_configurations.iter().zip(&weights) {
+                    for (id, value) in hidden.iter().zip(configuration) {
+                        assignment.insert(*id, value.clone());
+                    }
+                    for node in &self.nodes {
+                        let key = node
+                            .parents
+                            .iter()
+                            .map(|parent| assignment[parent].clone())
+                            .collect::<Vec<T>>();
+                        *counts
+                            .entry(groups[node.id].clone())
+                            .or_default()
+                            .entry(key)
+                            .or_default()
+                            .entry(assignment[&node.id].clone())
+                            .or_insert(0.0) += count * weight / total;
+                    }
+                }
+            }
+            estimates = estimate(&counts);
+            let converged = current - log_likelihood < EM_TOLERANCE;
+            log_likelihood = current;
+            if converged {
+                break;
+            }
+        }
+
+        for id in 0..self.nodes.len() {
+            let rows = estimates[&groups[id]].clone();
+            let node = &mut self.nodes[id];
+            node.dirichlet = None;
+            if let NodeType::Root(prior) = &mut node.node_type {
+                *prior = rows[&Vec::new()].clone();
+            } else {
+                node.probability = rows.clone();
+                node.compact_cpt = None;
+            }
+            if let Some(name) = node.shared_cpt.clone() {
+                self.shared_cpts.insert(name, rows);
+            }
+        }
+        self.normalize_learned();
+        log_likelihood
+    }
+
+    /// Render the structure as an acyclic directed mixed graph, one statement per line:
+    /// `a -> b` for an edge, `a <-> b` for a latent confounder (see `bidirected_edges`),
+    /// and a node name alone, followed by `[latent]` if hidden, for every other node not
+    /// named by an edge.
+    pub fn to_admg(&self) -> String {
+        let confounders = self.latent_confounders();
+        let mut named = vec![false; self.nodes.len()];
+        let mut lines = Vec::new();
+        for node in &self.nodes {
+            if confounders.contains(&node.id) {
+                continue;
+            }
+            for child in &node.children {
+                named[node.id] = true;
+                named[*child] = true;
+                lines.push(format!(
+                    "{} -> {}",
+                    self.get_node_name(node.id),
+                    self.get_node_name(*child)
+                ));
+            }
+        }
+        for (a, b) in self.bidirected_edges() {
+            named[self.node_map[&a]] = true;
+            named[self.node_map[&b]] = true;
+            lines.push(format!("{} <-> {}", a, b));
+        }
+        for node in &self.nodes {
+            let name = self.get_node_name(node.id);
+            if confounders.contains(&node.id) || named[node.id] && !self.is_node_hidden(name) {
+                continue;
+            }
+            if self.is_node_hidden(name) {
+                lines.push(format!("{} [latent]", name));
+            } else {
+                lines.push(name.to_string());
+            }
+        }
+        let mut s = lines.join("\n");
+        s.push('\n');
+        s
+    }
+
+    /// Read a structure written like `to_admg`, creating nodes in order of appearance as
+    /// `from_edges` does. Each bidirected edge `a <-> b` becomes a latent confounder taking
+    /// `latent_states`, as added by `add_latent_confounder`; the CPTs can then be learned
+    /// with `fit_em`. Lines starting with `#` are comments. Returns `Error::CycleDetected` if
+    /// the directed edges close a cycle, and `Error::Invalid` for a node that is its own
+    /// parent or confounder, a repeated bidirected edge, or a confounder whose name is taken.
+    pub fn from_admg(
+        source: &str,
+        value_space: Vec<T>,
+        latent_states: Vec<T>,
+    ) -> Result<BayesianNetwork<T>, Error> {
+        let mut names: Vec<String> = Vec::new();
+        let mut latent = Vec::new();
+        let mut edges = Vec::new();
+        let mut bidirected = Vec::new();
+        let mut declare = |name: &str, line: usize| {
+            let valid = !name.is_empty()
+                && !name.contains(char::is_whitespace)
+                && !name.contains(['-', '<', '>', '[', ']']);
+            if !valid {
+                return Err(Error::Syntax {
+                    format: FORMAT,
+                    line,
+                    message: format!("invalid node name `{}`", name),
+                });
+            }
+            if !names.iter().any(|other| other == name) {
+                names.push(name.to_string());
+            }
+            Ok(name.to_string())
+        };
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            if let Some((a, b)) = text.split_once("<->") {
+                let a = declare(a.trim(), line)?;
+                let b = declare(b.trim(), line)?;
+                bidirected.push((a, b));
+            } else if let Some((a, b)) = text.split_once("->") {
+                let a = declare(a.trim(), line)?;
+                let b = declare(b.trim(), line)?;
+                edges.push((a, b));
+            } else if let Some(name) = text.strip_suffix("[latent]") {
+                latent.push(declare(name.trim(), line)?);
+            } else {
+                declare(text, line)?;
+            }
+        }
+        for (parent, child) in &edges {
+            if parent == child {
+                return Err(Error::Invalid {
+                    format: FORMAT,
+                    message: format!("node `{}` is its own parent", parent),
+                });
+            }
+        }
+        for (index, (a, b)) in bidirected.iter().enumerate() {
+            if a == b {
+                return Err(Error::Invalid {
+                    format: FORMAT,
+                    message: format!("node `{}` is its own confounder", a),
+                });
+            }
+            if bidirected[..index]
+                .iter()
+                .any(|(c, d)| (a, b) == (c, d) || (a, b) == (d, c))
+            {
+                return Err(Error::Invalid {
+                    format: FORMAT,
+                    message: format!("bidirected edge `{} <-> {}` is repeated", a, b),
+                });
+            }
+            let name = confounder_name(a, b);
+            if names.contains(&name) {
+                return Err(Error::Invalid {
+                    format: FORMAT,
+                    message: format!("latent confounder `{}` is already a node", name),
+                });
+            }
+        }
+        for state in &latent_states {
+            if !value_space.contains(state) {
+                return Err(Error::Invalid {
+                    format: FORMAT,
+                    message: format!("latent state {:?} not found in value space", state),
+                });
+            }
+        }
+        let mut network = BayesianNetwork::try_from_edges(
+            value_space,
+            names.iter().map(|name| name.as_str()).collect(),
+            &edges,
+        )?;
+        for name in &latent {
+            network.set_node_hidden(name, true);
+        }
+        for (a, b) in &bidirected {
+            network.add_latent_confounder(a, b, latent_states.clone());
+        }
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, brute_force_probability, confounded, row, TOLERANCE};
+
+    /// A hidden class `u` with three observed indicators, the smallest latent class model
+    /// whose CPTs are identified up to swapping the classes.
+    fn latent_class() -> BayesianNetwork<bool> {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("u", NodeType::Root(row(0.3)));
+        for (name, given_true, given_false) in [("a", 0.9, 0.2), ("b", 0.8, 0.1), ("c", 0.7, 0.25)]
+        {
+            network.add_node(name, NodeType::Leaf);
+            network.add_dependency(
+                vec!["u"],
+                name,
+                HashMap::from([
+                    (vec![true], row(given_true)),
+                    (vec![false], row(given_false)),
+                ]),
+            );
+        }
+        network.set_node_hidden("u", true);
+        network
+    }
+
+    #[test]
+    fn add_latent_node_keeps_the_observed_joint() {
+        let network = confounded();
+        let mut latent = network.copy();
+        latent.add_latent_node("h", &["z", "y"], vec![true, false]);
+        assert!(latent.is_node_hidden("h"));
+        let evidence = HashMap::from([("x", true)]);
+        for name in ["z", "y"] {
+            assert_close(
+                brute_force_probability(&latent, name, &true, &evidence),
+                brute_force_probability(&network, name, &true, &evidence),
+                TOLERANCE,
+            );
+        }
+    }
+
+    #[test]
+    fn admg_round_trips() {
+        let source = "a -> b\nb -> c\nd -> c\na <-> c\nh [latent]\ne\n";
+        let network =
+            BayesianNetwork::from_admg(source, vec![true, false], vec![true, false]).unwrap();
+        assert_eq!(
+            network.bidirected_edges(),
+            vec![("a".to_string(), "c".to_string())]
+        );
+        assert!(network.is_node_hidden(&confounder_name("a", "c")));
+        assert!(network.is_node_hidden("h"));
+        assert!(!network.is_node_hidden("e"));
+        let admg = network.to_admg();
+        assert_eq!(admg, source);
+        let read = BayesianNetwork::from_admg(&admg, vec![true, false], vec![true, false]).unwrap();
+        assert_eq!(read.to_admg(), admg);
+        assert_eq!(read.node_names(), network.node_names());
+    }
+
+    #[test]
+    fn from_admg_rejects_cycles_and_bad_bidirected_edges() {
+        let read =
+            |source| BayesianNetwork::from_admg(source, vec![true, false], vec![true, false]);
+        assert!(matches!(
+            read("a -> b\nb -> a"),
+            Err(Error::CycleDetected(_))
+        ));
+        assert!(matches!(
+            read("a <-> b\nb <-> a"),
+            Err(Error::Invalid { .. })
+        ));
+        assert!(matches!(
+            read("a <-> b\na <-> b"),
+            Err(Error::Invalid { .. })
+        ));
+        assert!(matches!(read("a <-> a"), Err(Error::Invalid { .. })));
+        assert!(matches!(
+            read("a <-> b\nU_a_b -> a"),
+            Err(Error::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn fit_em_recovers_the_cpts_of_a_latent_class() {
+        let truth = latent_class();
+        let mut rng = seeded_rng(7);
+        let sample = truth.sample_with_rng(20_000, &mut rng);
+        let mut dataset = Dataset::new(vec!["a", "b", "c"]);
+        for record in sample.rows() {
+            dataset.add_row(record[1..].to_vec());
+        }
+        let mut network = BayesianNetwork::from_admg(
+            "u -> a\nu -> b\nu -> c\nu [latent]",
+            vec![true, false],
+            vec![true, false],
+        )
+        .unwrap();
+        let log_likelihood = network.fit_em_with_rng(&dataset, 1.0, 500, &mut rng);
+        assert!(log_likelihood.is_finite() && log_likelihood < 0.0);
+
+        // The classes may come out swapped.
+        let a = &network.nodes[network.get_node_index("a")];
+        let swapped = network.cpt_probability(a, &[true], &true)
+            < network.cpt_probability(a, &[false], &true);
+        let class = |value: bool| value != swapped;
+        let u = &network.nodes[network.get_node_index("u")];
+        let expected_u = truth.local_probability(&truth.nodes[0], &true, &HashMap::new());
+        assert_close(
+            network.local_probability(u, &class(true), &HashMap::new()),
+            expected_u,
+            0.05,
+        );
+        for name in ["a", "b", "c"] {
+            let fitted = &network.nodes[network.get_node_index(name)];
+            let expected = &truth.nodes[truth.get_node_index(name)];
+            for value in [true, false] {
+                assert_close(
+                    network.cpt_probability(fitted, &[class(value)], &true),
+                    truth.cpt_probability(expected, &[value], &true),
+                    0.05,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,796 @@
+use crate::bayesian_network::{BayesianNetwork, NodeId, NodeType, Probability};
+use crate::cpt::CompactCpt;
+use crate::dataset::Dataset;
+use crate::error::Error;
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::Name;
+
+/// A directed edge `(parent, child)` between named nodes.
+pub type Edge = (Name, Name);
+
+/// Per-edge inclusion frequencies over bootstrap replicates.
+#[derive(Clone, Debug)]
+pub struct ArcConfidence {
+    replicates: usize,
+    frequencies: HashMap<Edge, f64>,
+}
+
+impl ArcConfidence {
+    /// Get the number of bootstrap replicates the frequencies were computed from.
+    pub fn replicates(&self) -> usize {
+        self.replicates
+    }
+
+    /// Get the fraction of replicates in which `parent -> child` was learned.
+    pub fn confidence(&self, parent: &str, child: &str) -> f64 {
+        self.frequencies
+            .get(&(parent.to_string(), child.to_string()))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Get all learned edges with their frequencies, most confident first.
+    pub fn edges(&self) -> Vec<(&Edge, f64)> {
+        let mut edges = self
+            .frequencies
+            .iter()
+            .map(|(edge, frequency)| (edge, *frequency))
+            .collect::<Vec<(&Edge, f64)>>();
+        edges.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        edges
+    }
+
+    /// Get the consensus structure: every edge learned in at least `threshold` of the replicates.
+    /// A threshold above 0.5 guarantees that no edge appears in both directions.
+    pub fn consensus(&self, threshold: f64) -> Vec<Edge> {
+        self.edges()
+            .into_iter()
+            .filter(|(_, frequency)| *frequency >= threshold)
+            .map(|(edge, _)| edge.clone())
+            .collect()
+    }
+}
+
+/// Estimate arc confidence by learning a structure on each of `replicates` bootstrap resamples
+/// of `dataset`. `learn` returns the edges of the structure learned from one resample.
+pub fn bootstrap_structure<T, F>(dataset: &Dataset<T>, replicates: usize, learn: F) -> ArcConfidence
+where
+    T: Clone,
+    F: Fn(&Dataset<T>) -> Vec<Edge>,
+{
+    bootstrap_structure_with_rng(dataset, replicates, learn, &mut rand::thread_rng())
+}
+
+/// Estimate arc confidence like `bootstrap_structure`, resampling with `rng`.
+pub fn bootstrap_structure_with_rng<T, F, R>(
+    dataset: &Dataset<T>,
+    replicates: usize,
+    learn: F,
+    rng: &mut R,
+) -> ArcConfidence
+where
+    T: Clone,
+    F: Fn(&Dataset<T>) -> Vec<Edge>,
+    R: Rng + ?Sized,
+{
+    if dataset.is_empty() {
+        panic!("Cannot bootstrap an empty dataset");
+    }
+    let columns = dataset
+        .columns()
+        .iter()
+        .map(|name| name.as_str())
+        .collect::<Vec<&str>>();
+    let mut counts: HashMap<Edge, usize> = HashMap::new();
+    for _ in 0..replicates {
+        let mut resample = Dataset::new(columns.clone());
+        for _ in 0..dataset.len() {
+            let index = rng.gen_range(0..dataset.len());
+            resample.add_row(dataset.rows()[index].clone());
+        }
+        let mut edges = learn(&resample);
+        edges.sort();
+        edges.dedup();
+        for edge in edges {
+            *counts.entry(edge).or_insert(0) += 1;
+        }
+    }
+    ArcConfidence {
+        replicates,
+        frequencies: counts
+            .into_iter()
+            .map(|(edge, count)| (edge, count as f64 / replicates as f64))
+            .collect(),
+    }
+}
+
+/// Enumerate every combination taking one value from each domain, in row-major order.
+pub(crate) fn configurations<T: Clone>(domains: &[Vec<T>]) -> Vec<Vec<T>> {
+    let mut configurations = vec![Vec::new()];
+    for domain in domains {
+        configurations = configurations
+            .into_iter()
+            .flat_map(|prefix| {
+                domain.iter().map(move |value| {
+                    let mut configuration = prefix.clone();
+                    configuration.push(value.clone());
+                    configuration
+                })
+            })
+            .collect();
+    }
+    configurations
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Estimate every CPT of the network from a dataset of complete records.
+    /// Each node's domain is its declared states, or else the set of values observed in its
+    /// column, and `pseudo_count` is added to every cell of a row before normalizing (0.0 gives
+    /// maximum likelihood estimates).
+    /// Parent configurations that never occur get a uniform row unless smoothed.
+    /// Nodes tied to the same shared CPT pool their counts and get the same estimate, and a
+    /// node with a CPT tree keeps the tree and pools the counts reaching each of its leaves.
+    /// Deterministic nodes given by a function are left as they are.
+    /// The counts plus pseudo counts are kept as the Dirichlet posterior of each row, for
+    /// `credible_interval`.
+    pub fn fit(&mut self, dataset: &Dataset<T>, pseudo_count: Probability) {
+        self.fit_by(dataset, |_, _| pseudo_count);
+    }
+
+    /// Estimate every CPT like `fit`, with the BDeu prior of equivalent sample size `ess`:
+    /// each cell of a node with `r` states and `q` parent configurations gets `ess / (r q)`.
+    pub fn fit_bdeu(&mut self, dataset: &Dataset<T>, ess: Probability) {
+        self.fit_by(dataset, |states, configurations| {
+            ess / (states * configurations) as Probability
+        });
+    }
+
+    /// Estimate every CPT with a pseudo count given by the number of states and of parent
+    /// configurations of each node.
+    fn fit_by<F: Fn(usize, usize) -> Probability>(
+        &mut self,
+        dataset: &Dataset<T>,
+        pseudo_count: F,
+    ) {
+        let columns = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let name = self.get_node_name(node.id);
+                match dataset.column_index(name) {
+                    Some(column) => column,
+                    None if self.is_node_hidden(name) => {
+                        panic!("Hidden node `{}` has no dataset column; use `fit_em`", name)
+                    }
+                    None => panic!("Dataset column `{}` not found", name),
+                }
+            })
+            .collect::<Vec<usize>>();
+        for row in dataset.rows() {
+            for value in row {
+                if !self.value_space.contains(value) {
+                    panic!("Dataset value {:?} not found in value space", value);
+                }
+            }
+        }
+        let domains = columns
+            .iter()
+            .zip(&self.nodes)
+            .map(|(column, node)| {
+                if let Some(states) = &node.states {
+                    return states.clone();
+                }
+                self.value_space
+                    .iter()
+                    .filter(|value| dataset.rows().iter().any(|row| row[*column] == **value))
+                    .cloned()
+                    .collect::<Vec<T>>()
+            })
+            .collect::<Vec<Vec<T>>>();
+
+        // Nodes tied to the same shared CPT pool their counts into one estimate.
+        let mut groups: Vec<Vec<NodeId>> = Vec::new();
+        let mut shared_groups: HashMap<&str, usize> = HashMap::new();
+        let mut trees = Vec::new();
+        for node in &self.nodes {
+            match &node.compact_cpt {
+                Some(CompactCpt::Tree(tree)) => {
+                    trees.push((node.id, tree.clone()));
+                    continue;
+                }
+                Some(CompactCpt::Function(_)) => continue,
+                None => {}
+            }
+            match &node.shared_cpt {
+                Some(name) => match shared_groups.get(name.as_str()) {
+                    Some(group) => groups[*group].push(node.id),
+                    None => {
+                        shared_groups.insert(name, groups.len());
+                        groups.push(vec![node.id]);
+                    }
+                },
+                None => groups.push(vec![node.id]),
+            }
+        }
+        // The values taken by any of `ids`, in value space order.
+        let union = |ids: &[NodeId]| {
+            self.value_space
+                .iter()
+                .filter(|value| ids.iter().any(|id| domains[*id].contains(value)))
+                .cloned()
+                .collect::<Vec<T>>()
+        };
+
+        let mut estimates = Vec::new();
+        for group in groups {
+            let mut counts: HashMap<Vec<T>, HashMap<T, Probability>> = HashMap::new();
+            for id in &group {
+                for row in dataset.rows() {
+                    let key = self.nodes[*id]
+                        .parents
+                        .iter()
+                        .map(|parent| row[columns[*parent]].clone())
+                        .collect::<Vec<T>>();
+                    *counts
+                        .entry(key)
+                        .or_default()
+                        .entry(row[columns[*id]].clone())
+                        .or_insert(0.0) += 1.0;
+                }
+            }
+            let domain = union(&group);
+            let parent_domains = (0..self.nodes[group[0]].parents.len())
+                .map(|i| {
+                    union(
+                        &group
+                            .iter()
+                            .map(|id| self.nodes[*id].parents[i])
+                            .collect::<Vec<NodeId>>(),
+                    )
+                })
+                .collect::<Vec<Vec<T>>>();
+            let keys = configurations(&parent_domains);
+            let pseudo_count = pseudo_count(domain.len(), keys.len());
+            let mut probability = HashMap::new();
+            let mut dirichlet = HashMap::new();
+            for key in keys {
+                let row_counts = counts.remove(&key).unwrap_or_default();
+                let total = domain
+                    .iter()
+                    .map(|value| row_counts.get(value).copied().unwrap_or(0.0) + pseudo_count)
+                    .sum::<Probability>();
+                let mut map = HashMap::new();
+                for value in &self.value_space {
+                    let prob = if !domain.contains(value) {
+                        0.0
+                    } else if total > 0.0 {
+                        (row_counts.get(value).copied().unwrap_or(0.0) + pseudo_count) / total
+                    } else {
+                        1.0 / domain.len() as Probability
+                    };
+                    map.insert(value.clone(), prob);
+                }
+                let alphas = domain
+                    .iter()
+                    .map(|value| {
+                        let count = row_counts.get(value).copied().unwrap_or(0.0);
+                        (value.clone(), count + pseudo_count)
+                    })
+                    .collect::<HashMap<T, Probability>>();
+                dirichlet.insert(key.clone(), alphas);
+                probability.insert(key, map);
+            }
+            estimates.push((group, probability, dirichlet));
+        }
+
+        for (group, probability, dirichlet) in estimates {
+            for id in &group {
+                let node = &mut self.nodes[*id];
+                node.dirichlet = Some(dirichlet.clone());
+                if let NodeType::Root(prob_map) = &mut node.node_type {
+                    *prob_map = probability[&Vec::new()].clone();
+                } else {
+                    node.probability = probability.clone();
+                    node.compact_cpt = None;
+                }
+            }
+            if let Some(name) = self.nodes[group[0]].shared_cpt.clone() {
+                self.shared_cpts.insert(name, probability);
+            }
+        }
+        // Tree CPTs keep their structure and pool the counts of each leaf.
+        for (id, mut tree) in trees {
+            let parent_columns = self.nodes[id]
+                .parents
+                .iter()
+                .map(|parent| columns[*parent])
+                .collect::<Vec<usize>>();
+            let pseudo_count = pseudo_count(domains[id].len(), tree.leaf_count());
+            self.estimate_leaves(
+                &mut tree,
+                dataset,
+                columns[id],
+                &parent_columns,
+                &domains[id],
+                pseudo_count,
+            );
+            let node = &mut self.nodes[id];
+            node.compact_cpt = Some(CompactCpt::Tree(tree));
+            node.dirichlet = None;
+        }
+        self.normalize_learned();
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Create a network with the given nodes and edges. Nodes without parents are roots with a
+    /// uniform prior, and every other CPT is empty until the parameters are learned with `fit`.
+    /// Panics with the error `try_from_edges` would return.
+    pub fn from_edges(value_space: Vec<T>, names: Vec<&str>, edges: &[Edge]) -> BayesianNetwork<T> {
+        match BayesianNetwork::try_from_edges(value_space, names, edges) {
+            Ok(network) => network,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Create a network like `from_edges`, or return the error of `try_add_dependency` for
+    /// the first edge it rejects, such as `Error::CycleDetected`.
+    pub(crate) fn try_from_edges(
+        value_space: Vec<T>,
+        names: Vec<&str>,
+        edges: &[Edge],
+    ) -> Result<BayesianNetwork<T>, Error> {
+        let uniform = 1.0 / value_space.len() as Probability;
+        let prior = value_space
+            .iter()
+            .map(|value| (value.clone(), uniform))
+            .collect::<HashMap<T, Probability>>();
+        let mut network = BayesianNetwork::new(value_space);
+        for name in &names {
+            let has_parents = edges.iter().any(|(_, child)| child == name);
+            let has_children = edges.iter().any(|(parent, _)| parent == name);
+            let node_type = if !has_parents {
+                NodeType::Root(prior.clone())
+            } else if has_children {
+                NodeType::Inner
+            } else {
+                NodeType::Leaf
+            };
+            network.add_node(name, node_type);
+        }
+        for name in &names {
+            let parents = edges
+                .iter()
+                .filter(|(_, child)| child == name)
+                .map(|(parent, _)| parent.as_str())
+                .collect::<Vec<&str>>();
+            if !parents.is_empty() {
+                network.try_add_dependency(parents, name, HashMap::new())?;
+            }
+        }
+        Ok(network)
+    }
+}
+
+/// A node with its parent columns.
+type Family = (usize, Vec<usize>);
+
+/// Natural log of the gamma function (Lanczos approximation).
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        std::f64::consts::PI.ln() - (std::f64::consts::PI * x).sin().ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let t = x + 7.5;
+        let series = COEFFICIENTS[1..]
+            .iter()
+            .enumerate()
+            .fold(COEFFICIENTS[0], |sum, (i, c)| {
+                sum + c / (x + i as f64 + 1.0)
+            });
+        0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+    }
+}
+
+/// The distinct values of every column, in order of first appearance.
+pub(crate) fn column_domains<T: Clone + PartialEq>(dataset: &Dataset<T>) -> Vec<Vec<T>> {
+    (0..dataset.columns().len())
+        .map(|column| {
+            let mut domain = Vec::new();
+            for row in dataset.rows() {
+                if !domain.contains(&row[column]) {
+                    domain.push(row[column].clone());
+                }
+            }
+            domain
+        })
+        .collect()
+}
+
+/// Count the rows of each configuration of `columns`.
+pub(crate) fn joint_counts<T: Clone + Eq + Hash>(
+    dataset: &Dataset<T>,
+    columns: &[usize],
+) -> HashMap<Vec<T>, f64> {
+    let mut counts = HashMap::new();
+    for row in dataset.rows() {
+        let key = columns
+            .iter()
+            .map(|column| row[*column].clone())
+            .collect::<Vec<T>>();
+        *counts.entry(key).or_insert(0.0) += 1.0;
+    }
+    counts
+}
+
+/// The BDeu score of `column` given `parents`, with equivalent sample size `ess`.
+fn bdeu_score<T: Clone + Eq + Hash>(
+    dataset: &Dataset<T>,
+    domains: &[Vec<T>],
+    column: usize,
+    parents: &[usize],
+    ess: f64,
+) -> f64 {
+    let states = domains[column].len() as f64;
+    let configurations = parents
+        .iter()
+        .map(|parent| domains[*parent].len() as f64)
+        .product::<f64>();
+    let alpha_configuration = ess / configurations;
+    let alpha_cell = alpha_configuration / states;
+    let mut family = parents.to_vec();
+    family.push(column);
+    let mut score = 0.0;
+    for count in joint_counts(dataset, parents).values() {
+        score += ln_gamma(alpha_configuration) - ln_gamma(alpha_configuration + count);
+    }
+    for count in joint_counts(dataset, &family).values() {
+        score += ln_gamma(alpha_cell + count) - ln_gamma(alpha_cell);
+    }
+    score
+}
+
+/// Learn a tree structure with the Chow-Liu algorithm: the maximum spanning tree of pairwise
+/// mutual information, directed away from the first column.
+pub fn chow_liu<T: Clone + PartialEq + Eq + Hash>(dataset: &Dataset<T>) -> Vec<Edge> {
+    let n = dataset.columns().len();
+    let total = dataset.len() as f64;
+    let marginals = (0..n)
+        .map(|column| joint_counts(dataset, &[column]))
+        .collect::<Vec<HashMap<Vec<T>, f64>>>();
+    let mut information = vec![vec![0.0; n]; n];
+    for a in 0..n {
+        for b in a + 1..n {
+            let mut mi = 0.0;
+            for (key, count) in joint_counts(dataset, &[a, b]) {
+                let pa = marginals[a][&key[..1]] / total;
+                let pb = marginals[b][&key[1..]] / total;
+                let pab = count / total;
+                mi += pab * (pab / (pa * pb)).ln();
+            }
+            information[a][b] = mi;
+            information[b][a] = mi;
+        }
+    }
+    // Prim's algorithm from the first column, so every edge points away from the tree.
+    let mut in_tree = vec![false; n];
+    let mut edges = Vec::new();
+    if n == 0 {
+        return edges;
+    }
+    in_tree[0] = true;
+    for _ in 1..n {
+        let (parent, child) = (0..n)
+            .filter(|a| in_tree[*a])
+            .flat_map(|a| (0..n).filter(|b| !in_tree[*b]).map(move |b| (a, b)))
+            .max_by(|x, y| information[x.0][x.1].total_cmp(&information[y.0][y.1]))
+            .unwrap();
+        in_tree[child] = true;
+        edges.push((
+            dataset.columns()[parent].clone(),
+            dataset.columns()[child].clone(),
+        ));
+    }
+    edges
+}
+
+/// Learn a structure by greedy hill climbing on the BDeu score with equivalent sample size
+/// `ess`, adding, removing and reversing edges while the score improves. The search stays
+/// within polytrees (no undirected cycles), which `infer` requires, and gives each node at most
+/// `max_parents` parents.
+pub fn hill_climb<T: Clone + PartialEq + Eq + Hash>(
+    dataset: &Dataset<T>,
+    ess: f64,
+    max_parents: usize,
+) -> Vec<Edge> {
+    let n = dataset.columns().len();
+    let domains = column_domains(dataset);
+    let mut parents: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut scores = (0..n)
+        .map(|column| bdeu_score(dataset, &domains, column, &[], ess))
+        .collect::<Vec<f64>>();
+    let connected = |parents: &[Vec<usize>], a: usize, b: usize| {
+        let mut stack = vec![a];
+        let mut seen = vec![false; n];
+        seen[a] = true;
+        while let Some(node) = stack.pop() {
+            if node == b {
+                return true;
+            }
+            let neighbours = parents[node]
+                .iter()
+                .copied()
+                .chain((0..n).filter(|child| parents[*child].contains(&node)));
+            for neighbour in neighbours.collect::<Vec<usize>>() {
+                if !seen[neighbour] {
+                    seen[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+        false
+    };
+    let with = |list: &[usize], item: usize| {
+        let mut list = list.to_vec();
+        list.push(item);
+        list
+    };
+    let without = |list: &[usize], item: usize| {
+        list.iter()
+            .copied()
+            .filter(|other| *other != item)
+            .collect::<Vec<usize>>()
+    };
+
+    loop {
+        // The best move as (gain, new parent sets of the changed nodes).
+        let mut best: Option<(f64, Vec<Family>)> = None;
+        let mut consider = |changes: Vec<Family>, scores: &[f64]| {
+            let gain = changes
+                .iter()
+                .map(|(child, new_parents)| {
+                    bdeu_score(dataset, &domains, *child, new_parents, ess) - scores[*child]
+                })
+                .sum::<f64>();
+            if gain > 1e-9 && best.as_ref().is_none_or(|(best_gain, _)| gain > *best_gain) {
+                best = Some((gain, changes));
+            }
+        };
+        for parent in 0..n {
+            for child in 0..n {
+                if parent == child {
+                    continue;
+                }
+                if parents[child].contains(&parent) {
+                    consider(vec![(child, without(&parents[child], parent))], &scores);
+                    if parents[parent].len() < max_parents {
+                        consider(
+                            vec![
+                                (child, without(&parents[child], parent)),
+                                (parent, with(&parents[parent], child)),
+                            ],
+                            &scores,
+                        );
+                    }
+                } else if !parents[parent].contains(&child)
+                    && parents[child].len() < max_parents
+                    && !connected(&parents, parent, child)
+                {
+                    consider(vec![(child, with(&parents[child], parent))], &scores);
+                }
+            }
+        }
+        match best {
+            Some((_, changes)) => {
+                for (child, new_parents) in changes {
+                    scores[child] = bdeu_score(dataset, &domains, child, &new_parents, ess);
+                    parents[child] = new_parents;
+                }
+            }
+            None => break,
+        }
+    }
+
+    let mut edges = Vec::new();
+    for (child, child_parents) in parents.iter().enumerate() {
+        for parent in child_parents {
+            edges.push((
+                dataset.columns()[*parent].clone(),
+                dataset.columns()[child].clone(),
+            ));
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded_rng;
+
+    fn edge(parent: &str, child: &str) -> Edge {
+        (parent.to_string(), child.to_string())
+    }
+
+    /// Two columns that agree in four of six records.
+    fn agreeing() -> Dataset<bool> {
+        let mut dataset = Dataset::new(vec!["a", "b"]);
+        for row in [
+            [true, true],
+            [true, true],
+            [false, false],
+            [false, false],
+            [true, false],
+            [false, true],
+        ] {
+            dataset.add_row(row.to_vec());
+        }
+        dataset
+    }
+
+    #[test]
+    fn bootstrap_structure_counts_edge_frequencies() {
+        let dataset = agreeing();
+        // Always learn a -> b, twice, and b -> a when a resample has two disagreements.
+        let confidence = bootstrap_structure_with_rng(
+            &dataset,
+            50,
+            |resample| {
+                let mut edges = vec![edge("a", "b"), edge("a", "b")];
+                if resample
+                    .rows()
+                    .iter()
+                    .filter(|row| row[0] != row[1])
+                    .count()
+                    >= 2
+                {
+                    edges.push(edge("b", "a"));
+                }
+                edges
+            },
+            &mut seeded_rng(1),
+        );
+        assert_eq!(confidence.replicates(), 50);
+        assert_eq!(confidence.confidence("a", "b"), 1.0);
+        let reversed = confidence.confidence("b", "a");
+        assert!(reversed > 0.0 && reversed < 1.0);
+        assert_eq!(confidence.confidence("b", "c"), 0.0);
+        assert_eq!(
+            confidence.edges(),
+            vec![(&edge("a", "b"), 1.0), (&edge("b", "a"), reversed)]
+        );
+        assert_eq!(confidence.consensus(1.0), vec![edge("a", "b")]);
+        assert_eq!(confidence.consensus(0.0).len(), 2);
+    }
+
+    #[test]
+    fn fit_counts_each_row_with_pseudo_counts() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node(
+            "a",
+            NodeType::Root(HashMap::from([(true, 0.5), (false, 0.5)])),
+        );
+        network.add_node("b", NodeType::Leaf);
+        network.add_dependency(vec!["a"], "b", HashMap::new());
+        let mut dataset = agreeing();
+        dataset.add_row(vec![true, true]);
+        network.fit(&dataset, 1.0);
+
+        // a is true in 4 of 7 records, and b agrees with it in 3 of 4 and 2 of 3.
+        let a = &network.nodes[network.get_node_index("a")];
+        match &a.node_type {
+            NodeType::Root(prior) => assert!((prior[&true] - 5.0 / 9.0).abs() < 1e-12),
+            _ => unreachable!(),
+        }
+        let b = &network.nodes[network.get_node_index("b")];
+        assert!((b.probability[&vec![true]][&true] - 4.0 / 6.0).abs() < 1e-12);
+        assert!((b.probability[&vec![false]][&false] - 3.0 / 5.0).abs() < 1e-12);
+        let dirichlet = b.dirichlet.as_ref().unwrap();
+        assert_eq!(dirichlet[&vec![true]][&true], 4.0);
+        assert_eq!(dirichlet[&vec![true]][&false], 2.0);
+    }
+
+    #[test]
+    fn fit_bdeu_spreads_the_equivalent_sample_size() {
+        let mut network =
+            BayesianNetwork::from_edges(vec![true, false], vec!["a", "b"], &[edge("a", "b")]);
+        network.fit_bdeu(&agreeing(), 4.0);
+        // a gets 4 / 2 per cell and b, with two parent configurations, 4 / 4.
+        match &network.nodes[network.get_node_index("a")].node_type {
+            NodeType::Root(prior) => assert!((prior[&true] - 0.5).abs() < 1e-12),
+            _ => unreachable!(),
+        }
+        let b = &network.nodes[network.get_node_index("b")];
+        assert!((b.probability[&vec![true]][&true] - 3.0 / 5.0).abs() < 1e-12);
+        assert_eq!(b.dirichlet.as_ref().unwrap()[&vec![false]][&true], 2.0);
+    }
+
+    #[test]
+    fn from_edges_builds_roots_inner_nodes_and_leaves() {
+        let network = BayesianNetwork::from_edges(
+            vec![1, 2, 3, 4],
+            vec!["a", "b", "c"],
+            &[edge("a", "b"), edge("b", "c")],
+        );
+        match &network.nodes[network.get_node_index("a")].node_type {
+            NodeType::Root(prior) => assert_eq!(prior[&3], 0.25),
+            _ => panic!("expected a root"),
+        }
+        assert!(matches!(
+            network.nodes[network.get_node_index("b")].node_type,
+            NodeType::Inner
+        ));
+        assert!(matches!(
+            network.nodes[network.get_node_index("c")].node_type,
+            NodeType::Leaf
+        ));
+        assert_eq!(
+            network.nodes[network.get_node_index("c")].parents,
+            vec![network.get_node_index("b")]
+        );
+    }
+
+    /// A chain x -> y -> z: y copies x in all but one of 20 records, and z copies y in all
+    /// but three.
+    fn chain() -> Dataset<bool> {
+        let mut dataset = Dataset::new(vec!["x", "y", "z"]);
+        for i in 0..20 {
+            let x = i % 2 == 0;
+            let y = if i == 0 { !x } else { x };
+            let z = if i % 7 == 1 { !y } else { y };
+            dataset.add_row(vec![x, y, z]);
+        }
+        dataset
+    }
+
+    #[test]
+    fn chow_liu_recovers_the_chain() {
+        assert_eq!(chow_liu(&chain()), vec![edge("x", "y"), edge("y", "z")]);
+    }
+
+    #[test]
+    fn hill_climb_links_the_chain_within_its_limits() {
+        let edges = hill_climb(&chain(), 1.0, 1);
+        let skeleton = edges
+            .iter()
+            .map(|(a, b)| {
+                let mut pair = [a.as_str(), b.as_str()];
+                pair.sort();
+                pair
+            })
+            .collect::<Vec<[&str; 2]>>();
+        assert_eq!(edges.len(), 2);
+        assert!(skeleton.contains(&["x", "y"]));
+        assert!(skeleton.contains(&["y", "z"]));
+        assert!(hill_climb(&chain(), 1.0, 0).is_empty());
+    }
+
+    #[test]
+    fn ln_gamma_matches_factorials() {
+        assert!((ln_gamma(5.0) - 24.0_f64.ln()).abs() < 1e-10);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-10);
+        assert!((ln_gamma(0.25) - 3.625_609_908_221_908_f64.ln()).abs() < 1e-10);
+    }
+
+    #[test]
+    fn configurations_are_row_major() {
+        assert_eq!(
+            configurations(&[vec![1, 2], vec![3, 4]]),
+            vec![vec![1, 3], vec![1, 4], vec![2, 3], vec![2, 4]]
+        );
+        assert_eq!(configurations::<u8>(&[]), vec![Vec::<u8>::new()]);
+    }
+}
@@ -0,0 +1,132 @@
+mod adjustment;
+mod anomaly;
+mod bayesian_network;
+#[cfg(feature = "binary")]
+mod binary;
+mod causal;
+mod classifier;
+mod complexity;
+mod components;
+mod conflict;
+mod convergence;
+mod counterfactual;
+mod cpdag;
+mod cpt;
+mod cpt_builder;
+mod cpt_tree;
+mod credal;
+mod dataset;
+mod decoding;
+mod diagram;
+mod dirichlet;
+mod discretize;
+mod divergence;
+mod dynamic;
+mod edge_strength;
+mod error;
+mod estimate;
+#[cfg(feature = "serde")]
+mod evidence;
+mod explanation;
+mod fci;
+mod formats;
+mod fragment;
+mod gaussian;
+mod generator;
+mod hmm;
+mod hybrid;
+mod impact;
+mod imputation;
+mod influence;
+mod influence_diagram;
+mod information;
+mod jeffrey;
+mod latent;
+mod learning;
+mod macros;
+mod markov;
+mod mixture;
+mod moments;
+mod normalization;
+mod observation;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod particle_filter;
+mod plate;
+mod policy;
+mod posterior;
+mod pruning;
+mod query;
+mod sampling;
+mod scenario;
+mod sensitivity;
+mod separation;
+#[cfg(feature = "serde")]
+mod serialization;
+mod softmax;
+mod stats;
+mod temporal;
+#[cfg(test)]
+mod testing;
+mod trace;
+mod traversal;
+mod treatment;
+mod triangulation;
+mod tying;
+mod validation;
+mod voi;
+
+pub use adjustment::*;
+pub use anomaly::*;
+pub use bayesian_network::*;
+pub use causal::*;
+pub use classifier::*;
+pub use complexity::*;
+pub use conflict::*;
+pub use convergence::*;
+pub use counterfactual::*;
+pub use cpdag::*;
+pub use cpt::*;
+pub use cpt_builder::*;
+pub use cpt_tree::*;
+pub use credal::*;
+pub use dataset::*;
+pub use diagram::*;
+pub use dirichlet::*;
+pub use discretize::*;
+pub use dynamic::*;
+pub use edge_strength::*;
+pub use error::*;
+pub use estimate::*;
+pub use explanation::*;
+pub use fci::*;
+pub use fragment::*;
+pub use gaussian::*;
+pub use generator::*;
+pub use hmm::*;
+pub use hybrid::*;
+pub use impact::*;
+pub use imputation::*;
+pub use influence::*;
+pub use influence_diagram::*;
+pub use latent::*;
+pub use learning::*;
+pub use markov::*;
+pub use mixture::*;
+pub use normalization::*;
+pub use observation::*;
+pub use particle_filter::*;
+pub use plate::*;
+pub use policy::*;
+pub use posterior::*;
+pub use query::*;
+pub use sampling::*;
+pub use scenario::*;
+pub use sensitivity::*;
+pub use softmax::*;
+pub use stats::*;
+pub use temporal::*;
+pub use trace::*;
+pub use treatment::*;
+pub use triangulation::*;
+pub use validation::*;
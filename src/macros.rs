@@ -0,0 +1,80 @@
+/// Build a CPT, as taken by `add_dependency`, from rows of parent values and distributions,
+/// such as `cpt! { [true] => { true: 0.3, false: 0.7 }, [false] => { true: 0.01, false: 0.99 } }`.
+/// Without parent values, as in `cpt! { true: 0.01, false: 0.99 }`, it builds a single
+/// distribution, as taken by `NodeType::Root`. Rows with different numbers of parent values
+/// do not compile, and a repeated row or value panics.
+#[macro_export]
+macro_rules! cpt {
+    ($([$($parent:expr),* $(,)?] => { $($value:tt : $prob:expr),* $(,)? }),* $(,)?) => {{
+        let keys = [$([$($parent),*]),*];
+        let rows = [$($crate::cpt! { $($value: $prob),* }),*];
+        let mut prob = ::std::collections::HashMap::new();
+        for (key, row) in keys.into_iter().zip(rows) {
+            if prob.insert(key.to_vec(), row).is_some() {
+                panic!("Parent values {:?} appear twice in a CPT", key);
+            }
+        }
+        prob
+    }};
+    ($($value:tt : $prob:expr),* $(,)?) => {{
+        let mut prob = ::std::collections::HashMap::new();
+        $(
+            if prob.insert($value, $prob as f64).is_some() {
+                panic!("Value {:?} appears twice in a CPT row", $value);
+            }
+        )*
+        prob
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::testing::{bonus, row};
+
+    #[test]
+    fn cpt_builds_rows_and_priors() {
+        assert_eq!(cpt! { true: 0.01, false: 0.99 }, row(0.01));
+        assert_eq!(
+            cpt! { "low": 1, "high": 0 },
+            HashMap::from([("low", 1.0), ("high", 0.0)])
+        );
+        let table = cpt! {
+            [true, false] => { true: 0.99, false: 0.01 },
+            [false, true] => { true: 0.6, false: 0.4 },
+        };
+        assert_eq!(
+            table,
+            HashMap::from([
+                (
+                    vec![true, false],
+                    HashMap::from([(true, 0.99), (false, 0.01)])
+                ),
+                (
+                    vec![false, true],
+                    HashMap::from([(true, 0.6), (false, 0.4)])
+                ),
+            ])
+        );
+        // The bonus network builds the mood's rows the same way.
+        let network = bonus();
+        let mood = &network.nodes[network.get_node_index("mood")].probability;
+        assert_eq!(mood[&vec![false, true]], cpt! { true: 0.6, false: 0.4 });
+    }
+
+    #[test]
+    #[should_panic(expected = "Parent values [true] appear twice in a CPT")]
+    fn cpt_rejects_a_repeated_row() {
+        cpt! {
+            [true] => { true: 0.3, false: 0.7 },
+            [true] => { true: 0.5, false: 0.5 },
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "Value true appears twice in a CPT row")]
+    fn cpt_rejects_a_repeated_value() {
+        cpt! { true: 0.3, true: 0.7 };
+    }
+}
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    learning::configurations,
+    triangulation::UndirectedGraph,
+};
+
+/// A nonnegative table over some nodes of a Markov network, indexed by the positions of
+/// their states with the last node varying fastest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CliqueFactor {
+    pub nodes: Vec<Name>,
+    pub values: Vec<Probability>,
+}
+
+/// A Markov random field: an undirected graph with a factor over each of some of its cliques,
+/// whose product is the joint distribution up to normalization.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkovNetwork<T> {
+    graph: UndirectedGraph,
+    domains: Vec<Vec<T>>,
+    factors: Vec<CliqueFactor>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> MarkovNetwork<T> {
+    /// Get the graph, in which the nodes of every factor form a clique.
+    pub fn graph(&self) -> &UndirectedGraph {
+        &self.graph
+    }
+
+    /// Get the states of a node, in the order its factors index them.
+    pub fn domain(&self, name: &str) -> &[T] {
+        &self.domains[self.index(name)]
+    }
+
+    /// Get the factors.
+    pub fn factors(&self) -> &[CliqueFactor] {
+        &self.factors
+    }
+
+    /// Get the product of the factors at an assignment of every node.
+    pub fn unnormalized_probability(&self, assignment: &HashMap<&str, T>) -> Probability {
+        self.factors
+            .iter()
+            .map(|factor| {
+                let mut index = 0;
+                for name in &factor.nodes {
+                    let id = self.index(name);
+                    let value = match assignment.get(name.as_str()) {
+                        Some(value) => value,
+                        None => panic!("Node `{}` is not assigned", name),
+                    };
+                    let position = match self.domains[id].iter().position(|state| state == value) {
+                        Some(position) => position,
+                        None => panic!("Value {:?} is not a state of `{}`", value, name),
+                    };
+                    index = index * self.domains[id].len() + position;
+                }
+                factor.values[index]
+            })
+            .product()
+    }
+
+    /// Write the network in the UAI format as a MARKOV network. Variables are numbered in
+    /// node order and states in the order of `domain`.
+    pub fn to_uai(&self) -> String {
+        let nodes = self.graph.nodes();
+        let mut s = String::new();
+        s.push_str("MARKOV\n");
+        s.push_str(&format!("{}\n", nodes.len()));
+        let cardinalities = self
+            .domains
+            .iter()
+            .map(|domain| domain.len().to_string())
+            .collect::<Vec<String>>();
+        s.push_str(&format!("{}\n", cardinalities.join(" ")));
+        s.push_str(&format!("{}\n", self.factors.len()));
+        for factor in &self.factors {
+            let scope = factor
+                .nodes
+                .iter()
+                .map(|name| self.index(name).to_string())
+                .collect::<Vec<String>>();
+            s.push_str(&format!("{} {}\n", scope.len(), scope.join(" ")));
+        }
+        for factor in &self.factors {
+            let values = factor
+                .values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<String>>();
+            s.push_str(&format!("\n{}\n {}\n", values.len(), values.join(" ")));
+        }
+        s
+    }
+
+    fn index(&self, name: &str) -> usize {
+        match self.graph.nodes().iter().position(|node| node == name) {
+            Some(index) => index,
+            None => panic!("Node `{}` not found", name),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Convert the network into a Markov random field over its moral graph, with the CPT of
+    /// each node as a factor over the node and its parents, in node order. The factors
+    /// multiply to the joint distribution, so their normalizing constant is 1.
+    pub fn to_markov_network(&self) -> MarkovNetwork<T> {
+        let domains = (0..self.nodes.len())
+            .map(|id| self.get_node_domain(id))
+            .collect::<Vec<Vec<T>>>();
+        let factors = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut nodes = node.parents.clone();
+                nodes.push(node.id);
+                nodes.sort();
+                let values = configurations(
+                    &nodes
+                        .iter()
+                        .map(|id| domains[*id].clone())
+                        .collect::<Vec<Vec<T>>>(),
+                )
+                .into_iter()
+                .map(|configuration| {
+                    let assignment = nodes
+                        .iter()
+                        .copied()
+                        .zip(configuration)
+                        .collect::<HashMap<NodeId, T>>();
+                    self.local_probability(node, &assignment[&node.id], &assignment)
+                })
+                .collect();
+                CliqueFactor {
+                    nodes: self.node_names_of(&nodes),
+                    values,
+                }
+            })
+            .collect();
+        MarkovNetwork {
+            graph: self.moral_graph(),
+            domains,
+            factors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, confounded, joint, sprinkler, TOLERANCE};
+
+    #[test]
+    fn factors_multiply_to_the_joint_distribution() {
+        let network = sprinkler();
+        let markov = network.to_markov_network();
+        assert_eq!(markov.graph(), &network.moral_graph());
+        assert_eq!(markov.factors().len(), 4);
+        let mut total = 0.0;
+        for (assignment, prob) in joint(&network) {
+            let named = assignment
+                .iter()
+                .map(|(id, value)| (network.get_node_name(*id), *value))
+                .collect::<HashMap<&str, bool>>();
+            let product = markov.unnormalized_probability(&named);
+            assert_close(product, prob, TOLERANCE);
+            total += product;
+        }
+        assert_close(total, 1.0, TOLERANCE);
+    }
+
+    #[test]
+    fn to_uai_lists_the_scopes_and_tables() {
+        let markov = confounded().to_markov_network();
+        assert_eq!(markov.domain("x"), [true, false]);
+        let factor = &markov.factors()[1];
+        assert_eq!(factor.nodes, ["z", "x"]);
+        for (value, expected) in factor.values.iter().zip([0.8, 0.2, 0.3, 0.7]) {
+            assert_close(*value, expected, TOLERANCE);
+        }
+        let uai = markov.to_uai();
+        assert!(uai.starts_with("MARKOV\n3\n2 2 2\n3\n1 0\n2 0 1\n3 0 1 2\n"));
+        assert!(uai.contains("\n2\n 0.4 0.6\n"));
+    }
+}
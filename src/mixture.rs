@@ -0,0 +1,355 @@
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{Name, NodeId},
+    gaussian::{ClgNetwork, Gaussian},
+    hybrid::standard_normal,
+};
+
+/// A mixture of normal distributions, each with a weight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GaussianMixture {
+    components: Vec<(f64, Gaussian)>,
+}
+
+impl GaussianMixture {
+    /// Create a mixture of the given weighted components, whose weights must sum to 1.
+    pub fn new(components: Vec<(f64, Gaussian)>) -> GaussianMixture {
+        if components.is_empty() {
+            panic!("Gaussian mixture has no components");
+        }
+        if components
+            .iter()
+            .any(|(weight, _)| !(0.0..=1.0).contains(weight))
+        {
+            panic!("Gaussian mixture has a weight outside 0 and 1");
+        }
+        let sum = components.iter().map(|(weight, _)| weight).sum::<f64>();
+        if (sum - 1.0).abs() > 0.0000001 {
+            panic!("Gaussian mixture weights sum to {} instead of 1.0", sum);
+        }
+        GaussianMixture { components }
+    }
+
+    /// Get the weighted components.
+    pub fn components(&self) -> &[(f64, Gaussian)] {
+        &self.components
+    }
+
+    /// Get the density at `x`.
+    pub fn density(&self, x: f64) -> f64 {
+        self.components
+            .iter()
+            .map(|(weight, gaussian)| weight * gaussian.density(x))
+            .sum()
+    }
+
+    /// Get the Gaussian with the mean and variance of the mixture.
+    pub fn moments(&self) -> Gaussian {
+        let mean = self
+            .components
+            .iter()
+            .map(|(weight, gaussian)| weight * gaussian.mean)
+            .sum::<f64>();
+        let second = self
+            .components
+            .iter()
+            .map(|(weight, gaussian)| weight * (gaussian.variance + gaussian.mean.powi(2)))
+            .sum::<f64>();
+        Gaussian {
+            mean,
+            variance: (second - mean * mean).max(0.0),
+        }
+    }
+
+    /// Draw a value with the given random number generator.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let mut u = rng.gen::<f64>();
+        let mut chosen = self.components[self.components.len() - 1].1;
+        for (weight, gaussian) in &self.components {
+            if u < *weight {
+                chosen = *gaussian;
+                break;
+            }
+            u -= weight;
+        }
+        chosen.mean + chosen.variance.sqrt() * standard_normal(rng)
+    }
+
+    /// Fit a mixture of `components` Gaussians to `values` by expectation maximization,
+    /// starting from means at evenly spaced quantiles and stopping after `iterations` steps
+    /// or once the log likelihood no longer improves. The values must be finite.
+    pub fn fit(values: &[f64], components: usize, iterations: usize) -> GaussianMixture {
+        if values.is_empty() {
+            panic!("Cannot fit a Gaussian mixture to no values");
+        }
+        if let Some(value) = values.iter().find(|value| !value.is_finite()) {
+            panic!("Cannot fit a Gaussian mixture to the value {}", value);
+        }
+        let components = components.clamp(1, values.len());
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        // Keeps a component from collapsing onto a single value.
+        let floor = (variance * 1e-6).max(1e-12);
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mut mixture = (0..components)
+            .map(|k| {
+                let quantile = sorted[(2 * k + 1) * sorted.len() / (2 * components)];
+                (
+                    1.0 / components as f64,
+                    Gaussian::new(quantile, variance.max(floor)),
+                )
+            })
+            .collect::<Vec<(f64, Gaussian)>>();
+
+        let mut likelihood = f64::NEG_INFINITY;
+        for _ in 0..iterations {
+            // E step: the responsibility of each component for each value.
+            let mut responsibilities = vec![vec![0.0; components]; values.len()];
+            let mut log_likelihood = 0.0;
+            for (x, row) in values.iter().zip(&mut responsibilities) {
+                for ((weight, gaussian), r) in mixture.iter().zip(row.iter_mut()) {
+                    *r = weight * gaussian.density(*x);
+                }
+                let total = row.iter().sum::<f64>();
+                if total <= 0.0 {
+                    row.iter_mut().for_each(|r| *r = 1.0 / components as f64);
+                    log_likelihood += f64::MIN_POSITIVE.ln();
+                } else {
+                    row.iter_mut().for_each(|r| *r /= total);
+                    log_likelihood += total.ln();
+                }
+            }
+            if log_likelihood - likelihood <= 1e-10 * log_likelihood.abs() {
+                break;
+            }
+            likelihood = log_likelihood;
+
+            // M step: the weighted maximum likelihood weights, means and variances.
+            for (k, (weight, gaussian)) in mixture.iter_mut().enumerate() {
+                let total = responsibilities.iter().map(|row| row[k]).sum::<f64>();
+                if total <= 0.0 {
+                    continue;
+                }
+                let mean = values
+                    .iter()
+                    .zip(&responsibilities)
+                    .map(|(x, row)| row[k] * x)
+                    .sum::<f64>()
+                    / total;
+                let variance = values
+                    .iter()
+                    .zip(&responsibilities)
+                    .map(|(x, row)| row[k] * (x - mean).powi(2))
+                    .sum::<f64>()
+                    / total;
+                *weight = total / n;
+                *gaussian = Gaussian::new(mean, variance.max(floor));
+            }
+        }
+        GaussianMixture {
+            components: mixture,
+        }
+    }
+}
+
+/// A continuous leaf node of a `ClgNetwork` with a Gaussian mixture for each configuration of
+/// its discrete parents.
+#[derive(Clone, Debug)]
+pub(crate) struct MixtureNode<T> {
+    pub(crate) name: Name,
+    pub(crate) discrete_parents: Vec<NodeId>,
+    pub(crate) mixtures: HashMap<Vec<T>, GaussianMixture>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> ClgNetwork<T> {
+    /// Add a continuous node with a Gaussian mixture for each configuration of its discrete
+    /// parents, keyed by their values in order. Mixture nodes have no continuous parents and
+    /// cannot be parents of other nodes.
+    pub fn add_gaussian_mixture(
+        &mut self,
+        name: &str,
+        discrete_parents: Vec<&str>,
+        mixtures: HashMap<Vec<T>, GaussianMixture>,
+    ) {
+        if self.discrete.node_map.contains_key(name)
+            || self.continuous_index(name).is_some()
+            || self.mixture_index(name).is_some()
+        {
+            panic!("Node `{}` already exists", name);
+        }
+        let discrete_parents = discrete_parents
+            .iter()
+            .map(|parent| match self.discrete.node_map.get(*parent) {
+                Some(id) => *id,
+                None => panic!("Discrete parent node `{}` not found", parent),
+            })
+            .collect::<Vec<NodeId>>();
+        for key in mixtures.keys() {
+            if key.len() != discrete_parents.len() {
+                panic!(
+                    "Gaussian mixture key {:?} of `{}` does not match its {} discrete parents",
+                    key,
+                    name,
+                    discrete_parents.len()
+                );
+            }
+        }
+        self.mixtures.push(MixtureNode {
+            name: name.to_string(),
+            discrete_parents,
+            mixtures,
+        });
+    }
+
+    /// Fit the mixtures of a mixture node by expectation maximization, each with
+    /// `components` Gaussians, from records of its discrete parent values in order and its
+    /// value. Configurations without records keep their mixture.
+    pub fn fit_gaussian_mixture(
+        &mut self,
+        name: &str,
+        records: &[(Vec<T>, f64)],
+        components: usize,
+        iterations: usize,
+    ) {
+        let index = match self.mixture_index(name) {
+            Some(index) => index,
+            None => panic!("Mixture node `{}` not found", name),
+        };
+        let node = &mut self.mixtures[index];
+        let mut groups: HashMap<Vec<T>, Vec<f64>> = HashMap::new();
+        for (key, value) in records {
+            if key.len() != node.discrete_parents.len() {
+                panic!(
+                    "Record {:?} of `{}` does not match its {} discrete parents",
+                    key,
+                    name,
+                    node.discrete_parents.len()
+                );
+            }
+            groups.entry(key.clone()).or_default().push(*value);
+        }
+        for (key, values) in groups {
+            node.mixtures
+                .insert(key, GaussianMixture::fit(&values, components, iterations));
+        }
+    }
+
+    pub(crate) fn mixture_index(&self, name: &str) -> Option<usize> {
+        self.mixtures.iter().position(|node| node.name == name)
+    }
+
+    /// Check continuous evidence on mixture nodes and key it by mixture node index.
+    pub(crate) fn mixture_evidence(
+        &self,
+        continuous_evidence: &HashMap<&str, f64>,
+    ) -> HashMap<usize, f64> {
+        continuous_evidence
+            .iter()
+            .filter_map(|(name, value)| self.mixture_index(name).map(|index| (index, *value)))
+            .collect()
+    }
+
+    /// The mixture of a mixture node given values of at least its discrete parents.
+    pub(crate) fn mixture(
+        &self,
+        index: usize,
+        assignment: &HashMap<NodeId, T>,
+    ) -> &GaussianMixture {
+        let node = &self.mixtures[index];
+        let key = node
+            .discrete_parents
+            .iter()
+            .map(|parent| assignment[parent].clone())
+            .collect::<Vec<T>>();
+        match node.mixtures.get(&key) {
+            Some(mixture) => mixture,
+            None => panic!(
+                "Mixture node `{}` has no mixture for discrete parents {:?}",
+                node.name, key
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bayesian_network::{BayesianNetwork, NodeType};
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, row};
+
+    /// Draws from 0.3 N(-5, 1) + 0.7 N(5, 4).
+    fn separated() -> (GaussianMixture, Vec<f64>) {
+        let mixture = GaussianMixture::new(vec![
+            (0.3, Gaussian::new(-5.0, 1.0)),
+            (0.7, Gaussian::new(5.0, 4.0)),
+        ]);
+        let mut rng = seeded_rng(8);
+        let values = (0..10_000).map(|_| mixture.sample(&mut rng)).collect();
+        (mixture, values)
+    }
+
+    /// Assert that `fitted` has the components of `expected`, in order, within `tolerance`.
+    fn assert_components(fitted: &GaussianMixture, expected: &GaussianMixture, tolerance: f64) {
+        assert_eq!(fitted.components().len(), expected.components().len());
+        for ((weight, gaussian), (expected_weight, expected_gaussian)) in
+            fitted.components().iter().zip(expected.components())
+        {
+            assert_close(*weight, *expected_weight, tolerance);
+            assert_close(gaussian.mean, expected_gaussian.mean, tolerance * 5.0);
+            assert_close(
+                gaussian.variance,
+                expected_gaussian.variance,
+                tolerance * 10.0,
+            );
+        }
+    }
+
+    #[test]
+    fn fit_recovers_well_separated_components() {
+        let (mixture, values) = separated();
+        let fitted = GaussianMixture::fit(&values, 2, 200);
+        assert_components(&fitted, &mixture, 0.02);
+        let sum = fitted
+            .components()
+            .iter()
+            .map(|(weight, _)| weight)
+            .sum::<f64>();
+        assert_close(sum, 1.0, 1e-9);
+    }
+
+    #[test]
+    fn fit_gaussian_mixture_fits_each_parent_configuration() {
+        let (mixture, values) = separated();
+        let mut discrete = BayesianNetwork::new(vec![true, false]);
+        discrete.add_node("s", NodeType::Root(row(0.5)));
+        let mut network = ClgNetwork::new(discrete);
+        let initial = GaussianMixture::new(vec![(1.0, Gaussian::new(0.0, 1.0))]);
+        network.add_gaussian_mixture(
+            "x",
+            vec!["s"],
+            HashMap::from([
+                (vec![true], initial.clone()),
+                (vec![false], initial.clone()),
+            ]),
+        );
+        let records = values
+            .into_iter()
+            .map(|value| (vec![true], value))
+            .collect::<Vec<(Vec<bool>, f64)>>();
+        network.fit_gaussian_mixture("x", &records, 2, 200);
+        let assignment = |value| HashMap::from([(0, value)]);
+        assert_components(network.mixture(0, &assignment(true)), &mixture, 0.02);
+        assert_eq!(network.mixture(0, &assignment(false)), &initial);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot fit a Gaussian mixture to the value NaN")]
+    fn fit_rejects_nan() {
+        GaussianMixture::fit(&[1.0, f64::NAN, 2.0], 2, 10);
+    }
+}
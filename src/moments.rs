@@ -0,0 +1,164 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Probability},
+    posterior::Posterior,
+};
+
+impl<T: Clone + PartialEq> Posterior<T> {
+    /// Get the expected value of a node whose states have the numeric values given by
+    /// `value`.
+    pub fn expected_value_by<F: Fn(&T) -> f64>(&self, name: &str, value: F) -> Option<f64> {
+        Some(
+            self.distribution(name)?
+                .iter()
+                .map(|(state, prob)| prob * value(state))
+                .sum(),
+        )
+    }
+
+    /// Get the variance of a node whose states have the numeric values given by `value`.
+    pub fn variance_by<F: Fn(&T) -> f64>(&self, name: &str, value: F) -> Option<f64> {
+        let mean = self.expected_value_by(name, &value)?;
+        self.expected_value_by(name, |state| (value(state) - mean).powi(2))
+    }
+
+    /// Get the `q` quantile of a node whose states have the numeric values given by `value`:
+    /// the state of smallest value whose cumulative probability reaches `q`.
+    pub fn quantile_by<F: Fn(&T) -> f64>(&self, name: &str, q: f64, value: F) -> Option<T> {
+        if !(0.0..=1.0).contains(&q) {
+            panic!("Quantile {} is outside 0 and 1", q);
+        }
+        let mut states = self
+            .distribution(name)?
+            .iter()
+            .filter(|(_, prob)| *prob > 0.0)
+            .map(|(state, prob)| (value(state), state, *prob))
+            .collect::<Vec<(f64, &T, Probability)>>();
+        states.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut cumulative = 0.0;
+        for (_, state, prob) in &states {
+            cumulative += prob;
+            if cumulative >= q - 0.0000001 {
+                return Some((*state).clone());
+            }
+        }
+        states.last().map(|(_, state, _)| (*state).clone())
+    }
+}
+
+impl<T: Clone + PartialEq + Copy + Into<f64>> Posterior<T> {
+    /// Get the expected value of a node with numeric states.
+    pub fn expected_value(&self, name: &str) -> Option<f64> {
+        self.expected_value_by(name, |state| (*state).into())
+    }
+
+    /// Get the variance of a node with numeric states.
+    pub fn variance(&self, name: &str) -> Option<f64> {
+        self.variance_by(name, |state| (*state).into())
+    }
+
+    /// Get the `q` quantile of a node with numeric states.
+    pub fn quantile(&self, name: &str, q: f64) -> Option<T> {
+        self.quantile_by(name, q, |state| (*state).into())
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Copy + Into<f64>> BayesianNetwork<T> {
+    /// Get the expected value of a node with numeric states given `evidence`.
+    pub fn expected_value(&self, name: &str, evidence: &HashMap<&str, T>) -> f64 {
+        match self.posterior(evidence).expected_value(name) {
+            Some(mean) => mean,
+            None => panic!("Node `{}` not found", name),
+        }
+    }
+
+    /// Get the variance of a node with numeric states given `evidence`.
+    pub fn variance(&self, name: &str, evidence: &HashMap<&str, T>) -> f64 {
+        match self.posterior(evidence).variance(name) {
+            Some(variance) => variance,
+            None => panic!("Node `{}` not found", name),
+        }
+    }
+
+    /// Get the `q` quantile of a node with numeric states given `evidence`, such as its
+    /// median for `q = 0.5`.
+    pub fn quantile(&self, name: &str, evidence: &HashMap<&str, T>, q: f64) -> T {
+        match self.posterior(evidence).quantile(name, q) {
+            Some(state) => state,
+            None => panic!("Node `{}` not found", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bayesian_network::NodeType,
+        testing::{assert_close, brute_force_probability, TOLERANCE},
+    };
+
+    /// A count `n` from 1 to 4 with probabilities .1 to .4, and a `report` of it that is
+    /// 1 when n is at most 2 and 2 otherwise, with one error in ten.
+    fn count() -> BayesianNetwork<u32> {
+        let mut network = BayesianNetwork::new(vec![1, 2, 3, 4]);
+        network.add_node(
+            "n",
+            NodeType::Root(HashMap::from([(1, 0.1), (2, 0.2), (3, 0.3), (4, 0.4)])),
+        );
+        network.add_node("report", NodeType::Leaf);
+        network.set_node_states("report", vec![1, 2]);
+        let low = HashMap::from([(1, 0.9), (2, 0.1)]);
+        let high = HashMap::from([(1, 0.1), (2, 0.9)]);
+        network.add_dependency(
+            vec!["n"],
+            "report",
+            HashMap::from([
+                (vec![1], low.clone()),
+                (vec![2], low),
+                (vec![3], high.clone()),
+                (vec![4], high),
+            ]),
+        );
+        network
+    }
+
+    #[test]
+    fn moments_of_a_numeric_node() {
+        let network = count();
+        let none = HashMap::new();
+        assert_close(network.expected_value("n", &none), 3.0, TOLERANCE);
+        assert_close(network.variance("n", &none), 1.0, TOLERANCE);
+        let evidence = HashMap::from([("report", 1)]);
+        let probabilities =
+            [1, 2, 3, 4].map(|n| brute_force_probability(&network, "n", &n, &evidence));
+        let mean = (0..4)
+            .map(|i| (i + 1) as f64 * probabilities[i])
+            .sum::<f64>();
+        assert_close(network.expected_value("n", &evidence), mean, TOLERANCE);
+        let variance = (0..4)
+            .map(|i| ((i + 1) as f64 - mean).powi(2) * probabilities[i])
+            .sum::<f64>();
+        assert_close(network.variance("n", &evidence), variance, TOLERANCE);
+    }
+
+    #[test]
+    fn quantile_is_the_first_state_reaching_the_cumulative_probability() {
+        let network = count();
+        let none = HashMap::new();
+        for (q, expected) in [(0.0, 1), (0.1, 1), (0.25, 2), (0.3, 2), (0.5, 3), (1.0, 4)] {
+            assert_eq!(network.quantile("n", &none, q), expected, "quantile {}", q);
+        }
+        // States are ordered by the given values, here from the largest count down.
+        let posterior = network.posterior(&none);
+        assert_eq!(posterior.quantile_by("n", 0.5, |n| -f64::from(*n)), Some(3));
+        assert_eq!(posterior.quantile("missing", 0.5), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quantile 1.5 is outside 0 and 1")]
+    fn quantile_rejects_levels_outside_the_unit_interval() {
+        count().quantile("n", &HashMap::new(), 1.5);
+    }
+}
@@ -0,0 +1,210 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeType, Probability},
+    error::Error,
+};
+
+type Rows<T> = HashMap<Vec<T>, HashMap<T, Probability>>;
+type SharedCpts<T> = HashMap<Name, Rows<T>>;
+type NodeRows<T> = (NodeType<T>, Rows<T>);
+
+/// What to do with a row of probabilities that does not sum to 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalizationPolicy {
+    /// Reject the row with `Error::Unnormalized`.
+    Strict,
+    /// Rescale the row to sum to 1. Rows with a negative probability or a sum of 0 are kept as
+    /// they are, for `validate` to report.
+    Lenient,
+}
+
+/// How a network handles unnormalized rows: its priors, CPT rows and shared CPT rows, whether
+/// given when the network is built, read from a file or learned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normalization {
+    pub policy: NormalizationPolicy,
+    /// How far from 1 the sum of a row may be before the policy applies.
+    pub tolerance: Probability,
+}
+
+impl Default for Normalization {
+    /// Lenient, with a tolerance of 1e-7.
+    fn default() -> Self {
+        Normalization {
+            policy: NormalizationPolicy::Lenient,
+            tolerance: 0.0000001,
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get how the network handles unnormalized rows.
+    pub fn get_normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    /// Set how the network handles unnormalized rows, and apply it to every row already in
+    /// the network. If the policy is strict and a row is unnormalized, return
+    /// `Error::Unnormalized` without changing the network.
+    pub fn set_normalization(&mut self, normalization: Normalization) -> Result<(), Error> {
+        let previous = self.normalization;
+        self.normalization = normalization;
+        match self.normalized_rows() {
+            Ok((nodes, shared_cpts)) => {
+                for (node, (node_type, probability)) in self.nodes.iter_mut().zip(nodes) {
+                    node.node_type = node_type;
+                    node.probability = probability;
+                }
+                self.shared_cpts = shared_cpts;
+                Ok(())
+            }
+            Err(error) => {
+                self.normalization = previous;
+                Err(error)
+            }
+        }
+    }
+
+    /// Apply the normalization policy to every row after learning. Panics if the policy is
+    /// strict and a learned row is unnormalized.
+    pub(crate) fn normalize_learned(&mut self) {
+        if let Err(error) = self.set_normalization(self.normalization) {
+            panic!("{}", error);
+        }
+    }
+
+    /// The prior and CPT of every node and every shared CPT, with the policy applied.
+    fn normalized_rows(&self) -> Result<(Vec<NodeRows<T>>, SharedCpts<T>), Error> {
+        let mut nodes = Vec::new();
+        for node in &self.nodes {
+            let name = self.get_node_name(node.id);
+            let mut node_type = node.node_type.clone();
+            if let NodeType::Root(prob_map) = &mut node_type {
+                self.normalize_row(name, &[], prob_map)?;
+            }
+            let mut probability = node.probability.clone();
+            for (key, row) in &mut probability {
+                self.normalize_row(name, key, row)?;
+            }
+            nodes.push((node_type, probability));
+        }
+        let mut shared_cpts = self.shared_cpts.clone();
+        for (name, cpt) in &mut shared_cpts {
+            for (key, row) in cpt {
+                self.normalize_row(name, key, row)?;
+            }
+        }
+        Ok((nodes, shared_cpts))
+    }
+
+    /// Apply the normalization policy to the row of `name` (a node or shared CPT) for the
+    /// parent values `key`.
+    pub(crate) fn normalize_row(
+        &self,
+        name: &str,
+        key: &[T],
+        row: &mut HashMap<T, Probability>,
+    ) -> Result<(), Error> {
+        let sum = row.values().sum::<Probability>();
+        if (sum - 1.0).abs() <= self.normalization.tolerance {
+            return Ok(());
+        }
+        match self.normalization.policy {
+            NormalizationPolicy::Strict => Err(Error::Unnormalized {
+                name: name.to_string(),
+                row: if key.is_empty() {
+                    String::new()
+                } else {
+                    format!("{:?}", key)
+                },
+                sum,
+            }),
+            NormalizationPolicy::Lenient => {
+                if sum > 0.0 && row.values().all(|prob| *prob >= 0.0) {
+                    for prob in row.values_mut() {
+                        *prob /= sum;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, row, TOLERANCE};
+
+    fn strict() -> Normalization {
+        Normalization {
+            policy: NormalizationPolicy::Strict,
+            tolerance: 1e-9,
+        }
+    }
+
+    fn two_nodes(normalization: Normalization) -> BayesianNetwork<bool> {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.set_normalization(normalization).unwrap();
+        network.add_node("a", NodeType::Root(row(0.5)));
+        network.add_node("b", NodeType::Leaf);
+        network
+    }
+
+    #[test]
+    fn strict_policy_rejects_unnormalized_rows() {
+        let mut network = two_nodes(strict());
+        let prob = HashMap::from([
+            (vec![true], HashMap::from([(true, 0.6), (false, 0.6)])),
+            (vec![false], row(0.1)),
+        ]);
+        match network.try_add_dependency(vec!["a"], "b", prob) {
+            Err(Error::Unnormalized { name, row, sum }) => {
+                assert_eq!(name, "b");
+                assert_eq!(row, "[true]");
+                assert_close(sum, 1.2, TOLERANCE);
+            }
+            other => panic!("expected an unnormalized row, got {:?}", other),
+        }
+        assert!(network.nodes[network.get_node_index("b")]
+            .parents
+            .is_empty());
+    }
+
+    #[test]
+    fn lenient_policy_rescales_rows() {
+        let mut network = two_nodes(Normalization::default());
+        network.add_dependency(
+            vec!["a"],
+            "b",
+            HashMap::from([
+                (vec![true], HashMap::from([(true, 0.6), (false, 0.6)])),
+                (vec![false], row(0.1)),
+            ]),
+        );
+        let b = &network.nodes[network.get_node_index("b")];
+        assert_close(b.probability[&vec![true]][&true], 0.5, TOLERANCE);
+        assert_close(b.probability[&vec![true]][&false], 0.5, TOLERANCE);
+    }
+
+    #[test]
+    fn set_normalization_keeps_the_previous_policy_on_error() {
+        let mut network = bonus();
+        let mood = network.get_node_index("mood");
+        network.nodes[mood].probability.insert(
+            vec![true, true],
+            HashMap::from([(true, 0.99), (false, 0.02)]),
+        );
+        match network.set_normalization(strict()) {
+            Err(Error::Unnormalized { name, .. }) => assert_eq!(name, "mood"),
+            other => panic!("expected an unnormalized row, got {:?}", other),
+        }
+        assert_eq!(network.get_normalization(), Normalization::default());
+
+        network.set_normalization(Normalization::default()).unwrap();
+        let row = &network.nodes[mood].probability[&vec![true, true]];
+        assert_close(row[&true] + row[&false], 1.0, TOLERANCE);
+        network.set_normalization(strict()).unwrap();
+    }
+}
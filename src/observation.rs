@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use crate::{
+    bayesian_network::BayesianNetwork,
+    gaussian::{ClgNetwork, HybridPosterior},
+    hybrid::HybridEngine,
+    posterior::Posterior,
+};
+
+/// An observation of a node: one of its states, or a real number, which is taken as is by
+/// a Gaussian node and mapped through the stored bins of a discretized node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Observation<T> {
+    State(T),
+    Value(f64),
+}
+
+impl<T> From<f64> for Observation<T> {
+    fn from(value: f64) -> Self {
+        Observation::Value(value)
+    }
+}
+
+impl BayesianNetwork<String> {
+    /// Turn observations into evidence, mapping real numbers to the states of discretized
+    /// nodes with `bin_value`.
+    pub fn observed_evidence<'a>(
+        &self,
+        observations: &HashMap<&'a str, Observation<String>>,
+    ) -> HashMap<&'a str, String> {
+        observations
+            .iter()
+            .map(|(name, observation)| match observation {
+                Observation::State(state) => (*name, state.clone()),
+                Observation::Value(value) => (*name, self.bin_value(name, *value)),
+            })
+            .collect()
+    }
+
+    /// Infer the posterior of every node given observations, as `posterior`.
+    pub fn posterior_observed(
+        &self,
+        observations: &HashMap<&str, Observation<String>>,
+    ) -> Posterior<String> {
+        self.posterior(&self.observed_evidence(observations))
+    }
+}
+
+impl ClgNetwork<String> {
+    /// Infer the posterior of every node exactly given observations, as `infer`. Real
+    /// numbers are evidence on Gaussian nodes, and are mapped through the bins of
+    /// discretized discrete nodes.
+    pub fn infer_observed(
+        &self,
+        observations: &HashMap<&str, Observation<String>>,
+    ) -> HybridPosterior<String> {
+        let (evidence, continuous_evidence) = self.split_observations(observations);
+        self.infer(&evidence, &continuous_evidence)
+    }
+
+    /// Infer the posterior of every node given observations with the given engine, as
+    /// `posterior`.
+    pub fn posterior_observed(
+        &self,
+        observations: &HashMap<&str, Observation<String>>,
+        engine: &HybridEngine,
+    ) -> HybridPosterior<String> {
+        let (evidence, continuous_evidence) = self.split_observations(observations);
+        self.posterior(&evidence, &continuous_evidence, engine)
+    }
+
+    fn split_observations<'a>(
+        &self,
+        observations: &HashMap<&'a str, Observation<String>>,
+    ) -> (HashMap<&'a str, String>, HashMap<&'a str, f64>) {
+        let mut evidence = HashMap::new();
+        let mut continuous_evidence = HashMap::new();
+        for (name, observation) in observations {
+            match observation {
+                Observation::State(state) => {
+                    if self.continuous_index(name).is_some() || self.mixture_index(name).is_some() {
+                        panic!("Continuous node `{}` observed as state {:?}", name, state);
+                    }
+                    evidence.insert(*name, state.clone());
+                }
+                Observation::Value(value) => {
+                    if self.continuous_index(name).is_some() || self.mixture_index(name).is_some() {
+                        continuous_evidence.insert(*name, *value);
+                    } else {
+                        evidence.insert(*name, self.discrete.bin_value(name, *value));
+                    }
+                }
+            }
+        }
+        (evidence, continuous_evidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bayesian_network::NodeType,
+        discretize::Bins,
+        gaussian::{Gaussian, LinearGaussian},
+        testing::{assert_close, TOLERANCE},
+    };
+
+    const LOW: &str = "[-inf,5)";
+    const HIGH: &str = "[5,inf)";
+
+    /// A discretized reading `r` whose bins are split at 5, and a noisy `alarm` it sets off.
+    fn discretized() -> BayesianNetwork<String> {
+        let states = vec![LOW.to_string(), HIGH.to_string()];
+        let row = |p: f64| HashMap::from([(LOW.to_string(), 1.0 - p), (HIGH.to_string(), p)]);
+        let mut network = BayesianNetwork::new(states);
+        network.add_node("r", NodeType::Root(row(0.2)));
+        network.add_node("alarm", NodeType::Leaf);
+        network.add_dependency(
+            vec!["r"],
+            "alarm",
+            HashMap::from([
+                (vec![LOW.to_string()], row(0.1)),
+                (vec![HIGH.to_string()], row(0.9)),
+            ]),
+        );
+        network.set_node_bins("r", &Bins::new(vec![5.0]));
+        network
+    }
+
+    #[test]
+    fn values_are_mapped_through_the_bins() {
+        let network = discretized();
+        let observations = HashMap::from([
+            ("r", Observation::from(7.5)),
+            ("alarm", Observation::State(LOW.to_string())),
+        ]);
+        let evidence = network.observed_evidence(&observations);
+        assert_eq!(evidence["r"], HIGH);
+        assert_eq!(evidence["alarm"], LOW);
+        assert_eq!(
+            network.posterior_observed(&HashMap::from([("r", Observation::Value(2.0))])),
+            network.posterior(&HashMap::from([("r", LOW.to_string())]))
+        );
+    }
+
+    #[test]
+    fn clg_observations_split_into_states_and_values() {
+        // The reading selects the mean of x: 0 below 5 and 10 above.
+        let mut network = ClgNetwork::new(discretized());
+        network.add_linear_gaussian(
+            "x",
+            vec!["r"],
+            Vec::new(),
+            HashMap::from([
+                (
+                    vec![LOW.to_string()],
+                    LinearGaussian::new(0.0, Vec::new(), 1.0),
+                ),
+                (
+                    vec![HIGH.to_string()],
+                    LinearGaussian::new(10.0, Vec::new(), 1.0),
+                ),
+            ]),
+        );
+        let posterior = network.infer_observed(&HashMap::from([("r", Observation::Value(7.5))]));
+        assert_close(posterior.mean("x").unwrap(), 10.0, TOLERANCE);
+        let posterior = network.infer_observed(&HashMap::from([("x", Observation::Value(3.0))]));
+        assert_eq!(posterior.gaussian("x"), Some(Gaussian::new(3.0, 0.0)));
+        // The density of 3 is N(3; 0, 1) = e^{-4.5} / sqrt(2 pi) below and e^{-24.5} above.
+        let low = 0.8 * (-4.5_f64).exp();
+        let high = 0.2 * (-24.5_f64).exp();
+        assert_close(
+            posterior.probability("r", &HIGH.to_string()).unwrap(),
+            high / (low + high),
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Continuous node `x` observed as state")]
+    fn clg_rejects_states_of_continuous_nodes() {
+        let mut network = ClgNetwork::new(discretized());
+        network.add_gaussian("x", Gaussian::new(0.0, 1.0));
+        network.infer_observed(&HashMap::from([("x", Observation::State(LOW.to_string()))]));
+    }
+}
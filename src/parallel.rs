@@ -0,0 +1,161 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, NodeId},
+    dataset::Dataset,
+    sampling::{resample, PosteriorSampler},
+};
+
+/// The number of draws made with one RNG stream. Work is split into chunks of this size
+/// regardless of the number of threads, so seeded results do not depend on the machine.
+const CHUNK: usize = 1024;
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Send + Sync> BayesianNetwork<T> {
+    /// Draw `n` complete joint assignments like `sample`, splitting the work across threads.
+    pub fn par_sample(&self, n: usize) -> Dataset<T> {
+        self.par_sample_with_rng(n, &mut rand::thread_rng())
+    }
+
+    /// Draw `n` complete joint assignments like `par_sample`. `rng` seeds an independent
+    /// stream for each chunk of draws.
+    pub fn par_sample_with_rng<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Dataset<T> {
+        let order = self.topological_order();
+        let evidence = HashMap::new();
+        let samples = par_chunks(n, rng, |count, rng| {
+            (0..count)
+                .map(|_| self.weighted_sample(&order, &evidence, rng).0)
+                .collect()
+        });
+        self.to_dataset(&samples)
+    }
+
+    /// Draw `n` complete assignments from the posterior given `evidence` like
+    /// `sample_posterior`, splitting the work across threads.
+    pub fn par_sample_posterior(&self, evidence: &HashMap<&str, T>, n: usize) -> Dataset<T> {
+        self.par_sample_posterior_with_rng(
+            evidence,
+            n,
+            &PosteriorSampler::default(),
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Draw `n` complete assignments from the posterior given `evidence` with `sampler`,
+    /// splitting the work across threads. Likelihood weighting draws its particles in
+    /// parallel and resamples them together; Gibbs sampling runs an independent chain, with
+    /// its own burn-in, for each chunk of 1024 samples. `rng` seeds an independent stream for
+    /// each chunk.
+    pub fn par_sample_posterior_with_rng<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<&str, T>,
+        n: usize,
+        sampler: &PosteriorSampler,
+        rng: &mut R,
+    ) -> Dataset<T> {
+        let evidence = self.evidence_ids(evidence);
+        if n == 0 {
+            return self.to_dataset(&[]);
+        }
+        let samples = match sampler {
+            PosteriorSampler::LikelihoodWeighting { particles } => {
+                let order = self.topological_order();
+                let particles = par_chunks((*particles).max(n), rng, |count, rng| {
+                    (0..count)
+                        .map(|_| self.weighted_sample(&order, &evidence, rng))
+                        .collect()
+                });
+                resample(&particles, n, rng)
+            }
+            PosteriorSampler::Gibbs { burn_in, thin } => par_chunks(n, rng, |count, rng| {
+                self.gibbs_chain(&evidence, count, *burn_in, *thin, rng)
+            }),
+        };
+        self.to_dataset(&samples)
+    }
+
+    fn to_dataset(&self, samples: &[HashMap<NodeId, T>]) -> Dataset<T> {
+        let mut dataset = Dataset::new(self.node_names());
+        for assignment in samples {
+            dataset.add_row(self.assignment_row(assignment));
+        }
+        dataset
+    }
+}
+
+/// Split `n` draws into chunks, run `draw(count, rng)` for each chunk in parallel with its
+/// own RNG seeded from `rng`, and concatenate the results in chunk order.
+fn par_chunks<U, R, F>(n: usize, rng: &mut R, draw: F) -> Vec<U>
+where
+    U: Send,
+    R: Rng + ?Sized,
+    F: Fn(usize, &mut StdRng) -> Vec<U> + Sync,
+{
+    let chunks = (0..n.div_ceil(CHUNK))
+        .map(|i| (CHUNK.min(n - i * CHUNK), rng.gen::<u64>()))
+        .collect::<Vec<(usize, u64)>>();
+    chunks
+        .into_par_iter()
+        .map(|(count, seed)| draw(count, &mut StdRng::seed_from_u64(seed)))
+        .collect::<Vec<Vec<U>>>()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, brute_force_probability, confounded};
+
+    #[test]
+    fn par_chunks_keep_chunk_order_and_seeds() {
+        let draw = |n| {
+            par_chunks(n, &mut seeded_rng(1), |count, rng| {
+                (0..count).map(|_| rng.gen::<u32>()).collect()
+            })
+        };
+        let draws = draw(2 * CHUNK + 5);
+        assert_eq!(draws.len(), 2 * CHUNK + 5);
+        assert_eq!(draws, draw(2 * CHUNK + 5));
+        // A longer run only adds chunks after the ones it shares.
+        assert_eq!(draw(CHUNK)[..], draws[..CHUNK]);
+    }
+
+    #[test]
+    fn parallel_samples_match_enumeration() {
+        let network = confounded();
+        let share = |dataset: &Dataset<bool>, column: usize| {
+            dataset.rows().iter().filter(|row| row[column]).count() as f64 / dataset.len() as f64
+        };
+        let dataset = network.par_sample_with_rng(20_000, &mut seeded_rng(2));
+        assert_close(
+            share(&dataset, 2),
+            brute_force_probability(&network, "y", &true, &HashMap::new()),
+            0.02,
+        );
+        let evidence = HashMap::from([("y", false)]);
+        for sampler in [
+            PosteriorSampler::default(),
+            PosteriorSampler::Gibbs {
+                burn_in: 100,
+                thin: 2,
+            },
+        ] {
+            let dataset = network.par_sample_posterior_with_rng(
+                &evidence,
+                20_000,
+                &sampler,
+                &mut seeded_rng(3),
+            );
+            assert_eq!(dataset.len(), 20_000);
+            assert_close(
+                share(&dataset, 0),
+                brute_force_probability(&network, "z", &true, &evidence),
+                0.03,
+            );
+        }
+    }
+}
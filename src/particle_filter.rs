@@ -0,0 +1,293 @@
+use rand::Rng;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    posterior::Posterior,
+    sampling::resample,
+};
+
+/// A particle filter over a temporal model given as two networks: `prior`, the first time
+/// slice, and `transition`, one slice together with the nodes of the previous slice it
+/// depends on. Evidence arrives one slice at a time and only the weighted particles of the
+/// current slice are kept, so each step costs the same however long the history is.
+pub struct ParticleFilter<'a, T: Clone + PartialEq + Eq + Hash + Debug> {
+    prior: &'a BayesianNetwork<T>,
+    transition: &'a BayesianNetwork<T>,
+    /// Pairs of a previous-slice node and the current-slice node whose value it takes, both
+    /// in `transition`.
+    interface: Vec<(NodeId, NodeId)>,
+    n: usize,
+    /// Assignments to the nodes of the current slice, `prior` at time 1 and `transition`
+    /// afterwards, with their weights.
+    particles: Vec<(HashMap<NodeId, T>, Probability)>,
+    time: usize,
+    log_likelihood: f64,
+}
+
+impl<'a, T: Clone + PartialEq + Eq + Hash + Debug> ParticleFilter<'a, T> {
+    /// Create a filter with `n` particles. `interface` pairs each node of `transition` that
+    /// stands for the previous slice with the node of the slice whose value it takes, such as
+    /// `("Rain_prev", "Rain")`; the second node of each pair must also be in `prior`.
+    pub fn new(
+        prior: &'a BayesianNetwork<T>,
+        transition: &'a BayesianNetwork<T>,
+        interface: &[(&str, &str)],
+        n: usize,
+    ) -> ParticleFilter<'a, T> {
+        if n == 0 {
+            panic!("A particle filter needs at least one particle");
+        }
+        let node = |network: &BayesianNetwork<T>, name: &str| match network.node_map.get(name) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", name),
+        };
+        let interface = interface
+            .iter()
+            .map(|(previous, current)| {
+                node(prior, current);
+                (node(transition, previous), node(transition, current))
+            })
+            .collect();
+        ParticleFilter {
+            prior,
+            transition,
+            interface,
+            n,
+            particles: Vec::new(),
+            time: 0,
+            log_likelihood: 0.0,
+        }
+    }
+
+    /// Get the number of slices filtered so far.
+    pub fn time(&self) -> usize {
+        self.time
+    }
+
+    /// Advance to the next slice and weight the particles by `evidence` about it.
+    pub fn step(&mut self, evidence: &HashMap<&str, T>) {
+        self.step_with_rng(evidence, &mut rand::thread_rng())
+    }
+
+    /// Advance to the next slice like `step`, using `rng`.
+    pub fn step_with_rng<R: Rng + ?Sized>(&mut self, evidence: &HashMap<&str, T>, rng: &mut R) {
+        let particles = if self.time == 0 {
+            let evidence = self.prior.evidence_ids(evidence);
+            let order = self.prior.topological_order();
+            (0..self.n)
+                .map(|_| self.prior.weighted_sample(&order, &evidence, rng))
+                .collect::<Vec<_>>()
+        } else {
+            let previous = self.current();
+            let evidence = self.transition.evidence_ids(evidence);
+            let order = self.transition.topological_order();
+            resample(&self.particles, self.n, rng)
+                .into_iter()
+                .map(|assignment| {
+                    let fixed = self
+                        .interface
+                        .iter()
+                        .map(|(from, to)| {
+                            let id = previous.get_node_index(self.transition.get_node_name(*to));
+                            (*from, assignment[&id].clone())
+                        })
+                        .collect();
+                    self.transition
+                        .weighted_sample_given(&order, &fixed, &evidence, rng)
+                })
+                .collect()
+        };
+        let total = particles
+            .iter()
+            .map(|(_, weight)| weight)
+            .sum::<Probability>();
+        if total <= 0.0 {
+            panic!("Evidence has zero probability");
+        }
+        self.log_likelihood += (total / self.n as Probability).ln();
+        self.particles = particles;
+        self.time += 1;
+    }
+
+    /// Estimate the filtered distribution of every node of the current slice given all the
+    /// evidence so far.
+    pub fn posterior(&self) -> Posterior<T> {
+        if self.time == 0 {
+            panic!("The particle filter has not been stepped yet");
+        }
+        let network = self.current();
+        let total = self
+            .particles
+            .iter()
+            .map(|(_, weight)| weight)
+            .sum::<Probability>();
+        let marginals = network
+            .nodes
+            .iter()
+            .filter(|node| {
+                self.time == 1 || !self.interface.iter().any(|(from, _)| *from == node.id)
+            })
+            .map(|node| {
+                let states = network
+                    .get_node_domain(node.id)
+                    .into_iter()
+                    .map(|value| {
+                        let weight = self
+                            .particles
+                            .iter()
+                            .filter(|(assignment, _)| assignment[&node.id] == value)
+                            .map(|(_, weight)| weight)
+                            .sum::<Probability>();
+                        (value, weight / total)
+                    })
+                    .collect();
+                (network.get_node_name(node.id).to_string(), states)
+            })
+            .collect();
+        Posterior { marginals }
+    }
+
+    /// Get the weighted particles over the nodes of the current slice.
+    pub fn particles(&self) -> Vec<(HashMap<Name, T>, Probability)> {
+        let network = self.current();
+        self.particles
+            .iter()
+            .map(|(assignment, weight)| (network.named_assignment(assignment), *weight))
+            .collect()
+    }
+
+    /// Get the estimated log-probability of all the evidence so far.
+    pub fn log_likelihood(&self) -> f64 {
+        self.log_likelihood
+    }
+
+    /// Get the effective number of particles, `(Σw)² / Σw²`, which falls as the weights
+    /// degenerate.
+    pub fn effective_sample_size(&self) -> f64 {
+        let total = self
+            .particles
+            .iter()
+            .map(|(_, weight)| weight)
+            .sum::<Probability>();
+        let squares = self
+            .particles
+            .iter()
+            .map(|(_, weight)| weight * weight)
+            .sum::<Probability>();
+        if squares > 0.0 {
+            total * total / squares
+        } else {
+            0.0
+        }
+    }
+
+    /// The network whose nodes the particles assign.
+    fn current(&self) -> &'a BayesianNetwork<T> {
+        if self.time <= 1 {
+            self.prior
+        } else {
+            self.transition
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded_rng;
+    use crate::testing::{assert_close, evidence_probability, umbrella};
+
+    const UMBRELLAS: [bool; 3] = [true, true, false];
+
+    /// The evidence of the first `slices` umbrella observations in the unrolled network.
+    fn unrolled_evidence(slices: usize) -> Vec<(String, bool)> {
+        (0..slices)
+            .map(|t| (format!("Umbrella[{}]", t), UMBRELLAS[t]))
+            .collect()
+    }
+
+    #[test]
+    fn particles_track_the_unrolled_posterior_and_likelihood() {
+        let network = umbrella();
+        let mut filter = ParticleFilter::new(
+            network.prior(),
+            network.transition(),
+            &[("Rain_prev", "Rain")],
+            20_000,
+        );
+        let mut rng = seeded_rng(1);
+        for (t, seen) in UMBRELLAS.iter().enumerate() {
+            filter.step_with_rng(&HashMap::from([("Umbrella", *seen)]), &mut rng);
+            assert_eq!(filter.time(), t + 1);
+
+            let unrolled = network.unroll(t + 1);
+            let evidence = unrolled_evidence(t + 1);
+            let evidence = evidence
+                .iter()
+                .map(|(name, value)| (name.as_str(), *value))
+                .collect::<HashMap<&str, bool>>();
+            let probabilities = unrolled.infer(&evidence);
+            let rain =
+                unrolled.get_inferred_probability(&probabilities, &format!("Rain[{}]", t), true);
+            let posterior = filter.posterior();
+            assert_eq!(posterior.nodes(), vec!["Rain", "Umbrella"]);
+            assert_close(posterior.probability("Rain", &true).unwrap(), rain, 0.02);
+            assert_close(
+                filter.log_likelihood(),
+                evidence_probability(&unrolled, &evidence).ln(),
+                0.02,
+            );
+        }
+        assert!(filter.effective_sample_size() < 20_000.0);
+    }
+
+    #[test]
+    fn without_evidence_every_particle_counts() {
+        let network = umbrella();
+        let mut filter = ParticleFilter::new(
+            network.prior(),
+            network.transition(),
+            &[("Rain_prev", "Rain")],
+            100,
+        );
+        filter.step_with_rng(&HashMap::new(), &mut seeded_rng(2));
+        assert_close(filter.effective_sample_size(), 100.0, 1e-9);
+        assert_eq!(filter.log_likelihood(), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The particle filter has not been stepped yet")]
+    fn posterior_needs_a_step() {
+        let network = umbrella();
+        ParticleFilter::new(
+            network.prior(),
+            network.transition(),
+            &[("Rain_prev", "Rain")],
+            10,
+        )
+        .posterior();
+    }
+
+    #[test]
+    fn particles_are_weighted_by_the_evidence() {
+        let network = umbrella();
+        let mut filter = ParticleFilter::new(
+            network.prior(),
+            network.transition(),
+            &[("Rain_prev", "Rain")],
+            50,
+        );
+        let mut rng = seeded_rng(3);
+        filter.step_with_rng(&HashMap::from([("Umbrella", true)]), &mut rng);
+        filter.step_with_rng(&HashMap::from([("Umbrella", false)]), &mut rng);
+        let particles = filter.particles();
+        assert_eq!(particles.len(), 50);
+        for (assignment, weight) in particles {
+            assert!(!assignment["Umbrella"]);
+            assert!(assignment.contains_key("Rain_prev"));
+            let expected = if assignment["Rain"] { 0.1 } else { 0.8 };
+            assert_close(weight, expected, 1e-12);
+        }
+    }
+}
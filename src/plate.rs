@@ -0,0 +1,257 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name, NodeType, Probability};
+
+/// A network fragment repeated once per index, such as one sensor or one patient, as used by
+/// `add_plate`. Copy `i` of plate node `X` is named `X[i]`; parents that are not plate nodes
+/// refer to nodes outside the plate, shared by every copy.
+#[derive(Clone)]
+pub struct Plate<T> {
+    nodes: Vec<TemplateNode<T>>,
+}
+
+/// A node of a plate or fragment, with parents given by name.
+#[derive(Clone)]
+pub(crate) struct TemplateNode<T> {
+    pub(crate) name: Name,
+    pub(crate) node_type: NodeType<T>,
+    pub(crate) parents: Vec<Name>,
+    pub(crate) probability: HashMap<Vec<T>, HashMap<T, Probability>>,
+    pub(crate) states: Option<Vec<T>>,
+}
+
+impl<T> TemplateNode<T> {
+    pub(crate) fn new(name: &str, node_type: NodeType<T>) -> TemplateNode<T> {
+        TemplateNode {
+            name: name.to_string(),
+            node_type,
+            parents: Vec::new(),
+            probability: HashMap::new(),
+            states: None,
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> Default for Plate<T> {
+    fn default() -> Self {
+        Plate::new()
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> Plate<T> {
+    /// Create an empty plate.
+    pub fn new() -> Plate<T> {
+        Plate { nodes: Vec::new() }
+    }
+
+    /// Add a node to the plate.
+    pub fn add_node(&mut self, name: &str, node_type: NodeType<T>) {
+        if self.nodes.iter().any(|node| node.name == name) {
+            panic!("Plate node `{}` already exists", name);
+        }
+        self.nodes.push(TemplateNode::new(name, node_type));
+    }
+
+    /// Add a dependency to a plate node. Each parent is a node of the plate, meaning its copy
+    /// with the same index, or else a node of the network outside the plate.
+    pub fn add_dependency(
+        &mut self,
+        parent_names: Vec<&str>,
+        child_name: &str,
+        prob: HashMap<Vec<T>, HashMap<T, Probability>>,
+    ) {
+        let node = self.node_mut(child_name);
+        node.parents = parent_names.iter().map(|name| name.to_string()).collect();
+        node.probability = prob;
+    }
+
+    /// Declare the values a plate node can take, in order.
+    pub fn set_node_states(&mut self, name: &str, states: Vec<T>) {
+        self.node_mut(name).states = Some(states);
+    }
+
+    fn node_mut(&mut self, name: &str) -> &mut TemplateNode<T> {
+        match self.nodes.iter_mut().find(|node| node.name == name) {
+            Some(node) => node,
+            None => panic!("Plate node `{}` not found", name),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Add `k` copies of `plate`, naming copy `i` of plate node `X` `X[i]`. The copies of
+    /// each plate node with a prior or a CPT are tied to the shared CPT `X.plate`, registered
+    /// on the first call, so `fit` pools their counts.
+    pub fn add_plate(&mut self, plate: &Plate<T>, k: usize) {
+        let is_plate_node = |name: &str| plate.nodes.iter().any(|node| node.name == name);
+        let mut tied = Vec::new();
+        for node in &plate.nodes {
+            let cpt = match &node.node_type {
+                NodeType::Root(prior) => HashMap::from([(Vec::new(), prior.clone())]),
+                _ if node.parents.is_empty() => continue,
+                _ => node.probability.clone(),
+            };
+            let cpt_name = format!("{}.plate", node.name);
+            if !self.shared_cpts.contains_key(&cpt_name) {
+                self.add_shared_cpt(&cpt_name, cpt);
+            }
+            tied.push((node.name.as_str(), cpt_name));
+        }
+        for i in 0..k {
+            self.add_template_nodes(&plate.nodes, |name| {
+                if is_plate_node(name) {
+                    format!("{}[{}]", name, i)
+                } else {
+                    name.to_string()
+                }
+            });
+            for (name, cpt_name) in &tied {
+                self.tie_node(&format!("{}[{}]", name, i), cpt_name);
+            }
+        }
+    }
+
+    /// Add a copy of template nodes, with every node and parent name translated by `name`.
+    pub(crate) fn add_template_nodes<F: Fn(&str) -> Name>(
+        &mut self,
+        nodes: &[TemplateNode<T>],
+        name: F,
+    ) {
+        for node in nodes {
+            self.add_node(&name(&node.name), node.node_type.clone());
+            if let Some(states) = &node.states {
+                self.set_node_states(&name(&node.name), states.clone());
+            }
+        }
+        for node in nodes.iter().filter(|node| !node.parents.is_empty()) {
+            let parents = node
+                .parents
+                .iter()
+                .map(|parent| name(parent))
+                .collect::<Vec<Name>>();
+            self.add_dependency(
+                parents.iter().map(|parent| parent.as_str()).collect(),
+                &name(&node.name),
+                node.probability.clone(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::Dataset,
+        testing::{assert_close, brute_force_probability, joint, row, TOLERANCE},
+    };
+
+    fn alarm_table() -> HashMap<Vec<bool>, HashMap<bool, Probability>> {
+        HashMap::from([
+            (vec![true, true], row(0.99)),
+            (vec![true, false], row(0.9)),
+            (vec![false, true], row(0.5)),
+            (vec![false, false], row(0.01)),
+        ])
+    }
+
+    /// A fire watched by three alarms, each of which may be faulty.
+    fn alarms() -> BayesianNetwork<bool> {
+        let mut plate = Plate::new();
+        plate.add_node("faulty", NodeType::Root(row(0.1)));
+        plate.add_node("alarm", NodeType::Leaf);
+        plate.add_dependency(vec!["fire", "faulty"], "alarm", alarm_table());
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("fire", NodeType::Root(row(0.2)));
+        network.add_plate(&plate, 3);
+        network
+    }
+
+    #[test]
+    fn plate_copies_match_a_network_built_by_hand() {
+        let plated = alarms();
+        assert_eq!(
+            plated.node_names(),
+            vec![
+                "fire",
+                "faulty[0]",
+                "alarm[0]",
+                "faulty[1]",
+                "alarm[1]",
+                "faulty[2]",
+                "alarm[2]"
+            ]
+        );
+        let mut manual = BayesianNetwork::new(vec![true, false]);
+        manual.add_node("fire", NodeType::Root(row(0.2)));
+        for i in 0..3 {
+            manual.add_node(&format!("faulty[{}]", i), NodeType::Root(row(0.1)));
+            manual.add_node(&format!("alarm[{}]", i), NodeType::Leaf);
+        }
+        for i in 0..3 {
+            let faulty = format!("faulty[{}]", i);
+            manual.add_dependency(
+                vec!["fire", faulty.as_str()],
+                &format!("alarm[{}]", i),
+                alarm_table(),
+            );
+        }
+        let expected = joint(&manual);
+        let actual = joint(&plated);
+        assert_eq!(actual.len(), expected.len());
+        for (assignment, prob) in expected {
+            let (_, actual) = actual
+                .iter()
+                .find(|(other, _)| *other == assignment)
+                .unwrap();
+            assert_close(*actual, prob, TOLERANCE);
+        }
+
+        // The alarms share the fire: P(alarm | fire) is .909, and .059 without a fire.
+        let evidence = HashMap::from([("alarm[0]", true), ("alarm[1]", true), ("alarm[2]", false)]);
+        let fire = 0.2 * 0.909 * 0.909 * 0.091;
+        let no_fire = 0.8 * 0.059 * 0.059 * 0.941;
+        assert_close(
+            brute_force_probability(&plated, "fire", &true, &evidence),
+            fire / (fire + no_fire),
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn plate_copies_are_tied_and_fitted_together() {
+        let mut network = alarms();
+        for i in 0..3 {
+            assert_eq!(
+                network.get_node_shared_cpt(&format!("faulty[{}]", i)),
+                Some("faulty.plate")
+            );
+            assert_eq!(
+                network.get_node_shared_cpt(&format!("alarm[{}]", i)),
+                Some("alarm.plate")
+            );
+        }
+        assert_eq!(network.get_node_shared_cpt("fire"), None);
+
+        // One faulty sensor out of six copies over two records.
+        let mut dataset = Dataset::new(network.node_names());
+        dataset.add_row(vec![true, false, true, true, true, false, true]);
+        dataset.add_row(vec![false, false, false, false, false, false, false]);
+        network.fit(&dataset, 0.0);
+        for i in 0..3 {
+            let id = network.get_node_index(&format!("faulty[{}]", i));
+            match &network.nodes[id].node_type {
+                NodeType::Root(prior) => assert_close(prior[&true], 1.0 / 6.0, TOLERANCE),
+                _ => panic!("`faulty[{}]` is not a root", i),
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Plate node `alarm` already exists")]
+    fn plate_nodes_are_unique() {
+        let mut plate: Plate<bool> = Plate::new();
+        plate.add_node("alarm", NodeType::Leaf);
+        plate.add_node("alarm", NodeType::Leaf);
+    }
+}
@@ -0,0 +1,439 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability},
+    influence_diagram::InfluenceDiagram,
+    learning::configurations,
+};
+
+/// The action a policy takes for one observation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyRule<T> {
+    /// The values of the observed nodes, in the order of the policy's `observed`.
+    pub observation: Vec<T>,
+    pub action: T,
+    /// The probability of the observation given the evidence when every decision follows the
+    /// strategy: 0 for an observation that earlier decisions rule out.
+    pub probability: Probability,
+    /// The expected total utility of taking the action after the observation.
+    pub expected_utility: f64,
+}
+
+/// How a decision is made: an action for each observation of the nodes it observes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Policy<T> {
+    pub decision: Name,
+    pub observed: Vec<Name>,
+    /// One rule per observation that can occur, with observations in row-major order.
+    pub rules: Vec<PolicyRule<T>>,
+}
+
+impl<T: PartialEq> Policy<T> {
+    /// Get the action taken for an observation, if the policy has a rule for it.
+    pub fn action(&self, observation: &[T]) -> Option<&T> {
+        self.rules
+            .iter()
+            .find(|rule| rule.observation == observation)
+            .map(|rule| &rule.action)
+    }
+}
+
+/// A policy for each of some decisions of an influence diagram.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Strategy<T> {
+    /// The policies, in the order the decisions are made.
+    pub policies: Vec<Policy<T>>,
+}
+
+impl<T> Strategy<T> {
+    /// Get the policy of a decision, if the strategy has one.
+    pub fn policy(&self, decision: &str) -> Option<&Policy<T>> {
+        self.policies
+            .iter()
+            .find(|policy| policy.decision == decision)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> InfluenceDiagram<T> {
+    /// Get the policy for each decision that maximizes the expected total utility given
+    /// `evidence`, each decision taking the best action for what is known when it is made.
+    /// Decisions are made in order and nothing is forgotten: each observes everything the
+    /// earlier ones did, and their actions (see `get_no_forgetting_information`). They are
+    /// solved by backward induction, from the last to the first, each assuming the later
+    /// decisions follow their policies. Panics if a decision influences a node known before
+    /// it is made.
+    pub fn optimal_strategy(&self, evidence: &HashMap<&str, T>) -> Strategy<T> {
+        self.check_decision_order();
+        let evidence = self.evidence_ids(evidence);
+        let mut strategy = Strategy {
+            policies: Vec::new(),
+        };
+        for (index, decision) in self.decisions.iter().enumerate().rev() {
+            let network = self.strategy_network(&strategy);
+            let id = network.get_node_index(&decision.name);
+            let observed = self.no_forgetting(index);
+            let domains = observed
+                .iter()
+                .map(|id| network.get_node_domain(*id))
+                .collect::<Vec<Vec<T>>>();
+            let mut rules = Vec::new();
+            for observation in configurations(&domains) {
+                let mut fixed = evidence.clone();
+                if observed
+                    .iter()
+                    .zip(&observation)
+                    .any(|(id, value)| fixed.get(id).is_some_and(|fixed| fixed != value))
+                {
+                    continue;
+                }
+                fixed.extend(observed.iter().copied().zip(observation.iter().cloned()));
+                if network.assignment_probability(&fixed) <= 0.0 {
+                    continue;
+                }
+                let mut best: Option<(T, f64)> = None;
+                for action in &decision.actions {
+                    fixed.insert(id, action.clone());
+                    if let Some(utility) = self.utility_given(&network, &fixed) {
+                        if best.as_ref().is_none_or(|(_, best)| utility > *best) {
+                            best = Some((action.clone(), utility));
+                        }
+                    }
+                }
+                if let Some((action, expected_utility)) = best {
+                    rules.push(PolicyRule {
+                        observation,
+                        action,
+                        probability: 0.0,
+                        expected_utility,
+                    });
+                }
+            }
+            strategy.policies.insert(
+                0,
+                Policy {
+                    decision: decision.name.clone(),
+                    observed: observed
+                        .iter()
+                        .map(|id| network.get_node_name(*id).to_string())
+                        .collect(),
+                    rules,
+                },
+            );
+        }
+
+        // The probability of each observation once every decision follows its policy.
+        let network = self.strategy_network(&strategy);
+        let total = network.assignment_probability(&evidence);
+        for policy in &mut strategy.policies {
+            let observed = policy
+                .observed
+                .iter()
+                .map(|name| network.get_node_index(name))
+                .collect::<Vec<NodeId>>();
+            for rule in &mut policy.rules {
+                let mut fixed = evidence.clone();
+                fixed.extend(
+                    observed
+                        .iter()
+                        .copied()
+                        .zip(rule.observation.iter().cloned()),
+                );
+                rule.probability = network.assignment_probability(&fixed) / total;
+            }
+        }
+        strategy
+    }
+
+    /// Get the expected total utility given `evidence` of following `strategy`, to compare
+    /// candidate strategies without solving the diagram. The strategy may be partial: a
+    /// decision without a policy, or a policy without a rule for what it observes, takes an
+    /// action uniformly at random.
+    pub fn expected_utility(&self, strategy: &Strategy<T>, evidence: &HashMap<&str, T>) -> f64 {
+        for policy in &strategy.policies {
+            let decision = self.decision(&policy.decision);
+            for rule in &policy.rules {
+                if rule.observation.len() != policy.observed.len() {
+                    panic!(
+                        "Policy observation length does not match observed length ({} and {}, {:?} -> {})",
+                        rule.observation.len(),
+                        policy.observed.len(),
+                        policy.observed,
+                        policy.decision
+                    );
+                }
+                if !decision.actions.contains(&rule.action) {
+                    panic!(
+                        "Policy action {:?} is not an action of decision node `{}`",
+                        rule.action, policy.decision
+                    );
+                }
+            }
+        }
+        let network = self.strategy_network(strategy);
+        match self.utility_given(&network, &self.evidence_ids(evidence)) {
+            Some(utility) => utility,
+            None => panic!("Evidence has zero probability"),
+        }
+    }
+
+    /// The network of the chance nodes in which each decision with a policy in `strategy`
+    /// becomes a chance node, child of the nodes the policy observes, taking the action of
+    /// its policy for each observation, or a
+    /// uniformly random action for an observation without a rule. The other decisions stay
+    /// uniformly random.
+    pub(crate) fn strategy_network(&self, strategy: &Strategy<T>) -> BayesianNetwork<T> {
+        let mut network = self.network.copy();
+        for policy in &strategy.policies {
+            let decision = self.decision(&policy.decision);
+            let id = network.get_node_index(&decision.name);
+            let observed = policy
+                .observed
+                .iter()
+                .map(|name| network.get_node_index(name))
+                .collect::<Vec<NodeId>>();
+            let descendants = network.descendants(&[id]);
+            if let Some(influenced) = observed.iter().find(|id| descendants.contains(id)) {
+                panic!(
+                    "Decision node `{}` observes `{}`, which it influences",
+                    decision.name,
+                    network.get_node_name(*influenced)
+                );
+            }
+            let domains = observed
+                .iter()
+                .map(|id| network.get_node_domain(*id))
+                .collect::<Vec<Vec<T>>>();
+            let uniform = 1.0 / decision.actions.len() as Probability;
+            let rows = configurations(&domains)
+                .into_iter()
+                .map(|observation| {
+                    let row = network
+                        .value_space
+                        .iter()
+                        .map(|value| {
+                            let prob = match policy.action(&observation) {
+                                Some(action) if action == value => 1.0,
+                                Some(_) => 0.0,
+                                None if decision.actions.contains(value) => uniform,
+                                None => 0.0,
+                            };
+                            (value.clone(), prob)
+                        })
+                        .collect::<HashMap<T, Probability>>();
+                    (observation, row)
+                })
+                .collect::<HashMap<Vec<T>, HashMap<T, Probability>>>();
+            if observed.is_empty() {
+                network.nodes[id].node_type = NodeType::Root(rows[&Vec::new()].clone());
+                continue;
+            }
+            for parent in &observed {
+                if let NodeType::Leaf = network.nodes[*parent].node_type {
+                    network.nodes[*parent].node_type = NodeType::Inner;
+                }
+                network.nodes[*parent].children.push(id);
+            }
+            let node = &mut network.nodes[id];
+            node.node_type = if node.children.is_empty() {
+                NodeType::Leaf
+            } else {
+                NodeType::Inner
+            };
+            node.parents = observed;
+            node.probability = rows;
+            node.compact_cpt = None;
+        }
+        network
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, oil_wildcatter, row, TOLERANCE};
+
+    /// A policy for `decision` observing `observed`, with the given action per observation.
+    fn policy(
+        decision: &str,
+        observed: &[&str],
+        actions: &[(Vec<&'static str>, &'static str)],
+    ) -> Policy<&'static str> {
+        Policy {
+            decision: decision.to_string(),
+            observed: observed.iter().map(|name| name.to_string()).collect(),
+            rules: actions
+                .iter()
+                .map(|(observation, action)| PolicyRule {
+                    observation: observation.clone(),
+                    action,
+                    probability: 0.0,
+                    expected_utility: 0.0,
+                })
+                .collect(),
+        }
+    }
+
+    /// Assert that a policy has one rule per observation with the given action and expected
+    /// utility, in order.
+    fn assert_rules(policy: &Policy<&str>, expected: &[(Vec<&str>, &str, f64)]) {
+        assert_eq!(policy.rules.len(), expected.len());
+        for (rule, (observation, action, utility)) in policy.rules.iter().zip(expected) {
+            assert_eq!(&rule.observation, observation);
+            assert_eq!(&rule.action, action);
+            assert_close(rule.expected_utility, *utility, TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn optimal_strategy_tests_and_then_drills_unless_the_result_is_diffuse() {
+        let diagram = oil_wildcatter();
+        let strategy = diagram.optimal_strategy(&HashMap::new());
+        assert_eq!(strategy.policies.len(), 2);
+
+        let test = strategy.policy("test").unwrap();
+        assert!(test.observed.is_empty());
+        assert_rules(test, &[(Vec::new(), "test", 22.5)]);
+        assert_close(test.rules[0].probability, 1.0, TOLERANCE);
+
+        // Each result weighs the payoffs -70, 50 and 200 by the joint probabilities of the
+        // oil and the result, over the probability of the result.
+        let drill = strategy.policy("drill").unwrap();
+        assert_eq!(drill.observed, ["test", "seismic"]);
+        assert_rules(
+            drill,
+            &[
+                (vec!["test", "closed"], "drill", 21.0 / 0.24 - 10.0),
+                (vec!["test", "open"], "drill", 11.5 / 0.35 - 10.0),
+                (vec!["test", "diffuse"], "no_drill", -10.0),
+                (vec!["no_test", "none"], "drill", 20.0),
+            ],
+        );
+        assert_eq!(drill.action(&["test", "diffuse"]), Some(&"no_drill"));
+        assert_eq!(drill.action(&["no_test", "closed"]), None);
+    }
+
+    #[test]
+    fn optimal_strategy_conditions_on_the_evidence() {
+        let diagram = oil_wildcatter();
+        // Knowing the well is dry, neither testing nor drilling pays.
+        let strategy = diagram.optimal_strategy(&HashMap::from([("oil", "dry")]));
+        let test = strategy.policy("test").unwrap();
+        assert_rules(test, &[(Vec::new(), "no_test", 0.0)]);
+        let drill = strategy.policy("drill").unwrap();
+        assert!(drill.rules.iter().all(|rule| rule.action == "no_drill"));
+    }
+
+    #[test]
+    fn expected_utility_evaluates_complete_strategies() {
+        let diagram = oil_wildcatter();
+        let optimal = diagram.optimal_strategy(&HashMap::new());
+        assert_close(
+            diagram.expected_utility(&optimal, &HashMap::new()),
+            22.5,
+            TOLERANCE,
+        );
+        let always_drill = Strategy {
+            policies: vec![
+                policy("test", &[], &[(Vec::new(), "test")]),
+                policy("drill", &[], &[(Vec::new(), "drill")]),
+            ],
+        };
+        assert_close(
+            diagram.expected_utility(&always_drill, &HashMap::new()),
+            10.0,
+            TOLERANCE,
+        );
+        assert_close(
+            diagram.expected_utility(&always_drill, &HashMap::from([("oil", "soaking")])),
+            190.0,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn expected_utility_randomizes_missing_policies_and_rules() {
+        let diagram = oil_wildcatter();
+        let empty = Strategy {
+            policies: Vec::new(),
+        };
+        assert_close(
+            diagram.expected_utility(&empty, &HashMap::new()),
+            (20.0 + 10.0 + 0.0 - 10.0) / 4.0,
+            TOLERANCE,
+        );
+        // Testing is a coin flip, worth 20 without a test and 10 with one.
+        let drill_only = Strategy {
+            policies: vec![policy("drill", &[], &[(Vec::new(), "drill")])],
+        };
+        assert_close(
+            diagram.expected_utility(&drill_only, &HashMap::new()),
+            15.0,
+            TOLERANCE,
+        );
+        // Only a closed result has a rule, so an open or diffuse one drills half the time.
+        let closed_only = Strategy {
+            policies: vec![
+                policy("test", &[], &[(Vec::new(), "test")]),
+                policy("drill", &["seismic"], &[(vec!["closed"], "drill")]),
+            ],
+        };
+        assert_close(
+            diagram.expected_utility(&closed_only, &HashMap::new()),
+            -10.0 + 21.0 + 0.5 * 11.5 + 0.5 * -12.5,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Policy action \"test\" is not an action of decision node `drill`")]
+    fn expected_utility_rejects_foreign_actions() {
+        let diagram = oil_wildcatter();
+        let strategy = Strategy {
+            policies: vec![policy("drill", &[], &[(Vec::new(), "test")])],
+        };
+        diagram.expected_utility(&strategy, &HashMap::new());
+    }
+
+    #[test]
+    fn later_decisions_remember_earlier_ones_and_their_observations() {
+        let diagram = oil_wildcatter();
+        let strategy = diagram.optimal_strategy(&HashMap::new());
+        // The drill decision only observes the result, but remembers whether it tested.
+        let drill = strategy.policy("drill").unwrap();
+        assert_eq!(
+            drill.observed,
+            diagram.get_no_forgetting_information("drill")
+        );
+        // Once testing is chosen, the results occur with their marginal probabilities and
+        // skipping the test cannot happen.
+        for (rule, probability) in drill.rules.iter().zip([0.24, 0.35, 0.41, 0.0]) {
+            assert_close(rule.probability, probability, TOLERANCE);
+        }
+        // Solving backwards, testing is worth more than the 20 of drilling blind.
+        let blind = diagram
+            .expected_utilities(&HashMap::new())
+            .into_iter()
+            .map(|(_, utility)| utility)
+            .fold(f64::NEG_INFINITY, f64::max);
+        assert_close(blind, 20.0, TOLERANCE);
+        assert!(strategy.policy("test").unwrap().rules[0].expected_utility > blind);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Decision node `second` influences `signal`, which is known before it is made"
+    )]
+    fn decisions_cannot_influence_what_earlier_decisions_observe() {
+        let mut diagram = InfluenceDiagram::new(vec![true, false]);
+        diagram.add_chance_node("signal", NodeType::Inner);
+        diagram.add_decision_node("first", vec![true, false], vec!["signal"]);
+        diagram.add_decision_node("second", vec![true, false], Vec::new());
+        diagram.add_dependency(
+            vec!["second"],
+            "signal",
+            HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.1))]),
+        );
+        diagram.add_utility_node("reward", vec!["first"], HashMap::from([(vec![true], 1.0)]));
+        diagram.optimal_strategy(&HashMap::new());
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    hash::Hash,
+    path::Path,
+};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, Probability},
+    dataset::quote_field,
+    error::Error,
+};
+
+/// The posterior distribution of each node given some evidence, as returned by `posterior`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Posterior<T> {
+    pub(crate) marginals: Vec<(Name, Vec<(T, Probability)>)>,
+}
+
+impl<T: Clone + PartialEq> Posterior<T> {
+    /// Get the names of the nodes, in order.
+    pub fn nodes(&self) -> Vec<&str> {
+        self.marginals
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Get the distribution of a node over its states.
+    pub fn distribution(&self, name: &str) -> Option<&[(T, Probability)]> {
+        self.marginals
+            .iter()
+            .find(|(node, _)| node == name)
+            .map(|(_, states)| states.as_slice())
+    }
+
+    /// Get the probability of a node taking `value`.
+    pub fn probability(&self, name: &str, value: &T) -> Option<Probability> {
+        self.distribution(name)?
+            .iter()
+            .find(|(state, _)| state == value)
+            .map(|(_, prob)| *prob)
+    }
+
+    /// Keep only the given nodes, in the given order.
+    pub fn select(&self, names: &[&str]) -> Posterior<T> {
+        Posterior {
+            marginals: names
+                .iter()
+                .map(|name| match self.distribution(name) {
+                    Some(states) => (name.to_string(), states.to_vec()),
+                    None => panic!("Node `{}` not found", name),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Display> Posterior<T> {
+    /// Write `node,state,probability` rows as CSV with a header line.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("node,state,probability\n");
+        for (name, states) in &self.marginals {
+            for (value, prob) in states {
+                csv.push_str(&format!(
+                    "{},{},{}\n",
+                    quote_field(name),
+                    quote_field(&value.to_string()),
+                    prob
+                ));
+            }
+        }
+        csv
+    }
+
+    /// Write a JSON object mapping each node to an object mapping its states to their
+    /// probabilities, in order.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+        for (i, (name, states)) in self.marginals.iter().enumerate() {
+            let states = states
+                .iter()
+                .map(|(value, prob)| {
+                    format!(
+                        "    {}: {}",
+                        json_string(&value.to_string()),
+                        json_number(*prob)
+                    )
+                })
+                .collect::<Vec<String>>();
+            json.push_str(if i == 0 { "\n" } else { ",\n" });
+            json.push_str(&format!(
+                "  {}: {{\n{}\n  }}",
+                json_string(name),
+                states.join(",\n")
+            ));
+        }
+        json.push_str("\n}\n");
+        json
+    }
+
+    /// Write the posterior to a file, as JSON if its extension is `.json` and CSV otherwise.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let json = path
+            .as_ref()
+            .extension()
+            .is_some_and(|extension| extension == "json");
+        let contents = if json { self.to_json() } else { self.to_csv() };
+        Ok(std::fs::write(path, contents)?)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Infer the posterior distribution of every node given `evidence`. Each node's
+    /// distribution is over its declared states, or else the values it can take.
+    pub fn posterior(&self, evidence: &HashMap<&str, T>) -> Posterior<T> {
+        let inferred_probabilities = self.infer(evidence);
+        Posterior {
+            marginals: self
+                .nodes
+                .iter()
+                .map(|node| {
+                    let states = self
+                        .get_node_domain(node.id)
+                        .into_iter()
+                        .map(|value| {
+                            let prob = inferred_probabilities[node.id][&value];
+                            (value, prob)
+                        })
+                        .collect();
+                    (self.get_node_name(node.id).to_string(), states)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A string as a JSON string literal.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// A probability as a JSON number, or `null` if it is not finite.
+pub(crate) fn json_number(prob: Probability) -> String {
+    if prob.is_finite() {
+        prob.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_probability, TOLERANCE};
+
+    #[test]
+    fn posterior_matches_enumeration_over_each_domain() {
+        let network = bonus();
+        let evidence = HashMap::from([("feast", true)]);
+        let posterior = network.posterior(&evidence);
+        assert_eq!(
+            posterior.nodes(),
+            vec!["performance", "horse_race", "mood", "bonus", "feast"]
+        );
+        for name in posterior.nodes() {
+            let distribution = posterior.distribution(name).unwrap();
+            assert_eq!(distribution.len(), 2);
+            for (value, prob) in distribution {
+                assert_close(
+                    *prob,
+                    brute_force_probability(&network, name, value, &evidence),
+                    TOLERANCE,
+                );
+            }
+        }
+        assert_eq!(posterior.probability("salary", &true), None);
+    }
+
+    #[test]
+    fn exports_list_each_state_in_order() {
+        let posterior = Posterior {
+            marginals: vec![
+                ("a, b".to_string(), vec![(1, 0.25), (2, 0.75)]),
+                ("c\"".to_string(), vec![(1, f64::NAN)]),
+            ],
+        };
+        assert_eq!(
+            posterior.to_csv(),
+            "node,state,probability\n\"a, b\",1,0.25\n\"a, b\",2,0.75\n\"c\"\"\",1,NaN\n"
+        );
+        assert_eq!(
+            posterior.to_json(),
+            "{\n  \"a, b\": {\n    \"1\": 0.25,\n    \"2\": 0.75\n  },\n  \"c\\\"\": {\n    \"1\": null\n  }\n}\n"
+        );
+        assert_eq!(posterior.select(&["c\""]).nodes(), vec!["c\""]);
+    }
+
+    #[test]
+    fn json_strings_escape_control_characters() {
+        assert_eq!(json_string("a\tb\\\u{1}"), "\"a\\tb\\\\\\u0001\"");
+        assert_eq!(json_number(f64::INFINITY), "null");
+    }
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId},
+    posterior::Posterior,
+};
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the nodes needed to compute the posterior of `targets` given evidence on
+    /// `observed`, in node order: those whose CPT matters, found by Bayes-ball, and the
+    /// observed nodes whose value does. Barren nodes and nodes d-separated from the targets
+    /// are left out.
+    pub fn requisite_nodes(&self, targets: &[&str], observed: &[&str]) -> Vec<Name> {
+        let (requisite, values) = self.bayes_ball(
+            &targets
+                .iter()
+                .map(|name| self.causal_node(name))
+                .collect::<Vec<NodeId>>(),
+            &observed
+                .iter()
+                .map(|name| self.causal_node(name))
+                .collect::<Vec<NodeId>>(),
+        );
+        let ids = (0..self.nodes.len())
+            .filter(|id| requisite[*id] || values[*id])
+            .collect::<Vec<NodeId>>();
+        self.node_names_of(&ids)
+    }
+
+    /// Prune the network to the nodes of `requisite_nodes` for `targets` given `evidence`.
+    /// Observed nodes kept only for their value become roots certain to take it, so the
+    /// posterior of the targets is unchanged but the probability of the evidence is not.
+    pub fn prune(&self, targets: &[&str], evidence: &HashMap<&str, T>) -> BayesianNetwork<T> {
+        let evidence = self.evidence_ids(evidence);
+        let (requisite, values) = self.bayes_ball(
+            &targets
+                .iter()
+                .map(|name| self.causal_node(name))
+                .collect::<Vec<NodeId>>(),
+            &evidence.keys().copied().collect::<Vec<NodeId>>(),
+        );
+        let ids = (0..self.nodes.len())
+            .filter(|id| requisite[*id] || values[*id])
+            .collect::<Vec<NodeId>>();
+        let fixed = ids
+            .iter()
+            .filter(|id| !requisite[**id])
+            .map(|id| (*id, evidence[id].clone()))
+            .collect::<HashMap<NodeId, T>>();
+        self.subnetwork(&ids, &fixed)
+    }
+
+    /// Infer the posterior of `targets` given `evidence` like `posterior`, on the network
+    /// pruned by `prune`, so that the cost depends only on the part of the network relevant
+    /// to the query.
+    pub fn posterior_pruned(&self, targets: &[&str], evidence: &HashMap<&str, T>) -> Posterior<T> {
+        let pruned = self.prune(targets, evidence);
+        let evidence = evidence
+            .iter()
+            .filter(|(name, _)| pruned.node_map.contains_key(**name))
+            .map(|(name, value)| (*name, value.clone()))
+            .collect::<HashMap<&str, T>>();
+        pruned.posterior(&evidence).select(targets)
+    }
+
+    /// Run Bayes-ball from `targets` given `observed`, returning for each node whether its
+    /// CPT is needed and whether its observed value is.
+    pub(crate) fn bayes_ball(
+        &self,
+        targets: &[NodeId],
+        observed: &[NodeId],
+    ) -> (Vec<bool>, Vec<bool>) {
+        let n = self.nodes.len();
+        let mut is_observed = vec![false; n];
+        for id in observed {
+            is_observed[*id] = true;
+        }
+        let mut top = vec![false; n];
+        let mut bottom = vec![false; n];
+        let mut visited = vec![false; n];
+        // Each entry is a node and whether the ball comes from one of its children.
+        let mut schedule = targets
+            .iter()
+            .map(|id| (*id, true))
+            .collect::<Vec<(NodeId, bool)>>();
+        while let Some((id, from_child)) = schedule.pop() {
+            visited[id] = true;
+            let node = &self.nodes[id];
+            if !is_observed[id] && from_child {
+                if !top[id] {
+                    top[id] = true;
+                    schedule.extend(node.parents.iter().map(|parent| (*parent, true)));
+                }
+                if !bottom[id] {
+                    bottom[id] = true;
+                    schedule.extend(node.children.iter().map(|child| (*child, false)));
+                }
+            } else if !from_child {
+                if is_observed[id] && !top[id] {
+                    top[id] = true;
+                    schedule.extend(node.parents.iter().map(|parent| (*parent, true)));
+                }
+                if !is_observed[id] && !bottom[id] {
+                    bottom[id] = true;
+                    schedule.extend(node.children.iter().map(|child| (*child, false)));
+                }
+            }
+        }
+        let values = (0..n).map(|id| is_observed[id] && visited[id]).collect();
+        (top, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bayesian_network::NodeType,
+        testing::{assert_close, bonus, brute_force_probability, graph, sprinkler, TOLERANCE},
+    };
+
+    #[test]
+    fn requisite_nodes_leave_out_barren_and_separated_nodes() {
+        let network = bonus();
+        assert_eq!(
+            network.requisite_nodes(&["mood"], &[]),
+            ["performance", "horse_race", "mood"]
+        );
+        assert_eq!(
+            network.requisite_nodes(&["performance"], &["bonus"]),
+            ["performance", "horse_race", "mood", "bonus"]
+        );
+        // Observing the middle of a chain cuts off the rest, keeping only its value.
+        let chain = graph(&["a", "b", "c"], &[("a", "b"), ("b", "c")]);
+        assert_eq!(chain.requisite_nodes(&["c"], &["b"]), ["b", "c"]);
+        assert_eq!(chain.requisite_nodes(&["a"], &["b"]), ["a", "b"]);
+    }
+
+    #[test]
+    fn prune_keeps_the_posterior_of_the_targets() {
+        let network = sprinkler();
+        let evidence = HashMap::from([("sprinkler", true), ("rain", false)]);
+        let pruned = network.prune(&["wet_grass"], &evidence);
+        assert_eq!(pruned.node_names(), ["sprinkler", "rain", "wet_grass"]);
+        // The cloud only mattered through the observed sprinkler and rain, which become
+        // roots certain to take their value.
+        let rain = pruned.get_node_index("rain");
+        assert!(matches!(
+            &pruned.nodes[rain].node_type,
+            NodeType::Root(prior) if prior[&false] == 1.0
+        ));
+        let posterior = network.posterior_pruned(&["wet_grass"], &evidence);
+        assert_eq!(posterior.nodes(), ["wet_grass"]);
+        assert_close(
+            posterior.probability("wet_grass", &true).unwrap(),
+            brute_force_probability(&network, "wet_grass", &true, &evidence),
+            TOLERANCE,
+        );
+        let posterior =
+            network.posterior_pruned(&["cloudy"], &HashMap::from([("sprinkler", true)]));
+        assert_close(
+            posterior.probability("cloudy", &true).unwrap(),
+            brute_force_probability(
+                &network,
+                "cloudy",
+                &true,
+                &HashMap::from([("sprinkler", true)]),
+            ),
+            TOLERANCE,
+        );
+    }
+}
@@ -0,0 +1,480 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display},
+    hash::Hash,
+};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    error::Error,
+    formats::lexer::{Lexer, Token},
+    learning::configurations,
+    posterior::Posterior,
+};
+
+const FORMAT: &str = "query";
+
+/// The answer to a query evaluated by `eval`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryResult<T> {
+    /// `P(A=a, B=b | e)`: the probability of the assignment given the evidence.
+    Probability(Probability),
+    /// `P(A, B | e)`: the posterior of each node over its states.
+    Distribution(Posterior<T>),
+    /// `MAP(A, B | e)`: the most probable joint assignment of the nodes, with its probability
+    /// given the evidence.
+    Map {
+        assignment: Vec<(Name, T)>,
+        probability: Probability,
+    },
+}
+
+impl<T: Clone + PartialEq + Display> Display for QueryResult<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryResult::Probability(prob) => writeln!(f, "{:.6}", prob),
+            QueryResult::Distribution(posterior) => {
+                for name in posterior.nodes() {
+                    let states = posterior
+                        .distribution(name)
+                        .unwrap()
+                        .iter()
+                        .map(|(value, prob)| format!("{}={:.6}", value, prob))
+                        .collect::<Vec<String>>();
+                    writeln!(f, "{}: {}", name, states.join(", "))?;
+                }
+                Ok(())
+            }
+            QueryResult::Map {
+                assignment,
+                probability,
+            } => {
+                let assignment = assignment
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<String>>();
+                writeln!(f, "{} ({:.6})", assignment.join(", "), probability)
+            }
+        }
+    }
+}
+
+/// A parsed query: `P(targets | evidence)` or `MAP(targets | evidence)`, where targets may
+/// be assigned values.
+struct Query {
+    map: bool,
+    targets: Vec<(String, Option<String>)>,
+    evidence: Vec<(String, String)>,
+}
+
+fn parse(source: &str) -> Result<Query, Error> {
+    let mut lexer = Lexer::new(FORMAT, source, &[])?;
+    let map = match lexer.next()? {
+        Token::Word(word) if word == "P" => false,
+        Token::Word(word) if word == "MAP" => true,
+        token => return lexer.error(format!("expected `P` or `MAP`, found {:?}", token)),
+    };
+    lexer.expect_punct('(')?;
+    let mut targets = Vec::new();
+    loop {
+        let name = lexer.name()?;
+        let value = if lexer.is_punct('=') {
+            lexer.next()?;
+            Some(lexer.name()?)
+        } else {
+            None
+        };
+        targets.push((name, value));
+        if !lexer.is_punct(',') {
+            break;
+        }
+        lexer.next()?;
+    }
+    let mut evidence = Vec::new();
+    if lexer.is_punct('|') {
+        lexer.next()?;
+        loop {
+            let name = lexer.name()?;
+            lexer.expect_punct('=')?;
+            evidence.push((name, lexer.name()?));
+            if !lexer.is_punct(',') {
+                break;
+            }
+            lexer.next()?;
+        }
+    }
+    lexer.expect_punct(')')?;
+    if let Some(token) = lexer.peek() {
+        return lexer.error(format!("unexpected {:?} after query", token));
+    }
+    Ok(Query {
+        map,
+        targets,
+        evidence,
+    })
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Display> BayesianNetwork<T> {
+    /// Evaluate a query written as text, such as `P(業績=true | ボーナス=true, ごちそう=false)`
+    /// for the probability of an assignment, `P(業績 | ボーナス=true)` for posterior
+    /// distributions or `MAP(業績, 競馬 | ボーナス=true)` for the most probable joint
+    /// assignment. Values are matched against the states of each node by their `Display` form.
+    pub fn eval(&self, query: &str) -> Result<QueryResult<T>, Error> {
+        let query = parse(query)?;
+        let invalid = Error::Query;
+        let node = |name: &str| {
+            self.node_map
+                .get(name)
+                .copied()
+                .ok_or_else(|| invalid(format!("node `{}` not found", name)))
+        };
+        let value = |id: NodeId, text: &str| {
+            let name = self.get_node_name(id);
+            self.get_node_states(name)
+                .into_iter()
+                .find(|value| value.to_string() == text)
+                .ok_or_else(|| invalid(format!("`{}` is not a state of `{}`", text, name)))
+        };
+
+        let mut evidence = HashMap::new();
+        for (name, text) in &query.evidence {
+            let id = node(name)?;
+            evidence.insert(self.get_node_name(id), value(id, text)?);
+        }
+        let evidence_likelihood = self.likelihood(&evidence);
+        if evidence_likelihood <= 0.0 {
+            return Err(invalid("the evidence has zero probability".to_string()));
+        }
+        let targets = query
+            .targets
+            .iter()
+            .map(|(name, text)| {
+                let id = node(name)?;
+                let value = match text {
+                    Some(text) => Some(value(id, text)?),
+                    None => None,
+                };
+                Ok((id, value))
+            })
+            .collect::<Result<Vec<(NodeId, Option<T>)>, Error>>()?;
+        let assigned = targets.iter().filter(|(_, value)| value.is_some()).count();
+
+        if query.map {
+            if assigned > 0 {
+                return Err(invalid("MAP targets cannot be assigned values".to_string()));
+            }
+            let names = targets
+                .iter()
+                .map(|(id, _)| self.get_node_name(*id))
+                .collect::<Vec<&str>>();
+            let (assignment, probability) = self.most_probable(&names, &evidence);
+            return Ok(QueryResult::Map {
+                assignment,
+                probability,
+            });
+        }
+
+        if assigned == targets.len() {
+            let values = targets
+                .iter()
+                .map(|(_, value)| value.clone().unwrap())
+                .collect::<Vec<T>>();
+            let probability =
+                self.joint_probability(&evidence, targets.iter().map(|(id, _)| *id), &values)
+                    / evidence_likelihood;
+            Ok(QueryResult::Probability(probability))
+        } else if assigned == 0 {
+            let names = targets
+                .iter()
+                .map(|(id, _)| self.get_node_name(*id))
+                .collect::<Vec<&str>>();
+            Ok(QueryResult::Distribution(
+                self.exact_posterior(&names, &evidence),
+            ))
+        } else {
+            Err(invalid(
+                "either all or none of the targets of P must be assigned values".to_string(),
+            ))
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// The number of joint assignments `eval` enumerates for a `MAP` query, the product of the
+    /// number of values of its targets, or `None` for a `P` query. This lets a caller refuse a
+    /// query too large to answer before evaluating it.
+    pub fn map_configurations(&self, query: &str) -> Result<Option<usize>, Error> {
+        let query = parse(query)?;
+        if !query.map {
+            return Ok(None);
+        }
+        query
+            .targets
+            .iter()
+            .try_fold(1usize, |count, (name, _)| match self.node_map.get(name) {
+                Some(id) => Ok(count.saturating_mul(self.get_node_domain(*id).len())),
+                None => Err(Error::Query(format!("node `{}` not found", name))),
+            })
+            .map(Some)
+    }
+
+    /// Find the most probable joint assignment of `targets` given `evidence` by enumerating
+    /// every combination of their states, and return it with its probability.
+    pub fn most_probable(
+        &self,
+        targets: &[&str],
+        evidence: &HashMap<&str, T>,
+    ) -> (Vec<(Name, T)>, Probability) {
+        let ids = targets
+            .iter()
+            .map(|name| match self.node_map.get(*name) {
+                Some(id) => *id,
+                None => panic!("Target node `{}` not found", name),
+            })
+            .collect::<Vec<NodeId>>();
+        let evidence_likelihood = self.likelihood(evidence);
+        if evidence_likelihood <= 0.0 {
+            panic!("Evidence has zero probability");
+        }
+        let domains = ids
+            .iter()
+            .map(|id| self.get_node_domain(*id))
+            .collect::<Vec<Vec<T>>>();
+        let mut best: Option<(Vec<T>, Probability)> = None;
+        for configuration in configurations(&domains) {
+            let probability = self.joint_probability(evidence, ids.iter().copied(), &configuration)
+                / evidence_likelihood;
+            if best.as_ref().is_none_or(|(_, best)| probability > *best) {
+                best = Some((configuration, probability));
+            }
+        }
+        let (configuration, probability) = best.unwrap();
+        (
+            targets
+                .iter()
+                .map(|name| name.to_string())
+                .zip(configuration)
+                .collect(),
+            probability,
+        )
+    }
+
+    /// Get the posterior of each target over its states given `evidence`, normalizing the
+    /// probability of each state together with the evidence. Unlike `posterior`, which
+    /// passes messages on polytrees only, this is exact on any network.
+    pub fn exact_posterior(&self, targets: &[&str], evidence: &HashMap<&str, T>) -> Posterior<T> {
+        let evidence_likelihood = self.likelihood(evidence);
+        if evidence_likelihood <= 0.0 {
+            panic!("Evidence has zero probability");
+        }
+        Posterior {
+            marginals: targets
+                .iter()
+                .map(|name| {
+                    let id = match self.node_map.get(*name) {
+                        Some(id) => *id,
+                        None => panic!("Target node `{}` not found", name),
+                    };
+                    let states = self
+                        .get_node_domain(id)
+                        .into_iter()
+                        .map(|value| {
+                            let prob = self.joint_probability(
+                                evidence,
+                                std::iter::once(id),
+                                std::slice::from_ref(&value),
+                            ) / evidence_likelihood;
+                            (value, prob)
+                        })
+                        .collect();
+                    (name.to_string(), states)
+                })
+                .collect(),
+        }
+    }
+
+    /// The probability of `evidence` together with the nodes `ids` taking `values`, or 0.0 if
+    /// they contradict the evidence or each other.
+    fn joint_probability<I: Iterator<Item = NodeId>>(
+        &self,
+        evidence: &HashMap<&str, T>,
+        ids: I,
+        values: &[T],
+    ) -> Probability {
+        let mut record = evidence.clone();
+        for (id, value) in ids.zip(values) {
+            let name = self.get_node_name(id);
+            if record.get(name).is_some_and(|other| other != value) {
+                return 0.0;
+            }
+            record.insert(name, value.clone());
+        }
+        self.likelihood(&record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        assert_close, bonus, brute_force_probability, evidence_probability, sprinkler, TOLERANCE,
+    };
+
+    #[test]
+    fn map_configurations_count_the_joint_assignments_of_map_targets() {
+        let network = bonus();
+        assert_eq!(
+            network
+                .map_configurations("MAP(performance, horse_race | bonus=true)")
+                .unwrap(),
+            Some(4)
+        );
+        assert_eq!(
+            network
+                .map_configurations("P(performance | bonus=true)")
+                .unwrap(),
+            None
+        );
+        assert!(network.map_configurations("MAP(salary)").is_err());
+    }
+
+    #[test]
+    fn assigned_targets_give_a_conditional_probability() {
+        let network = bonus();
+        let result = network
+            .eval("P(performance=true, horse_race=false | bonus=true)")
+            .unwrap();
+        let evidence = HashMap::from([("bonus", true)]);
+        let joint = HashMap::from([
+            ("performance", true),
+            ("horse_race", false),
+            ("bonus", true),
+        ]);
+        let expected =
+            evidence_probability(&network, &joint) / evidence_probability(&network, &evidence);
+        match result {
+            QueryResult::Probability(prob) => assert_close(prob, expected, TOLERANCE),
+            result => panic!("expected a probability, got {:?}", result),
+        }
+        // Contradicting the evidence gives zero.
+        assert_eq!(
+            network.eval("P(bonus=false | bonus=true)").unwrap(),
+            QueryResult::Probability(0.0)
+        );
+    }
+
+    #[test]
+    fn unassigned_targets_give_their_posteriors() {
+        let network = bonus();
+        let evidence = HashMap::from([("bonus", true), ("feast", false)]);
+        match network.eval("P(mood | bonus=true, feast=false)").unwrap() {
+            QueryResult::Distribution(posterior) => {
+                assert_eq!(posterior.nodes(), vec!["mood"]);
+                assert_close(
+                    posterior.probability("mood", &true).unwrap(),
+                    brute_force_probability(&network, "mood", &true, &evidence),
+                    TOLERANCE,
+                );
+            }
+            result => panic!("expected a distribution, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn distributions_are_exact_on_networks_with_loops() {
+        // The sprinkler network is not a polytree, so `posterior` cannot answer this.
+        let network = sprinkler();
+        let evidence = HashMap::from([("wet_grass", true)]);
+        match network.eval("P(rain, cloudy | wet_grass=true)").unwrap() {
+            QueryResult::Distribution(posterior) => {
+                assert_eq!(posterior.nodes(), vec!["rain", "cloudy"]);
+                for name in ["rain", "cloudy"] {
+                    for value in [true, false] {
+                        assert_close(
+                            posterior.probability(name, &value).unwrap(),
+                            brute_force_probability(&network, name, &value, &evidence),
+                            TOLERANCE,
+                        );
+                    }
+                }
+            }
+            result => panic!("expected a distribution, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn map_maximizes_the_marginal_over_the_targets() {
+        let network = bonus();
+        let evidence = HashMap::from([("bonus", true)]);
+        let mut best = (Vec::new(), 0.0);
+        for performance in [true, false] {
+            for horse_race in [true, false] {
+                let mut joint = evidence.clone();
+                joint.insert("performance", performance);
+                joint.insert("horse_race", horse_race);
+                let prob = evidence_probability(&network, &joint)
+                    / evidence_probability(&network, &evidence);
+                if prob > best.1 {
+                    best = (vec![performance, horse_race], prob);
+                }
+            }
+        }
+        match network
+            .eval("MAP(performance, horse_race | bonus=true)")
+            .unwrap()
+        {
+            QueryResult::Map {
+                assignment,
+                probability,
+            } => {
+                assert_eq!(
+                    assignment,
+                    vec![
+                        ("performance".to_string(), best.0[0]),
+                        ("horse_race".to_string(), best.0[1]),
+                    ]
+                );
+                assert_close(probability, best.1, TOLERANCE);
+            }
+            result => panic!("expected a MAP assignment, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn malformed_and_invalid_queries_are_errors() {
+        let network = bonus();
+        assert!(matches!(
+            network.eval("Q(mood)"),
+            Err(Error::Syntax { format: FORMAT, .. })
+        ));
+        assert!(matches!(
+            network.eval("P(mood | bonus)"),
+            Err(Error::Syntax { .. })
+        ));
+        for query in [
+            "P(salary)",
+            "P(mood=maybe)",
+            "P(mood=true, feast)",
+            "MAP(mood=true)",
+        ] {
+            assert!(
+                matches!(network.eval(query), Err(Error::Query(_))),
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn results_display_one_line_per_answer() {
+        let network = bonus();
+        assert_eq!(
+            network.eval("P(performance=true)").unwrap().to_string(),
+            "0.010000\n"
+        );
+        assert_eq!(
+            network.eval("P(performance)").unwrap().to_string(),
+            "performance: true=0.010000, false=0.990000\n"
+        );
+    }
+}
@@ -0,0 +1,630 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    dataset::Dataset,
+};
+
+/// Create a random number generator seeded with `seed`, for reproducible results from the
+/// `_with_rng` methods.
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// How many forward draws to try when looking for a Gibbs starting state.
+const MAX_INITIAL_ATTEMPTS: usize = 100_000;
+
+/// How `sample_posterior_with_rng` draws assignments consistent with the evidence.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PosteriorSampler {
+    /// Draw `particles` (at least as many as requested) weighted forward samples with the
+    /// evidence clamped, then resample them in proportion to their weights.
+    LikelihoodWeighting { particles: usize },
+    /// Run a Gibbs sampler over the non-evidence nodes, discarding the first `burn_in` sweeps
+    /// and then keeping every `thin`-th sweep. Consecutive samples are correlated, and
+    /// deterministic CPTs can keep the chain from mixing.
+    Gibbs { burn_in: usize, thin: usize },
+}
+
+impl Default for PosteriorSampler {
+    fn default() -> Self {
+        PosteriorSampler::LikelihoodWeighting { particles: 10_000 }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Draw `n` complete joint assignments from the network, one column per node in node
+    /// order: roots are drawn from their priors and every other node from its CPT given the
+    /// values drawn for its parents.
+    pub fn sample(&self, n: usize) -> Dataset<T> {
+        self.sample_with_rng(n, &mut rand::thread_rng())
+    }
+
+    /// Draw `n` complete joint assignments like `sample`, using `rng`.
+    pub fn sample_with_rng<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Dataset<T> {
+        let order = self.topological_order();
+        let mut dataset = Dataset::new(self.node_names());
+        for _ in 0..n {
+            let (assignment, _) = self.weighted_sample(&order, &HashMap::new(), rng);
+            dataset.add_row(self.assignment_row(&assignment));
+        }
+        dataset
+    }
+
+    /// Draw `n` complete assignments under the interventions `do(X = x)`: intervened nodes are
+    /// clamped to their values regardless of their CPTs, and every other node is drawn from
+    /// its CPT given its parents. This simulates a randomized experiment on the nodes.
+    pub fn sample_do(&self, interventions: &HashMap<&str, T>, n: usize) -> Dataset<T> {
+        self.sample_do_with_rng(interventions, n, &mut rand::thread_rng())
+    }
+
+    /// Draw `n` complete assignments under interventions like `sample_do`, using `rng`.
+    pub fn sample_do_with_rng<R: Rng + ?Sized>(
+        &self,
+        interventions: &HashMap<&str, T>,
+        n: usize,
+        rng: &mut R,
+    ) -> Dataset<T> {
+        let interventions = self.intervention_ids(interventions);
+        let order = self.topological_order();
+        let mut dataset = Dataset::new(self.node_names());
+        for _ in 0..n {
+            // Clamping without keeping the likelihood weight cuts the intervened nodes off
+            // from their parents.
+            let (assignment, _) = self.weighted_sample(&order, &interventions, rng);
+            dataset.add_row(self.assignment_row(&assignment));
+        }
+        dataset
+    }
+
+    /// Generate a synthetic dataset of `n` records, one column per node in node order.
+    /// Records are drawn from the joint distribution, conditioned on `evidence` if it is
+    /// not empty as by `sample_posterior`.
+    pub fn simulate(&self, n: usize, evidence: &HashMap<&str, T>) -> Dataset<T> {
+        self.simulate_with_rng(n, evidence, &mut rand::thread_rng())
+    }
+
+    /// Generate a synthetic dataset like `simulate`, using `rng`.
+    pub fn simulate_with_rng<R: Rng + ?Sized>(
+        &self,
+        n: usize,
+        evidence: &HashMap<&str, T>,
+        rng: &mut R,
+    ) -> Dataset<T> {
+        if evidence.is_empty() {
+            return self.sample_with_rng(n, rng);
+        }
+        self.sample_posterior_with_rng(evidence, n, &PosteriorSampler::default(), rng)
+    }
+
+    /// Draw `n` complete assignments from the posterior given `evidence`, one column per node
+    /// in node order, by likelihood weighting over at least 10 000 particles followed by
+    /// resampling.
+    pub fn sample_posterior(&self, evidence: &HashMap<&str, T>, n: usize) -> Dataset<T> {
+        self.sample_posterior_with_rng(
+            evidence,
+            n,
+            &PosteriorSampler::default(),
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Draw `n` complete assignments from the posterior given `evidence` with `sampler`,
+    /// using `rng`.
+    pub fn sample_posterior_with_rng<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<&str, T>,
+        n: usize,
+        sampler: &PosteriorSampler,
+        rng: &mut R,
+    ) -> Dataset<T> {
+        let evidence = self.evidence_ids(evidence);
+        let mut dataset = Dataset::new(self.node_names());
+        if n == 0 {
+            return dataset;
+        }
+        let samples = match sampler {
+            PosteriorSampler::LikelihoodWeighting { particles } => {
+                // Resample each connected component by its own weights, so the evidence in
+                // one component does not affect the draws in another.
+                let order = self.topological_order();
+                let components = self.component_indices();
+                let particles = (0..(*particles).max(n))
+                    .map(|_| {
+                        let (assignment, _) = self.weighted_sample(&order, &evidence, rng);
+                        let weights = self.component_weights(&assignment, &evidence, &components);
+                        (assignment, weights)
+                    })
+                    .collect::<Vec<(HashMap<NodeId, T>, Vec<Probability>)>>();
+                let mut samples = vec![HashMap::new(); n];
+                for component in 0..components.iter().max().map_or(0, |max| max + 1) {
+                    let weighted = particles
+                        .iter()
+                        .map(|(assignment, weights)| (assignment.clone(), weights[component]))
+                        .collect::<Vec<(HashMap<NodeId, T>, Probability)>>();
+                    for (sample, draw) in samples.iter_mut().zip(resample(&weighted, n, rng)) {
+                        sample.extend(
+                            draw.into_iter()
+                                .filter(|(id, _)| components[*id] == component),
+                        );
+                    }
+                }
+                samples
+            }
+            PosteriorSampler::Gibbs { burn_in, thin } => {
+                self.gibbs_chain(&evidence, n, *burn_in, *thin, rng)
+            }
+        };
+        for assignment in &samples {
+            dataset.add_row(self.assignment_row(assignment));
+        }
+        dataset
+    }
+
+    /// Draw `n` complete assignments by likelihood weighting with `evidence` clamped, each
+    /// with its weight: the probability of the evidence given the rest of the draw. The
+    /// weighted average of any function of the assignments estimates its posterior
+    /// expectation.
+    pub fn weighted_samples(
+        &self,
+        evidence: &HashMap<&str, T>,
+        n: usize,
+    ) -> Vec<(HashMap<Name, T>, Probability)> {
+        self.weighted_samples_with_rng(evidence, n, &mut rand::thread_rng())
+    }
+
+    /// Draw weighted assignments like `weighted_samples`, using `rng`.
+    pub fn weighted_samples_with_rng<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<&str, T>,
+        n: usize,
+        rng: &mut R,
+    ) -> Vec<(HashMap<Name, T>, Probability)> {
+        let evidence = self.evidence_ids(evidence);
+        let order = self.topological_order();
+        (0..n)
+            .map(|_| {
+                let (assignment, weight) = self.weighted_sample(&order, &evidence, rng);
+                (self.named_assignment(&assignment), weight)
+            })
+            .collect()
+    }
+
+    /// Key evidence (or interventions) by node id.
+    pub(crate) fn evidence_ids(&self, evidence: &HashMap<&str, T>) -> HashMap<NodeId, T> {
+        evidence
+            .iter()
+            .map(|(name, value)| {
+                if !self.node_map.contains_key(*name) {
+                    panic!("Node `{}` not found", name);
+                }
+                if self.is_node_hidden(name) {
+                    panic!("Hidden node `{}` cannot be observed", name);
+                }
+                (self.get_node_index(name), value.clone())
+            })
+            .collect()
+    }
+
+    /// The id of every intervened node. Unlike observing them, intervening on hidden nodes
+    /// is allowed.
+    fn intervention_ids(&self, interventions: &HashMap<&str, T>) -> HashMap<NodeId, T> {
+        interventions
+            .iter()
+            .map(|(name, value)| match self.node_map.get(*name) {
+                Some(id) => (*id, value.clone()),
+                None => panic!("Node `{}` not found", name),
+            })
+            .collect()
+    }
+
+    /// Run a Gibbs chain over the non-evidence nodes and keep `n` of its states, discarding
+    /// the first `burn_in` sweeps and then keeping every `thin`-th sweep.
+    pub(crate) fn gibbs_chain<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<NodeId, T>,
+        n: usize,
+        burn_in: usize,
+        thin: usize,
+        rng: &mut R,
+    ) -> Vec<HashMap<NodeId, T>> {
+        let thin = thin.max(1);
+        let mut samples = Vec::new();
+        if n == 0 {
+            return samples;
+        }
+        let mut assignment = self.initial_state(evidence, rng);
+        let free = (0..self.nodes.len())
+            .filter(|id| !evidence.contains_key(id))
+            .collect::<Vec<NodeId>>();
+        for sweep in 0.. {
+            for id in &free {
+                let value = self.draw_from_blanket(*id, &assignment, rng);
+                assignment.insert(*id, value);
+            }
+            if sweep >= burn_in && (sweep - burn_in).is_multiple_of(thin) {
+                samples.push(assignment.clone());
+                if samples.len() == n {
+                    break;
+                }
+            }
+        }
+        samples
+    }
+
+    /// A starting state for Gibbs sampling: a forward draw with the evidence clamped that has
+    /// nonzero probability.
+    fn initial_state<R: Rng + ?Sized>(
+        &self,
+        evidence: &HashMap<NodeId, T>,
+        rng: &mut R,
+    ) -> HashMap<NodeId, T> {
+        let order = self.topological_order();
+        for _ in 0..MAX_INITIAL_ATTEMPTS {
+            let (assignment, weight) = self.weighted_sample(&order, evidence, rng);
+            if weight > 0.0 {
+                return assignment;
+            }
+        }
+        panic!("Evidence has zero probability");
+    }
+
+    /// Draw a value of node `id` given the rest of `assignment`, which only depends on its
+    /// Markov blanket: P(x | pa(x)) times P(c | pa(c)) for each child c.
+    pub(crate) fn draw_from_blanket<R: Rng + ?Sized>(
+        &self,
+        id: NodeId,
+        assignment: &HashMap<NodeId, T>,
+        rng: &mut R,
+    ) -> T {
+        let node = &self.nodes[id];
+        let mut assignment = assignment.clone();
+        let domain = self.get_node_domain(id);
+        let weights = domain
+            .iter()
+            .map(|value| {
+                assignment.insert(id, value.clone());
+                self.local_probability(node, value, &assignment)
+                    * node
+                        .children
+                        .iter()
+                        .map(|child| {
+                            let child = &self.nodes[*child];
+                            self.local_probability(child, &assignment[&child.id], &assignment)
+                        })
+                        .product::<Probability>()
+            })
+            .collect::<Vec<Probability>>();
+        let total = weights.iter().sum::<Probability>();
+        let mut threshold = rng.gen::<Probability>() * total;
+        for (value, weight) in domain.iter().zip(&weights) {
+            threshold -= weight;
+            if threshold < 0.0 {
+                return value.clone();
+            }
+        }
+        domain
+            .iter()
+            .zip(&weights)
+            .rev()
+            .find(|(_, weight)| **weight > 0.0)
+            .map(|(value, _)| value)
+            .unwrap_or(&assignment[&id])
+            .clone()
+    }
+
+    /// A complete assignment keyed by node name.
+    pub(crate) fn named_assignment(&self, assignment: &HashMap<NodeId, T>) -> HashMap<Name, T> {
+        assignment
+            .iter()
+            .map(|(id, value)| (self.get_node_name(*id).to_string(), value.clone()))
+            .collect()
+    }
+
+    /// The values of a complete assignment in node order.
+    pub(crate) fn assignment_row(&self, assignment: &HashMap<NodeId, T>) -> Vec<T> {
+        (0..self.nodes.len())
+            .map(|id| assignment[&id].clone())
+            .collect()
+    }
+
+    /// The node ids ordered so that every node comes after its parents.
+    pub(crate) fn topological_order(&self) -> Vec<NodeId> {
+        let mut remaining = self
+            .nodes
+            .iter()
+            .map(|node| node.parents.len())
+            .collect::<Vec<usize>>();
+        let mut ready = (0..self.nodes.len())
+            .filter(|id| remaining[*id] == 0)
+            .collect::<Vec<NodeId>>();
+        let mut order = Vec::new();
+        while let Some(id) = ready.pop() {
+            order.push(id);
+            for child in &self.nodes[id].children {
+                remaining[*child] -= 1;
+                if remaining[*child] == 0 {
+                    ready.push(*child);
+                }
+            }
+        }
+        order
+    }
+
+    /// Draw one complete assignment in topological `order`, clamping the evidence nodes,
+    /// together with its likelihood weight (the probability of the evidence given the draw).
+    pub(crate) fn weighted_sample<R: Rng + ?Sized>(
+        &self,
+        order: &[NodeId],
+        evidence: &HashMap<NodeId, T>,
+        rng: &mut R,
+    ) -> (HashMap<NodeId, T>, Probability) {
+        self.weighted_sample_given(order, &HashMap::new(), evidence, rng)
+    }
+
+    /// Draw one complete assignment like `weighted_sample`, with the `fixed` nodes set to
+    /// their values without contributing to the weight.
+    pub(crate) fn weighted_sample_given<R: Rng + ?Sized>(
+        &self,
+        order: &[NodeId],
+        fixed: &HashMap<NodeId, T>,
+        evidence: &HashMap<NodeId, T>,
+        rng: &mut R,
+    ) -> (HashMap<NodeId, T>, Probability) {
+        let mut assignment = HashMap::new();
+        let mut weight = 1.0;
+        for id in order {
+            let node = &self.nodes[*id];
+            let value = match (fixed.get(id), evidence.get(id)) {
+                (Some(value), _) => value.clone(),
+                (None, Some(value)) => {
+                    weight *= self.local_probability(node, value, &assignment);
+                    value.clone()
+                }
+                (None, None) => self.draw(*id, &assignment, rng),
+            };
+            assignment.insert(*id, value);
+        }
+        (assignment, weight)
+    }
+
+    /// Draw a value of node `id` given the values of its parents in `assignment`.
+    pub(crate) fn draw<R: Rng + ?Sized>(
+        &self,
+        id: NodeId,
+        assignment: &HashMap<NodeId, T>,
+        rng: &mut R,
+    ) -> T {
+        let node = &self.nodes[id];
+        let domain = self.get_node_domain(id);
+        let mut threshold = rng.gen::<Probability>();
+        for value in &domain {
+            threshold -= self.local_probability(node, value, assignment);
+            if threshold < 0.0 {
+                return value.clone();
+            }
+        }
+        domain
+            .iter()
+            .rev()
+            .find(|value| self.local_probability(node, value, assignment) > 0.0)
+            .unwrap_or(&domain[0])
+            .clone()
+    }
+}
+
+/// Draw `n` of the weighted `particles` in proportion to their weights by systematic
+/// resampling: one uniform offset, then evenly spaced pointers.
+pub(crate) fn resample<T: Clone, R: Rng + ?Sized>(
+    particles: &[(HashMap<NodeId, T>, Probability)],
+    n: usize,
+    rng: &mut R,
+) -> Vec<HashMap<NodeId, T>> {
+    let total = particles
+        .iter()
+        .map(|(_, weight)| weight)
+        .sum::<Probability>();
+    if total <= 0.0 {
+        panic!("Evidence has zero probability");
+    }
+    let step = total / n as Probability;
+    let mut pointer = rng.gen::<Probability>() * step;
+    let mut cumulative = 0.0;
+    let mut samples = Vec::new();
+    for (assignment, weight) in particles {
+        cumulative += weight;
+        while pointer < cumulative && samples.len() < n {
+            samples.push(assignment.clone());
+            pointer += step;
+        }
+    }
+    // Rounding can leave the last pointer just past the total.
+    while samples.len() < n {
+        let (assignment, _) = particles
+            .iter()
+            .rev()
+            .find(|(_, weight)| *weight > 0.0)
+            .unwrap();
+        samples.push(assignment.clone());
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bayesian_network::NodeType;
+    use crate::testing::{assert_close, brute_force_probability, confounded};
+
+    /// The share of records with `value` in `column`.
+    fn frequency<T: Clone + PartialEq>(dataset: &Dataset<T>, column: &str, value: &T) -> f64 {
+        let column = dataset.column_index(column).unwrap();
+        dataset
+            .rows()
+            .iter()
+            .filter(|row| row[column] == *value)
+            .count() as f64
+            / dataset.len() as f64
+    }
+
+    #[test]
+    fn simulate_draws_records_given_the_evidence() {
+        let network = confounded();
+        let evidence = HashMap::from([("y", true)]);
+        let dataset = network.simulate_with_rng(20_000, &evidence, &mut seeded_rng(1));
+        assert_eq!(dataset.columns(), ["z", "x", "y"]);
+        assert_eq!(dataset.len(), 20_000);
+        assert_eq!(frequency(&dataset, "y", &true), 1.0);
+        assert_close(
+            frequency(&dataset, "z", &true),
+            brute_force_probability(&network, "z", &true, &evidence),
+            0.02,
+        );
+    }
+
+    #[test]
+    fn sample_draws_from_the_joint() {
+        let network = confounded();
+        let dataset = network.sample_with_rng(20_000, &mut seeded_rng(2));
+        assert_eq!(dataset.len(), 20_000);
+        for name in ["z", "x", "y"] {
+            assert_close(
+                frequency(&dataset, name, &true),
+                brute_force_probability(&network, name, &true, &HashMap::new()),
+                0.02,
+            );
+        }
+        // Pairs follow the joint too, not just the marginals.
+        let both = dataset.rows().iter().filter(|row| row[0] && row[1]).count() as f64;
+        assert_close(both / 20_000.0, 0.4 * 0.8, 0.02);
+    }
+
+    #[test]
+    fn posterior_samplers_agree_with_enumeration() {
+        let network = confounded();
+        let evidence = HashMap::from([("y", false)]);
+        let expected = brute_force_probability(&network, "z", &true, &evidence);
+        for sampler in [
+            PosteriorSampler::default(),
+            PosteriorSampler::Gibbs {
+                burn_in: 100,
+                thin: 2,
+            },
+        ] {
+            let dataset =
+                network.sample_posterior_with_rng(&evidence, 20_000, &sampler, &mut seeded_rng(3));
+            assert_eq!(dataset.len(), 20_000);
+            assert_eq!(frequency(&dataset, "y", &false), 1.0);
+            assert_close(frequency(&dataset, "z", &true), expected, 0.03);
+        }
+    }
+
+    #[test]
+    fn resample_draws_in_proportion_to_the_weights() {
+        let particles = vec![
+            (HashMap::from([(0, 'a')]), 1.0),
+            (HashMap::from([(0, 'b')]), 0.0),
+            (HashMap::from([(0, 'c')]), 3.0),
+        ];
+        let samples = resample(&particles, 8, &mut seeded_rng(4));
+        let count = |value| samples.iter().filter(|sample| sample[&0] == value).count();
+        // Systematic resampling is exact up to one draw.
+        assert_eq!((count('a'), count('b'), count('c')), (2, 0, 6));
+    }
+
+    #[test]
+    #[should_panic(expected = "Evidence has zero probability")]
+    fn impossible_evidence_panics() {
+        let mut network = confounded();
+        network.add_node(
+            "never",
+            NodeType::Root(HashMap::from([(true, 0.0), (false, 1.0)])),
+        );
+        network.sample_posterior_with_rng(
+            &HashMap::from([("never", true)]),
+            10,
+            &PosteriorSampler::LikelihoodWeighting { particles: 100 },
+            &mut seeded_rng(5),
+        );
+    }
+
+    #[test]
+    fn seeded_rngs_reproduce_their_draws() {
+        let network = confounded();
+        let evidence = HashMap::from([("x", true)]);
+        let sampler = PosteriorSampler::Gibbs {
+            burn_in: 10,
+            thin: 1,
+        };
+        let draw = |seed| {
+            let mut rng = seeded_rng(seed);
+            (
+                network.sample_with_rng(50, &mut rng).rows().to_vec(),
+                network
+                    .sample_posterior_with_rng(&evidence, 50, &sampler, &mut rng)
+                    .rows()
+                    .to_vec(),
+            )
+        };
+        assert_eq!(draw(6), draw(6));
+        assert_ne!(draw(6), draw(7));
+    }
+
+    #[test]
+    fn sample_do_cuts_the_intervened_node_from_its_causes() {
+        let network = confounded();
+        let interventions = HashMap::from([("x", true)]);
+        let dataset = network.sample_do_with_rng(&interventions, 20_000, &mut seeded_rng(8));
+        let expected = network.infer_do(&interventions, &HashMap::new());
+        assert_eq!(frequency(&dataset, "x", &true), 1.0);
+        // z keeps its prior, unlike when x = true is observed.
+        assert_close(frequency(&dataset, "z", &true), 0.4, 0.02);
+        assert_close(
+            frequency(&dataset, "y", &true),
+            expected[network.get_node_index("y")][&true],
+            0.02,
+        );
+    }
+
+    #[test]
+    fn sample_do_intervenes_on_hidden_nodes() {
+        let mut network = confounded();
+        network.set_node_hidden("z", true);
+        let interventions = HashMap::from([("z", true)]);
+        let dataset = network.sample_do_with_rng(&interventions, 20_000, &mut seeded_rng(10));
+        assert_eq!(frequency(&dataset, "z", &true), 1.0);
+        assert_close(frequency(&dataset, "x", &true), 0.8, 0.02);
+        // P(y | do(z)) = .8 * .9 + .2 * .6.
+        assert_close(frequency(&dataset, "y", &true), 0.84, 0.02);
+    }
+
+    #[test]
+    fn weighted_samples_average_to_the_posterior() {
+        let network = confounded();
+        let evidence = HashMap::from([("y", true)]);
+        let samples = network.weighted_samples_with_rng(&evidence, 20_000, &mut seeded_rng(9));
+        assert!(samples.iter().all(|(assignment, _)| assignment["y"]));
+        // Each weight is P(y = true | z, x).
+        for (assignment, weight) in samples.iter().take(10) {
+            let expected = match (assignment["z"], assignment["x"]) {
+                (true, true) => 0.9,
+                (true, false) => 0.6,
+                (false, true) => 0.5,
+                (false, false) => 0.1,
+            };
+            assert_close(*weight, expected, 1e-12);
+        }
+        let total = samples.iter().map(|(_, weight)| weight).sum::<f64>();
+        let z = samples
+            .iter()
+            .filter(|(assignment, _)| assignment["z"])
+            .map(|(_, weight)| weight)
+            .sum::<f64>();
+        assert_close(
+            z / total,
+            brute_force_probability(&network, "z", &true, &evidence),
+            0.02,
+        );
+    }
+}
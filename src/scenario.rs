@@ -0,0 +1,225 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    hash::Hash,
+};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, Probability},
+    dataset::quote_field,
+    posterior::Posterior,
+};
+
+/// A named evidence configuration, such as "best case" or "sensor failure".
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario<T> {
+    pub name: String,
+    pub evidence: HashMap<Name, T>,
+}
+
+impl<T> Scenario<T> {
+    /// Create a scenario from its name and evidence.
+    pub fn new(name: &str, evidence: HashMap<Name, T>) -> Scenario<T> {
+        Scenario {
+            name: name.to_string(),
+            evidence,
+        }
+    }
+}
+
+/// The posteriors of the same targets under each of several scenarios.
+#[derive(Clone, Debug)]
+pub struct ScenarioReport<T> {
+    scenarios: Vec<String>,
+    posteriors: Vec<Posterior<T>>,
+}
+
+impl<T: Clone + PartialEq> ScenarioReport<T> {
+    /// Get the scenario names, in order.
+    pub fn scenarios(&self) -> &[String] {
+        &self.scenarios
+    }
+
+    /// Get the posterior of the targets under a scenario.
+    pub fn posterior(&self, scenario: &str) -> Option<&Posterior<T>> {
+        self.scenarios
+            .iter()
+            .position(|name| name == scenario)
+            .map(|index| &self.posteriors[index])
+    }
+
+    /// Get the probability of a target taking `value` under a scenario.
+    pub fn probability(&self, scenario: &str, name: &str, value: &T) -> Option<Probability> {
+        self.posterior(scenario)?.probability(name, value)
+    }
+
+    /// The `node, state` pairs of the report with their probability under each scenario.
+    fn rows(&self) -> Vec<(&str, &T, Vec<Probability>)> {
+        let Some(first) = self.posteriors.first() else {
+            return Vec::new();
+        };
+        first
+            .nodes()
+            .into_iter()
+            .flat_map(|name| {
+                first
+                    .distribution(name)
+                    .unwrap()
+                    .iter()
+                    .map(move |(value, _)| {
+                        let probs = self
+                            .posteriors
+                            .iter()
+                            .map(|posterior| posterior.probability(name, value).unwrap())
+                            .collect();
+                        (name, value, probs)
+                    })
+            })
+            .collect()
+    }
+}
+
+impl<T: Clone + PartialEq + Display> ScenarioReport<T> {
+    /// Write the report as CSV: a `node,state` column pair followed by one column per scenario.
+    pub fn to_csv(&self) -> String {
+        let mut csv = ["node", "state"]
+            .iter()
+            .map(|column| column.to_string())
+            .chain(self.scenarios.iter().map(|name| quote_field(name)))
+            .collect::<Vec<String>>()
+            .join(",");
+        csv.push('\n');
+        for (name, value, probs) in self.rows() {
+            let mut fields = vec![quote_field(name), quote_field(&value.to_string())];
+            fields.extend(probs.iter().map(|prob| prob.to_string()));
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Lay out the report as an aligned plain-text table.
+    pub fn to_table(&self) -> String {
+        let mut lines = vec![["node", "state"]
+            .iter()
+            .map(|column| column.to_string())
+            .chain(self.scenarios.iter().cloned())
+            .collect::<Vec<String>>()];
+        for (name, value, probs) in self.rows() {
+            let mut line = vec![name.to_string(), value.to_string()];
+            line.extend(probs.iter().map(|prob| format!("{:.6}", prob)));
+            lines.push(line);
+        }
+        let width = |s: &str| s.chars().count();
+        let widths = (0..lines[0].len())
+            .map(|column| lines.iter().map(|line| width(&line[column])).max().unwrap())
+            .collect::<Vec<usize>>();
+        let mut s = String::new();
+        for line in &lines {
+            let cells = line
+                .iter()
+                .zip(&widths)
+                .map(|(cell, to)| format!("{}{}", cell, " ".repeat(to - width(cell))))
+                .collect::<Vec<String>>();
+            s.push_str(cells.join("  ").trim_end());
+            s.push('\n');
+        }
+        s
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Infer the posteriors of `targets` under each scenario, for side-by-side comparison.
+    pub fn run_scenarios(&self, scenarios: &[Scenario<T>], targets: &[&str]) -> ScenarioReport<T> {
+        ScenarioReport {
+            scenarios: scenarios
+                .iter()
+                .map(|scenario| scenario.name.clone())
+                .collect(),
+            posteriors: scenarios
+                .iter()
+                .map(|scenario| {
+                    let evidence = scenario
+                        .evidence
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.clone()))
+                        .collect::<HashMap<&str, T>>();
+                    self.posterior(&evidence).select(targets)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_probability, TOLERANCE};
+
+    fn scenarios() -> Vec<Scenario<bool>> {
+        vec![
+            Scenario::new("baseline", HashMap::new()),
+            Scenario::new(
+                "celebration",
+                HashMap::from([("bonus".to_string(), true), ("feast".to_string(), true)]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn each_scenario_conditions_on_its_own_evidence() {
+        let network = bonus();
+        let report = network.run_scenarios(&scenarios(), &["mood", "performance"]);
+        assert_eq!(report.scenarios(), ["baseline", "celebration"]);
+        assert_eq!(
+            report.posterior("celebration").unwrap().nodes(),
+            vec!["mood", "performance"]
+        );
+        for scenario in scenarios() {
+            let evidence = scenario
+                .evidence
+                .iter()
+                .map(|(name, value)| (name.as_str(), *value))
+                .collect();
+            assert_close(
+                report
+                    .probability(&scenario.name, "performance", &true)
+                    .unwrap(),
+                brute_force_probability(&network, "performance", &true, &evidence),
+                TOLERANCE,
+            );
+        }
+        assert_eq!(report.probability("worst case", "mood", &true), None);
+    }
+
+    #[test]
+    fn reports_have_one_column_per_scenario() {
+        let report = bonus().run_scenarios(&scenarios(), &["performance"]);
+        let celebration = report
+            .probability("celebration", "performance", &true)
+            .unwrap();
+        assert_eq!(
+            report.to_csv(),
+            format!(
+                "node,state,baseline,celebration\nperformance,true,0.01,{}\nperformance,false,0.99,{}\n",
+                celebration,
+                report
+                    .probability("celebration", "performance", &false)
+                    .unwrap()
+            )
+        );
+        let table = report.to_table();
+        let lines = table.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], "node         state  baseline  celebration");
+        assert_eq!(
+            lines[1],
+            format!("performance  true   0.010000  {:.6}", celebration)
+        );
+    }
+
+    #[test]
+    fn an_empty_report_has_only_a_header() {
+        let report = bonus().run_scenarios(&[], &["mood"]);
+        assert_eq!(report.to_csv(), "node,state\n");
+    }
+}
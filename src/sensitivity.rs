@@ -0,0 +1,276 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability},
+    learning::configurations,
+};
+
+/// A parameter of a CPT: the probability of `node` taking `value` given its parents taking
+/// `parents`, in parent order. A root node has a single row, with no parent values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CptEntry<T> {
+    pub node: Name,
+    pub parents: Vec<T>,
+    pub value: T,
+}
+
+/// How a posterior probability depends on one CPT parameter `x`, as returned by
+/// `sensitivity`: `(a * x + b) / (c * x + d)`, with the other entries of the parameter's row
+/// scaled to keep it normalized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SensitivityFunction {
+    pub a: Probability,
+    pub b: Probability,
+    pub c: Probability,
+    pub d: Probability,
+    /// The current value of the parameter.
+    pub parameter: Probability,
+}
+
+impl SensitivityFunction {
+    /// Get the posterior probability when the parameter is `x`.
+    pub fn value(&self, x: Probability) -> Probability {
+        (self.a * x + self.b) / (self.c * x + self.d)
+    }
+
+    /// Get the derivative of the posterior probability with respect to the parameter at `x`.
+    pub fn derivative(&self, x: Probability) -> Probability {
+        (self.a * self.d - self.b * self.c) / (self.c * x + self.d).powi(2)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get how `P(target = value | evidence)` varies with one CPT parameter, when the other
+    /// entries of its row are scaled proportionally (or set uniformly if it is 1). The
+    /// function is exact, from variable elimination at two values of the parameter.
+    pub fn sensitivity(
+        &self,
+        target: &str,
+        value: &T,
+        evidence: &HashMap<&str, T>,
+        entry: &CptEntry<T>,
+    ) -> SensitivityFunction {
+        let id = self.causal_node(&entry.node);
+        let domains = self.sensitivity_domains(target, value, evidence);
+        self.sensitivity_function(&domains, id, &entry.parents, &entry.value)
+    }
+
+    /// Get the sensitivity function of `P(target = value | evidence)` for every CPT parameter
+    /// it can depend on, most sensitive first: ranked by the absolute derivative at the
+    /// current value. Parameters of nodes that Bayes-ball finds irrelevant to the query are
+    /// left out.
+    pub fn sensitivity_report(
+        &self,
+        target: &str,
+        value: &T,
+        evidence: &HashMap<&str, T>,
+    ) -> Vec<(CptEntry<T>, SensitivityFunction)> {
+        let domains = self.sensitivity_domains(target, value, evidence);
+        let observed = self.evidence_ids(evidence);
+        let (requisite, _) = self.bayes_ball(
+            &[self.causal_node(target)],
+            &observed.keys().copied().collect::<Vec<NodeId>>(),
+        );
+        let mut report = Vec::new();
+        for node in self.nodes.iter().filter(|node| requisite[node.id]) {
+            let rows = configurations(
+                &node
+                    .parents
+                    .iter()
+                    .map(|parent| self.get_node_domain(*parent))
+                    .collect::<Vec<Vec<T>>>(),
+            );
+            for parents in rows {
+                for state in self.get_node_domain(node.id) {
+                    let function = self.sensitivity_function(&domains, node.id, &parents, &state);
+                    let entry = CptEntry {
+                        node: self.get_node_name(node.id).to_string(),
+                        parents: parents.clone(),
+                        value: state,
+                    };
+                    report.push((entry, function));
+                }
+            }
+        }
+        report.sort_by(|(_, x), (_, y)| {
+            y.derivative(y.parameter)
+                .abs()
+                .total_cmp(&x.derivative(x.parameter).abs())
+        });
+        report
+    }
+
+    /// The domain of each node restricted to the evidence, and the same with the target
+    /// restricted to `value` as well.
+    fn sensitivity_domains(
+        &self,
+        target: &str,
+        value: &T,
+        evidence: &HashMap<&str, T>,
+    ) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+        let target = self.causal_node(target);
+        let evidence = self.evidence_ids(evidence);
+        let domains = (0..self.nodes.len())
+            .map(|id| match evidence.get(&id) {
+                Some(value) => vec![value.clone()],
+                None => self.get_node_domain(id),
+            })
+            .collect::<Vec<Vec<T>>>();
+        let mut restricted = domains.clone();
+        restricted[target].retain(|state| state == value);
+        (domains, restricted)
+    }
+
+    /// The sensitivity function of the query given by `domains` for the entry of node `id`.
+    fn sensitivity_function(
+        &self,
+        (domains, restricted): &(Vec<Vec<T>>, Vec<Vec<T>>),
+        id: NodeId,
+        parents: &[T],
+        value: &T,
+    ) -> SensitivityFunction {
+        let name = self.get_node_name(id);
+        let node = &self.nodes[id];
+        let row = match &node.node_type {
+            NodeType::Root(prob_map) if parents.is_empty() => prob_map.clone(),
+            NodeType::Root(_) => panic!("Root node `{}` has no parent values", name),
+            _ => match self.get_cpt(id).get(parents) {
+                Some(row) => row.clone(),
+                None => panic!("CPT of `{}` has no row {:?}", name, parents),
+            },
+        };
+        let parameter = row.get(value).copied().unwrap_or(0.0);
+        let states = self.get_node_domain(id);
+        let mut network = self.copy();
+        network.expand_cpt(id);
+        // The query probabilities with the parameter at 0 and at 1.
+        let mut points = Vec::new();
+        for x in [0.0, 1.0] {
+            let varied = states
+                .iter()
+                .map(|state| {
+                    let prob = if state == value {
+                        x
+                    } else if parameter < 1.0 {
+                        row.get(state).copied().unwrap_or(0.0) * (1.0 - x) / (1.0 - parameter)
+                    } else {
+                        (1.0 - x) / (states.len() - 1) as Probability
+                    };
+                    (state.clone(), prob)
+                })
+                .collect::<HashMap<T, Probability>>();
+            match &mut network.nodes[id].node_type {
+                NodeType::Root(prob_map) => *prob_map = varied,
+                _ => {
+                    network.nodes[id]
+                        .probability
+                        .insert(parents.to_vec(), varied);
+                }
+            }
+            let (joint, _) = network.eliminate(restricted, false);
+            let (evidence, _) = network.eliminate(domains, false);
+            points.push((joint, evidence));
+        }
+        let (a, b) = (points[1].0 - points[0].0, points[0].0);
+        let (c, d) = (points[1].1 - points[0].1, points[0].1);
+        if c * parameter + d <= 0.0 {
+            panic!("Evidence has zero probability");
+        }
+        SensitivityFunction {
+            a,
+            b,
+            c,
+            d,
+            parameter,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, brute_force_probability, row, sprinkler};
+
+    #[test]
+    fn sensitivity_function_matches_recomputing_with_the_edited_entry() {
+        let network = sprinkler();
+        let evidence = HashMap::from([("wet_grass", true)]);
+        let entry = CptEntry {
+            node: "sprinkler".to_string(),
+            parents: vec![true],
+            value: true,
+        };
+        let function = network.sensitivity("rain", &true, &evidence, &entry);
+        assert_close(function.parameter, 0.1, 1e-12);
+        assert_close(
+            function.value(function.parameter),
+            brute_force_probability(&network, "rain", &true, &evidence),
+            1e-12,
+        );
+        let id = network.get_node_index("sprinkler");
+        for x in [0.0, 0.25, 0.6, 1.0] {
+            let mut edited = network.copy();
+            edited.nodes[id].probability.insert(vec![true], row(x));
+            assert_close(
+                function.value(x),
+                brute_force_probability(&edited, "rain", &true, &evidence),
+                1e-12,
+            );
+        }
+        let step = 1e-6;
+        assert_close(
+            function.derivative(0.4),
+            (function.value(0.4 + step) - function.value(0.4 - step)) / (2.0 * step),
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn sensitivity_of_a_root_scales_the_rest_of_its_row() {
+        let network = sprinkler();
+        let evidence = HashMap::from([("sprinkler", true)]);
+        let entry = CptEntry {
+            node: "cloudy".to_string(),
+            parents: Vec::new(),
+            value: false,
+        };
+        let function = network.sensitivity("wet_grass", &true, &evidence, &entry);
+        let id = network.get_node_index("cloudy");
+        for x in [0.1, 0.9] {
+            let mut edited = network.copy();
+            edited.nodes[id].node_type = NodeType::Root(row(1.0 - x));
+            assert_close(
+                function.value(x),
+                brute_force_probability(&edited, "wet_grass", &true, &evidence),
+                1e-12,
+            );
+        }
+    }
+
+    #[test]
+    fn sensitivity_report_ranks_the_requisite_parameters() {
+        let network = bonus();
+        let report = network.sensitivity_report("mood", &true, &HashMap::new());
+        // Without evidence, the bonus and the feast are barren and cannot matter.
+        assert!(report
+            .iter()
+            .all(|(entry, _)| entry.node != "bonus" && entry.node != "feast"));
+        // Two states for each of two prior rows and four mood rows.
+        assert_eq!(report.len(), 2 * (1 + 1 + 4));
+        let slopes = report
+            .iter()
+            .map(|(_, function)| function.derivative(function.parameter).abs())
+            .collect::<Vec<Probability>>();
+        assert!(slopes.windows(2).all(|pair| pair[0] >= pair[1]));
+        // P(mood) = sum over the parents of P(mood | parents) P(parents), so the most
+        // sensitive entry is the likeliest row, which has slope P(no performance, no race).
+        let (entry, function) = &report[0];
+        assert_eq!(entry.node, "mood");
+        assert_eq!(entry.parents, [false, false]);
+        assert_close(
+            function.derivative(function.parameter).abs(),
+            0.99 * 0.9,
+            1e-12,
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::{fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name, NodeId};
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Whether `x` and `y` are d-separated given the nodes `given`, so that the network makes
+    /// them independent given those nodes whatever its CPTs. A node in `given` is d-separated
+    /// from every other node.
+    pub fn d_separated(&self, x: &str, y: &str, given: &[&str]) -> bool {
+        let x = self.causal_node(x);
+        let y = self.causal_node(y);
+        let given = given
+            .iter()
+            .map(|name| self.causal_node(name))
+            .collect::<Vec<NodeId>>();
+        !self.d_connected(&[x], &given, &[]).contains(&y)
+    }
+
+    /// Get the Markov blanket of a node, in node order: its parents, its children and the
+    /// other parents of its children. Given its Markov blanket, a node is independent of
+    /// every other node.
+    pub fn markov_blanket(&self, name: &str) -> Vec<Name> {
+        let id = self.causal_node(name);
+        let node = &self.nodes[id];
+        let mut blanket = vec![false; self.nodes.len()];
+        for parent in &node.parents {
+            blanket[*parent] = true;
+        }
+        for child in &node.children {
+            blanket[*child] = true;
+            for parent in &self.nodes[*child].parents {
+                blanket[*parent] = true;
+            }
+        }
+        blanket[id] = false;
+        let ids = (0..self.nodes.len())
+            .filter(|id| blanket[*id])
+            .collect::<Vec<NodeId>>();
+        self.node_names_of(&ids)
+    }
+
+    /// Get the paths between `x` and `y`, ignoring edge direction, that are active given the
+    /// nodes `given`: each collider on the path is in `given` or has a descendant there, and
+    /// no other node on it is in `given`. The list is empty exactly when `x` and `y` are
+    /// d-separated. The search goes through every simple path, so its cost grows
+    /// exponentially with the size of the network.
+    pub fn active_paths(&self, x: &str, y: &str, given: &[&str]) -> Vec<Vec<Name>> {
+        let x = self.causal_node(x);
+        let y = self.causal_node(y);
+        let given = given
+            .iter()
+            .map(|name| self.causal_node(name))
+            .collect::<Vec<NodeId>>();
+        if given.contains(&x) || given.contains(&y) {
+            return Vec::new();
+        }
+        self.simple_paths(x, y)
+            .into_iter()
+            .filter(|path| self.is_path_open(path, &given))
+            .map(|path| self.node_names_of(&path))
+            .collect()
+    }
+
+    /// The simple paths from `from` to `to`, ignoring edge direction, in order.
+    pub(crate) fn simple_paths(&self, from: NodeId, to: NodeId) -> Vec<Vec<NodeId>> {
+        let mut paths = Vec::new();
+        let mut stack = vec![vec![from]];
+        while let Some(path) = stack.pop() {
+            let last = path[path.len() - 1];
+            if last == to {
+                paths.push(path);
+                continue;
+            }
+            let node = &self.nodes[last];
+            for next in node.parents.iter().chain(&node.children) {
+                if !path.contains(next) {
+                    let mut longer = path.clone();
+                    longer.push(*next);
+                    stack.push(longer);
+                }
+            }
+        }
+        paths.sort();
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::testing::{assert_close, brute_force_probability, graph, sprinkler, TOLERANCE};
+
+    #[test]
+    fn d_separation_follows_chains_forks_and_colliders() {
+        let network = graph(
+            &["a", "b", "c", "d", "e"],
+            &[("a", "c"), ("b", "c"), ("c", "d"), ("d", "e")],
+        );
+        assert!(network.d_separated("a", "b", &[]));
+        assert!(!network.d_separated("a", "b", &["c"]));
+        // A descendant of the collider opens it too.
+        assert!(!network.d_separated("a", "b", &["e"]));
+        assert!(!network.d_separated("a", "e", &[]));
+        assert!(network.d_separated("a", "e", &["d"]));
+        assert!(network.d_separated("a", "c", &["c"]));
+        assert_eq!(network.active_paths("a", "b", &["e"]), [["a", "c", "b"]]);
+        assert!(network.active_paths("a", "e", &["c"]).is_empty());
+    }
+
+    #[test]
+    fn active_paths_agree_with_d_separation() {
+        let network = graph(
+            &["z1", "z2", "z3", "x", "w", "y"],
+            &[
+                ("z1", "z3"),
+                ("z2", "z3"),
+                ("z1", "x"),
+                ("z3", "x"),
+                ("z3", "y"),
+                ("z2", "y"),
+                ("x", "w"),
+                ("w", "y"),
+            ],
+        );
+        let names = ["z1", "z2", "z3", "x", "w", "y"];
+        for x in names {
+            for y in names {
+                if x == y {
+                    continue;
+                }
+                for mask in 0..1 << names.len() {
+                    let given = (0..names.len())
+                        .filter(|i| mask & (1 << i) != 0 && names[*i] != y)
+                        .map(|i| names[i])
+                        .collect::<Vec<&str>>();
+                    assert_eq!(
+                        network.d_separated(x, y, &given),
+                        network.active_paths(x, y, &given).is_empty(),
+                        "{} and {} given {:?}",
+                        x,
+                        y,
+                        given
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn d_separated_nodes_are_independent() {
+        let network = sprinkler();
+        assert!(network.d_separated("sprinkler", "rain", &["cloudy"]));
+        assert!(!network.d_separated("sprinkler", "rain", &["cloudy", "wet_grass"]));
+        for cloudy in [true, false] {
+            let given = HashMap::from([("cloudy", cloudy)]);
+            let mut both = given.clone();
+            both.insert("rain", true);
+            assert_close(
+                brute_force_probability(&network, "sprinkler", &true, &both),
+                brute_force_probability(&network, "sprinkler", &true, &given),
+                TOLERANCE,
+            );
+        }
+    }
+
+    #[test]
+    fn markov_blanket_holds_parents_children_and_coparents() {
+        let network = sprinkler();
+        assert_eq!(network.markov_blanket("cloudy"), ["sprinkler", "rain"]);
+        assert_eq!(
+            network.markov_blanket("sprinkler"),
+            ["cloudy", "rain", "wet_grass"]
+        );
+        assert_eq!(network.markov_blanket("wet_grass"), ["sprinkler", "rain"]);
+        // Given its blanket, cloudy does not depend on the wet grass.
+        let blanket = HashMap::from([("sprinkler", true), ("rain", false)]);
+        let mut everything = blanket.clone();
+        everything.insert("wet_grass", true);
+        assert_close(
+            brute_force_probability(&network, "cloudy", &true, &everything),
+            brute_force_probability(&network, "cloudy", &true, &blanket),
+            TOLERANCE,
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use crate::bayesian_network::{BayesianNetwork, NodeType, Probability};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    hash::Hash,
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "NodeType")]
+enum NodeTypeRepr<T> {
+    Root(Vec<(T, Probability)>),
+    Leaf,
+    Inner,
+}
+
+/// One row of a CPT: the parent values and the distribution of the child.
+#[derive(Serialize, Deserialize)]
+struct RowRepr<T> {
+    given: Vec<T>,
+    probabilities: Vec<(T, Probability)>,
+}
+
+// Optional fields are always written, since compact formats cannot skip them.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+struct NodeRepr<T> {
+    name: String,
+    node_type: NodeTypeRepr<T>,
+    #[serde(default)]
+    parents: Vec<String>,
+    #[serde(default)]
+    probability: Vec<RowRepr<T>>,
+    #[serde(default)]
+    states: Option<Vec<T>>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    shared_cpt: Option<String>,
+}
+
+/// A CPT registered once and shared by the nodes tied to it.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+struct SharedCptRepr<T> {
+    name: String,
+    probability: Vec<RowRepr<T>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "BayesianNetwork", bound(deserialize = "T: Deserialize<'de>"))]
+struct NetworkRepr<T> {
+    value_space: Vec<T>,
+    nodes: Vec<NodeRepr<T>>,
+    #[serde(default)]
+    shared_cpts: Vec<SharedCptRepr<T>>,
+}
+
+impl<T: Clone + Serialize> Serialize for NodeType<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            NodeType::Root(prob) => NodeTypeRepr::Root(
+                prob.iter()
+                    .map(|(value, prob)| (value.clone(), *prob))
+                    .collect(),
+            ),
+            NodeType::Leaf => NodeTypeRepr::Leaf,
+            NodeType::Inner => NodeTypeRepr::Inner,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de> + Eq + Hash> Deserialize<'de> for NodeType<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<NodeType<T>, D::Error> {
+        Ok(match NodeTypeRepr::deserialize(deserializer)? {
+            NodeTypeRepr::Root(prob) => NodeType::Root(prob.into_iter().collect()),
+            NodeTypeRepr::Leaf => NodeType::Leaf,
+            NodeTypeRepr::Inner => NodeType::Inner,
+        })
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Serialize> Serialize for BayesianNetwork<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Maps are written in value space order so the output is deterministic.
+        let ordered = |prob: &HashMap<T, Probability>| {
+            self.value_space
+                .iter()
+                .filter_map(|value| prob.get(value).map(|prob| (value.clone(), *prob)))
+                .collect::<Vec<(T, Probability)>>()
+        };
+        let position = |value: &T| self.value_space.iter().position(|v| v == value);
+        let rows = |cpt: &HashMap<Vec<T>, HashMap<T, Probability>>| {
+            let mut rows = cpt
+                .iter()
+                .map(|(given, prob)| RowRepr {
+                    given: given.clone(),
+                    probabilities: ordered(prob),
+                })
+                .collect::<Vec<RowRepr<T>>>();
+            rows.sort_by_key(|row| row.given.iter().map(position).collect::<Vec<_>>());
+            rows
+        };
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let probability = rows(&self.get_cpt(node.id));
+                NodeRepr {
+                    name: self.get_node_name(node.id).to_string(),
+                    node_type: match &node.node_type {
+                        NodeType::Root(prob) => NodeTypeRepr::Root(ordered(prob)),
+                        NodeType::Leaf => NodeTypeRepr::Leaf,
+                        NodeType::Inner => NodeTypeRepr::Inner,
+                    },
+                    parents: node
+                        .parents
+                        .iter()
+                        .map(|parent| self.get_node_name(*parent).to_string())
+                        .collect(),
+                    probability,
+                    states: node.states.clone(),
+                    metadata: node.metadata.clone(),
+                    shared_cpt: node.shared_cpt.clone(),
+                }
+            })
+            .collect();
+        let shared_cpts = self
+            .shared_cpt_names()
+            .into_iter()
+            .map(|name| SharedCptRepr {
+                name: name.to_string(),
+                probability: rows(&self.shared_cpts[name]),
+            })
+            .collect();
+        NetworkRepr {
+            value_space: self.value_space.clone(),
+            nodes,
+            shared_cpts,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for BayesianNetwork<T>
+where
+    T: Clone + PartialEq + Eq + Hash + Debug + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BayesianNetwork<T>, D::Error> {
+        let repr = NetworkRepr::<T>::deserialize(deserializer)?;
+        // Check everything `add_node` and `add_dependency` would panic on.
+        let mut node_types = HashMap::new();
+        for node in &repr.nodes {
+            if node_types
+                .insert(node.name.as_str(), &node.node_type)
+                .is_some()
+            {
+                return Err(D::Error::custom(format!(
+                    "node `{}` is defined twice",
+                    node.name
+                )));
+            }
+        }
+        let check_value = |value: &T| {
+            if repr.value_space.contains(value) {
+                Ok(())
+            } else {
+                Err(D::Error::custom(format!(
+                    "value {:?} not in value space",
+                    value
+                )))
+            }
+        };
+        for node in &repr.nodes {
+            if let NodeTypeRepr::Root(prob) = &node.node_type {
+                if !node.parents.is_empty() {
+                    return Err(D::Error::custom(format!(
+                        "root node `{}` has parents",
+                        node.name
+                    )));
+                }
+                for (value, _) in prob {
+                    check_value(value)?;
+                }
+            }
+            for parent in &node.parents {
+                match node_types.get(parent.as_str()) {
+                    None => {
+                        return Err(D::Error::custom(format!(
+                            "parent `{}` of `{}` not found",
+                            parent, node.name
+                        )))
+                    }
+                    Some(NodeTypeRepr::Leaf) => {
+                        return Err(D::Error::custom(format!(
+                            "leaf node `{}` is a parent of `{}`",
+                            parent, node.name
+                        )))
+                    }
+                    _ => {}
+                }
+            }
+            for row in &node.probability {
+                if row.given.len() != node.parents.len() {
+                    return Err(D::Error::custom(format!(
+                        "probability row of `{}` does not match its parents",
+                        node.name
+                    )));
+                }
+                for value in row
+                    .given
+                    .iter()
+                    .chain(row.probabilities.iter().map(|(value, _)| value))
+                {
+                    check_value(value)?;
+                }
+            }
+            for value in node.states.iter().flatten() {
+                check_value(value)?;
+            }
+        }
+        // And everything `add_shared_cpt` and `tie_node` would panic on.
+        let mut shared_cpts = HashMap::new();
+        for cpt in &repr.shared_cpts {
+            if shared_cpts.insert(cpt.name.as_str(), cpt).is_some() {
+                return Err(D::Error::custom(format!(
+                    "shared CPT `{}` is defined twice",
+                    cpt.name
+                )));
+            }
+            for row in &cpt.probability {
+                if row.given.len() != cpt.probability[0].given.len() {
+                    return Err(D::Error::custom(format!(
+                        "shared CPT `{}` keys have different lengths",
+                        cpt.name
+                    )));
+                }
+                for value in row
+                    .given
+                    .iter()
+                    .chain(row.probabilities.iter().map(|(value, _)| value))
+                {
+                    check_value(value)?;
+                }
+            }
+        }
+        for node in &repr.nodes {
+            let name = match &node.shared_cpt {
+                Some(name) => name,
+                None => continue,
+            };
+            let cpt = match shared_cpts.get(name.as_str()) {
+                Some(cpt) => cpt,
+                None => {
+                    return Err(D::Error::custom(format!(
+                        "shared CPT `{}` of `{}` not found",
+                        name, node.name
+                    )))
+                }
+            };
+            let is_root = matches!(node.node_type, NodeTypeRepr::Root(_));
+            if cpt
+                .probability
+                .iter()
+                .any(|row| row.given.len() != node.parents.len())
+                || (is_root && cpt.probability.is_empty())
+            {
+                return Err(D::Error::custom(format!(
+                    "shared CPT `{}` does not match the parents of `{}`",
+                    name, node.name
+                )));
+            }
+        }
+
+        let mut network = BayesianNetwork::new(repr.value_space.clone());
+        for node in &repr.nodes {
+            let node_type = match &node.node_type {
+                NodeTypeRepr::Root(prob) => NodeType::Root(prob.iter().cloned().collect()),
+                NodeTypeRepr::Leaf => NodeType::Leaf,
+                NodeTypeRepr::Inner => NodeType::Inner,
+            };
+            network.add_node(&node.name, node_type);
+            if let Some(states) = &node.states {
+                network.set_node_states(&node.name, states.clone());
+            }
+            for (key, value) in &node.metadata {
+                network.set_node_metadata(&node.name, key, value);
+            }
+        }
+        let ties = repr
+            .nodes
+            .iter()
+            .filter_map(|node| Some((node.name.clone(), node.shared_cpt.clone()?)))
+            .collect::<Vec<(String, String)>>();
+        for node in repr.nodes {
+            if node.parents.is_empty() {
+                continue;
+            }
+            let prob = node
+                .probability
+                .into_iter()
+                .map(|row| (row.given, row.probabilities.into_iter().collect()))
+                .collect();
+            network
+                .try_add_dependency(
+                    node.parents.iter().map(|parent| parent.as_str()).collect(),
+                    &node.name,
+                    prob,
+                )
+                .map_err(D::Error::custom)?;
+        }
+        for cpt in repr.shared_cpts {
+            let prob = cpt
+                .probability
+                .into_iter()
+                .map(|row| (row.given, row.probabilities.into_iter().collect()))
+                .collect();
+            network.add_shared_cpt(&cpt.name, prob);
+        }
+        for (node, cpt) in ties {
+            network.tie_node(&node, &cpt);
+        }
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_same_joint, bonus, row, TOLERANCE};
+
+    #[test]
+    fn json_round_trip_keeps_states_metadata_and_ties() {
+        let mut network = bonus();
+        network.set_node_states("mood", vec![true, false]);
+        network.set_node_metadata("mood", "label", "Good mood");
+        network.add_shared_cpt(
+            "gift",
+            HashMap::from([(vec![true], row(0.3)), (vec![false], row(0.01))]),
+        );
+        network.tie_node("bonus", "gift");
+        let json = serde_json::to_string(&network).unwrap();
+        // Maps are written in a fixed order.
+        assert_eq!(serde_json::to_string(&network).unwrap(), json);
+
+        let read: BayesianNetwork<bool> = serde_json::from_str(&json).unwrap();
+        assert_same_joint(
+            &network,
+            &read.map_values(|value| value.to_string()),
+            TOLERANCE,
+        );
+        assert_eq!(read.get_node_states("mood"), vec![true, false]);
+        assert_eq!(read.get_node_metadata("mood", "label"), Some("Good mood"));
+        assert_eq!(read.shared_cpt_names(), vec!["gift"]);
+        let bonus = &read.nodes[read.get_node_index("bonus")];
+        assert_eq!(bonus.shared_cpt.as_deref(), Some("gift"));
+    }
+
+    #[test]
+    fn invalid_networks_are_rejected_instead_of_panicking() {
+        let json = serde_json::to_value(bonus()).unwrap();
+        let with_node = |index: usize, field: &str, value: serde_json::Value| {
+            let mut json = json.clone();
+            json["nodes"][index][field] = value;
+            serde_json::from_value::<BayesianNetwork<bool>>(json)
+        };
+        // `bonus` is a leaf, so it cannot be a parent of `feast`.
+        let error = with_node(4, "parents", serde_json::json!(["bonus"])).unwrap_err();
+        assert!(error.to_string().contains("leaf node `bonus`"));
+        // `mood` given `mood` closes a cycle.
+        let error = with_node(2, "parents", serde_json::json!(["mood", "horse_race"])).unwrap_err();
+        assert!(error.to_string().contains("Directed cycle"));
+        let error = with_node(3, "shared_cpt", serde_json::json!("missing")).unwrap_err();
+        assert!(error.to_string().contains("shared CPT `missing`"));
+    }
+}
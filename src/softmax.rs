@@ -0,0 +1,307 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, NodeType, Probability},
+    cpt::CptSpec,
+    dataset::Dataset,
+};
+
+/// A CPD with a softmax link: the child takes state `c` with probability proportional to
+/// `exp(bias[c] + sum_i weight[i][x_i][c])`, one weight per state of the child for each value
+/// of each parent, so the number of parameters grows linearly with the number of parents.
+#[derive(Clone, Debug)]
+pub struct SoftmaxCpd<T> {
+    states: Vec<T>,
+    bias: Vec<f64>,
+    weights: Vec<HashMap<T, Vec<f64>>>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> SoftmaxCpd<T> {
+    /// Create a CPD over the child states `states` with `parents` parents and every weight
+    /// zero, which gives a uniform distribution.
+    pub fn new(states: Vec<T>, parents: usize) -> SoftmaxCpd<T> {
+        SoftmaxCpd {
+            bias: vec![0.0; states.len()],
+            states,
+            weights: vec![HashMap::new(); parents],
+        }
+    }
+
+    /// Set the bias of a child state.
+    pub fn set_bias(&mut self, state: &T, bias: f64) {
+        let index = self.state_index(state);
+        self.bias[index] = bias;
+    }
+
+    /// Set the weight that parent `parent` taking `value` adds to child state `state`.
+    pub fn set_weight(&mut self, parent: usize, value: T, state: &T, weight: f64) {
+        let index = self.state_index(state);
+        let states = self.states.len();
+        self.parent_weights(parent)
+            .entry(value)
+            .or_insert_with(|| vec![0.0; states])[index] = weight;
+    }
+
+    /// Get the weight that parent `parent` taking `value` adds to child state `state`.
+    pub fn get_weight(&self, parent: usize, value: &T, state: &T) -> f64 {
+        let index = self.state_index(state);
+        match self.weights.get(parent) {
+            Some(weights) => weights.get(value).map_or(0.0, |weights| weights[index]),
+            None => panic!(
+                "Softmax CPD has no parent {} of {}",
+                parent,
+                self.weights.len()
+            ),
+        }
+    }
+
+    /// Get the distribution of the child given the parent values, in parent order.
+    pub fn distribution(&self, parent_values: &[T]) -> HashMap<T, Probability> {
+        self.states
+            .iter()
+            .cloned()
+            .zip(self.probabilities(parent_values))
+            .collect()
+    }
+
+    /// Fit the weights to `(parent values, child value)` examples by gradient ascent on the
+    /// log-likelihood, starting from the current weights. `regularization` is the strength of
+    /// an L2 penalty on every weight, which keeps them finite when some state is never seen.
+    pub fn fit(
+        &mut self,
+        examples: &[(Vec<T>, T)],
+        iterations: usize,
+        learning_rate: f64,
+        regularization: f64,
+    ) {
+        if examples.is_empty() {
+            return;
+        }
+        let states = self.states.len();
+        for (parent_values, _) in examples {
+            for (parent, value) in parent_values.iter().enumerate() {
+                self.parent_weights(parent)
+                    .entry(value.clone())
+                    .or_insert_with(|| vec![0.0; states]);
+            }
+        }
+        let scale = 1.0 / examples.len() as f64;
+        for _ in 0..iterations {
+            let mut bias_gradient = vec![0.0; states];
+            let mut weight_gradient = self
+                .weights
+                .iter()
+                .map(|weights| {
+                    weights
+                        .keys()
+                        .map(|value| (value.clone(), vec![0.0; states]))
+                        .collect::<HashMap<T, Vec<f64>>>()
+                })
+                .collect::<Vec<HashMap<T, Vec<f64>>>>();
+            for (parent_values, value) in examples {
+                let observed = self.state_index(value);
+                let probabilities = self.probabilities(parent_values);
+                for (state, prob) in probabilities.iter().enumerate() {
+                    let error = if state == observed { 1.0 } else { 0.0 } - prob;
+                    bias_gradient[state] += error;
+                    for (parent, value) in parent_values.iter().enumerate() {
+                        weight_gradient[parent].get_mut(value).unwrap()[state] += error;
+                    }
+                }
+            }
+            for (bias, gradient) in self.bias.iter_mut().zip(bias_gradient) {
+                *bias += learning_rate * (gradient * scale - regularization * *bias);
+            }
+            for (weights, gradients) in self.weights.iter_mut().zip(weight_gradient) {
+                for (value, gradient) in gradients {
+                    for (weight, gradient) in
+                        weights.get_mut(&value).unwrap().iter_mut().zip(gradient)
+                    {
+                        *weight += learning_rate * (gradient * scale - regularization * *weight);
+                    }
+                }
+            }
+        }
+    }
+
+    fn probabilities(&self, parent_values: &[T]) -> Vec<Probability> {
+        if parent_values.len() != self.weights.len() {
+            panic!(
+                "Softmax CPD has {} parents but got {} parent values",
+                self.weights.len(),
+                parent_values.len()
+            );
+        }
+        let mut scores = self.bias.clone();
+        for (weights, value) in self.weights.iter().zip(parent_values) {
+            if let Some(weights) = weights.get(value) {
+                for (score, weight) in scores.iter_mut().zip(weights) {
+                    *score += weight;
+                }
+            }
+        }
+        let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exp = scores
+            .iter()
+            .map(|score| (score - max).exp())
+            .collect::<Vec<f64>>();
+        let total = exp.iter().sum::<f64>();
+        exp.iter().map(|value| value / total).collect()
+    }
+
+    fn state_index(&self, state: &T) -> usize {
+        match self.states.iter().position(|s| s == state) {
+            Some(index) => index,
+            None => panic!("Softmax CPD state {:?} not found", state),
+        }
+    }
+
+    fn parent_weights(&mut self, parent: usize) -> &mut HashMap<T, Vec<f64>> {
+        let parents = self.weights.len();
+        match self.weights.get_mut(parent) {
+            Some(weights) => weights,
+            None => panic!("Softmax CPD has no parent {} of {}", parent, parents),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Fit `cpd` as the CPD of `child` to a dataset of complete records by gradient ascent,
+    /// as `SoftmaxCpd::fit`, and set the CPT of `child` to the result.
+    pub fn fit_softmax(
+        &mut self,
+        child: &str,
+        cpd: &mut SoftmaxCpd<T>,
+        dataset: &Dataset<T>,
+        iterations: usize,
+        learning_rate: f64,
+        regularization: f64,
+    ) {
+        let id = match self.node_map.get(child) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", child),
+        };
+        if let NodeType::Root(_) = self.nodes[id].node_type {
+            panic!("Cannot fit a softmax CPD to root node `{}`", child);
+        }
+        let column = |name: &str| {
+            dataset
+                .column_index(name)
+                .unwrap_or_else(|| panic!("Dataset column `{}` not found", name))
+        };
+        let child_column = column(child);
+        let parent_columns = self.nodes[id]
+            .parents
+            .iter()
+            .map(|parent| column(self.get_node_name(*parent)))
+            .collect::<Vec<usize>>();
+        let examples = dataset
+            .rows()
+            .iter()
+            .map(|row| {
+                (
+                    parent_columns
+                        .iter()
+                        .map(|column| row[*column].clone())
+                        .collect(),
+                    row[child_column].clone(),
+                )
+            })
+            .collect::<Vec<(Vec<T>, T)>>();
+        cpd.fit(&examples, iterations, learning_rate, regularization);
+
+        let parent_domains = self.nodes[id]
+            .parents
+            .iter()
+            .map(|parent| self.get_node_domain(*parent))
+            .collect::<Vec<Vec<T>>>();
+        let mut table = CptSpec::Softmax(cpd.clone()).table(&parent_domains);
+        for row in table.values_mut() {
+            for value in &self.value_space {
+                row.entry(value.clone()).or_insert(0.0);
+            }
+        }
+        self.nodes[id].probability = table;
+        self.nodes[id].compact_cpt = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, TOLERANCE};
+
+    #[test]
+    fn distribution_is_the_softmax_of_the_scores() {
+        let mut cpd = SoftmaxCpd::new(vec!["a", "b", "c"], 2);
+        assert_close(cpd.distribution(&["x", "y"])["a"], 1.0 / 3.0, TOLERANCE);
+        cpd.set_bias(&"b", 1.0);
+        cpd.set_weight(0, "x", &"b", 2.0);
+        cpd.set_weight(1, "y", &"c", -1.0);
+        assert_eq!(cpd.get_weight(0, &"x", &"b"), 2.0);
+        assert_eq!(cpd.get_weight(0, &"z", &"b"), 0.0);
+        let total = 1.0 + 3.0_f64.exp() + (-1.0_f64).exp();
+        let distribution = cpd.distribution(&["x", "y"]);
+        assert_close(distribution["a"], 1.0 / total, TOLERANCE);
+        assert_close(distribution["b"], 3.0_f64.exp() / total, TOLERANCE);
+        assert_close(distribution["c"], (-1.0_f64).exp() / total, TOLERANCE);
+        // A parent value without weights adds nothing.
+        let total = 2.0 + 1.0_f64.exp();
+        assert_close(
+            cpd.distribution(&["z", "w"])["b"],
+            1.0_f64.exp() / total,
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn fit_recovers_the_frequencies_of_a_single_parent() {
+        // With one parent, the softmax can match each row exactly, so the unregularized fit
+        // converges to the observed frequencies: 3 in 4 for each parent value.
+        let mut examples = Vec::new();
+        for (parent, child, count) in [
+            (true, true, 3),
+            (true, false, 1),
+            (false, false, 3),
+            (false, true, 1),
+        ] {
+            for _ in 0..count {
+                examples.push((vec![parent], child));
+            }
+        }
+        let mut cpd = SoftmaxCpd::new(vec![true, false], 1);
+        cpd.fit(&examples, 5000, 1.0, 0.0);
+        assert_close(cpd.distribution(&[true])[&true], 0.75, 1e-4);
+        assert_close(cpd.distribution(&[false])[&false], 0.75, 1e-4);
+        // A penalty pulls the weights towards zero and the rows towards uniform.
+        let mut penalized = SoftmaxCpd::new(vec![true, false], 1);
+        penalized.fit(&examples, 5000, 1.0, 0.5);
+        let prob = penalized.distribution(&[true])[&true];
+        assert!(prob > 0.5 && prob < 0.75);
+    }
+
+    #[test]
+    fn fit_softmax_sets_the_cpt_of_the_child() {
+        let mut network = BayesianNetwork::from_edges(
+            vec![true, false],
+            vec!["a", "b"],
+            &[("a".to_string(), "b".to_string())],
+        );
+        let mut dataset = Dataset::new(vec!["a", "b"]);
+        for (a, b, count) in [
+            (true, true, 9),
+            (true, false, 1),
+            (false, true, 2),
+            (false, false, 8),
+        ] {
+            for _ in 0..count {
+                dataset.add_row(vec![a, b]);
+            }
+        }
+        let mut cpd = SoftmaxCpd::new(vec![true, false], 1);
+        network.fit_softmax("b", &mut cpd, &dataset, 5000, 1.0, 0.0);
+        let b = network.get_node_index("b");
+        assert_close(network.nodes[b].probability[&vec![true]][&true], 0.9, 1e-4);
+        assert_close(network.nodes[b].probability[&vec![false]][&true], 0.2, 1e-4);
+    }
+}
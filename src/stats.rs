@@ -0,0 +1,137 @@
+use std::{
+    fmt::{self, Debug},
+    hash::Hash,
+};
+
+use crate::bayesian_network::{BayesianNetwork, Name};
+
+/// A summary of the size and shape of a network, as returned by `stats`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    /// The largest number of parents of a node.
+    pub max_in_degree: usize,
+    /// The number of entries of the largest CPT: one for each state of the node in each
+    /// configuration of its parents.
+    pub largest_cpt: usize,
+    /// The number of free parameters of all the CPTs, one less than the number of states of
+    /// the node in each configuration of its parents.
+    pub parameter_count: usize,
+    /// The number of states of each node, in node order.
+    pub domain_sizes: Vec<(Name, usize)>,
+    /// The number of edges of the longest directed path.
+    pub depth: usize,
+}
+
+impl fmt::Display for NetworkStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sizes = self.domain_sizes.iter().map(|(_, size)| *size);
+        write!(
+            f,
+            "{} nodes, {} edges, max in-degree {}, largest CPT {}, {} parameters, \
+             domain sizes {}..{}, depth {}",
+            self.node_count,
+            self.edge_count,
+            self.max_in_degree,
+            self.largest_cpt,
+            self.parameter_count,
+            sizes.clone().min().unwrap_or(0),
+            sizes.max().unwrap_or(0),
+            self.depth
+        )
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Summarize the network: its node and edge counts, the largest in-degree and CPT, the
+    /// number of free parameters, the size of each domain and the length of the longest
+    /// directed path.
+    pub fn stats(&self) -> NetworkStats {
+        let sizes = (0..self.nodes.len())
+            .map(|id| self.get_node_domain(id).len())
+            .collect::<Vec<usize>>();
+        let mut largest_cpt = 0;
+        let mut parameter_count = 0;
+        for node in &self.nodes {
+            let rows = node
+                .parents
+                .iter()
+                .map(|parent| sizes[*parent])
+                .product::<usize>();
+            largest_cpt = largest_cpt.max(rows * sizes[node.id]);
+            parameter_count += rows * sizes[node.id].saturating_sub(1);
+        }
+        let mut depths = vec![0; self.nodes.len()];
+        for id in self.topological_order() {
+            depths[id] = self.nodes[id]
+                .parents
+                .iter()
+                .map(|parent| depths[*parent] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        NetworkStats {
+            node_count: self.nodes.len(),
+            edge_count: self.nodes.iter().map(|node| node.parents.len()).sum(),
+            max_in_degree: self
+                .nodes
+                .iter()
+                .map(|node| node.parents.len())
+                .max()
+                .unwrap_or(0),
+            largest_cpt,
+            parameter_count,
+            domain_sizes: self
+                .nodes
+                .iter()
+                .map(|node| (self.get_node_name(node.id).to_string(), sizes[node.id]))
+                .collect(),
+            depth: depths.into_iter().max().unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{learning::Edge, testing::bonus};
+
+    #[test]
+    fn stats_count_the_bonus_network() {
+        let stats = bonus().stats();
+        assert_eq!(stats.node_count, 5);
+        assert_eq!(stats.edge_count, 4);
+        assert_eq!(stats.max_in_degree, 2);
+        assert_eq!(stats.largest_cpt, 8);
+        // One free parameter per row: two priors, four mood rows and two rows for each leaf.
+        assert_eq!(stats.parameter_count, 10);
+        assert_eq!(stats.depth, 2);
+        assert_eq!(
+            stats.to_string(),
+            "5 nodes, 4 edges, max in-degree 2, largest CPT 8, 10 parameters, \
+             domain sizes 2..2, depth 2"
+        );
+    }
+
+    #[test]
+    fn stats_use_the_states_of_each_node() {
+        let edges: Vec<Edge> = vec![
+            ("a".to_string(), "b".to_string()),
+            ("b".to_string(), "c".to_string()),
+        ];
+        let mut network = BayesianNetwork::from_edges(vec![1, 2, 3], vec!["a", "b", "c"], &edges);
+        network.set_node_states("a", vec![1, 2]);
+        let stats = network.stats();
+        assert_eq!(
+            stats.domain_sizes,
+            [
+                ("a".to_string(), 2),
+                ("b".to_string(), 3),
+                ("c".to_string(), 3)
+            ]
+        );
+        assert_eq!(stats.largest_cpt, 9);
+        assert_eq!(stats.parameter_count, 1 + 2 * 2 + 3 * 2);
+    }
+}
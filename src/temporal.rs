@@ -0,0 +1,546 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, Probability},
+    dynamic::DynamicBayesianNetwork,
+    learning::configurations,
+    posterior::Posterior,
+};
+
+/// Observations of one time slice, by node name in the slice.
+pub type SliceEvidence<'a, T> = HashMap<&'a str, T>;
+
+/// Exact filtering of a stream of observations, created by `online_filter`. Only the belief
+/// over the interface nodes is kept between slices, so memory stays constant however long
+/// the stream runs.
+pub struct OnlineFilter<'a, T: Clone + PartialEq + Eq + Hash + Debug> {
+    network: &'a DynamicBayesianNetwork<T>,
+    time: usize,
+    /// The belief over the interface of the slice before the current one.
+    previous: Option<Vec<Probability>>,
+    /// The belief over the interface of the current slice.
+    belief: Vec<Probability>,
+    /// The observations of the current slice.
+    evidence: HashMap<Name, T>,
+    log_likelihood: f64,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> OnlineFilter<'_, T> {
+    /// Get the number of slices observed so far.
+    pub fn time(&self) -> usize {
+        self.time
+    }
+
+    /// Advance to the next slice with its observations.
+    pub fn step(&mut self, evidence: &SliceEvidence<T>) {
+        let previous = (self.time > 0).then(|| std::mem::take(&mut self.belief));
+        let (belief, probability) =
+            self.network
+                .propagate(previous.as_deref(), self.time, evidence);
+        self.log_likelihood += probability.ln();
+        self.previous = previous;
+        self.belief = belief;
+        self.evidence = evidence
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        self.time += 1;
+    }
+
+    /// Infer the distribution of every node of the current slice given all the observations
+    /// so far.
+    pub fn posterior(&self) -> Posterior<T> {
+        if self.time == 0 {
+            panic!("The filter has not been stepped yet");
+        }
+        let evidence = self
+            .evidence
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+        self.network
+            .slice_posterior(self.time - 1, self.previous.as_deref(), None, &evidence)
+    }
+
+    /// Get the joint distribution of the interface nodes of the current slice, as pairs of
+    /// (node, value) assignments and probabilities.
+    pub fn interface_belief(&self) -> Vec<(Vec<(Name, T)>, Probability)> {
+        if self.time == 0 {
+            panic!("The filter has not been stepped yet");
+        }
+        let nodes = self.network.interface_nodes();
+        self.network
+            .interface_configurations(self.time - 1)
+            .into_iter()
+            .zip(self.belief.iter().copied())
+            .map(|(configuration, prob)| {
+                (
+                    nodes
+                        .iter()
+                        .map(|name| name.to_string())
+                        .zip(configuration)
+                        .collect(),
+                    prob,
+                )
+            })
+            .collect()
+    }
+
+    /// Get the log-probability of all the observations so far.
+    pub fn log_likelihood(&self) -> f64 {
+        self.log_likelihood
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> DynamicBayesianNetwork<T> {
+    /// Infer the distribution of every node of the last observed slice given the
+    /// observations of each slice so far.
+    pub fn filter(&self, observations: &[SliceEvidence<T>]) -> Posterior<T> {
+        let last = Self::last_slice(observations);
+        let forward = self.forward(observations);
+        let previous = last.checked_sub(1).map(|slice| forward[slice].as_slice());
+        self.slice_posterior(last, previous, None, &observations[last])
+    }
+
+    /// Infer the distribution of every node of slice `t` given the observations of every
+    /// slice, past and future.
+    pub fn smooth(&self, observations: &[SliceEvidence<T>], t: usize) -> Posterior<T> {
+        let last = Self::last_slice(observations);
+        if t > last {
+            panic!("Slice {} is after the last observed slice {}", t, last);
+        }
+        let forward = self.forward(observations);
+        // The probability of the observations after slice t given each interface
+        // configuration of slice t, up to a constant.
+        let mut backward = vec![1.0; self.interface_configurations(last).len()];
+        for slice in (t + 1..=last).rev() {
+            let kernel = self.kernel(slice, &observations[slice]);
+            backward = normalized(
+                kernel
+                    .iter()
+                    .map(|row| row.iter().zip(&backward).map(|(k, b)| k * b).sum())
+                    .collect(),
+            );
+        }
+        let previous = t.checked_sub(1).map(|slice| forward[slice].as_slice());
+        self.slice_posterior(t, previous, Some(&backward), &observations[t])
+    }
+
+    /// Infer the distribution of every node `k` slices after the last observed one, given
+    /// the observations so far.
+    pub fn predict(&self, observations: &[SliceEvidence<T>], k: usize) -> Posterior<T> {
+        if k == 0 {
+            return self.filter(observations);
+        }
+        let last = Self::last_slice(observations);
+        let mut belief = self.forward(observations).pop().unwrap();
+        let no_evidence = HashMap::new();
+        for slice in last + 1..last + k {
+            (belief, _) = self.propagate(Some(&belief), slice, &no_evidence);
+        }
+        self.slice_posterior(last + k, Some(&belief), None, &no_evidence)
+    }
+
+    /// Start filtering a stream of observations one slice at a time.
+    pub fn online_filter(&self) -> OnlineFilter<'_, T> {
+        OnlineFilter {
+            network: self,
+            time: 0,
+            previous: None,
+            belief: Vec::new(),
+            evidence: HashMap::new(),
+            log_likelihood: 0.0,
+        }
+    }
+
+    fn last_slice(observations: &[SliceEvidence<T>]) -> usize {
+        match observations.len() {
+            0 => panic!("At least one slice of observations is needed"),
+            len => len - 1,
+        }
+    }
+
+    /// The network of slice `slice`.
+    fn slice(&self, slice: usize) -> &BayesianNetwork<T> {
+        if slice == 0 {
+            &self.prior
+        } else {
+            &self.transition
+        }
+    }
+
+    /// The nodes of a slice that the next slice depends on.
+    fn interface_nodes(&self) -> Vec<&str> {
+        let mut nodes = Vec::new();
+        for (_, current) in &self.interface {
+            if !nodes.contains(&current.as_str()) {
+                nodes.push(current.as_str());
+            }
+        }
+        nodes
+    }
+
+    /// Every joint assignment to the interface nodes of a slice.
+    fn interface_configurations(&self, slice: usize) -> Vec<Vec<T>> {
+        let network = self.slice(slice);
+        let domains = self
+            .interface_nodes()
+            .iter()
+            .map(|name| network.get_node_domain(network.get_node_index(name)))
+            .collect::<Vec<Vec<T>>>();
+        configurations(&domains)
+    }
+
+    /// The evidence of slice `slice` with the interface nodes of the previous slice set to
+    /// `previous` and (if given) those of this slice set to `current`, or `None` if they
+    /// contradict the evidence.
+    fn record<'a>(
+        &'a self,
+        slice: usize,
+        previous: &[T],
+        current: Option<&[T]>,
+        evidence: &SliceEvidence<'a, T>,
+    ) -> Option<HashMap<&'a str, T>> {
+        let nodes = self.slice_nodes(slice);
+        for name in evidence.keys() {
+            if !nodes.contains(name) {
+                panic!("Node `{}` not found in slice {}", name, slice);
+            }
+        }
+        let mut record = evidence.clone();
+        if let Some(current) = current {
+            for (name, value) in self.interface_nodes().into_iter().zip(current) {
+                if record.get(name).is_some_and(|other| other != value) {
+                    return None;
+                }
+                record.insert(name, value.clone());
+            }
+        }
+        if slice > 0 {
+            let interface = self.interface_nodes();
+            for (from, to) in &self.interface {
+                let index = interface.iter().position(|name| name == to).unwrap();
+                record.insert(from.as_str(), previous[index].clone());
+            }
+        }
+        Some(record)
+    }
+
+    /// The probability of the previous-slice nodes of `record` under the slice network, by
+    /// which the probability of the whole record is divided to condition on them.
+    fn previous_probability(&self, slice: usize, record: &HashMap<&str, T>) -> Probability {
+        if slice == 0 {
+            return 1.0;
+        }
+        let previous = self
+            .interface
+            .iter()
+            .map(|(from, _)| (from.as_str(), record[from.as_str()].clone()))
+            .collect::<HashMap<&str, T>>();
+        self.transition.likelihood(&previous)
+    }
+
+    /// `P(interface of slice = j, evidence | interface of previous slice = i)` for every pair
+    /// of configurations, one row per `i`. Slice 0 has a single row.
+    fn kernel(&self, slice: usize, evidence: &SliceEvidence<T>) -> Vec<Vec<Probability>> {
+        let previous = if slice == 0 {
+            vec![Vec::new()]
+        } else {
+            self.interface_configurations(slice - 1)
+        };
+        let current = self.interface_configurations(slice);
+        let network = self.slice(slice);
+        previous
+            .iter()
+            .map(|previous| {
+                current
+                    .iter()
+                    .map(|current| {
+                        let Some(record) = self.record(slice, previous, Some(current), evidence)
+                        else {
+                            return 0.0;
+                        };
+                        let denominator = self.previous_probability(slice, &record);
+                        if denominator <= 0.0 {
+                            0.0
+                        } else {
+                            network.likelihood(&record) / denominator
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The belief over the interface configurations of slice `slice` given the belief over
+    /// those of the previous slice (none for slice 0) and the evidence of this slice, with
+    /// the probability of the evidence given the previous belief.
+    fn propagate(
+        &self,
+        belief: Option<&[Probability]>,
+        slice: usize,
+        evidence: &SliceEvidence<T>,
+    ) -> (Vec<Probability>, Probability) {
+        let kernel = self.kernel(slice, evidence);
+        let belief = belief.unwrap_or(&[1.0]);
+        let mut next = vec![0.0; kernel[0].len()];
+        for (weight, row) in belief.iter().zip(&kernel) {
+            for (next, k) in next.iter_mut().zip(row) {
+                *next += weight * k;
+            }
+        }
+        let total = next.iter().sum::<Probability>();
+        (normalized(next), total)
+    }
+
+    /// The belief over the interface configurations of every observed slice given the
+    /// observations up to it. Only one belief is needed to go on, which is what keeps the
+    /// cost of each slice independent of the length of the history.
+    fn forward(&self, observations: &[SliceEvidence<T>]) -> Vec<Vec<Probability>> {
+        let mut beliefs: Vec<Vec<Probability>> = Vec::new();
+        for (slice, evidence) in observations.iter().enumerate() {
+            let (belief, _) = self.propagate(beliefs.last().map(Vec::as_slice), slice, evidence);
+            beliefs.push(belief);
+        }
+        beliefs
+    }
+
+    /// The distribution of every node of slice `slice` given the belief over the previous
+    /// interface, the backward message over this slice's interface if any, and the evidence.
+    fn slice_posterior(
+        &self,
+        slice: usize,
+        previous: Option<&[Probability]>,
+        backward: Option<&[Probability]>,
+        evidence: &SliceEvidence<T>,
+    ) -> Posterior<T> {
+        let network = self.slice(slice);
+        let previous = match previous {
+            Some(belief) => self
+                .interface_configurations(slice - 1)
+                .into_iter()
+                .zip(belief.iter().copied())
+                .collect::<Vec<(Vec<T>, Probability)>>(),
+            None => vec![(Vec::new(), 1.0)],
+        };
+        let current = match backward {
+            Some(message) => self
+                .interface_configurations(slice)
+                .into_iter()
+                .map(Some)
+                .zip(message.iter().copied())
+                .collect::<Vec<(Option<Vec<T>>, Probability)>>(),
+            None => vec![(None, 1.0)],
+        };
+
+        let nodes = self.slice_nodes(slice);
+        let domains = nodes
+            .iter()
+            .map(|name| network.get_node_domain(network.get_node_index(name)))
+            .collect::<Vec<Vec<T>>>();
+        let mut sums = domains
+            .iter()
+            .map(|domain| vec![0.0; domain.len()])
+            .collect::<Vec<Vec<Probability>>>();
+        let mut total = 0.0;
+        for (previous, prior_weight) in &previous {
+            for (current, backward_weight) in &current {
+                if *prior_weight <= 0.0 || *backward_weight <= 0.0 {
+                    continue;
+                }
+                let Some(record) = self.record(slice, previous, current.as_deref(), evidence)
+                else {
+                    continue;
+                };
+                let denominator = self.previous_probability(slice, &record);
+                if denominator <= 0.0 {
+                    continue;
+                }
+                let weight =
+                    prior_weight * backward_weight * network.likelihood(&record) / denominator;
+                if weight <= 0.0 {
+                    continue;
+                }
+                total += weight;
+                let inferred_probabilities = network.infer(&record);
+                for ((name, domain), sums) in nodes.iter().zip(&domains).zip(&mut sums) {
+                    let probabilities = &inferred_probabilities[network.get_node_index(name)];
+                    for (value, sum) in domain.iter().zip(sums) {
+                        *sum += weight * probabilities.get(value).copied().unwrap_or(0.0);
+                    }
+                }
+            }
+        }
+        if total <= 0.0 {
+            panic!("Evidence has zero probability");
+        }
+        Posterior {
+            marginals: nodes
+                .iter()
+                .zip(domains)
+                .zip(sums)
+                .map(|((name, domain), sums)| {
+                    (
+                        name.to_string(),
+                        domain
+                            .into_iter()
+                            .zip(sums.into_iter().map(|sum| sum / total))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Scale a nonnegative vector to sum to one.
+fn normalized(vector: Vec<Probability>) -> Vec<Probability> {
+    let total = vector.iter().sum::<Probability>();
+    if total <= 0.0 {
+        panic!("Evidence has zero probability");
+    }
+    vector.into_iter().map(|value| value / total).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        assert_close, brute_force_probability, evidence_probability, umbrella, TOLERANCE,
+    };
+
+    const UMBRELLAS: [bool; 3] = [true, true, false];
+
+    fn observations() -> Vec<SliceEvidence<'static, bool>> {
+        UMBRELLAS
+            .iter()
+            .map(|seen| HashMap::from([("Umbrella", *seen)]))
+            .collect()
+    }
+
+    /// The posterior of `Rain[t]` in the network unrolled over `slices` slices, given the
+    /// umbrella observations of those slices.
+    fn unrolled_rain(slices: usize, t: usize) -> Probability {
+        let unrolled = umbrella().unroll(slices);
+        let names = (0..slices.min(UMBRELLAS.len()))
+            .map(|i| format!("Umbrella[{}]", i))
+            .collect::<Vec<String>>();
+        let evidence = names
+            .iter()
+            .map(String::as_str)
+            .zip(UMBRELLAS)
+            .collect::<HashMap<&str, bool>>();
+        let probabilities = unrolled.infer(&evidence);
+        unrolled.get_inferred_probability(&probabilities, &format!("Rain[{}]", t), true)
+    }
+
+    #[test]
+    fn filter_matches_inference_on_the_unrolled_network() {
+        let network = umbrella();
+        for t in 0..UMBRELLAS.len() {
+            let posterior = network.filter(&observations()[..=t]);
+            assert_close(
+                posterior.probability("Rain", &true).unwrap(),
+                unrolled_rain(t + 1, t),
+                TOLERANCE,
+            );
+        }
+        let predicted = network.predict(&observations(), 2);
+        assert_close(
+            predicted.probability("Rain", &true).unwrap(),
+            unrolled_rain(UMBRELLAS.len() + 2, UMBRELLAS.len() + 1),
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn smooth_matches_inference_on_the_unrolled_network() {
+        let network = umbrella();
+        for t in 0..UMBRELLAS.len() {
+            let posterior = network.smooth(&observations(), t);
+            assert_close(
+                posterior.probability("Rain", &true).unwrap(),
+                unrolled_rain(UMBRELLAS.len(), t),
+                TOLERANCE,
+            );
+        }
+    }
+
+    #[test]
+    fn online_filter_matches_the_unrolled_network() {
+        let network = umbrella();
+        let mut filter = network.online_filter();
+        for (t, evidence) in observations().iter().enumerate() {
+            filter.step(evidence);
+            assert_close(
+                filter.posterior().probability("Rain", &true).unwrap(),
+                unrolled_rain(t + 1, t),
+                TOLERANCE,
+            );
+        }
+        let unrolled = network.unroll(UMBRELLAS.len());
+        let names = (0..UMBRELLAS.len())
+            .map(|i| format!("Umbrella[{}]", i))
+            .collect::<Vec<String>>();
+        let evidence = names
+            .iter()
+            .map(String::as_str)
+            .zip(UMBRELLAS)
+            .collect::<HashMap<&str, bool>>();
+        assert_close(
+            filter.log_likelihood(),
+            evidence_probability(&unrolled, &evidence).ln(),
+            TOLERANCE,
+        );
+    }
+
+    #[test]
+    fn online_filter_keeps_the_interface_belief_over_a_long_stream() {
+        // Slices without observations, in the middle and at the end, only propagate.
+        let stream = [Some(true), None, Some(false), Some(true), Some(true), None];
+        let network = umbrella();
+        let unrolled = network.unroll(stream.len());
+        let mut filter = network.online_filter();
+        let mut names = Vec::new();
+        for (t, seen) in stream.iter().enumerate() {
+            let evidence = match seen {
+                Some(seen) => HashMap::from([("Umbrella", *seen)]),
+                None => HashMap::new(),
+            };
+            filter.step(&evidence);
+            if let Some(seen) = seen {
+                names.push((format!("Umbrella[{}]", t), *seen));
+            }
+            let evidence = names
+                .iter()
+                .map(|(name, seen)| (name.as_str(), *seen))
+                .collect::<HashMap<&str, bool>>();
+            let expected =
+                brute_force_probability(&unrolled, &format!("Rain[{}]", t), &true, &evidence);
+            assert_eq!(filter.time(), t + 1);
+            let belief = filter.interface_belief();
+            assert_eq!(belief.len(), 2);
+            for (assignment, prob) in belief {
+                assert_eq!(assignment.len(), 1);
+                assert_eq!(assignment[0].0, "Rain");
+                let expected = if assignment[0].1 {
+                    expected
+                } else {
+                    1.0 - expected
+                };
+                assert_close(prob, expected, TOLERANCE);
+            }
+            assert_close(
+                filter.log_likelihood(),
+                evidence_probability(&unrolled, &evidence).ln(),
+                TOLERANCE,
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The filter has not been stepped yet")]
+    fn online_filter_needs_a_slice() {
+        umbrella().online_filter().posterior();
+    }
+}
@@ -0,0 +1,345 @@
+use std::{collections::HashMap, fmt::Debug, fmt::Display, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability},
+    dynamic::DynamicBayesianNetwork,
+    influence_diagram::InfluenceDiagram,
+    learning::{configurations, Edge},
+};
+
+pub(crate) const TOLERANCE: f64 = 1e-9;
+
+/// Assert that two numbers agree within `tolerance`.
+pub(crate) fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+/// The bonus example: performance and horse racing cause a good mood, which causes a bonus
+/// and a feast.
+pub(crate) fn bonus() -> BayesianNetwork<bool> {
+    let mut network = BayesianNetwork::new(vec![true, false]);
+    network.add_node(
+        "performance",
+        NodeType::Root(HashMap::from([(true, 0.01), (false, 0.99)])),
+    );
+    network.add_node(
+        "horse_race",
+        NodeType::Root(HashMap::from([(true, 0.1), (false, 0.9)])),
+    );
+    network.add_node("mood", NodeType::Inner);
+    network.add_node("bonus", NodeType::Leaf);
+    network.add_node("feast", NodeType::Leaf);
+    network.add_dependency(
+        vec!["performance", "horse_race"],
+        "mood",
+        HashMap::from([
+            (vec![true, true], row(0.99)),
+            (vec![false, true], row(0.6)),
+            (vec![true, false], row(0.9)),
+            (vec![false, false], row(0.01)),
+        ]),
+    );
+    network.add_dependency(
+        vec!["mood"],
+        "bonus",
+        HashMap::from([(vec![true], row(0.3)), (vec![false], row(0.01))]),
+    );
+    network.add_dependency(
+        vec!["mood"],
+        "feast",
+        HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.01))]),
+    );
+    network
+}
+
+/// The sprinkler network: a cloudy sky makes the sprinkler less likely and rain more likely,
+/// and either wets the grass. It is not a polytree, so `infer` and `posterior`, which pass
+/// messages on polytrees only, panic on it; use the exact methods instead.
+pub(crate) fn sprinkler() -> BayesianNetwork<bool> {
+    let mut network = BayesianNetwork::new(vec![true, false]);
+    network.add_node("cloudy", NodeType::Root(row(0.5)));
+    network.add_node("sprinkler", NodeType::Inner);
+    network.add_node("rain", NodeType::Inner);
+    network.add_node("wet_grass", NodeType::Leaf);
+    network.add_dependency(
+        vec!["cloudy"],
+        "sprinkler",
+        HashMap::from([(vec![true], row(0.1)), (vec![false], row(0.5))]),
+    );
+    network.add_dependency(
+        vec!["cloudy"],
+        "rain",
+        HashMap::from([(vec![true], row(0.8)), (vec![false], row(0.2))]),
+    );
+    network.add_dependency(
+        vec!["sprinkler", "rain"],
+        "wet_grass",
+        HashMap::from([
+            (vec![true, true], row(0.99)),
+            (vec![true, false], row(0.9)),
+            (vec![false, true], row(0.9)),
+            (vec![false, false], row(0.0)),
+        ]),
+    );
+    network
+}
+
+/// A boolean row with `true` at probability `p`.
+pub(crate) fn row(p: Probability) -> HashMap<bool, Probability> {
+    HashMap::from([(true, p), (false, 1.0 - p)])
+}
+
+/// A boolean network with only the given structure, for the purely graphical queries.
+pub(crate) fn graph(names: &[&str], edges: &[(&str, &str)]) -> BayesianNetwork<bool> {
+    let edges = edges
+        .iter()
+        .map(|(parent, child)| (parent.to_string(), child.to_string()))
+        .collect::<Vec<Edge>>();
+    BayesianNetwork::from_edges(vec![true, false], names.to_vec(), &edges)
+}
+
+/// Every full assignment of the network with its probability, by enumeration.
+pub(crate) fn joint<T: Clone + PartialEq + Eq + Hash + Debug>(
+    network: &BayesianNetwork<T>,
+) -> Vec<(HashMap<NodeId, T>, Probability)> {
+    let domains = network
+        .nodes
+        .iter()
+        .map(|node| network.get_node_domain(node.id))
+        .collect::<Vec<Vec<T>>>();
+    configurations(&domains)
+        .into_iter()
+        .map(|values| {
+            let assignment = values
+                .into_iter()
+                .enumerate()
+                .collect::<HashMap<NodeId, T>>();
+            let prob = network
+                .nodes
+                .iter()
+                .map(|node| network.local_probability(node, &assignment[&node.id], &assignment))
+                .product();
+            (assignment, prob)
+        })
+        .collect()
+}
+
+/// Whether a full assignment agrees with `evidence`.
+pub(crate) fn consistent<T: Clone + PartialEq + Eq + Hash + Debug>(
+    network: &BayesianNetwork<T>,
+    assignment: &HashMap<NodeId, T>,
+    evidence: &HashMap<&str, T>,
+) -> bool {
+    evidence
+        .iter()
+        .all(|(name, value)| assignment[&network.get_node_index(name)] == *value)
+}
+
+/// The posterior of `value` for `name` given `evidence`, by enumeration.
+pub(crate) fn brute_force_probability<T: Clone + PartialEq + Eq + Hash + Debug>(
+    network: &BayesianNetwork<T>,
+    name: &str,
+    value: &T,
+    evidence: &HashMap<&str, T>,
+) -> Probability {
+    let id = network.get_node_index(name);
+    let mut joint_probability = 0.0;
+    let mut total = 0.0;
+    for (assignment, prob) in joint(network) {
+        if consistent(network, &assignment, evidence) {
+            total += prob;
+            if assignment[&id] == *value {
+                joint_probability += prob;
+            }
+        }
+    }
+    joint_probability / total
+}
+
+/// The probability of `evidence`, by enumeration.
+pub(crate) fn evidence_probability<T: Clone + PartialEq + Eq + Hash + Debug>(
+    network: &BayesianNetwork<T>,
+    evidence: &HashMap<&str, T>,
+) -> Probability {
+    joint(network)
+        .into_iter()
+        .filter(|(assignment, _)| consistent(network, assignment, evidence))
+        .map(|(_, prob)| prob)
+        .sum()
+}
+
+/// The most probable full assignment consistent with `evidence` and its joint probability,
+/// by enumeration.
+pub(crate) fn brute_force_mpe<T: Clone + PartialEq + Eq + Hash + Debug>(
+    network: &BayesianNetwork<T>,
+    evidence: &HashMap<&str, T>,
+) -> (HashMap<Name, T>, Probability) {
+    let (assignment, prob) = joint(network)
+        .into_iter()
+        .filter(|(assignment, _)| consistent(network, assignment, evidence))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap();
+    let names = assignment
+        .into_iter()
+        .map(|(id, value)| (network.get_node_name(id).to_string(), value))
+        .collect();
+    (names, prob)
+}
+
+/// Assert that `actual`, read back from a file, gives every full assignment of `expected`
+/// the same probability, matching nodes by name and values by their text.
+pub(crate) fn assert_same_joint<T: Clone + PartialEq + Eq + Hash + Debug + Display>(
+    expected: &BayesianNetwork<T>,
+    actual: &BayesianNetwork<String>,
+    tolerance: f64,
+) {
+    assert_eq!(expected.nodes.len(), actual.nodes.len());
+    let actual_joint = joint(actual);
+    for (assignment, prob) in joint(expected) {
+        let evidence = assignment
+            .iter()
+            .map(|(id, value)| (expected.get_node_name(*id), value.to_string()))
+            .collect::<HashMap<&str, String>>();
+        let matching = actual_joint
+            .iter()
+            .filter(|(actual_assignment, _)| consistent(actual, actual_assignment, &evidence))
+            .map(|(_, prob)| prob)
+            .sum();
+        assert_close(matching, prob, tolerance);
+    }
+}
+
+/// A dog that may be out and may have a bowel problem, with node and state names containing
+/// spaces and punctuation, for the text formats.
+pub(crate) fn odd_names() -> BayesianNetwork<String> {
+    let state = |name: &str| name.to_string();
+    let row = |first: &str, second: &str, p: Probability| {
+        HashMap::from([(state(first), p), (state(second), 1.0 - p)])
+    };
+    let mut network = BayesianNetwork::new(
+        ["out, really", "in (home)", "yes", "no = 0"]
+            .iter()
+            .map(|name| state(name))
+            .collect(),
+    );
+    network.add_node(
+        "dog out",
+        NodeType::Root(row("out, really", "in (home)", 0.3)),
+    );
+    network.set_node_states("dog out", vec![state("out, really"), state("in (home)")]);
+    network.add_node("bowel-problem?", NodeType::Leaf);
+    network.set_node_states("bowel-problem?", vec![state("yes"), state("no = 0")]);
+    network.add_dependency(
+        vec!["dog out"],
+        "bowel-problem?",
+        HashMap::from([
+            (vec![state("out, really")], row("yes", "no = 0", 0.6)),
+            (vec![state("in (home)")], row("yes", "no = 0", 0.1)),
+        ]),
+    );
+    network
+}
+
+/// The umbrella world: rain persists from day to day, and an umbrella is seen more often on
+/// rainy days.
+pub(crate) fn umbrella() -> DynamicBayesianNetwork<bool> {
+    let mut prior = BayesianNetwork::new(vec![true, false]);
+    prior.add_node("Rain", NodeType::Root(row(0.5)));
+    prior.add_node("Umbrella", NodeType::Leaf);
+    prior.add_dependency(
+        vec!["Rain"],
+        "Umbrella",
+        HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.2))]),
+    );
+    let mut transition = BayesianNetwork::new(vec![true, false]);
+    transition.add_node("Rain_prev", NodeType::Root(row(0.5)));
+    transition.add_node("Rain", NodeType::Inner);
+    transition.add_node("Umbrella", NodeType::Leaf);
+    transition.add_dependency(
+        vec!["Rain_prev"],
+        "Rain",
+        HashMap::from([(vec![true], row(0.7)), (vec![false], row(0.3))]),
+    );
+    transition.add_dependency(
+        vec!["Rain"],
+        "Umbrella",
+        HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.2))]),
+    );
+    DynamicBayesianNetwork::new(prior, transition, &[("Rain_prev", "Rain")])
+}
+
+/// A confounded chain: `z` causes both `x` and `y`, and `x` causes `y`.
+pub(crate) fn confounded() -> BayesianNetwork<bool> {
+    let mut network = BayesianNetwork::new(vec![true, false]);
+    network.add_node("z", NodeType::Root(row(0.4)));
+    network.add_node("x", NodeType::Inner);
+    network.add_node("y", NodeType::Leaf);
+    network.add_dependency(
+        vec!["z"],
+        "x",
+        HashMap::from([(vec![true], row(0.8)), (vec![false], row(0.3))]),
+    );
+    network.add_dependency(
+        vec!["z", "x"],
+        "y",
+        HashMap::from([
+            (vec![true, true], row(0.9)),
+            (vec![true, false], row(0.6)),
+            (vec![false, true], row(0.5)),
+            (vec![false, false], row(0.1)),
+        ]),
+    );
+    network
+}
+
+/// The oil wildcatter: whether to pay 10 for a seismic test of the soil, whose result is
+/// `none` without it, and then whether to drill, which pays -70, 50 or 200 as the well is
+/// dry, wet or soaking. Testing, then drilling unless the result is diffuse, is worth 22.5.
+pub(crate) fn oil_wildcatter() -> InfluenceDiagram<&'static str> {
+    let mut diagram = InfluenceDiagram::new(vec![
+        "dry", "wet", "soaking", "test", "no_test", "closed", "open", "diffuse", "none", "drill",
+        "no_drill",
+    ]);
+    diagram.add_chance_node(
+        "oil",
+        NodeType::Root(HashMap::from([
+            ("dry", 0.5),
+            ("wet", 0.3),
+            ("soaking", 0.2),
+        ])),
+    );
+    diagram.add_decision_node("test", vec!["test", "no_test"], Vec::new());
+    diagram.add_chance_node("seismic", NodeType::Inner);
+    diagram.add_decision_node("drill", vec!["drill", "no_drill"], vec!["seismic"]);
+    let result = |closed, open, diffuse| {
+        HashMap::from([("closed", closed), ("open", open), ("diffuse", diffuse)])
+    };
+    diagram.add_dependency(
+        vec!["oil", "test"],
+        "seismic",
+        HashMap::from([
+            (vec!["dry", "test"], result(0.1, 0.3, 0.6)),
+            (vec!["wet", "test"], result(0.3, 0.4, 0.3)),
+            (vec!["soaking", "test"], result(0.5, 0.4, 0.1)),
+            (vec!["dry", "no_test"], HashMap::from([("none", 1.0)])),
+            (vec!["wet", "no_test"], HashMap::from([("none", 1.0)])),
+            (vec!["soaking", "no_test"], HashMap::from([("none", 1.0)])),
+        ]),
+    );
+    diagram.add_utility_node("cost", vec!["test"], HashMap::from([(vec!["test"], -10.0)]));
+    diagram.add_utility_node(
+        "payoff",
+        vec!["oil", "drill"],
+        HashMap::from([
+            (vec!["dry", "drill"], -70.0),
+            (vec!["wet", "drill"], 50.0),
+            (vec!["soaking", "drill"], 200.0),
+        ]),
+    );
+    diagram
+}
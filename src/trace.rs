@@ -0,0 +1,200 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    hash::Hash,
+    path::Path,
+};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, Probability},
+    error::Error,
+    posterior::{json_number, json_string},
+};
+
+/// The kind of a message of belief propagation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    /// A π message, from a parent to a child: the parent's distribution given the evidence
+    /// not below the child.
+    Pi,
+    /// A λ message, from a child to a parent: the likelihood of the evidence below the child
+    /// given each value of the parent.
+    Lambda,
+}
+
+/// A message passed during `infer`, as recorded by `infer_traced`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceMessage<T> {
+    pub kind: MessageKind,
+    pub from: Name,
+    pub to: Name,
+    /// The round of the propagation loop, from 0, in which the message was passed.
+    pub iteration: usize,
+    /// The message, for each value of the value space in order.
+    pub values: Vec<(T, Probability)>,
+}
+
+/// Every message passed during one run of `infer`, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InferenceTrace<T> {
+    pub messages: Vec<TraceMessage<T>>,
+    /// The number of rounds of the propagation loop that passed a message.
+    pub iterations: usize,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> InferenceTrace<T> {
+    /// Record the message just passed from `from` to `to`, read from `messages`.
+    pub(crate) fn record(
+        &mut self,
+        network: &BayesianNetwork<T>,
+        kind: MessageKind,
+        from: NodeId,
+        to: NodeId,
+        messages: &HashMap<(NodeId, NodeId), HashMap<T, Probability>>,
+    ) {
+        let message = &messages[&(from, to)];
+        self.messages.push(TraceMessage {
+            kind,
+            from: network.get_node_name(from).to_string(),
+            to: network.get_node_name(to).to_string(),
+            iteration: self.iterations,
+            values: network
+                .value_space
+                .iter()
+                .map(|value| (value.clone(), message[value]))
+                .collect(),
+        });
+    }
+}
+
+impl<T: Display> InferenceTrace<T> {
+    /// Write the trace as a JSON object with the number of iterations and the list of
+    /// messages, each with its kind (`"pi"` or `"lambda"`), endpoints, iteration and values.
+    pub fn to_json(&self) -> String {
+        let messages = self
+            .messages
+            .iter()
+            .map(|message| {
+                let kind = match message.kind {
+                    MessageKind::Pi => "pi",
+                    MessageKind::Lambda => "lambda",
+                };
+                let values = message
+                    .values
+                    .iter()
+                    .map(|(value, prob)| {
+                        format!(
+                            "{}: {}",
+                            json_string(&value.to_string()),
+                            json_number(*prob)
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                format!(
+                    "    {{\"kind\": \"{}\", \"from\": {}, \"to\": {}, \"iteration\": {}, \
+                     \"values\": {{{}}}}}",
+                    kind,
+                    json_string(&message.from),
+                    json_string(&message.to),
+                    message.iteration,
+                    values.join(", ")
+                )
+            })
+            .collect::<Vec<String>>();
+        let messages = if messages.is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[\n{}\n  ]", messages.join(",\n"))
+        };
+        format!(
+            "{{\n  \"iterations\": {},\n  \"messages\": {}\n}}\n",
+            self.iterations, messages
+        )
+    }
+
+    /// Write the trace to a file as JSON.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_json())?)
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Infer the probability of each node given the evidence like `infer`, also recording
+    /// every π and λ message passed, to debug the propagation on a particular network.
+    pub fn infer_traced(
+        &self,
+        evidence: &HashMap<&str, T>,
+    ) -> (Vec<HashMap<T, Probability>>, InferenceTrace<T>) {
+        let mut trace = InferenceTrace {
+            messages: Vec::new(),
+            iterations: 0,
+        };
+        let probabilities = self.propagate(evidence, Some(&mut trace));
+        (probabilities, trace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bayesian_network::NodeType,
+        testing::{assert_close, bonus, row, TOLERANCE},
+    };
+
+    #[test]
+    fn infer_traced_records_the_messages_of_a_single_edge() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.3)));
+        network.add_node("b", NodeType::Leaf);
+        network.add_dependency(
+            vec!["a"],
+            "b",
+            HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.2))]),
+        );
+        let evidence = HashMap::from([("b", true)]);
+        let (probabilities, trace) = network.infer_traced(&evidence);
+        assert_eq!(probabilities, network.infer(&evidence));
+        assert_close(
+            probabilities[0][&true],
+            0.3 * 0.9 / (0.3 * 0.9 + 0.7 * 0.2),
+            TOLERANCE,
+        );
+        assert_eq!(trace.messages.len(), 2);
+        let pi = trace
+            .messages
+            .iter()
+            .find(|message| message.kind == MessageKind::Pi)
+            .unwrap();
+        assert_eq!((pi.from.as_str(), pi.to.as_str()), ("a", "b"));
+        assert_close(pi.values[0].1, 0.3, TOLERANCE);
+        assert_close(pi.values[1].1, 0.7, TOLERANCE);
+        // The λ message is the likelihood of b = true given each value of a.
+        let lambda = trace
+            .messages
+            .iter()
+            .find(|message| message.kind == MessageKind::Lambda)
+            .unwrap();
+        assert_eq!((lambda.from.as_str(), lambda.to.as_str()), ("b", "a"));
+        assert_close(lambda.values[0].1, 0.9, TOLERANCE);
+        assert_close(lambda.values[1].1, 0.2, TOLERANCE);
+        assert!(trace.iterations >= 1);
+        let json = trace.to_json();
+        assert!(json.contains("\"kind\": \"lambda\", \"from\": \"b\", \"to\": \"a\""));
+    }
+
+    #[test]
+    fn infer_traced_passes_one_message_each_way_on_every_edge_of_a_polytree() {
+        let (_, trace) = bonus().infer_traced(&HashMap::from([("feast", true)]));
+        for kind in [MessageKind::Pi, MessageKind::Lambda] {
+            assert_eq!(
+                trace
+                    .messages
+                    .iter()
+                    .filter(|message| message.kind == kind)
+                    .count(),
+                4
+            );
+        }
+    }
+}
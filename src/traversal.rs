@@ -0,0 +1,75 @@
+use std::{fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name};
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the names of the nodes ordered so that every node comes after its parents.
+    pub fn get_topological_order(&self) -> Vec<Name> {
+        let order = self.topological_order();
+        if order.len() < self.nodes.len() {
+            panic!("Cannot order a cyclic network topologically");
+        }
+        self.node_names_of(&order)
+    }
+
+    /// Get the parents of a node, in the order of its CPT keys.
+    pub fn get_node_parents(&self, name: &str) -> Vec<Name> {
+        let id = self.causal_node(name);
+        self.node_names_of(&self.nodes[id].parents)
+    }
+
+    /// Get the children of a node, in the order they were added.
+    pub fn get_node_children(&self, name: &str) -> Vec<Name> {
+        let id = self.causal_node(name);
+        self.node_names_of(&self.nodes[id].children)
+    }
+
+    /// Get the ancestors of a node, not including it, in node order.
+    pub fn get_node_ancestors(&self, name: &str) -> Vec<Name> {
+        let id = self.causal_node(name);
+        let mut ancestors = self.ancestors(&self.nodes[id].parents);
+        ancestors.retain(|other| *other != id);
+        self.node_names_of(&ancestors)
+    }
+
+    /// Get the descendants of a node, not including it, in node order.
+    pub fn get_node_descendants(&self, name: &str) -> Vec<Name> {
+        let id = self.causal_node(name);
+        let mut descendants = self.descendants(&self.nodes[id].children);
+        descendants.retain(|other| *other != id);
+        self.node_names_of(&descendants)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{graph, sprinkler};
+
+    #[test]
+    fn traversal_follows_the_edges() {
+        let network = graph(
+            &["d", "c", "b", "a", "e"],
+            &[("a", "b"), ("b", "c"), ("a", "d"), ("c", "d"), ("e", "c")],
+        );
+        let order = network.get_topological_order();
+        let position = |name: &str| order.iter().position(|other| other == name).unwrap();
+        assert_eq!(order.len(), 5);
+        for (parent, child) in [("a", "b"), ("b", "c"), ("a", "d"), ("c", "d"), ("e", "c")] {
+            assert!(position(parent) < position(child));
+        }
+        assert_eq!(network.get_node_parents("c"), ["b", "e"]);
+        // `from_edges` adds the dependencies of the nodes in order, so d's comes first.
+        assert_eq!(network.get_node_children("a"), ["d", "b"]);
+        assert_eq!(network.get_node_ancestors("d"), ["c", "b", "a", "e"]);
+        assert_eq!(network.get_node_descendants("e"), ["d", "c"]);
+        assert!(network.get_node_ancestors("a").is_empty());
+        assert!(network.get_node_descendants("d").is_empty());
+    }
+
+    #[test]
+    fn sprinkler_order_puts_the_cloud_first_and_the_grass_last() {
+        let order = sprinkler().get_topological_order();
+        assert_eq!(order.first().unwrap(), "cloudy");
+        assert_eq!(order.last().unwrap(), "wet_grass");
+    }
+}
@@ -0,0 +1,144 @@
+use rand::Rng;
+use std::{fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, Probability},
+    causal::{CausalEffect, Criterion},
+    dataset::Dataset,
+};
+
+/// The pseudo count of the CPTs fitted by `average_treatment_effect`, which keeps every
+/// treatment value possible in every adjustment stratum.
+const ATE_PSEUDO_COUNT: Probability = 1.0;
+
+/// An average treatment effect estimated from data, as returned by
+/// `average_treatment_effect`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreatmentEffect {
+    /// P(outcome | do(treated)) - P(outcome | do(control)) under the CPTs fitted to the data.
+    pub estimate: f64,
+    pub criterion: Criterion,
+    /// The nodes adjusted for (the mediators for the front-door criterion), in node order.
+    pub adjustment_set: Vec<Name>,
+    replicates: Vec<f64>,
+}
+
+impl TreatmentEffect {
+    /// Get the estimates from the bootstrap resamples.
+    pub fn replicates(&self) -> &[f64] {
+        &self.replicates
+    }
+
+    /// Get the standard deviation of the bootstrap estimates.
+    pub fn std_error(&self) -> f64 {
+        let n = self.replicates.len() as f64;
+        let mean = self.replicates.iter().sum::<f64>() / n;
+        (self
+            .replicates
+            .iter()
+            .map(|estimate| (estimate - mean).powi(2))
+            .sum::<f64>()
+            / n)
+            .sqrt()
+    }
+
+    /// Get the central `level` bootstrap percentile interval, such as 0.95.
+    pub fn interval(&self, level: f64) -> (f64, f64) {
+        if !(0.0..=1.0).contains(&level) {
+            panic!("Interval level {} is outside 0 and 1", level);
+        }
+        let mut sorted = self.replicates.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let quantile = |q: f64| sorted[(q * (sorted.len() - 1) as f64).round() as usize];
+        (quantile((1.0 - level) / 2.0), quantile((1.0 + level) / 2.0))
+    }
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Estimate the average effect of setting `treatment` to the first rather than the
+    /// second value of `contrast` on the probability of the `outcome` node taking the given
+    /// value. The CPTs of the network structure are fitted to `dataset`, which needs a column
+    /// for every node, and the effect is identified as by `causal_effect`. Its uncertainty
+    /// comes from refitting on `replicates` bootstrap resamples. Returns `None` if the effect
+    /// is not identified, or positivity does not hold for the formula identifying it.
+    pub fn average_treatment_effect(
+        &self,
+        dataset: &Dataset<T>,
+        treatment: &str,
+        contrast: (&T, &T),
+        outcome: (&str, &T),
+        replicates: usize,
+    ) -> Option<TreatmentEffect> {
+        self.average_treatment_effect_with_rng(
+            dataset,
+            treatment,
+            contrast,
+            outcome,
+            replicates,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Estimate the average treatment effect like `average_treatment_effect`, resampling with
+    /// `rng`.
+    pub fn average_treatment_effect_with_rng<R: Rng + ?Sized>(
+        &self,
+        dataset: &Dataset<T>,
+        treatment: &str,
+        contrast: (&T, &T),
+        outcome: (&str, &T),
+        replicates: usize,
+        rng: &mut R,
+    ) -> Option<TreatmentEffect> {
+        if dataset.is_empty() {
+            panic!("Cannot estimate a treatment effect from an empty dataset");
+        }
+        let (outcome, value) = outcome;
+        let effect = |dataset: &Dataset<T>| {
+            let mut network = self.map_values(T::clone);
+            network.fit(dataset, ATE_PSEUDO_COUNT);
+            let probability = |effect: &CausalEffect<T>| {
+                effect
+                    .distribution
+                    .iter()
+                    .find(|(state, _)| state == value)
+                    .map(|(_, prob)| *prob)
+                    .unwrap_or(0.0)
+            };
+            let treated = network
+                .causal_effect(treatment, contrast.0, outcome)
+                .ok()??;
+            let control = network
+                .causal_effect(treatment, contrast.1, outcome)
+                .ok()??;
+            Some((probability(&treated) - probability(&control), treated))
+        };
+
+        let (estimate, identified) = effect(dataset)?;
+        let columns = dataset
+            .columns()
+            .iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<&str>>();
+        let mut estimates = Vec::with_capacity(replicates);
+        for _ in 0..replicates {
+            let mut resample = Dataset::new(columns.clone());
+            for _ in 0..dataset.len() {
+                let index = rng.gen_range(0..dataset.len());
+                resample.add_row(dataset.rows()[index].clone());
+            }
+            if let Some((estimate, _)) = effect(&resample) {
+                estimates.push(estimate);
+            }
+        }
+        if estimates.is_empty() {
+            estimates.push(estimate);
+        }
+        Some(TreatmentEffect {
+            estimate,
+            criterion: identified.criterion,
+            adjustment_set: identified.adjustment_set,
+            replicates: estimates,
+        })
+    }
+}
@@ -0,0 +1,240 @@
+use std::{cmp::Reverse, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId},
+    learning::Edge,
+};
+
+/// An undirected graph over the nodes of a network, such as its moral graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UndirectedGraph {
+    nodes: Vec<Name>,
+    adjacent: Vec<Vec<bool>>,
+}
+
+impl UndirectedGraph {
+    /// Get the names of the nodes, in node order.
+    pub fn nodes(&self) -> &[Name] {
+        &self.nodes
+    }
+
+    /// Get the edges, each as a pair of names in node order.
+    pub fn edges(&self) -> Vec<Edge> {
+        let mut edges = Vec::new();
+        for a in 0..self.nodes.len() {
+            for b in a + 1..self.nodes.len() {
+                if self.adjacent[a][b] {
+                    edges.push((self.nodes[a].clone(), self.nodes[b].clone()));
+                }
+            }
+        }
+        edges
+    }
+
+    /// Get the neighbours of a node, in node order.
+    pub fn neighbours(&self, name: &str) -> Vec<Name> {
+        let id = self.index(name);
+        (0..self.nodes.len())
+            .filter(|other| self.adjacent[id][*other])
+            .map(|other| self.nodes[other].clone())
+            .collect()
+    }
+
+    /// Whether two nodes are linked by an edge.
+    pub fn is_adjacent(&self, a: &str, b: &str) -> bool {
+        self.adjacent[self.index(a)][self.index(b)]
+    }
+
+    fn index(&self, name: &str) -> usize {
+        match self.nodes.iter().position(|node| node == name) {
+            Some(index) => index,
+            None => panic!("Node `{}` not found", name),
+        }
+    }
+}
+
+/// A separator of a junction tree: the nodes shared by two adjacent cliques.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Separator {
+    /// The positions of the two cliques in `Triangulation::cliques`.
+    pub cliques: (usize, usize),
+    pub nodes: Vec<Name>,
+}
+
+/// The triangulation of the moral graph by an elimination order, as returned by
+/// `triangulate`, with the junction tree of its cliques.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Triangulation {
+    /// The moral graph with the fill edges added.
+    pub graph: UndirectedGraph,
+    /// The edges added by the elimination, each as a pair of names in node order.
+    pub fill_edges: Vec<Edge>,
+    /// The maximal cliques of the triangulated graph, each in node order, in the order the
+    /// elimination forms them.
+    pub cliques: Vec<Vec<Name>>,
+    /// The separators of a junction tree over the cliques: a maximum spanning tree by
+    /// separator size, with a tree for each connected component.
+    pub separators: Vec<Separator>,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the moral graph: each node linked to its parents, its children and the other
+    /// parents of its children.
+    pub fn moral_graph(&self) -> UndirectedGraph {
+        UndirectedGraph {
+            nodes: self.node_names_of(&(0..self.nodes.len()).collect::<Vec<NodeId>>()),
+            adjacent: self.moral_adjacency(),
+        }
+    }
+
+    /// Triangulate the moral graph by eliminating every node in `order`, such as one from
+    /// `elimination_order`, and build the junction tree of the resulting cliques.
+    pub fn triangulate(&self, order: &[&str]) -> Triangulation {
+        let order = self.order_ids(order);
+        let (cliques, adjacent) = self.fill_in(&order);
+        let moral = self.moral_adjacency();
+        let mut fill_edges = Vec::new();
+        for a in 0..self.nodes.len() {
+            for b in a + 1..self.nodes.len() {
+                if adjacent[a][b] && !moral[a][b] {
+                    fill_edges.push((
+                        self.get_node_name(a).to_string(),
+                        self.get_node_name(b).to_string(),
+                    ));
+                }
+            }
+        }
+
+        let mut maximal: Vec<Vec<NodeId>> = Vec::new();
+        for (i, clique) in cliques.iter().enumerate() {
+            let contained = cliques.iter().enumerate().any(|(j, other)| {
+                j != i
+                    && clique.iter().all(|id| other.contains(id))
+                    && (other.len() > clique.len() || j < i)
+            });
+            if !contained {
+                maximal.push(clique.clone());
+            }
+        }
+
+        // Kruskal's algorithm for a maximum spanning forest by separator size.
+        let mut candidates = Vec::new();
+        for a in 0..maximal.len() {
+            for b in a + 1..maximal.len() {
+                let shared = maximal[a]
+                    .iter()
+                    .copied()
+                    .filter(|id| maximal[b].contains(id))
+                    .collect::<Vec<NodeId>>();
+                if !shared.is_empty() {
+                    candidates.push((a, b, shared));
+                }
+            }
+        }
+        candidates.sort_by_key(|candidate| Reverse(candidate.2.len()));
+        let mut tree = (0..maximal.len()).collect::<Vec<usize>>();
+        let root = |tree: &[usize], mut clique: usize| {
+            while tree[clique] != clique {
+                clique = tree[clique];
+            }
+            clique
+        };
+        let mut separators = Vec::new();
+        for (a, b, shared) in candidates {
+            let (root_a, root_b) = (root(&tree, a), root(&tree, b));
+            if root_a != root_b {
+                tree[root_a] = root_b;
+                separators.push(Separator {
+                    cliques: (a, b),
+                    nodes: self.node_names_of(&shared),
+                });
+            }
+        }
+
+        Triangulation {
+            graph: UndirectedGraph {
+                nodes: self.node_names_of(&(0..self.nodes.len()).collect::<Vec<NodeId>>()),
+                adjacent,
+            },
+            fill_edges,
+            cliques: maximal
+                .iter()
+                .map(|clique| self.node_names_of(clique))
+                .collect(),
+            separators,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{graph, sprinkler};
+
+    fn pair(a: &str, b: &str) -> Edge {
+        (a.to_string(), b.to_string())
+    }
+
+    #[test]
+    fn moral_graph_marries_the_parents() {
+        let moral = sprinkler().moral_graph();
+        assert_eq!(moral.nodes(), ["cloudy", "sprinkler", "rain", "wet_grass"]);
+        assert!(moral.is_adjacent("sprinkler", "rain"));
+        assert!(!moral.is_adjacent("cloudy", "wet_grass"));
+        assert_eq!(
+            moral.neighbours("rain"),
+            ["cloudy", "sprinkler", "wet_grass"]
+        );
+        assert_eq!(moral.edges().len(), 5);
+    }
+
+    #[test]
+    fn triangulate_fills_a_five_cycle_into_a_junction_tree() {
+        // The moral graph is the cycle a - b - d - e - c - a, with f married to d and e.
+        let network = graph(
+            &["a", "b", "c", "d", "e", "f"],
+            &[
+                ("a", "b"),
+                ("a", "c"),
+                ("b", "d"),
+                ("c", "e"),
+                ("d", "f"),
+                ("e", "f"),
+            ],
+        );
+        let triangulation = network.triangulate(&["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(triangulation.fill_edges, [pair("b", "c"), pair("c", "d")]);
+        assert!(triangulation.graph.is_adjacent("b", "c"));
+        assert_eq!(
+            triangulation.cliques,
+            [
+                vec!["a", "b", "c"],
+                vec!["b", "c", "d"],
+                vec!["c", "d", "e"],
+                vec!["d", "e", "f"]
+            ]
+        );
+        assert_eq!(
+            triangulation.separators,
+            [
+                Separator {
+                    cliques: (0, 1),
+                    nodes: vec!["b".to_string(), "c".to_string()],
+                },
+                Separator {
+                    cliques: (1, 2),
+                    nodes: vec!["c".to_string(), "d".to_string()],
+                },
+                Separator {
+                    cliques: (2, 3),
+                    nodes: vec!["d".to_string(), "e".to_string()],
+                },
+            ]
+        );
+        // A chordal moral graph needs no fill edges.
+        let triangulation = sprinkler().triangulate(&["wet_grass", "cloudy", "sprinkler", "rain"]);
+        assert!(triangulation.fill_edges.is_empty());
+        assert_eq!(triangulation.cliques.len(), 2);
+        assert_eq!(triangulation.separators[0].nodes, ["sprinkler", "rain"]);
+    }
+}
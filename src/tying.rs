@@ -0,0 +1,200 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, NodeType, Probability};
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Register a CPT under `name` so several nodes can share it. A CPT shared by root nodes
+    /// has a single row keyed by no parent values.
+    pub fn add_shared_cpt(&mut self, name: &str, cpt: HashMap<Vec<T>, HashMap<T, Probability>>) {
+        if self.shared_cpts.contains_key(name) {
+            panic!("Shared CPT `{}` already exists", name);
+        }
+        let cpt = self.checked_shared_cpt(name, cpt);
+        self.shared_cpts.insert(name.to_string(), cpt);
+    }
+
+    /// Add a dependency whose CPT is the shared CPT `cpt_name`, tying the child to it.
+    pub fn add_shared_dependency(
+        &mut self,
+        parent_names: Vec<&str>,
+        child_name: &str,
+        cpt_name: &str,
+    ) {
+        let cpt = self.get_shared_cpt(cpt_name).clone();
+        self.add_dependency(parent_names, child_name, cpt);
+        self.tie_node(child_name, cpt_name);
+    }
+
+    /// Tie a node to the shared CPT `cpt_name`: its CPT, or its prior if it is a root node,
+    /// becomes a copy of the shared one that follows every later update, and `fit` pools the
+    /// counts of all nodes tied to the same CPT. Adding a dependency to the node unties it.
+    pub fn tie_node(&mut self, name: &str, cpt_name: &str) {
+        let id = match self.node_map.get(name) {
+            Some(id) => *id,
+            None => panic!("Node `{}` not found", name),
+        };
+        let cpt = self.get_shared_cpt(cpt_name).clone();
+        let node = &mut self.nodes[id];
+        if cpt.keys().any(|key| key.len() != node.parents.len()) {
+            panic!(
+                "Shared CPT `{}` does not match the parents of node `{}`",
+                cpt_name, name
+            );
+        }
+        match &mut node.node_type {
+            NodeType::Root(prob) => match cpt.get(&Vec::new()) {
+                Some(row) => *prob = row.clone(),
+                None => panic!(
+                    "Shared CPT `{}` has no row for root node `{}`",
+                    cpt_name, name
+                ),
+            },
+            _ => {
+                node.probability = cpt;
+                node.compact_cpt = None;
+            }
+        }
+        node.shared_cpt = Some(cpt_name.to_string());
+    }
+
+    /// Replace the shared CPT `name`, updating every node tied to it.
+    pub fn set_shared_cpt(&mut self, name: &str, cpt: HashMap<Vec<T>, HashMap<T, Probability>>) {
+        self.get_shared_cpt(name);
+        let cpt = self.checked_shared_cpt(name, cpt);
+        self.shared_cpts.insert(name.to_string(), cpt);
+        let tied = self
+            .nodes
+            .iter()
+            .filter(|node| node.shared_cpt.as_deref() == Some(name))
+            .map(|node| self.get_node_name(node.id).to_string())
+            .collect::<Vec<String>>();
+        for node in tied {
+            self.tie_node(&node, name);
+        }
+    }
+
+    /// Get the shared CPT `name`.
+    pub fn get_shared_cpt(&self, name: &str) -> &HashMap<Vec<T>, HashMap<T, Probability>> {
+        match self.shared_cpts.get(name) {
+            Some(cpt) => cpt,
+            None => panic!("Shared CPT `{}` not found", name),
+        }
+    }
+
+    /// Get the names of the shared CPTs, sorted.
+    pub fn shared_cpt_names(&self) -> Vec<&str> {
+        let mut names = self
+            .shared_cpts
+            .keys()
+            .map(|name| name.as_str())
+            .collect::<Vec<&str>>();
+        names.sort();
+        names
+    }
+
+    /// Get the name of the shared CPT a node is tied to, if any.
+    pub fn get_node_shared_cpt(&self, name: &str) -> Option<&str> {
+        match self.node_map.get(name) {
+            Some(id) => self.nodes[*id].shared_cpt.as_deref(),
+            None => panic!("Node `{}` not found", name),
+        }
+    }
+
+    /// Check that every value of a shared CPT is in the value space, and fill in missing
+    /// values with probability 0.0 like `add_dependency`.
+    fn checked_shared_cpt(
+        &self,
+        name: &str,
+        cpt: HashMap<Vec<T>, HashMap<T, Probability>>,
+    ) -> HashMap<Vec<T>, HashMap<T, Probability>> {
+        let mut cpt = cpt;
+        let length = cpt.keys().next().map(|key| key.len());
+        for (key, map) in &mut cpt {
+            if Some(key.len()) != length {
+                panic!("Shared CPT `{}` keys have different lengths", name);
+            }
+            for value in key.iter().chain(map.keys()) {
+                if !self.value_space.contains(value) {
+                    panic!(
+                        "Shared CPT `{}` contains value not in value space ({:?})",
+                        name, value
+                    );
+                }
+            }
+            for value in &self.value_space {
+                map.entry(value.clone()).or_insert(0.0);
+            }
+            if let Err(error) = self.normalize_row(name, key, map) {
+                panic!("{}", error);
+            }
+        }
+        cpt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dataset::Dataset, hmm::Hmm, plate::Plate, testing::row};
+
+    #[test]
+    fn fit_pools_the_transitions_of_a_tied_chain() {
+        let hmm = Hmm::new(
+            vec![true, false],
+            vec![true, false],
+            vec![0.5, 0.5],
+            vec![vec![0.5, 0.5], vec![0.5, 0.5]],
+            vec![vec![0.5, 0.5], vec![0.5, 0.5]],
+        );
+        let mut network = hmm.network(3);
+        let names = network.node_names();
+        let mut dataset = Dataset::new(names.clone());
+        // States, then observations, in node order (State[0], Observation[0], ...).
+        for states in [
+            [true, true, false],
+            [false, false, false],
+            [true, false, true],
+        ] {
+            let mut values = Vec::new();
+            for state in states {
+                values.push(state);
+                values.push(state);
+            }
+            dataset.add_row(values);
+        }
+        network.fit(&dataset, 0.0);
+
+        // Pooled over both positions: true -> (true 1, false 2), false -> (true 1, false 2).
+        let pooled = HashMap::from([(vec![true], row(1.0 / 3.0)), (vec![false], row(1.0 / 3.0))]);
+        let transition = network.get_shared_cpt("State.transition");
+        for (key, expected) in &pooled {
+            for (value, prob) in expected {
+                assert!((transition[key][value] - prob).abs() < 1e-12);
+            }
+        }
+        let first = network.get_node_index("State[1]");
+        let second = network.get_node_index("State[2]");
+        assert_eq!(network.nodes[first].probability, *transition);
+        assert_eq!(network.nodes[second].probability, *transition);
+    }
+
+    #[test]
+    fn add_plate_ties_every_copy() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("disease", NodeType::Root(row(0.1)));
+        let mut plate = Plate::new();
+        plate.add_node("test", NodeType::Leaf);
+        plate.add_dependency(
+            vec!["disease"],
+            "test",
+            HashMap::from([(vec![true], row(0.9)), (vec![false], row(0.2))]),
+        );
+        network.add_plate(&plate, 3);
+        for i in 0..3 {
+            assert_eq!(
+                network.get_node_shared_cpt(&format!("test[{}]", i)),
+                Some("test.plate")
+            );
+        }
+    }
+}
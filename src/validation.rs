@@ -0,0 +1,281 @@
+use std::{collections::HashMap, fmt, fmt::Debug, hash::Hash};
+
+use crate::{
+    bayesian_network::{BayesianNetwork, Name, NodeId, NodeType, Probability},
+    learning::configurations,
+};
+
+/// How serious a diagnostic is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The network is not usable as is.
+    Error,
+    /// The network is usable but probably not what was intended.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found by `validate`.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The name of the check that failed, e.g. `cycle` or `normalization`.
+    pub check: &'static str,
+    /// The node the problem is about, if any.
+    pub node: Option<Name>,
+    pub message: String,
+}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Check the whole network: directed cycles, leaf nodes with children, CPT completeness,
+    /// normalization within the tolerance of `get_normalization`, declared influences and nodes
+    /// unreachable from the rest of the network.
+    /// An empty list means the network is valid. Construction does not warn about rows the
+    /// lenient policy cannot normalize, so call this once a network is built or loaded.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut report = |severity, check, id: Option<NodeId>, message| {
+            diagnostics.push(Diagnostic {
+                severity,
+                check,
+                node: id.map(|id| self.get_node_name(id).to_string()),
+                message,
+            })
+        };
+
+        let order = self.topological_order();
+        if order.len() < self.nodes.len() {
+            let cyclic = (0..self.nodes.len())
+                .filter(|id| !order.contains(id))
+                .map(|id| self.get_node_name(id))
+                .collect::<Vec<&str>>();
+            report(
+                Severity::Error,
+                "cycle",
+                None,
+                format!("directed cycle through {:?}", cyclic),
+            );
+        }
+
+        for node in &self.nodes {
+            if matches!(node.node_type, NodeType::Leaf) && !node.children.is_empty() {
+                report(
+                    Severity::Error,
+                    "leaf",
+                    Some(node.id),
+                    format!("leaf node has {} children", node.children.len()),
+                );
+            }
+            let cpt = self.get_cpt(node.id);
+            let rows = match &node.node_type {
+                NodeType::Root(prob_map) => vec![(Vec::new(), prob_map)],
+                _ => {
+                    if node.parents.is_empty() {
+                        report(
+                            Severity::Error,
+                            "completeness",
+                            Some(node.id),
+                            "non-root node has no parents".to_string(),
+                        );
+                        continue;
+                    }
+                    if cpt.is_empty() {
+                        report(
+                            Severity::Error,
+                            "completeness",
+                            Some(node.id),
+                            "node has parents but no probabilities".to_string(),
+                        );
+                        continue;
+                    }
+                    let parent_domains = node
+                        .parents
+                        .iter()
+                        .map(|parent| self.get_node_domain(*parent))
+                        .collect::<Vec<Vec<T>>>();
+                    for key in configurations(&parent_domains) {
+                        if !cpt.contains_key(&key) {
+                            report(
+                                Severity::Error,
+                                "completeness",
+                                Some(node.id),
+                                format!("no probabilities for parent values {:?}", key),
+                            );
+                        }
+                    }
+                    cpt.iter()
+                        .map(|(key, prob_map)| (key.clone(), prob_map))
+                        .collect()
+                }
+            };
+            for (key, prob_map) in rows {
+                let negative = prob_map.values().any(|prob| *prob < 0.0);
+                let sum = prob_map.values().sum::<Probability>();
+                let given = if key.is_empty() {
+                    String::new()
+                } else {
+                    format!(" given {:?}", key)
+                };
+                if negative {
+                    report(
+                        Severity::Error,
+                        "normalization",
+                        Some(node.id),
+                        format!("negative probability{}", given),
+                    );
+                } else if (sum - 1.0).abs() > self.normalization.tolerance {
+                    report(
+                        Severity::Error,
+                        "normalization",
+                        Some(node.id),
+                        format!("probabilities{} sum to {}", given, sum),
+                    );
+                }
+            }
+        }
+
+        for id in self.unreachable_nodes() {
+            report(
+                Severity::Warning,
+                "unreachable",
+                Some(id),
+                "node is not connected to the rest of the network".to_string(),
+            );
+        }
+        diagnostics.extend(self.check_influences());
+        diagnostics
+    }
+
+    /// The nodes outside the largest connected part of the network (ignoring edge direction).
+    fn unreachable_nodes(&self) -> Vec<NodeId> {
+        let mut component = HashMap::new();
+        let mut sizes = Vec::new();
+        for start in 0..self.nodes.len() {
+            if component.contains_key(&start) {
+                continue;
+            }
+            let index = sizes.len();
+            let mut size = 0;
+            let mut stack = vec![start];
+            component.insert(start, index);
+            while let Some(id) = stack.pop() {
+                size += 1;
+                let node = &self.nodes[id];
+                for neighbour in node.parents.iter().chain(&node.children) {
+                    if !component.contains_key(neighbour) {
+                        component.insert(*neighbour, index);
+                        stack.push(*neighbour);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        let largest = (0..sizes.len()).max_by_key(|index| (sizes[*index], usize::MAX - index));
+        (0..self.nodes.len())
+            .filter(|id| Some(component[id]) != largest)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bonus, row};
+
+    fn checks(network: &BayesianNetwork<bool>) -> Vec<(&'static str, Option<Name>)> {
+        network
+            .validate()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.check, diagnostic.node))
+            .collect()
+    }
+
+    #[test]
+    fn a_complete_network_is_valid() {
+        assert!(bonus().validate().is_empty());
+    }
+
+    #[test]
+    fn missing_and_unnormalizable_rows_are_errors() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.5)));
+        network.add_node("b", NodeType::Leaf);
+        network.add_node("c", NodeType::Leaf);
+        network.add_dependency(vec!["a"], "b", HashMap::from([(vec![true], row(0.5))]));
+        network.add_dependency(
+            vec!["a"],
+            "c",
+            HashMap::from([
+                (vec![true], row(0.5)),
+                (vec![false], HashMap::from([(true, -0.5), (false, 1.5)])),
+            ]),
+        );
+        let diagnostics = network.validate();
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Error));
+        assert_eq!(
+            checks(&network),
+            vec![
+                ("completeness", Some("b".to_string())),
+                ("normalization", Some("c".to_string())),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "no probabilities for parent values [false]"
+        );
+    }
+
+    #[test]
+    fn rows_left_unnormalized_are_errors() {
+        let mut network = BayesianNetwork::new(vec![true, false]);
+        network.add_node("a", NodeType::Root(row(0.5)));
+        network.add_node("b", NodeType::Leaf);
+        // The lenient policy rescales the first row but cannot rescale a row of zeros.
+        network.add_dependency(
+            vec!["a"],
+            "b",
+            HashMap::from([
+                (vec![true], HashMap::from([(true, 0.7), (false, 0.5)])),
+                (vec![false], HashMap::from([(true, 0.0), (false, 0.0)])),
+            ]),
+        );
+        // Nor does it see a prior edited in place.
+        network.nodes[0].node_type = NodeType::Root(HashMap::from([(true, 0.5), (false, 0.4)]));
+        let diagnostics = network.validate();
+        assert_eq!(
+            checks(&network),
+            vec![
+                ("normalization", Some("a".to_string())),
+                ("normalization", Some("b".to_string())),
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "probabilities sum to 0.9");
+        assert_eq!(
+            diagnostics[1].message,
+            "probabilities given [false] sum to 0"
+        );
+    }
+
+    #[test]
+    fn disconnected_nodes_are_warnings() {
+        let mut network = bonus();
+        network.add_node("weather", NodeType::Root(row(0.3)));
+        let diagnostics = network.validate();
+        assert_eq!(
+            checks(&network),
+            vec![("unreachable", Some("weather".to_string()))]
+        );
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(Severity::Warning.to_string(), "warning");
+    }
+}
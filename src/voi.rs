@@ -0,0 +1,174 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::bayesian_network::{BayesianNetwork, Name};
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug> BayesianNetwork<T> {
+    /// Get the expected reduction in the entropy of `target`, in nats, from observing
+    /// `candidate` on top of `evidence`: the mutual information between them given the
+    /// evidence.
+    pub fn value_of_information(
+        &self,
+        candidate: &str,
+        target: &str,
+        evidence: &HashMap<&str, T>,
+    ) -> f64 {
+        if evidence.contains_key(candidate) {
+            panic!("Node `{}` is already observed", candidate);
+        }
+        self.mutual_information(candidate, target, evidence)
+    }
+
+    /// Get the expected gain in utility from observing `candidate` before choosing one of
+    /// `actions`, when taking `action` with the target at `value` is worth
+    /// `utility(action, value)`: the expected utility of the best action after the
+    /// observation minus that of the best action now.
+    pub fn decision_value_of_information<A, F: Fn(&A, &T) -> f64>(
+        &self,
+        candidate: &str,
+        target: &str,
+        evidence: &HashMap<&str, T>,
+        actions: &[A],
+        utility: F,
+    ) -> f64 {
+        let best = |evidence: &HashMap<&str, T>| {
+            let distribution = self.node_distribution(target, evidence);
+            actions
+                .iter()
+                .map(|action| {
+                    distribution
+                        .iter()
+                        .map(|(value, prob)| prob * utility(action, value))
+                        .sum::<f64>()
+                })
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+        if evidence.contains_key(candidate) {
+            panic!("Node `{}` is already observed", candidate);
+        }
+        let before = best(evidence);
+        let after = self
+            .outcomes(candidate, evidence)
+            .into_iter()
+            .map(|(prob, evidence)| prob * best(&evidence))
+            .sum::<f64>();
+        (after - before).max(0.0)
+    }
+
+    /// Rank the `candidates` by `value_of_information` about `target`, the most informative
+    /// observation first.
+    pub fn rank_observations(
+        &self,
+        candidates: &[&str],
+        target: &str,
+        evidence: &HashMap<&str, T>,
+    ) -> Vec<(Name, f64)> {
+        let mut ranking = candidates
+            .iter()
+            .map(|candidate| {
+                (
+                    candidate.to_string(),
+                    self.value_of_information(candidate, target, evidence),
+                )
+            })
+            .collect::<Vec<(Name, f64)>>();
+        ranking.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranking
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_close, bonus, evidence_probability, TOLERANCE};
+
+    /// The mutual information between two boolean nodes given `evidence`, by enumeration.
+    fn brute_force_information(
+        network: &BayesianNetwork<bool>,
+        a: &str,
+        b: &str,
+        evidence: &HashMap<&str, bool>,
+    ) -> f64 {
+        let total = evidence_probability(network, evidence);
+        let p = |findings: &[(&str, bool)]| {
+            let mut evidence = evidence.clone();
+            evidence.extend(findings.iter().copied());
+            evidence_probability(network, &evidence) / total
+        };
+        let mut information = 0.0;
+        for x in [true, false] {
+            for y in [true, false] {
+                let both = p(&[(a, x), (b, y)]);
+                if both > 0.0 {
+                    information += both * (both / (p(&[(a, x)]) * p(&[(b, y)]))).ln();
+                }
+            }
+        }
+        information
+    }
+
+    #[test]
+    fn value_of_information_is_the_mutual_information() {
+        let network = bonus();
+        for evidence in [HashMap::new(), HashMap::from([("horse_race", true)])] {
+            assert_close(
+                network.value_of_information("bonus", "performance", &evidence),
+                brute_force_information(&network, "bonus", "performance", &evidence),
+                TOLERANCE,
+            );
+        }
+        let ranking = network.rank_observations(
+            &["horse_race", "feast", "mood"],
+            "performance",
+            &HashMap::new(),
+        );
+        let names = ranking
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<&str>>();
+        // The mood tells the most, the feast less through it, and the race nothing alone.
+        assert_eq!(names, ["mood", "feast", "horse_race"]);
+        assert_close(ranking[2].1, 0.0, TOLERANCE);
+    }
+
+    #[test]
+    fn decision_value_of_information_matches_enumeration() {
+        let network = bonus();
+        let evidence = HashMap::from([("horse_race", false)]);
+        // Promoting pays 100 after a good performance and costs 5 otherwise.
+        let utility = |promote: &bool, performance: &bool| match (promote, performance) {
+            (true, true) => 100.0,
+            (true, false) => -5.0,
+            (false, _) => 0.0,
+        };
+        let expected = |evidence: &HashMap<&str, bool>| {
+            let total = evidence_probability(&network, evidence);
+            let mut good = evidence.clone();
+            good.insert("performance", true);
+            let p = evidence_probability(&network, &good) / total;
+            (p * 100.0 - (1.0 - p) * 5.0).max(0.0)
+        };
+        let mut after = 0.0;
+        for mood in [true, false] {
+            let mut observed = evidence.clone();
+            observed.insert("mood", mood);
+            after += evidence_probability(&network, &observed)
+                / evidence_probability(&network, &evidence)
+                * expected(&observed);
+        }
+        let value = network.decision_value_of_information(
+            "mood",
+            "performance",
+            &evidence,
+            &[true, false],
+            utility,
+        );
+        assert_close(value, after - expected(&evidence), TOLERANCE);
+        assert!(value > 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Node `mood` is already observed")]
+    fn value_of_information_rejects_an_observed_candidate() {
+        bonus().value_of_information("mood", "performance", &HashMap::from([("mood", true)]));
+    }
+}